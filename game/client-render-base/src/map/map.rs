@@ -7,7 +7,10 @@ use std::{
 };
 
 use crate::map::{
-    map_buffered::{MapRenderLayer, MapRenderTextOverlayType, QuadVisualRangeAnim},
+    map_buffered::{
+        eval_anim, eval_color_anim, eval_pos_anim, group_clip_intersects_view, MapRenderLayer,
+        MapRenderTextOverlayType, QuadVisualRangeAnim,
+    },
     render_pipe::RenderPipelinePhysics,
 };
 
@@ -35,7 +38,7 @@ use graphics::handles::{
     buffer_object::buffer_object::BufferObject,
     canvas::canvas::GraphicsCanvasHandle,
     shader_storage::shader_storage::ShaderStorage,
-    stream::stream::{GraphicsStreamHandle, StreamedUniforms},
+    stream::stream::{GraphicsStreamHandle, StreamedSprites, StreamedUniforms},
     stream_types::StreamedQuad,
     texture::texture::{
         TextureContainer, TextureContainer2dArray, TextureType, TextureType2dArray,
@@ -66,7 +69,10 @@ use math::math::{
     PI,
 };
 
-use graphics_types::rendering::{BlendType, ColorRgba, State};
+use graphics_types::{
+    commands::RenderSpriteInfo,
+    rendering::{BlendType, ColorRgba, State},
+};
 use sound::sound_object::SoundObject;
 
 #[derive(Debug, Clone, Copy)]
@@ -165,14 +171,13 @@ impl RenderMap {
     where
         F: Copy + FromFixed + ToFixed,
     {
-        let total_time = if anim.synchronized {
-            time::Duration::try_from(*cur_anim_time).unwrap_or_default()
-        } else {
-            time::Duration::try_from(*cur_time).unwrap_or_default()
-        };
-        let anim_time = total_time + *anim_time_offset;
-
-        RenderTools::render_eval_anim(&anim.points, anim_time, include_last_anim_point)
+        eval_anim(
+            anim,
+            anim_time_offset,
+            cur_time,
+            cur_anim_time,
+            include_last_anim_point,
+        )
     }
 
     fn render_tile_layer<AN, AS>(
@@ -190,6 +195,7 @@ impl RenderMap {
         animations: &AnimationsSkeleton<AN, AS>,
         mut color: ColorRgba,
     ) {
+        let state = &visuals.layer_state(state);
         let (screen_x0, screen_y0, screen_x1, screen_y1) = state.get_canvas_mapping();
         let channels = if let Some(anim) = {
             if let Some(color_anim) = color_anim {
@@ -275,6 +281,7 @@ impl RenderMap {
                 texture,
                 shader_storage,
                 &color,
+                &visuals.tint.unwrap_or([1.0; 4]),
                 EditorTileLayerRenderProps {
                     x: border_x0 as f32,
                     y: border_y0 as f32,
@@ -336,6 +343,7 @@ impl RenderMap {
                             texture.clone(),
                             shader_storage,
                             &color,
+                            &visuals.tint.unwrap_or([1.0; 4]),
                             draws,
                         );
                     }
@@ -370,6 +378,7 @@ impl RenderMap {
         border_x1: i32,
         border_y1: i32,
     ) {
+        let tint = visuals.tint.unwrap_or([1.0; 4]);
         if let Some(buffer_container_index) = &buffer_object_index {
             let mut y0 = border_y0;
             let mut x0 = border_x0;
@@ -402,6 +411,7 @@ impl RenderMap {
                         texture.clone(),
                         buffer_container_index,
                         color,
+                        &tint,
                         &offset,
                         &scale,
                         visuals.corner_top_left.quad_offset(),
@@ -417,6 +427,7 @@ impl RenderMap {
                         texture.clone(),
                         buffer_container_index,
                         color,
+                        &tint,
                         &offset,
                         &scale,
                         visuals.corner_bottom_left.quad_offset(),
@@ -435,6 +446,7 @@ impl RenderMap {
                         texture.clone(),
                         buffer_container_index,
                         color,
+                        &tint,
                         &offset,
                         &scale,
                         visuals.corner_top_right.quad_offset(),
@@ -450,6 +462,7 @@ impl RenderMap {
                         texture.clone(),
                         buffer_container_index,
                         color,
+                        &tint,
                         &offset,
                         &scale,
                         visuals.corner_bottom_right.quad_offset(),
@@ -477,6 +490,7 @@ impl RenderMap {
                         texture.clone(),
                         buffer_container_index,
                         color,
+                        &tint,
                         &offset,
                         &scale,
                         quad_offset,
@@ -505,6 +519,7 @@ impl RenderMap {
                         texture.clone(),
                         buffer_container_index,
                         color,
+                        &tint,
                         &offset,
                         &scale,
                         quad_offset,
@@ -532,6 +547,7 @@ impl RenderMap {
                         texture.clone(),
                         buffer_container_index,
                         color,
+                        &tint,
                         &offset,
                         &scale,
                         quad_offset,
@@ -559,6 +575,7 @@ impl RenderMap {
                         texture.clone(),
                         buffer_container_index,
                         color,
+                        &tint,
                         &offset,
                         &scale,
                         quad_offset,
@@ -643,6 +660,7 @@ impl RenderMap {
                     texture.clone(),
                     buffer_container_index,
                     color,
+                    &tint,
                     &offset,
                     &scale,
                     visuals.base.border_kill_tile.quad_offset(),
@@ -661,6 +679,7 @@ impl RenderMap {
                     texture.clone(),
                     buffer_container_index,
                     color,
+                    &tint,
                     &offset,
                     &scale,
                     visuals.base.border_kill_tile.quad_offset(),
@@ -679,6 +698,7 @@ impl RenderMap {
                     texture.clone(),
                     buffer_container_index,
                     color,
+                    &tint,
                     &offset,
                     &scale,
                     visuals.base.border_kill_tile.quad_offset(),
@@ -697,6 +717,7 @@ impl RenderMap {
                     texture,
                     buffer_container_index,
                     color,
+                    &tint,
                     &offset,
                     &scale,
                     visuals.base.border_kill_tile.quad_offset(),
@@ -903,6 +924,46 @@ impl RenderMap {
         );
     }
 
+    fn render_quad_instances(
+        &self,
+        state: &State,
+        texture: &TextureType,
+        buffer_object: &BufferObject,
+        template: usize,
+        offsets: &[vec2],
+    ) {
+        let map_graphics = &self.map_graphics;
+        self.stream_handle.fill_sprites_uniform_instance(
+            hi_closure!([offsets: &[vec2]], |mut stream_handle: StreamedSprites<'_>| -> () {
+                for offset in offsets {
+                    stream_handle.add(RenderSpriteInfo {
+                        pos: *offset,
+                        scale: 1.0,
+                        rotation: 0.0,
+                        color: ColorRgba::new(1.0, 1.0, 1.0, 1.0),
+                    });
+                }
+            }),
+            hi_closure!([
+                map_graphics: &MapGraphics,
+                state: &State,
+                texture: &TextureType,
+                buffer_object: &BufferObject,
+                template: usize
+            ],
+            |instance: usize, count: usize| -> () {
+                map_graphics.render_quad_instances(
+                    state,
+                    texture.clone(),
+                    buffer_object,
+                    template,
+                    instance,
+                    count,
+                );
+            }),
+        );
+    }
+
     pub fn prepare_quad_anims<AN: HiarcTrait, AS: HiarcTrait>(
         pos_anims: &Pool<FxHashMap<(usize, time::Duration), fvec3>>,
         color_anims: &Pool<FxHashMap<(usize, time::Duration), nfvec4>>,
@@ -916,26 +977,26 @@ impl RenderMap {
         let mut color_anims_values = color_anims.new();
 
         for &(pos_anim, pos_anim_offset) in &visuals.pos_anims {
-            if let Some(anim) = animations.pos.get(pos_anim) {
-                let pos_channels = RenderMap::animation_eval(
-                    &anim.def,
-                    cur_time,
-                    cur_anim_time,
-                    &pos_anim_offset,
-                    include_last_anim_point,
-                );
+            if let Some(pos_channels) = eval_pos_anim(
+                animations,
+                pos_anim,
+                &pos_anim_offset,
+                cur_time,
+                cur_anim_time,
+                include_last_anim_point,
+            ) {
                 pos_anims_values.insert((pos_anim, pos_anim_offset), pos_channels);
             }
         }
         for &(color_anim, color_anim_offset) in &visuals.color_anims {
-            if let Some(anim) = animations.color.get(color_anim) {
-                let color_channels = RenderMap::animation_eval(
-                    &anim.def,
-                    cur_time,
-                    cur_anim_time,
-                    &color_anim_offset,
-                    include_last_anim_point,
-                );
+            if let Some(color_channels) = eval_color_anim(
+                animations,
+                color_anim,
+                &color_anim_offset,
+                cur_time,
+                cur_anim_time,
+                include_last_anim_point,
+            ) {
                 color_anims_values.insert((color_anim, color_anim_offset), color_channels);
             }
         }
@@ -970,84 +1031,102 @@ impl RenderMap {
             animations,
         );
 
-        if let Some(buffer_container) = &visuals.buffer_object_index {
-            let texture = &texture;
-            for draw_range in &visuals.draw_ranges {
-                match draw_range.anim {
-                    QuadVisualRangeAnim::NoAnim => {
-                        self.map_graphics.render_quad_layer_grouped(
-                            state,
-                            texture.clone(),
-                            buffer_container,
-                            draw_range.range.end - draw_range.range.start,
-                            draw_range.range.start,
-                            QuadRenderInfo {
-                                color: ColorRgba::new(1.0, 1.0, 1.0, 1.0),
-                                offsets: Default::default(),
-                                rotation: 0.0,
-                                padding: 0.0,
-                            },
-                        );
-                    }
-                    QuadVisualRangeAnim::ColorAnim { anim, anim_offset } => {
-                        self.prepare_group_rendering(
-                            &color_anims_values,
-                            &pos_anims_values,
-                            Some(anim),
-                            &anim_offset,
-                            None,
-                            &Default::default(),
-                            draw_range.range.clone(),
-                            state,
-                            texture,
-                            buffer_container,
-                        );
-                    }
-                    QuadVisualRangeAnim::PosAnim { anim, anim_offset } => {
-                        self.prepare_group_rendering(
-                            &color_anims_values,
-                            &pos_anims_values,
-                            None,
-                            &Default::default(),
-                            Some(anim),
-                            &anim_offset,
-                            draw_range.range.clone(),
-                            state,
-                            texture,
-                            buffer_container,
-                        );
-                    }
-                    QuadVisualRangeAnim::FullAnim {
-                        pos,
-                        pos_offset,
-                        color,
-                        color_offset,
-                    } => {
-                        self.prepare_group_rendering(
-                            &color_anims_values,
-                            &pos_anims_values,
-                            Some(color),
-                            &color_offset,
-                            Some(pos),
-                            &pos_offset,
-                            draw_range.range.clone(),
-                            state,
-                            texture,
-                            buffer_container,
-                        );
-                    }
-                    QuadVisualRangeAnim::Chaos => {
-                        self.render_quads_with_anim(
-                            state,
-                            texture,
-                            &color_anims_values,
-                            &pos_anims_values,
-                            &quads[draw_range.range.clone()],
-                            buffer_container,
-                            draw_range.range.start,
-                        );
-                    }
+        let texture = &texture;
+        // only the quads of the draw ranges that are not instanced
+        // are part of the vertex buffer, in the order of their ranges.
+        let mut buffer_offset = 0;
+        for draw_range in &visuals.draw_ranges {
+            if let QuadVisualRangeAnim::Instanced { template } = draw_range.anim {
+                if let Some((buffer_object, offsets)) = visuals
+                    .instanced_buffer_object_index
+                    .as_ref()
+                    .zip(visuals.instance_offsets.get(template))
+                {
+                    self.render_quad_instances(state, texture, buffer_object, template, offsets);
                 }
+                continue;
+            }
+            let range = buffer_offset..buffer_offset + draw_range.range.len();
+            buffer_offset = range.end;
+            let Some(buffer_container) = &visuals.buffer_object_index else {
+                continue;
+            };
+            match draw_range.anim {
+                QuadVisualRangeAnim::NoAnim => {
+                    self.map_graphics.render_quad_layer_grouped(
+                        state,
+                        texture.clone(),
+                        buffer_container,
+                        range.end - range.start,
+                        range.start,
+                        QuadRenderInfo {
+                            color: ColorRgba::new(1.0, 1.0, 1.0, 1.0),
+                            offsets: Default::default(),
+                            rotation: 0.0,
+                            padding: 0.0,
+                        },
+                    );
+                }
+                QuadVisualRangeAnim::ColorAnim { anim, anim_offset } => {
+                    self.prepare_group_rendering(
+                        &color_anims_values,
+                        &pos_anims_values,
+                        Some(anim),
+                        &anim_offset,
+                        None,
+                        &Default::default(),
+                        range.clone(),
+                        state,
+                        texture,
+                        buffer_container,
+                    );
+                }
+                QuadVisualRangeAnim::PosAnim { anim, anim_offset } => {
+                    self.prepare_group_rendering(
+                        &color_anims_values,
+                        &pos_anims_values,
+                        None,
+                        &Default::default(),
+                        Some(anim),
+                        &anim_offset,
+                        range.clone(),
+                        state,
+                        texture,
+                        buffer_container,
+                    );
+                }
+                QuadVisualRangeAnim::FullAnim {
+                    pos,
+                    pos_offset,
+                    color,
+                    color_offset,
+                } => {
+                    self.prepare_group_rendering(
+                        &color_anims_values,
+                        &pos_anims_values,
+                        Some(color),
+                        &color_offset,
+                        Some(pos),
+                        &pos_offset,
+                        range.clone(),
+                        state,
+                        texture,
+                        buffer_container,
+                    );
+                }
+                QuadVisualRangeAnim::Chaos => {
+                    self.render_quads_with_anim(
+                        state,
+                        texture,
+                        &color_anims_values,
+                        &pos_anims_values,
+                        &quads[draw_range.range.clone()],
+                        buffer_container,
+                        range.start,
+                    );
+                }
+                // rendered above
+                QuadVisualRangeAnim::Instanced { .. } => {}
             }
         }
     }
@@ -1075,6 +1154,11 @@ impl RenderMap {
         camera.project(&self.canvas_handle, &mut fake_state, None);
         let (tl_x, tl_y, br_x, br_y) = fake_state.get_canvas_mapping();
 
+        if !group_clip_intersects_view(clipping, (tl_x, tl_y, br_x, br_y)) {
+            // group is not visible at all
+            return false;
+        }
+
         let x0 = (clipping.pos.x.to_num::<f32>() - tl_x) / (br_x - tl_x);
         let y0 = (clipping.pos.y.to_num::<f32>() - tl_y) / (br_y - tl_y);
         let x1 = ((clipping.pos.x.to_num::<f32>() + clipping.size.x.to_num::<f32>()) - tl_x)
@@ -1082,11 +1166,6 @@ impl RenderMap {
         let y1 = ((clipping.pos.y.to_num::<f32>() + clipping.size.y.to_num::<f32>()) - tl_y)
             / (br_y - tl_y);
 
-        if x1 < 0.0 || x0 > 1.0 || y1 < 0.0 || y0 > 1.0 {
-            // group is not visible at all
-            return false;
-        }
-
         let (x, y, w, h) = State::auto_round_clipping(
             x0 * self.canvas_handle.canvas_width() as f32,
             y0 * self.canvas_handle.canvas_height() as f32,
@@ -1475,13 +1554,14 @@ impl RenderMap {
             }
             true
         }) {
-            let render_info = render_layer.get_render_info();
             let groups = if matches!(layer_ty, RenderLayerType::Background) {
                 &map.groups.background
             } else {
                 &map.groups.foreground
             };
-            let group = &groups[render_info.group_index];
+            let Some((group, layer)) = render_layer.resolve(groups) else {
+                continue;
+            };
 
             self.render_layer(
                 &map.animations,
@@ -1492,7 +1572,7 @@ impl RenderMap {
                 pipe.cur_anim_time,
                 pipe.include_last_anim_point,
                 &group.attr,
-                &group.layers[render_info.layer_index],
+                layer,
                 None,
             );
         }
@@ -1503,13 +1583,16 @@ impl RenderMap {
         pipe: &mut RenderPipelinePhysics,
         render_infos: &[MapPhysicsRenderInfo],
     ) {
-        for render_info in render_infos {
+        for layer in render_infos
+            .iter()
+            .filter_map(|render_info| render_info.resolve(&pipe.base.map.groups.physics.layers))
+        {
             self.render_physics_layer(
                 &pipe.base.map.animations,
                 pipe.entities_container,
                 pipe.entities_key,
                 pipe.physics_group_name,
-                &pipe.base.map.groups.physics.layers[render_info.layer_index],
+                layer,
                 pipe.base.camera,
                 pipe.base.cur_time,
                 pipe.base.cur_anim_time,
@@ -1,7 +1,18 @@
 pub mod graphic_border_tile;
 pub mod graphic_tile;
+mod layer_reuse;
+pub mod upload_cache;
+pub mod upload_verification;
+
+use std::{
+    borrow::BorrowMut,
+    collections::HashMap,
+    fmt::Debug,
+    ops::{IndexMut, Range},
+    sync::Arc,
+};
 
-use std::{borrow::BorrowMut, collections::HashMap, ops::Range, sync::Arc};
+use fixed::traits::{FromFixed, ToFixed};
 
 use graphics::{
     graphics_mt::GraphicsMultiThreaded,
@@ -16,22 +27,29 @@ use hiarc::{hiarc_safer_rc_refcell, Hiarc};
 use legacy_map::mapdef_06::{DdraceTileNum, TILE_SWITCHTIMEDOPEN};
 use map::{
     map::{
+        animations::{AnimBase, AnimPoint},
         groups::{
             layers::{
-                design::{MapLayer, MapLayerQuadsAttrs, Quad},
+                design::{MapLayer, MapLayerQuad, MapLayerQuadsAttrs, MapLayerTile, Quad},
                 physics::MapLayerPhysics,
-                tiles::{MapTileLayerPhysicsTilesRef, TileBase, TileFlags},
+                tiles::{
+                    MapTileLayerPhysicsTilesRef, SpeedupTile, SwitchTile, TeleTile, TileBase,
+                    TileFlags, TuneTile,
+                },
             },
-            MapGroup,
+            MapGroup, MapGroupAttrClipping, MapGroupPhysicsAttr,
         },
         Map,
     },
-    skeleton::groups::layers::{
-        design::{MapLayerQuadSkeleton, MapLayerTileSkeleton},
-        physics::{
-            MapLayerArbitraryPhysicsSkeleton, MapLayerPhysicsSkeleton,
-            MapLayerSwitchPhysicsSkeleton, MapLayerTelePhysicsSkeleton,
-            MapLayerTilePhysicsBaseSkeleton, MapLayerTunePhysicsSkeleton,
+    skeleton::{
+        animations::AnimationsSkeleton,
+        groups::layers::{
+            design::{MapLayerQuadSkeleton, MapLayerTileSkeleton},
+            physics::{
+                MapLayerArbitraryPhysicsSkeleton, MapLayerPhysicsSkeleton,
+                MapLayerSwitchPhysicsSkeleton, MapLayerTelePhysicsSkeleton,
+                MapLayerTilePhysicsBaseSkeleton, MapLayerTunePhysicsSkeleton,
+            },
         },
     },
     types::NonZeroU16MinusOne,
@@ -42,17 +60,20 @@ use rayon::{
     slice::ParallelSliceMut,
 };
 
-use math::math::vector::ivec2;
+use math::math::vector::{fvec3, ivec2, nfvec4, ubvec4, vec2};
 
 use graphics_types::{
     commands::{CommandUpdateBufferObjectRegion, CommandUpdateShaderStorageRegion},
+    rendering::{GlVertex, State, TextureFilter},
     types::{GraphicsBackendMemory, GraphicsMemoryAllocationType},
 };
 use rustc_hash::FxHashSet;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use sound::{
     scene_object::SceneObject, sound_listener::SoundListener, sound_object::SoundObject,
     sound_play_handle::SoundPlayHandle, types::SoundPlayBaseProps,
 };
+use thiserror::Error;
 
 use crate::map::{
     map_pipeline::GRAPHICS_MAX_QUADS_RENDER_COUNT,
@@ -61,13 +82,14 @@ use crate::map::{
         MapVisualLayerSound, MapVisualLayerTile, MapVisualMetadata, MapVisualPhysicsLayer,
         MapVisualProps,
     },
+    render_tools::RenderTools,
 };
 
 use self::{
-    graphic_border_tile::{
-        add_border_tile, GraphicBorderTile, GraphicsBorderTilePos, GraphicsBorderTileTex,
-    },
+    graphic_border_tile::{add_border_tile, border_tile_size, BorderTileLayout, GraphicBorderTile},
     graphic_tile::{add_tile, GraphicTile},
+    layer_reuse::MapBufferedLayerReuse,
+    upload_verification::{BufferUploadMismatch, MapUploadVerification},
 };
 
 use super::map_with_visual::{
@@ -76,7 +98,100 @@ use super::map_with_visual::{
     MapVisualResources, MapVisualSound, MapVisualSoundAnimation,
 };
 
-#[derive(Debug, Hiarc, Copy, Clone, Default)]
+/// The minimum number of consecutive quads, that only differ by their position,
+/// to render them as instances of a single template quad.
+/// Shorter runs are cheaper to render as normal quads.
+pub const QUAD_INSTANCING_MIN_RUN: usize = 32;
+
+/// The tile count of a tile layer does not fit into the
+/// index range used by the tile layer visuals.
+#[derive(Error, Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[error("the tile layer of size {width}x{height} has too many tiles to be rendered.")]
+pub struct TileLayerTooLarge {
+    pub width: u32,
+    pub height: u32,
+}
+
+/// The reason a layer has no buffers after its upload.
+#[derive(Error, Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LayerUploadError {
+    /// The layer has nothing to render, which is not a failure.
+    #[error("the layer has nothing to render.")]
+    Empty,
+    /// The graphics implementation returned less memory than requested.
+    #[error("allocating {required_size} bytes for the layer failed.")]
+    AllocationFailed { required_size: usize },
+    #[error(transparent)]
+    TooLarge(#[from] TileLayerTooLarge),
+}
+
+/// How a quad layer is uploaded, see [`ClientMapBuffered::upload_design_quad_layer`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct QuadLayerUploadOptions {
+    /// Upload the texture coordinates, even if the layer has no image.
+    pub ignore_is_textured_check: bool,
+    /// Render runs of quads that only differ by their position
+    /// as instances of a single template quad,
+    /// see [`QuadVisualRangeAnim::Instanced`].
+    /// Makes the layer impossible to update per quad.
+    pub instance_repeated_quads: bool,
+    /// Don't upload quads without any area.
+    /// Makes the layer impossible to update per quad.
+    pub skip_degenerate_quads: bool,
+    /// Block until the buffers are GPU-resident,
+    /// instead of possibly deferring the real upload to their first use.
+    pub do_expensive_flushing: bool,
+}
+
+/// How a tile layer is uploaded, see [`ClientMapBuffered::upload_design_tile_layer`]
+/// and [`ClientMapBuffered::upload_physics_layer`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct TileLayerUploadOptions {
+    /// Upload all tiles with texture coordinates, even empty ones,
+    /// so single tiles can be updated later.
+    pub ignore_tile_index_and_is_textured_check: bool,
+    /// Also upload layers that show the index & flags of every tile.
+    pub create_tile_index_flag: bool,
+    /// Block until the buffers are GPU-resident,
+    /// instead of possibly deferring the real upload to their first use.
+    pub do_expensive_flushing: bool,
+}
+
+/// Where a layer is located inside the map.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MapLayerLocation {
+    Physics {
+        layer_index: usize,
+    },
+    Background {
+        group_index: usize,
+        layer_index: usize,
+    },
+    Foreground {
+        group_index: usize,
+        layer_index: usize,
+    },
+}
+
+/// The layers of a [`ClientMapBufferUploadData`] do not match its uploads,
+/// e.g. because the map was modified after [`ClientMapBuffered::prepare_upload`].
+#[derive(Error, Debug, Clone, Copy, PartialEq, Eq)]
+#[error("the map has {layers} {kind} layers, but {uploads} uploads for them.")]
+pub struct MapUploadMismatch {
+    pub kind: &'static str,
+    pub layers: usize,
+    pub uploads: usize,
+}
+
+/// A layer that is missing in the buffered map,
+/// because its upload failed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MapMissingLayer {
+    pub location: MapLayerLocation,
+    pub err: LayerUploadError,
+}
+
+#[derive(Debug, Hiarc, Copy, Clone, Default, Serialize, Deserialize)]
 pub struct TileVisual {
     quad_count_and_is_drawable_flag: u32,
 }
@@ -108,7 +223,7 @@ impl TileVisual {
     }
 }
 
-#[derive(Debug, Default, Clone, Hiarc)]
+#[derive(Debug, Default, Clone, Hiarc, Serialize, Deserialize)]
 pub struct TileLayerVisualsBase {
     pub tiles_of_layer: Vec<TileVisual>,
 
@@ -130,6 +245,13 @@ pub struct TileLayerVisualsBase {
     pub width: u32,
     pub height: u32,
     pub is_textured: bool,
+
+    /// Multiplied with the layer color when rendering, see [`Self::set_layer_tint`].
+    #[serde(skip)]
+    pub tint: Option<[f32; 4]>,
+    /// See [`Self::set_texture_filter`].
+    #[serde(skip)]
+    pub texture_filter: TextureFilter,
 }
 
 impl TileLayerVisualsBase {
@@ -137,15 +259,57 @@ impl TileLayerVisualsBase {
         Default::default()
     }
 
-    fn init(&mut self, width: u32, height: u32) -> bool {
+    /// Tints the whole layer at render time, e.g. to fade it while editing
+    /// other layers. The uploaded tiles are not touched.
+    /// `[1.0; 4]` renders the layer unchanged.
+    pub fn set_layer_tint(&mut self, tint: [f32; 4]) {
+        self.tint = Some(tint);
+    }
+
+    /// Filters the tiles of this layer with `filter` instead of the default
+    /// linear filter, e.g. [`TextureFilter::Nearest`] for pixel art tilesets.
+    pub fn set_texture_filter(&mut self, filter: TextureFilter) {
+        self.texture_filter = filter;
+    }
+
+    /// The state to render this layer with, see [`Self::set_texture_filter`].
+    pub fn layer_state(&self, state: &State) -> State {
+        let mut state = *state;
+        state.filter(self.texture_filter);
+        state
+    }
+
+    /// The drawable tiles as a bitset of `width * height` bits in row order,
+    /// tile `i` is bit `i % 64` of the word `i / 64`.
+    pub fn drawable_bitmask(&self) -> Vec<u64> {
+        let mut bits = vec![0u64; self.tiles_of_layer.len().div_ceil(64)];
+        for (i, _) in self
+            .tiles_of_layer
+            .iter()
+            .enumerate()
+            .filter(|(_, tile)| tile.drawable())
+        {
+            bits[i / 64] |= 1 << (i % 64);
+        }
+        bits
+    }
+
+    fn check_tile_count(width: u32, height: u32) -> Result<(), TileLayerTooLarge> {
+        if width as usize * height as usize >= u32::MAX as usize {
+            Err(TileLayerTooLarge { width, height })
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Returns `Ok(false)` if the layer has no tiles.
+    fn init(&mut self, width: u32, height: u32) -> Result<bool, TileLayerTooLarge> {
         self.width = width;
         self.height = height;
         if width == 0 || height == 0 {
-            return false;
-        }
-        if width as usize * height as usize >= u32::MAX as usize {
-            return false;
+            return Ok(false);
         }
+        Self::check_tile_count(width, height)?;
 
         self.tiles_of_layer
             .resize(height as usize * width as usize, TileVisual::default());
@@ -159,7 +323,7 @@ impl TileLayerVisualsBase {
         self.border_right
             .resize(height as usize, TileVisual::default());
 
-        true
+        Ok(true)
     }
 }
 
@@ -185,6 +349,78 @@ pub struct TileLayerVisuals {
     pub tile_flag_obj: TileLayerBufferedVisualObjects,
 }
 
+impl TileLayerVisuals {
+    /// See [`TileLayerVisualsBase::set_layer_tint`].
+    pub fn set_layer_tint(&mut self, tint: [f32; 4]) {
+        self.base.base.set_layer_tint(tint);
+    }
+
+    /// See [`TileLayerVisualsBase::set_texture_filter`].
+    pub fn set_texture_filter(&mut self, filter: TextureFilter) {
+        self.base.base.set_texture_filter(filter);
+    }
+
+    /// Drops the buffers of the layer to free GPU memory, while the layer
+    /// stays part of the map. It's not rendered until [`Self::reupload`].
+    /// The tile index & flag buffers of the editor are not touched.
+    pub fn evict(&mut self) {
+        self.base.obj = TileLayerBufferedVisualObjects {
+            buffer_object: None,
+            shader_storage: None,
+        };
+    }
+
+    pub fn is_evicted(&self) -> bool {
+        self.base.obj.buffer_object.is_none() && self.base.obj.shader_storage.is_none()
+    }
+
+    /// Recreates the buffers dropped by [`Self::evict`] from `layer`,
+    /// which must be the layer these visuals were uploaded from.
+    pub fn reupload(
+        &mut self,
+        graphics_mt: &GraphicsMultiThreaded,
+        shader_storage_handle: &GraphicsShaderStorageHandle,
+        buffer_object_handle: &GraphicsBufferObjectHandle,
+        backend_handle: &GraphicsBackendHandle,
+        layer: &MapLayerTile,
+    ) -> Result<(), LayerUploadError> {
+        let mut tiles = layer.tiles.iter().map(|tile| (tile.index, tile.flags, -1));
+        let base = match ClientMapBuffered::upload_tile_layer_buffer(
+            (
+                layer.attr.width,
+                layer.attr.height,
+                self.base.base.is_textured,
+                &mut tiles,
+            ),
+            false,
+            false,
+            graphics_mt,
+            TileLayerUploadOptions {
+                ignore_tile_index_and_is_textured_check: self
+                    .base
+                    .base
+                    .ignored_tile_index_and_is_textured_check,
+                ..Default::default()
+            },
+        ) {
+            Ok(base) => base,
+            Err(LayerUploadError::Empty) => return Ok(()),
+            Err(err) => return Err(err),
+        };
+        let visuals = ClientMapBuffered::finish_upload_tile_layer(
+            shader_storage_handle,
+            buffer_object_handle,
+            backend_handle,
+            MapBufferTileLayer {
+                base,
+                ..Default::default()
+            },
+        );
+        self.base.obj = visuals.base.obj;
+        Ok(())
+    }
+}
+
 #[derive(Debug, Hiarc, Clone)]
 pub struct PhysicsTileLayerOverlayVisuals {
     pub ty: MapRenderTextOverlayType,
@@ -197,12 +433,105 @@ pub struct PhysicsTileLayerVisuals {
     pub overlays: Vec<PhysicsTileLayerOverlayVisuals>,
 }
 
+impl PhysicsTileLayerVisuals {
+    /// Tints the layer and all its overlays,
+    /// see [`TileLayerVisualsBase::set_layer_tint`].
+    pub fn set_layer_tint(&mut self, tint: [f32; 4]) {
+        self.base.set_layer_tint(tint);
+        for overlay in self.overlays.iter_mut() {
+            overlay.visuals.base.set_layer_tint(tint);
+        }
+    }
+
+    /// Which tiles of the layer are not air, e.g. for collision preprocessing,
+    /// see [`TileLayerVisualsBase::drawable_bitmask`].
+    pub fn occupancy_bitmask(&self) -> Vec<u64> {
+        self.base.base.base.drawable_bitmask()
+    }
+
+    /// All drawable tiles of the layer together with their position in the tile grid.
+    ///
+    /// `tiles` must be the tiles this layer was uploaded from.
+    pub fn drawable_tiles<'a>(
+        &'a self,
+        tiles: MapTileLayerPhysicsTilesRef<'a>,
+    ) -> impl Iterator<Item = (ivec2, PhysicsTileKind)> + 'a {
+        let visuals = &self.base.base.base;
+        let width = visuals.width as usize;
+        let kinds: Box<dyn Iterator<Item = PhysicsTileKind> + 'a> = match tiles {
+            MapTileLayerPhysicsTilesRef::Arbitrary(_) => Box::new(std::iter::empty()),
+            MapTileLayerPhysicsTilesRef::Game(tiles) => {
+                Box::new(tiles.iter().copied().map(PhysicsTileKind::Game))
+            }
+            MapTileLayerPhysicsTilesRef::Front(tiles) => {
+                Box::new(tiles.iter().copied().map(PhysicsTileKind::Front))
+            }
+            MapTileLayerPhysicsTilesRef::Tele(tiles) => {
+                Box::new(tiles.iter().copied().map(PhysicsTileKind::Tele))
+            }
+            MapTileLayerPhysicsTilesRef::Speedup(tiles) => {
+                Box::new(tiles.iter().copied().map(PhysicsTileKind::Speedup))
+            }
+            MapTileLayerPhysicsTilesRef::Switch(tiles) => {
+                Box::new(tiles.iter().copied().map(PhysicsTileKind::Switch))
+            }
+            MapTileLayerPhysicsTilesRef::Tune(tiles) => {
+                Box::new(tiles.iter().copied().map(PhysicsTileKind::Tune))
+            }
+        };
+        visuals
+            .tiles_of_layer
+            .iter()
+            .zip(kinds)
+            .enumerate()
+            .filter(|(_, (visual, _))| visual.drawable())
+            .map(move |(i, (_, kind))| (ivec2::new((i % width) as i32, (i / width) as i32), kind))
+    }
+}
+
+/// A tile of a physics layer, see [`PhysicsTileLayerVisuals::drawable_tiles`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PhysicsTileKind {
+    Game(TileBase),
+    Front(TileBase),
+    Tele(TeleTile),
+    Speedup(SpeedupTile),
+    Switch(SwitchTile),
+    Tune(TuneTile),
+}
+
+/// The kind of a physics layer, see [`ClientMapBuffered::physics_layer_kinds`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PhysicsLayerKind {
+    Arbitrary,
+    Game,
+    Front,
+    Tele,
+    Speedup,
+    Switch,
+    Tune,
+}
+
+impl<L> From<&MapLayerPhysicsSkeleton<L>> for PhysicsLayerKind {
+    fn from(layer: &MapLayerPhysicsSkeleton<L>) -> Self {
+        match layer {
+            MapLayerPhysicsSkeleton::Arbitrary(_) => Self::Arbitrary,
+            MapLayerPhysicsSkeleton::Game(_) => Self::Game,
+            MapLayerPhysicsSkeleton::Front(_) => Self::Front,
+            MapLayerPhysicsSkeleton::Tele(_) => Self::Tele,
+            MapLayerPhysicsSkeleton::Speedup(_) => Self::Speedup,
+            MapLayerPhysicsSkeleton::Switch(_) => Self::Switch,
+            MapLayerPhysicsSkeleton::Tune(_) => Self::Tune,
+        }
+    }
+}
+
 #[derive(Copy, Clone, Default)]
 pub struct QuadVisual {
     pub index_buffer_byte_offset: usize,
 }
 
-#[derive(Debug, Hiarc, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Hiarc, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum QuadVisualRangeAnim {
     NoAnim,
     ColorAnim {
@@ -221,9 +550,159 @@ pub enum QuadVisualRangeAnim {
     },
     /// Too many quads with alternating anims
     Chaos,
+    /// Unanimated quads that only differ by their position,
+    /// rendered as instances of the template quad `template`,
+    /// see [`QuadLayerVisuals::instance_offsets`].
+    Instanced {
+        template: usize,
+    },
 }
 
-#[derive(Debug, Hiarc, Clone)]
+/// Evaluates the animation `anim` at the current time, shifted by `anim_offset`.
+/// Synchronized animations use `cur_anim_time`, all others `cur_time`.
+///
+/// The time wraps around at the last point of the animation,
+/// an animation without any points evaluates to the default value.
+pub fn eval_anim<
+    F,
+    T: DeserializeOwned + Debug + Copy + Default + IndexMut<usize, Output = F>,
+    const CHANNELS: usize,
+>(
+    anim: &AnimBase<AnimPoint<T, CHANNELS>>,
+    anim_offset: &time::Duration,
+    cur_time: &std::time::Duration,
+    cur_anim_time: &std::time::Duration,
+    include_last_anim_point: bool,
+) -> T
+where
+    F: Copy + FromFixed + ToFixed,
+{
+    let total_time = if anim.synchronized {
+        time::Duration::try_from(*cur_anim_time).unwrap_or_default()
+    } else {
+        time::Duration::try_from(*cur_time).unwrap_or_default()
+    };
+    let anim_time = total_time + *anim_offset;
+
+    RenderTools::render_eval_anim(&anim.points, anim_time, include_last_anim_point)
+}
+
+/// Evaluates the position animation `anim` of a quad the same way
+/// the renderer does, see [`eval_anim`].
+///
+/// Returns `None` if the animation does not exist.
+pub fn eval_pos_anim<AN, AS>(
+    animations: &AnimationsSkeleton<AN, AS>,
+    anim: usize,
+    anim_offset: &time::Duration,
+    cur_time: &std::time::Duration,
+    cur_anim_time: &std::time::Duration,
+    include_last_anim_point: bool,
+) -> Option<fvec3> {
+    animations.pos.get(anim).map(|anim| {
+        eval_anim(
+            &anim.def,
+            anim_offset,
+            cur_time,
+            cur_anim_time,
+            include_last_anim_point,
+        )
+    })
+}
+
+/// Evaluates the color animation `anim` of a quad the same way
+/// the renderer does, see [`eval_anim`].
+///
+/// Returns `None` if the animation does not exist.
+pub fn eval_color_anim<AN, AS>(
+    animations: &AnimationsSkeleton<AN, AS>,
+    anim: usize,
+    anim_offset: &time::Duration,
+    cur_time: &std::time::Duration,
+    cur_anim_time: &std::time::Duration,
+    include_last_anim_point: bool,
+) -> Option<nfvec4> {
+    animations.color.get(anim).map(|anim| {
+        eval_anim(
+            &anim.def,
+            anim_offset,
+            cur_time,
+            cur_anim_time,
+            include_last_anim_point,
+        )
+    })
+}
+
+/// The quad layers for which `uses_anim` is true,
+/// based on the distinct anims stored in their visuals.
+fn quad_layers_using_anim(
+    groups: &MapVisualGroups,
+    uses_anim: impl Fn(&QuadLayerVisuals) -> bool,
+) -> Vec<MapLayerLocation> {
+    let uses_anim = &uses_anim;
+    let background = groups.background.iter().map(|group| (true, group));
+    let foreground = groups.foreground.iter().map(|group| (false, group));
+    background
+        .enumerate()
+        .chain(foreground.enumerate())
+        .flat_map(|(group_index, (is_background, group))| {
+            group
+                .layers
+                .iter()
+                .enumerate()
+                .filter_map(move |(layer_index, layer)| {
+                    let MapVisualLayer::Quad(layer) = layer else {
+                        return None;
+                    };
+                    uses_anim(&layer.user).then_some(if is_background {
+                        MapLayerLocation::Background {
+                            group_index,
+                            layer_index,
+                        }
+                    } else {
+                        MapLayerLocation::Foreground {
+                            group_index,
+                            layer_index,
+                        }
+                    })
+                })
+        })
+        .collect()
+}
+
+/// Replaces the points of the pos animation `anim` and returns the
+/// quad layers that use it, which the renderer has to refresh.
+///
+/// Returns `None` if the animation does not exist.
+pub fn set_pos_anim_points(
+    animations: &mut MapVisualAnimations,
+    groups: &MapVisualGroups,
+    anim: usize,
+    points: Vec<AnimPoint<fvec3, 3>>,
+) -> Option<Vec<MapLayerLocation>> {
+    animations.pos.get_mut(anim)?.def.points = points;
+    Some(quad_layers_using_anim(groups, |visuals| {
+        visuals.pos_anims.iter().any(|&(index, _)| index == anim)
+    }))
+}
+
+/// Replaces the points of the color animation `anim` and returns the
+/// quad layers that use it, see [`set_pos_anim_points`].
+///
+/// Returns `None` if the animation does not exist.
+pub fn set_color_anim_points(
+    animations: &mut MapVisualAnimations,
+    groups: &MapVisualGroups,
+    anim: usize,
+    points: Vec<AnimPoint<nfvec4, 4>>,
+) -> Option<Vec<MapLayerLocation>> {
+    animations.color.get_mut(anim)?.def.points = points;
+    Some(quad_layers_using_anim(groups, |visuals| {
+        visuals.color_anims.iter().any(|&(index, _)| index == anim)
+    }))
+}
+
+#[derive(Debug, Hiarc, Clone, Serialize, Deserialize)]
 pub struct QuadVisualRange {
     pub anim: QuadVisualRangeAnim,
     pub range: Range<usize>,
@@ -237,17 +716,125 @@ pub struct QuadLayerVisuals {
     pub pos_anims: Vec<(usize, time::Duration)>,
     /// distinct color anims in this layer
     pub color_anims: Vec<(usize, time::Duration)>,
+    /// The template quads of [`QuadVisualRangeAnim::Instanced`] ranges,
+    /// in the vertex format of quad containers.
+    pub instanced_buffer_object_index: Option<BufferObject>,
+    /// The centers of the instances of each template quad.
+    pub instance_offsets: Vec<Vec<vec2>>,
+}
+
+impl QuadLayerVisuals {
+    /// Drops the buffers of the layer to free GPU memory, while the layer
+    /// stays part of the map. It's not rendered until [`Self::reupload`].
+    pub fn evict(&mut self) {
+        self.buffer_object_index = None;
+        self.instanced_buffer_object_index = None;
+    }
+
+    pub fn is_evicted(&self) -> bool {
+        self.buffer_object_index.is_none() && self.instanced_buffer_object_index.is_none()
+    }
+
+    /// Recreates the buffers dropped by [`Self::evict`] from `layer`,
+    /// which must be the layer these visuals were uploaded from.
+    /// `options` should be the ones the layer was uploaded with,
+    /// the draw ranges are rebuilt too, so they always match the new buffers.
+    pub fn reupload(
+        &mut self,
+        graphics_mt: &GraphicsMultiThreaded,
+        buffer_object_handle: &GraphicsBufferObjectHandle,
+        backend_handle: &GraphicsBackendHandle,
+        layer: &MapLayerQuad,
+        options: QuadLayerUploadOptions,
+    ) -> Result<(), LayerUploadError> {
+        let upload_data = match ClientMapBuffered::upload_quad_layer_buffer(
+            &layer.attr,
+            &layer.quads,
+            0,
+            0,
+            graphics_mt,
+            options,
+        ) {
+            Ok(upload_data) => upload_data,
+            Err(LayerUploadError::Empty) => Default::default(),
+            Err(err) => return Err(err),
+        };
+        *self = ClientMapBuffered::finish_upload_quad_layer(
+            buffer_object_handle,
+            backend_handle,
+            upload_data,
+        );
+        Ok(())
+    }
+}
+
+/// How the sounds of a sound layer fade out towards the border of their shape.
+#[derive(Debug, Hiarc, Default, Clone, Copy, PartialEq, Eq)]
+pub enum SoundLayerAttenuation {
+    /// The falloff of the sound shape as is.
+    #[default]
+    Linear,
+    /// Squared falloff, the volume drops faster once leaving the full volume area.
+    Quadratic,
+    /// Smoothstep of the falloff, the fade starts and ends softly.
+    Smooth,
+}
+
+impl SoundLayerAttenuation {
+    /// Maps the falloff (`0.0..=1.0`) of a sound to its volume factor.
+    pub fn apply(&self, falloff: f64) -> f64 {
+        let falloff = falloff.clamp(0.0, 1.0);
+        match self {
+            SoundLayerAttenuation::Linear => falloff,
+            SoundLayerAttenuation::Quadratic => falloff * falloff,
+            SoundLayerAttenuation::Smooth => falloff * falloff * (3.0 - 2.0 * falloff),
+        }
+    }
 }
 
 #[hiarc_safer_rc_refcell]
-#[derive(Debug, Hiarc, Default)]
+#[derive(Debug, Hiarc)]
 pub struct SoundLayerSounds {
     /// `usize` here equals the sound index ([`map::map::MapLayerSound`])
     sound_plays: HashMap<usize, SoundPlayHandle>,
+
+    /// Master volume of the whole layer.
+    volume: f64,
+    attenuation: SoundLayerAttenuation,
+}
+
+#[hiarc_safer_rc_refcell]
+impl Default for SoundLayerSounds {
+    fn default() -> Self {
+        Self {
+            sound_plays: Default::default(),
+            volume: 1.0,
+            attenuation: Default::default(),
+        }
+    }
 }
 
 #[hiarc_safer_rc_refcell]
 impl SoundLayerSounds {
+    /// Sets the master volume of the layer, which scales the volume of all its sounds.
+    /// Applies to the next [`Self::update`] of the playing sounds.
+    pub fn set_layer_volume(&mut self, v: f32) {
+        self.volume = (v as f64).max(0.0);
+    }
+    pub fn layer_volume(&self) -> f64 {
+        self.volume
+    }
+    pub fn set_attenuation(&mut self, attenuation: SoundLayerAttenuation) {
+        self.attenuation = attenuation;
+    }
+    pub fn attenuation(&self) -> SoundLayerAttenuation {
+        self.attenuation
+    }
+    /// Composites the layer's volume into the props of a sound of this layer.
+    pub fn layer_props(&self, mut props: SoundPlayBaseProps) -> SoundPlayBaseProps {
+        props.volume *= self.volume;
+        props
+    }
     pub fn is_playing(&self, index: usize) -> bool {
         self.sound_plays.contains_key(&index)
     }
@@ -259,9 +846,10 @@ impl SoundLayerSounds {
             sound_play.resume();
         }
     }
+    /// Updates the sound, the layer's volume is applied to the `props`.
     pub fn update(&self, index: usize, props: SoundPlayBaseProps) {
         if let Some(sound_play) = self.sound_plays.get(&index) {
-            sound_play.update(props);
+            sound_play.update(self.layer_props(props));
         }
     }
     pub fn pause(&self, index: usize) {
@@ -293,6 +881,42 @@ struct TmpQuadVertexTextured {
 }
 
 impl TmpQuadVertexTextured {
+    /// Creates a vertex from the converted quad values.
+    /// Non-finite values are replaced by 0 and colors are clamped to `0..=255`,
+    /// so a single broken quad cannot make the whole layer vanish.
+    ///
+    /// Returns `false` if any of the values was not finite.
+    fn new(pos: [f32; 2], center: [f32; 2], tex: [f32; 2], color: [f32; 4]) -> (Self, bool) {
+        let mut all_finite = true;
+        let mut finite_or_zero = |v: f32| {
+            if v.is_finite() {
+                v
+            } else {
+                all_finite = false;
+                0.0
+            }
+        };
+        let [x, y] = pos.map(&mut finite_or_zero);
+        let [center_x, center_y] = center.map(&mut finite_or_zero);
+        let [u, v] = tex.map(&mut finite_or_zero);
+        let [r, g, b, a] = color.map(|c| (finite_or_zero(c) * 255.0).clamp(0.0, 255.0) as u8);
+        (
+            Self {
+                x,
+                y,
+                center_x,
+                center_y,
+                r,
+                g,
+                b,
+                a,
+                u,
+                v,
+            },
+            all_finite,
+        )
+    }
+
     fn copy_into_slice(&self, dest: &mut [u8], textured: bool) -> usize {
         let mut off: usize = 0;
         self.x.to_ne_bytes().iter().for_each(|byte| {
@@ -355,26 +979,53 @@ impl TmpQuadTextured {
         });
         off
     }
+
+    /// Copies the quad in the vertex format of quad containers ([`GlVertex`]),
+    /// which is used by instanced quads. The center is not part of it.
+    fn copy_into_slice_instanced(&self, dest: &mut [u8]) -> usize {
+        let mut bytes: Vec<u8> = Vec::with_capacity(std::mem::size_of::<GlVertex>() * 4);
+        self.vertices.iter().for_each(|v| {
+            GlVertex {
+                pos: vec2::new(v.x, v.y),
+                tex: vec2::new(v.u, v.v),
+                color: ubvec4::new(v.r, v.g, v.b, v.a),
+            }
+            .append_to_bytes_vec(&mut bytes);
+        });
+        dest[..bytes.len()].copy_from_slice(&bytes);
+        bytes.len()
+    }
 }
 
-#[derive(Debug, Hiarc, Copy, Clone)]
+#[derive(Debug, Hiarc, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum MapRenderTextOverlayType {
     Top,
     Bottom,
     Center,
 }
 
-#[derive(Debug, Hiarc, Default, Copy, Clone)]
+#[derive(Debug, Hiarc, Default, Copy, Clone, Serialize, Deserialize)]
 pub struct MapRenderInfo {
     pub group_index: usize,
     pub layer_index: usize,
 }
 
-#[derive(Debug, Default, Clone)]
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
 pub struct MapPhysicsRenderInfo {
     pub layer_index: usize,
 }
 
+impl MapPhysicsRenderInfo {
+    /// The physics layer this render info points to,
+    /// `None` if the index is out of bounds.
+    pub fn resolve<'a>(
+        &self,
+        layers: &'a [MapVisualPhysicsLayer],
+    ) -> Option<&'a MapVisualPhysicsLayer> {
+        layers.get(self.layer_index)
+    }
+}
+
 #[derive(Debug, Copy, Clone)]
 pub enum MapRenderLayer {
     Tile(MapRenderInfo),
@@ -388,6 +1039,76 @@ impl MapRenderLayer {
             MapRenderLayer::Quad(render_info) => render_info,
         }
     }
+
+    /// The group and layer this render layer points to.
+    /// `None` if the indices are out of bounds or the layer is not of the
+    /// same kind as the render layer.
+    pub fn resolve<'a>(
+        &self,
+        groups: &'a [MapVisualGroup],
+    ) -> Option<(&'a MapVisualGroup, &'a MapVisualLayer)> {
+        let render_info = self.get_render_info();
+        let group = groups.get(render_info.group_index)?;
+        let layer = group.layers.get(render_info.layer_index)?;
+        match (self, layer) {
+            (MapRenderLayer::Tile(_), MapVisualLayer::Tile(_))
+            | (MapRenderLayer::Quad(_), MapVisualLayer::Quad(_)) => Some((group, layer)),
+            _ => None,
+        }
+    }
+}
+
+/// Whether the clip rect of a group intersects the view rect `(tl_x, tl_y, br_x, br_y)`.
+/// The view rect is in map units, without any parallax or offset of the group applied,
+/// the same as the clip rect.
+pub fn group_clip_intersects_view(
+    clipping: &MapGroupAttrClipping,
+    (tl_x, tl_y, br_x, br_y): (f32, f32, f32, f32),
+) -> bool {
+    let x0 = clipping.pos.x.to_num::<f32>();
+    let y0 = clipping.pos.y.to_num::<f32>();
+    let x1 = x0 + clipping.size.x.to_num::<f32>();
+    let y1 = y0 + clipping.size.y.to_num::<f32>();
+    !(x1 < tl_x || x0 > br_x || y1 < tl_y || y0 > br_y)
+}
+
+/// Indices into `render_layers` of the layers, whose group is not clipped away
+/// by the view rect, see [`group_clip_intersects_view`].
+/// Layers of groups without clipping are always visible.
+fn visible_render_layers(
+    render_layers: &[MapRenderLayer],
+    groups: &[MapVisualGroup],
+    view: (f32, f32, f32, f32),
+) -> Vec<usize> {
+    render_layers
+        .iter()
+        .enumerate()
+        .filter(|(_, render_layer)| {
+            groups
+                .get(render_layer.get_render_info().group_index)
+                .is_some_and(|group| {
+                    group
+                        .attr
+                        .clipping
+                        .as_ref()
+                        .is_none_or(|clipping| group_clip_intersects_view(clipping, view))
+                })
+        })
+        .map(|(index, _)| index)
+        .collect()
+}
+
+/// Pairs the render layers with the layers of `groups` they point to,
+/// skipping the ones that can not be resolved.
+fn resolve_render_layers<'a>(
+    render_layers: &'a [MapRenderLayer],
+    groups: &'a [MapVisualGroup],
+) -> impl Iterator<Item = (&'a MapRenderLayer, &'a MapVisualLayer)> {
+    render_layers.iter().filter_map(|render_layer| {
+        render_layer
+            .resolve(groups)
+            .map(|(_, layer)| (render_layer, layer))
+    })
 }
 
 fn flag_to_bits(mut flags: TileFlags) -> u8 {
@@ -418,6 +1139,10 @@ pub struct ClientMapBuffered {
     pub map_visual: MapVisual,
     pub render: ClientMapBufferedRenderProcess,
     pub sound: ClientMapBufferedSoundProcess,
+    /// The layers that failed to upload and are not rendered.
+    pub missing_layers: Vec<MapMissingLayer>,
+    /// See [`ClientMapBufferedBuilder::verify_uploads`].
+    pub upload_verification: MapUploadVerification,
 }
 
 #[derive(Debug, Default, Hiarc)]
@@ -436,6 +1161,9 @@ pub struct MapBufferTileLayer {
     /// For editor
     tile_index: Option<MapBufferTileLayerBase>,
     tile_flag: Option<MapBufferTileLayerBase>,
+    /// Why the upload of the layer failed, if it did
+    #[hiarc_skip_unsafe]
+    upload_err: Option<LayerUploadError>,
 }
 
 #[derive(Debug, Default)]
@@ -446,23 +1174,39 @@ pub struct MapBufferPhysicsTileLayer {
     tile_flag: Option<MapBufferTileLayerBase>,
     render_info: MapPhysicsRenderInfo,
     overlays: Vec<(MapRenderTextOverlayType, MapBufferTileLayerBase)>,
+    /// Why the upload of the layer failed, if it did
+    upload_err: Option<LayerUploadError>,
 }
 
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
 struct QuadVisualExtra {
     draw_ranges: Vec<QuadVisualRange>,
     // distinct pos & color anims
     pos_anims: Vec<(usize, time::Duration)>,
     color_anims: Vec<(usize, time::Duration)>,
+    instance_offsets: Vec<Vec<vec2>>,
 }
 
 #[derive(Debug, Default)]
 pub struct ClientMapBufferQuadLayer {
     mem: Option<GraphicsBackendMemory>,
+    /// The template quads of instanced quads
+    instanced_mem: Option<GraphicsBackendMemory>,
     quad_count_for_indices: u64,
     render_info: MapRenderInfo,
+    /// How many degenerate quads were not uploaded
+    skipped_quads: usize,
 
     extra: QuadVisualExtra,
+    /// Why the upload of the layer failed, if it did
+    upload_err: Option<LayerUploadError>,
+}
+
+impl ClientMapBufferQuadLayer {
+    /// How many degenerate (zero-area) quads were skipped during the upload.
+    pub fn skipped_quad_count(&self) -> usize {
+        self.skipped_quads
+    }
 }
 
 pub struct ClientMapBufferUploadData {
@@ -475,252 +1219,653 @@ pub struct ClientMapBufferUploadData {
     pub map: Map,
 }
 
-impl ClientMapBuffered {
-    pub fn new(
-        backend_handle: &GraphicsBackendHandle,
-        shader_storage_handle: &GraphicsShaderStorageHandle,
-        buffer_object_handle: &GraphicsBufferObjectHandle,
-        upload_data: ClientMapBufferUploadData,
-        images: Vec<TextureContainer>,
-        images_2d_array: Vec<TextureContainer2dArray>,
-        sound_scene: SceneObject,
-        sound_listener: SoundListener,
-        sound_objects: Vec<SoundObject>,
-    ) -> Self {
-        fn collect_groups(
-            shader_storage_handle: &GraphicsShaderStorageHandle,
-            buffer_object_handle: &GraphicsBufferObjectHandle,
-            backend_handle: &GraphicsBackendHandle,
-            groups: Vec<MapGroup>,
-            mut tile_layer_uploads: impl Iterator<Item = MapBufferTileLayer>,
-            mut quad_layer_uploads: impl Iterator<Item = ClientMapBufferQuadLayer>,
-            tile_render_infos: &mut Vec<MapRenderInfo>,
-            quad_render_infos: &mut Vec<MapRenderInfo>,
-            sound: &mut Vec<MapSoundProcessInfo>,
-        ) -> Vec<MapVisualGroup> {
+impl ClientMapBufferUploadData {
+    /// Checks that every layer of the map has exactly one upload.
+    /// Maps without any layers or groups are valid.
+    pub fn validate(&self) -> Result<(), MapUploadMismatch> {
+        let layer_count = |groups: &[MapGroup], is_kind: fn(&MapLayer) -> bool| {
             groups
-                .into_iter()
-                .enumerate()
-                .map(|(g, def)| MapVisualGroup {
-                    attr: def.attr,
-                    layers: def
-                        .layers
-                        .into_iter()
-                        .enumerate()
-                        .map(|(l, def)| match def {
-                            MapLayer::Abritrary(def) => {
-                                MapVisualLayer::Abritrary(MapVisualLayerArbitrary {
-                                    buf: def,
-                                    user: (),
-                                })
-                            }
-                            MapLayer::Tile(def) => {
-                                let upload_data = tile_layer_uploads.next().unwrap();
-                                let render_info = upload_data.render_info;
-
-                                let visuals = ClientMapBuffered::finish_upload_tile_layer(
-                                    shader_storage_handle,
-                                    buffer_object_handle,
-                                    backend_handle,
-                                    upload_data,
-                                );
-
-                                if visuals.base.obj.buffer_object.is_some()
-                                    || visuals.base.obj.shader_storage.is_some()
-                                {
-                                    tile_render_infos.push(render_info);
-                                }
+                .iter()
+                .flat_map(|group| group.layers.iter())
+                .filter(|layer| is_kind(layer))
+                .count()
+        };
+        let is_tile: fn(&MapLayer) -> bool = |layer| matches!(layer, MapLayer::Tile(_));
+        let is_quad: fn(&MapLayer) -> bool = |layer| matches!(layer, MapLayer::Quad(_));
+        let groups = &self.map.groups;
+        [
+            (
+                "physics",
+                groups.physics.layers.len(),
+                self.physics_tile_layer_uploads.len(),
+            ),
+            (
+                "background tile",
+                layer_count(&groups.background, is_tile),
+                self.bg_tile_layer_uploads.len(),
+            ),
+            (
+                "background quad",
+                layer_count(&groups.background, is_quad),
+                self.bg_quad_layer_uploads.len(),
+            ),
+            (
+                "foreground tile",
+                layer_count(&groups.foreground, is_tile),
+                self.fg_tile_layer_uploads.len(),
+            ),
+            (
+                "foreground quad",
+                layer_count(&groups.foreground, is_quad),
+                self.fg_quad_layer_uploads.len(),
+            ),
+        ]
+        .into_iter()
+        .try_for_each(|(kind, layers, uploads)| {
+            if layers == uploads {
+                Ok(())
+            } else {
+                Err(MapUploadMismatch {
+                    kind,
+                    layers,
+                    uploads,
+                })
+            }
+        })
+    }
 
-                                MapVisualLayer::Tile(MapVisualLayerTile {
-                                    layer: def,
-                                    user: visuals,
-                                })
-                            }
-                            MapLayer::Quad(def) => {
-                                let upload_data = quad_layer_uploads.next().unwrap();
-                                let render_info = upload_data.render_info;
-                                let visuals = ClientMapBuffered::finish_upload_quad_layer(
-                                    buffer_object_handle,
-                                    backend_handle,
-                                    upload_data,
-                                );
-
-                                if visuals.buffer_object_index.is_some() {
-                                    quad_render_infos.push(render_info);
-                                }
+    /// The largest amount of quads any of the layers
+    /// must be able to draw with the index buffer.
+    pub fn max_quad_count_for_indices(&self) -> u64 {
+        let tile_layer = |base: &MapBufferTileLayerBase,
+                          tile_index: &Option<MapBufferTileLayerBase>,
+                          tile_flag: &Option<MapBufferTileLayerBase>| {
+            [Some(base), tile_index.as_ref(), tile_flag.as_ref()]
+                .into_iter()
+                .flatten()
+                .map(|base| base.quad_count_for_indices)
+                .max()
+                .unwrap_or_default()
+        };
+        let design_tile_layers = self
+            .bg_tile_layer_uploads
+            .iter()
+            .chain(self.fg_tile_layer_uploads.iter())
+            .map(|layer| tile_layer(&layer.base, &layer.tile_index, &layer.tile_flag));
+        let physics_layers = self.physics_tile_layer_uploads.iter().map(|layer| {
+            layer
+                .overlays
+                .iter()
+                .map(|(_, overlay)| overlay.quad_count_for_indices)
+                .fold(
+                    tile_layer(&layer.base, &layer.tile_index, &layer.tile_flag),
+                    u64::max,
+                )
+        });
+        let quad_layers = self
+            .bg_quad_layer_uploads
+            .iter()
+            .chain(self.fg_quad_layer_uploads.iter())
+            .map(|layer| layer.quad_count_for_indices);
+        design_tile_layers
+            .chain(physics_layers)
+            .chain(quad_layers)
+            .max()
+            .unwrap_or_default()
+    }
+}
 
-                                MapVisualLayer::Quad(MapVisualLayerQuad {
-                                    layer: def,
-                                    user: visuals,
-                                })
-                            }
-                            MapLayer::Sound(def) => {
-                                sound.push(MapSoundProcessInfo {
-                                    group_index: g,
-                                    layer_index: l,
-                                });
-
-                                MapVisualLayer::Sound(MapVisualLayerSound {
-                                    layer: def,
-                                    user: SoundLayerSounds::default(),
-                                })
-                            }
-                        })
-                        .collect(),
-                    name: def.name,
-                    user: (),
-                })
-                .collect()
-        }
+/// How many layers of a [`ClientMapBufferedBuilder`] are already uploaded.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ClientMapBufferedUploadProgress {
+    pub uploaded_layers: usize,
+    pub total_layers: usize,
+    /// How many of the uploaded layers reused the buffers of a previous map,
+    /// see [`ClientMapBufferedBuilder::reuse_layers_of`].
+    pub reused_layers: usize,
+}
 
-        let mut bg_tile_render_infos: Vec<MapRenderInfo> = Default::default();
-        let mut fg_tile_render_infos: Vec<MapRenderInfo> = Default::default();
-        let mut physics_tile_render_infos: Vec<MapPhysicsRenderInfo> = Default::default();
-        let mut bg_quad_render_infos: Vec<MapRenderInfo> = Default::default();
-        let mut fg_quad_render_infos: Vec<MapRenderInfo> = Default::default();
+impl ClientMapBufferedUploadProgress {
+    pub fn is_finished(&self) -> bool {
+        self.uploaded_layers >= self.total_layers
+    }
+}
 
-        bg_tile_render_infos.reserve(upload_data.bg_tile_layer_uploads.len());
-        fg_tile_render_infos.reserve(upload_data.fg_tile_layer_uploads.len());
-        physics_tile_render_infos.reserve(upload_data.physics_tile_layer_uploads.len());
-        bg_quad_render_infos.reserve(upload_data.bg_quad_layer_uploads.len());
-        fg_quad_render_infos.reserve(upload_data.fg_quad_layer_uploads.len());
+/// The physics layers that still wait for their upload to finish.
+struct MapBufferedPhysicsUpload {
+    layers: std::vec::IntoIter<MapLayerPhysics>,
+    tile_layer_uploads: std::vec::IntoIter<MapBufferPhysicsTileLayer>,
 
-        let mut physics_tile_layer_uploads = upload_data.physics_tile_layer_uploads.into_iter();
-        let bg_tile_layer_uploads = upload_data.bg_tile_layer_uploads.into_iter();
-        let fg_tile_layer_uploads = upload_data.fg_tile_layer_uploads.into_iter();
-        let bg_quad_layer_uploads = upload_data.bg_quad_layer_uploads.into_iter();
-        let fg_quad_layer_uploads = upload_data.fg_quad_layer_uploads.into_iter();
+    visual_layers: Vec<MapVisualPhysicsLayer>,
+    render_infos: Vec<MapPhysicsRenderInfo>,
+    missing_layers: Vec<MapMissingLayer>,
+}
 
-        let mut sound = ClientMapBufferedSoundProcess::default();
+impl MapBufferedPhysicsUpload {
+    fn new(layers: Vec<MapLayerPhysics>, uploads: Vec<MapBufferPhysicsTileLayer>) -> Self {
+        Self {
+            visual_layers: Vec::with_capacity(layers.len()),
+            render_infos: Vec::with_capacity(uploads.len()),
+            missing_layers: Default::default(),
+            layers: layers.into_iter(),
+            tile_layer_uploads: uploads.into_iter(),
+        }
+    }
 
-        let mut res = ClientMapBuffered {
-            map_visual: MapVisual {
-                user: MapVisualProps {
-                    sound_scene,
-                    global_listener: sound_listener,
-                },
-                groups: MapVisualGroups {
-                    physics: MapVisualPhysicsGroup {
-                        attr: upload_data.map.groups.physics.attr,
-                        layers: upload_data
-                            .map
-                            .groups
-                            .physics
-                            .layers
-                            .into_iter()
-                            .map(|def| {
-                                let uploaded_data = physics_tile_layer_uploads.next().unwrap();
-                                let render_info = uploaded_data.render_info.clone();
-                                let visuals = Self::finish_upload_physics_tile_layer(
-                                    shader_storage_handle,
-                                    buffer_object_handle,
-                                    backend_handle,
-                                    uploaded_data,
-                                );
-
-                                if visuals.base.base.obj.buffer_object.is_some()
-                                    || visuals.base.base.obj.shader_storage.is_some()
-                                {
-                                    physics_tile_render_infos.push(render_info);
-                                }
-                                match def {
-                                    MapLayerPhysics::Arbitrary(layer) => {
-                                        MapVisualPhysicsLayer::Arbitrary(
-                                            MapLayerArbitraryPhysicsSkeleton {
-                                                buf: layer,
-                                                user: visuals,
-                                            },
-                                        )
-                                    }
-                                    MapLayerPhysics::Game(layer) => MapVisualPhysicsLayer::Game(
-                                        MapLayerTilePhysicsBaseSkeleton {
-                                            layer,
-                                            user: visuals,
-                                        },
-                                    ),
-                                    MapLayerPhysics::Front(layer) => MapVisualPhysicsLayer::Front(
-                                        MapLayerTilePhysicsBaseSkeleton {
-                                            layer,
-                                            user: visuals,
-                                        },
-                                    ),
-                                    MapLayerPhysics::Tele(layer) => {
-                                        MapVisualPhysicsLayer::Tele(MapLayerTelePhysicsSkeleton {
-                                            layer,
-                                            user: visuals,
-                                        })
-                                    }
-                                    MapLayerPhysics::Speedup(layer) => {
-                                        MapVisualPhysicsLayer::Speedup(
-                                            MapLayerTilePhysicsBaseSkeleton {
-                                                layer,
-                                                user: visuals,
-                                            },
-                                        )
-                                    }
-                                    MapLayerPhysics::Switch(layer) => {
-                                        MapVisualPhysicsLayer::Switch(
-                                            MapLayerSwitchPhysicsSkeleton {
-                                                layer,
-                                                user: visuals,
-                                            },
-                                        )
-                                    }
-                                    MapLayerPhysics::Tune(layer) => {
-                                        MapVisualPhysicsLayer::Tune(MapLayerTunePhysicsSkeleton {
-                                            layer,
-                                            user: visuals,
-                                        })
-                                    }
-                                }
-                            })
-                            .collect(),
+    /// Returns `false` if there was no layer left to upload.
+    fn upload_next_layer(
+        &mut self,
+        shader_storage_handle: &GraphicsShaderStorageHandle,
+        buffer_object_handle: &GraphicsBufferObjectHandle,
+        backend_handle: &GraphicsBackendHandle,
+        attr: &MapGroupPhysicsAttr,
+        reuse: &mut MapBufferedLayerReuse,
+        verification: &mut MapUploadVerification,
+    ) -> bool {
+        let Some(def) = self.layers.next() else {
+            return false;
+        };
+        let location = MapLayerLocation::Physics {
+            layer_index: self.visual_layers.len(),
+        };
+        let mut uploaded_data = self
+            .tile_layer_uploads
+            .next()
+            .expect("the upload counts are validated by ClientMapBufferedBuilder::new");
+        let render_info = uploaded_data.render_info.clone();
+        let visuals = match reuse.take_physics_layer(attr, def.as_ref()) {
+            Some(visuals) => visuals,
+            None => {
+                if let Some(err) = uploaded_data.upload_err.take() {
+                    self.missing_layers.push(MapMissingLayer { location, err });
+                }
+                let samples = verification.sample_physics_layer(&uploaded_data);
+                let visuals = ClientMapBuffered::finish_upload_physics_tile_layer(
+                    shader_storage_handle,
+                    buffer_object_handle,
+                    backend_handle,
+                    uploaded_data,
+                );
+                verification.add_physics_layer(location, &visuals, samples);
+                visuals
+            }
+        };
+
+        if visuals.base.base.obj.buffer_object.is_some()
+            || visuals.base.base.obj.shader_storage.is_some()
+        {
+            self.render_infos.push(render_info);
+        }
+        self.visual_layers.push(match def {
+            MapLayerPhysics::Arbitrary(layer) => {
+                MapVisualPhysicsLayer::Arbitrary(MapLayerArbitraryPhysicsSkeleton {
+                    buf: layer,
+                    user: visuals,
+                })
+            }
+            MapLayerPhysics::Game(layer) => {
+                MapVisualPhysicsLayer::Game(MapLayerTilePhysicsBaseSkeleton {
+                    layer,
+                    user: visuals,
+                })
+            }
+            MapLayerPhysics::Front(layer) => {
+                MapVisualPhysicsLayer::Front(MapLayerTilePhysicsBaseSkeleton {
+                    layer,
+                    user: visuals,
+                })
+            }
+            MapLayerPhysics::Tele(layer) => {
+                MapVisualPhysicsLayer::Tele(MapLayerTelePhysicsSkeleton {
+                    layer,
+                    user: visuals,
+                })
+            }
+            MapLayerPhysics::Speedup(layer) => {
+                MapVisualPhysicsLayer::Speedup(MapLayerTilePhysicsBaseSkeleton {
+                    layer,
+                    user: visuals,
+                })
+            }
+            MapLayerPhysics::Switch(layer) => {
+                MapVisualPhysicsLayer::Switch(MapLayerSwitchPhysicsSkeleton {
+                    layer,
+                    user: visuals,
+                })
+            }
+            MapLayerPhysics::Tune(layer) => {
+                MapVisualPhysicsLayer::Tune(MapLayerTunePhysicsSkeleton {
+                    layer,
+                    user: visuals,
+                })
+            }
+        });
+        true
+    }
+}
+
+/// The design groups of the background or the foreground
+/// that still wait for their upload to finish.
+struct MapBufferedGroupsUpload {
+    groups: std::vec::IntoIter<MapGroup>,
+    /// The remaining layers of the last group in `visual_groups`
+    layers: std::vec::IntoIter<MapLayer>,
+    tile_layer_uploads: std::vec::IntoIter<MapBufferTileLayer>,
+    quad_layer_uploads: std::vec::IntoIter<ClientMapBufferQuadLayer>,
+
+    visual_groups: Vec<MapVisualGroup>,
+    tile_render_infos: Vec<MapRenderInfo>,
+    quad_render_infos: Vec<MapRenderInfo>,
+    sound: Vec<MapSoundProcessInfo>,
+
+    is_background: bool,
+    missing_layers: Vec<MapMissingLayer>,
+}
+
+impl MapBufferedGroupsUpload {
+    fn new(
+        groups: Vec<MapGroup>,
+        tile_layer_uploads: Vec<MapBufferTileLayer>,
+        quad_layer_uploads: Vec<ClientMapBufferQuadLayer>,
+        is_background: bool,
+    ) -> Self {
+        Self {
+            visual_groups: Vec::with_capacity(groups.len()),
+            tile_render_infos: Vec::with_capacity(tile_layer_uploads.len()),
+            quad_render_infos: Vec::with_capacity(quad_layer_uploads.len()),
+            sound: Default::default(),
+            is_background,
+            missing_layers: Default::default(),
+            groups: groups.into_iter(),
+            layers: Vec::new().into_iter(),
+            tile_layer_uploads: tile_layer_uploads.into_iter(),
+            quad_layer_uploads: quad_layer_uploads.into_iter(),
+        }
+    }
+
+    fn layer_count(groups: &[MapGroup]) -> usize {
+        groups.iter().map(|group| group.layers.len()).sum()
+    }
+
+    fn location(&self, group_index: usize, layer_index: usize) -> MapLayerLocation {
+        if self.is_background {
+            MapLayerLocation::Background {
+                group_index,
+                layer_index,
+            }
+        } else {
+            MapLayerLocation::Foreground {
+                group_index,
+                layer_index,
+            }
+        }
+    }
+
+    fn add_missing_layer(&mut self, group_index: usize, layer_index: usize, err: LayerUploadError) {
+        self.missing_layers.push(MapMissingLayer {
+            location: self.location(group_index, layer_index),
+            err,
+        });
+    }
+
+    /// Returns `false` if there was no layer left to upload.
+    fn upload_next_layer(
+        &mut self,
+        shader_storage_handle: &GraphicsShaderStorageHandle,
+        buffer_object_handle: &GraphicsBufferObjectHandle,
+        backend_handle: &GraphicsBackendHandle,
+        reuse: &mut MapBufferedLayerReuse,
+        verification: &mut MapUploadVerification,
+    ) -> bool {
+        let def = loop {
+            if let Some(def) = self.layers.next() {
+                break def;
+            }
+            let Some(group) = self.groups.next() else {
+                return false;
+            };
+            self.visual_groups.push(MapVisualGroup {
+                attr: group.attr,
+                layers: Vec::with_capacity(group.layers.len()),
+                name: group.name,
+                user: (),
+            });
+            self.layers = group.layers.into_iter();
+        };
+        let g = self.visual_groups.len() - 1;
+        let l = self.visual_groups[g].layers.len();
+
+        let layer = match def {
+            MapLayer::Abritrary(def) => {
+                MapVisualLayer::Abritrary(MapVisualLayerArbitrary { buf: def, user: () })
+            }
+            MapLayer::Tile(def) => {
+                let mut upload_data = self
+                    .tile_layer_uploads
+                    .next()
+                    .expect("the upload counts are validated by ClientMapBufferedBuilder::new");
+                let render_info = upload_data.render_info;
+
+                let visuals = match reuse.take_tile_layer(&def) {
+                    Some(visuals) => visuals,
+                    None => {
+                        if let Some(err) = upload_data.upload_err.take() {
+                            self.add_missing_layer(g, l, err);
+                        }
+                        let samples = verification.sample_tile_layer(&upload_data);
+                        let visuals = ClientMapBuffered::finish_upload_tile_layer(
+                            shader_storage_handle,
+                            buffer_object_handle,
+                            backend_handle,
+                            upload_data,
+                        );
+                        verification.add_tile_layer(self.location(g, l), &visuals, samples);
+                        visuals
+                    }
+                };
+
+                if visuals.base.obj.buffer_object.is_some()
+                    || visuals.base.obj.shader_storage.is_some()
+                {
+                    self.tile_render_infos.push(render_info);
+                }
+
+                MapVisualLayer::Tile(MapVisualLayerTile {
+                    layer: def,
+                    user: visuals,
+                })
+            }
+            MapLayer::Quad(def) => {
+                let mut upload_data = self
+                    .quad_layer_uploads
+                    .next()
+                    .expect("the upload counts are validated by ClientMapBufferedBuilder::new");
+                let render_info = upload_data.render_info;
+                let visuals = match reuse.take_quad_layer(&def) {
+                    Some(visuals) => visuals,
+                    None => {
+                        if let Some(err) = upload_data.upload_err.take() {
+                            self.add_missing_layer(g, l, err);
+                        }
+                        let samples = verification.sample(upload_data.mem.as_ref());
+                        let instanced_samples =
+                            verification.sample(upload_data.instanced_mem.as_ref());
+                        let visuals = ClientMapBuffered::finish_upload_quad_layer(
+                            buffer_object_handle,
+                            backend_handle,
+                            upload_data,
+                        );
+                        let location = self.location(g, l);
+                        verification.add(location, visuals.buffer_object_index.as_ref(), samples);
+                        verification.add(
+                            location,
+                            visuals.instanced_buffer_object_index.as_ref(),
+                            instanced_samples,
+                        );
+                        visuals
+                    }
+                };
+
+                if visuals.buffer_object_index.is_some() {
+                    self.quad_render_infos.push(render_info);
+                }
+
+                MapVisualLayer::Quad(MapVisualLayerQuad {
+                    layer: def,
+                    user: visuals,
+                })
+            }
+            MapLayer::Sound(def) => {
+                self.sound.push(MapSoundProcessInfo {
+                    group_index: g,
+                    layer_index: l,
+                });
+
+                MapVisualLayer::Sound(MapVisualLayerSound {
+                    layer: def,
+                    user: SoundLayerSounds::default(),
+                })
+            }
+        };
+        self.visual_groups[g].layers.push(layer);
+        true
+    }
+
+    /// The tile & quad render layers in the order of their groups & layers.
+    fn render_layers(&mut self) -> Vec<MapRenderLayer> {
+        let mut render_layers = [
+            std::mem::take(&mut self.tile_render_infos)
+                .into_iter()
+                .map(MapRenderLayer::Tile)
+                .collect::<Vec<MapRenderLayer>>(),
+            std::mem::take(&mut self.quad_render_infos)
+                .into_iter()
+                .map(MapRenderLayer::Quad)
+                .collect::<Vec<MapRenderLayer>>(),
+        ]
+        .concat();
+        render_layers.sort_by(|a1, a2| {
+            let a1 = a1.get_render_info();
+            let a2 = a2.get_render_info();
+            let a1 = a1.group_index as u128 * u64::MAX as u128 + a1.layer_index as u128;
+            let a2 = a2.group_index as u128 * u64::MAX as u128 + a2.layer_index as u128;
+            a1.cmp(&a2)
+        });
+        render_layers
+    }
+}
+
+/// Finishes the upload of the layers of a [`ClientMapBufferUploadData`]
+/// a few layers at a time, so that e.g. a loading screen can keep
+/// rendering while a big map is uploaded.
+///
+/// The physics layers are uploaded first, then the background and
+/// finally the foreground layers, just like [`ClientMapBuffered::new`] does.
+pub struct ClientMapBufferedBuilder {
+    backend_handle: GraphicsBackendHandle,
+    shader_storage_handle: GraphicsShaderStorageHandle,
+    buffer_object_handle: GraphicsBufferObjectHandle,
+
+    physics: MapBufferedPhysicsUpload,
+    background: MapBufferedGroupsUpload,
+    foreground: MapBufferedGroupsUpload,
+    /// The map without the layers & groups that are moved into the uploads
+    map: Map,
+    reuse: MapBufferedLayerReuse,
+    verification: MapUploadVerification,
+
+    progress: ClientMapBufferedUploadProgress,
+}
+
+impl ClientMapBufferedBuilder {
+    /// Fails if the layers of the map do not match the uploads,
+    /// see [`ClientMapBufferUploadData::validate`].
+    pub fn new(
+        backend_handle: &GraphicsBackendHandle,
+        shader_storage_handle: &GraphicsShaderStorageHandle,
+        buffer_object_handle: &GraphicsBufferObjectHandle,
+        upload_data: ClientMapBufferUploadData,
+    ) -> Result<Self, MapUploadMismatch> {
+        upload_data.validate()?;
+
+        // inform the backend only once about the indices the largest layer requires,
+        // else the index buffer is recreated for every layer that is larger
+        // than the ones before.
+        let quad_count_for_indices = upload_data.max_quad_count_for_indices();
+        if quad_count_for_indices > 0 {
+            backend_handle.indices_for_quads_required_notify(quad_count_for_indices);
+        }
+
+        let ClientMapBufferUploadData {
+            bg_tile_layer_uploads,
+            fg_tile_layer_uploads,
+            physics_tile_layer_uploads,
+            bg_quad_layer_uploads,
+            fg_quad_layer_uploads,
+            mut map,
+        } = upload_data;
+
+        let physics_layers = std::mem::take(&mut map.groups.physics.layers);
+        let background = std::mem::take(&mut map.groups.background);
+        let foreground = std::mem::take(&mut map.groups.foreground);
+
+        let total_layers = physics_layers.len()
+            + MapBufferedGroupsUpload::layer_count(&background)
+            + MapBufferedGroupsUpload::layer_count(&foreground);
+
+        Ok(Self {
+            backend_handle: backend_handle.clone(),
+            shader_storage_handle: shader_storage_handle.clone(),
+            buffer_object_handle: buffer_object_handle.clone(),
+
+            physics: MapBufferedPhysicsUpload::new(physics_layers, physics_tile_layer_uploads),
+            background: MapBufferedGroupsUpload::new(
+                background,
+                bg_tile_layer_uploads,
+                bg_quad_layer_uploads,
+                true,
+            ),
+            foreground: MapBufferedGroupsUpload::new(
+                foreground,
+                fg_tile_layer_uploads,
+                fg_quad_layer_uploads,
+                false,
+            ),
+            map,
+            reuse: Default::default(),
+            verification: Default::default(),
+
+            progress: ClientMapBufferedUploadProgress {
+                uploaded_layers: 0,
+                total_layers,
+                reused_layers: 0,
+            },
+        })
+    }
+
+    /// Layers whose tiles or quads did not change compared to the given,
+    /// previously loaded, map share its buffers instead of uploading them again.
+    /// Useful when reloading a map that is mostly unchanged, e.g. after an editor save.
+    ///
+    /// Must be called before any layer is uploaded.
+    pub fn reuse_layers_of(mut self, map: &ClientMapBuffered) -> Self {
+        debug_assert!(self.progress.uploaded_layers == 0);
+        self.reuse = MapBufferedLayerReuse::new(map);
+        self
+    }
+
+    /// Samples the source of every uploaded buffer object & shader storage, so that
+    /// [`ClientMapBuffered::verify_uploads`] can detect buffers whose content
+    /// differs from it. Only has an effect in debug builds.
+    ///
+    /// Must be called before any layer is uploaded.
+    pub fn verify_uploads(mut self) -> Self {
+        debug_assert!(self.progress.uploaded_layers == 0);
+        self.verification = MapUploadVerification::new(true);
+        self
+    }
+
+    /// Finishes the upload of up to `count` layers.
+    pub fn upload_layers(&mut self, count: usize) -> ClientMapBufferedUploadProgress {
+        for _ in 0..count {
+            let uploaded = self.physics.upload_next_layer(
+                &self.shader_storage_handle,
+                &self.buffer_object_handle,
+                &self.backend_handle,
+                &self.map.groups.physics.attr,
+                &mut self.reuse,
+                &mut self.verification,
+            ) || self.background.upload_next_layer(
+                &self.shader_storage_handle,
+                &self.buffer_object_handle,
+                &self.backend_handle,
+                &mut self.reuse,
+                &mut self.verification,
+            ) || self.foreground.upload_next_layer(
+                &self.shader_storage_handle,
+                &self.buffer_object_handle,
+                &self.backend_handle,
+                &mut self.reuse,
+                &mut self.verification,
+            );
+            if !uploaded {
+                break;
+            }
+            self.progress.uploaded_layers += 1;
+        }
+        self.progress.reused_layers = self.reuse.reused_layers;
+        self.progress
+    }
+
+    pub fn progress(&self) -> ClientMapBufferedUploadProgress {
+        self.progress
+    }
+
+    /// Uploads all remaining layers and builds the buffered map.
+    pub fn finish(
+        mut self,
+        images: Vec<TextureContainer>,
+        images_2d_array: Vec<TextureContainer2dArray>,
+        sound_scene: SceneObject,
+        sound_listener: SoundListener,
+        sound_objects: Vec<SoundObject>,
+    ) -> ClientMapBuffered {
+        self.upload_layers(usize::MAX);
+
+        let background_render_layers = self.background.render_layers();
+        let foreground_render_layers = self.foreground.render_layers();
+        let mut physics_render_layers = self.physics.render_infos;
+        physics_render_layers.sort_by(|a1, a2| {
+            let a1 = a1.layer_index as u128;
+            let a2 = a2.layer_index as u128;
+            a1.cmp(&a2)
+        });
+
+        let missing_layers = [
+            self.physics.missing_layers,
+            self.background.missing_layers,
+            self.foreground.missing_layers,
+        ]
+        .concat();
+
+        let Map {
+            resources,
+            groups,
+            animations,
+            config,
+            meta,
+        } = self.map;
+
+        ClientMapBuffered {
+            map_visual: MapVisual {
+                user: MapVisualProps {
+                    sound_scene,
+                    global_listener: sound_listener,
+                },
+                groups: MapVisualGroups {
+                    physics: MapVisualPhysicsGroup {
+                        attr: groups.physics.attr,
+                        layers: self.physics.visual_layers,
                         user: (),
                     },
-                    background: collect_groups(
-                        shader_storage_handle,
-                        buffer_object_handle,
-                        backend_handle,
-                        upload_data.map.groups.background,
-                        bg_tile_layer_uploads,
-                        bg_quad_layer_uploads,
-                        &mut bg_tile_render_infos,
-                        &mut bg_quad_render_infos,
-                        &mut sound.background_sound_layers,
-                    ),
-                    foreground: collect_groups(
-                        shader_storage_handle,
-                        buffer_object_handle,
-                        backend_handle,
-                        upload_data.map.groups.foreground,
-                        fg_tile_layer_uploads,
-                        fg_quad_layer_uploads,
-                        &mut fg_tile_render_infos,
-                        &mut fg_quad_render_infos,
-                        &mut sound.foreground_sound_layers,
-                    ),
+                    background: self.background.visual_groups,
+                    foreground: self.foreground.visual_groups,
                     user: (),
                 },
                 resources: MapVisualResources {
-                    images: upload_data
-                        .map
-                        .resources
+                    images: resources
                         .images
                         .into_iter()
                         .zip(images)
                         .map(|(def, user)| MapVisualImage { def, user })
                         .collect(),
-                    image_arrays: upload_data
-                        .map
-                        .resources
+                    image_arrays: resources
                         .image_arrays
                         .into_iter()
                         .zip(images_2d_array)
                         .map(|(def, user)| MapVisualImage2dArray { def, user })
                         .collect(),
-                    sounds: upload_data
-                        .map
-                        .resources
+                    sounds: resources
                         .sounds
                         .into_iter()
                         .zip(sound_objects)
@@ -733,23 +1878,17 @@ impl ClientMapBuffered {
                     user: (),
                 },
                 animations: MapVisualAnimations {
-                    pos: upload_data
-                        .map
-                        .animations
+                    pos: animations
                         .pos
                         .into_iter()
                         .map(|def| MapVisualPosAnimation { def, user: () })
                         .collect(),
-                    color: upload_data
-                        .map
-                        .animations
+                    color: animations
                         .color
                         .into_iter()
                         .map(|def| MapVisualColorAnimation { def, user: () })
                         .collect(),
-                    sound: upload_data
-                        .map
-                        .animations
+                    sound: animations
                         .sound
                         .into_iter()
                         .map(|def| MapVisualSoundAnimation { def, user: () })
@@ -758,65 +1897,136 @@ impl ClientMapBuffered {
                     user: (),
                 },
                 config: MapVisualConfig {
-                    def: upload_data.map.config,
+                    def: config,
                     user: (),
                 },
                 meta: MapVisualMetadata {
-                    def: upload_data.map.meta,
+                    def: meta,
                     user: (),
                 },
             },
-            render: Default::default(),
-            sound,
-        };
+            render: ClientMapBufferedRenderProcess {
+                background_render_layers,
+                foreground_render_layers,
+                physics_render_layers,
+            },
+            sound: ClientMapBufferedSoundProcess {
+                background_sound_layers: self.background.sound,
+                foreground_sound_layers: self.foreground.sound,
+            },
+            missing_layers,
+            upload_verification: self.verification,
+        }
+    }
+}
 
-        let mut background_render_layers = [
-            bg_tile_render_infos
-                .into_iter()
-                .map(MapRenderLayer::Tile)
-                .collect::<Vec<MapRenderLayer>>(),
-            bg_quad_render_infos
-                .into_iter()
-                .map(MapRenderLayer::Quad)
-                .collect::<Vec<MapRenderLayer>>(),
-        ]
-        .concat();
-        background_render_layers.sort_by(|a1, a2| {
-            let a1 = a1.get_render_info();
-            let a2 = a2.get_render_info();
-            let a1 = a1.group_index as u128 * u64::MAX as u128 + a1.layer_index as u128;
-            let a2 = a2.group_index as u128 * u64::MAX as u128 + a2.layer_index as u128;
-            a1.cmp(&a2)
-        });
-        let mut foreground_render_layers = [
-            fg_tile_render_infos
-                .into_iter()
-                .map(MapRenderLayer::Tile)
-                .collect::<Vec<MapRenderLayer>>(),
-            fg_quad_render_infos
-                .into_iter()
-                .map(MapRenderLayer::Quad)
-                .collect::<Vec<MapRenderLayer>>(),
-        ]
-        .concat();
-        foreground_render_layers.sort_by(|a1, a2| {
-            let a1 = a1.get_render_info();
-            let a2 = a2.get_render_info();
-            let a1 = a1.group_index as u128 * u64::MAX as u128 + a1.layer_index as u128;
-            let a2 = a2.group_index as u128 * u64::MAX as u128 + a2.layer_index as u128;
-            a1.cmp(&a2)
-        });
-        let mut physics_render_layers = physics_tile_render_infos;
-        physics_render_layers.sort_by(|a1, a2| {
-            let a1 = a1.layer_index as u128;
-            let a2 = a2.layer_index as u128;
-            a1.cmp(&a2)
-        });
-        res.render.background_render_layers = background_render_layers;
-        res.render.foreground_render_layers = foreground_render_layers;
-        res.render.physics_render_layers = physics_render_layers;
+impl ClientMapBuffered {
+    /// Compares the buffers sampled by [`ClientMapBufferedBuilder::verify_uploads`]
+    /// against their source and logs every mismatch.
+    ///
+    /// Must be called after the backend ran the upload commands,
+    /// every sample is only verified once.
+    pub fn verify_uploads(&mut self) -> Vec<BufferUploadMismatch> {
+        self.upload_verification.verify()
+    }
 
-        res
+    /// The background render layers together with the visual layers they render.
+    pub fn iter_background_layers(
+        &self,
+    ) -> impl Iterator<Item = (&MapRenderLayer, &MapVisualLayer)> {
+        resolve_render_layers(
+            &self.render.background_render_layers,
+            &self.map_visual.groups.background,
+        )
+    }
+
+    /// The foreground render layers together with the visual layers they render.
+    pub fn iter_foreground_layers(
+        &self,
+    ) -> impl Iterator<Item = (&MapRenderLayer, &MapVisualLayer)> {
+        resolve_render_layers(
+            &self.render.foreground_render_layers,
+            &self.map_visual.groups.foreground,
+        )
+    }
+
+    /// Indices into the background render layers, that are not clipped away
+    /// for the view rect `(tl_x, tl_y, br_x, br_y)` of the camera.
+    /// See [`group_clip_intersects_view`].
+    pub fn visible_background_layers(&self, view: (f32, f32, f32, f32)) -> Vec<usize> {
+        visible_render_layers(
+            &self.render.background_render_layers,
+            &self.map_visual.groups.background,
+            view,
+        )
+    }
+
+    /// Indices into the foreground render layers, that are not clipped away
+    /// for the view rect `(tl_x, tl_y, br_x, br_y)` of the camera.
+    /// See [`group_clip_intersects_view`].
+    pub fn visible_foreground_layers(&self, view: (f32, f32, f32, f32)) -> Vec<usize> {
+        visible_render_layers(
+            &self.render.foreground_render_layers,
+            &self.map_visual.groups.foreground,
+            view,
+        )
+    }
+
+    /// The physics render layers together with the visual layers they render.
+    pub fn iter_physics_layers(
+        &self,
+    ) -> impl Iterator<Item = (&MapPhysicsRenderInfo, &MapVisualPhysicsLayer)> {
+        self.render
+            .physics_render_layers
+            .iter()
+            .filter_map(|render_info| {
+                render_info
+                    .resolve(&self.map_visual.groups.physics.layers)
+                    .map(|layer| (render_info, layer))
+            })
+    }
+
+    /// The width and height of the physics layers in tiles.
+    pub fn physics_size(&self) -> (u16, u16) {
+        let attr = &self.map_visual.groups.physics.attr;
+        (attr.width.get(), attr.height.get())
+    }
+
+    /// The kinds of the physics layers, in the order they are stored in the map.
+    pub fn physics_layer_kinds(&self) -> Vec<PhysicsLayerKind> {
+        self.map_visual
+            .groups
+            .physics
+            .layers
+            .iter()
+            .map(PhysicsLayerKind::from)
+            .collect()
+    }
+
+    pub fn new(
+        backend_handle: &GraphicsBackendHandle,
+        shader_storage_handle: &GraphicsShaderStorageHandle,
+        buffer_object_handle: &GraphicsBufferObjectHandle,
+        upload_data: ClientMapBufferUploadData,
+        images: Vec<TextureContainer>,
+        images_2d_array: Vec<TextureContainer2dArray>,
+        sound_scene: SceneObject,
+        sound_listener: SoundListener,
+        sound_objects: Vec<SoundObject>,
+    ) -> Result<Self, MapUploadMismatch> {
+        Ok(ClientMapBufferedBuilder::new(
+            backend_handle,
+            shader_storage_handle,
+            buffer_object_handle,
+            upload_data,
+        )?
+        .finish(
+            images,
+            images_2d_array,
+            sound_scene,
+            sound_listener,
+            sound_objects,
+        ))
     }
 
     pub fn finish_upload_tile_layer(
@@ -907,37 +2117,33 @@ impl ClientMapBuffered {
     ) -> QuadLayerVisuals {
         let ClientMapBufferQuadLayer {
             mem: raw_data,
+            instanced_mem,
             quad_count_for_indices,
             extra:
                 QuadVisualExtra {
                     draw_ranges,
                     pos_anims,
                     color_anims,
+                    instance_offsets,
                 },
             ..
         } = upload_data;
-        if raw_data
-            .as_ref()
-            .is_some_and(|raw_data| !raw_data.as_slice().is_empty())
-        {
+        let raw_data = raw_data.filter(|raw_data| !raw_data.as_slice().is_empty());
+        let instanced_mem = instanced_mem.filter(|mem| !mem.as_slice().is_empty());
+        if raw_data.is_some() || instanced_mem.is_some() {
             // and finally inform the backend how many indices are required
             backend_handle.indices_for_quads_required_notify(quad_count_for_indices);
-            // create the buffer object
-            QuadLayerVisuals {
-                buffer_object_index: Some(
-                    buffer_object_handle.create_buffer_object(raw_data.unwrap()),
-                ),
-                draw_ranges,
-                pos_anims,
-                color_anims,
-            }
-        } else {
-            QuadLayerVisuals {
-                buffer_object_index: None,
-                draw_ranges,
-                pos_anims,
-                color_anims,
-            }
+        }
+        // create the buffer objects
+        QuadLayerVisuals {
+            buffer_object_index: raw_data
+                .map(|raw_data| buffer_object_handle.create_buffer_object(raw_data)),
+            draw_ranges,
+            pos_anims,
+            color_anims,
+            instanced_buffer_object_index: instanced_mem
+                .map(|mem| buffer_object_handle.create_buffer_object(mem)),
+            instance_offsets,
         }
     }
 
@@ -953,6 +2159,7 @@ impl ClientMapBuffered {
             tile_flag,
             render_info,
             overlays,
+            ..
         } = upload_data;
         let layer_visuals = Self::finish_upload_tile_layer(
             shader_storage_handle,
@@ -966,30 +2173,23 @@ impl ClientMapBuffered {
                     group_index: 0,
                     layer_index: render_info.layer_index,
                 },
+                upload_err: None,
             },
         );
 
-        let mut overlay_buffer_objects: Vec<PhysicsTileLayerOverlayVisuals> = Vec::new();
-        for (ty, base) in overlays {
-            let visuals = Self::finish_upload_tile_layer(
-                shader_storage_handle,
-                buffer_object_handle,
-                backend_handle,
-                MapBufferTileLayer {
+        let overlay_buffer_objects: Vec<PhysicsTileLayerOverlayVisuals> = overlays
+            .into_iter()
+            .map(|(ty, base)| {
+                Self::finish_upload_physics_overlay(
+                    shader_storage_handle,
+                    buffer_object_handle,
+                    backend_handle,
+                    render_info.layer_index,
+                    ty,
                     base,
-                    render_info: MapRenderInfo {
-                        group_index: 0,
-                        layer_index: render_info.layer_index,
-                    },
-                    tile_index: None,
-                    tile_flag: None,
-                },
-            );
-            overlay_buffer_objects.push(PhysicsTileLayerOverlayVisuals {
-                ty,
-                visuals: visuals.base,
-            });
-        }
+                )
+            })
+            .collect();
 
         PhysicsTileLayerVisuals {
             base: layer_visuals,
@@ -997,6 +2197,74 @@ impl ClientMapBuffered {
         }
     }
 
+    fn finish_upload_physics_overlay(
+        shader_storage_handle: &GraphicsShaderStorageHandle,
+        buffer_object_handle: &GraphicsBufferObjectHandle,
+        backend_handle: &GraphicsBackendHandle,
+        layer_index: usize,
+        ty: MapRenderTextOverlayType,
+        base: MapBufferTileLayerBase,
+    ) -> PhysicsTileLayerOverlayVisuals {
+        let visuals = Self::finish_upload_tile_layer(
+            shader_storage_handle,
+            buffer_object_handle,
+            backend_handle,
+            MapBufferTileLayer {
+                base,
+                render_info: MapRenderInfo {
+                    group_index: 0,
+                    layer_index,
+                },
+                tile_index: None,
+                tile_flag: None,
+                upload_err: None,
+            },
+        );
+        PhysicsTileLayerOverlayVisuals {
+            ty,
+            visuals: visuals.base,
+        }
+    }
+
+    /// Allocates the memory for a layer upload.
+    /// Fails if the graphics implementation returned less memory than requested.
+    fn alloc_layer_mem(
+        graphics_mt: &GraphicsMultiThreaded,
+        alloc_type: GraphicsMemoryAllocationType,
+        required_size: usize,
+    ) -> Result<GraphicsBackendMemory, LayerUploadError> {
+        let mem = graphics_mt.mem_alloc(alloc_type);
+        if mem.as_slice().len() < required_size {
+            return Err(LayerUploadError::AllocationFailed { required_size });
+        }
+        Ok(mem)
+    }
+
+    /// Flushes all buffers of a layer at once, which only synchronizes
+    /// with the graphics implementation once per layer.
+    ///
+    /// The cheap flush only makes the memory visible to the graphics implementation,
+    /// the real upload might be deferred until the buffers are first used.
+    /// The expensive flush blocks the uploading thread until the buffers are
+    /// GPU-resident, which is worth it for large static geometry that must be ready
+    /// on its first use, but slows down loading many layers, e.g. a whole map.
+    fn flush_layer_mem<const N: usize>(
+        graphics_mt: &GraphicsMultiThreaded,
+        mems: [Option<&mut GraphicsBackendMemory>; N],
+        do_expensive_flushing: bool,
+    ) {
+        let mut mems: Vec<&mut GraphicsBackendMemory> = mems.into_iter().flatten().collect();
+        if mems.is_empty() {
+            return;
+        }
+        if let Err(err) = graphics_mt.try_flush_mem_batch(&mut mems, do_expensive_flushing) {
+            // Ignore the error, but log it.
+            log::debug!("err while flushing memory: {err}");
+        }
+    }
+
+    /// Uploads a single buffer of a tile layer,
+    /// [`TileLayerUploadOptions::create_tile_index_flag`] is up to the caller.
     pub fn upload_tile_layer_buffer(
         layer: (
             NonZeroU16MinusOne,
@@ -1006,9 +2274,14 @@ impl ClientMapBuffered {
         ),
         is_speedup_layer: bool,
         is_game_layer: bool,
-        ignore_tile_index_and_is_textured_check: bool,
         graphics_mt: &GraphicsMultiThreaded,
-    ) -> Option<MapBufferTileLayerBase> {
+        options: TileLayerUploadOptions,
+    ) -> Result<MapBufferTileLayerBase, LayerUploadError> {
+        let TileLayerUploadOptions {
+            ignore_tile_index_and_is_textured_check,
+            do_expensive_flushing,
+            ..
+        } = options;
         let mut visuals = TileLayerVisualsBase::default();
 
         let (width, height, is_textured, tiles) = layer;
@@ -1016,8 +2289,8 @@ impl ClientMapBuffered {
         let width = width.get() as usize;
         let height = height.get() as usize;
 
-        if !visuals.init(width as u32, height as u32) {
-            return None;
+        if !visuals.init(width as u32, height as u32)? {
+            return Err(LayerUploadError::Empty);
         }
         visuals.is_textured = is_textured;
 
@@ -1029,7 +2302,8 @@ impl ClientMapBuffered {
         let mut tmp_border_left_tiles: Vec<GraphicBorderTile> = Vec::new();
         let mut tmp_border_right_tiles: Vec<GraphicBorderTile> = Vec::new();
         let mut tmp_border_bottom_tiles: Vec<GraphicBorderTile> = Vec::new();
-        let mut tmp_border_corners: Vec<GraphicBorderTile> = Vec::new();
+        let mut tmp_border_corners: [Vec<GraphicBorderTile>; BorderTileLayout::CORNER_COUNT] =
+            Default::default();
 
         tmp_tiles.reserve(width * height);
         tmp_border_tiles.reserve(width * 2 + height * 2 + 4);
@@ -1037,7 +2311,6 @@ impl ClientMapBuffered {
         tmp_border_bottom_tiles.reserve(width);
         tmp_border_left_tiles.reserve(height);
         tmp_border_right_tiles.reserve(height);
-        tmp_border_corners.reserve(4);
 
         for (i, (index, flags, angle_rotate)) in tiles.enumerate() {
             let y = i / width;
@@ -1063,11 +2336,8 @@ impl ClientMapBuffered {
             //do the border tiles
             if x == 0 {
                 if y == 0 {
-                    visuals
-                        .corner_top_left
-                        .set_index_buffer_offset_quad(tmp_border_corners.len() as u32);
                     if add_border_tile(
-                        &mut tmp_border_corners,
+                        &mut tmp_border_corners[BorderTileLayout::CORNER_TOP_LEFT],
                         index,
                         flags,
                         0,
@@ -1079,12 +2349,10 @@ impl ClientMapBuffered {
                     ) {
                         visuals.corner_top_left.set_drawable(true);
                     }
-                } else if y == height - 1 {
-                    visuals
-                        .corner_bottom_left
-                        .set_index_buffer_offset_quad(tmp_border_corners.len() as u32);
+                }
+                if y == height - 1 {
                     if add_border_tile(
-                        &mut tmp_border_corners,
+                        &mut tmp_border_corners[BorderTileLayout::CORNER_BOTTOM_LEFT],
                         index,
                         flags,
                         0,
@@ -1112,13 +2380,11 @@ impl ClientMapBuffered {
                 ) {
                     visuals.border_left[y].set_drawable(true);
                 }
-            } else if x == width - 1 {
+            }
+            if x == width - 1 {
                 if y == 0 {
-                    visuals
-                        .corner_top_right
-                        .set_index_buffer_offset_quad(tmp_border_corners.len() as u32);
                     if add_border_tile(
-                        &mut tmp_border_corners,
+                        &mut tmp_border_corners[BorderTileLayout::CORNER_TOP_RIGHT],
                         index,
                         flags,
                         0,
@@ -1130,12 +2396,10 @@ impl ClientMapBuffered {
                     ) {
                         visuals.corner_top_right.set_drawable(true);
                     }
-                } else if y == height - 1 {
-                    visuals
-                        .corner_bottom_right
-                        .set_index_buffer_offset_quad(tmp_border_corners.len() as u32);
+                }
+                if y == height - 1 {
                     if add_border_tile(
-                        &mut tmp_border_corners,
+                        &mut tmp_border_corners[BorderTileLayout::CORNER_BOTTOM_RIGHT],
                         index,
                         flags,
                         0,
@@ -1180,7 +2444,8 @@ impl ClientMapBuffered {
                 ) {
                     visuals.border_top[x].set_drawable(true);
                 }
-            } else if y == height - 1 {
+            }
+            if y == height - 1 {
                 visuals.border_bottom[x]
                     .set_index_buffer_offset_quad(tmp_border_bottom_tiles.len() as u32);
                 if add_border_tile(
@@ -1199,25 +2464,23 @@ impl ClientMapBuffered {
             }
         }
 
-        // add the border corners, then the borders and fix their byte offsets
-        let mut tiles_handled_count = tmp_border_tiles.len();
-        visuals
-            .corner_top_left
-            .add_index_buffer_offset_quad(tiles_handled_count as u32);
-        visuals
-            .corner_top_right
-            .add_index_buffer_offset_quad(tiles_handled_count as u32);
-        visuals
-            .corner_bottom_left
-            .add_index_buffer_offset_quad(tiles_handled_count as u32);
-        visuals
-            .corner_bottom_right
-            .add_index_buffer_offset_quad(tiles_handled_count as u32);
-        // add the Corners to the tiles
-        tmp_border_tiles.append(&mut tmp_border_corners);
+        // add the border corners, then the borders and fix their byte offsets,
+        // this order must match [`BorderTileLayout`]
+        for (corner, corner_tiles) in [
+            &mut visuals.corner_top_left,
+            &mut visuals.corner_top_right,
+            &mut visuals.corner_bottom_left,
+            &mut visuals.corner_bottom_right,
+        ]
+        .into_iter()
+        .zip(tmp_border_corners.iter_mut())
+        {
+            corner.set_index_buffer_offset_quad(tmp_border_tiles.len() as u32);
+            tmp_border_tiles.append(corner_tiles);
+        }
 
         // now the borders
-        tiles_handled_count = tmp_border_tiles.len();
+        let mut tiles_handled_count = tmp_border_tiles.len();
         for i in 0..width {
             visuals.border_top[i].add_index_buffer_offset_quad(tiles_handled_count as u32);
         }
@@ -1240,6 +2503,10 @@ impl ClientMapBuffered {
             visuals.border_right[i].add_index_buffer_offset_quad(tiles_handled_count as u32);
         }
         tmp_border_tiles.append(&mut tmp_border_right_tiles);
+        debug_assert!(
+            !ignore_tile_index_and_is_textured_check
+                || tmp_border_tiles.len() == BorderTileLayout::new(width, height).tile_count()
+        );
 
         // append one kill tile to the gamelayer
         if is_game_layer {
@@ -1262,22 +2529,20 @@ impl ClientMapBuffered {
         }
 
         let tile_size = std::mem::size_of::<GraphicTile>();
-        let border_tile_size = std::mem::size_of::<GraphicsBorderTilePos>() * 4
-            + if is_textured {
-                std::mem::size_of::<GraphicsBorderTileTex>() * 4
-            } else {
-                0
-            };
+        let border_tile_size = border_tile_size(is_textured);
         let tile_upload_data_size = tmp_tiles.len() * tile_size;
         let border_upload_data_size = tmp_border_tiles.len() * border_tile_size;
         if tile_upload_data_size > 0 || border_upload_data_size > 0 {
             let quad_count_for_indices = (tmp_tiles.len().max(tmp_border_tiles.len())) as u64;
 
-            let tile_shader_storage = if tile_upload_data_size > 0 {
-                let mut upload_data_buffer =
-                    graphics_mt.mem_alloc(GraphicsMemoryAllocationType::ShaderStorage {
+            let mut tile_shader_storage = if tile_upload_data_size > 0 {
+                let mut upload_data_buffer = Self::alloc_layer_mem(
+                    graphics_mt,
+                    GraphicsMemoryAllocationType::ShaderStorage {
                         required_size: tile_upload_data_size.try_into().unwrap(),
-                    });
+                    },
+                    tile_upload_data_size,
+                )?;
 
                 let data = upload_data_buffer.as_mut_slice();
                 if !tmp_tiles.is_empty() {
@@ -1292,19 +2557,18 @@ impl ClientMapBuffered {
                     visuals.ignored_tile_index_and_is_textured_check =
                         ignore_tile_index_and_is_textured_check;
                 }
-                if let Err(err) = graphics_mt.try_flush_mem(&mut upload_data_buffer, false) {
-                    // Ignore the error, but log it.
-                    log::debug!("err while flushing memory: {err}");
-                }
                 Some(upload_data_buffer)
             } else {
                 None
             };
-            let border_buffer = if border_upload_data_size > 0 {
-                let mut upload_data_buffer =
-                    graphics_mt.mem_alloc(GraphicsMemoryAllocationType::VertexBuffer {
+            let mut border_buffer = if border_upload_data_size > 0 {
+                let mut upload_data_buffer = Self::alloc_layer_mem(
+                    graphics_mt,
+                    GraphicsMemoryAllocationType::VertexBuffer {
                         required_size: border_upload_data_size.try_into().unwrap(),
-                    });
+                    },
+                    border_upload_data_size,
+                )?;
 
                 let data = upload_data_buffer.as_mut_slice();
                 if !tmp_border_tiles.is_empty() {
@@ -1316,31 +2580,39 @@ impl ClientMapBuffered {
                             tile.copy_into_slice(data, is_textured);
                         });
                 }
-                if let Err(err) = graphics_mt.try_flush_mem(&mut upload_data_buffer, false) {
-                    // Ignore the error, but log it.
-                    log::debug!("err while flushing memory: {err}");
-                }
                 Some(upload_data_buffer)
             } else {
                 None
             };
 
-            Some(MapBufferTileLayerBase {
+            Self::flush_layer_mem(
+                graphics_mt,
+                [tile_shader_storage.as_mut(), border_buffer.as_mut()],
+                do_expensive_flushing,
+            );
+
+            Ok(MapBufferTileLayerBase {
                 mem: border_buffer,
                 shader_storage_mem: tile_shader_storage,
                 quad_count_for_indices,
                 visuals,
             })
         } else {
-            None
+            Err(LayerUploadError::Empty)
         }
     }
 
-    fn fill_tmp_quads_for_upload(quads: &[Quad]) -> Vec<TmpQuadTextured> {
+    /// `first_quad_index` and `layer_index` are only used to report broken quads.
+    fn fill_tmp_quads_for_upload(
+        quads: &[Quad],
+        first_quad_index: usize,
+        layer_index: Option<usize>,
+    ) -> Vec<TmpQuadTextured> {
         let mut tmp_quads_textured: Vec<TmpQuadTextured> = Vec::new();
         tmp_quads_textured.resize(quads.len(), Default::default());
 
         quads.iter().enumerate().for_each(|(i, quad)| {
+            let mut all_finite = true;
             for j in 0..4 {
                 let mut quad_index = j;
                 if j == 2 {
@@ -1349,21 +2621,36 @@ impl ClientMapBuffered {
                     quad_index = 2;
                 }
 
+                let point = &quad.points[quad_index];
+                let center = &quad.points[4];
+                let tex_coord = &quad.tex_coords[quad_index];
+                let color = &quad.colors[quad_index];
                 // ignore the conversion for the position coordinates
-                tmp_quads_textured[i].vertices[j].x = quad.points[quad_index].x.to_num::<f32>();
-                tmp_quads_textured[i].vertices[j].y = quad.points[quad_index].y.to_num::<f32>();
-                tmp_quads_textured[i].vertices[j].center_x = quad.points[4].x.to_num::<f32>();
-                tmp_quads_textured[i].vertices[j].center_y = quad.points[4].y.to_num::<f32>();
-                tmp_quads_textured[i].vertices[j].u = quad.tex_coords[quad_index].x.to_num::<f32>();
-                tmp_quads_textured[i].vertices[j].v = quad.tex_coords[quad_index].y.to_num::<f32>();
-                tmp_quads_textured[i].vertices[j].r =
-                    (quad.colors[quad_index].r().to_num::<f32>() * 255.0) as u8;
-                tmp_quads_textured[i].vertices[j].g =
-                    (quad.colors[quad_index].g().to_num::<f32>() * 255.0) as u8;
-                tmp_quads_textured[i].vertices[j].b =
-                    (quad.colors[quad_index].b().to_num::<f32>() * 255.0) as u8;
-                tmp_quads_textured[i].vertices[j].a =
-                    (quad.colors[quad_index].a().to_num::<f32>() * 255.0) as u8;
+                let (vertex, finite) = TmpQuadVertexTextured::new(
+                    [point.x.to_num::<f32>(), point.y.to_num::<f32>()],
+                    [center.x.to_num::<f32>(), center.y.to_num::<f32>()],
+                    [tex_coord.x.to_num::<f32>(), tex_coord.y.to_num::<f32>()],
+                    [
+                        color.r().to_num::<f32>(),
+                        color.g().to_num::<f32>(),
+                        color.b().to_num::<f32>(),
+                        color.a().to_num::<f32>(),
+                    ],
+                );
+                tmp_quads_textured[i].vertices[j] = vertex;
+                all_finite &= finite;
+            }
+            if !all_finite {
+                let quad_index = first_quad_index + i;
+                match layer_index {
+                    Some(layer_index) => log::warn!(
+                        "quad {quad_index} of layer {layer_index} \
+                        contained non-finite values, they were replaced by 0."
+                    ),
+                    None => log::warn!(
+                        "quad {quad_index} contained non-finite values, they were replaced by 0."
+                    ),
+                }
             }
         });
         tmp_quads_textured
@@ -1454,20 +2741,183 @@ impl ClientMapBuffered {
             draw_ranges: res_ranges,
             pos_anims: pos_anims.into_iter().collect(),
             color_anims: color_anims.into_iter().collect(),
+            instance_offsets: Default::default(),
+        }
+    }
+
+    /// A quad is degenerate if both of its triangles have no area,
+    /// e.g. if all of its points are at the same position.
+    fn is_degenerate_quad(quad: &Quad) -> bool {
+        let [tl, tr, bl, br, _] = quad
+            .points
+            .map(|point| (point.x.to_num::<f64>(), point.y.to_num::<f64>()));
+        let area = |a: (f64, f64), b: (f64, f64), c: (f64, f64)| {
+            (b.0 - a.0) * (c.1 - a.1) - (b.1 - a.1) * (c.0 - a.0)
+        };
+        // same triangles as the index buffer of the backend
+        area(tl, tr, br) == 0.0 && area(tl, br, bl) == 0.0
+    }
+
+    /// Removes all degenerate quads from the draw ranges of `extra`,
+    /// see [`Self::is_degenerate_quad`].
+    ///
+    /// Returns how many quads were removed.
+    fn skip_degenerate_quads(quads: &[Quad], extra: &mut QuadVisualExtra) -> usize {
+        let mut skipped_quads = 0;
+        let mut draw_ranges: Vec<QuadVisualRange> = Vec::with_capacity(extra.draw_ranges.len());
+        for draw_range in std::mem::take(&mut extra.draw_ranges) {
+            let mut start = draw_range.range.start;
+            for i in draw_range.range.clone() {
+                if Self::is_degenerate_quad(&quads[i]) {
+                    if start < i {
+                        draw_ranges.push(QuadVisualRange {
+                            anim: draw_range.anim,
+                            range: start..i,
+                        });
+                    }
+                    start = i + 1;
+                    skipped_quads += 1;
+                }
+            }
+            if start < draw_range.range.end {
+                draw_ranges.push(QuadVisualRange {
+                    anim: draw_range.anim,
+                    range: start..draw_range.range.end,
+                });
+            }
+        }
+        extra.draw_ranges = draw_ranges;
+        skipped_quads
+    }
+
+    /// Splits runs of at least [`QUAD_INSTANCING_MIN_RUN`] quads that only differ
+    /// by their position out of the [`QuadVisualRangeAnim::NoAnim`] ranges of `extra`
+    /// into [`QuadVisualRangeAnim::Instanced`] ranges.
+    ///
+    /// Returns the template quad of each run, relative to the center of the quads.
+    fn instance_repeated_quads(quads: &[Quad], extra: &mut QuadVisualExtra) -> Vec<Quad> {
+        fn relative_to_center(quad: &Quad) -> Quad {
+            let center = quad.points[4];
+            let mut quad = *quad;
+            quad.points.iter_mut().for_each(|point| {
+                point.x = point.x.wrapping_sub(center.x);
+                point.y = point.y.wrapping_sub(center.y);
+            });
+            quad
+        }
+
+        let mut templates: Vec<Quad> = Vec::new();
+        let mut draw_ranges: Vec<QuadVisualRange> = Vec::with_capacity(extra.draw_ranges.len());
+        for draw_range in std::mem::take(&mut extra.draw_ranges) {
+            if draw_range.anim != QuadVisualRangeAnim::NoAnim {
+                draw_ranges.push(draw_range);
+                continue;
+            }
+
+            let range = draw_range.range;
+            let mut not_instanced_start = range.start;
+            let mut i = range.start;
+            while i < range.end {
+                let template = relative_to_center(&quads[i]);
+                let run_end = (i + 1..range.end)
+                    .find(|&j| relative_to_center(&quads[j]) != template)
+                    .unwrap_or(range.end);
+                if run_end - i >= QUAD_INSTANCING_MIN_RUN {
+                    if not_instanced_start < i {
+                        draw_ranges.push(QuadVisualRange {
+                            anim: QuadVisualRangeAnim::NoAnim,
+                            range: not_instanced_start..i,
+                        });
+                    }
+                    draw_ranges.push(QuadVisualRange {
+                        anim: QuadVisualRangeAnim::Instanced {
+                            template: templates.len(),
+                        },
+                        range: i..run_end,
+                    });
+                    extra.instance_offsets.push(
+                        quads[i..run_end]
+                            .iter()
+                            .map(|quad| {
+                                vec2::new(
+                                    quad.points[4].x.to_num::<f32>(),
+                                    quad.points[4].y.to_num::<f32>(),
+                                )
+                            })
+                            .collect(),
+                    );
+                    templates.push(template);
+                    not_instanced_start = run_end;
+                }
+                i = run_end;
+            }
+            if not_instanced_start < range.end {
+                draw_ranges.push(QuadVisualRange {
+                    anim: QuadVisualRangeAnim::NoAnim,
+                    range: not_instanced_start..range.end,
+                });
+            }
         }
+        extra.draw_ranges = draw_ranges;
+        templates
     }
 
+    /// Only quads in non-instanced draw ranges are part of the vertex buffer,
+    /// in the order of their ranges.
     fn upload_quad_layer_buffer(
         attr: &MapLayerQuadsAttrs,
         quads: &[Quad],
         group_index: usize,
         layer_index: usize,
         graphics_mt: &GraphicsMultiThreaded,
-        ignore_is_textured_check: bool,
-    ) -> Option<ClientMapBufferQuadLayer> {
+        options: QuadLayerUploadOptions,
+    ) -> Result<ClientMapBufferQuadLayer, LayerUploadError> {
+        let QuadLayerUploadOptions {
+            ignore_is_textured_check,
+            instance_repeated_quads,
+            skip_degenerate_quads,
+            do_expensive_flushing,
+        } = options;
+        if quads.is_empty() {
+            return Err(LayerUploadError::Empty);
+        }
+
         let is_textured = attr.image.is_some() || ignore_is_textured_check;
 
-        let tmp_quads_textured = Self::fill_tmp_quads_for_upload(quads);
+        let mut extra = Self::quad_visual_ranges(quads);
+        let skipped_quads = if skip_degenerate_quads {
+            Self::skip_degenerate_quads(quads, &mut extra)
+        } else {
+            0
+        };
+        if skipped_quads > 0 {
+            log::debug!(
+                "skipped {skipped_quads} degenerate quads of layer {layer_index} \
+                of group {group_index}"
+            );
+        }
+        if skipped_quads == quads.len() {
+            return Err(LayerUploadError::Empty);
+        }
+        let templates = if instance_repeated_quads {
+            Self::instance_repeated_quads(quads, &mut extra)
+        } else {
+            Vec::new()
+        };
+
+        // instanced quads are not part of the vertex buffer
+        let tmp_quads_textured: Vec<TmpQuadTextured> = extra
+            .draw_ranges
+            .iter()
+            .filter(|draw_range| !matches!(draw_range.anim, QuadVisualRangeAnim::Instanced { .. }))
+            .flat_map(|draw_range| {
+                Self::fill_tmp_quads_for_upload(
+                    &quads[draw_range.range.clone()],
+                    draw_range.range.start,
+                    Some(layer_index),
+                )
+            })
+            .collect();
 
         let upload_data_size = tmp_quads_textured.len() * std::mem::size_of::<TmpQuadTextured>()
             - if is_textured {
@@ -1476,35 +2926,66 @@ impl ClientMapBuffered {
                 tmp_quads_textured.len() * std::mem::size_of::<f32>() * 4 * 2
             };
 
-        if upload_data_size > 0 {
-            let mut upload_data_buffer =
-                graphics_mt.mem_alloc(GraphicsMemoryAllocationType::VertexBuffer {
+        let mut upload_data_buffer = if upload_data_size > 0 {
+            let mut upload_data_buffer = Self::alloc_layer_mem(
+                graphics_mt,
+                GraphicsMemoryAllocationType::VertexBuffer {
                     required_size: upload_data_size.try_into().unwrap(),
-                });
+                },
+                upload_data_size,
+            )?;
 
             let mut off = 0;
             tmp_quads_textured.iter().for_each(|q| {
                 off += q.copy_into_slice(&mut upload_data_buffer.as_mut_slice()[off..], is_textured)
             });
 
-            if let Err(err) = graphics_mt.try_flush_mem(&mut upload_data_buffer, false) {
-                // Ignore the error, but log it.
-                log::debug!("err while flushing memory: {err}");
-            }
+            Some(upload_data_buffer)
+        } else {
+            None
+        };
 
-            let extra = Self::quad_visual_ranges(quads);
-            Some(ClientMapBufferQuadLayer {
-                mem: Some(upload_data_buffer),
-                quad_count_for_indices: quads.len() as u64,
-                render_info: MapRenderInfo {
-                    group_index,
-                    layer_index,
+        let instanced_data_size = templates.len() * std::mem::size_of::<GlVertex>() * 4;
+        let mut instanced_data_buffer = if instanced_data_size > 0 {
+            let mut instanced_data_buffer = Self::alloc_layer_mem(
+                graphics_mt,
+                GraphicsMemoryAllocationType::VertexBuffer {
+                    required_size: instanced_data_size.try_into().unwrap(),
                 },
-                extra,
-            })
+                instanced_data_size,
+            )?;
+
+            let mut off = 0;
+            Self::fill_tmp_quads_for_upload(&templates, 0, Some(layer_index))
+                .iter()
+                .for_each(|q| {
+                    off += q
+                        .copy_into_slice_instanced(&mut instanced_data_buffer.as_mut_slice()[off..])
+                });
+
+            Some(instanced_data_buffer)
         } else {
             None
-        }
+        };
+
+        Self::flush_layer_mem(
+            graphics_mt,
+            [upload_data_buffer.as_mut(), instanced_data_buffer.as_mut()],
+            do_expensive_flushing,
+        );
+
+        Ok(ClientMapBufferQuadLayer {
+            mem: upload_data_buffer,
+            instanced_mem: instanced_data_buffer,
+            quad_count_for_indices: tmp_quads_textured.len().max(templates.len()) as u64,
+            render_info: MapRenderInfo {
+                group_index,
+                layer_index,
+            },
+            skipped_quads,
+            extra,
+            upload_err: None,
+        })
     }
 
     pub fn upload_design_quad_layer(
@@ -1513,21 +2994,28 @@ impl ClientMapBuffered {
         quads: &[Quad],
         group_index: usize,
         layer_index: usize,
-        ignore_is_textured_check: bool,
+        options: QuadLayerUploadOptions,
     ) -> ClientMapBufferQuadLayer {
-        let mut res = ClientMapBufferQuadLayer::default();
-
-        if let Some(data) = Self::upload_quad_layer_buffer(
+        match Self::upload_quad_layer_buffer(
             attr,
             quads,
             group_index,
             layer_index,
             graphics_mt,
-            ignore_is_textured_check,
+            options,
         ) {
-            res = data;
+            Ok(data) => data,
+            Err(LayerUploadError::Empty) => ClientMapBufferQuadLayer::default(),
+            Err(err) => {
+                log::error!(
+                    "could not upload quad layer {layer_index} of group {group_index}: {err}"
+                );
+                ClientMapBufferQuadLayer {
+                    upload_err: Some(err),
+                    ..Default::default()
+                }
+            }
         }
-        res
     }
 
     pub fn upload_design_tile_layer(
@@ -1538,8 +3026,7 @@ impl ClientMapBuffered {
         has_texture: bool,
         group_index: usize,
         layer_index: usize,
-        ignore_tile_index_and_is_textured_check: bool,
-        create_tile_index_flag: bool,
+        options: TileLayerUploadOptions,
     ) -> MapBufferTileLayer {
         let mut res = MapBufferTileLayer {
             render_info: MapRenderInfo {
@@ -1551,28 +3038,41 @@ impl ClientMapBuffered {
 
         let mut tiles_it = tiles.iter().map(|tile| (tile.index, tile.flags, -1));
 
-        if let Some(data) = Self::upload_tile_layer_buffer(
+        let data = match Self::upload_tile_layer_buffer(
             (width, height, has_texture, &mut tiles_it),
             false,
             false,
-            ignore_tile_index_and_is_textured_check,
             graphics_mt,
+            options,
         ) {
+            Ok(data) => Some(data),
+            Err(LayerUploadError::Empty) => None,
+            Err(err) => {
+                log::error!(
+                    "could not upload tile layer {layer_index} of group {group_index}: {err}"
+                );
+                res.upload_err = Some(err);
+                None
+            }
+        };
+        if let Some(data) = data {
             res.base = data;
 
-            if create_tile_index_flag {
+            if options.create_tile_index_flag {
                 // index
                 let mut tiles_it = tiles
                     .iter()
                     .map(|tile| (tile.index, TileFlags::empty(), -1));
 
+                // same size as the base layer, which was uploaded successfully
                 let tile_index = Self::upload_tile_layer_buffer(
                     (width, height, true, &mut tiles_it),
                     false,
                     false,
-                    ignore_tile_index_and_is_textured_check,
                     graphics_mt,
-                );
+                    options,
+                )
+                .ok();
 
                 res.tile_index = tile_index;
 
@@ -1581,13 +3081,15 @@ impl ClientMapBuffered {
                     .iter()
                     .map(|tile| (flag_to_bits(tile.flags), TileFlags::empty(), -1));
 
+                // same size as the base layer, which was uploaded successfully
                 let tile_flag = Self::upload_tile_layer_buffer(
                     (width, height, true, &mut tiles_it),
                     false,
                     false,
-                    ignore_tile_index_and_is_textured_check,
                     graphics_mt,
-                );
+                    options,
+                )
+                .ok();
 
                 res.tile_flag = tile_flag;
             }
@@ -1601,14 +3103,15 @@ impl ClientMapBuffered {
         height: NonZeroU16MinusOne,
         tiles: MapTileLayerPhysicsTilesRef,
         layer_index: usize,
-        ignore_tile_index_check: bool,
-        create_tile_index_flag: bool,
+        options: TileLayerUploadOptions,
     ) -> MapBufferPhysicsTileLayer {
+        let create_tile_index_flag = options.create_tile_index_flag;
         let mut res = MapBufferPhysicsTileLayer::default();
 
         let mut is_switch_layer = false;
         let mut is_tele_layer = false;
         let mut is_speedup_layer = false;
+        let mut is_tune_layer = false;
 
         match tiles {
             MapTileLayerPhysicsTilesRef::Arbitrary(_) => {}
@@ -1623,7 +3126,9 @@ impl ClientMapBuffered {
             MapTileLayerPhysicsTilesRef::Switch(_) => {
                 is_switch_layer = true;
             }
-            MapTileLayerPhysicsTilesRef::Tune(_) => {}
+            MapTileLayerPhysicsTilesRef::Tune(_) => {
+                is_tune_layer = true;
+            }
         }
 
         let mut text_overlay_count = 0;
@@ -1633,6 +3138,8 @@ impl ClientMapBuffered {
             text_overlay_count = 1;
         } else if is_speedup_layer {
             text_overlay_count = 2;
+        } else if is_tune_layer {
+            text_overlay_count = 1;
         }
 
         for cur_text_overlay in
@@ -1797,22 +3304,37 @@ impl ClientMapBuffered {
                                 (flag_to_bits(tile.base.flags), Default::default(), -1)
                             }))
                         } else {
-                            Box::new(
-                                tiles
-                                    .iter()
-                                    .map(|tile| (tile.base.index, tile.base.flags, -1)),
-                            )
+                            if cur_text_overlay == 1 {
+                                text_overlay_type = Some(MapRenderTextOverlayType::Center);
+                            }
+                            Box::new(tiles.iter().map(|tile| {
+                                if cur_text_overlay == 1 {
+                                    (tile.number, TileFlags::empty(), -1)
+                                } else {
+                                    (tile.base.index, tile.base.flags, -1)
+                                }
+                            }))
                         }
                     }
                 };
 
-            if let Some(data) = Self::upload_tile_layer_buffer(
+            let data = match Self::upload_tile_layer_buffer(
                 (width, height, true, &mut tiles),
                 is_speedup_layer,
                 is_game_layer,
-                ignore_tile_index_check,
                 graphics_mt,
+                options,
             ) {
+                Ok(data) => Some(data),
+                Err(LayerUploadError::Empty) => None,
+                Err(err) => {
+                    log::error!("could not upload physics layer {layer_index}: {err}");
+                    res.upload_err = Some(err);
+                    // all overlays share the size of the layer
+                    break;
+                }
+            };
+            if let Some(data) = data {
                 if cur_text_overlay == 0 {
                     res = MapBufferPhysicsTileLayer {
                         base: MapBufferTileLayerBase {
@@ -1825,6 +3347,7 @@ impl ClientMapBuffered {
                         overlays: Vec::new(),
                         tile_index: None,
                         tile_flag: None,
+                        upload_err: None,
                     };
                 } else if !create_tile_index_flag || cur_text_overlay < text_overlay_count + 1 {
                     res.base.quad_count_for_indices = res
@@ -1862,25 +3385,10 @@ impl ClientMapBuffered {
         F: Fn(usize) -> Box<dyn Iterator<Item = (u8, TileFlags, i16)> + 'a> + 'a,
     {
         let size_of_tile = std::mem::size_of::<GraphicTile>();
-        let size_of_border_tile = if is_textured {
-            std::mem::size_of::<GraphicBorderTile>()
-        } else {
-            std::mem::size_of::<GraphicsBorderTilePos>() * 4
-        };
 
         let add_as_speedup = is_speedup_layer;
         let ignore_tile_index_check = true;
 
-        // border tiles after all rows are updated
-        let mut tmp_border_top_tiles: Vec<GraphicBorderTile> = Vec::new();
-        let mut tmp_border_left_tiles: Vec<GraphicBorderTile> = Vec::new();
-        let mut tmp_border_right_tiles: Vec<GraphicBorderTile> = Vec::new();
-        let mut tmp_border_bottom_tiles: Vec<GraphicBorderTile> = Vec::new();
-        let mut tmp_border_corner_top_left: Vec<GraphicBorderTile> = Vec::new();
-        let mut tmp_border_corner_top_right: Vec<GraphicBorderTile> = Vec::new();
-        let mut tmp_border_corner_bottom_left: Vec<GraphicBorderTile> = Vec::new();
-        let mut tmp_border_corner_bottom_right: Vec<GraphicBorderTile> = Vec::new();
-
         let mut tmp_tiles: Vec<GraphicTile> =
             Vec::with_capacity(width.get() as usize * height.get() as usize);
         let mut tile_update_regions: Vec<CommandUpdateShaderStorageRegion> =
@@ -1896,12 +3404,8 @@ impl ClientMapBuffered {
             update_tiles
                 .enumerate()
                 .for_each(|(i, (index, flags, angle_rotate))| {
-                    let y = (i + tile_index_skip) / layer_width.get() as usize;
                     let x = (i + tile_index_skip) % layer_width.get() as usize;
 
-                    let width = layer_width.get() as usize;
-                    let height = layer_height.get() as usize;
-
                     if add_tile(
                         &mut tmp_tiles,
                         index,
@@ -1913,122 +3417,6 @@ impl ClientMapBuffered {
                     ) {
                         // nothing to do
                     }
-                    if x == 0 {
-                        if y == 0 {
-                            if add_border_tile(
-                                &mut tmp_border_corner_top_left,
-                                index,
-                                flags,
-                                0,
-                                0,
-                                add_as_speedup,
-                                angle_rotate,
-                                &ivec2::new(-1, -1),
-                                ignore_tile_index_check,
-                            ) {
-                                // nothing to do
-                            }
-                        } else if y == height - 1
-                            && add_border_tile(
-                                &mut tmp_border_corner_bottom_left,
-                                index,
-                                flags,
-                                0,
-                                0,
-                                add_as_speedup,
-                                angle_rotate,
-                                &ivec2::new(-1, 0),
-                                ignore_tile_index_check,
-                            )
-                        {
-                            // nothing to do
-                        }
-                        if add_border_tile(
-                            &mut tmp_border_left_tiles,
-                            index,
-                            flags,
-                            0,
-                            y as i32,
-                            add_as_speedup,
-                            angle_rotate,
-                            &ivec2::new(-1, 0),
-                            ignore_tile_index_check,
-                        ) {
-                            // nothing to do
-                        }
-                    } else if x == width - 1 {
-                        if y == 0 {
-                            if add_border_tile(
-                                &mut tmp_border_corner_top_right,
-                                index,
-                                flags,
-                                0,
-                                0,
-                                add_as_speedup,
-                                angle_rotate,
-                                &ivec2::new(0, -1),
-                                ignore_tile_index_check,
-                            ) {
-                                // nothing to do
-                            }
-                        } else if y == height - 1
-                            && add_border_tile(
-                                &mut tmp_border_corner_bottom_right,
-                                index,
-                                flags,
-                                0,
-                                0,
-                                add_as_speedup,
-                                angle_rotate,
-                                &ivec2::new(0, 0),
-                                ignore_tile_index_check,
-                            )
-                        {
-                            // nothing to do
-                        }
-                        if add_border_tile(
-                            &mut tmp_border_right_tiles,
-                            index,
-                            flags,
-                            0,
-                            y as i32,
-                            add_as_speedup,
-                            angle_rotate,
-                            &ivec2::new(0, 0),
-                            ignore_tile_index_check,
-                        ) {
-                            // nothing to do
-                        }
-                    }
-                    if y == 0 {
-                        if add_border_tile(
-                            &mut tmp_border_top_tiles,
-                            index,
-                            flags,
-                            x as i32,
-                            0,
-                            add_as_speedup,
-                            angle_rotate,
-                            &ivec2::new(0, -1),
-                            ignore_tile_index_check,
-                        ) {
-                            // nothing to do
-                        }
-                    } else if y == height - 1
-                        && add_border_tile(
-                            &mut tmp_border_bottom_tiles,
-                            index,
-                            flags,
-                            x as i32,
-                            0,
-                            add_as_speedup,
-                            angle_rotate,
-                            &ivec2::new(0, 0),
-                            ignore_tile_index_check,
-                        )
-                    {
-                        // nothing to do
-                    }
                 });
 
             if tmp_tiles.len() > tmp_tiles_len {
@@ -2058,195 +3446,226 @@ impl ClientMapBuffered {
                 .update_shader_storage(upload_data, tile_update_regions);
         }
 
-        // do the corner tiles
-        if !tmp_border_corner_top_left.is_empty() {
-            let mut upload_data: Vec<u8> = Default::default();
-            upload_data.resize(
-                tmp_border_corner_top_left.len() * size_of_border_tile,
-                Default::default(),
-            );
-            let mut off = 0;
-            tmp_border_corner_top_left
-                .iter()
-                .for_each(|tile| off += tile.copy_into_slice(&mut upload_data[off..], is_textured));
+        let (upload_data, update_regions) = Self::border_tile_updates(
+            layer_width,
+            layer_height,
+            x,
+            y,
+            width,
+            height,
+            &get_tiles_it,
+            is_speedup_layer,
+            is_textured,
+        );
+        if !update_regions.is_empty() {
+            buffer_object
+                .as_ref()
+                .unwrap()
+                .update_buffer_object(upload_data, update_regions);
+        }
+    }
 
-            let upload_data_len = upload_data.len();
-            buffer_object.as_ref().unwrap().update_buffer_object(
-                upload_data,
-                [CommandUpdateBufferObjectRegion {
-                    src_offset: 0,
-                    dst_offset: 0,
-                    size: upload_data_len,
-                }]
-                .into(),
-            );
-        }
-        if !tmp_border_corner_top_right.is_empty() {
-            let mut upload_data: Vec<u8> = Default::default();
-            upload_data.resize(
-                tmp_border_corner_top_right.len() * size_of_border_tile,
-                Default::default(),
-            );
-            let mut off = 0;
-            tmp_border_corner_top_right
-                .iter()
-                .for_each(|tile| off += tile.copy_into_slice(&mut upload_data[off..], is_textured));
-
-            let upload_data_len = upload_data.len();
-            buffer_object.as_ref().unwrap().update_buffer_object(
-                upload_data,
-                [CommandUpdateBufferObjectRegion {
-                    src_offset: 0,
-                    dst_offset: size_of_border_tile,
-                    size: upload_data_len,
-                }]
-                .into(),
-            );
-        }
-        if !tmp_border_corner_bottom_left.is_empty() {
-            let mut upload_data: Vec<u8> = Default::default();
-            upload_data.resize(
-                tmp_border_corner_bottom_left.len() * size_of_border_tile,
-                Default::default(),
-            );
-            let mut off = 0;
-            tmp_border_corner_bottom_left
-                .iter()
-                .for_each(|tile| off += tile.copy_into_slice(&mut upload_data[off..], is_textured));
+    /// Collects the border tiles that are affected by an update of the given area
+    /// and returns their data together with the regions inside the border buffer object,
+    /// see [`BorderTileLayout`].
+    ///
+    /// `F` takes the amount of tiles to skip as argument
+    fn border_tile_updates<'a, F>(
+        layer_width: NonZeroU16MinusOne,
+        layer_height: NonZeroU16MinusOne,
+        x: u16,
+        y: u16,
+        width: NonZeroU16MinusOne,
+        height: NonZeroU16MinusOne,
+        get_tiles_it: &F,
+        is_speedup_layer: bool,
+        is_textured: bool,
+    ) -> (Vec<u8>, Vec<CommandUpdateBufferObjectRegion>)
+    where
+        F: Fn(usize) -> Box<dyn Iterator<Item = (u8, TileFlags, i16)> + 'a> + 'a,
+    {
+        let layer_width = layer_width.get() as usize;
+        let layer_height = layer_height.get() as usize;
+        let layout = BorderTileLayout::new(layer_width, layer_height);
+        let size_of_border_tile = border_tile_size(is_textured);
 
-            let upload_data_len = upload_data.len();
-            buffer_object.as_ref().unwrap().update_buffer_object(
-                upload_data,
-                [CommandUpdateBufferObjectRegion {
-                    src_offset: 0,
-                    dst_offset: 2 * size_of_border_tile,
-                    size: upload_data_len,
-                }]
-                .into(),
-            );
-        }
-        if !tmp_border_corner_bottom_right.is_empty() {
-            let mut upload_data: Vec<u8> = Default::default();
-            upload_data.resize(
-                tmp_border_corner_bottom_right.len() * size_of_border_tile,
-                Default::default(),
-            );
-            let mut off = 0;
-            tmp_border_corner_bottom_right
-                .iter()
-                .for_each(|tile| off += tile.copy_into_slice(&mut upload_data[off..], is_textured));
+        let add_as_speedup = is_speedup_layer;
+        let ignore_tile_index_check = true;
 
-            let upload_data_len = upload_data.len();
-            buffer_object.as_ref().unwrap().update_buffer_object(
-                upload_data,
-                [CommandUpdateBufferObjectRegion {
-                    src_offset: 0,
-                    dst_offset: 3 * size_of_border_tile,
-                    size: upload_data_len,
-                }]
-                .into(),
-            );
-        }
+        let mut tmp_border_top_tiles: Vec<GraphicBorderTile> = Vec::new();
+        let mut tmp_border_left_tiles: Vec<GraphicBorderTile> = Vec::new();
+        let mut tmp_border_right_tiles: Vec<GraphicBorderTile> = Vec::new();
+        let mut tmp_border_bottom_tiles: Vec<GraphicBorderTile> = Vec::new();
+        let mut tmp_border_corners: [Vec<GraphicBorderTile>; BorderTileLayout::CORNER_COUNT] =
+            Default::default();
 
-        // now do the border tiles
-        if !tmp_border_top_tiles.is_empty() {
-            let mut upload_data: Vec<u8> = Default::default();
-            upload_data.resize(
-                tmp_border_top_tiles.len() * size_of_border_tile,
-                Default::default(),
-            );
-            let mut off = 0;
-            tmp_border_top_tiles
-                .iter()
-                .for_each(|tile| off += tile.copy_into_slice(&mut upload_data[off..], is_textured));
+        let touches_left = x == 0;
+        let touches_right = x as usize + width.get() as usize == layer_width;
+        for y in y..y + height.get() {
+            let y = y as usize;
+            if y != 0 && y != layer_height - 1 && !touches_left && !touches_right {
+                continue;
+            }
+            let tile_index_skip = y * layer_width + x as usize;
+            let tiles = get_tiles_it(tile_index_skip).take(width.get() as usize);
 
-            let upload_data_len = upload_data.len();
-            buffer_object.as_ref().unwrap().update_buffer_object(
-                upload_data,
-                [CommandUpdateBufferObjectRegion {
-                    src_offset: 0,
-                    dst_offset: (4 + x as usize) * size_of_border_tile,
-                    size: upload_data_len,
-                }]
-                .into(),
-            );
-        }
-        if !tmp_border_bottom_tiles.is_empty() {
-            let mut upload_data: Vec<u8> = Default::default();
-            upload_data.resize(
-                tmp_border_bottom_tiles.len() * size_of_border_tile,
-                Default::default(),
-            );
-            let mut off = 0;
-            tmp_border_bottom_tiles
-                .iter()
-                .for_each(|tile| off += tile.copy_into_slice(&mut upload_data[off..], is_textured));
+            for (i, (index, flags, angle_rotate)) in tiles.enumerate() {
+                let x = x as usize + i;
 
-            let upload_data_len = upload_data.len();
-            buffer_object.as_ref().unwrap().update_buffer_object(
-                upload_data,
-                [CommandUpdateBufferObjectRegion {
-                    src_offset: 0,
-                    dst_offset: (4 + layer_width.get() as usize + x as usize) * size_of_border_tile,
-                    size: upload_data_len,
-                }]
-                .into(),
-            );
+                if x == 0 {
+                    if y == 0 {
+                        add_border_tile(
+                            &mut tmp_border_corners[BorderTileLayout::CORNER_TOP_LEFT],
+                            index,
+                            flags,
+                            0,
+                            0,
+                            add_as_speedup,
+                            angle_rotate,
+                            &ivec2::new(-1, -1),
+                            ignore_tile_index_check,
+                        );
+                    }
+                    if y == layer_height - 1 {
+                        add_border_tile(
+                            &mut tmp_border_corners[BorderTileLayout::CORNER_BOTTOM_LEFT],
+                            index,
+                            flags,
+                            0,
+                            0,
+                            add_as_speedup,
+                            angle_rotate,
+                            &ivec2::new(-1, 0),
+                            ignore_tile_index_check,
+                        );
+                    }
+                    add_border_tile(
+                        &mut tmp_border_left_tiles,
+                        index,
+                        flags,
+                        0,
+                        y as i32,
+                        add_as_speedup,
+                        angle_rotate,
+                        &ivec2::new(-1, 0),
+                        ignore_tile_index_check,
+                    );
+                }
+                if x == layer_width - 1 {
+                    if y == 0 {
+                        add_border_tile(
+                            &mut tmp_border_corners[BorderTileLayout::CORNER_TOP_RIGHT],
+                            index,
+                            flags,
+                            0,
+                            0,
+                            add_as_speedup,
+                            angle_rotate,
+                            &ivec2::new(0, -1),
+                            ignore_tile_index_check,
+                        );
+                    }
+                    if y == layer_height - 1 {
+                        add_border_tile(
+                            &mut tmp_border_corners[BorderTileLayout::CORNER_BOTTOM_RIGHT],
+                            index,
+                            flags,
+                            0,
+                            0,
+                            add_as_speedup,
+                            angle_rotate,
+                            &ivec2::new(0, 0),
+                            ignore_tile_index_check,
+                        );
+                    }
+                    add_border_tile(
+                        &mut tmp_border_right_tiles,
+                        index,
+                        flags,
+                        0,
+                        y as i32,
+                        add_as_speedup,
+                        angle_rotate,
+                        &ivec2::new(0, 0),
+                        ignore_tile_index_check,
+                    );
+                }
+                if y == 0 {
+                    add_border_tile(
+                        &mut tmp_border_top_tiles,
+                        index,
+                        flags,
+                        x as i32,
+                        0,
+                        add_as_speedup,
+                        angle_rotate,
+                        &ivec2::new(0, -1),
+                        ignore_tile_index_check,
+                    );
+                }
+                if y == layer_height - 1 {
+                    add_border_tile(
+                        &mut tmp_border_bottom_tiles,
+                        index,
+                        flags,
+                        x as i32,
+                        0,
+                        add_as_speedup,
+                        angle_rotate,
+                        &ivec2::new(0, 0),
+                        ignore_tile_index_check,
+                    );
+                }
+            }
         }
-        if !tmp_border_left_tiles.is_empty() {
-            let mut upload_data: Vec<u8> = Default::default();
-            upload_data.resize(
-                tmp_border_left_tiles.len() * size_of_border_tile,
-                Default::default(),
-            );
-            let mut off = 0;
-            tmp_border_left_tiles
-                .iter()
-                .for_each(|tile| off += tile.copy_into_slice(&mut upload_data[off..], is_textured));
 
-            let upload_data_len = upload_data.len();
-            buffer_object.as_ref().unwrap().update_buffer_object(
-                upload_data,
-                [CommandUpdateBufferObjectRegion {
-                    src_offset: 0,
-                    dst_offset: (4 + layer_width.get() as usize * 2 + y as usize)
-                        * size_of_border_tile,
-                    size: upload_data_len,
-                }]
-                .into(),
-            );
-        }
-        if !tmp_border_right_tiles.is_empty() {
-            let mut upload_data: Vec<u8> = Default::default();
+        let [corner_top_left, corner_top_right, corner_bottom_left, corner_bottom_right] =
+            tmp_border_corners;
+        let border_updates = [
+            (corner_top_left, BorderTileLayout::CORNER_TOP_LEFT),
+            (corner_top_right, BorderTileLayout::CORNER_TOP_RIGHT),
+            (corner_bottom_left, BorderTileLayout::CORNER_BOTTOM_LEFT),
+            (corner_bottom_right, BorderTileLayout::CORNER_BOTTOM_RIGHT),
+            (tmp_border_top_tiles, layout.top(x as usize)),
+            (tmp_border_bottom_tiles, layout.bottom(x as usize)),
+            (tmp_border_left_tiles, layout.left(y as usize)),
+            (tmp_border_right_tiles, layout.right(y as usize)),
+        ];
+
+        let mut upload_data: Vec<u8> = Vec::new();
+        let mut update_regions: Vec<CommandUpdateBufferObjectRegion> = Vec::new();
+        for (tiles, tile_offset) in border_updates {
+            if tiles.is_empty() {
+                continue;
+            }
+            let src_offset = upload_data.len();
             upload_data.resize(
-                tmp_border_right_tiles.len() * size_of_border_tile,
+                src_offset + tiles.len() * size_of_border_tile,
                 Default::default(),
             );
-            let mut off = 0;
-            tmp_border_right_tiles
+            let mut off = src_offset;
+            tiles
                 .iter()
                 .for_each(|tile| off += tile.copy_into_slice(&mut upload_data[off..], is_textured));
 
-            let upload_data_len = upload_data.len();
-            buffer_object.as_ref().unwrap().update_buffer_object(
-                upload_data,
-                [CommandUpdateBufferObjectRegion {
-                    src_offset: 0,
-                    dst_offset: (4
-                        + layer_width.get() as usize * 2
-                        + layer_height.get() as usize
-                        + y as usize)
-                        * size_of_border_tile,
-                    size: upload_data_len,
-                }]
-                .into(),
-            );
+            update_regions.push(CommandUpdateBufferObjectRegion {
+                src_offset,
+                dst_offset: tile_offset * size_of_border_tile,
+                size: upload_data.len() - src_offset,
+            });
         }
+        (upload_data, update_regions)
     }
 
     /// should only be called on layers that were created with `ignore_tile_index_check`
+    ///
+    /// Text overlays that the layer did not have so far are created from the whole layer.
     pub fn update_physics_layer<L>(
         tp: &Arc<rayon::ThreadPool>,
+        graphics_mt: &GraphicsMultiThreaded,
+        shader_storage_handle: &GraphicsShaderStorageHandle,
+        buffer_object_handle: &GraphicsBufferObjectHandle,
+        backend_handle: &GraphicsBackendHandle,
         group_width: NonZeroU16MinusOne,
         group_height: NonZeroU16MinusOne,
         layer: &mut MapLayerPhysicsSkeleton<L>,
@@ -2260,6 +3679,7 @@ impl ClientMapBuffered {
         let mut is_switch_layer = false;
         let mut is_tele_layer = false;
         let mut is_speedup_layer = false;
+        let mut is_tune_layer = false;
 
         match &layer {
             MapLayerPhysicsSkeleton::Arbitrary(_) => {}
@@ -2274,7 +3694,9 @@ impl ClientMapBuffered {
             MapLayerPhysicsSkeleton::Switch(_) => {
                 is_switch_layer = true;
             }
-            MapLayerPhysicsSkeleton::Tune(_) => {}
+            MapLayerPhysicsSkeleton::Tune(_) => {
+                is_tune_layer = true;
+            }
         }
 
         let mut text_overlay_count = 0;
@@ -2284,6 +3706,57 @@ impl ClientMapBuffered {
             text_overlay_count = 1;
         } else if is_speedup_layer {
             text_overlay_count = 2;
+        } else if is_tune_layer {
+            text_overlay_count = 1;
+        }
+        let overlay_type = |cur_text_overlay: usize| {
+            if is_tele_layer || is_tune_layer {
+                MapRenderTextOverlayType::Center
+            } else if cur_text_overlay == 1 {
+                MapRenderTextOverlayType::Bottom
+            } else {
+                MapRenderTextOverlayType::Top
+            }
+        };
+
+        // overlays are only uploaded if they contain tiles, e.g. a switch layer
+        // without any delays has no delay overlay, so create the missing ones now
+        let has_overlay = |layer: &MapLayerPhysicsSkeleton<L>, ty| {
+            layer
+                .user()
+                .borrow()
+                .overlays
+                .iter()
+                .any(|overlay| overlay.ty == ty)
+        };
+        let mut created_overlays: Vec<MapRenderTextOverlayType> = Vec::new();
+        if (1..=text_overlay_count).any(|i| !has_overlay(layer, overlay_type(i))) {
+            let uploaded = Self::upload_physics_layer(
+                graphics_mt,
+                group_width,
+                group_height,
+                layer.layer_ref().tiles_ref(),
+                0,
+                TileLayerUploadOptions {
+                    ignore_tile_index_and_is_textured_check: true,
+                    ..Default::default()
+                },
+            );
+            for (ty, base) in uploaded.overlays {
+                if has_overlay(layer, ty) {
+                    continue;
+                }
+                let overlay = Self::finish_upload_physics_overlay(
+                    shader_storage_handle,
+                    buffer_object_handle,
+                    backend_handle,
+                    0,
+                    ty,
+                    base,
+                );
+                layer.user_mut().borrow_mut().overlays.push(overlay);
+                created_overlays.push(ty);
+            }
         }
 
         let obj = &layer.user().borrow().base.tile_index_obj;
@@ -2302,11 +3775,31 @@ impl ClientMapBuffered {
                 create_tile_index_flag && cur_text_overlay == text_overlay_count + 1;
             let is_tile_flag_layer =
                 create_tile_index_flag && cur_text_overlay == text_overlay_count + 2;
+            // find the overlays by the type `upload_physics_layer` gave them
+            let overlay_index = if (1..=text_overlay_count).contains(&cur_text_overlay) {
+                let ty = overlay_type(cur_text_overlay);
+                // the created overlays already contain the whole layer
+                if created_overlays.contains(&ty) {
+                    continue;
+                }
+                let Some(overlay_index) = layer
+                    .user()
+                    .borrow()
+                    .overlays
+                    .iter()
+                    .position(|overlay| overlay.ty == ty)
+                else {
+                    continue;
+                };
+                Some(overlay_index)
+            } else {
+                None
+            };
             let (mut buffer_object, mut shader_storage) = if cur_text_overlay == 0 {
                 let obj = &layer.user_mut().borrow().base.base.obj;
                 (obj.buffer_object.clone(), obj.shader_storage.clone())
-            } else if !create_tile_index_flag || cur_text_overlay < text_overlay_count + 1 {
-                let obj = &layer.user_mut().borrow_mut().overlays[cur_text_overlay - 1]
+            } else if let Some(overlay_index) = overlay_index {
+                let obj = &layer.user_mut().borrow_mut().overlays[overlay_index]
                     .visuals
                     .obj;
                 (obj.buffer_object.clone(), obj.shader_storage.clone())
@@ -2471,11 +3964,13 @@ impl ClientMapBuffered {
                                 (flag_to_bits(tile.base.flags), Default::default(), -1)
                             }))
                         } else {
-                            Box::new(
-                                layer.layer.base.tiles[skip..]
-                                    .iter()
-                                    .map(|tile| (tile.base.index, tile.base.flags, -1)),
-                            )
+                            Box::new(layer.layer.base.tiles[skip..].iter().map(|tile| {
+                                if cur_text_overlay == 1 {
+                                    (tile.number, TileFlags::empty(), -1)
+                                } else {
+                                    (tile.base.index, tile.base.flags, -1)
+                                }
+                            }))
                         }
                     }
                 },
@@ -2485,8 +3980,8 @@ impl ClientMapBuffered {
             let (buffer_obj, shader_stor) = if cur_text_overlay == 0 {
                 let obj = &mut layer.user_mut().borrow_mut().base.base.obj;
                 (&mut obj.buffer_object, &mut obj.shader_storage)
-            } else if !create_tile_index_flag || cur_text_overlay < text_overlay_count + 1 {
-                let obj = &mut layer.user_mut().borrow_mut().overlays[cur_text_overlay - 1]
+            } else if let Some(overlay_index) = overlay_index {
+                let obj = &mut layer.user_mut().borrow_mut().overlays[overlay_index]
                     .visuals
                     .obj;
                 (&mut obj.buffer_object, &mut obj.shader_storage)
@@ -2595,8 +4090,11 @@ impl ClientMapBuffered {
         // for quad layers that are update, always assume they are textured
         let is_textured = true;
 
-        let tmp_quads_textured =
-            Self::fill_tmp_quads_for_upload(&layer.layer.quads[update_range.clone()]);
+        let tmp_quads_textured = Self::fill_tmp_quads_for_upload(
+            &layer.layer.quads[update_range.clone()],
+            update_range.start,
+            None,
+        );
 
         let single_quad_size = std::mem::size_of::<TmpQuadTextured>()
             - if is_textured {
@@ -2702,8 +4200,7 @@ impl ClientMapBuffered {
                             layer.attr.image_array.is_some(),
                             group_index,
                             layer_index,
-                            false,
-                            false,
+                            TileLayerUploadOptions::default(),
                         )
                     } else {
                         panic!("this should not happen")
@@ -2731,8 +4228,7 @@ impl ClientMapBuffered {
                             layer.attr.image_array.is_some(),
                             group_index,
                             layer_index,
-                            false,
-                            false,
+                            TileLayerUploadOptions::default(),
                         )
                     } else {
                         panic!("this should not happen")
@@ -2757,8 +4253,12 @@ impl ClientMapBuffered {
                     group.attr.height,
                     layer.as_ref().tiles_ref(),
                     layer_index,
-                    false,
-                    false,
+                    TileLayerUploadOptions {
+                        // the physics layers span the whole map and are rendered
+                        // right away, so make them GPU-resident while loading
+                        do_expensive_flushing: true,
+                        ..Default::default()
+                    },
                 )
             })
             .collect();
@@ -2775,7 +4275,10 @@ impl ClientMapBuffered {
                         &layer.quads,
                         group_index,
                         layer_index,
-                        false,
+                        QuadLayerUploadOptions {
+                            instance_repeated_quads: true,
+                            ..Default::default()
+                        },
                     )
                 } else {
                     panic!("this should not happen.")
@@ -2796,7 +4299,10 @@ impl ClientMapBuffered {
                         &layer.quads,
                         group_index,
                         layer_index,
-                        false,
+                        QuadLayerUploadOptions {
+                            instance_repeated_quads: true,
+                            ..Default::default()
+                        },
                     )
                 } else {
                     panic!("this should not happen.")
@@ -2834,8 +4340,11 @@ impl ClientMapBuffered {
             true,
             0,
             0,
-            true,
-            true,
+            TileLayerUploadOptions {
+                ignore_tile_index_and_is_textured_check: true,
+                create_tile_index_flag: true,
+                ..Default::default()
+            },
         );
         ClientMapBuffered::finish_upload_tile_layer(
             shader_storage_handle,
@@ -2845,3 +4354,2147 @@ impl ClientMapBuffered {
         )
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::RefCell, collections::HashMap, rc::Rc, sync::Arc, time::Duration};
+
+    use base::hash::generate_hash_for;
+    use graphics::{
+        graphics_mt::GraphicsMultiThreaded,
+        handles::{
+            backend::backend::GraphicsBackendHandle,
+            buffer_object::buffer_object::GraphicsBufferObjectHandle,
+            shader_storage::shader_storage::GraphicsShaderStorageHandle,
+            texture::texture::{TextureContainer2dArray, TextureType2dArray},
+        },
+    };
+    use graphics_backend_traits::{
+        plugin::BackendRenderExecuteInterface, traits::GraphicsBackendMtInterface,
+    };
+    use graphics_types::{
+        commands::{AllCommands, CommandsMisc, CommandsRender},
+        rendering::{ColorRgba, GlVertex, State, TextureFilter},
+        types::{
+            GraphicsBackendMemory, GraphicsBackendMemoryAllocation, GraphicsMemoryAllocationMode,
+            GraphicsMemoryAllocationType,
+        },
+    };
+    use legacy_map::mapdef_06::TILE_SWITCHTIMEDOPEN;
+    use map::{
+        map::{
+            animations::{AnimBase, AnimPoint, AnimPointCurveType, AnimPointPos},
+            groups::{
+                layers::{
+                    design::{MapLayer, MapLayerQuad, MapLayerQuadsAttrs, Quad},
+                    physics::{MapLayerTilePhysicsBase, MapLayerTilePhysicsSwitch},
+                    tiles::{
+                        MapTileLayerPhysicsTilesRef, SwitchTile, TeleTile, TileBase, TileFlags,
+                        TuneTile,
+                    },
+                },
+                MapGroupAttr, MapGroupAttrClipping, MapGroupPhysicsAttr,
+            },
+        },
+        skeleton::groups::layers::{
+            design::MapLayerArbitrarySkeleton,
+            physics::{MapLayerPhysicsSkeleton, MapLayerSwitchPhysicsSkeleton},
+        },
+        types::NonZeroU16MinusOne,
+    };
+    use math::math::vector::{ffixed, fvec2, fvec3, ivec2, nffixed, nfvec4, uffixed, ufvec2, vec2};
+    use pool::mt_datatypes::PoolVec;
+    use sound::sound::SoundManager;
+
+    use crate::map::{
+        map_buffered::upload_cache::ClientMapBufferUploadCache,
+        map_pipeline::{
+            tint_color, CommandsRenderMap, MapGraphics, MapPipeline, TileLayerDrawInfo,
+        },
+        map_with_visual::{
+            MapVisualAnimations, MapVisualColorAnimation, MapVisualGroup, MapVisualGroups,
+            MapVisualLayer, MapVisualPhysicsGroup, MapVisualPosAnimation,
+        },
+        test_util::{
+            quad_layer, test_group, test_map, tile_layer, NullBackend, RecordingSoundBackend,
+            VecBackendMt,
+        },
+    };
+
+    use super::{
+        eval_color_anim, eval_pos_anim, resolve_render_layers, set_color_anim_points,
+        set_pos_anim_points, visible_render_layers, ClientMapBufferUploadData, ClientMapBuffered,
+        ClientMapBufferedBuilder, LayerUploadError, MapBufferPhysicsTileLayer, MapLayerLocation,
+        MapRenderInfo, MapRenderLayer, MapRenderTextOverlayType, MapUploadMismatch,
+        PhysicsLayerKind, PhysicsTileKind, QuadLayerUploadOptions, QuadVisualRangeAnim,
+        TileLayerTooLarge, TileLayerUploadOptions, TileLayerVisualsBase, TmpQuadTextured,
+        TmpQuadVertexTextured, QUAD_INSTANCING_MIN_RUN,
+    };
+
+    /// Records how many memories every batched flush contained
+    /// and whether it was an expensive flush.
+    /// Like the vulkan backend, an expensive flush copies the memories
+    /// on another thread and waits for that copy, as it would for its fence.
+    #[derive(Debug, Default)]
+    struct FlushRecorderBackendMt {
+        flushes: std::sync::Mutex<Vec<(usize, bool)>>,
+        /// The memories copied by expensive flushes.
+        resident: Arc<std::sync::Mutex<Vec<Vec<u8>>>>,
+    }
+
+    impl GraphicsBackendMtInterface for FlushRecorderBackendMt {
+        fn mem_alloc(
+            &self,
+            alloc_type: GraphicsMemoryAllocationType,
+            mode: GraphicsMemoryAllocationMode,
+        ) -> GraphicsBackendMemory {
+            VecBackendMt.mem_alloc(alloc_type, mode)
+        }
+
+        fn try_flush_mem(
+            &self,
+            _mem: &mut GraphicsBackendMemory,
+            _do_expensive_flushing: bool,
+        ) -> anyhow::Result<()> {
+            panic!("layer buffers are flushed in batches")
+        }
+
+        fn try_flush_mem_batch(
+            &self,
+            mems: &mut [&mut GraphicsBackendMemory],
+            do_expensive_flushing: bool,
+        ) -> anyhow::Result<()> {
+            self.flushes
+                .lock()
+                .unwrap()
+                .push((mems.len(), do_expensive_flushing));
+            if do_expensive_flushing {
+                let copies: Vec<Vec<u8>> = mems.iter().map(|mem| mem.as_slice().to_vec()).collect();
+                let resident = self.resident.clone();
+                let fence = std::thread::spawn(move || {
+                    std::thread::sleep(Duration::from_millis(20));
+                    resident.lock().unwrap().extend(copies);
+                });
+                fence
+                    .join()
+                    .map_err(|_| anyhow::anyhow!("copying the memories failed"))?;
+            }
+            Ok(())
+        }
+    }
+
+    /// Returns at most `max_size` bytes, like a graphics implementation
+    /// that ran out of memory.
+    #[derive(Debug)]
+    struct TinyBackendMt {
+        max_size: usize,
+    }
+
+    impl GraphicsBackendMtInterface for TinyBackendMt {
+        fn mem_alloc(
+            &self,
+            alloc_type: GraphicsMemoryAllocationType,
+            mode: GraphicsMemoryAllocationMode,
+        ) -> GraphicsBackendMemory {
+            let (allocation, alloc_type) = VecBackendMt.mem_alloc(alloc_type, mode).take();
+            let GraphicsBackendMemoryAllocation::Vector(mut mem) = allocation else {
+                unreachable!()
+            };
+            mem.truncate(self.max_size);
+            GraphicsBackendMemory::new(GraphicsBackendMemoryAllocation::Vector(mem), alloc_type)
+        }
+
+        fn try_flush_mem(
+            &self,
+            _mem: &mut GraphicsBackendMemory,
+            _do_expensive_flushing: bool,
+        ) -> anyhow::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn upload_border_buffer(
+        graphics_mt: &GraphicsMultiThreaded,
+        width: u16,
+        height: u16,
+        tiles: &[(u8, TileFlags, i16)],
+    ) -> Vec<u8> {
+        let mut tiles = tiles.iter().copied();
+        let layer = ClientMapBuffered::upload_tile_layer_buffer(
+            (
+                NonZeroU16MinusOne::new(width).unwrap(),
+                NonZeroU16MinusOne::new(height).unwrap(),
+                true,
+                &mut tiles,
+            ),
+            false,
+            false,
+            graphics_mt,
+            TileLayerUploadOptions {
+                ignore_tile_index_and_is_textured_check: true,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        layer.mem.unwrap().as_slice().to_vec()
+    }
+
+    /// Updates the row `y` of a layer and checks that the updated border buffer
+    /// matches a full upload of the updated layer.
+    fn check_border_row_update(width: u16, height: u16, y: u16) {
+        let graphics_mt = GraphicsMultiThreaded::new(Arc::new(VecBackendMt));
+
+        let tiles: Vec<(u8, TileFlags, i16)> = (0..width as usize * height as usize)
+            .map(|i| ((i % 255) as u8 + 1, TileFlags::empty(), 0))
+            .collect();
+        let mut updated_tiles = tiles.clone();
+        for x in 0..width as usize {
+            updated_tiles[y as usize * width as usize + x] = (255, TileFlags::ROTATE, 0);
+        }
+
+        let mut border_buffer = upload_border_buffer(&graphics_mt, width, height, &tiles);
+        let update_tiles = updated_tiles.clone();
+        let (update_data, update_regions) = ClientMapBuffered::border_tile_updates(
+            NonZeroU16MinusOne::new(width).unwrap(),
+            NonZeroU16MinusOne::new(height).unwrap(),
+            0,
+            y,
+            NonZeroU16MinusOne::new(width).unwrap(),
+            NonZeroU16MinusOne::new(1).unwrap(),
+            &move |skip| -> Box<dyn Iterator<Item = (u8, TileFlags, i16)>> {
+                Box::new(update_tiles.clone().into_iter().skip(skip))
+            },
+            false,
+            true,
+        );
+        for region in update_regions {
+            border_buffer[region.dst_offset..region.dst_offset + region.size]
+                .copy_from_slice(&update_data[region.src_offset..region.src_offset + region.size]);
+        }
+
+        assert_eq!(
+            border_buffer,
+            upload_border_buffer(&graphics_mt, width, height, &updated_tiles)
+        );
+    }
+
+    #[test]
+    fn border_tile_update_matches_upload() {
+        check_border_row_update(5, 4, 0);
+        check_border_row_update(5, 4, 3);
+        check_border_row_update(5, 4, 1);
+        // single row & column layers are left & right or top & bottom border at once
+        check_border_row_update(1, 3, 0);
+        check_border_row_update(4, 1, 0);
+    }
+
+    #[test]
+    fn tile_layer_too_large() {
+        // the largest tile count below the limit, not allocated
+        assert_eq!(TileLayerVisualsBase::check_tile_count(65535, 65536), Ok(()));
+        // 65535 * 65537 == u32::MAX
+        assert_eq!(
+            TileLayerVisualsBase::check_tile_count(65535, 65537),
+            Err(TileLayerTooLarge {
+                width: 65535,
+                height: 65537
+            })
+        );
+        assert_eq!(
+            TileLayerVisualsBase::default().init(u32::MAX, 2),
+            Err(TileLayerTooLarge {
+                width: u32::MAX,
+                height: 2
+            })
+        );
+        // empty layers are not an error
+        assert_eq!(TileLayerVisualsBase::default().init(0, 2), Ok(false));
+    }
+
+    #[test]
+    fn render_layers_resolve_visual_layers() {
+        let groups: Vec<MapVisualGroup> = vec![
+            MapVisualGroup {
+                attr: MapGroupAttr::default(),
+                layers: vec![
+                    MapVisualLayer::Abritrary(MapLayerArbitrarySkeleton {
+                        buf: Vec::new(),
+                        user: (),
+                    }),
+                    tile_layer("tiles"),
+                ],
+                name: "first".into(),
+                user: (),
+            },
+            MapVisualGroup {
+                attr: MapGroupAttr::default(),
+                layers: vec![quad_layer("quads"), tile_layer("more tiles")],
+                name: "second".into(),
+                user: (),
+            },
+        ];
+        let render_info = |group_index, layer_index| MapRenderInfo {
+            group_index,
+            layer_index,
+        };
+        let render_layers = [
+            MapRenderLayer::Tile(render_info(0, 1)),
+            MapRenderLayer::Quad(render_info(1, 0)),
+            MapRenderLayer::Tile(render_info(1, 1)),
+            // out of bounds
+            MapRenderLayer::Tile(render_info(2, 0)),
+            MapRenderLayer::Quad(render_info(1, 2)),
+            // not of the same kind
+            MapRenderLayer::Quad(render_info(0, 0)),
+            MapRenderLayer::Tile(render_info(1, 0)),
+        ];
+
+        let resolved: Vec<_> = resolve_render_layers(&render_layers, &groups)
+            .map(|(render_layer, layer)| {
+                let name = match layer {
+                    MapVisualLayer::Tile(layer) => {
+                        assert!(matches!(render_layer, MapRenderLayer::Tile(_)));
+                        &layer.layer.name
+                    }
+                    MapVisualLayer::Quad(layer) => {
+                        assert!(matches!(render_layer, MapRenderLayer::Quad(_)));
+                        &layer.layer.name
+                    }
+                    _ => panic!("render layers only resolve to tile or quad layers"),
+                };
+                name.as_str()
+            })
+            .collect();
+        assert_eq!(resolved, ["tiles", "quads", "more tiles"]);
+    }
+
+    #[test]
+    fn clipped_groups_are_culled() {
+        let group = |clipping: Option<(f64, f64, f64, f64)>| MapVisualGroup {
+            attr: MapGroupAttr {
+                clipping: clipping.map(|(x, y, w, h)| MapGroupAttrClipping {
+                    pos: fvec2::new(ffixed::from_num(x), ffixed::from_num(y)),
+                    size: ufvec2::new(uffixed::from_num(w), uffixed::from_num(h)),
+                }),
+                ..Default::default()
+            },
+            layers: vec![tile_layer("tiles"), quad_layer("quads")],
+            name: "".into(),
+            user: (),
+        };
+        let groups = vec![
+            // not clipped at all
+            group(None),
+            // far right of the view
+            group(Some((100.0, 0.0, 10.0, 10.0))),
+            // overlaps the view's top left corner
+            group(Some((-5.0, -5.0, 10.0, 10.0))),
+        ];
+        let render_layers: Vec<_> = (0..groups.len())
+            .flat_map(|group_index| {
+                [
+                    MapRenderLayer::Tile(MapRenderInfo {
+                        group_index,
+                        layer_index: 0,
+                    }),
+                    MapRenderLayer::Quad(MapRenderInfo {
+                        group_index,
+                        layer_index: 1,
+                    }),
+                ]
+            })
+            .collect();
+
+        // a view of 50x30 tiles at the map's origin
+        let view = (0.0, 0.0, 50.0, 30.0);
+        assert_eq!(
+            visible_render_layers(&render_layers, &groups, view),
+            [0, 1, 4, 5]
+        );
+
+        // moving the camera to the right makes the clipped group visible,
+        // while the other clipped group goes out of view
+        let view = (80.0, 0.0, 130.0, 30.0);
+        assert_eq!(
+            visible_render_layers(&render_layers, &groups, view),
+            [0, 1, 2, 3]
+        );
+    }
+
+    #[test]
+    fn tune_layer_zone_overlay() {
+        let graphics_mt = GraphicsMultiThreaded::new(Arc::new(VecBackendMt));
+        let width = NonZeroU16MinusOne::new(3).unwrap();
+        let height = NonZeroU16MinusOne::new(2).unwrap();
+        let tiles: Vec<TuneTile> = (0..6)
+            .map(|i| TuneTile {
+                base: TileBase {
+                    index: 68,
+                    flags: TileFlags::empty(),
+                },
+                number: i + 1,
+            })
+            .collect();
+
+        let layer = ClientMapBuffered::upload_physics_layer(
+            &graphics_mt,
+            width,
+            height,
+            MapTileLayerPhysicsTilesRef::Tune(&tiles),
+            0,
+            TileLayerUploadOptions::default(),
+        );
+        assert_eq!(layer.overlays.len(), 1);
+        let (ty, overlay) = &layer.overlays[0];
+        assert!(matches!(ty, MapRenderTextOverlayType::Center));
+
+        // the overlay renders the zone numbers as tile indices
+        let mut numbers = tiles
+            .iter()
+            .map(|tile| (tile.number, TileFlags::empty(), -1));
+        let expected = ClientMapBuffered::upload_tile_layer_buffer(
+            (width, height, true, &mut numbers),
+            false,
+            false,
+            &graphics_mt,
+            TileLayerUploadOptions::default(),
+        )
+        .unwrap();
+        assert_eq!(
+            overlay.shader_storage_mem.as_ref().unwrap().as_slice(),
+            expected.shader_storage_mem.as_ref().unwrap().as_slice()
+        );
+        assert_eq!(
+            overlay.mem.as_ref().unwrap().as_slice(),
+            expected.mem.as_ref().unwrap().as_slice()
+        );
+    }
+
+    #[test]
+    fn physics_drawable_tiles() {
+        let backend_handle = GraphicsBackendHandle::new(Rc::new(NullBackend::default()));
+        let shader_storage_handle = GraphicsShaderStorageHandle::new(backend_handle.clone());
+        let buffer_object_handle = GraphicsBufferObjectHandle::new(backend_handle.clone());
+        let graphics_mt = GraphicsMultiThreaded::new(Arc::new(VecBackendMt));
+        let width = NonZeroU16MinusOne::new(3).unwrap();
+        let height = NonZeroU16MinusOne::new(2).unwrap();
+        let upload = |tiles: MapTileLayerPhysicsTilesRef| {
+            ClientMapBuffered::finish_upload_physics_tile_layer(
+                &shader_storage_handle,
+                &buffer_object_handle,
+                &backend_handle,
+                ClientMapBuffered::upload_physics_layer(
+                    &graphics_mt,
+                    width,
+                    height,
+                    tiles,
+                    0,
+                    TileLayerUploadOptions::default(),
+                ),
+            )
+        };
+
+        let tile = |index| TileBase {
+            index,
+            flags: TileFlags::empty(),
+        };
+        let game_tiles: Vec<TileBase> = [1, 0, 0, 0, 2, 3].into_iter().map(tile).collect();
+        let layer = upload(MapTileLayerPhysicsTilesRef::Game(&game_tiles));
+        let drawable_tiles: Vec<_> = layer
+            .drawable_tiles(MapTileLayerPhysicsTilesRef::Game(&game_tiles))
+            .collect();
+        assert_eq!(
+            drawable_tiles,
+            [
+                (ivec2::new(0, 0), PhysicsTileKind::Game(tile(1))),
+                (ivec2::new(1, 1), PhysicsTileKind::Game(tile(2))),
+                (ivec2::new(2, 1), PhysicsTileKind::Game(tile(3))),
+            ]
+        );
+
+        let tele_tiles: Vec<TeleTile> = (0..6)
+            .map(|i| TeleTile {
+                base: tile(if i == 2 { 26 } else { 0 }),
+                number: i,
+            })
+            .collect();
+        let layer = upload(MapTileLayerPhysicsTilesRef::Tele(&tele_tiles));
+        let drawable_tiles: Vec<_> = layer
+            .drawable_tiles(MapTileLayerPhysicsTilesRef::Tele(&tele_tiles))
+            .collect();
+        assert_eq!(
+            drawable_tiles,
+            [(ivec2::new(2, 0), PhysicsTileKind::Tele(tele_tiles[2]))]
+        );
+    }
+
+    #[test]
+    fn physics_occupancy_bitmask() {
+        let backend_handle = GraphicsBackendHandle::new(Rc::new(NullBackend::default()));
+        let graphics_mt = GraphicsMultiThreaded::new(Arc::new(VecBackendMt));
+        // 70 tiles, so the bitset needs a second word
+        let (width, height) = (10, 7);
+        let tiles: Vec<TileBase> = (0..width * height)
+            .map(|i| TileBase {
+                index: if i % 3 == 0 || i == 69 { 1 } else { 0 },
+                flags: TileFlags::empty(),
+            })
+            .collect();
+        let layer = ClientMapBuffered::finish_upload_physics_tile_layer(
+            &GraphicsShaderStorageHandle::new(backend_handle.clone()),
+            &GraphicsBufferObjectHandle::new(backend_handle.clone()),
+            &backend_handle,
+            ClientMapBuffered::upload_physics_layer(
+                &graphics_mt,
+                NonZeroU16MinusOne::new(width as u16).unwrap(),
+                NonZeroU16MinusOne::new(height as u16).unwrap(),
+                MapTileLayerPhysicsTilesRef::Game(&tiles),
+                0,
+                TileLayerUploadOptions::default(),
+            ),
+        );
+
+        let mut expected = vec![0u64; 2];
+        for (i, tile) in tiles.iter().enumerate() {
+            if tile.index != 0 {
+                expected[i / 64] |= 1 << (i % 64);
+            }
+        }
+        assert_eq!(layer.occupancy_bitmask(), expected);
+    }
+
+    fn upload_test_map(graphics_mt: &GraphicsMultiThreaded) -> ClientMapBufferUploadData {
+        let design_tile_layer = || {
+            let MapVisualLayer::Tile(layer) = tile_layer("tiles") else {
+                unreachable!()
+            };
+            let mut layer = layer.layer;
+            layer.tiles = vec![TileBase {
+                index: 1,
+                flags: TileFlags::empty(),
+            }];
+            MapLayer::Tile(layer)
+        };
+        let design_quad_layer = || {
+            let MapVisualLayer::Quad(layer) = quad_layer("quads") else {
+                unreachable!()
+            };
+            MapLayer::Quad(layer.layer)
+        };
+        ClientMapBuffered::prepare_upload(
+            graphics_mt,
+            test_map(
+                vec![
+                    test_group(vec![design_tile_layer(), design_quad_layer()]),
+                    // empty groups must not stop the upload
+                    test_group(Vec::new()),
+                    test_group(vec![MapLayer::Abritrary(Vec::new()), design_tile_layer()]),
+                ],
+                vec![test_group(vec![design_quad_layer(), design_tile_layer()])],
+            ),
+        )
+    }
+
+    fn render_layer_infos(render_layers: &[MapRenderLayer]) -> Vec<(bool, usize, usize)> {
+        render_layers
+            .iter()
+            .map(|render_layer| {
+                let info = render_layer.get_render_info();
+                (
+                    matches!(render_layer, MapRenderLayer::Tile(_)),
+                    info.group_index,
+                    info.layer_index,
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn incremental_upload_matches_eager_upload() {
+        let backend_handle = GraphicsBackendHandle::new(Rc::new(NullBackend::default()));
+        let shader_storage_handle = GraphicsShaderStorageHandle::new(backend_handle.clone());
+        let buffer_object_handle = GraphicsBufferObjectHandle::new(backend_handle.clone());
+        let graphics_mt = GraphicsMultiThreaded::new(Arc::new(VecBackendMt));
+
+        let builder = || {
+            ClientMapBufferedBuilder::new(
+                &backend_handle,
+                &shader_storage_handle,
+                &buffer_object_handle,
+                upload_test_map(&graphics_mt),
+            )
+            .unwrap()
+        };
+
+        let mut eager = builder();
+        let progress = eager.upload_layers(usize::MAX);
+        assert_eq!(progress.uploaded_layers, 7);
+        assert_eq!(progress.total_layers, 7);
+        assert!(progress.is_finished());
+
+        let mut incremental = builder();
+        assert_eq!(incremental.progress().uploaded_layers, 0);
+        for uploaded_layers in 1..=7 {
+            let progress = incremental.upload_layers(1);
+            assert_eq!(progress.uploaded_layers, uploaded_layers);
+            assert_eq!(progress.is_finished(), uploaded_layers == 7);
+        }
+        // nothing left to upload
+        assert_eq!(incremental.upload_layers(3).uploaded_layers, 7);
+
+        let layer_counts = |groups: &[MapVisualGroup]| -> Vec<usize> {
+            groups.iter().map(|group| group.layers.len()).collect()
+        };
+        assert_eq!(
+            layer_counts(&incremental.background.visual_groups),
+            layer_counts(&eager.background.visual_groups)
+        );
+        assert_eq!(
+            layer_counts(&incremental.background.visual_groups),
+            [2, 0, 2]
+        );
+        assert_eq!(
+            layer_counts(&incremental.foreground.visual_groups),
+            layer_counts(&eager.foreground.visual_groups)
+        );
+        assert_eq!(
+            incremental.physics.visual_layers.len(),
+            eager.physics.visual_layers.len()
+        );
+        assert_eq!(
+            incremental.physics.render_infos.len(),
+            eager.physics.render_infos.len()
+        );
+        assert_eq!(incremental.physics.render_infos.len(), 1);
+
+        // quad layers without quads are not rendered
+        let background = render_layer_infos(&incremental.background.render_layers());
+        assert_eq!(
+            background,
+            render_layer_infos(&eager.background.render_layers())
+        );
+        assert_eq!(background, [(true, 0, 0), (true, 2, 1)]);
+        let foreground = render_layer_infos(&incremental.foreground.render_layers());
+        assert_eq!(
+            foreground,
+            render_layer_infos(&eager.foreground.render_layers())
+        );
+        assert_eq!(foreground, [(true, 0, 1)]);
+    }
+
+    #[test]
+    fn reloading_identical_map_reuses_all_layers() {
+        let backend_handle = GraphicsBackendHandle::new(Rc::new(NullBackend::default()));
+        let shader_storage_handle = GraphicsShaderStorageHandle::new(backend_handle.clone());
+        let buffer_object_handle = GraphicsBufferObjectHandle::new(backend_handle.clone());
+        let graphics_mt = GraphicsMultiThreaded::new(Arc::new(VecBackendMt));
+        let sound = SoundManager::new(Rc::new(RecordingSoundBackend::default())).unwrap();
+
+        let load = |prev_map: Option<&ClientMapBuffered>| {
+            let mut builder = ClientMapBufferedBuilder::new(
+                &backend_handle,
+                &shader_storage_handle,
+                &buffer_object_handle,
+                upload_test_map(&graphics_mt),
+            )
+            .unwrap();
+            if let Some(prev_map) = prev_map {
+                builder = builder.reuse_layers_of(prev_map);
+            }
+            let progress = builder.upload_layers(usize::MAX);
+            let scene = sound.scene_handle.create(Default::default());
+            let listener = scene.sound_listener_handle.create(vec2::new(0.0, 0.0));
+            let map = builder.finish(Vec::new(), Vec::new(), scene, listener, Vec::new());
+            (progress, map)
+        };
+        let buffer_cmd_count = |is_buffer_cmd: fn(&CommandsMisc) -> bool| {
+            backend_handle
+                .backend_cmds
+                .take()
+                .into_iter()
+                .filter(|cmd| matches!(cmd, AllCommands::Misc(cmd) if is_buffer_cmd(cmd)))
+                .count()
+        };
+        let is_create = |cmd: &CommandsMisc| {
+            matches!(
+                cmd,
+                CommandsMisc::CreateBufferObject(_) | CommandsMisc::CreateShaderStorage(_)
+            )
+        };
+        let is_delete = |cmd: &CommandsMisc| {
+            matches!(
+                cmd,
+                CommandsMisc::DeleteBufferObject(_) | CommandsMisc::DeleteShaderStorage(_)
+            )
+        };
+
+        let (progress, map) = load(None);
+        assert_eq!(progress.reused_layers, 0);
+        assert!(buffer_cmd_count(is_create) > 0);
+
+        let (progress, reloaded) = load(Some(&map));
+        // everything but the arbitrary layer & the quad layers without quads
+        assert_eq!(progress.reused_layers, 4);
+        assert_eq!(buffer_cmd_count(is_create), 0);
+        assert_eq!(
+            render_layer_infos(&reloaded.render.background_render_layers),
+            render_layer_infos(&map.render.background_render_layers)
+        );
+        assert_eq!(
+            render_layer_infos(&reloaded.render.foreground_render_layers),
+            render_layer_infos(&map.render.foreground_render_layers)
+        );
+        assert_eq!(reloaded.render.physics_render_layers.len(), 1);
+
+        // the buffers are shared, so dropping the previous map keeps them alive
+        drop(map);
+        assert_eq!(buffer_cmd_count(is_delete), 0);
+        drop(reloaded);
+        assert!(buffer_cmd_count(is_delete) > 0);
+    }
+
+    #[test]
+    fn reloading_does_not_reuse_failed_layers() {
+        let backend_handle = GraphicsBackendHandle::new(Rc::new(NullBackend::default()));
+        let shader_storage_handle = GraphicsShaderStorageHandle::new(backend_handle.clone());
+        let buffer_object_handle = GraphicsBufferObjectHandle::new(backend_handle.clone());
+        let sound = SoundManager::new(Rc::new(RecordingSoundBackend::default())).unwrap();
+
+        let load = |graphics_mt: &GraphicsMultiThreaded, prev_map: Option<&ClientMapBuffered>| {
+            let mut builder = ClientMapBufferedBuilder::new(
+                &backend_handle,
+                &shader_storage_handle,
+                &buffer_object_handle,
+                upload_test_map(graphics_mt),
+            )
+            .unwrap();
+            if let Some(prev_map) = prev_map {
+                builder = builder.reuse_layers_of(prev_map);
+            }
+            let progress = builder.upload_layers(usize::MAX);
+            let scene = sound.scene_handle.create(Default::default());
+            let listener = scene.sound_listener_handle.create(vec2::new(0.0, 0.0));
+            let map = builder.finish(Vec::new(), Vec::new(), scene, listener, Vec::new());
+            (progress, map)
+        };
+        let failing_mt = GraphicsMultiThreaded::new(Arc::new(TinyBackendMt { max_size: 0 }));
+        let graphics_mt = GraphicsMultiThreaded::new(Arc::new(VecBackendMt));
+
+        let (_, failed) = load(&failing_mt, None);
+        assert_eq!(failed.missing_layers.len(), 4);
+
+        // the same upload fails again, so the layers are still reported as missing
+        let (progress, failed_again) = load(&failing_mt, Some(&failed));
+        assert_eq!(progress.reused_layers, 0);
+        assert_eq!(
+            failed_again
+                .missing_layers
+                .iter()
+                .map(|missing_layer| missing_layer.location)
+                .collect::<Vec<_>>(),
+            failed
+                .missing_layers
+                .iter()
+                .map(|missing_layer| missing_layer.location)
+                .collect::<Vec<_>>()
+        );
+
+        // now the upload succeeds, so the layers are rendered
+        let (progress, reloaded) = load(&graphics_mt, Some(&failed_again));
+        assert_eq!(progress.reused_layers, 0);
+        assert!(reloaded.missing_layers.is_empty());
+        let (_, fresh) = load(&graphics_mt, None);
+        assert_eq!(
+            render_layer_infos(&reloaded.render.background_render_layers),
+            render_layer_infos(&fresh.render.background_render_layers)
+        );
+        assert_eq!(
+            render_layer_infos(&reloaded.render.foreground_render_layers),
+            render_layer_infos(&fresh.render.foreground_render_layers)
+        );
+        assert_eq!(reloaded.render.physics_render_layers.len(), 1);
+    }
+
+    #[test]
+    fn physics_size_and_layer_kinds_match_the_map() {
+        let backend_handle = GraphicsBackendHandle::new(Rc::new(NullBackend::default()));
+        let shader_storage_handle = GraphicsShaderStorageHandle::new(backend_handle.clone());
+        let buffer_object_handle = GraphicsBufferObjectHandle::new(backend_handle.clone());
+        let graphics_mt = GraphicsMultiThreaded::new(Arc::new(VecBackendMt));
+        let sound = SoundManager::new(Rc::new(RecordingSoundBackend::default())).unwrap();
+
+        let scene = sound.scene_handle.create(Default::default());
+        let listener = scene.sound_listener_handle.create(vec2::new(0.0, 0.0));
+        let map = ClientMapBuffered::new(
+            &backend_handle,
+            &shader_storage_handle,
+            &buffer_object_handle,
+            upload_test_map(&graphics_mt),
+            Vec::new(),
+            Vec::new(),
+            scene,
+            listener,
+            Vec::new(),
+        )
+        .unwrap();
+
+        // the physics group of the test map is 2x1 tiles with a single game layer
+        assert_eq!(map.physics_size(), (2, 1));
+        assert_eq!(map.physics_layer_kinds(), [PhysicsLayerKind::Game]);
+    }
+
+    #[test]
+    fn map_without_design_groups_is_buffered() {
+        let backend_handle = GraphicsBackendHandle::new(Rc::new(NullBackend::default()));
+        let shader_storage_handle = GraphicsShaderStorageHandle::new(backend_handle.clone());
+        let buffer_object_handle = GraphicsBufferObjectHandle::new(backend_handle.clone());
+        let graphics_mt = GraphicsMultiThreaded::new(Arc::new(VecBackendMt));
+        let sound = SoundManager::new(Rc::new(RecordingSoundBackend::default())).unwrap();
+
+        let mut map = test_map(Vec::new(), Vec::new());
+        map.groups.physics.layers.clear();
+        let scene = sound.scene_handle.create(Default::default());
+        let listener = scene.sound_listener_handle.create(vec2::new(0.0, 0.0));
+        let map = ClientMapBuffered::new(
+            &backend_handle,
+            &shader_storage_handle,
+            &buffer_object_handle,
+            ClientMapBuffered::prepare_upload(&graphics_mt, map),
+            Vec::new(),
+            Vec::new(),
+            scene,
+            listener,
+            Vec::new(),
+        )
+        .unwrap();
+        assert!(map.map_visual.groups.background.is_empty());
+        assert!(map.map_visual.groups.foreground.is_empty());
+        assert!(map.physics_layer_kinds().is_empty());
+        assert!(map.missing_layers.is_empty());
+
+        // a layer that was added after preparing the upload has no upload
+        let mut upload_data =
+            ClientMapBuffered::prepare_upload(&graphics_mt, test_map(Vec::new(), Vec::new()));
+        let MapVisualLayer::Tile(layer) = tile_layer("tiles") else {
+            unreachable!()
+        };
+        upload_data
+            .map
+            .groups
+            .foreground
+            .push(test_group(vec![MapLayer::Tile(layer.layer)]));
+        assert_eq!(
+            ClientMapBufferedBuilder::new(
+                &backend_handle,
+                &shader_storage_handle,
+                &buffer_object_handle,
+                upload_data,
+            )
+            .err(),
+            Some(MapUploadMismatch {
+                kind: "foreground tile",
+                layers: 1,
+                uploads: 0,
+            })
+        );
+    }
+
+    #[test]
+    fn expensive_flushing_is_requested_per_layer() {
+        let recorder = Arc::new(FlushRecorderBackendMt::default());
+        let graphics_mt = GraphicsMultiThreaded::new(recorder.clone());
+        let tiles = vec![
+            TileBase {
+                index: 1,
+                flags: TileFlags::empty(),
+            };
+            4
+        ];
+        let upload = |do_expensive_flushing| {
+            ClientMapBuffered::upload_design_tile_layer(
+                &graphics_mt,
+                &tiles,
+                NonZeroU16MinusOne::new(2).unwrap(),
+                NonZeroU16MinusOne::new(2).unwrap(),
+                true,
+                0,
+                0,
+                TileLayerUploadOptions {
+                    do_expensive_flushing,
+                    ..Default::default()
+                },
+            )
+        };
+        let layer = upload(true);
+        // the expensive flush was awaited, the layer is resident once the upload returned
+        let layer_mems: Vec<Vec<u8>> = [
+            layer.base.shader_storage_mem.as_ref(),
+            layer.base.mem.as_ref(),
+        ]
+        .into_iter()
+        .flatten()
+        .map(|mem| mem.as_slice().to_vec())
+        .collect();
+        assert!(!layer_mems.is_empty());
+        assert_eq!(*recorder.resident.lock().unwrap(), layer_mems);
+
+        // the cheap flush leaves the upload to the graphics implementation
+        upload(false);
+        assert_eq!(recorder.resident.lock().unwrap().len(), layer_mems.len());
+
+        // all buffers of a layer are flushed in a single batch
+        let flushes = recorder.flushes.lock().unwrap();
+        assert_eq!(
+            flushes
+                .iter()
+                .map(|&(_, expensive)| expensive)
+                .collect::<Vec<_>>(),
+            [true, false]
+        );
+        assert!(flushes.iter().all(|&(mem_count, _)| mem_count > 0));
+    }
+
+    #[test]
+    fn index_buffer_is_recreated_once() {
+        let backend_handle = GraphicsBackendHandle::new(Rc::new(NullBackend::default()));
+        let shader_storage_handle = GraphicsShaderStorageHandle::new(backend_handle.clone());
+        let buffer_object_handle = GraphicsBufferObjectHandle::new(backend_handle.clone());
+        let graphics_mt = GraphicsMultiThreaded::new(Arc::new(VecBackendMt));
+
+        let design_tile_layer = |width: u16| {
+            let MapVisualLayer::Tile(layer) = tile_layer("tiles") else {
+                unreachable!()
+            };
+            let mut layer = layer.layer;
+            layer.attr.width = NonZeroU16MinusOne::new(width).unwrap();
+            layer.tiles = vec![
+                TileBase {
+                    index: 1,
+                    flags: TileFlags::empty(),
+                };
+                width as usize
+            ];
+            MapLayer::Tile(layer)
+        };
+        // every layer requires more indices than the one before
+        let map = test_map(
+            vec![test_group((1..=8).map(design_tile_layer).collect())],
+            vec![test_group((9..=16).map(design_tile_layer).collect())],
+        );
+        let upload_data = ClientMapBuffered::prepare_upload(&graphics_mt, map);
+        let max_quad_count = upload_data.max_quad_count_for_indices();
+
+        let mut builder = ClientMapBufferedBuilder::new(
+            &backend_handle,
+            &shader_storage_handle,
+            &buffer_object_handle,
+            upload_data,
+        )
+        .unwrap();
+        assert!(builder.upload_layers(usize::MAX).is_finished());
+
+        // like the backend, recreate the index buffer whenever more quads are required
+        let mut quad_count = 0;
+        let mut recreations = 0;
+        for cmd in backend_handle.backend_cmds.take() {
+            if let AllCommands::Misc(CommandsMisc::IndicesForQuadsRequiredNotify(cmd)) = cmd {
+                if cmd.quad_count_required > quad_count {
+                    quad_count = cmd.quad_count_required;
+                    recreations += 1;
+                }
+            }
+        }
+        assert_eq!(recreations, 1);
+        assert_eq!(quad_count, max_quad_count);
+    }
+
+    #[test]
+    fn tile_layer_tint_is_forwarded() {
+        let backend_handle = GraphicsBackendHandle::new(Rc::new(NullBackend::default()));
+        let mut shader_storage_handle = GraphicsShaderStorageHandle::new(backend_handle.clone());
+        let map_graphics = MapGraphics::new(&backend_handle);
+
+        let MapVisualLayer::Tile(mut layer) = tile_layer("tiles") else {
+            unreachable!()
+        };
+        assert_eq!(layer.user.base.base.tint, None);
+        let tint = [1.0, 1.0, 1.0, 0.5];
+        layer.user.set_layer_tint(tint);
+        assert_eq!(layer.user.base.base.tint, Some(tint));
+
+        let shader_storage = shader_storage_handle.create_shader_storage(backend_handle.mem_alloc(
+            GraphicsMemoryAllocationType::ShaderStorage {
+                required_size: 16usize.try_into().unwrap(),
+            },
+        ));
+        let color = ColorRgba {
+            r: 0.5,
+            g: 1.0,
+            b: 1.0,
+            a: 0.8,
+        };
+        let mut draws = PoolVec::new_without_pool();
+        draws.push(TileLayerDrawInfo {
+            quad_offset: 0,
+            quad_count: 1,
+            pos_y: 0.0,
+        });
+        map_graphics.render_tile_layer(
+            &State::new(),
+            TextureType2dArray::None,
+            &shader_storage,
+            &color,
+            &layer.user.base.base.tint.unwrap(),
+            draws,
+        );
+
+        let cmd = backend_handle
+            .backend_cmds
+            .take()
+            .into_iter()
+            .find_map(|cmd| match cmd {
+                AllCommands::Render(CommandsRender::Mod(cmd)) => Some(cmd),
+                _ => None,
+            })
+            .unwrap();
+        let (CommandsRenderMap::TileLayer(cmd), _) =
+            bincode::serde::decode_from_slice::<CommandsRenderMap, _>(
+                &cmd.cmd[..],
+                bincode::config::standard(),
+            )
+            .unwrap()
+        else {
+            panic!("expected a tile layer command");
+        };
+        // the layer color is kept, the tint is applied when rendering
+        let rgba = |color: ColorRgba| (color.r, color.g, color.b, color.a);
+        assert_eq!(rgba(cmd.color), rgba(color));
+        assert_eq!(cmd.tint, tint);
+        assert_eq!(
+            rgba(tint_color(&cmd.color, &cmd.tint)),
+            (0.5, 1.0, 1.0, 0.4)
+        );
+    }
+
+    /// Records the samplers that the map pipeline binds while filling its execute buffers.
+    #[derive(Default)]
+    struct SamplerRecorder {
+        address_mode_indices: Vec<u64>,
+    }
+
+    impl BackendRenderExecuteInterface for SamplerRecorder {
+        fn get_address_mode_index(&self, state: &State) -> u64 {
+            state.wrap_mode as u64 | ((state.texture_filter as u64) << 1)
+        }
+
+        fn estimated_render_calls(&mut self, _: u64) {}
+
+        fn set_texture(&mut self, _: u64, _: u128, _: u64) {}
+
+        fn set_color_attachment_as_texture(&mut self, _: u64, _: u64) {}
+
+        fn set_offscreen_attachment_as_texture(&mut self, _: u128, _: u64, _: u64) {}
+
+        fn set_texture_3d(&mut self, _: u64, _: u128, address_mode_index: u64) {
+            self.address_mode_indices.push(address_mode_index);
+        }
+
+        fn uses_stream_vertex_buffer(&mut self, _: u64) {}
+
+        fn uses_stream_uniform_buffer(&mut self, _: u64, _: u64, _: u64) {}
+
+        fn uses_index_buffer(&mut self) {}
+
+        fn exec_buffer_fill_dynamic_states(&mut self, _: &State) {}
+
+        fn set_vertex_buffer(&mut self, _: u128) {}
+
+        fn set_vertex_buffer_with_offset(&mut self, _: u128, _: usize) {}
+
+        fn set_shader_storage(&mut self, _: u128) {}
+    }
+
+    #[test]
+    fn tile_layer_texture_filter_selects_its_sampler() {
+        let backend_handle = GraphicsBackendHandle::new(Rc::new(NullBackend::default()));
+        let mut shader_storage_handle = GraphicsShaderStorageHandle::new(backend_handle.clone());
+        let map_graphics = MapGraphics::new(&backend_handle);
+        let texture = TextureContainer2dArray::new(1, backend_handle.clone());
+        let shader_storage = shader_storage_handle.create_shader_storage(backend_handle.mem_alloc(
+            GraphicsMemoryAllocationType::ShaderStorage {
+                required_size: 16usize.try_into().unwrap(),
+            },
+        ));
+
+        let mut address_mode_indices = Vec::new();
+        for filter in [TextureFilter::Linear, TextureFilter::Nearest] {
+            let MapVisualLayer::Tile(mut layer) = tile_layer("tiles") else {
+                unreachable!()
+            };
+            layer.user.set_texture_filter(filter);
+
+            let mut draws = PoolVec::new_without_pool();
+            draws.push(TileLayerDrawInfo {
+                quad_offset: 0,
+                quad_count: 1,
+                pos_y: 0.0,
+            });
+            map_graphics.render_tile_layer(
+                &layer.user.base.base.layer_state(&State::new()),
+                (&texture).into(),
+                &shader_storage,
+                &ColorRgba::new(1.0, 1.0, 1.0, 1.0),
+                &[1.0; 4],
+                draws,
+            );
+            let cmd = backend_handle
+                .backend_cmds
+                .take()
+                .into_iter()
+                .find_map(|cmd| match cmd {
+                    AllCommands::Render(CommandsRender::Mod(cmd)) => Some(cmd),
+                    _ => None,
+                })
+                .unwrap();
+
+            let mut recorder = SamplerRecorder::default();
+            MapPipeline::new_boxed().fill_exec_buffer(&cmd.cmd, &mut recorder);
+            address_mode_indices.extend(recorder.address_mode_indices);
+        }
+        assert_eq!(address_mode_indices.len(), 2);
+        assert_ne!(address_mode_indices[0], address_mode_indices[1]);
+    }
+
+    /// The bytes of all upload buffers in the order of the layers.
+    fn upload_buffers(upload_data: &ClientMapBufferUploadData) -> Vec<Option<Vec<u8>>> {
+        let mem =
+            |mem: &Option<GraphicsBackendMemory>| mem.as_ref().map(|mem| mem.as_slice().to_vec());
+        let tile_layer = |base: &MapBufferTileLayerBase| {
+            [mem(&base.mem), mem(&base.shader_storage_mem)].into_iter()
+        };
+        let design_tile_layers = upload_data
+            .bg_tile_layer_uploads
+            .iter()
+            .chain(upload_data.fg_tile_layer_uploads.iter())
+            .flat_map(|layer| tile_layer(&layer.base));
+        let physics_layers = upload_data
+            .physics_tile_layer_uploads
+            .iter()
+            .flat_map(|layer| {
+                tile_layer(&layer.base).chain(
+                    layer
+                        .overlays
+                        .iter()
+                        .flat_map(|(_, overlay)| tile_layer(overlay)),
+                )
+            });
+        let quad_layers = upload_data
+            .bg_quad_layer_uploads
+            .iter()
+            .chain(upload_data.fg_quad_layer_uploads.iter())
+            .flat_map(|layer| [mem(&layer.mem), mem(&layer.instanced_mem)]);
+        design_tile_layers
+            .chain(physics_layers)
+            .chain(quad_layers)
+            .collect()
+    }
+
+    #[test]
+    fn upload_cache_round_trip() {
+        let graphics_mt = GraphicsMultiThreaded::new(Arc::new(VecBackendMt));
+
+        let MapVisualLayer::Tile(tile_visual) = tile_layer("tiles") else {
+            unreachable!()
+        };
+        let mut tile_def = tile_visual.layer;
+        tile_def.tiles = vec![TileBase {
+            index: 1,
+            flags: TileFlags::empty(),
+        }];
+        let MapVisualLayer::Quad(quad_visual) = quad_layer("quads") else {
+            unreachable!()
+        };
+        let mut quad_def = quad_visual.layer;
+        quad_def.quads = vec![Quad::default(); 2];
+        let map = test_map(
+            vec![test_group(vec![MapLayer::Tile(tile_def)])],
+            vec![test_group(vec![MapLayer::Quad(quad_def)])],
+        );
+        let upload_data = ClientMapBuffered::prepare_upload(&graphics_mt, map);
+        let buffers = upload_buffers(&upload_data);
+        assert!(buffers.iter().flatten().count() > 1);
+        assert!(upload_data.fg_quad_layer_uploads[0].mem.is_some());
+
+        let map_hash = generate_hash_for(b"map");
+        let cache_file = ClientMapBufferUploadCache::new(&upload_data, map_hash)
+            .unwrap()
+            .encode()
+            .unwrap();
+        let map = upload_data.map.clone();
+        let cached_upload_data = ClientMapBufferUploadCache::decode(&cache_file, &map_hash, &map)
+            .unwrap()
+            .into_upload_data(&graphics_mt, map);
+
+        assert_eq!(upload_buffers(&cached_upload_data), buffers);
+        assert_eq!(
+            cached_upload_data.max_quad_count_for_indices(),
+            upload_data.max_quad_count_for_indices()
+        );
+        let draw_ranges = |upload_data: &ClientMapBufferUploadData| {
+            upload_data.fg_quad_layer_uploads[0]
+                .extra
+                .draw_ranges
+                .iter()
+                .map(|draw_range| (draw_range.anim, draw_range.range.clone()))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(draw_ranges(&cached_upload_data), draw_ranges(&upload_data));
+
+        // the cache of another map is never used
+        assert!(ClientMapBufferUploadCache::decode(
+            &cache_file,
+            &generate_hash_for(b"another map"),
+            &upload_data.map
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn non_finite_quad_values_are_sanitized() {
+        let (vertex, all_finite) = TmpQuadVertexTextured::new(
+            [f32::NAN, 2.0],
+            [f32::INFINITY, 3.0],
+            [0.5, f32::NEG_INFINITY],
+            [f32::NAN, 2.0, -1.0, 0.5],
+        );
+        assert!(!all_finite);
+        assert_eq!(
+            (vertex.x, vertex.y, vertex.center_x, vertex.center_y),
+            (0.0, 2.0, 0.0, 3.0)
+        );
+        assert_eq!((vertex.u, vertex.v), (0.5, 0.0));
+        assert_eq!((vertex.r, vertex.g, vertex.b, vertex.a), (0, 255, 0, 127));
+
+        let (_, all_finite) =
+            TmpQuadVertexTextured::new([1.0, 2.0], [3.0, 4.0], [0.0, 1.0], [1.0; 4]);
+        assert!(all_finite);
+
+        // extreme values still produce an uploaded layer with finite vertices
+        let graphics_mt = GraphicsMultiThreaded::new(Arc::new(VecBackendMt));
+        let mut quad = Quad::default();
+        quad.points[0] = fvec2::new(ffixed::MAX, ffixed::MIN);
+        quad.colors[0] = nfvec4::new(nffixed::MAX, nffixed::MAX, nffixed::MAX, nffixed::MAX);
+        let tmp_quads = ClientMapBuffered::fill_tmp_quads_for_upload(&[quad], 0, Some(0));
+        assert!(tmp_quads[0]
+            .vertices
+            .iter()
+            .all(|v| [v.x, v.y, v.center_x, v.center_y, v.u, v.v]
+                .iter()
+                .all(|v| v.is_finite())));
+        assert_eq!(tmp_quads[0].vertices[0].r, 255);
+
+        let layer = ClientMapBuffered::upload_quad_layer_buffer(
+            &MapLayerQuadsAttrs {
+                image: None,
+                high_detail: false,
+            },
+            &[quad, Quad::default()],
+            0,
+            0,
+            &graphics_mt,
+            QuadLayerUploadOptions::default(),
+        )
+        .unwrap();
+        assert!(layer.mem.is_some());
+        assert_eq!(layer.quad_count_for_indices, 2);
+    }
+
+    #[test]
+    fn repeated_quads_are_instanced() {
+        let graphics_mt = GraphicsMultiThreaded::new(Arc::new(VecBackendMt));
+        let attr = MapLayerQuadsAttrs {
+            image: Some(0),
+            high_detail: false,
+        };
+        let quad_at = |x: i32, y: i32| {
+            let mut quad = Quad::default();
+            let offsets = [(-1, -1), (1, -1), (-1, 1), (1, 1), (0, 0)];
+            for (point, (off_x, off_y)) in quad.points.iter_mut().zip(offsets) {
+                *point = fvec2::new(ffixed::from_num(x + off_x), ffixed::from_num(y + off_y));
+            }
+            quad
+        };
+        let quads: Vec<Quad> = (0..1000).map(|i| quad_at(i % 40 * 4, i / 40 * 4)).collect();
+
+        let naive = ClientMapBuffered::upload_quad_layer_buffer(
+            &attr,
+            &quads,
+            0,
+            0,
+            &graphics_mt,
+            QuadLayerUploadOptions::default(),
+        )
+        .unwrap();
+        let naive_size = naive.mem.as_ref().unwrap().as_slice().len();
+        assert_eq!(naive_size, 1000 * std::mem::size_of::<TmpQuadTextured>());
+        assert!(naive.instanced_mem.is_none());
+
+        let instanced = ClientMapBuffered::upload_quad_layer_buffer(
+            &attr,
+            &quads,
+            0,
+            0,
+            &graphics_mt,
+            QuadLayerUploadOptions {
+                instance_repeated_quads: true,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        assert!(instanced.mem.is_none());
+        let instanced_size = instanced.instanced_mem.as_ref().unwrap().as_slice().len();
+        assert_eq!(instanced_size, std::mem::size_of::<GlVertex>() * 4);
+        assert!(instanced_size * 100 < naive_size);
+        assert_eq!(instanced.extra.draw_ranges.len(), 1);
+        assert_eq!(
+            instanced.extra.draw_ranges[0].anim,
+            QuadVisualRangeAnim::Instanced { template: 0 }
+        );
+        assert_eq!(instanced.extra.draw_ranges[0].range, 0..1000);
+        assert_eq!(instanced.extra.instance_offsets[0].len(), 1000);
+        assert_eq!(instanced.extra.instance_offsets[0][41], vec2::new(4.0, 4.0));
+
+        // runs below the threshold and differing quads stay in the vertex buffer
+        let mut quads: Vec<Quad> = (0..QUAD_INSTANCING_MIN_RUN as i32 - 1)
+            .map(|i| quad_at(i * 4, 0))
+            .collect();
+        let mut bigger = quad_at(0, 8);
+        bigger.points[0] = fvec2::new(ffixed::from_num(-2), ffixed::from_num(6));
+        quads.push(bigger);
+        quads.extend((0..QUAD_INSTANCING_MIN_RUN as i32).map(|i| quad_at(i * 4, 16)));
+        let mixed = ClientMapBuffered::upload_quad_layer_buffer(
+            &attr,
+            &quads,
+            0,
+            0,
+            &graphics_mt,
+            QuadLayerUploadOptions {
+                instance_repeated_quads: true,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        let ranges: Vec<_> = mixed
+            .extra
+            .draw_ranges
+            .iter()
+            .map(|draw_range| (draw_range.anim, draw_range.range.clone()))
+            .collect();
+        assert_eq!(
+            ranges,
+            [
+                (QuadVisualRangeAnim::NoAnim, 0..QUAD_INSTANCING_MIN_RUN),
+                (
+                    QuadVisualRangeAnim::Instanced { template: 0 },
+                    QUAD_INSTANCING_MIN_RUN..QUAD_INSTANCING_MIN_RUN * 2
+                ),
+            ]
+        );
+        assert_eq!(
+            mixed.mem.as_ref().unwrap().as_slice().len(),
+            QUAD_INSTANCING_MIN_RUN * std::mem::size_of::<TmpQuadTextured>()
+        );
+        assert_eq!(mixed.quad_count_for_indices, QUAD_INSTANCING_MIN_RUN as u64);
+    }
+
+    #[test]
+    fn degenerate_quads_are_skipped() {
+        let graphics_mt = GraphicsMultiThreaded::new(Arc::new(VecBackendMt));
+        let attr = MapLayerQuadsAttrs {
+            image: Some(0),
+            high_detail: false,
+        };
+        let quad_with_points = |points: [(i32, i32); 4]| {
+            let mut quad = Quad::default();
+            for (point, (x, y)) in quad.points.iter_mut().zip(points) {
+                *point = fvec2::new(ffixed::from_num(x), ffixed::from_num(y));
+            }
+            quad
+        };
+        let valid = |x: i32| quad_with_points([(x, 0), (x + 1, 0), (x, 1), (x + 1, 1)]);
+        // all points on a line
+        let line = quad_with_points([(0, 0), (1, 1), (2, 2), (3, 3)]);
+        let mut color_anim = valid(10);
+        color_anim.color_anim = Some(0);
+        let quads = [
+            valid(0),
+            Quad::default(),
+            valid(2),
+            color_anim,
+            line,
+            Quad::default(),
+            valid(4),
+        ];
+
+        let upload = |skip_degenerate_quads: bool| {
+            ClientMapBuffered::upload_quad_layer_buffer(
+                &attr,
+                &quads,
+                0,
+                0,
+                &graphics_mt,
+                QuadLayerUploadOptions {
+                    skip_degenerate_quads,
+                    ..Default::default()
+                },
+            )
+            .unwrap()
+        };
+
+        // skipping is off by default
+        let all = upload(false);
+        assert_eq!(all.skipped_quad_count(), 0);
+        assert_eq!(all.quad_count_for_indices, quads.len() as u64);
+
+        let layer = upload(true);
+        assert_eq!(layer.skipped_quad_count(), 3);
+        assert_eq!(layer.quad_count_for_indices, 4);
+        let ranges: Vec<_> = layer
+            .extra
+            .draw_ranges
+            .iter()
+            .map(|draw_range| (draw_range.anim, draw_range.range.clone()))
+            .collect();
+        assert_eq!(
+            ranges,
+            [
+                (QuadVisualRangeAnim::Chaos, 0..1),
+                (QuadVisualRangeAnim::Chaos, 2..4),
+                (QuadVisualRangeAnim::Chaos, 6..7),
+            ]
+        );
+
+        // the vertex buffer only contains the valid quads, in order
+        let quad_size = std::mem::size_of::<TmpQuadTextured>();
+        let mem = layer.mem.as_ref().unwrap().as_slice();
+        assert_eq!(mem.len(), 4 * quad_size);
+        let all_mem = all.mem.as_ref().unwrap().as_slice();
+        for (buffer_index, quad_index) in [0, 2, 3, 6].into_iter().enumerate() {
+            assert_eq!(
+                mem[buffer_index * quad_size..(buffer_index + 1) * quad_size],
+                all_mem[quad_index * quad_size..(quad_index + 1) * quad_size]
+            );
+        }
+
+        // a layer of only degenerate quads is empty
+        assert!(matches!(
+            ClientMapBuffered::upload_quad_layer_buffer(
+                &attr,
+                &[Quad::default(); 3],
+                0,
+                0,
+                &graphics_mt,
+                QuadLayerUploadOptions {
+                    skip_degenerate_quads: true,
+                    ..Default::default()
+                }
+            ),
+            Err(LayerUploadError::Empty)
+        ));
+    }
+
+    #[test]
+    fn failed_layer_uploads_are_reported() {
+        let backend_handle = GraphicsBackendHandle::new(Rc::new(NullBackend::default()));
+        let shader_storage_handle = GraphicsShaderStorageHandle::new(backend_handle.clone());
+        let buffer_object_handle = GraphicsBufferObjectHandle::new(backend_handle.clone());
+        let graphics_mt = GraphicsMultiThreaded::new(Arc::new(TinyBackendMt { max_size: 0 }));
+
+        let mut builder = ClientMapBufferedBuilder::new(
+            &backend_handle,
+            &shader_storage_handle,
+            &buffer_object_handle,
+            upload_test_map(&graphics_mt),
+        )
+        .unwrap();
+        assert!(builder.upload_layers(usize::MAX).is_finished());
+
+        // quad layers without quads are empty, which is not a failure
+        let missing_layers: Vec<_> = builder
+            .physics
+            .missing_layers
+            .iter()
+            .chain(builder.background.missing_layers.iter())
+            .chain(builder.foreground.missing_layers.iter())
+            .collect();
+        assert_eq!(
+            missing_layers
+                .iter()
+                .map(|missing_layer| missing_layer.location)
+                .collect::<Vec<_>>(),
+            [
+                MapLayerLocation::Physics { layer_index: 0 },
+                MapLayerLocation::Background {
+                    group_index: 0,
+                    layer_index: 0
+                },
+                MapLayerLocation::Background {
+                    group_index: 2,
+                    layer_index: 1
+                },
+                MapLayerLocation::Foreground {
+                    group_index: 0,
+                    layer_index: 1
+                },
+            ]
+        );
+        assert!(missing_layers.iter().all(|missing_layer| matches!(
+            missing_layer.err,
+            LayerUploadError::AllocationFailed { .. }
+        )));
+        // the failed layers are not rendered
+        assert!(builder.physics.render_infos.is_empty());
+        assert!(builder.background.render_layers().is_empty());
+        assert!(builder.foreground.render_layers().is_empty());
+    }
+
+    #[test]
+    fn switch_delay_overlay_region_update() {
+        let graphics_mt = GraphicsMultiThreaded::new(Arc::new(VecBackendMt));
+        let backend_handle = GraphicsBackendHandle::new(Rc::new(NullBackend::default()));
+        let shader_storage_handle = GraphicsShaderStorageHandle::new(backend_handle.clone());
+        let buffer_object_handle = GraphicsBufferObjectHandle::new(backend_handle.clone());
+        let tp = Arc::new(
+            rayon::ThreadPoolBuilder::new()
+                .num_threads(1)
+                .build()
+                .unwrap(),
+        );
+
+        let width = NonZeroU16MinusOne::new(4).unwrap();
+        let height = NonZeroU16MinusOne::new(3).unwrap();
+        let tiles: Vec<SwitchTile> = (0..12)
+            .map(|i| SwitchTile {
+                base: TileBase {
+                    index: TILE_SWITCHTIMEDOPEN,
+                    flags: TileFlags::empty(),
+                },
+                number: 1,
+                delay: i + 1,
+            })
+            .collect();
+
+        let upload = |tiles: &[SwitchTile]| {
+            ClientMapBuffered::upload_physics_layer(
+                &graphics_mt,
+                width,
+                height,
+                MapTileLayerPhysicsTilesRef::Switch(tiles),
+                0,
+                TileLayerUploadOptions {
+                    ignore_tile_index_and_is_textured_check: true,
+                    ..Default::default()
+                },
+            )
+        };
+        let delay_overlay = |layer: &MapBufferPhysicsTileLayer| {
+            let (_, overlay) = layer
+                .overlays
+                .iter()
+                .find(|(ty, _)| *ty == MapRenderTextOverlayType::Top)
+                .unwrap();
+            overlay
+                .shader_storage_mem
+                .as_ref()
+                .unwrap()
+                .as_slice()
+                .to_vec()
+        };
+
+        let uploaded = upload(&tiles);
+        let mut delay_buffer = delay_overlay(&uploaded);
+        let visuals = ClientMapBuffered::finish_upload_physics_tile_layer(
+            &shader_storage_handle,
+            &buffer_object_handle,
+            &backend_handle,
+            uploaded,
+        );
+        let delay_shader_storage = visuals
+            .overlays
+            .iter()
+            .find(|overlay| overlay.ty == MapRenderTextOverlayType::Top)
+            .and_then(|overlay| overlay.visuals.obj.shader_storage.as_ref())
+            .unwrap()
+            .get_index_unsafe();
+        let mut layer = MapLayerPhysicsSkeleton::Switch(MapLayerSwitchPhysicsSkeleton {
+            layer: MapLayerTilePhysicsSwitch {
+                base: MapLayerTilePhysicsBase { tiles },
+                switch_names: Default::default(),
+            },
+            user: visuals,
+        });
+        // only the commands of the update are of interest
+        backend_handle.backend_cmds.take();
+
+        // change the delays of a 2x2 region
+        let MapLayerPhysicsSkeleton::Switch(switch_layer) = &mut layer else {
+            unreachable!()
+        };
+        for (x, y) in [(1, 1), (2, 1), (1, 2), (2, 2)] {
+            switch_layer.layer.base.tiles[y * 4 + x].delay = 100;
+        }
+        ClientMapBuffered::update_physics_layer(
+            &tp,
+            &graphics_mt,
+            &shader_storage_handle,
+            &buffer_object_handle,
+            &backend_handle,
+            width,
+            height,
+            &mut layer,
+            1,
+            1,
+            NonZeroU16MinusOne::new(2).unwrap(),
+            NonZeroU16MinusOne::new(2).unwrap(),
+        );
+
+        let mut delay_overlay_updated = false;
+        for cmd in backend_handle.backend_cmds.take() {
+            let AllCommands::Misc(CommandsMisc::UpdateShaderStorage(cmd)) = cmd else {
+                continue;
+            };
+            if cmd.shader_storage_index != delay_shader_storage {
+                continue;
+            }
+            delay_overlay_updated = true;
+            for region in cmd.update_regions {
+                delay_buffer[region.dst_offset..region.dst_offset + region.size].copy_from_slice(
+                    &cmd.update_data[region.src_offset..region.src_offset + region.size],
+                );
+            }
+        }
+        assert!(delay_overlay_updated);
+
+        let MapLayerPhysicsSkeleton::Switch(switch_layer) = &layer else {
+            unreachable!()
+        };
+        assert_eq!(
+            delay_buffer,
+            delay_overlay(&upload(&switch_layer.layer.base.tiles))
+        );
+    }
+
+    #[test]
+    fn switch_overlays_are_created_by_region_update() {
+        let graphics_mt = GraphicsMultiThreaded::new(Arc::new(VecBackendMt));
+        let backend_handle = GraphicsBackendHandle::new(Rc::new(NullBackend::default()));
+        let shader_storage_handle = GraphicsShaderStorageHandle::new(backend_handle.clone());
+        let buffer_object_handle = GraphicsBufferObjectHandle::new(backend_handle.clone());
+        let tp = Arc::new(
+            rayon::ThreadPoolBuilder::new()
+                .num_threads(1)
+                .build()
+                .unwrap(),
+        );
+
+        let width = NonZeroU16MinusOne::new(4).unwrap();
+        let height = NonZeroU16MinusOne::new(3).unwrap();
+        let mut tiles = vec![SwitchTile::default(); 12];
+
+        let upload = |tiles: &[SwitchTile]| {
+            ClientMapBuffered::upload_physics_layer(
+                &graphics_mt,
+                width,
+                height,
+                MapTileLayerPhysicsTilesRef::Switch(tiles),
+                0,
+                TileLayerUploadOptions {
+                    ignore_tile_index_and_is_textured_check: true,
+                    ..Default::default()
+                },
+            )
+        };
+        let mut visuals = ClientMapBuffered::finish_upload_physics_tile_layer(
+            &shader_storage_handle,
+            &buffer_object_handle,
+            &backend_handle,
+            upload(&tiles),
+        );
+        // like a layer, whose overlays had no tiles when it was uploaded
+        visuals.overlays.clear();
+        backend_handle.backend_cmds.take();
+
+        // the first switch tile gets a number & a delay
+        tiles[5] = SwitchTile {
+            base: TileBase {
+                index: TILE_SWITCHTIMEDOPEN,
+                flags: TileFlags::empty(),
+            },
+            number: 1,
+            delay: 3,
+        };
+        let mut layer = MapLayerPhysicsSkeleton::Switch(MapLayerSwitchPhysicsSkeleton {
+            layer: MapLayerTilePhysicsSwitch {
+                base: MapLayerTilePhysicsBase { tiles },
+                switch_names: Default::default(),
+            },
+            user: visuals,
+        });
+        ClientMapBuffered::update_physics_layer(
+            &tp,
+            &graphics_mt,
+            &shader_storage_handle,
+            &buffer_object_handle,
+            &backend_handle,
+            width,
+            height,
+            &mut layer,
+            1,
+            1,
+            NonZeroU16MinusOne::new(1).unwrap(),
+            NonZeroU16MinusOne::new(1).unwrap(),
+        );
+
+        let mut created_shader_storages: HashMap<u128, Vec<u8>> = backend_handle
+            .backend_cmds
+            .take()
+            .into_iter()
+            .filter_map(|cmd| match cmd {
+                AllCommands::Misc(CommandsMisc::CreateShaderStorage(cmd)) => Some((
+                    cmd.shader_storage_index,
+                    cmd.upload_data.as_slice().to_vec(),
+                )),
+                _ => None,
+            })
+            .collect();
+
+        let MapLayerPhysicsSkeleton::Switch(switch_layer) = &layer else {
+            unreachable!()
+        };
+        let expected = upload(&switch_layer.layer.base.tiles);
+        // both the number & the delay overlay exist and contain the new tile
+        for ty in [
+            MapRenderTextOverlayType::Bottom,
+            MapRenderTextOverlayType::Top,
+        ] {
+            let overlay = switch_layer
+                .user
+                .overlays
+                .iter()
+                .find(|overlay| overlay.ty == ty)
+                .expect("the overlay was not created");
+            let shader_storage = overlay
+                .visuals
+                .obj
+                .shader_storage
+                .as_ref()
+                .unwrap()
+                .get_index_unsafe();
+            let (_, expected) = expected
+                .overlays
+                .iter()
+                .find(|(expected_ty, _)| *expected_ty == ty)
+                .unwrap();
+            assert_eq!(
+                created_shader_storages.remove(&shader_storage),
+                Some(
+                    expected
+                        .shader_storage_mem
+                        .as_ref()
+                        .unwrap()
+                        .as_slice()
+                        .to_vec()
+                )
+            );
+        }
+    }
+
+    #[test]
+    fn evicted_layers_reupload_the_same_buffers() {
+        let graphics_mt = GraphicsMultiThreaded::new(Arc::new(VecBackendMt));
+        let backend_handle = GraphicsBackendHandle::new(Rc::new(NullBackend::default()));
+        let shader_storage_handle = GraphicsShaderStorageHandle::new(backend_handle.clone());
+        let buffer_object_handle = GraphicsBufferObjectHandle::new(backend_handle.clone());
+
+        let created_buffers = || {
+            backend_handle
+                .backend_cmds
+                .take()
+                .into_iter()
+                .filter_map(|cmd| match cmd {
+                    AllCommands::Misc(CommandsMisc::CreateBufferObject(cmd)) => {
+                        Some(cmd.upload_data.as_slice().to_vec())
+                    }
+                    AllCommands::Misc(CommandsMisc::CreateShaderStorage(cmd)) => {
+                        Some(cmd.upload_data.as_slice().to_vec())
+                    }
+                    _ => None,
+                })
+                .collect::<Vec<_>>()
+        };
+
+        let MapVisualLayer::Tile(tile_layer) = tile_layer("tiles") else {
+            unreachable!()
+        };
+        let mut tile_layer = tile_layer.layer;
+        tile_layer.attr.width = NonZeroU16MinusOne::new(3).unwrap();
+        tile_layer.attr.height = NonZeroU16MinusOne::new(2).unwrap();
+        tile_layer.tiles = (0..6)
+            .map(|i| TileBase {
+                index: i,
+                flags: TileFlags::empty(),
+            })
+            .collect();
+        let mut tile_visuals = ClientMapBuffered::finish_upload_tile_layer(
+            &shader_storage_handle,
+            &buffer_object_handle,
+            &backend_handle,
+            ClientMapBuffered::upload_design_tile_layer(
+                &graphics_mt,
+                &tile_layer.tiles,
+                tile_layer.attr.width,
+                tile_layer.attr.height,
+                tile_layer.attr.image_array.is_some(),
+                0,
+                0,
+                TileLayerUploadOptions::default(),
+            ),
+        );
+        let uploaded = created_buffers();
+        assert!(!uploaded.is_empty());
+
+        tile_visuals.evict();
+        assert!(tile_visuals.is_evicted());
+        assert!(created_buffers().is_empty());
+        tile_visuals
+            .reupload(
+                &graphics_mt,
+                &shader_storage_handle,
+                &buffer_object_handle,
+                &backend_handle,
+                &tile_layer,
+            )
+            .unwrap();
+        assert!(!tile_visuals.is_evicted());
+        assert_eq!(created_buffers(), uploaded);
+
+        let quad_layer = MapLayerQuad {
+            attr: MapLayerQuadsAttrs {
+                image: None,
+                high_detail: false,
+            },
+            quads: vec![Quad::default(); 3],
+            name: "quads".into(),
+        };
+        let mut quad_visuals = ClientMapBuffered::finish_upload_quad_layer(
+            &buffer_object_handle,
+            &backend_handle,
+            ClientMapBuffered::upload_design_quad_layer(
+                &graphics_mt,
+                &quad_layer.attr,
+                &quad_layer.quads,
+                0,
+                0,
+                QuadLayerUploadOptions {
+                    instance_repeated_quads: true,
+                    ..Default::default()
+                },
+            ),
+        );
+        let uploaded = created_buffers();
+        assert!(!uploaded.is_empty());
+        let draw_ranges = quad_visuals.draw_ranges.len();
+
+        quad_visuals.evict();
+        assert!(quad_visuals.is_evicted());
+        quad_visuals
+            .reupload(
+                &graphics_mt,
+                &buffer_object_handle,
+                &backend_handle,
+                &quad_layer,
+                QuadLayerUploadOptions {
+                    instance_repeated_quads: true,
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        assert!(!quad_visuals.is_evicted());
+        assert_eq!(quad_visuals.draw_ranges.len(), draw_ranges);
+        assert_eq!(created_buffers(), uploaded);
+    }
+
+    #[test]
+    fn corrupted_buffer_uploads_are_detected() {
+        let backend = Rc::new(NullBackend::default());
+        let backend_handle = GraphicsBackendHandle::new(backend.clone());
+        let shader_storage_handle = GraphicsShaderStorageHandle::new(backend_handle.clone());
+        let buffer_object_handle = GraphicsBufferObjectHandle::new(backend_handle.clone());
+        let graphics_mt = GraphicsMultiThreaded::new(Arc::new(VecBackendMt));
+        let sound = SoundManager::new(Rc::new(RecordingSoundBackend::default())).unwrap();
+
+        let load = || {
+            let builder = ClientMapBufferedBuilder::new(
+                &backend_handle,
+                &shader_storage_handle,
+                &buffer_object_handle,
+                upload_test_map(&graphics_mt),
+            )
+            .unwrap()
+            .verify_uploads();
+            let scene = sound.scene_handle.create(Default::default());
+            let listener = scene.sound_listener_handle.create(vec2::new(0.0, 0.0));
+            builder.finish(Vec::new(), Vec::new(), scene, listener, Vec::new())
+        };
+
+        // an intact upload has no mismatches
+        let mut map = load();
+        assert!(map.upload_verification.pending_samples() > 0);
+        backend.execute(backend_handle.backend_cmds.take());
+        assert!(map.verify_uploads().is_empty());
+        assert_eq!(map.upload_verification.pending_samples(), 0);
+        drop(map);
+
+        // corrupt the first byte of a buffer, like a wrong offset would
+        let corrupt_first_buffer = |buffers: &RefCell<HashMap<u128, Vec<u8>>>| {
+            let mut buffers = buffers.borrow_mut();
+            let first = *buffers.keys().min().unwrap();
+            buffers.get_mut(&first).unwrap()[0] ^= 0xFF;
+        };
+        let mut map = load();
+        backend.execute(backend_handle.backend_cmds.take());
+        corrupt_first_buffer(&backend.buffer_objects);
+        let mismatches = map.verify_uploads();
+        assert_eq!(mismatches.len(), 1);
+        assert_eq!(mismatches[0].offset, 0);
+        drop(map);
+
+        // the tiles of tile layers are uploaded as shader storages
+        let mut map = load();
+        backend.execute(backend_handle.backend_cmds.take());
+        corrupt_first_buffer(&backend.shader_storages);
+        let mismatches = map.verify_uploads();
+        assert_eq!(mismatches.len(), 1);
+        assert_eq!(mismatches[0].offset, 0);
+    }
+
+    fn linear_point<T, const CHANNELS: usize>(millis: u64, value: T) -> AnimPoint<T, CHANNELS> {
+        AnimPoint {
+            time: Duration::from_millis(millis),
+            curve_type: AnimPointCurveType::Linear,
+            value,
+        }
+    }
+
+    fn pos(x: f64, y: f64, rot: f64) -> fvec3 {
+        fvec3::new(
+            ffixed::from_num(x),
+            ffixed::from_num(y),
+            ffixed::from_num(rot),
+        )
+    }
+
+    /// A pos anim from (0, 0, 0) to (8, -4, 360) over one second,
+    /// a synchronized copy of it, a color anim from black to white
+    /// and animations without any points.
+    fn test_animations() -> MapVisualAnimations {
+        let pos_points: Vec<AnimPointPos> = vec![
+            linear_point(0, pos(0.0, 0.0, 0.0)),
+            linear_point(1000, pos(8.0, -4.0, 360.0)),
+        ];
+        let pos_anim = |points, synchronized| MapVisualPosAnimation {
+            def: AnimBase {
+                points,
+                synchronized,
+                name: Default::default(),
+            },
+            user: (),
+        };
+        let color_anim = |points| MapVisualColorAnimation {
+            def: AnimBase {
+                points,
+                synchronized: false,
+                name: Default::default(),
+            },
+            user: (),
+        };
+        MapVisualAnimations {
+            pos: vec![
+                pos_anim(pos_points.clone(), false),
+                pos_anim(pos_points, true),
+                pos_anim(Vec::new(), false),
+            ],
+            color: vec![
+                color_anim(vec![
+                    linear_point(0, nfvec4::default()),
+                    linear_point(
+                        1000,
+                        nfvec4::new(
+                            nffixed::from_num(1),
+                            nffixed::from_num(1),
+                            nffixed::from_num(1),
+                            nffixed::from_num(1),
+                        ),
+                    ),
+                ]),
+                color_anim(Vec::new()),
+            ],
+            sound: Vec::new(),
+            user: (),
+        }
+    }
+
+    #[test]
+    fn anims_are_interpolated() {
+        let animations = test_animations();
+        let eval_pos = |anim, offset_millis, millis, anim_millis| {
+            eval_pos_anim(
+                &animations,
+                anim,
+                &time::Duration::milliseconds(offset_millis),
+                &Duration::from_millis(millis),
+                &Duration::from_millis(anim_millis),
+                false,
+            )
+        };
+
+        assert_eq!(eval_pos(0, 0, 0, 0), Some(pos(0.0, 0.0, 0.0)));
+        assert_eq!(eval_pos(0, 0, 250, 0), Some(pos(2.0, -1.0, 90.0)));
+        assert_eq!(eval_pos(0, 0, 500, 0), Some(pos(4.0, -2.0, 180.0)));
+        // the offset is added to the time
+        assert_eq!(eval_pos(0, 250, 500, 0), Some(pos(6.0, -3.0, 270.0)));
+        // synchronized anims use the anim time
+        assert_eq!(eval_pos(1, 0, 500, 250), Some(pos(2.0, -1.0, 90.0)));
+
+        let color = eval_color_anim(
+            &animations,
+            0,
+            &time::Duration::ZERO,
+            &Duration::from_millis(500),
+            &Duration::ZERO,
+            false,
+        )
+        .unwrap();
+        let half = nffixed::from_num(0.5);
+        assert_eq!(color, nfvec4::new(half, half, half, half));
+    }
+
+    #[test]
+    fn editing_an_anim_reports_the_quad_layers_using_it() {
+        let mut animations = test_animations();
+        let anim_layer = |pos_anims: Vec<(usize, time::Duration)>| {
+            let mut layer = quad_layer("quads");
+            let MapVisualLayer::Quad(quad_layer) = &mut layer else {
+                unreachable!()
+            };
+            quad_layer.user.pos_anims = pos_anims;
+            layer
+        };
+        let group = |layers| MapVisualGroup {
+            attr: MapGroupAttr::default(),
+            layers,
+            name: "group".into(),
+            user: (),
+        };
+        let groups = MapVisualGroups {
+            physics: MapVisualPhysicsGroup {
+                attr: MapGroupPhysicsAttr {
+                    width: NonZeroU16MinusOne::new(1).unwrap(),
+                    height: NonZeroU16MinusOne::new(1).unwrap(),
+                },
+                layers: Vec::new(),
+                user: (),
+            },
+            background: vec![group(vec![
+                tile_layer("tiles"),
+                anim_layer(vec![(0, time::Duration::ZERO)]),
+                anim_layer(vec![(1, time::Duration::ZERO)]),
+            ])],
+            foreground: vec![group(vec![anim_layer(vec![
+                (1, time::Duration::ZERO),
+                (0, time::Duration::milliseconds(500)),
+            ])])],
+            user: (),
+        };
+
+        let points = vec![
+            linear_point(0, pos(1.0, 1.0, 0.0)),
+            linear_point(1000, pos(1.0, 1.0, 0.0)),
+        ];
+        assert_eq!(
+            set_pos_anim_points(&mut animations, &groups, 0, points),
+            Some(vec![
+                MapLayerLocation::Background {
+                    group_index: 0,
+                    layer_index: 1,
+                },
+                MapLayerLocation::Foreground {
+                    group_index: 0,
+                    layer_index: 0,
+                },
+            ])
+        );
+        let time = Duration::from_millis(500);
+        assert_eq!(
+            eval_pos_anim(&animations, 0, &Default::default(), &time, &time, false),
+            Some(pos(1.0, 1.0, 0.0))
+        );
+
+        // no quad uses a color anim
+        assert_eq!(
+            set_color_anim_points(&mut animations, &groups, 0, Vec::new()),
+            Some(Vec::new())
+        );
+        assert_eq!(
+            set_pos_anim_points(&mut animations, &groups, 3, Vec::new()),
+            None
+        );
+    }
+
+    #[test]
+    fn anims_wrap_around() {
+        let animations = test_animations();
+        let eval_pos = |offset_millis, millis, include_last_anim_point| {
+            eval_pos_anim(
+                &animations,
+                0,
+                &time::Duration::milliseconds(offset_millis),
+                &Duration::from_millis(millis),
+                &Duration::ZERO,
+                include_last_anim_point,
+            )
+        };
+
+        assert_eq!(eval_pos(0, 1250, false), Some(pos(2.0, -1.0, 90.0)));
+        assert_eq!(eval_pos(0, 3750, false), Some(pos(6.0, -3.0, 270.0)));
+        assert_eq!(eval_pos(1000, 250, false), Some(pos(2.0, -1.0, 90.0)));
+        // the last point is only reached if it is included
+        assert_eq!(eval_pos(0, 1000, false), Some(pos(0.0, 0.0, 0.0)));
+        assert_eq!(eval_pos(0, 1000, true), Some(pos(8.0, -4.0, 360.0)));
+    }
+
+    #[test]
+    fn empty_and_missing_anims() {
+        let animations = test_animations();
+        let time = Duration::from_millis(500);
+
+        // anims without points evaluate to the default value
+        assert_eq!(
+            eval_pos_anim(&animations, 2, &Default::default(), &time, &time, false),
+            Some(fvec3::default())
+        );
+        assert_eq!(
+            eval_color_anim(&animations, 1, &Default::default(), &time, &time, false),
+            Some(nfvec4::default())
+        );
+
+        assert_eq!(
+            eval_pos_anim(&animations, 3, &Default::default(), &time, &time, false),
+            None
+        );
+        assert_eq!(
+            eval_color_anim(&animations, 2, &Default::default(), &time, &time, false),
+            None
+        );
+    }
+}
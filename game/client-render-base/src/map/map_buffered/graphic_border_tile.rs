@@ -8,6 +8,63 @@ use super::graphic_tile::tile_flags_to_uv;
 pub(super) type GraphicsBorderTilePos = vec2;
 pub(super) type GraphicsBorderTileTex = ubvec4;
 
+/// The size in bytes of an uploaded border tile.
+pub(super) const fn border_tile_size(is_textured: bool) -> usize {
+    std::mem::size_of::<GraphicsBorderTilePos>() * 4
+        + if is_textured {
+            std::mem::size_of::<GraphicsBorderTileTex>() * 4
+        } else {
+            0
+        }
+}
+
+/// The order of the border tiles inside the border buffer object of a tile layer,
+/// that was uploaded without skipping air tiles:
+/// the corners (top left, top right, bottom left, bottom right) first,
+/// followed by the top, bottom, left and right borders.
+#[derive(Debug, Clone, Copy)]
+pub(super) struct BorderTileLayout {
+    width: usize,
+    height: usize,
+}
+
+impl BorderTileLayout {
+    pub(super) const CORNER_TOP_LEFT: usize = 0;
+    pub(super) const CORNER_TOP_RIGHT: usize = 1;
+    pub(super) const CORNER_BOTTOM_LEFT: usize = 2;
+    pub(super) const CORNER_BOTTOM_RIGHT: usize = 3;
+    pub(super) const CORNER_COUNT: usize = 4;
+
+    pub(super) fn new(width: usize, height: usize) -> Self {
+        Self { width, height }
+    }
+
+    /// Tile offset of the top border tile in column `x`.
+    pub(super) fn top(&self, x: usize) -> usize {
+        Self::CORNER_COUNT + x
+    }
+
+    /// Tile offset of the bottom border tile in column `x`.
+    pub(super) fn bottom(&self, x: usize) -> usize {
+        Self::CORNER_COUNT + self.width + x
+    }
+
+    /// Tile offset of the left border tile in row `y`.
+    pub(super) fn left(&self, y: usize) -> usize {
+        Self::CORNER_COUNT + self.width * 2 + y
+    }
+
+    /// Tile offset of the right border tile in row `y`.
+    pub(super) fn right(&self, y: usize) -> usize {
+        Self::CORNER_COUNT + self.width * 2 + self.height + y
+    }
+
+    /// The total amount of border tiles, without the game layer's kill tile.
+    pub(super) fn tile_count(&self) -> usize {
+        Self::CORNER_COUNT + self.width * 2 + self.height * 2
+    }
+}
+
 #[repr(C)]
 #[derive(Default)]
 pub(super) struct GraphicBorderTile {
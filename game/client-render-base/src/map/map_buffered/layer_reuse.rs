@@ -0,0 +1,179 @@
+use std::collections::HashMap;
+
+use base::hash::{generate_hash_for, Hash};
+use map::map::groups::{
+    layers::{
+        design::{MapLayerQuad, MapLayerTile},
+        physics::MapLayerPhysicsRef,
+    },
+    MapGroupPhysicsAttr,
+};
+use serde::Serialize;
+
+use crate::map::map_with_visual::{MapVisualGroup, MapVisualLayer};
+
+use super::{
+    ClientMapBuffered, PhysicsTileLayerVisuals, QuadLayerVisuals, TileLayerBufferedVisualObjects,
+    TileLayerVisuals,
+};
+
+/// Hashes everything of a layer's source that ends up in its buffers.
+/// `None` if the source could not be serialized, such layers are never reused.
+fn content_hash<T: Serialize + ?Sized>(content: &T) -> Option<Hash> {
+    bincode::serde::encode_to_vec(content, bincode::config::standard())
+        .ok()
+        .map(|bytes| generate_hash_for(&bytes))
+}
+
+fn tile_layer_content_hash(layer: &MapLayerTile) -> Option<Hash> {
+    content_hash(&(
+        layer.attr.width,
+        layer.attr.height,
+        layer.attr.image_array.is_some(),
+        &layer.tiles,
+    ))
+}
+
+fn quad_layer_content_hash(layer: &MapLayerQuad) -> Option<Hash> {
+    content_hash(&(&layer.attr, &layer.quads))
+}
+
+fn physics_layer_content_hash(
+    attr: &MapGroupPhysicsAttr,
+    layer: MapLayerPhysicsRef<'_>,
+) -> Option<Hash> {
+    match layer {
+        MapLayerPhysicsRef::Arbitrary(layer) => content_hash(&(0u8, attr, layer)),
+        MapLayerPhysicsRef::Game(layer) => content_hash(&(1u8, attr, layer)),
+        MapLayerPhysicsRef::Front(layer) => content_hash(&(2u8, attr, layer)),
+        MapLayerPhysicsRef::Tele(layer) => content_hash(&(3u8, attr, layer)),
+        MapLayerPhysicsRef::Speedup(layer) => content_hash(&(4u8, attr, layer)),
+        MapLayerPhysicsRef::Switch(layer) => content_hash(&(5u8, attr, layer)),
+        MapLayerPhysicsRef::Tune(layer) => content_hash(&(6u8, attr, layer)),
+    }
+}
+
+/// Layers without buffers are either empty or their upload failed.
+/// Reusing a failed layer would hide that it's missing, so such layers
+/// are always uploaded again, which is cheap for empty layers anyway.
+fn has_buffers(obj: &TileLayerBufferedVisualObjects) -> bool {
+    obj.buffer_object.is_some() || obj.shader_storage.is_some()
+}
+
+/// The buffered layers of a previously loaded map by the hash of their source,
+/// so that layers that did not change are not uploaded again.
+#[derive(Debug, Default)]
+pub(super) struct MapBufferedLayerReuse {
+    tile_layers: HashMap<Hash, Vec<TileLayerVisuals>>,
+    quad_layers: HashMap<Hash, Vec<QuadLayerVisuals>>,
+    physics_layers: HashMap<Hash, Vec<PhysicsTileLayerVisuals>>,
+
+    /// How many layers were taken from the previous map.
+    pub(super) reused_layers: usize,
+}
+
+impl MapBufferedLayerReuse {
+    pub(super) fn new(map: &ClientMapBuffered) -> Self {
+        let mut reuse = Self::default();
+        let groups = &map.map_visual.groups;
+        let design_layers = groups
+            .background
+            .iter()
+            .chain(groups.foreground.iter())
+            .flat_map(|group: &MapVisualGroup| group.layers.iter());
+        for layer in design_layers {
+            match layer {
+                MapVisualLayer::Tile(layer) => {
+                    if !has_buffers(&layer.user.base.obj) {
+                        continue;
+                    }
+                    if let Some(hash) = tile_layer_content_hash(&layer.layer) {
+                        reuse
+                            .tile_layers
+                            .entry(hash)
+                            .or_default()
+                            .push(layer.user.clone());
+                    }
+                }
+                MapVisualLayer::Quad(layer) => {
+                    if layer.user.buffer_object_index.is_none() {
+                        continue;
+                    }
+                    if let Some(hash) = quad_layer_content_hash(&layer.layer) {
+                        reuse
+                            .quad_layers
+                            .entry(hash)
+                            .or_default()
+                            .push(layer.user.clone());
+                    }
+                }
+                MapVisualLayer::Sound(_) | MapVisualLayer::Abritrary(_) => {}
+            }
+        }
+        for layer in groups.physics.layers.iter() {
+            if !has_buffers(&layer.user().base.base.obj) {
+                continue;
+            }
+            if let Some(hash) = physics_layer_content_hash(&groups.physics.attr, layer.layer_ref())
+            {
+                reuse
+                    .physics_layers
+                    .entry(hash)
+                    .or_default()
+                    .push(layer.user().clone());
+            }
+        }
+        reuse
+    }
+
+    fn take<T>(
+        layers: &mut HashMap<Hash, Vec<T>>,
+        reused_layers: &mut usize,
+        hash: impl FnOnce() -> Option<Hash>,
+    ) -> Option<T> {
+        // don't hash the layer at all, if there is nothing to reuse
+        if layers.is_empty() {
+            return None;
+        }
+        let visuals = layers.get_mut(&hash()?)?.pop()?;
+        *reused_layers += 1;
+        Some(visuals)
+    }
+
+    /// The visuals of a previous layer with the same tiles, if any.
+    pub(super) fn take_tile_layer(&mut self, layer: &MapLayerTile) -> Option<TileLayerVisuals> {
+        Self::take(&mut self.tile_layers, &mut self.reused_layers, || {
+            tile_layer_content_hash(layer)
+        })
+        .map(|mut visuals| {
+            // a fresh upload is not tinted either
+            visuals.base.base.tint = None;
+            visuals
+        })
+    }
+
+    /// The visuals of a previous layer with the same quads, if any.
+    pub(super) fn take_quad_layer(&mut self, layer: &MapLayerQuad) -> Option<QuadLayerVisuals> {
+        Self::take(&mut self.quad_layers, &mut self.reused_layers, || {
+            quad_layer_content_hash(layer)
+        })
+    }
+
+    /// The visuals of a previous physics layer with the same tiles, if any.
+    pub(super) fn take_physics_layer(
+        &mut self,
+        attr: &MapGroupPhysicsAttr,
+        layer: MapLayerPhysicsRef<'_>,
+    ) -> Option<PhysicsTileLayerVisuals> {
+        Self::take(&mut self.physics_layers, &mut self.reused_layers, || {
+            physics_layer_content_hash(attr, layer)
+        })
+        .map(|mut visuals| {
+            visuals.base.base.base.tint = None;
+            for overlay in visuals.overlays.iter_mut() {
+                overlay.visuals.base.tint = None;
+            }
+            visuals
+        })
+    }
+}
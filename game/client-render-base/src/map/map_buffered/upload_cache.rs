@@ -0,0 +1,398 @@
+use std::num::NonZeroUsize;
+
+use anyhow::anyhow;
+use base::hash::{fmt_hash, Hash};
+use graphics::graphics_mt::GraphicsMultiThreaded;
+use graphics_types::types::{GraphicsBackendMemory, GraphicsMemoryAllocationType};
+use map::map::{
+    groups::{layers::design::MapLayer, MapGroup},
+    Map,
+};
+use serde::{Deserialize, Serialize};
+
+use super::{
+    ClientMapBufferQuadLayer, ClientMapBufferUploadData, ClientMapBuffered, LayerUploadError,
+    MapBufferPhysicsTileLayer, MapBufferTileLayer, MapBufferTileLayerBase, MapPhysicsRenderInfo,
+    MapRenderInfo, MapRenderTextOverlayType, QuadVisualExtra, TileLayerVisualsBase,
+};
+
+/// The version of the cache files.
+///
+/// Must be bumped whenever the serialized layout of [`ClientMapBufferUploadCache`]
+/// (or any type it contains) changes, else old cache files are decoded into garbage.
+pub const VERSION: usize = 20261018;
+
+#[derive(Debug, Serialize, Deserialize)]
+struct CachedTileLayerBase {
+    mem: Option<Vec<u8>>,
+    shader_storage_mem: Option<Vec<u8>>,
+    quad_count_for_indices: u64,
+    visuals: TileLayerVisualsBase,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct CachedTileLayer {
+    base: CachedTileLayerBase,
+    render_info: MapRenderInfo,
+    tile_index: Option<CachedTileLayerBase>,
+    tile_flag: Option<CachedTileLayerBase>,
+    upload_err: Option<LayerUploadError>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct CachedPhysicsTileLayer {
+    base: CachedTileLayerBase,
+    tile_index: Option<CachedTileLayerBase>,
+    tile_flag: Option<CachedTileLayerBase>,
+    render_info: MapPhysicsRenderInfo,
+    overlays: Vec<(MapRenderTextOverlayType, CachedTileLayerBase)>,
+    upload_err: Option<LayerUploadError>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct CachedQuadLayer {
+    mem: Option<Vec<u8>>,
+    instanced_mem: Option<Vec<u8>>,
+    quad_count_for_indices: u64,
+    render_info: MapRenderInfo,
+    skipped_quads: usize,
+    extra: QuadVisualExtra,
+    upload_err: Option<LayerUploadError>,
+}
+
+/// The prepared upload buffers of a map, see [`ClientMapBufferUploadData`],
+/// in a form that can be written to a cache file.
+///
+/// Loading it skips all the CPU work of [`ClientMapBuffered::prepare_upload`].
+/// The buffers are stored in the byte order of the machine that created them,
+/// so the cache must never leave this machine.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ClientMapBufferUploadCache {
+    /// The blake3 hash of the map file the buffers were prepared for.
+    map_hash: Hash,
+
+    bg_tile_layer_uploads: Vec<CachedTileLayer>,
+    fg_tile_layer_uploads: Vec<CachedTileLayer>,
+    physics_tile_layer_uploads: Vec<CachedPhysicsTileLayer>,
+    bg_quad_layer_uploads: Vec<CachedQuadLayer>,
+    fg_quad_layer_uploads: Vec<CachedQuadLayer>,
+}
+
+impl ClientMapBufferUploadCache {
+    fn cache_mem(mem: &Option<GraphicsBackendMemory>) -> Option<Vec<u8>> {
+        mem.as_ref().map(|mem| mem.as_slice().to_vec())
+    }
+
+    fn cache_tile_layer_base(layer: &MapBufferTileLayerBase) -> CachedTileLayerBase {
+        CachedTileLayerBase {
+            mem: Self::cache_mem(&layer.mem),
+            shader_storage_mem: Self::cache_mem(&layer.shader_storage_mem),
+            quad_count_for_indices: layer.quad_count_for_indices,
+            visuals: layer.visuals.clone(),
+        }
+    }
+
+    fn cache_tile_layer(layer: &MapBufferTileLayer) -> CachedTileLayer {
+        CachedTileLayer {
+            base: Self::cache_tile_layer_base(&layer.base),
+            render_info: layer.render_info,
+            tile_index: layer.tile_index.as_ref().map(Self::cache_tile_layer_base),
+            tile_flag: layer.tile_flag.as_ref().map(Self::cache_tile_layer_base),
+            upload_err: layer.upload_err,
+        }
+    }
+
+    fn cache_physics_tile_layer(layer: &MapBufferPhysicsTileLayer) -> CachedPhysicsTileLayer {
+        CachedPhysicsTileLayer {
+            base: Self::cache_tile_layer_base(&layer.base),
+            tile_index: layer.tile_index.as_ref().map(Self::cache_tile_layer_base),
+            tile_flag: layer.tile_flag.as_ref().map(Self::cache_tile_layer_base),
+            render_info: layer.render_info.clone(),
+            overlays: layer
+                .overlays
+                .iter()
+                .map(|(ty, overlay)| (*ty, Self::cache_tile_layer_base(overlay)))
+                .collect(),
+            upload_err: layer.upload_err,
+        }
+    }
+
+    fn cache_quad_layer(layer: &ClientMapBufferQuadLayer) -> CachedQuadLayer {
+        CachedQuadLayer {
+            mem: Self::cache_mem(&layer.mem),
+            instanced_mem: Self::cache_mem(&layer.instanced_mem),
+            quad_count_for_indices: layer.quad_count_for_indices,
+            render_info: layer.render_info,
+            skipped_quads: layer.skipped_quads,
+            extra: layer.extra.clone(),
+            upload_err: layer.upload_err,
+        }
+    }
+
+    /// Creates the cache of the upload data of the map with the given blake3 hash.
+    ///
+    /// Fails if any layer failed to allocate its memory, since that
+    /// depends on the graphics implementation and not on the map.
+    pub fn new(upload_data: &ClientMapBufferUploadData, map_hash: Hash) -> anyhow::Result<Self> {
+        let upload_errs = upload_data
+            .bg_tile_layer_uploads
+            .iter()
+            .chain(upload_data.fg_tile_layer_uploads.iter())
+            .map(|layer| layer.upload_err)
+            .chain(
+                upload_data
+                    .physics_tile_layer_uploads
+                    .iter()
+                    .map(|layer| layer.upload_err),
+            )
+            .chain(
+                upload_data
+                    .bg_quad_layer_uploads
+                    .iter()
+                    .chain(upload_data.fg_quad_layer_uploads.iter())
+                    .map(|layer| layer.upload_err),
+            );
+        for err in upload_errs {
+            if let Some(err @ LayerUploadError::AllocationFailed { .. }) = err {
+                return Err(anyhow!("a layer of the map can not be cached: {err}"));
+            }
+        }
+
+        Ok(Self {
+            map_hash,
+            bg_tile_layer_uploads: upload_data
+                .bg_tile_layer_uploads
+                .iter()
+                .map(Self::cache_tile_layer)
+                .collect(),
+            fg_tile_layer_uploads: upload_data
+                .fg_tile_layer_uploads
+                .iter()
+                .map(Self::cache_tile_layer)
+                .collect(),
+            physics_tile_layer_uploads: upload_data
+                .physics_tile_layer_uploads
+                .iter()
+                .map(Self::cache_physics_tile_layer)
+                .collect(),
+            bg_quad_layer_uploads: upload_data
+                .bg_quad_layer_uploads
+                .iter()
+                .map(Self::cache_quad_layer)
+                .collect(),
+            fg_quad_layer_uploads: upload_data
+                .fg_quad_layer_uploads
+                .iter()
+                .map(Self::cache_quad_layer)
+                .collect(),
+        })
+    }
+
+    pub fn encode(&self) -> anyhow::Result<Vec<u8>> {
+        Ok(bincode::serde::encode_to_vec(
+            self,
+            bincode::config::standard(),
+        )?)
+    }
+
+    /// Decodes a cache that was created by [`Self::encode`].
+    ///
+    /// Fails if the cache was created for another map than the one with the
+    /// given blake3 hash, or if its layers don't match the layers of `map`.
+    pub fn decode(file: &[u8], map_hash: &Hash, map: &Map) -> anyhow::Result<Self> {
+        let (cache, _) =
+            bincode::serde::decode_from_slice::<Self, _>(file, bincode::config::standard())?;
+        anyhow::ensure!(
+            cache.map_hash == *map_hash,
+            "the cache belongs to the map {} and not to {}",
+            fmt_hash(&cache.map_hash),
+            fmt_hash(map_hash)
+        );
+
+        let count_layers = |groups: &[MapGroup]| {
+            groups.iter().flat_map(|group| group.layers.iter()).fold(
+                (0, 0),
+                |(tiles, quads), layer| match layer {
+                    MapLayer::Tile(_) => (tiles + 1, quads),
+                    MapLayer::Quad(_) => (tiles, quads + 1),
+                    _ => (tiles, quads),
+                },
+            )
+        };
+        let layers_match = count_layers(&map.groups.background)
+            == (
+                cache.bg_tile_layer_uploads.len(),
+                cache.bg_quad_layer_uploads.len(),
+            )
+            && count_layers(&map.groups.foreground)
+                == (
+                    cache.fg_tile_layer_uploads.len(),
+                    cache.fg_quad_layer_uploads.len(),
+                )
+            && map.groups.physics.layers.len() == cache.physics_tile_layer_uploads.len();
+        anyhow::ensure!(layers_match, "the cached layers don't match the map");
+
+        Ok(cache)
+    }
+
+    fn upload_mem(
+        graphics_mt: &GraphicsMultiThreaded,
+        mem: Option<Vec<u8>>,
+        alloc_type: impl FnOnce(NonZeroUsize) -> GraphicsMemoryAllocationType,
+    ) -> Result<Option<GraphicsBackendMemory>, LayerUploadError> {
+        let Some((data, required_size)) =
+            mem.and_then(|data| NonZeroUsize::new(data.len()).map(|size| (data, size)))
+        else {
+            return Ok(None);
+        };
+        let mut mem =
+            ClientMapBuffered::alloc_layer_mem(graphics_mt, alloc_type(required_size), data.len())?;
+        mem.as_mut_slice()[..data.len()].copy_from_slice(&data);
+        if let Err(err) = graphics_mt.try_flush_mem(&mut mem, false) {
+            // Ignore the error, but log it.
+            log::debug!("err while flushing memory: {err}");
+        }
+        Ok(Some(mem))
+    }
+
+    fn upload_tile_layer_base(
+        graphics_mt: &GraphicsMultiThreaded,
+        layer: CachedTileLayerBase,
+    ) -> Result<MapBufferTileLayerBase, LayerUploadError> {
+        Ok(MapBufferTileLayerBase {
+            mem: Self::upload_mem(graphics_mt, layer.mem, |required_size| {
+                GraphicsMemoryAllocationType::VertexBuffer { required_size }
+            })?,
+            shader_storage_mem: Self::upload_mem(
+                graphics_mt,
+                layer.shader_storage_mem,
+                |required_size| GraphicsMemoryAllocationType::ShaderStorage { required_size },
+            )?,
+            quad_count_for_indices: layer.quad_count_for_indices,
+            visuals: layer.visuals,
+        })
+    }
+
+    /// Like [`Self::upload_tile_layer_base`], but a failed upload only
+    /// logs the error, since the layers are optional.
+    fn upload_optional_tile_layer_base(
+        graphics_mt: &GraphicsMultiThreaded,
+        layer: Option<CachedTileLayerBase>,
+    ) -> Option<MapBufferTileLayerBase> {
+        layer.and_then(|layer| Self::upload_tile_layer_base(graphics_mt, layer).ok())
+    }
+
+    fn upload_tile_layer(
+        graphics_mt: &GraphicsMultiThreaded,
+        layer: CachedTileLayer,
+    ) -> MapBufferTileLayer {
+        match Self::upload_tile_layer_base(graphics_mt, layer.base) {
+            Ok(base) => MapBufferTileLayer {
+                base,
+                render_info: layer.render_info,
+                tile_index: Self::upload_optional_tile_layer_base(graphics_mt, layer.tile_index),
+                tile_flag: Self::upload_optional_tile_layer_base(graphics_mt, layer.tile_flag),
+                upload_err: layer.upload_err,
+            },
+            Err(err) => MapBufferTileLayer {
+                render_info: layer.render_info,
+                upload_err: Some(err),
+                ..Default::default()
+            },
+        }
+    }
+
+    fn upload_physics_tile_layer(
+        graphics_mt: &GraphicsMultiThreaded,
+        layer: CachedPhysicsTileLayer,
+    ) -> MapBufferPhysicsTileLayer {
+        match Self::upload_tile_layer_base(graphics_mt, layer.base) {
+            Ok(base) => MapBufferPhysicsTileLayer {
+                base,
+                tile_index: Self::upload_optional_tile_layer_base(graphics_mt, layer.tile_index),
+                tile_flag: Self::upload_optional_tile_layer_base(graphics_mt, layer.tile_flag),
+                render_info: layer.render_info,
+                overlays: layer
+                    .overlays
+                    .into_iter()
+                    .filter_map(|(ty, overlay)| {
+                        Self::upload_tile_layer_base(graphics_mt, overlay)
+                            .ok()
+                            .map(|overlay| (ty, overlay))
+                    })
+                    .collect(),
+                upload_err: layer.upload_err,
+            },
+            Err(err) => MapBufferPhysicsTileLayer {
+                render_info: layer.render_info,
+                upload_err: Some(err),
+                ..Default::default()
+            },
+        }
+    }
+
+    fn upload_quad_layer(
+        graphics_mt: &GraphicsMultiThreaded,
+        layer: CachedQuadLayer,
+    ) -> ClientMapBufferQuadLayer {
+        let vertex_buffer =
+            |required_size| GraphicsMemoryAllocationType::VertexBuffer { required_size };
+        let upload = || -> Result<_, LayerUploadError> {
+            Ok((
+                Self::upload_mem(graphics_mt, layer.mem, vertex_buffer)?,
+                Self::upload_mem(graphics_mt, layer.instanced_mem, vertex_buffer)?,
+            ))
+        };
+        match upload() {
+            Ok((mem, instanced_mem)) => ClientMapBufferQuadLayer {
+                mem,
+                instanced_mem,
+                quad_count_for_indices: layer.quad_count_for_indices,
+                render_info: layer.render_info,
+                skipped_quads: layer.skipped_quads,
+                extra: layer.extra,
+                upload_err: layer.upload_err,
+            },
+            Err(err) => ClientMapBufferQuadLayer {
+                render_info: layer.render_info,
+                upload_err: Some(err),
+                ..Default::default()
+            },
+        }
+    }
+
+    /// Rehydrates the upload data of `map` by copying the cached buffers
+    /// into graphics memory.
+    ///
+    /// `map` must be the map this cache was decoded for, see [`Self::decode`].
+    pub fn into_upload_data(
+        self,
+        graphics_mt: &GraphicsMultiThreaded,
+        map: Map,
+    ) -> ClientMapBufferUploadData {
+        let tile_layers = |layers: Vec<CachedTileLayer>| -> Vec<MapBufferTileLayer> {
+            layers
+                .into_iter()
+                .map(|layer| Self::upload_tile_layer(graphics_mt, layer))
+                .collect()
+        };
+        let quad_layers = |layers: Vec<CachedQuadLayer>| -> Vec<ClientMapBufferQuadLayer> {
+            layers
+                .into_iter()
+                .map(|layer| Self::upload_quad_layer(graphics_mt, layer))
+                .collect()
+        };
+        ClientMapBufferUploadData {
+            bg_tile_layer_uploads: tile_layers(self.bg_tile_layer_uploads),
+            fg_tile_layer_uploads: tile_layers(self.fg_tile_layer_uploads),
+            physics_tile_layer_uploads: self
+                .physics_tile_layer_uploads
+                .into_iter()
+                .map(|layer| Self::upload_physics_tile_layer(graphics_mt, layer))
+                .collect(),
+            bg_quad_layer_uploads: quad_layers(self.bg_quad_layer_uploads),
+            fg_quad_layer_uploads: quad_layers(self.fg_quad_layer_uploads),
+            map,
+        }
+    }
+}
@@ -0,0 +1,275 @@
+use graphics::handles::{
+    buffer_object::buffer_object::BufferObject, shader_storage::shader_storage::ShaderStorage,
+};
+use graphics_types::types::GraphicsBackendMemory;
+use thiserror::Error;
+
+use super::{
+    MapBufferPhysicsTileLayer, MapBufferTileLayer, MapBufferTileLayerBase, MapLayerLocation,
+    PhysicsTileLayerVisuals, TileLayerBufferedVisualObjects, TileLayerVisuals,
+};
+
+/// How many bytes are compared at the start, the middle and the end of a buffer.
+const SAMPLE_SIZE: usize = 256;
+
+/// The uploaded buffer of a layer differs from its source.
+#[derive(Error, Debug, Clone, Copy, PartialEq, Eq)]
+#[error("the uploaded buffer of layer {location:?} differs from its source at byte {offset}.")]
+pub struct BufferUploadMismatch {
+    pub location: MapLayerLocation,
+    /// The first byte that differs.
+    pub offset: usize,
+}
+
+#[derive(Debug, Clone)]
+enum UploadedBuffer {
+    BufferObject(BufferObject),
+    ShaderStorage(ShaderStorage),
+}
+
+impl UploadedBuffer {
+    fn read(&self, offset: usize, size: usize) -> anyhow::Result<Vec<u8>> {
+        match self {
+            Self::BufferObject(buffer) => buffer.read_buffer_object(offset, size),
+            Self::ShaderStorage(shader_storage) => shader_storage.read_shader_storage(offset, size),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct BufferUploadSample {
+    location: MapLayerLocation,
+    buffer: UploadedBuffer,
+    offset: usize,
+    bytes: Vec<u8>,
+}
+
+type Samples = Vec<(usize, Vec<u8>)>;
+
+/// The samples of the border buffer & the tile shader storage of a tile layer.
+#[derive(Debug, Default)]
+pub(super) struct TileLayerBaseSamples {
+    buffer_object: Samples,
+    shader_storage: Samples,
+}
+
+/// The samples of all buffers of a tile layer, including the editor only buffers.
+#[derive(Debug, Default)]
+pub(super) struct TileLayerSamples {
+    base: TileLayerBaseSamples,
+    tile_index: TileLayerBaseSamples,
+    tile_flag: TileLayerBaseSamples,
+}
+
+/// The samples of a physics layer and its text overlays.
+#[derive(Debug, Default)]
+pub(super) struct PhysicsTileLayerSamples {
+    layer: TileLayerSamples,
+    overlays: Vec<TileLayerBaseSamples>,
+}
+
+/// Samples of the source of uploaded buffers, which are compared against
+/// the buffers once the backend executed the uploads.
+///
+/// Catches e.g. wrong strides or offsets between the CPU source and the
+/// GPU buffer. Never takes samples in release builds.
+#[derive(Debug, Default)]
+pub struct MapUploadVerification {
+    enabled: bool,
+    samples: Vec<BufferUploadSample>,
+}
+
+impl MapUploadVerification {
+    pub fn new(enabled: bool) -> Self {
+        Self {
+            enabled: cfg!(debug_assertions) && enabled,
+            samples: Default::default(),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// The samples that were not verified yet.
+    pub fn pending_samples(&self) -> usize {
+        self.samples.len()
+    }
+
+    /// Samples the start, the middle and the end of `mem`.
+    /// Nothing is sampled if the verification is disabled.
+    pub(super) fn sample(&self, mem: Option<&GraphicsBackendMemory>) -> Samples {
+        let Some(mem) = mem.filter(|_| self.enabled) else {
+            return Vec::new();
+        };
+        let data = mem.as_slice();
+        let last = data.len().saturating_sub(SAMPLE_SIZE);
+        let mut offsets = vec![0, last / 2, last];
+        offsets.dedup();
+        offsets
+            .into_iter()
+            .map(|offset| {
+                (
+                    offset,
+                    &data[offset..(offset + SAMPLE_SIZE).min(data.len())],
+                )
+            })
+            .filter(|(_, bytes)| !bytes.is_empty())
+            .map(|(offset, bytes)| (offset, bytes.to_vec()))
+            .collect()
+    }
+
+    fn sample_tile_layer_base(
+        &self,
+        base: Option<&MapBufferTileLayerBase>,
+    ) -> TileLayerBaseSamples {
+        let Some(base) = base else {
+            return Default::default();
+        };
+        TileLayerBaseSamples {
+            buffer_object: self.sample(base.mem.as_ref()),
+            shader_storage: self.sample(base.shader_storage_mem.as_ref()),
+        }
+    }
+
+    /// Samples all buffers of a tile layer upload.
+    pub(super) fn sample_tile_layer(&self, upload: &MapBufferTileLayer) -> TileLayerSamples {
+        TileLayerSamples {
+            base: self.sample_tile_layer_base(Some(&upload.base)),
+            tile_index: self.sample_tile_layer_base(upload.tile_index.as_ref()),
+            tile_flag: self.sample_tile_layer_base(upload.tile_flag.as_ref()),
+        }
+    }
+
+    /// Samples all buffers of a physics layer upload, including its text overlays.
+    pub(super) fn sample_physics_layer(
+        &self,
+        upload: &MapBufferPhysicsTileLayer,
+    ) -> PhysicsTileLayerSamples {
+        PhysicsTileLayerSamples {
+            layer: TileLayerSamples {
+                base: self.sample_tile_layer_base(Some(&upload.base)),
+                tile_index: self.sample_tile_layer_base(upload.tile_index.as_ref()),
+                tile_flag: self.sample_tile_layer_base(upload.tile_flag.as_ref()),
+            },
+            overlays: upload
+                .overlays
+                .iter()
+                .map(|(_, overlay)| self.sample_tile_layer_base(Some(overlay)))
+                .collect(),
+        }
+    }
+
+    /// Remembers the `samples` of the source of `buffer` for [`Self::verify`].
+    pub(super) fn add(
+        &mut self,
+        location: MapLayerLocation,
+        buffer: Option<&BufferObject>,
+        samples: Samples,
+    ) {
+        self.add_samples(
+            location,
+            buffer.cloned().map(UploadedBuffer::BufferObject),
+            samples,
+        );
+    }
+
+    fn add_tile_layer_base(
+        &mut self,
+        location: MapLayerLocation,
+        obj: &TileLayerBufferedVisualObjects,
+        samples: TileLayerBaseSamples,
+    ) {
+        self.add(location, obj.buffer_object.as_ref(), samples.buffer_object);
+        self.add_samples(
+            location,
+            obj.shader_storage
+                .clone()
+                .map(UploadedBuffer::ShaderStorage),
+            samples.shader_storage,
+        );
+    }
+
+    /// Remembers the samples of [`Self::sample_tile_layer`] for [`Self::verify`].
+    pub(super) fn add_tile_layer(
+        &mut self,
+        location: MapLayerLocation,
+        visuals: &TileLayerVisuals,
+        samples: TileLayerSamples,
+    ) {
+        self.add_tile_layer_base(location, &visuals.base.obj, samples.base);
+        self.add_tile_layer_base(location, &visuals.tile_index_obj, samples.tile_index);
+        self.add_tile_layer_base(location, &visuals.tile_flag_obj, samples.tile_flag);
+    }
+
+    /// Remembers the samples of [`Self::sample_physics_layer`] for [`Self::verify`].
+    pub(super) fn add_physics_layer(
+        &mut self,
+        location: MapLayerLocation,
+        visuals: &PhysicsTileLayerVisuals,
+        samples: PhysicsTileLayerSamples,
+    ) {
+        self.add_tile_layer(location, &visuals.base, samples.layer);
+        // every uploaded overlay is finished in order
+        for (overlay, samples) in visuals.overlays.iter().zip(samples.overlays) {
+            self.add_tile_layer_base(location, &overlay.visuals.obj, samples);
+        }
+    }
+
+    fn add_samples(
+        &mut self,
+        location: MapLayerLocation,
+        buffer: Option<UploadedBuffer>,
+        samples: Samples,
+    ) {
+        let Some(buffer) = buffer else {
+            return;
+        };
+        self.samples.extend(
+            samples
+                .into_iter()
+                .map(|(offset, bytes)| BufferUploadSample {
+                    location,
+                    buffer: buffer.clone(),
+                    offset,
+                    bytes,
+                }),
+        );
+    }
+
+    /// Reads back the sampled bytes of the buffers and logs every
+    /// sample that differs from its source.
+    ///
+    /// Must be called after the backend ran the upload commands.
+    /// Buffers that can not be read back are skipped.
+    pub fn verify(&mut self) -> Vec<BufferUploadMismatch> {
+        std::mem::take(&mut self.samples)
+            .into_iter()
+            .filter_map(|sample| {
+                let bytes = match sample.buffer.read(sample.offset, sample.bytes.len()) {
+                    Ok(bytes) => bytes,
+                    Err(err) => {
+                        log::debug!(
+                            "could not read back the buffer of layer {:?}: {err}",
+                            sample.location
+                        );
+                        return None;
+                    }
+                };
+                (bytes != sample.bytes).then(|| {
+                    let mismatch = BufferUploadMismatch {
+                        location: sample.location,
+                        offset: sample.offset
+                            + bytes
+                                .iter()
+                                .zip(&sample.bytes)
+                                .position(|(a, b)| a != b)
+                                .unwrap_or(bytes.len().min(sample.bytes.len())),
+                    };
+                    log::error!("{mismatch}");
+                    mismatch
+                })
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,516 @@
+use map::{
+    map::groups::layers::physics::MapLayerTilePhysicsBase,
+    skeleton::{
+        animations::{AnimBaseSkeleton, AnimationsSkeleton},
+        groups::{
+            layers::{design::MapLayerSkeleton, physics::MapLayerPhysicsSkeleton},
+            MapGroupPhysicsSkeleton, MapGroupSkeleton, MapGroupsSkeleton,
+        },
+        resources::{MapResourceRefSkeleton, MapResourcesSkeleton},
+        MapSkeleton,
+    },
+};
+use serde::{de::DeserializeOwned, Serialize};
+
+use super::map_buffered::MapLayerLocation;
+
+/// Where a group is located inside the map.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MapGroupLocation {
+    Physics,
+    Background { group_index: usize },
+    Foreground { group_index: usize },
+}
+
+impl MapGroupLocation {
+    fn layer(&self, layer_index: usize) -> MapLayerLocation {
+        match *self {
+            MapGroupLocation::Physics => MapLayerLocation::Physics { layer_index },
+            MapGroupLocation::Background { group_index } => MapLayerLocation::Background {
+                group_index,
+                layer_index,
+            },
+            MapGroupLocation::Foreground { group_index } => MapLayerLocation::Foreground {
+                group_index,
+                layer_index,
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MapResourceKind {
+    Image,
+    ImageArray,
+    Sound,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MapAnimationKind {
+    Pos,
+    Color,
+    Sound,
+}
+
+/// A single difference between two maps, see [`diff_maps`].
+///
+/// Changes are reported as deep as possible, e.g. a moved quad
+/// is reported as [`MapChange::Quad`], but if the quad count of
+/// the layer changed, the whole layer is reported as changed instead.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MapChange {
+    /// The amount of background (or foreground) groups differs.
+    GroupCount {
+        is_background: bool,
+    },
+    /// The attributes or the name of the group differ.
+    Group {
+        location: MapGroupLocation,
+    },
+    /// The amount of layers in the group differs.
+    LayerCount {
+        location: MapGroupLocation,
+    },
+    /// The kind, the attributes, the name or the size of the layer differ.
+    Layer {
+        location: MapLayerLocation,
+    },
+    Tile {
+        location: MapLayerLocation,
+        tile_index: usize,
+    },
+    Quad {
+        location: MapLayerLocation,
+        quad_index: usize,
+    },
+    Sound {
+        location: MapLayerLocation,
+        sound_index: usize,
+    },
+    ResourceCount {
+        kind: MapResourceKind,
+    },
+    Resource {
+        kind: MapResourceKind,
+        index: usize,
+    },
+    AnimationCount {
+        kind: MapAnimationKind,
+    },
+    Animation {
+        kind: MapAnimationKind,
+        index: usize,
+    },
+    Config,
+    Metadata,
+}
+
+/// Compares types that don't implement [`PartialEq`] by their serialized form.
+fn serialized_eq<T: Serialize>(a: &T, b: &T) -> bool {
+    let serialize = |v: &T| bincode::serde::encode_to_vec(v, bincode::config::standard()).ok();
+    match (serialize(a), serialize(b)) {
+        (Some(a), Some(b)) => a == b,
+        _ => false,
+    }
+}
+
+fn diff_elements<T: PartialEq>(
+    a: &[T],
+    b: &[T],
+    changes: &mut Vec<MapChange>,
+    change: impl Fn(usize) -> MapChange,
+) {
+    changes.extend(
+        a.iter()
+            .zip(b.iter())
+            .enumerate()
+            .filter(|(_, (a, b))| a != b)
+            .map(|(index, _)| change(index)),
+    );
+}
+
+fn diff_tiles<T: PartialEq>(
+    location: MapLayerLocation,
+    a: &[T],
+    b: &[T],
+    changes: &mut Vec<MapChange>,
+) {
+    if a.len() != b.len() {
+        changes.push(MapChange::Layer { location });
+    } else {
+        diff_elements(a, b, changes, |tile_index| MapChange::Tile {
+            location,
+            tile_index,
+        });
+    }
+}
+
+fn diff_physics_tiles<T: PartialEq>(
+    location: MapLayerLocation,
+    a: &MapLayerTilePhysicsBase<T>,
+    b: &MapLayerTilePhysicsBase<T>,
+    changes: &mut Vec<MapChange>,
+) {
+    diff_tiles(location, &a.tiles, &b.tiles, changes)
+}
+
+fn diff_physics_layer<L>(
+    location: MapLayerLocation,
+    a: &MapLayerPhysicsSkeleton<L>,
+    b: &MapLayerPhysicsSkeleton<L>,
+    changes: &mut Vec<MapChange>,
+) {
+    match (a, b) {
+        (MapLayerPhysicsSkeleton::Arbitrary(a), MapLayerPhysicsSkeleton::Arbitrary(b)) => {
+            if a.buf != b.buf {
+                changes.push(MapChange::Layer { location });
+            }
+        }
+        (MapLayerPhysicsSkeleton::Game(a), MapLayerPhysicsSkeleton::Game(b))
+        | (MapLayerPhysicsSkeleton::Front(a), MapLayerPhysicsSkeleton::Front(b)) => {
+            diff_physics_tiles(location, &a.layer, &b.layer, changes)
+        }
+        (MapLayerPhysicsSkeleton::Tele(a), MapLayerPhysicsSkeleton::Tele(b)) => {
+            if a.layer.tele_names != b.layer.tele_names {
+                changes.push(MapChange::Layer { location });
+            } else {
+                diff_physics_tiles(location, &a.layer.base, &b.layer.base, changes)
+            }
+        }
+        (MapLayerPhysicsSkeleton::Speedup(a), MapLayerPhysicsSkeleton::Speedup(b)) => {
+            diff_physics_tiles(location, &a.layer, &b.layer, changes)
+        }
+        (MapLayerPhysicsSkeleton::Switch(a), MapLayerPhysicsSkeleton::Switch(b)) => {
+            if a.layer.switch_names != b.layer.switch_names {
+                changes.push(MapChange::Layer { location });
+            } else {
+                diff_physics_tiles(location, &a.layer.base, &b.layer.base, changes)
+            }
+        }
+        (MapLayerPhysicsSkeleton::Tune(a), MapLayerPhysicsSkeleton::Tune(b)) => {
+            if a.layer.tune_zones != b.layer.tune_zones {
+                changes.push(MapChange::Layer { location });
+            } else {
+                diff_physics_tiles(location, &a.layer.base, &b.layer.base, changes)
+            }
+        }
+        _ => changes.push(MapChange::Layer { location }),
+    }
+}
+
+fn diff_design_layer<T, Q, S, A>(
+    location: MapLayerLocation,
+    a: &MapLayerSkeleton<T, Q, S, A>,
+    b: &MapLayerSkeleton<T, Q, S, A>,
+    changes: &mut Vec<MapChange>,
+) {
+    match (a, b) {
+        (MapLayerSkeleton::Abritrary(a), MapLayerSkeleton::Abritrary(b)) => {
+            if a.buf != b.buf {
+                changes.push(MapChange::Layer { location });
+            }
+        }
+        (MapLayerSkeleton::Tile(a), MapLayerSkeleton::Tile(b)) => {
+            if a.layer.attr != b.layer.attr || a.layer.name != b.layer.name {
+                changes.push(MapChange::Layer { location });
+            } else {
+                diff_tiles(location, &a.layer.tiles, &b.layer.tiles, changes)
+            }
+        }
+        (MapLayerSkeleton::Quad(a), MapLayerSkeleton::Quad(b)) => {
+            if a.layer.attr != b.layer.attr
+                || a.layer.name != b.layer.name
+                || a.layer.quads.len() != b.layer.quads.len()
+            {
+                changes.push(MapChange::Layer { location });
+            } else {
+                diff_elements(&a.layer.quads, &b.layer.quads, changes, |quad_index| {
+                    MapChange::Quad {
+                        location,
+                        quad_index,
+                    }
+                })
+            }
+        }
+        (MapLayerSkeleton::Sound(a), MapLayerSkeleton::Sound(b)) => {
+            if a.layer.attr != b.layer.attr
+                || a.layer.name != b.layer.name
+                || a.layer.sounds.len() != b.layer.sounds.len()
+            {
+                changes.push(MapChange::Layer { location });
+            } else {
+                diff_elements(&a.layer.sounds, &b.layer.sounds, changes, |sound_index| {
+                    MapChange::Sound {
+                        location,
+                        sound_index,
+                    }
+                })
+            }
+        }
+        _ => changes.push(MapChange::Layer { location }),
+    }
+}
+
+fn diff_physics_group<PG, PL>(
+    a: &MapGroupPhysicsSkeleton<PG, PL>,
+    b: &MapGroupPhysicsSkeleton<PG, PL>,
+    changes: &mut Vec<MapChange>,
+) {
+    let location = MapGroupLocation::Physics;
+    if a.attr != b.attr {
+        changes.push(MapChange::Group { location });
+    }
+    if a.layers.len() != b.layers.len() {
+        changes.push(MapChange::LayerCount { location });
+        return;
+    }
+    for (layer_index, (a, b)) in a.layers.iter().zip(b.layers.iter()).enumerate() {
+        diff_physics_layer(location.layer(layer_index), a, b, changes);
+    }
+}
+
+fn diff_design_group<G, T, Q, S, A>(
+    location: MapGroupLocation,
+    a: &MapGroupSkeleton<G, T, Q, S, A>,
+    b: &MapGroupSkeleton<G, T, Q, S, A>,
+    changes: &mut Vec<MapChange>,
+) {
+    if a.attr != b.attr || a.name != b.name {
+        changes.push(MapChange::Group { location });
+    }
+    if a.layers.len() != b.layers.len() {
+        changes.push(MapChange::LayerCount { location });
+        return;
+    }
+    for (layer_index, (a, b)) in a.layers.iter().zip(b.layers.iter()).enumerate() {
+        diff_design_layer(location.layer(layer_index), a, b, changes);
+    }
+}
+
+fn diff_design_groups<G, T, Q, S, A>(
+    is_background: bool,
+    a: &[MapGroupSkeleton<G, T, Q, S, A>],
+    b: &[MapGroupSkeleton<G, T, Q, S, A>],
+    changes: &mut Vec<MapChange>,
+) {
+    if a.len() != b.len() {
+        changes.push(MapChange::GroupCount { is_background });
+        return;
+    }
+    for (group_index, (a, b)) in a.iter().zip(b.iter()).enumerate() {
+        let location = if is_background {
+            MapGroupLocation::Background { group_index }
+        } else {
+            MapGroupLocation::Foreground { group_index }
+        };
+        diff_design_group(location, a, b, changes);
+    }
+}
+
+/// Structurally compares the groups of two maps.
+///
+/// Only the map data is compared, the user data (e.g. the buffered visuals) is ignored.
+/// The changes are ordered physics group first, then background and foreground groups.
+pub fn diff_map_groups<GS, PG, PL, G, T, Q, S, A>(
+    a: &MapGroupsSkeleton<GS, PG, PL, G, T, Q, S, A>,
+    b: &MapGroupsSkeleton<GS, PG, PL, G, T, Q, S, A>,
+) -> Vec<MapChange> {
+    let mut changes = Vec::new();
+    diff_physics_group(&a.physics, &b.physics, &mut changes);
+    diff_design_groups(true, &a.background, &b.background, &mut changes);
+    diff_design_groups(false, &a.foreground, &b.foreground, &mut changes);
+    changes
+}
+
+fn diff_resources<R>(
+    kind: MapResourceKind,
+    a: &[MapResourceRefSkeleton<R>],
+    b: &[MapResourceRefSkeleton<R>],
+    changes: &mut Vec<MapChange>,
+) {
+    if a.len() != b.len() {
+        changes.push(MapChange::ResourceCount { kind });
+        return;
+    }
+    changes.extend(
+        a.iter()
+            .zip(b.iter())
+            .enumerate()
+            .filter(|(_, (a, b))| a.def != b.def)
+            .map(|(index, _)| MapChange::Resource { kind, index }),
+    );
+}
+
+fn diff_map_resources<R, RI, RI2, RS>(
+    a: &MapResourcesSkeleton<R, RI, RI2, RS>,
+    b: &MapResourcesSkeleton<R, RI, RI2, RS>,
+    changes: &mut Vec<MapChange>,
+) {
+    diff_resources(MapResourceKind::Image, &a.images, &b.images, changes);
+    diff_resources(
+        MapResourceKind::ImageArray,
+        &a.image_arrays,
+        &b.image_arrays,
+        changes,
+    );
+    diff_resources(MapResourceKind::Sound, &a.sounds, &b.sounds, changes);
+}
+
+fn diff_animations<A, AP: DeserializeOwned + PartialOrd + Clone + Serialize>(
+    kind: MapAnimationKind,
+    a: &[AnimBaseSkeleton<A, AP>],
+    b: &[AnimBaseSkeleton<A, AP>],
+    changes: &mut Vec<MapChange>,
+) {
+    if a.len() != b.len() {
+        changes.push(MapChange::AnimationCount { kind });
+        return;
+    }
+    changes.extend(
+        a.iter()
+            .zip(b.iter())
+            .enumerate()
+            .filter(|(_, (a, b))| !serialized_eq(&a.def, &b.def))
+            .map(|(index, _)| MapChange::Animation { kind, index }),
+    );
+}
+
+fn diff_map_animations<AS, A>(
+    a: &AnimationsSkeleton<AS, A>,
+    b: &AnimationsSkeleton<AS, A>,
+    changes: &mut Vec<MapChange>,
+) {
+    diff_animations(MapAnimationKind::Pos, &a.pos, &b.pos, changes);
+    diff_animations(MapAnimationKind::Color, &a.color, &b.color, changes);
+    diff_animations(MapAnimationKind::Sound, &a.sound, &b.sound, changes);
+}
+
+/// Structurally compares two maps, e.g. two [`super::map_with_visual::MapVisual`]s
+/// before and after an editor action.
+///
+/// Returns the paths to everything that changed, empty if the maps are equal.
+/// Only the map data is compared, the user data is ignored.
+pub fn diff_maps<E, R, RI, RI2, RS, GS, PG, PL, G, T, Q, S, CA, AS, A, C, M>(
+    a: &MapSkeleton<E, R, RI, RI2, RS, GS, PG, PL, G, T, Q, S, CA, AS, A, C, M>,
+    b: &MapSkeleton<E, R, RI, RI2, RS, GS, PG, PL, G, T, Q, S, CA, AS, A, C, M>,
+) -> Vec<MapChange> {
+    let mut changes = Vec::new();
+    diff_map_resources(&a.resources, &b.resources, &mut changes);
+    changes.extend(diff_map_groups(&a.groups, &b.groups));
+    diff_map_animations(&a.animations, &b.animations, &mut changes);
+    if !serialized_eq(&a.config.def, &b.config.def) {
+        changes.push(MapChange::Config);
+    }
+    if a.meta.def != b.meta.def {
+        changes.push(MapChange::Metadata);
+    }
+    changes
+}
+
+#[cfg(test)]
+mod tests {
+    use map::{
+        map::groups::{
+            layers::{
+                design::{MapLayerQuad, MapLayerQuadsAttrs, MapLayerTile, Quad},
+                tiles::MapTileLayerAttr,
+            },
+            MapGroupAttr, MapGroupPhysicsAttr,
+        },
+        skeleton::groups::{
+            layers::design::{MapLayerQuadSkeleton, MapLayerSkeleton, MapLayerTileSkeleton},
+            MapGroupPhysicsSkeleton, MapGroupSkeleton, MapGroupsSkeleton,
+        },
+        types::NonZeroU16MinusOne,
+    };
+    use math::math::vector::{ffixed, fvec2, nffixed, nfvec4};
+
+    use crate::map::map_buffered::MapLayerLocation;
+
+    use super::{diff_map_groups, MapChange};
+
+    #[test]
+    fn moved_quad_is_the_only_change() {
+        let quad = |x: i32| Quad {
+            points: [
+                fvec2::new(ffixed::from_num(x), ffixed::from_num(0)),
+                fvec2::new(ffixed::from_num(x + 1), ffixed::from_num(0)),
+                fvec2::new(ffixed::from_num(x), ffixed::from_num(1)),
+                fvec2::new(ffixed::from_num(x + 1), ffixed::from_num(1)),
+                fvec2::new(ffixed::from_num(x), ffixed::from_num(0)),
+            ],
+            ..Default::default()
+        };
+        let groups = || {
+            let tile_layer = MapLayerSkeleton::Tile(MapLayerTileSkeleton {
+                layer: MapLayerTile {
+                    attr: MapTileLayerAttr {
+                        width: NonZeroU16MinusOne::new(1).unwrap(),
+                        height: NonZeroU16MinusOne::new(1).unwrap(),
+                        color: nfvec4::new(
+                            nffixed::const_from_int(1),
+                            nffixed::const_from_int(1),
+                            nffixed::const_from_int(1),
+                            nffixed::const_from_int(1),
+                        ),
+                        high_detail: false,
+                        color_anim: None,
+                        color_anim_offset: time::Duration::ZERO,
+                        image_array: None,
+                    },
+                    tiles: vec![Default::default()],
+                    name: "tiles".into(),
+                },
+                user: (),
+            });
+            let quad_layer = MapLayerSkeleton::Quad(MapLayerQuadSkeleton {
+                layer: MapLayerQuad {
+                    attr: MapLayerQuadsAttrs {
+                        image: None,
+                        high_detail: false,
+                    },
+                    quads: (0..4).map(|i| quad(i * 2)).collect(),
+                    name: "quads".into(),
+                },
+                user: (),
+            });
+            MapGroupsSkeleton::<(), (), (), (), (), (), (), ()> {
+                physics: MapGroupPhysicsSkeleton {
+                    attr: MapGroupPhysicsAttr {
+                        width: NonZeroU16MinusOne::new(1).unwrap(),
+                        height: NonZeroU16MinusOne::new(1).unwrap(),
+                    },
+                    layers: Vec::new(),
+                    user: (),
+                },
+                background: vec![MapGroupSkeleton {
+                    attr: MapGroupAttr::default(),
+                    layers: vec![tile_layer, quad_layer],
+                    name: "group".into(),
+                    user: (),
+                }],
+                foreground: Vec::new(),
+                user: (),
+            }
+        };
+
+        let before = groups();
+        let mut after = groups();
+        assert!(diff_map_groups(&before, &after).is_empty());
+
+        let MapLayerSkeleton::Quad(layer) = &mut after.background[0].layers[1] else {
+            unreachable!()
+        };
+        layer.layer.quads[2] = quad(100);
+        assert_eq!(
+            diff_map_groups(&before, &after),
+            [MapChange::Quad {
+                location: MapLayerLocation::Background {
+                    group_index: 0,
+                    layer_index: 1,
+                },
+                quad_index: 2,
+            }]
+        );
+    }
+}
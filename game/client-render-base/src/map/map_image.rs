@@ -1,5 +1,22 @@
-use graphics_types::types::GraphicsBackendMemory;
+use std::{
+    cell::RefCell,
+    collections::{hash_map::Entry, HashMap},
+    rc::Rc,
+};
+
+use anyhow::anyhow;
+use base::hash::Hash;
+use graphics::{
+    graphics_mt::GraphicsMultiThreaded,
+    handles::texture::texture::{GraphicsTextureHandle, TextureContainer, TextureContainer2dArray},
+};
+use graphics_types::{
+    commands::TexFlags,
+    types::{GraphicsBackendMemory, GraphicsMemoryAllocationType},
+};
 use hiarc::Hiarc;
+use map::map::resources::{MapResourceMetaData, MapResourceRef};
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
 use sound::sound_mt_types::SoundBackendMemory;
 
 #[derive(Debug, Hiarc)]
@@ -9,6 +26,8 @@ pub struct ClientMapImageLoading {
     pub depth: u32,
     pub mem: GraphicsBackendMemory,
     pub name: String,
+    /// The blake3 hash of the image file.
+    pub hash: Hash,
 }
 
 #[derive(Debug, Hiarc)]
@@ -16,10 +35,445 @@ pub struct ClientMapSoundLoading {
     pub mem: SoundBackendMemory,
 }
 
+/// Resources of a map with the same blake3 hash share a single loaded
+/// resource (e.g. one texture), instead of being loaded once per resource.
+#[derive(Debug, Hiarc, Default, Clone)]
+pub struct ClientMapResourceDedup {
+    /// For every resource of the map, the index of the loaded resource it uses.
+    indices: Vec<usize>,
+    /// For every loaded resource, the index of the first resource of the map with its hash.
+    unique: Vec<usize>,
+}
+
+impl ClientMapResourceDedup {
+    pub fn new(hashes: impl IntoIterator<Item = Hash>) -> Self {
+        let mut loaded: HashMap<Hash, usize> = Default::default();
+        let mut res = Self::default();
+        for (index, hash) in hashes.into_iter().enumerate() {
+            let loaded_index = *loaded.entry(hash).or_insert_with(|| {
+                res.unique.push(index);
+                res.unique.len() - 1
+            });
+            res.indices.push(loaded_index);
+        }
+        res
+    }
+
+    /// Only keeps the resources that actually have to be loaded.
+    pub fn unique_resources<T>(&self, resources: Vec<T>) -> Vec<T> {
+        resources
+            .into_iter()
+            .enumerate()
+            .filter(|(index, _)| self.unique[self.indices[*index]] == *index)
+            .map(|(_, resource)| resource)
+            .collect()
+    }
+
+    /// Expands the loaded resources to one entry per resource of the map,
+    /// resources with the same hash share the loaded resource.
+    pub fn share_loaded<T: Clone>(&self, loaded: Vec<T>) -> Vec<T> {
+        self.indices
+            .iter()
+            .map(|&index| loaded[index].clone())
+            .collect()
+    }
+
+    /// The amount of resources that share a loaded resource with a previous resource.
+    pub fn deduped_count(&self) -> usize {
+        self.indices.len() - self.unique.len()
+    }
+}
+
+type SharedResources<T> = Rc<RefCell<HashMap<Hash, (T, usize)>>>;
+
+/// Resources that are shared by all maps loaded at the same time
+/// (e.g. editor tabs or a vote preview), keyed by their blake3 hash.
+///
+/// A resource stays in the registry as long as
+/// any map holds a [`ClientMapSharedResource`] of it.
+#[derive(Debug)]
+pub struct ClientMapResourceRegistry<T> {
+    resources: SharedResources<T>,
+}
+
+impl<T> Default for ClientMapResourceRegistry<T> {
+    fn default() -> Self {
+        Self {
+            resources: Default::default(),
+        }
+    }
+}
+
+impl<T> Clone for ClientMapResourceRegistry<T> {
+    fn clone(&self) -> Self {
+        Self {
+            resources: self.resources.clone(),
+        }
+    }
+}
+
+impl<T: Clone> ClientMapResourceRegistry<T> {
+    /// Returns the resource with the given hash.
+    /// If no map uses it yet, it is created by `load`.
+    pub fn acquire(
+        &self,
+        hash: Hash,
+        load: impl FnOnce() -> anyhow::Result<T>,
+    ) -> anyhow::Result<ClientMapSharedResource<T>> {
+        let resource = match self.resources.borrow_mut().entry(hash) {
+            Entry::Occupied(mut entry) => {
+                let (resource, refs) = entry.get_mut();
+                *refs += 1;
+                resource.clone()
+            }
+            Entry::Vacant(entry) => {
+                let resource = load()?;
+                entry.insert((resource.clone(), 1));
+                resource
+            }
+        };
+        Ok(ClientMapSharedResource {
+            hash,
+            resource,
+            resources: self.resources.clone(),
+        })
+    }
+}
+
+impl<T> ClientMapResourceRegistry<T> {
+    /// The amount of distinct resources that are currently in use.
+    pub fn len(&self) -> usize {
+        self.resources.borrow().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.resources.borrow().is_empty()
+    }
+}
+
+/// A map's reference to a resource of a [`ClientMapResourceRegistry`].
+/// Dropping it releases the reference.
+#[derive(Debug)]
+pub struct ClientMapSharedResource<T> {
+    hash: Hash,
+    resource: T,
+    resources: SharedResources<T>,
+}
+
+impl<T> ClientMapSharedResource<T> {
+    pub fn resource(&self) -> &T {
+        &self.resource
+    }
+}
+
+impl<T> Drop for ClientMapSharedResource<T> {
+    fn drop(&mut self) {
+        let mut resources = self.resources.borrow_mut();
+        let Entry::Occupied(mut entry) = resources.entry(self.hash) else {
+            return;
+        };
+        entry.get_mut().1 -= 1;
+        if entry.get().1 == 0 {
+            let unused = entry.remove();
+            // don't destroy the resource while the registry is borrowed
+            drop(resources);
+            drop(unused);
+        }
+    }
+}
+
+/// The textures shared by all maps that are loaded with it,
+/// see [`ClientMapResourceRegistry`].
+#[derive(Debug, Default, Clone)]
+pub struct ClientMapTextureRegistry {
+    pub images: ClientMapResourceRegistry<TextureContainer>,
+    pub images_2d_array: ClientMapResourceRegistry<TextureContainer2dArray>,
+}
+
+/// The textures a map uses from a [`ClientMapTextureRegistry`].
+#[derive(Debug, Default)]
+pub struct ClientMapSharedTextures {
+    pub images: Vec<ClientMapSharedResource<TextureContainer>>,
+    pub images_2d_array: Vec<ClientMapSharedResource<TextureContainer2dArray>>,
+}
+
 #[derive(Debug, Hiarc, Default)]
 pub struct ClientMapImagesLoading {
     pub images: Vec<ClientMapImageLoading>,
+    pub images_dedup: ClientMapResourceDedup,
     pub images_2d_array: Vec<ClientMapImageLoading>,
+    pub images_2d_array_dedup: ClientMapResourceDedup,
+}
+
+/// The textures of a map's images, see [`ClientMapImagesLoading::create_textures`].
+#[derive(Debug, Default)]
+pub struct ClientMapTextures {
+    /// One texture per image of the map.
+    pub images: Vec<TextureContainer>,
+    /// One texture per image array of the map.
+    pub images_2d_array: Vec<TextureContainer2dArray>,
+    pub shared: ClientMapSharedTextures,
+}
+
+/// The meta data of the variant of the resource that is loaded.
+fn loaded_meta(resource: &MapResourceRef, load_hq_assets: bool) -> &MapResourceMetaData {
+    load_hq_assets
+        .then_some(resource.hq_meta.as_ref())
+        .flatten()
+        .unwrap_or(&resource.meta)
+}
+
+impl ClientMapImagesLoading {
+    /// Prepares the uploads of the map's images, `img_files` are the decoded
+    /// images (data, width, height) by their blake3 hash.
+    /// Identical images are only prepared once, so they share a single texture.
+    /// `generate_3d_data` converts an image into the memory of a 2d array texture
+    /// and returns its width, height and depth.
+    pub fn new(
+        graphics_mt: &GraphicsMultiThreaded,
+        images: Vec<MapResourceRef>,
+        image_arrays: Vec<MapResourceRef>,
+        img_files: &HashMap<Hash, (Vec<u8>, u32, u32)>,
+        load_hq_assets: bool,
+        generate_3d_data: impl Fn(usize, usize, &[u8]) -> (usize, usize, usize, GraphicsBackendMemory)
+            + Sync,
+    ) -> anyhow::Result<Self> {
+        let resource_hash = |res: &MapResourceRef| loaded_meta(res, load_hq_assets).blake3_hash;
+        let images_dedup = ClientMapResourceDedup::new(images.iter().map(resource_hash));
+        let images_2d_array_dedup =
+            ClientMapResourceDedup::new(image_arrays.iter().map(resource_hash));
+        let img_file = |img: &MapResourceRef| {
+            let meta = loaded_meta(img, load_hq_assets);
+            img_files
+                .get(&meta.blake3_hash)
+                .map(|img_file| (meta.blake3_hash, img_file))
+                .ok_or(anyhow!("img with that name not found"))
+        };
+        Ok(Self {
+            images: images_dedup
+                .unique_resources(images)
+                .into_par_iter()
+                .map(|img| {
+                    let (hash, (img_data, width, height)) = img_file(&img)?;
+                    let mut loading_img = ClientMapImageLoading {
+                        mem: graphics_mt.mem_alloc(GraphicsMemoryAllocationType::TextureRgbaU8 {
+                            width: (*width as usize).try_into().unwrap(),
+                            height: (*height as usize).try_into().unwrap(),
+                            flags: TexFlags::empty(),
+                        }),
+                        width: *width,
+                        height: *height,
+                        depth: 1,
+                        name: img.name.to_string(),
+                        hash,
+                    };
+                    loading_img.mem.as_mut_slice().copy_from_slice(img_data);
+                    if graphics_mt
+                        .try_flush_mem(&mut loading_img.mem, false)
+                        .is_err()
+                    {
+                        // TODO: handle/log ?
+                    }
+                    anyhow::Ok(loading_img)
+                })
+                .collect::<anyhow::Result<Vec<ClientMapImageLoading>>>()?,
+            images_dedup,
+            images_2d_array: images_2d_array_dedup
+                .unique_resources(image_arrays)
+                .into_par_iter()
+                .map(|img| {
+                    let (hash, (img_data, width, height)) = img_file(&img)?;
+                    let (width, height, depth, mem) =
+                        generate_3d_data(*width as usize, *height as usize, img_data);
+                    anyhow::Ok(ClientMapImageLoading {
+                        mem,
+                        width: width as u32,
+                        height: height as u32,
+                        depth: depth as u32,
+                        name: img.name.to_string(),
+                        hash,
+                    })
+                })
+                .collect::<anyhow::Result<Vec<ClientMapImageLoading>>>()?,
+            images_2d_array_dedup,
+        })
+    }
+
+    /// Creates a texture for every prepared image, images that share a loaded image
+    /// share its texture. With a `registry` the textures are also shared with other maps.
+    pub fn create_textures(
+        self,
+        texture_handle: &GraphicsTextureHandle,
+        registry: Option<&ClientMapTextureRegistry>,
+    ) -> anyhow::Result<ClientMapTextures> {
+        let mut shared = ClientMapSharedTextures::default();
+        let images = self.images_dedup.share_loaded(
+            self.images
+                .into_iter()
+                .map(|img| {
+                    let ClientMapImageLoading {
+                        mem, name, hash, ..
+                    } = img;
+                    let load = || texture_handle.load_texture_rgba_u8(mem, &name);
+                    let Some(registry) = registry else {
+                        return load();
+                    };
+                    let shared_texture = registry.images.acquire(hash, load)?;
+                    let texture = shared_texture.resource().clone();
+                    shared.images.push(shared_texture);
+                    anyhow::Ok(texture)
+                })
+                .collect::<anyhow::Result<Vec<TextureContainer>>>()?,
+        );
+        let images_2d_array = self.images_2d_array_dedup.share_loaded(
+            self.images_2d_array
+                .into_iter()
+                .map(|img| {
+                    let ClientMapImageLoading {
+                        mem, name, hash, ..
+                    } = img;
+                    let load = || texture_handle.load_texture_2d_array_rgba_u8(mem, &name);
+                    let Some(registry) = registry else {
+                        return load();
+                    };
+                    let shared_texture = registry.images_2d_array.acquire(hash, load)?;
+                    let texture = shared_texture.resource().clone();
+                    shared.images_2d_array.push(shared_texture);
+                    anyhow::Ok(texture)
+                })
+                .collect::<anyhow::Result<Vec<TextureContainer2dArray>>>()?,
+        );
+        Ok(ClientMapTextures {
+            images,
+            images_2d_array,
+            shared,
+        })
+    }
 }
 
-pub type ClientMapSoundsLoading = Vec<ClientMapSoundLoading>;
+#[derive(Debug, Hiarc, Default)]
+pub struct ClientMapSoundsLoading {
+    pub sounds: Vec<ClientMapSoundLoading>,
+    pub sounds_dedup: ClientMapResourceDedup,
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::Cell, collections::HashMap, rc::Rc, sync::Arc};
+
+    use graphics::{graphics::graphics::Graphics, graphics_mt::GraphicsMultiThreaded};
+    use graphics_base_traits::traits::{GraphicsStreamVertices, GraphicsStreamedData};
+    use graphics_types::{
+        commands::{AllCommands, CommandsMisc},
+        types::WindowProps,
+    };
+    use map::map::resources::{MapResourceMetaData, MapResourceRef};
+    use pool::mt_datatypes::PoolVec;
+
+    use crate::map::test_util::{NullBackend, VecBackendMt};
+
+    use super::{ClientMapImagesLoading, ClientMapResourceDedup, ClientMapResourceRegistry};
+
+    #[test]
+    fn same_image_is_loaded_once() {
+        let graphics = Graphics::new(
+            Rc::new(NullBackend::default()),
+            GraphicsStreamedData::new(
+                GraphicsStreamVertices::Vec(Vec::new()),
+                PoolVec::new_without_pool(),
+            ),
+            WindowProps {
+                canvas_width: 64,
+                canvas_height: 64,
+                window_width: 64.0,
+                window_height: 64.0,
+            },
+        );
+        let graphics_mt = GraphicsMultiThreaded::new(Arc::new(VecBackendMt));
+        let image = |name: &str| MapResourceRef {
+            name: name.try_into().unwrap(),
+            meta: MapResourceMetaData {
+                blake3_hash: [1; 32],
+                ty: "png".try_into().unwrap(),
+            },
+            hq_meta: None,
+        };
+        let img_files = HashMap::from([([1; 32], (vec![255; 2 * 2 * 4], 2, 2))]);
+
+        // two layers use two images of the map with the same content
+        let images = ClientMapImagesLoading::new(
+            &graphics_mt,
+            vec![image("grass"), image("grass2")],
+            Vec::new(),
+            &img_files,
+            false,
+            |_, _, _| unreachable!("the map has no image arrays"),
+        )
+        .unwrap();
+        assert_eq!(images.images.len(), 1);
+        assert_eq!(images.images_dedup.deduped_count(), 1);
+
+        let textures = images
+            .create_textures(&graphics.texture_handle, None)
+            .unwrap();
+        let created_textures = graphics
+            .backend_handle
+            .backend_cmds
+            .take()
+            .into_iter()
+            .filter(|cmd| matches!(cmd, AllCommands::Misc(CommandsMisc::TextureCreate(_))))
+            .count();
+        assert_eq!(created_textures, 1);
+        assert_eq!(textures.images.len(), 2);
+        assert!(textures.images[0].tex_eq(&textures.images[1]));
+    }
+
+    #[test]
+    fn resource_dedup_shares_loaded_resources() {
+        // the first and last image of the map have the same content
+        let dedup = ClientMapResourceDedup::new([[1; 32], [2; 32], [1; 32]]);
+        assert_eq!(dedup.deduped_count(), 1);
+
+        let loaded = dedup.unique_resources(vec!["grass", "sky", "grass copy"]);
+        assert_eq!(loaded, ["grass", "sky"]);
+
+        // a layer using the first and a layer using the last image share a texture
+        let textures = dedup.share_loaded(vec![10, 20]);
+        assert_eq!(textures, [10, 20, 10]);
+
+        let dedup = ClientMapResourceDedup::new([]);
+        assert_eq!(dedup.deduped_count(), 0);
+        assert!(dedup.share_loaded::<u32>(Vec::new()).is_empty());
+    }
+
+    #[test]
+    fn maps_share_textures_of_the_same_image() {
+        let registry: ClientMapResourceRegistry<Rc<&str>> = Default::default();
+        let loads = Cell::new(0);
+        let load = || {
+            loads.set(loads.get() + 1);
+            anyhow::Ok(Rc::new("grass"))
+        };
+
+        // both maps use the same image
+        let first_map = registry.acquire([1; 32], load).unwrap();
+        let second_map = registry.acquire([1; 32], load).unwrap();
+        assert_eq!(loads.get(), 1);
+        assert_eq!(registry.len(), 1);
+        assert!(Rc::ptr_eq(first_map.resource(), second_map.resource()));
+
+        let texture = first_map.resource().clone();
+        drop(first_map);
+        assert_eq!(registry.len(), 1);
+        // the registry and the second map still hold the texture
+        assert_eq!(Rc::strong_count(&texture), 3);
+
+        drop(second_map);
+        assert!(registry.is_empty());
+        assert_eq!(Rc::strong_count(&texture), 1);
+
+        // once all maps are gone, the image is loaded again
+        let _third_map = registry.acquire([1; 32], load).unwrap();
+        assert_eq!(loads.get(), 2);
+    }
+}
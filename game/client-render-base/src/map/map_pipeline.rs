@@ -16,7 +16,8 @@ use graphics_backend_traits::plugin::{
 };
 use graphics_types::{
     commands::{
-        AllCommands, CommandsRender, CommandsRenderMod, SColorf, GRAPHICS_DEFAULT_UNIFORM_SIZE,
+        AllCommands, CommandRenderQuadContainerAsSpriteMultiple, CommandsRender, CommandsRenderMod,
+        CommandsRenderQuadContainer, SColorf, GRAPHICS_DEFAULT_UNIFORM_SIZE,
         GRAPHICS_MAX_UNIFORM_RENDER_COUNT,
     },
     rendering::{ColorRgba, GlColorf, State, StateTexture, StateTexture2dArray},
@@ -61,6 +62,8 @@ pub struct CommandRenderTileLayer {
     pub state: State,
     pub texture_index: StateTexture2dArray,
     pub color: SColorf, // the color of the whole tilelayer -- already enveloped
+    /// Multiplied with `color` when rendering, see [`tint_color`].
+    pub tint: [f32; 4],
 
     pub draws: PoolVec<TileLayerDrawInfo>,
 
@@ -84,6 +87,8 @@ pub struct CommandRenderEditorTileLayer {
     pub state: State,
     pub texture_index: StateTexture2dArray,
     pub color: SColorf, // the color of the whole tilelayer -- already enveloped
+    /// Multiplied with `color` when rendering, see [`tint_color`].
+    pub tint: [f32; 4],
 
     pub render: EditorTileLayerRenderProps,
 
@@ -95,6 +100,8 @@ pub struct CommandRenderBorderTile {
     pub state: State,
     pub texture_index: StateTexture2dArray,
     pub color: SColorf, // the color of the whole tilelayer -- already enveloped
+    /// Multiplied with `color` when rendering, see [`tint_color`].
+    pub tint: [f32; 4],
 
     pub draw: TileLayerDrawInfo,
 
@@ -104,6 +111,16 @@ pub struct CommandRenderBorderTile {
     pub scale: vec2,
 }
 
+/// Applies the runtime tint of a tile layer to its color.
+pub fn tint_color(color: &SColorf, tint: &[f32; 4]) -> SColorf {
+    SColorf {
+        r: color.r * tint[0],
+        g: color.g * tint[1],
+        b: color.b * tint[2],
+        a: color.a * tint[3],
+    }
+}
+
 #[repr(C)]
 #[derive(Debug, Hiarc, Default, Clone, Serialize, Deserialize)]
 pub struct QuadRenderInfo {
@@ -475,7 +492,8 @@ impl MapPipeline {
 
         match texture_index {
             StateTexture2dArray::Texture(texture_index) => {
-                render_execute_manager.set_texture_3d(0, *texture_index);
+                let address_mode_index = render_execute_manager.get_address_mode_index(state);
+                render_execute_manager.set_texture_3d(0, *texture_index, address_mode_index);
             }
             StateTexture2dArray::None => {
                 // nothing to do
@@ -502,7 +520,8 @@ impl MapPipeline {
 
         match texture_index {
             StateTexture2dArray::Texture(texture_index) => {
-                render_execute_manager.set_texture_3d(0, *texture_index);
+                let address_mode_index = render_execute_manager.get_address_mode_index(state);
+                render_execute_manager.set_texture_3d(0, *texture_index, address_mode_index);
             }
             StateTexture2dArray::None => {
                 // nothing to do
@@ -537,7 +556,8 @@ impl MapPipeline {
 
         match &cmd.texture_index {
             StateTexture2dArray::Texture(texture_index) => {
-                render_execute_manager.set_texture_3d(0, *texture_index);
+                let address_mode_index = render_execute_manager.get_address_mode_index(&cmd.state);
+                render_execute_manager.set_texture_3d(0, *texture_index, address_mode_index);
             }
             StateTexture2dArray::None => {
                 // nothing to do
@@ -624,6 +644,7 @@ impl MapPipeline {
         texture_index: &StateTexture2dArray,
         is_border: bool,
         color: &GlColorf,
+        tint: &[f32; 4],
         scale: &vec2,
         off: &vec2,
         draws: &[TileLayerDrawInfo],
@@ -661,7 +682,7 @@ impl MapPipeline {
 
         vertex_push_constants.base.pos = m;
 
-        let frag_push_constants: SUniformTileGVertColor = *color;
+        let frag_push_constants: SUniformTileGVertColor = tint_color(color, tint);
 
         if is_border {
             vertex_push_constants.scale = *scale;
@@ -731,6 +752,7 @@ impl MapPipeline {
             &cmd.texture_index,
             false,
             &cmd.color,
+            &cmd.tint,
             &scale,
             &off,
             &cmd.draws,
@@ -768,7 +790,7 @@ impl MapPipeline {
         vertex_push_constants.scale = vec2::new(cmd.render.w, cmd.render.h);
 
         let frag_push_constants = UniformEditorTileGFrag {
-            color: cmd.color,
+            color: tint_color(&cmd.color, &cmd.tint),
             layer_size: uvec2::new(cmd.render.layer_width, cmd.render.layer_height),
         };
 
@@ -807,6 +829,7 @@ impl MapPipeline {
             &cmd.texture_index,
             true,
             &cmd.color,
+            &cmd.tint,
             &scale,
             &off,
             &[cmd.draw],
@@ -1278,6 +1301,7 @@ impl MapGraphics {
         texture: TextureType2dArray,
         shader_storage: &ShaderStorage,
         color: &ColorRgba,
+        tint: &[f32; 4],
         draws: PoolVec<TileLayerDrawInfo>,
     ) {
         if draws.is_empty() {
@@ -1290,6 +1314,7 @@ impl MapGraphics {
             texture_index: texture.into(),
             shader_storage_index: shader_storage.get_index_unsafe(),
             color: *color,
+            tint: *tint,
 
             draws,
         };
@@ -1318,6 +1343,7 @@ impl MapGraphics {
         texture: TextureType2dArray,
         shader_storage: &ShaderStorage,
         color: &ColorRgba,
+        tint: &[f32; 4],
         render: EditorTileLayerRenderProps,
     ) {
         // add the VertexArrays and draw
@@ -1326,6 +1352,7 @@ impl MapGraphics {
             texture_index: texture.into(),
             shader_storage_index: shader_storage.get_index_unsafe(),
             color: *color,
+            tint: *tint,
 
             render,
         };
@@ -1354,6 +1381,7 @@ impl MapGraphics {
         texture: TextureType2dArray,
         buffer_object_index: &BufferObject,
         color: &ColorRgba,
+        tint: &[f32; 4],
         offset: &vec2,
         scale: &vec2,
         quad_offset: usize,
@@ -1375,6 +1403,7 @@ impl MapGraphics {
             buffer_object_index: buffer_object_index.get_index_unsafe(),
 
             color: *color,
+            tint: *tint,
 
             offset: *offset,
             scale: *scale,
@@ -1486,4 +1515,44 @@ impl MapGraphics {
                 },
             )));
     }
+
+    /// Renders `instance_count` instances of the template quad `template`
+    /// of the instanced quads of a quad layer.
+    /// The centers of the instances are read from the sprite uniforms
+    /// at `instance_uniform_instance`.
+    pub fn render_quad_instances(
+        &self,
+        state: &State,
+        texture: TextureType,
+        buffer_object: &BufferObject,
+        template: usize,
+        instance_uniform_instance: usize,
+        instance_count: usize,
+    ) {
+        if instance_count == 0 {
+            return;
+        }
+
+        let cmd = CommandRenderQuadContainerAsSpriteMultiple {
+            state: *state,
+            texture_index: texture.into(),
+
+            quad_num: 1,
+            instance_count,
+            quad_offset: template,
+            buffer_object_index: buffer_object.get_index_unsafe(),
+
+            vertex_color: ColorRgba::new(1.0, 1.0, 1.0, 1.0),
+
+            // the template is relative to the center of the instances
+            center: vec2::default(),
+
+            render_info_uniform_instance: instance_uniform_instance,
+        };
+
+        self.backend_handle
+            .add_cmd(AllCommands::Render(CommandsRender::QuadContainer(
+                CommandsRenderQuadContainer::RenderAsSpriteMultiple(cmd),
+            )));
+    }
 }
@@ -187,7 +187,9 @@ impl MapSoundProcess {
                     let base_props = SoundPlayBaseProps {
                         pos: SoundPlayBasePos::Global,
                         looped: sound.looped,
-                        volume: volume * falloff.x.max(falloff.y) as f64 * map_sound_volume,
+                        volume: volume
+                            * sounds.attenuation().apply(falloff.x.max(falloff.y) as f64)
+                            * map_sound_volume,
                         panning: panning as f64,
                         playback_speed: 1.0,
                     };
@@ -195,7 +197,7 @@ impl MapSoundProcess {
                         sounds.play(
                             index,
                             sound_object.play(SoundPlayProps {
-                                base: base_props,
+                                base: sounds.layer_props(base_props),
                                 start_time_delay: sound.time_delay,
                                 min_distance: 1.0,
                                 max_distance: 50.0,
@@ -296,3 +298,80 @@ impl MapSoundProcess {
         )
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{rc::Rc, time::Duration};
+
+    use sound::{
+        commands::{SoundCommand, SoundCommandPlay},
+        sound::SoundManager,
+        sound_mt_types::SoundBackendMemory,
+        types::{SoundPlayBasePos, SoundPlayBaseProps, SoundPlayProps},
+    };
+
+    use crate::map::{
+        map_buffered::{SoundLayerAttenuation, SoundLayerSounds},
+        test_util::RecordingSoundBackend,
+    };
+
+    #[test]
+    fn layer_volume_scales_sound_updates() {
+        let backend = Rc::new(RecordingSoundBackend::default());
+        let sound = SoundManager::new(backend.clone()).unwrap();
+        let scene = sound.scene_handle.create(Default::default());
+        let sound_object = scene
+            .sound_object_handle
+            .create(SoundBackendMemory::Vector { data: Vec::new() });
+
+        let props = |volume| SoundPlayBaseProps {
+            pos: SoundPlayBasePos::Global,
+            looped: true,
+            volume,
+            panning: 0.5,
+            playback_speed: 1.0,
+        };
+
+        let sounds = SoundLayerSounds::default();
+        for index in 0..2 {
+            sounds.play(
+                index,
+                sound_object.play(SoundPlayProps {
+                    base: props(1.0),
+                    start_time_delay: Duration::ZERO,
+                    min_distance: 1.0,
+                    max_distance: 50.0,
+                    pow_attenuation_value: None,
+                    spatial: false,
+                }),
+            );
+        }
+
+        sounds.set_layer_volume(0.5);
+        sounds.update(0, props(1.0));
+        sounds.update(1, props(0.5));
+        sound.swap();
+
+        let volumes: Vec<_> = backend
+            .cmds
+            .borrow()
+            .iter()
+            .filter_map(|cmd| match cmd {
+                SoundCommand::Play(SoundCommandPlay::Update { play_id, props, .. }) => {
+                    Some((*play_id, props.volume))
+                }
+                _ => None,
+            })
+            .collect();
+        assert_eq!(volumes, [(0, 0.5), (1, 0.25)]);
+
+        // negative volumes are clamped
+        sounds.set_layer_volume(-1.0);
+        assert_eq!(sounds.layer_volume(), 0.0);
+
+        assert_eq!(SoundLayerAttenuation::Linear.apply(0.5), 0.5);
+        assert_eq!(SoundLayerAttenuation::Quadratic.apply(0.5), 0.25);
+        assert_eq!(SoundLayerAttenuation::Smooth.apply(0.5), 0.5);
+        assert_eq!(SoundLayerAttenuation::Smooth.apply(2.0), 1.0);
+    }
+}
@@ -1,6 +1,7 @@
 pub mod map_buffered;
 
 pub mod map;
+pub mod map_diff;
 pub mod map_image;
 pub mod map_pipeline;
 pub mod map_sound;
@@ -8,3 +9,5 @@ pub mod map_with_visual;
 pub mod render_map_base;
 pub mod render_pipe;
 pub mod render_tools;
+#[cfg(test)]
+mod test_util;
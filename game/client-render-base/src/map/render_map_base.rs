@@ -1,25 +1,29 @@
 use std::{
     collections::{HashMap, HashSet},
-    path::Path,
+    path::{Path, PathBuf},
     sync::Arc,
 };
 
 use super::{
     map::RenderMap,
-    map_buffered::{ClientMapBufferUploadData, ClientMapBuffered},
+    map_buffered::{
+        upload_cache::{self, ClientMapBufferUploadCache},
+        ClientMapBufferUploadData, ClientMapBuffered, ClientMapBufferedBuilder,
+    },
     map_image::{
-        ClientMapImageLoading, ClientMapImagesLoading, ClientMapSoundLoading,
-        ClientMapSoundsLoading,
+        ClientMapImagesLoading, ClientMapResourceDedup, ClientMapSharedTextures,
+        ClientMapSoundLoading, ClientMapSoundsLoading, ClientMapTextureRegistry, ClientMapTextures,
     },
 };
 use anyhow::anyhow;
 use assets_base::verify::ogg_vorbis::verify_ogg_vorbis;
 use base::{
     benchmark::Benchmark,
-    hash::{fmt_hash, Hash},
+    hash::{fmt_hash, generate_hash_for, Hash},
     join_all,
 };
 use base_io::{io::Io, path_to_url::relative_path_to_url, runtime::IoRuntimeTask};
+use cache::Cache;
 use config::config::ConfigDebug;
 use graphics::{
     graphics::graphics::Graphics,
@@ -28,8 +32,7 @@ use graphics::{
         buffer_object::buffer_object::GraphicsBufferObjectHandle,
         canvas::canvas::GraphicsCanvasHandle,
         shader_storage::shader_storage::GraphicsShaderStorageHandle,
-        stream::stream::GraphicsStreamHandle,
-        texture::texture::{GraphicsTextureHandle, TextureContainer, TextureContainer2dArray},
+        stream::stream::GraphicsStreamHandle, texture::texture::GraphicsTextureHandle,
     },
 };
 use graphics_types::{commands::TexFlags, types::GraphicsMemoryAllocationType};
@@ -37,16 +40,28 @@ use image_utils::{
     png::{is_png_image_valid, load_png_image_as_rgba, resize_rgba, PngValidatorOptions},
     utils::{highest_bit, texture_2d_to_3d},
 };
-use map::{file::MapFileReader, map::Map};
+use map::{
+    file::MapFileReader,
+    map::{resources::MapResourceRef, Map},
+};
 use math::math::vector::vec2;
 use rayon::iter::{IntoParallelIterator, ParallelIterator};
 use sound::{commands::SoundSceneCreateProps, scene_handle::SoundSceneHandle, sound::SoundManager};
 use url::Url;
 use vanilla::collision::collision::Collision;
 
+/// The cache of the prepared map buffers, see [`ClientMapBufferUploadCache`].
+const MAP_UPLOAD_CACHE_PATH: &str = "map-upload";
+
 pub struct ClientMapFileData {
     pub collision: Box<Collision>,
     pub buffered_map: ClientMapBuffered,
+    /// The amount of images and sounds that share their
+    /// loaded resource with another one with the same blake3 hash.
+    pub deduped_resources: usize,
+    /// The textures this map shares with other loaded maps,
+    /// see [`RenderMapLoading::share_textures_with`].
+    pub shared_textures: ClientMapSharedTextures,
 }
 
 pub struct ClientMapRenderAndFile {
@@ -73,7 +88,11 @@ pub struct RenderMapLoading {
     pub sound_scene_handle: SoundSceneHandle,
     pub scene_create_props: SoundSceneCreateProps,
 
+    pub texture_registry: Option<ClientMapTextureRegistry>,
+
     pub do_benchmarks: bool,
+    /// See [`ConfigDebug::verify_map_uploads`].
+    pub verify_uploads: bool,
 }
 
 impl RenderMapLoading {
@@ -99,6 +118,7 @@ impl RenderMapLoading {
         Self {
             task: io.rt.spawn(async move {
                 let benchmark = Benchmark::new(do_benchmark);
+                let map_hash = generate_hash_for(&file);
                 let map_reader = MapFileReader::new(file)?;
                 // open the map file
                 let resources = Map::read_resources_and_header(&map_reader)?;
@@ -242,7 +262,22 @@ impl RenderMapLoading {
                     }
                 }
 
+                let upload_cache = Cache::<{ upload_cache::VERSION }>::new_async(
+                    MAP_UPLOAD_CACHE_PATH,
+                    &file_system,
+                )
+                .await;
+                let upload_cache_path: PathBuf = format!("{}.cached", fmt_hash(&map_hash)).into();
+                let cached_upload = upload_cache.read_named(&upload_cache_path).await.ok();
+
                 let resources_clone = resources.clone();
+                let resource_hash = |res: &MapResourceRef| {
+                    load_hq_assets
+                        .then_some(res.hq_meta.as_ref())
+                        .flatten()
+                        .unwrap_or(&res.meta)
+                        .blake3_hash
+                };
 
                 let generate_3d_data = |w: usize, h: usize, img_data: &[u8]| {
                     // first check image dimensions
@@ -334,82 +369,26 @@ impl RenderMapLoading {
                                 })
                                 .collect::<anyhow::Result<HashMap<Hash, (Vec<u8>, u32, u32)>>>()?;
 
-                            let images_loading = ClientMapImagesLoading {
-                                images: resources_clone
-                                    .images
-                                    .into_par_iter()
-                                    .map(|img| {
-                                        let meta = if let Some(hq_meta) =
-                                            load_hq_assets.then_some(img.hq_meta.as_ref()).flatten()
-                                        {
-                                            hq_meta
-                                        } else {
-                                            &img.meta
-                                        };
-                                        let (img_data, width, height) = img_files
-                                            .get(&meta.blake3_hash)
-                                            .ok_or(anyhow!("img with that name not found"))?;
-                                        let mut loading_img = ClientMapImageLoading {
-                                            mem: graphics_mt.mem_alloc(
-                                                GraphicsMemoryAllocationType::TextureRgbaU8 {
-                                                    width: (*width as usize).try_into().unwrap(),
-                                                    height: (*height as usize).try_into().unwrap(),
-                                                    flags: TexFlags::empty(),
-                                                },
-                                            ),
-                                            width: *width,
-                                            height: *height,
-                                            depth: 1,
-                                            name: img.name.to_string(),
-                                        };
-                                        loading_img.mem.as_mut_slice().copy_from_slice(img_data);
-                                        if graphics_mt
-                                            .try_flush_mem(&mut loading_img.mem, false)
-                                            .is_err()
-                                        {
-                                            // TODO: handle/log ?
-                                        }
-                                        anyhow::Ok(loading_img)
-                                    })
-                                    .collect::<anyhow::Result<Vec<ClientMapImageLoading>>>()?,
-                                images_2d_array: resources_clone
-                                    .image_arrays
-                                    .into_par_iter()
-                                    .map(|img| {
-                                        let meta = if let Some(hq_meta) =
-                                            load_hq_assets.then_some(img.hq_meta.as_ref()).flatten()
-                                        {
-                                            hq_meta
-                                        } else {
-                                            &img.meta
-                                        };
-                                        let (img_data, width, height) = img_files
-                                            .get(&meta.blake3_hash)
-                                            .ok_or(anyhow!("img with that name not found"))?;
-                                        let (width, height, depth, mem) = generate_3d_data(
-                                            *width as usize,
-                                            *height as usize,
-                                            img_data,
-                                        );
-                                        anyhow::Ok(ClientMapImageLoading {
-                                            mem,
-                                            width: width as u32,
-                                            height: height as u32,
-                                            depth: depth as u32,
-                                            name: img.name.to_string(),
-                                        })
-                                    })
-                                    .collect::<anyhow::Result<Vec<ClientMapImageLoading>>>()?,
-                            };
+                            let images_loading = ClientMapImagesLoading::new(
+                                &graphics_mt,
+                                resources_clone.images,
+                                resources_clone.image_arrays,
+                                &img_files,
+                                load_hq_assets,
+                                &generate_3d_data,
+                            )?;
 
                             benchmark.bench_multi("decompressing all map images");
                             anyhow::Ok(images_loading)
                         },
                         || {
                             benchmark.bench_multi("decompressing all sounds");
-                            anyhow::Ok(
-                                resources_clone
-                                    .sounds
+                            let sounds_dedup = ClientMapResourceDedup::new(
+                                resources_clone.sounds.iter().map(resource_hash),
+                            );
+                            anyhow::Ok(ClientMapSoundsLoading {
+                                sounds: sounds_dedup
+                                    .unique_resources(resources_clone.sounds)
                                     .into_par_iter()
                                     .map(|img| {
                                         let meta = if let Some(hq_meta) =
@@ -430,7 +409,8 @@ impl RenderMapLoading {
                                         anyhow::Ok(ClientMapSoundLoading { mem })
                                     })
                                     .collect::<anyhow::Result<Vec<_>>>()?,
-                            )
+                                sounds_dedup,
+                            })
                         },
                         || {
                             let map =
@@ -447,10 +427,32 @@ impl RenderMapLoading {
                                     collision
                                 },
                                 || {
-                                    let upload_data =
-                                        ClientMapBuffered::prepare_upload(&graphics_mt, map);
-                                    benchmark.bench_multi("preparing the map buffering");
-                                    upload_data
+                                    let cache = cached_upload.as_deref().map(|file| {
+                                        ClientMapBufferUploadCache::decode(file, &map_hash, &map)
+                                    });
+                                    match cache {
+                                        Some(Ok(cache)) => {
+                                            let upload_data =
+                                                cache.into_upload_data(&graphics_mt, map);
+                                            benchmark.bench_multi(
+                                                "loading the map buffering from the cache",
+                                            );
+                                            (upload_data, true)
+                                        }
+                                        cache => {
+                                            if let Some(Err(err)) = cache {
+                                                log::info!(
+                                                    "ignoring the map buffering cache: {err}"
+                                                );
+                                            }
+                                            let upload_data = ClientMapBuffered::prepare_upload(
+                                                &graphics_mt,
+                                                map,
+                                            );
+                                            benchmark.bench_multi("preparing the map buffering");
+                                            (upload_data, false)
+                                        }
+                                    }
                                 },
                             );
 
@@ -461,7 +463,21 @@ impl RenderMapLoading {
 
                 benchmark.bench("loading the full map (excluding opening it)");
 
-                let (collision, upload_data) = map_prepare?;
+                let (collision, (upload_data, upload_data_from_cache)) = map_prepare?;
+                if !upload_data_from_cache {
+                    let cache_file = ClientMapBufferUploadCache::new(&upload_data, map_hash)
+                        .and_then(|cache| cache.encode());
+                    let written = match cache_file {
+                        Ok(cache_file) => upload_cache
+                            .write_named(&upload_cache_path, cache_file)
+                            .await
+                            .map_err(anyhow::Error::from),
+                        Err(err) => Err(err),
+                    };
+                    if let Err(err) = written {
+                        log::info!("could not cache the map buffering: {err}");
+                    }
+                }
                 Ok(ClientMapFileProcessed {
                     collision,
                     upload_data,
@@ -479,10 +495,21 @@ impl RenderMapLoading {
             sound_scene_handle: sound.scene_handle.clone(),
             scene_create_props,
 
+            texture_registry: None,
+
             do_benchmarks: config.bench,
+            verify_uploads: config.verify_map_uploads,
         }
     }
 
+    /// Images with the same blake3 hash as an image of another map
+    /// loaded with the same registry share its texture,
+    /// instead of being uploaded to the GPU again.
+    pub fn share_textures_with(mut self, registry: &ClientMapTextureRegistry) -> Self {
+        self.texture_registry = Some(registry.clone());
+        self
+    }
+
     fn verify_resource(
         file_ty: &str,
         file_name: &str,
@@ -566,51 +593,61 @@ impl ClientMapRender {
                         let do_benchmark = map_upload.do_benchmarks;
                         let benchmark = Benchmark::new(do_benchmark);
 
-                        let images = map_file
-                            .images
-                            .images
-                            .into_iter()
-                            .map(|img| {
-                                map_upload
-                                    .texture_handle
-                                    .load_texture_rgba_u8(img.mem, &img.name)
-                            })
-                            .collect::<anyhow::Result<Vec<TextureContainer>>>()?;
-                        let images_2d_array = map_file
-                            .images
-                            .images_2d_array
-                            .into_iter()
-                            .map(|img| {
-                                map_upload
-                                    .texture_handle
-                                    .load_texture_2d_array_rgba_u8(img.mem, &img.name)
-                            })
-                            .collect::<anyhow::Result<Vec<TextureContainer2dArray>>>()?;
+                        let sounds_dedup = map_file.sounds.sounds_dedup;
+                        let deduped_resources = map_file.images.images_dedup.deduped_count()
+                            + map_file.images.images_2d_array_dedup.deduped_count()
+                            + sounds_dedup.deduped_count();
+
+                        let ClientMapTextures {
+                            images,
+                            images_2d_array,
+                            shared: shared_textures,
+                        } = map_file.images.create_textures(
+                            &map_upload.texture_handle,
+                            map_upload.texture_registry.as_ref(),
+                        )?;
 
                         // sound scene
                         let scene = map_upload
                             .sound_scene_handle
                             .create(map_upload.scene_create_props);
                         let listener = scene.sound_listener_handle.create(vec2::default());
-                        let sound_objects: Vec<_> = map_file
-                            .sounds
-                            .into_iter()
-                            .map(|sound| scene.sound_object_handle.create(sound.mem))
-                            .collect();
+                        let sound_objects = sounds_dedup.share_loaded(
+                            map_file
+                                .sounds
+                                .sounds
+                                .into_iter()
+                                .map(|sound| scene.sound_object_handle.create(sound.mem))
+                                .collect(),
+                        );
 
                         benchmark.bench("creating the image graphics cmds");
 
-                        let map_buffered = ClientMapBuffered::new(
+                        let mut builder = ClientMapBufferedBuilder::new(
                             &map_upload.backend_handle,
                             &map_upload.shader_storage_handle,
                             &map_upload.buffer_object_handle,
                             map_file.upload_data,
-                            images,
-                            images_2d_array,
-                            scene,
-                            listener,
-                            sound_objects,
-                        );
+                        )?;
+                        if map_upload.verify_uploads {
+                            builder = builder.verify_uploads();
+                        }
+                        let mut map_buffered =
+                            builder.finish(images, images_2d_array, scene, listener, sound_objects);
+                        if map_buffered.upload_verification.is_enabled() {
+                            // the buffers can only be read back after the backend created them
+                            map_upload
+                                .backend_handle
+                                .run_backend_buffer(map_upload.stream_handle.stream_data());
+                            map_buffered.verify_uploads();
+                        }
+                        for missing_layer in &map_buffered.missing_layers {
+                            log::warn!(
+                                "map layer {:?} is not rendered: {}",
+                                missing_layer.location,
+                                missing_layer.err
+                            );
+                        }
 
                         benchmark.bench("creating the map buffers graphics cmds");
 
@@ -618,6 +655,8 @@ impl ClientMapRender {
                             data: ClientMapFileData {
                                 collision: map_file.collision,
                                 buffered_map: map_buffered,
+                                deduped_resources,
+                                shared_textures,
                             },
                             render: RenderMap::new(
                                 &map_upload.backend_handle,
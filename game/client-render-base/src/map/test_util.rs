@@ -0,0 +1,346 @@
+//! Mocks and fixtures shared by the tests of the map modules.
+
+use std::{cell::RefCell, collections::HashMap, sync::Arc};
+
+use graphics_backend_traits::{
+    frame_fetcher_plugin::BackendFrameFetcher,
+    plugin::GraphicsObjectRewriteFunc,
+    traits::{GraphicsBackendInterface, GraphicsBackendMtInterface},
+    types::BackendCommands,
+};
+use graphics_base_traits::traits::GraphicsStreamedData;
+use graphics_types::{
+    commands::{AllCommands, CommandsMisc},
+    gpu::Gpus,
+    types::{
+        GraphicsBackendMemory, GraphicsBackendMemoryAllocation, GraphicsMemoryAllocationMode,
+        GraphicsMemoryAllocationType,
+    },
+};
+use map::{
+    map::{
+        config::Config,
+        groups::{
+            layers::{
+                design::{MapLayer, MapLayerQuad, MapLayerQuadsAttrs, MapLayerTile},
+                physics::{MapLayerPhysics, MapLayerTilePhysicsBase},
+                tiles::{MapTileLayerAttr, TileBase, TileFlags},
+            },
+            MapGroup, MapGroupAttr, MapGroupPhysics, MapGroupPhysicsAttr, MapGroups,
+        },
+        metadata::Metadata,
+        resources::Resources,
+        Map,
+    },
+    skeleton::groups::layers::design::{MapLayerQuadSkeleton, MapLayerTileSkeleton},
+    types::NonZeroU16MinusOne,
+};
+use math::math::vector::{nffixed, nfvec4};
+use pool::{mixed_pool::PoolSyncPoint, mt_datatypes::PoolVec};
+use sound::{
+    backend_types::{SoundBackendInterface, SoundManagerMtInterface},
+    commands::SoundCommand,
+    sound_mt_types::SoundBackendMemory,
+};
+
+use super::{
+    map_buffered::{
+        QuadLayerVisuals, TileLayerBufferedVisualObjects, TileLayerBufferedVisuals,
+        TileLayerVisuals, TileLayerVisualsBase,
+    },
+    map_with_visual::MapVisualLayer,
+};
+
+/// Allocates all memory on the heap.
+#[derive(Debug)]
+pub(crate) struct VecBackendMt;
+
+impl GraphicsBackendMtInterface for VecBackendMt {
+    fn mem_alloc(
+        &self,
+        alloc_type: GraphicsMemoryAllocationType,
+        _mode: GraphicsMemoryAllocationMode,
+    ) -> GraphicsBackendMemory {
+        let size = match alloc_type {
+            GraphicsMemoryAllocationType::VertexBuffer { required_size }
+            | GraphicsMemoryAllocationType::ShaderStorage { required_size } => required_size.get(),
+            GraphicsMemoryAllocationType::TextureRgbaU8 { width, height, .. } => {
+                width.get() * height.get() * 4
+            }
+            _ => panic!("maps only upload buffers and rgba textures"),
+        };
+        GraphicsBackendMemory::new(
+            GraphicsBackendMemoryAllocation::Vector(vec![0; size]),
+            alloc_type,
+        )
+    }
+
+    fn try_flush_mem(
+        &self,
+        _mem: &mut GraphicsBackendMemory,
+        _do_expensive_flushing: bool,
+    ) -> anyhow::Result<()> {
+        Ok(())
+    }
+}
+
+/// Discards all commands, except for the buffers that are explicitly
+/// [executed](NullBackend::execute), so that they can be read back.
+#[derive(Debug, Default)]
+pub(crate) struct NullBackend {
+    pub(crate) buffer_objects: RefCell<HashMap<u128, Vec<u8>>>,
+    pub(crate) shader_storages: RefCell<HashMap<u128, Vec<u8>>>,
+}
+
+impl NullBackend {
+    /// Keeps the created buffer objects & shader storages in memory.
+    pub(crate) fn execute(&self, cmds: Vec<AllCommands>) {
+        let mut buffer_objects = self.buffer_objects.borrow_mut();
+        let mut shader_storages = self.shader_storages.borrow_mut();
+        for cmd in cmds {
+            match cmd {
+                AllCommands::Misc(CommandsMisc::CreateBufferObject(cmd)) => {
+                    buffer_objects.insert(cmd.buffer_index, cmd.upload_data.as_slice().to_vec());
+                }
+                AllCommands::Misc(CommandsMisc::DeleteBufferObject(cmd)) => {
+                    buffer_objects.remove(&cmd.buffer_index);
+                }
+                AllCommands::Misc(CommandsMisc::CreateShaderStorage(cmd)) => {
+                    shader_storages.insert(
+                        cmd.shader_storage_index,
+                        cmd.upload_data.as_slice().to_vec(),
+                    );
+                }
+                AllCommands::Misc(CommandsMisc::DeleteShaderStorage(cmd)) => {
+                    shader_storages.remove(&cmd.shader_storage_index);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn read(
+        buffers: &RefCell<HashMap<u128, Vec<u8>>>,
+        index: u128,
+        offset: usize,
+        size: usize,
+    ) -> anyhow::Result<Vec<u8>> {
+        buffers
+            .borrow()
+            .get(&index)
+            .and_then(|data| data.get(offset..offset + size))
+            .map(|data| data.to_vec())
+            .ok_or_else(|| anyhow::anyhow!("no such buffer"))
+    }
+}
+
+impl GraphicsBackendInterface for NullBackend {
+    fn run_cmds(&self, _buffer: &BackendCommands, _stream_data: &GraphicsStreamedData) {}
+
+    fn check_mod_cmd(
+        &self,
+        _mod_name: &str,
+        _cmd: &mut PoolVec<u8>,
+        _f: &dyn Fn(GraphicsObjectRewriteFunc),
+    ) {
+    }
+
+    fn mem_alloc(&self, alloc_type: GraphicsMemoryAllocationType) -> GraphicsBackendMemory {
+        VecBackendMt.mem_alloc(alloc_type, GraphicsMemoryAllocationMode::Immediate)
+    }
+
+    fn attach_frame_fetcher(
+        &self,
+        _name: String,
+        _fetcher: Arc<dyn BackendFrameFetcher>,
+    ) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    fn detach_frame_fetcher(&self, _name: String) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    fn wait_idle(&self) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    fn add_sync_point(&self, _sync_point: Box<dyn PoolSyncPoint>) {}
+
+    fn gpus(&self) -> Arc<Gpus> {
+        panic!("map uploads do not query gpus")
+    }
+
+    fn get_backend_mt(&self) -> Arc<dyn GraphicsBackendMtInterface + Sync + Send + 'static> {
+        Arc::new(VecBackendMt)
+    }
+
+    fn read_buffer_object(
+        &self,
+        buffer_index: u128,
+        offset: usize,
+        size: usize,
+    ) -> anyhow::Result<Vec<u8>> {
+        Self::read(&self.buffer_objects, buffer_index, offset, size)
+    }
+
+    fn read_shader_storage(
+        &self,
+        shader_storage_index: u128,
+        offset: usize,
+        size: usize,
+    ) -> anyhow::Result<Vec<u8>> {
+        Self::read(&self.shader_storages, shader_storage_index, offset, size)
+    }
+}
+
+/// A 1x1 tile layer without uploaded buffers.
+pub(crate) fn tile_layer(name: &str) -> MapVisualLayer {
+    let obj = || TileLayerBufferedVisualObjects {
+        buffer_object: None,
+        shader_storage: None,
+    };
+    MapVisualLayer::Tile(MapLayerTileSkeleton {
+        layer: MapLayerTile {
+            attr: MapTileLayerAttr {
+                width: NonZeroU16MinusOne::new(1).unwrap(),
+                height: NonZeroU16MinusOne::new(1).unwrap(),
+                color: nfvec4::new(
+                    nffixed::const_from_int(1),
+                    nffixed::const_from_int(1),
+                    nffixed::const_from_int(1),
+                    nffixed::const_from_int(1),
+                ),
+                high_detail: false,
+                color_anim: None,
+                color_anim_offset: time::Duration::ZERO,
+                image_array: None,
+            },
+            tiles: vec![Default::default()],
+            name: name.into(),
+        },
+        user: TileLayerVisuals {
+            base: TileLayerBufferedVisuals {
+                base: TileLayerVisualsBase::default(),
+                obj: obj(),
+            },
+            tile_index_obj: obj(),
+            tile_flag_obj: obj(),
+        },
+    })
+}
+
+/// A quad layer without quads.
+pub(crate) fn quad_layer(name: &str) -> MapVisualLayer {
+    MapVisualLayer::Quad(MapLayerQuadSkeleton {
+        layer: MapLayerQuad {
+            attr: MapLayerQuadsAttrs {
+                image: None,
+                high_detail: false,
+            },
+            quads: Vec::new(),
+            name: name.into(),
+        },
+        user: QuadLayerVisuals {
+            buffer_object_index: None,
+            draw_ranges: Vec::new(),
+            pos_anims: Vec::new(),
+            color_anims: Vec::new(),
+            instanced_buffer_object_index: None,
+            instance_offsets: Vec::new(),
+        },
+    })
+}
+
+pub(crate) fn test_group(layers: Vec<MapLayer>) -> MapGroup {
+    MapGroup {
+        attr: MapGroupAttr::default(),
+        layers,
+        name: "group".into(),
+    }
+}
+
+/// A map with a 2x1 game layer and the given design groups.
+pub(crate) fn test_map(background: Vec<MapGroup>, foreground: Vec<MapGroup>) -> Map {
+    Map {
+        resources: Resources {
+            images: Vec::new(),
+            image_arrays: Vec::new(),
+            sounds: Vec::new(),
+        },
+        groups: MapGroups {
+            physics: MapGroupPhysics {
+                attr: MapGroupPhysicsAttr {
+                    width: NonZeroU16MinusOne::new(2).unwrap(),
+                    height: NonZeroU16MinusOne::new(1).unwrap(),
+                },
+                layers: vec![MapLayerPhysics::Game(MapLayerTilePhysicsBase {
+                    tiles: vec![
+                        TileBase {
+                            index: 1,
+                            flags: TileFlags::empty(),
+                        };
+                        2
+                    ],
+                })],
+            },
+            background,
+            foreground,
+        },
+        animations: Default::default(),
+        config: Config {
+            config_variables: Default::default(),
+            commands: Default::default(),
+        },
+        meta: Metadata {
+            authors: Vec::new(),
+            licenses: Vec::new(),
+            version: String::new(),
+            credits: String::new(),
+            memo: String::new(),
+        },
+    }
+}
+
+#[derive(Debug)]
+struct VecSoundBackendMt;
+
+impl SoundManagerMtInterface for VecSoundBackendMt {
+    fn mem_alloc(&self, size: usize) -> SoundBackendMemory {
+        SoundBackendMemory::Vector {
+            data: vec![0; size],
+        }
+    }
+
+    fn try_flush_mem(&self, _mem: &mut SoundBackendMemory) -> anyhow::Result<()> {
+        Ok(())
+    }
+}
+
+/// Keeps all sound commands instead of playing them.
+#[derive(Debug, Default)]
+pub(crate) struct RecordingSoundBackend {
+    pub(crate) cmds: RefCell<Vec<SoundCommand>>,
+}
+
+impl SoundBackendInterface for RecordingSoundBackend {
+    fn run_cmds(&self, mut cmds: Vec<SoundCommand>) {
+        self.cmds.borrow_mut().append(&mut cmds);
+    }
+
+    fn get_backend_mt(&self) -> Arc<dyn SoundManagerMtInterface> {
+        Arc::new(VecSoundBackendMt)
+    }
+
+    fn attach_frame_fetcher(
+        &self,
+        _name: String,
+        _fetcher: Arc<dyn sound::frame_fetcher_plugin::BackendFrameFetcher>,
+    ) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    fn detach_frame_fetcher(&self, _name: String) -> anyhow::Result<()> {
+        Ok(())
+    }
+}
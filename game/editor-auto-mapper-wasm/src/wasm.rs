@@ -4,7 +4,7 @@ use api_wasm_macros::wasm_mod_prepare_auto_mapper;
 pub mod wasm {
     use anyhow::anyhow;
     use api_wasm_macros::wasm_func_auto_call;
-    use wasm_runtime::{MemoryLimit, WasmManager, WasmManagerModuleType};
+    use wasm_runtime::{IntEncoding, MemoryLimit, WasmManager, WasmManagerModuleType};
     use wasmer::Module;
 
     use editor_interface::auto_mapper::{
@@ -27,6 +27,7 @@ pub mod wasm {
                 }),
                 |_, _| None,
                 MemoryLimit::OneGibiByte,
+                IntEncoding::Fixed,
             )?;
             wasm_manager.run_by_name::<()>("auto_mapper_new").unwrap();
             wasm_manager
@@ -17,7 +17,7 @@ pub mod editor_wasm {
     use wasm_logic_graphics::WasmGraphicsLogic;
     use wasm_logic_http::http::WasmHttpLogic;
     use wasm_logic_sound::sound::WasmSoundLogic;
-    use wasm_runtime::{MemoryLimit, WasmManager, WasmManagerModuleType};
+    use wasm_runtime::{IntEncoding, MemoryLimit, WasmManager, WasmManagerModuleType};
     use wasmer::Module;
 
     pub struct EditorWasm {
@@ -53,6 +53,7 @@ pub mod editor_wasm {
                     Some(imports)
                 },
                 MemoryLimit::OneGibiByte,
+                IntEncoding::Fixed,
             )
             .unwrap();
             wasm_manager.add_param(0, &graphics.canvas_handle.window_props());
@@ -2070,6 +2070,10 @@ pub fn do_action(
 
             update_physics_layer(
                 tp,
+                graphics_mt,
+                shader_storage_handle,
+                buffer_object_handle,
+                backend_handle,
                 group_width,
                 group_height,
                 layer,
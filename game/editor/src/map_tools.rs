@@ -2,7 +2,8 @@ use std::{ops::Range, sync::Arc};
 
 use client_render_base::map::map_buffered::{
     ClientMapBufferQuadLayer, ClientMapBuffered, MapBufferPhysicsTileLayer, MapBufferTileLayer,
-    PhysicsTileLayerVisuals, QuadLayerVisuals, TileLayerVisuals,
+    PhysicsTileLayerVisuals, QuadLayerUploadOptions, QuadLayerVisuals, TileLayerUploadOptions,
+    TileLayerVisuals,
 };
 use graphics::{
     graphics_mt::GraphicsMultiThreaded,
@@ -35,8 +36,11 @@ pub fn upload_physics_layer_buffer(
         height,
         tiles,
         0,
-        true,
-        create_tile_index_flag,
+        TileLayerUploadOptions {
+            ignore_tile_index_and_is_textured_check: true,
+            create_tile_index_flag,
+            ..Default::default()
+        },
     )
 }
 
@@ -56,6 +60,10 @@ pub fn finish_physics_layer_buffer(
 
 pub fn update_physics_layer(
     tp: &Arc<rayon::ThreadPool>,
+    graphics_mt: &GraphicsMultiThreaded,
+    shader_storage_handle: &GraphicsShaderStorageHandle,
+    buffer_object_handle: &GraphicsBufferObjectHandle,
+    backend_handle: &GraphicsBackendHandle,
     group_width: NonZeroU16MinusOne,
     group_height: NonZeroU16MinusOne,
     layer: &mut EditorPhysicsLayer,
@@ -66,6 +74,10 @@ pub fn update_physics_layer(
 ) {
     ClientMapBuffered::update_physics_layer(
         tp,
+        graphics_mt,
+        shader_storage_handle,
+        buffer_object_handle,
+        backend_handle,
         group_width,
         group_height,
         layer,
@@ -92,8 +104,11 @@ pub fn upload_design_tile_layer_buffer(
         has_texture,
         0,
         0,
-        true,
-        create_tile_index_flag,
+        TileLayerUploadOptions {
+            ignore_tile_index_and_is_textured_check: true,
+            create_tile_index_flag,
+            ..Default::default()
+        },
     )
 }
 
@@ -127,7 +142,19 @@ pub fn upload_design_quad_layer_buffer(
     attr: &MapLayerQuadsAttrs,
     quads: &[Quad],
 ) -> ClientMapBufferQuadLayer {
-    ClientMapBuffered::upload_design_quad_layer(graphics_mt, attr, quads, 0, 0, true)
+    // the editor updates single quads, which is not possible
+    // for instanced or skipped quads
+    ClientMapBuffered::upload_design_quad_layer(
+        graphics_mt,
+        attr,
+        quads,
+        0,
+        0,
+        QuadLayerUploadOptions {
+            ignore_is_textured_check: true,
+            ..Default::default()
+        },
+    )
 }
 
 pub fn finish_design_quad_layer_buffer(
@@ -1,7 +1,7 @@
 use std::{collections::BTreeMap, time::Duration};
 
 use camera::CameraInterface;
-use client_render_base::map::render_tools::RenderTools;
+use client_render_base::map::map_buffered::{eval_color_anim, eval_pos_anim};
 use graphics::handles::{
     canvas::canvas::GraphicsCanvasHandle,
     stream::stream::{GraphicsStreamHandle, QuadStreamHandle},
@@ -66,13 +66,16 @@ pub fn in_box(pos: &fvec2, x0: f32, y0: f32, x1: f32, y1: f32) -> bool {
 
 pub fn get_quad_points_animated(quad: &Quad, map: &EditorMap, time: Duration) -> [fvec2; 5] {
     let mut points = quad.points;
-    if let Some(pos_anim) = quad.pos_anim {
-        let anim = &map.active_animations().pos[pos_anim];
-        let anim_pos = RenderTools::render_eval_anim(
-            anim.def.points.as_slice(),
-            time::Duration::try_from(time).unwrap(),
+    if let Some(anim_pos) = quad.pos_anim.and_then(|pos_anim| {
+        eval_pos_anim(
+            map.active_animations(),
+            pos_anim,
+            &quad.pos_anim_offset,
+            &time,
+            &time,
             map.user.include_last_anim_point(),
-        );
+        )
+    }) {
         let rot = anim_pos.z / ffixed::from_num(360.0) * ffixed::PI * ffixed::from_num(2.0);
         let center = points[4];
 
@@ -87,14 +90,16 @@ pub fn get_quad_points_animated(quad: &Quad, map: &EditorMap, time: Duration) ->
 
 pub fn get_quad_points_color_animated(quad: &Quad, map: &EditorMap, time: Duration) -> [nfvec4; 4] {
     let mut color = quad.colors;
-    if let Some(color_anim) = quad.color_anim {
-        let anim = &map.active_animations().color[color_anim];
-        let anim_color = RenderTools::render_eval_anim(
-            anim.def.points.as_slice(),
-            time::Duration::try_from(time).unwrap(),
+    if let Some(anim_color) = quad.color_anim.and_then(|color_anim| {
+        eval_color_anim(
+            map.active_animations(),
+            color_anim,
+            &quad.color_anim_offset,
+            &time,
+            &time,
             map.user.include_last_anim_point(),
-        );
-
+        )
+    }) {
         for color in color.iter_mut() {
             color.x *= nffixed::from_num(anim_color.x);
             color.y *= nffixed::from_num(anim_color.y);
@@ -38,7 +38,7 @@ pub mod state_wasm {
     use pool::datatypes::{PoolFxLinkedHashMap, PoolVec};
     use pool::mt_datatypes::PoolCow as MtPoolCow;
     use wasm_logic_db::db::WasmDatabaseLogic;
-    use wasm_runtime::{MemoryLimit, WasmManager, WasmManagerModuleType};
+    use wasm_runtime::{IntEncoding, MemoryLimit, WasmManager, WasmManagerModuleType};
     use wasmer::Module;
 
     use game_interface::{
@@ -78,6 +78,7 @@ pub mod state_wasm {
                     Some(imports)
                 },
                 MemoryLimit::TenMebiBytes,
+                IntEncoding::Fixed,
             )?;
             wasm_manager.add_param(0, &map);
             wasm_manager.add_param(1, &map_name);
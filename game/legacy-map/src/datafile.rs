@@ -53,7 +53,7 @@ use map::{
                     TuneTile,
                 },
             },
-            MapGroup, MapGroupAttr, MapGroupAttrClipping, MapGroupPhysicsAttr,
+            MapGroup, MapGroupAttr, MapGroupAttrClipping, MapGroupPhysics, MapGroupPhysicsAttr,
         },
         metadata::Metadata,
         resources::{MapResourceMetaData, MapResourceRef, Resources},
@@ -210,6 +210,38 @@ impl CDatafileHeader {
         }
     }
 
+    /// The header as it would be read in the other byte order.
+    fn byte_swapped(&self) -> Self {
+        let mut id = self.id;
+        id.reverse();
+        Self {
+            id,
+            version: self.version.swap_bytes(),
+            size: self.size.swap_bytes(),
+            swap_len: self.swap_len.swap_bytes(),
+            num_item_types: self.num_item_types.swap_bytes(),
+            num_items: self.num_items.swap_bytes(),
+            num_raw_data: self.num_raw_data.swap_bytes(),
+            item_size: self.item_size.swap_bytes(),
+            data_size: self.data_size.swap_bytes(),
+        }
+    }
+
+    /// The size of the header, the info and the items,
+    /// which are the integers of the file that precede the raw data.
+    fn integers_size(&self) -> u64 {
+        size_of::<Self>() as u64
+            + self.num_item_types as u64 * size_of::<CDatafileItemType>() as u64
+            + (self.num_items as u64 + self.num_raw_data as u64 * 2) * size_of::<u32>() as u64
+            + self.item_size as u64
+    }
+
+    /// Whether the sizes of the header fit into a file of `file_size` bytes.
+    /// A header read in the wrong byte order practically always exceeds it.
+    fn is_plausible(&self, file_size: usize) -> bool {
+        self.integers_size() + self.data_size as u64 <= file_size as u64
+    }
+
     pub fn write_to_vec(&self, w: &mut Vec<u8>) {
         w.extend(self.id.iter().flat_map(|v| v.to_le_bytes()));
         w.extend(self.version.to_le_bytes());
@@ -284,6 +316,9 @@ pub struct CDatafileWrapper {
 pub struct MapFileOpenOptions {
     pub do_benchmark: bool,
     pub dont_load_map_item: [bool; MapItemTypes::Count as usize],
+    /// Parse maps whose integers are stored in big endian byte order,
+    /// e.g. written by old big endian machines.
+    pub allow_byte_swapped: bool,
 }
 
 #[derive(Default)]
@@ -309,12 +344,30 @@ pub struct LegacyMapToNewRes {
     pub name: String,
 }
 
+/// The resources are ordered like the map's resources reference them,
+/// so converting the same map always emits them in the same order.
 #[derive(Debug)]
 pub struct LegacyMapToNewResources {
     /// blake3 hash
-    pub images: HashMap<Hash, LegacyMapToNewRes>,
+    pub images: LinkedHashMap<Hash, LegacyMapToNewRes>,
     /// blake3 hash
-    pub sounds: HashMap<Hash, LegacyMapToNewRes>,
+    pub sounds: LinkedHashMap<Hash, LegacyMapToNewRes>,
+}
+
+impl LegacyMapToNewResources {
+    /// Orders the resources by their blake3 hash instead,
+    /// which is stable even if the map reorders its resources.
+    pub fn sort_by_hash(&mut self) {
+        fn sorted(
+            resources: LinkedHashMap<Hash, LegacyMapToNewRes>,
+        ) -> LinkedHashMap<Hash, LegacyMapToNewRes> {
+            let mut resources: Vec<_> = resources.into_iter().collect();
+            resources.sort_by(|(hash1, _), (hash2, _)| hash1.cmp(hash2));
+            resources.into_iter().collect()
+        }
+        self.images = sorted(std::mem::take(&mut self.images));
+        self.sounds = sorted(std::mem::take(&mut self.sounds));
+    }
 }
 
 #[derive(Debug)]
@@ -323,6 +376,23 @@ pub struct LegacyMapToNewOutput {
     pub resources: LegacyMapToNewResources,
 }
 
+/// A group that was completely converted by [`CDatafileWrapper::into_map_with_groups`].
+///
+/// The index is the index of the group inside the final map.
+#[derive(Debug, Clone)]
+pub enum ConvertedGroup {
+    Background {
+        index: usize,
+        group: MapGroup,
+    },
+    Foreground {
+        index: usize,
+        group: MapGroup,
+    },
+    /// The physics group is only complete once all other groups are converted.
+    Physics(MapGroupPhysics),
+}
+
 impl Default for CDatafileWrapper {
     fn default() -> Self {
         Self::new()
@@ -375,8 +445,39 @@ impl CDatafileWrapper {
         }
     }
 
+    /// Swaps the byte order of the integers of a datafile,
+    /// which are the header, the info and the items.
+    /// The raw data is kept as is, same as the original reader does.
+    ///
+    /// Swapping twice restores the original file.
+    pub fn swap_byte_order(data: &[u8]) -> anyhow::Result<Vec<u8>> {
+        anyhow::ensure!(
+            data.len() >= size_of::<CDatafileHeader>(),
+            "size is smaller than the header size"
+        );
+        let header = CDatafileHeader::read_from_slice(data);
+        let header = if header.is_plausible(data.len()) {
+            header
+        } else {
+            header.byte_swapped()
+        };
+        anyhow::ensure!(
+            header.is_plausible(data.len()),
+            "the header is neither valid in little nor in big endian byte order"
+        );
+
+        let mut swapped = data.to_vec();
+        swapped[..header.integers_size() as usize]
+            .chunks_exact_mut(size_of::<u32>())
+            .for_each(|int| int.reverse());
+        Ok(swapped)
+    }
+
     /// Returns a tuple of various information about the file:
     /// - the a slice of the data containers of the file vec
+    ///
+    /// Maps in big endian byte order are only parsed if
+    /// [`MapFileOpenOptions::allow_byte_swapped`] is set.
     pub fn open<'a>(
         &mut self,
         data_param: &'a Vec<u8>,
@@ -388,7 +489,26 @@ impl CDatafileWrapper {
         self.name = file_name.to_string();
 
         let mut data_file: CDatafile = CDatafile::default();
+        let swapped_data;
         let mut read_data = data_param.as_slice();
+        if read_data.len() >= std::mem::size_of::<CDatafileHeader>() {
+            let header = CDatafileHeader::read_from_slice(read_data);
+            if !header.is_plausible(read_data.len()) {
+                if !header.byte_swapped().is_plausible(read_data.len()) {
+                    return Err(anyhow!(
+                        "the header is neither valid in little nor in big endian byte order"
+                    ));
+                }
+                if !options.allow_byte_swapped {
+                    return Err(anyhow!(
+                        "the map is stored in big endian byte order, \
+                        which must be allowed explicitly"
+                    ));
+                }
+                swapped_data = Self::swap_byte_order(read_data)?;
+                read_data = swapped_data.as_slice();
+            }
+        }
 
         let mut items: Vec<CDatafileItemAndData> = Vec::new();
         let data_start: &[u8];
@@ -502,8 +622,10 @@ impl CDatafileWrapper {
                 panic!("not supported");
             }
 
-            let (datas, _) = read_data.split_at(data_file.header.data_size as usize);
-            data_start = datas;
+            // the raw data is never swapped, so it can always be borrowed from the file
+            let data_offset = data_param.len() - read_data.len();
+            data_start =
+                &data_param[data_offset..data_offset + data_file.header.data_size as usize];
 
             true
         } {
@@ -1454,8 +1576,37 @@ impl CDatafileWrapper {
         png_validation: PngValidatorOptions,
         dilate: bool,
     ) -> anyhow::Result<LegacyMapToNewOutput> {
-        let mut image_resources: HashMap<Hash, LegacyMapToNewRes> = Default::default();
-        let mut sound_resources: HashMap<Hash, LegacyMapToNewRes> = Default::default();
+        self.into_map_with_groups(thread_pool, images, png_validation, dilate, None)
+    }
+
+    fn emit_last_group(
+        on_group: &mut Option<&mut dyn FnMut(ConvertedGroup)>,
+        is_background: bool,
+        groups: &[MapGroup],
+    ) {
+        if let (Some(on_group), Some(group)) = (on_group.as_deref_mut(), groups.last()) {
+            let index = groups.len() - 1;
+            let group = group.clone();
+            on_group(if is_background {
+                ConvertedGroup::Background { index, group }
+            } else {
+                ConvertedGroup::Foreground { index, group }
+            });
+        }
+    }
+
+    /// Like [`Self::into_map`], but additionally calls `on_group`
+    /// for every group as soon as it is completely converted.
+    pub fn into_map_with_groups(
+        self,
+        thread_pool: &rayon::ThreadPool,
+        images: &[Vec<u8>],
+        png_validation: PngValidatorOptions,
+        dilate: bool,
+        mut on_group: Option<&mut dyn FnMut(ConvertedGroup)>,
+    ) -> anyhow::Result<LegacyMapToNewOutput> {
+        let mut image_resources: LinkedHashMap<Hash, LegacyMapToNewRes> = Default::default();
+        let mut sound_resources: LinkedHashMap<Hash, LegacyMapToNewRes> = Default::default();
 
         let mut map = Map {
             animations: Animations {
@@ -2042,7 +2193,8 @@ impl CDatafileWrapper {
                     String::new()
                 },
             };
-            let mut groups = if !passed_game_layer {
+            let mut groups_are_background = !passed_game_layer;
+            let mut groups = if groups_are_background {
                 &mut map.groups.background
             } else {
                 &mut map.groups.foreground
@@ -2065,8 +2217,11 @@ impl CDatafileWrapper {
                             // if game layer is first layer in group -> ignore empty groups
                             if g.layers.is_empty() {
                                 groups.pop();
+                            } else {
+                                Self::emit_last_group(&mut on_group, groups_are_background, groups);
                             }
-                            groups = if !passed_game_layer {
+                            groups_are_background = !passed_game_layer;
+                            groups = if groups_are_background {
                                 &mut map.groups.background
                             } else {
                                 &mut map.groups.foreground
@@ -2383,8 +2538,13 @@ impl CDatafileWrapper {
             // ignore empty groups
             if g.layers.is_empty() {
                 groups.pop();
+            } else {
+                Self::emit_last_group(&mut on_group, groups_are_background, groups);
             }
         }
+        if let Some(on_group) = on_group {
+            on_group(ConvertedGroup::Physics(map.groups.physics.clone()));
+        }
 
         Ok(LegacyMapToNewOutput {
             map,
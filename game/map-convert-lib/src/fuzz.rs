@@ -0,0 +1,122 @@
+//! Entry points to fuzz the map conversion, e.g. from a `cargo fuzz` target:
+//!
+//! ```ignore
+//! fuzz_target!(|data: &[u8]| map_convert_lib::fuzz::fuzz_legacy_map(data));
+//! ```
+use std::{future::Future, path::Path, pin::Pin, sync::Arc};
+
+use anyhow::anyhow;
+use base::hash::Hash;
+use base_io::{io::create_runtime, runtime::IoRuntime};
+use hashlink::LinkedHashMap;
+use legacy_map::datafile::LegacyMapToNewRes;
+use map::map::{resources::MapResourceRef, Map};
+
+use crate::{
+    legacy_to_new::legacy_to_new_from_buf_async, new_to_legacy::new_to_legacy_from_buf_async,
+};
+
+thread_local! {
+    static FUZZ_RUNTIME: (IoRuntime, Arc<rayon::ThreadPool>) = (
+        IoRuntime::new(create_runtime()),
+        Arc::new(
+            rayon::ThreadPoolBuilder::new()
+                .num_threads(1)
+                .build()
+                .unwrap(),
+        ),
+    );
+}
+
+fn no_external_images(_: &Path) -> Pin<Box<dyn Future<Output = anyhow::Result<Vec<u8>>> + Send>> {
+    Box::pin(async { Err(anyhow!("external images are not loaded while fuzzing.")) })
+}
+
+/// Panics if the structure of both maps differs.
+///
+/// Sounds are ignored, since their hash changes with every conversion.
+fn assert_structural_eq(map: &Map, map2: &Map) {
+    assert_eq!(map.resources.images, map2.resources.images);
+    assert_eq!(map.resources.image_arrays, map2.resources.image_arrays);
+    assert_eq!(map.animations.pos, map2.animations.pos);
+    assert_eq!(map.animations.color, map2.animations.color);
+    assert_eq!(map.animations.sound, map2.animations.sound);
+    assert_eq!(map.groups.background, map2.groups.background);
+    assert_eq!(map.groups.foreground, map2.groups.foreground);
+    assert_eq!(map.groups.physics.attr, map2.groups.physics.attr);
+    assert_eq!(map.groups.physics.layers, map2.groups.physics.layers);
+}
+
+/// Converts `bytes` as legacy map to the new map format and, if that succeeded,
+/// back to the legacy format and once more to the new one.
+///
+/// Inputs that are no valid legacy map are expected and ignored, but a map that
+/// converted once must survive the round trip with the same structure,
+/// else this panics.
+pub fn fuzz_legacy_map(bytes: &[u8]) {
+    let bytes = bytes.to_vec();
+    let maps = FUZZ_RUNTIME.with(|(rt, tp)| {
+        let tp = tp.clone();
+        rt.spawn(async move {
+            let Ok(new_map) =
+                legacy_to_new_from_buf_async(bytes, "fuzz", no_external_images, &tp, false).await
+            else {
+                return Ok(None);
+            };
+
+            let file = new_map.map.write(&tp)?;
+            let bufs = |refs: &[MapResourceRef], bufs: &LinkedHashMap<Hash, LegacyMapToNewRes>| {
+                refs.iter()
+                    .map(|r| {
+                        bufs.get(&r.meta.blake3_hash)
+                            .map(|res| res.buf.clone())
+                            .ok_or_else(|| anyhow!("resource {} is missing.", r.name.as_str()))
+                    })
+                    .collect::<anyhow::Result<Vec<_>>>()
+            };
+            let resources = &new_map.map.resources;
+            let images = bufs(&resources.images, &new_map.resources.images)?;
+            let image_arrays = bufs(&resources.image_arrays, &new_map.resources.images)?;
+            let sounds = bufs(&resources.sounds, &new_map.resources.sounds)?;
+
+            let legacy_map = new_to_legacy_from_buf_async(
+                &file,
+                |_| Box::pin(async move { Ok((images, image_arrays, sounds)) }),
+                &tp,
+            )
+            .await?;
+            let new_map2 = legacy_to_new_from_buf_async(
+                legacy_map.map,
+                "fuzz",
+                no_external_images,
+                &tp,
+                false,
+            )
+            .await?;
+
+            Ok(Some((new_map.map, new_map2.map)))
+        })
+        .get()
+    });
+
+    match maps {
+        Ok(Some((map, map2))) => assert_structural_eq(&map, &map2),
+        Ok(None) => {}
+        Err(err) => panic!("a converted legacy map failed the round trip: {err}"),
+    }
+}
+
+/// A seed corpus for [`fuzz_legacy_map`] from a known-good legacy map.
+///
+/// Contains the map itself and truncated copies of it, so the fuzzer
+/// starts with inputs that already reach deep into the parser.
+/// Each seed is meant to be written as own file into the corpus directory.
+pub fn fuzz_legacy_map_seed_corpus(map: &[u8]) -> Vec<Vec<u8>> {
+    let mut seeds = vec![map.to_vec()];
+    seeds.extend(
+        [2, 4, 8, 16, 64]
+            .into_iter()
+            .map(|divisor| map[..map.len() / divisor].to_vec()),
+    );
+    seeds
+}
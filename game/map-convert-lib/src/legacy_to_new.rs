@@ -5,7 +5,10 @@ use std::{
     num::{NonZeroU32, NonZeroU8},
     path::Path,
     pin::Pin,
-    sync::{Arc, Mutex},
+    sync::{
+        mpsc::{Receiver, Sender},
+        Arc, Mutex,
+    },
 };
 
 use anyhow::anyhow;
@@ -13,15 +16,50 @@ use base::{
     benchmark::Benchmark,
     hash::{generate_hash_for, Hash},
 };
-use base_io::io::IoFileSys;
+use base_io::{io::IoFileSys, runtime::IoRuntimeTask};
 use legacy_map::datafile::{
-    CDatafileWrapper, LegacyMapToNewOutput, LegacyMapToNewRes, MapFileImageReadOptions,
-    MapFileLayersReadOptions, MapFileOpenOptions, MapFileSoundReadOptions,
+    CDatafileWrapper, ConvertedGroup, LegacyMapToNewOutput, LegacyMapToNewRes,
+    MapFileImageReadOptions, MapFileLayersReadOptions, MapFileOpenOptions, MapFileSoundReadOptions,
 };
 use oxipng::optimize_from_memory;
 use rayon::iter::{IntoParallelIterator, IntoParallelRefMutIterator, ParallelIterator};
+use thiserror::Error;
 use vorbis_rs::VorbisEncoderBuilder;
 
+#[derive(Error, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MapNameError {
+    #[error("the map name is empty or only contains whitespace.")]
+    Empty,
+    #[error("the map name must not contain path separators.")]
+    PathSeparator,
+}
+
+/// Validates and sanitizes the name of a map that is converted.
+///
+/// Leading and trailing whitespace is removed. Afterwards every character
+/// that is not alphanumeric or one of ` `, `-`, `_`, `.`, `(`, `)`
+/// is replaced by `_`.
+/// Names that are empty, consist only of dots or contain path separators are rejected.
+pub fn sanitize_map_name(name: &str) -> Result<String, MapNameError> {
+    let name = name.trim();
+    if name.contains(['/', '\\']) {
+        return Err(MapNameError::PathSeparator);
+    }
+    if name.chars().all(|c| c == '.') {
+        return Err(MapNameError::Empty);
+    }
+    Ok(name
+        .chars()
+        .map(|c| {
+            if c.is_alphanumeric() || matches!(c, ' ' | '-' | '_' | '.' | '(' | ')') {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect())
+}
+
 pub fn legacy_to_new(
     path: &Path,
     io: &IoFileSys,
@@ -51,6 +89,49 @@ pub fn legacy_to_new(
     )
 }
 
+/// Like [`legacy_to_new`], but converts the map on the IO runtime.
+///
+/// Every group is sent over the returned channel as soon as it is converted,
+/// background and foreground groups first, the physics group last.
+/// The task contains the complete map including all (deduplicated) resources.
+pub fn legacy_to_new_stream(
+    path: &Path,
+    io: &IoFileSys,
+    thread_pool: &Arc<rayon::ThreadPool>,
+    optimize: bool,
+) -> anyhow::Result<(
+    Receiver<ConvertedGroup>,
+    IoRuntimeTask<LegacyMapToNewOutput>,
+)> {
+    let name = path
+        .file_stem()
+        .ok_or(anyhow!("wrong file name"))?
+        .to_str()
+        .ok_or(anyhow!("file name not utf8"))?
+        .to_string();
+    let path = path.to_path_buf();
+    let tp = thread_pool.clone();
+    let fs = io.fs.clone();
+    let (sender, receiver) = std::sync::mpsc::channel();
+    let task = io.rt.spawn(async move {
+        let map_file = fs.read_file(&path).await?;
+        legacy_to_new_from_buf_impl(
+            map_file,
+            &name,
+            |path| {
+                let path = path.to_path_buf();
+                let fs = fs.clone();
+                Box::pin(async move { Ok(fs.read_file(&path).await?) })
+            },
+            &tp,
+            optimize,
+            Some(sender),
+        )
+        .await
+    });
+    Ok((receiver, task))
+}
+
 pub async fn legacy_to_new_from_buf_async(
     map_file: Vec<u8>,
     name: &str,
@@ -58,9 +139,21 @@ pub async fn legacy_to_new_from_buf_async(
     thread_pool: &Arc<rayon::ThreadPool>,
     optimize: bool,
 ) -> anyhow::Result<LegacyMapToNewOutput> {
+    legacy_to_new_from_buf_impl(map_file, name, load_image, thread_pool, optimize, None).await
+}
+
+async fn legacy_to_new_from_buf_impl(
+    map_file: Vec<u8>,
+    name: &str,
+    load_image: impl Fn(&Path) -> Pin<Box<dyn Future<Output = anyhow::Result<Vec<u8>>> + Send>>,
+    thread_pool: &Arc<rayon::ThreadPool>,
+    optimize: bool,
+    groups: Option<Sender<ConvertedGroup>>,
+) -> anyhow::Result<LegacyMapToNewOutput> {
+    let name = sanitize_map_name(name)?;
     let mut map_legacy = CDatafileWrapper::new();
     let load_options = MapFileOpenOptions::default();
-    let res = map_legacy.open(&map_file, name, thread_pool.as_ref(), &load_options);
+    let res = map_legacy.open(&map_file, &name, thread_pool.as_ref(), &load_options);
     match res {
         Ok(data_start) => {
             CDatafileWrapper::read_map_layers(
@@ -114,13 +207,31 @@ pub async fn legacy_to_new_from_buf_async(
     let benchmark = Benchmark::new(true);
 
     benchmark.bench("encoding images to png");
-    let mut map_output = map_legacy.into_map(thread_pool, &images, Default::default(), true)?;
+    // groups only reference resources by index, so they are final
+    // before the resources are optimized or transcoded below.
+    let mut send_group = groups.map(|groups| {
+        move |group: ConvertedGroup| {
+            // the receiver might not be interested in the groups anymore
+            let _ = groups.send(group);
+        }
+    });
+    let mut map_output = map_legacy.into_map_with_groups(
+        thread_pool,
+        &images,
+        Default::default(),
+        true,
+        send_group
+            .as_mut()
+            .map(|send_group| send_group as &mut dyn FnMut(ConvertedGroup)),
+    )?;
     benchmark.bench("converting map");
 
     if optimize {
         thread_pool.install(|| {
             let hashes: Mutex<HashMap<Hash, Hash>> = Default::default();
             map_output.resources.images = std::mem::take(&mut map_output.resources.images)
+                .into_iter()
+                .collect::<Vec<_>>()
                 .into_par_iter()
                 .map(|(old_hash, mut i)| {
                     i.buf = optimize_from_memory(&i.buf, &oxipng::Options::default())?;
@@ -128,7 +239,10 @@ pub async fn legacy_to_new_from_buf_async(
                     hashes.lock().unwrap().insert(old_hash, hash);
                     anyhow::Ok((hash, i))
                 })
-                .collect::<anyhow::Result<HashMap<Hash, LegacyMapToNewRes>>>()?;
+                // keeps the order of the resources
+                .collect::<anyhow::Result<Vec<(Hash, LegacyMapToNewRes)>>>()?
+                .into_iter()
+                .collect();
 
             map_output
                 .map
@@ -1,16 +1,34 @@
+pub mod fuzz;
 pub mod legacy_to_new;
 pub mod new_to_legacy;
 
 #[cfg(test)]
 mod test {
-    use std::path::Path;
+    use std::path::{Path, PathBuf};
     use std::sync::Arc;
 
+    use base::hash::{fmt_hash, Hash};
     use base_fs::filesys::FileSystem;
     use base_io::io::IoFileSys;
 
-    use crate::legacy_to_new::{legacy_to_new, legacy_to_new_from_buf};
-    use crate::new_to_legacy::new_to_legacy_from_buf_async;
+    use hashlink::LinkedHashMap;
+    use legacy_map::datafile::{
+        CDatafileWrapper, ConvertedGroup, LegacyMapToNewRes, MapFileOpenOptions,
+    };
+    use map::file::MapFileReader;
+    use map::map::groups::MapGroup;
+    use map::map::resources::MapResourceRef;
+    use map::map::Map;
+
+    use crate::fuzz::{fuzz_legacy_map, fuzz_legacy_map_seed_corpus};
+    use crate::legacy_to_new::{
+        legacy_to_new, legacy_to_new_from_buf, legacy_to_new_stream, sanitize_map_name,
+        MapNameError,
+    };
+    use crate::new_to_legacy::{
+        new_to_legacy, new_to_legacy_from_buf_async, new_to_legacy_from_paths, NewMapResourceError,
+        NewMapResourcePaths,
+    };
 
     fn convert_back_and_forth_for_map(io: &IoFileSys, tp: &Arc<rayon::ThreadPool>, path: &Path) {
         let map_name = path.file_stem().unwrap().to_str().unwrap();
@@ -150,6 +168,74 @@ mod test {
         );
     }
 
+    fn stream_matches_sync_conversion_for_map(
+        io: &IoFileSys,
+        tp: &Arc<rayon::ThreadPool>,
+        path: &Path,
+    ) {
+        let new_map = legacy_to_new(path, io, tp, false).unwrap();
+
+        let (groups, task) = legacy_to_new_stream(path, io, tp, false).unwrap();
+        let mut background: Vec<Option<MapGroup>> = Vec::new();
+        let mut foreground: Vec<Option<MapGroup>> = Vec::new();
+        let mut physics = None;
+        let insert = |groups: &mut Vec<Option<MapGroup>>, index: usize, group| {
+            if groups.len() <= index {
+                groups.resize(index + 1, None);
+            }
+            assert!(groups[index].replace(group).is_none());
+        };
+        // ends once the conversion dropped the sender
+        for group in groups {
+            assert!(physics.is_none(), "physics group must be sent last");
+            match group {
+                ConvertedGroup::Background { index, group } => {
+                    insert(&mut background, index, group)
+                }
+                ConvertedGroup::Foreground { index, group } => {
+                    insert(&mut foreground, index, group)
+                }
+                ConvertedGroup::Physics(group) => physics = Some(group),
+            }
+        }
+        let stream_map = task.get().unwrap();
+
+        let background: Vec<MapGroup> = background.into_iter().map(Option::unwrap).collect();
+        let foreground: Vec<MapGroup> = foreground.into_iter().map(Option::unwrap).collect();
+        assert_eq!(background, new_map.map.groups.background);
+        assert_eq!(foreground, new_map.map.groups.foreground);
+        assert_eq!(
+            serde_json::to_string(&physics.unwrap()).unwrap(),
+            serde_json::to_string(&new_map.map.groups.physics).unwrap()
+        );
+        assert_eq!(
+            stream_map.map.groups.background,
+            new_map.map.groups.background
+        );
+        assert_eq!(
+            stream_map.map.groups.foreground,
+            new_map.map.groups.foreground
+        );
+        assert_eq!(
+            serde_json::to_string(&stream_map.map.groups.physics).unwrap(),
+            serde_json::to_string(&new_map.map.groups.physics).unwrap()
+        );
+        // resources are deduplicated the same way
+        assert_eq!(
+            serde_json::to_string(&stream_map.map.resources.images).unwrap(),
+            serde_json::to_string(&new_map.map.resources.images).unwrap()
+        );
+        assert_eq!(
+            serde_json::to_string(&stream_map.map.resources.image_arrays).unwrap(),
+            serde_json::to_string(&new_map.map.resources.image_arrays).unwrap()
+        );
+        let mut stream_images: Vec<_> = stream_map.resources.images.keys().collect();
+        let mut images: Vec<_> = new_map.resources.images.keys().collect();
+        stream_images.sort();
+        images.sort();
+        assert_eq!(stream_images, images);
+    }
+
     #[test]
     fn convert_back_and_forth() {
         let workspace_root = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("../../");
@@ -200,4 +286,285 @@ mod test {
         in_ty("race");
         in_ty("solo");
     }
+
+    #[test]
+    fn stream_matches_sync_conversion() {
+        let workspace_root = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("../../");
+        std::env::set_current_dir(workspace_root).unwrap();
+        let io = IoFileSys::new(|rt| {
+            Arc::new(
+                FileSystem::new(rt, "ddnet-test", "ddnet-test", "ddnet-test", "ddnet-test")
+                    .unwrap(),
+            )
+        });
+        let thread_pool = Arc::new(
+            rayon::ThreadPoolBuilder::new()
+                .num_threads(1)
+                .build()
+                .unwrap(),
+        );
+        let fs = io.fs.clone();
+        let entries = io
+            .rt
+            .spawn(async move {
+                Ok(fs
+                    .entries_in_dir("types/novice/maps".as_ref())
+                    .await
+                    .unwrap())
+            })
+            .get()
+            .unwrap();
+
+        for (path, _) in entries {
+            if path.ends_with(".map") {
+                stream_matches_sync_conversion_for_map(&io, &thread_pool, path.as_ref());
+            }
+        }
+    }
+
+    #[test]
+    fn resources_are_loaded_from_paths() {
+        let workspace_root = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("../../");
+        std::env::set_current_dir(workspace_root).unwrap();
+        let io = IoFileSys::new(|rt| {
+            Arc::new(
+                FileSystem::new(rt, "ddnet-test", "ddnet-test", "ddnet-test", "ddnet-test")
+                    .unwrap(),
+            )
+        });
+        let thread_pool = Arc::new(
+            rayon::ThreadPoolBuilder::new()
+                .num_threads(1)
+                .build()
+                .unwrap(),
+        );
+        let fs = io.fs.clone();
+        let file = io
+            .rt
+            .spawn(async move { Ok(fs.read_file("map/maps/ctf1.twmap.tar".as_ref()).await?) })
+            .get()
+            .unwrap();
+        let map = Map::read(&MapFileReader::new(file.clone()).unwrap(), &thread_pool).unwrap();
+
+        let paths_of = |dir: &str, resources: &[MapResourceRef]| -> Vec<PathBuf> {
+            resources
+                .iter()
+                .map(|res| {
+                    format!(
+                        "map/resources/{dir}/{}_{}.{}",
+                        res.name.as_str(),
+                        fmt_hash(&res.meta.blake3_hash),
+                        res.meta.ty.as_str()
+                    )
+                    .into()
+                })
+                .collect()
+        };
+        let mut paths = NewMapResourcePaths {
+            images: paths_of("images", &map.resources.images),
+            image_arrays: paths_of("images", &map.resources.image_arrays),
+            sounds: paths_of("sounds", &map.resources.sounds),
+        };
+        new_to_legacy_from_paths(&file, paths.clone(), &io, &thread_pool).unwrap();
+
+        // the last image array is missing, all other resources are present
+        assert!(map.resources.image_arrays.len() + map.resources.images.len() >= 2);
+        let missing = map.resources.image_arrays.last().unwrap();
+        *paths.image_arrays.last_mut().unwrap() = "map/resources/images/does_not_exist.png".into();
+        let err = new_to_legacy_from_paths(&file, paths, &io, &thread_pool).unwrap_err();
+        let Some(NewMapResourceError::NotFound { kind, name, .. }) =
+            err.downcast_ref::<NewMapResourceError>()
+        else {
+            panic!("expected a missing resource, got: {err}");
+        };
+        assert_eq!(*kind, "image array");
+        assert_eq!(name, missing.name.as_str());
+    }
+
+    #[test]
+    fn conversion_output_is_deterministic() {
+        let workspace_root = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("../../");
+        std::env::set_current_dir(workspace_root).unwrap();
+        let io = IoFileSys::new(|rt| {
+            Arc::new(
+                FileSystem::new(rt, "ddnet-test", "ddnet-test", "ddnet-test", "ddnet-test")
+                    .unwrap(),
+            )
+        });
+        // multiple threads, so the resources are optimized in parallel
+        let thread_pool = Arc::new(
+            rayon::ThreadPoolBuilder::new()
+                .num_threads(4)
+                .build()
+                .unwrap(),
+        );
+        let legacy_map = new_to_legacy("map/maps/ctf1.twmap.tar".as_ref(), &io, &thread_pool)
+            .unwrap()
+            .map;
+
+        let convert = || {
+            let mut new_map =
+                legacy_to_new_from_buf(legacy_map.clone(), "ctf1", &io, &thread_pool, true)
+                    .unwrap();
+            let file = new_map.map.write(&thread_pool).unwrap();
+            let resources = |resources: &LinkedHashMap<Hash, LegacyMapToNewRes>| {
+                resources
+                    .iter()
+                    .map(|(hash, res)| (*hash, res.buf.clone()))
+                    .collect::<Vec<_>>()
+            };
+            let in_map_order = (
+                resources(&new_map.resources.images),
+                resources(&new_map.resources.sounds),
+            );
+            new_map.resources.sort_by_hash();
+            let by_hash = (
+                resources(&new_map.resources.images),
+                resources(&new_map.resources.sounds),
+            );
+            (file, in_map_order, by_hash)
+        };
+
+        let (file, in_map_order, by_hash) = convert();
+        let (file2, in_map_order2, by_hash2) = convert();
+        assert!(file == file2, "the map files differ");
+        assert!(
+            in_map_order == in_map_order2,
+            "the resources are ordered differently"
+        );
+        assert!(by_hash == by_hash2, "the sorted resources differ");
+        assert!(by_hash.0.is_sorted_by_key(|(hash, _)| *hash));
+        assert!(by_hash.1.is_sorted_by_key(|(hash, _)| *hash));
+    }
+
+    #[test]
+    fn fuzz_harness_survives_truncated_input() {
+        let workspace_root = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("../../");
+        std::env::set_current_dir(workspace_root).unwrap();
+        let io = IoFileSys::new(|rt| {
+            Arc::new(
+                FileSystem::new(rt, "ddnet-test", "ddnet-test", "ddnet-test", "ddnet-test")
+                    .unwrap(),
+            )
+        });
+        let thread_pool = Arc::new(
+            rayon::ThreadPoolBuilder::new()
+                .num_threads(1)
+                .build()
+                .unwrap(),
+        );
+        let legacy_map = new_to_legacy("map/maps/ctf1.twmap.tar".as_ref(), &io, &thread_pool)
+            .unwrap()
+            .map;
+
+        fuzz_legacy_map(&[]);
+        fuzz_legacy_map(b"DATA");
+        fuzz_legacy_map(&legacy_map[..legacy_map.len() - 1]);
+        let seeds = fuzz_legacy_map_seed_corpus(&legacy_map);
+        assert_eq!(seeds[0], legacy_map);
+        for seed in seeds {
+            fuzz_legacy_map(&seed);
+        }
+    }
+
+    #[test]
+    fn byte_swapped_maps_are_detected() {
+        let workspace_root = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("../../");
+        std::env::set_current_dir(workspace_root).unwrap();
+        let io = IoFileSys::new(|rt| {
+            Arc::new(
+                FileSystem::new(rt, "ddnet-test", "ddnet-test", "ddnet-test", "ddnet-test")
+                    .unwrap(),
+            )
+        });
+        let thread_pool = Arc::new(
+            rayon::ThreadPoolBuilder::new()
+                .num_threads(1)
+                .build()
+                .unwrap(),
+        );
+        let legacy_map = new_to_legacy("map/maps/ctf1.twmap.tar".as_ref(), &io, &thread_pool)
+            .unwrap()
+            .map;
+
+        let swapped_map = CDatafileWrapper::swap_byte_order(&legacy_map).unwrap();
+        assert_ne!(swapped_map, legacy_map);
+        assert_eq!(
+            CDatafileWrapper::swap_byte_order(&swapped_map).unwrap(),
+            legacy_map
+        );
+
+        let open = |data: &Vec<u8>, allow_byte_swapped: bool| {
+            let mut map = CDatafileWrapper::new();
+            map.open(
+                data,
+                "ctf1",
+                &thread_pool,
+                &MapFileOpenOptions {
+                    allow_byte_swapped,
+                    ..Default::default()
+                },
+            )
+            .map(|data_start| {
+                (
+                    data_start.len(),
+                    map.num_groups(),
+                    map.layers.len(),
+                    map.images.len(),
+                )
+            })
+        };
+
+        let expected = open(&legacy_map, false).unwrap();
+        let err = open(&swapped_map, false).unwrap_err();
+        assert!(err.to_string().contains("big endian"), "{err}");
+        assert_eq!(open(&swapped_map, true).unwrap(), expected);
+
+        // implausible in both byte orders
+        let mut broken_map = legacy_map.clone();
+        broken_map[16..20].fill(u8::MAX);
+        assert!(open(&broken_map, true).is_err());
+        assert!(CDatafileWrapper::swap_byte_order(&broken_map).is_err());
+    }
+
+    #[test]
+    fn map_names_are_validated() {
+        assert_eq!(sanitize_map_name(""), Err(MapNameError::Empty));
+        assert_eq!(sanitize_map_name(" \t\n"), Err(MapNameError::Empty));
+        assert_eq!(sanitize_map_name(".."), Err(MapNameError::Empty));
+        assert_eq!(
+            sanitize_map_name("maps/ctf1"),
+            Err(MapNameError::PathSeparator)
+        );
+        assert_eq!(
+            sanitize_map_name("..\\ctf1"),
+            Err(MapNameError::PathSeparator)
+        );
+
+        assert_eq!(sanitize_map_name(" ctf1 ").as_deref(), Ok("ctf1"));
+        assert_eq!(
+            sanitize_map_name("Sunny Side Up (v2)").as_deref(),
+            Ok("Sunny Side Up (v2)")
+        );
+        assert_eq!(sanitize_map_name("a:b*c\0").as_deref(), Ok("a_b_c_"));
+
+        // the conversion rejects invalid names before reading the map
+        let io = IoFileSys::new(|rt| {
+            Arc::new(
+                FileSystem::new(rt, "ddnet-test", "ddnet-test", "ddnet-test", "ddnet-test")
+                    .unwrap(),
+            )
+        });
+        let thread_pool = Arc::new(
+            rayon::ThreadPoolBuilder::new()
+                .num_threads(1)
+                .build()
+                .unwrap(),
+        );
+        let err = legacy_to_new_from_buf(Vec::new(), "  ", &io, &thread_pool, false).unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<MapNameError>(),
+            Some(&MapNameError::Empty)
+        );
+    }
 }
@@ -1,9 +1,20 @@
 use anyhow::anyhow;
 use base::{benchmark::Benchmark, hash::fmt_hash};
 use base_io::io::IoFileSys;
+use base_io_traits::fs_traits::FileSystemInterface;
 use legacy_map::datafile::CDatafileWrapper;
-use map::{file::MapFileReader, map::Map};
-use std::{future::Future, io::Cursor, path::Path, pin::Pin, sync::Arc};
+use map::{
+    file::MapFileReader,
+    map::{resources::MapResourceRef, Map},
+};
+use std::{
+    future::Future,
+    io::Cursor,
+    path::{Path, PathBuf},
+    pin::Pin,
+    sync::Arc,
+};
+use thiserror::Error;
 use vorbis_rs::VorbisDecoder;
 
 // the map is prepared to be written to disk. the map format is not used in the code base
@@ -12,6 +23,29 @@ pub struct NewMapToLegacyOutput {
     pub map: Vec<u8>,
 }
 
+#[derive(Error, Debug)]
+pub enum NewMapResourceError {
+    #[error("no path was given for the {kind} \"{name}\".")]
+    NoPath { kind: &'static str, name: String },
+    #[error("the {kind} \"{name}\" could not be loaded from {path:?}: {err}")]
+    NotFound {
+        kind: &'static str,
+        name: String,
+        path: PathBuf,
+        #[source]
+        err: std::io::Error,
+    },
+}
+
+/// The file paths of the resources a map references,
+/// in the same order as the map's resources.
+#[derive(Debug, Default, Clone)]
+pub struct NewMapResourcePaths {
+    pub images: Vec<PathBuf>,
+    pub image_arrays: Vec<PathBuf>,
+    pub sounds: Vec<PathBuf>,
+}
+
 pub async fn new_to_legacy_from_buf_async(
     file: &[u8],
     load_resources: impl FnOnce(
@@ -144,6 +178,87 @@ pub fn new_to_legacy_from_buf(
         .get()
 }
 
+async fn read_resources_from_paths(
+    fs: &Arc<dyn FileSystemInterface>,
+    kind: &'static str,
+    resources: &[MapResourceRef],
+    paths: &[PathBuf],
+) -> Result<Vec<Vec<u8>>, NewMapResourceError> {
+    let mut files: Vec<Vec<u8>> = Default::default();
+    for (index, resource) in resources.iter().enumerate() {
+        let name = resource.name.as_str().to_string();
+        let path = paths
+            .get(index)
+            .ok_or_else(|| NewMapResourceError::NoPath {
+                kind,
+                name: name.clone(),
+            })?;
+        let file = fs
+            .read_file(path)
+            .await
+            .map_err(|err| NewMapResourceError::NotFound {
+                kind,
+                name,
+                path: path.clone(),
+                err,
+            })?;
+        files.push(file);
+    }
+    Ok(files)
+}
+
+/// Like [`new_to_legacy_from_buf`], but loads every resource of the map
+/// from the given `paths` instead of the default resource directories.
+///
+/// Resources that can not be loaded fail the conversion with a [`NewMapResourceError`].
+pub fn new_to_legacy_from_paths(
+    file: &[u8],
+    paths: NewMapResourcePaths,
+    io: &IoFileSys,
+    thread_pool: &Arc<rayon::ThreadPool>,
+) -> anyhow::Result<NewMapToLegacyOutput> {
+    let tp = thread_pool.clone();
+    let fs = io.fs.clone();
+    let file = file.to_vec();
+
+    io.rt
+        .spawn(async move {
+            new_to_legacy_from_buf_async(
+                &file,
+                |map| {
+                    let resources = map.resources.clone();
+                    Box::pin(async move {
+                        let images = read_resources_from_paths(
+                            &fs,
+                            "image",
+                            &resources.images,
+                            &paths.images,
+                        )
+                        .await?;
+                        let image_arrays = read_resources_from_paths(
+                            &fs,
+                            "image array",
+                            &resources.image_arrays,
+                            &paths.image_arrays,
+                        )
+                        .await?;
+                        let sounds = read_resources_from_paths(
+                            &fs,
+                            "sound",
+                            &resources.sounds,
+                            &paths.sounds,
+                        )
+                        .await?;
+                        Ok((images, image_arrays, sounds))
+                    })
+                },
+                &tp,
+            )
+            .await
+        })
+        .get()
+}
+
 /// this function will only be supported as long as the map format is equally convertable to the old format
 pub fn new_to_legacy(
     path: &Path,
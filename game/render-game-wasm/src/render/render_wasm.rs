@@ -21,7 +21,7 @@ pub mod render_wasm {
     use wasm_logic_graphics::WasmGraphicsLogic;
     use wasm_logic_http::http::WasmHttpLogic;
     use wasm_logic_sound::sound::WasmSoundLogic;
-    use wasm_runtime::{MemoryLimit, WasmManager, WasmManagerModuleType};
+    use wasm_runtime::{IntEncoding, MemoryLimit, WasmManager, WasmManagerModuleType};
     use wasmer::Module;
 
     pub struct RenderWasm {
@@ -61,6 +61,7 @@ pub mod render_wasm {
                     Some(imports)
                 },
                 MemoryLimit::OneGibiByte,
+                IntEncoding::Fixed,
             )
             .unwrap();
             wasm_manager.add_param(0, &map_file);
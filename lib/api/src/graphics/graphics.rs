@@ -70,6 +70,15 @@ impl GraphicsBackendInterface for GraphicsBackend {
                 width.get() * height.get() * depth.get() * 4,
                 Default::default(),
             ),
+            graphics_types::types::GraphicsMemoryAllocationType::TextureCompressed {
+                width,
+                height,
+                format,
+                ..
+            } => mem.resize(
+                format.image_size(width.get(), height.get()),
+                Default::default(),
+            ),
             graphics_types::types::GraphicsMemoryAllocationType::VertexBuffer { required_size } => {
                 mem.resize(required_size.get(), Default::default())
             }
@@ -47,6 +47,19 @@ impl GraphicsBackendMtInterface for GraphicsBackendMultiThreaded {
                     );
                     GraphicsBackendMemoryAllocation::Vector(res)
                 }
+                GraphicsMemoryAllocationType::TextureCompressed {
+                    width,
+                    height,
+                    format,
+                    ..
+                } => {
+                    let mut res = Vec::new();
+                    res.resize(
+                        format.image_size(width.get(), height.get()),
+                        Default::default(),
+                    );
+                    GraphicsBackendMemoryAllocation::Vector(res)
+                }
                 GraphicsMemoryAllocationType::VertexBuffer { required_size } => {
                     let mut res = Vec::new();
                     res.resize(required_size.get(), Default::default());
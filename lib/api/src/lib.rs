@@ -42,6 +42,7 @@ pub mod sound;
 
 extern "C" {
     fn host_println();
+    fn host_panic(ptr: u32, len: u32);
 }
 
 thread_local! {
@@ -145,11 +146,26 @@ impl log::Log for Logger {
     fn flush(&self) {}
 }
 
+/// Reports the formatted panic message (including its location) to the host,
+/// which attaches it to the error of the call that panicked.
+/// Otherwise the host only sees an `unreachable` trap.
+///
+/// Mods that install their own panic hook should call this, e.g.:
+/// ```ignore
+/// std::panic::set_hook(Box::new(|panic_info| {
+///     api::report_panic(&panic_info.to_string());
+/// }));
+/// ```
+pub fn report_panic(panic_text: &str) {
+    unsafe { host_panic(panic_text.as_ptr() as u32, panic_text.len() as u32) };
+}
+
 #[no_mangle]
 pub fn api_setup() {
     std::panic::set_hook(Box::new(|panic_info| {
-        let panic_text = format!("wasm module {}", panic_info);
-        println(panic_text);
+        let panic_text = panic_info.to_string();
+        println(format!("wasm module {panic_text}"));
+        report_panic(&panic_text);
     }));
     log::set_boxed_logger(Box::new(Logger {})).unwrap();
     log::set_max_level(log::LevelFilter::Info);
@@ -284,6 +300,21 @@ unsafe fn param_ptrs(index: usize) -> (*mut i32, *mut i32, *mut i32) {
     }
 }
 
+/// The bincode config for params and results,
+/// must match the `IntEncoding` the host runs this module with.
+#[cfg(not(feature = "variable_int_encoding"))]
+fn bincode_config(
+) -> bincode::config::Configuration<bincode::config::LittleEndian, bincode::config::Fixint> {
+    bincode::config::standard().with_fixed_int_encoding()
+}
+
+/// The bincode config for params and results,
+/// must match the `IntEncoding` the host runs this module with.
+#[cfg(feature = "variable_int_encoding")]
+fn bincode_config() -> bincode::config::Configuration {
+    bincode::config::standard()
+}
+
 fn set_param_params(index: usize, len: usize) {
     unsafe {
         let (ptr, size, alloc_size) = param_ptrs(index);
@@ -303,7 +334,7 @@ pub fn upload_param<F: Serialize>(index: usize, data: F) {
     let res = bincode::serde::encode_into_std_write::<F, _, Vec<_>>(
         data,
         unsafe { &mut PARAMS[index] },
-        bincode::config::standard().with_fixed_int_encoding(),
+        bincode_config(),
     );
 
     let data_len = unsafe {
@@ -331,7 +362,7 @@ pub fn upload_return_val<F: Serialize>(res: F) {
         bincode::serde::encode_into_std_write::<F, _, _>(
             res,
             &mut *res_container,
-            bincode::config::standard().with_fixed_int_encoding(),
+            bincode_config(),
         )
         .unwrap();
 
@@ -347,7 +378,7 @@ fn read_param_from_host_checked<F: DeserializeOwned>(index: u32) -> anyhow::Resu
         let (_, size, _) = param_ptrs(index as usize);
         bincode::serde::decode_from_slice(
             &PARAMS[index as usize].as_slice()[0..*size as usize],
-            bincode::config::standard().with_fixed_int_encoding(),
+            bincode_config(),
         )
         .map(|opt| opt.0)
         .map_err(|err| {
@@ -380,13 +411,18 @@ pub fn read_param_from_host_ex<F: DeserializeOwned>(
     }
 }
 
+/// Calls `f` with the bytes of a param that the host uploaded with `WasmManager::add_param_bytes`.
+/// The bytes are not bincode framed and are not copied.
+pub fn read_param_bytes_from_host<R>(index: u32, f: impl FnOnce(&[u8]) -> R) -> R {
+    unsafe {
+        let (_, size, _) = param_ptrs(index as usize);
+        f(&PARAMS[index as usize].as_slice()[0..*size as usize])
+    }
+}
+
 fn read_result_from_host_checked<F: DeserializeOwned>() -> Result<F, bincode::error::DecodeError> {
     RES.with(|g| {
-        bincode::serde::decode_from_slice(
-            g.borrow().as_slice(),
-            bincode::config::standard().with_fixed_int_encoding(),
-        )
-        .map(|opt| opt.0)
+        bincode::serde::decode_from_slice(g.borrow().as_slice(), bincode_config()).map(|opt| opt.0)
     })
 }
 
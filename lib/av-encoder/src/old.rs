@@ -200,10 +200,10 @@ impl BackendFrameFetcher for AudioVideoEncoderImpl {
 
     fn fetch_err(&self, err: FetchCanvasError) {
         match err {
-            FetchCanvasError::CanvasNotFound => {
+            FetchCanvasError::CanvasNotFound | FetchCanvasError::NotPresentedYet => {
                 // ignore for now
             }
-            FetchCanvasError::DriverErr(err) => {
+            FetchCanvasError::DriverErr(err) | FetchCanvasError::DepthUnsupported(err) => {
                 panic!("err in audio encoding: {err}");
             }
         }
@@ -19,6 +19,16 @@ pub type TaskJoinType = tokio::task::JoinHandle<()>;
 #[cfg(target_arch = "wasm32")]
 pub type TaskJoinType = async_task::Task<()>;
 
+/// Marks a task as finished when it is dropped,
+/// which also happens if the task panicked or was cancelled.
+struct Finisher(Arc<AtomicBool>);
+
+impl Drop for Finisher {
+    fn drop(&mut self) {
+        self.0.store(true, std::sync::atomic::Ordering::SeqCst);
+    }
+}
+
 #[derive(Debug, Hiarc)]
 enum TaskState {
     WaitAndDrop,
@@ -46,6 +56,21 @@ impl<S> IoRuntimeTask<S> {
             .unwrap()
     }
 
+    /// Returns `true` if the task was cancelled,
+    /// `false` if it completed before the cancellation took effect.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn cancel_and_wait(rt: &RuntimeType, task: TaskJoinType) -> bool {
+        task.abort();
+        rt.block_on(task).is_err_and(|err| err.is_cancelled())
+    }
+
+    /// Returns `true` if the task was cancelled,
+    /// `false` if it completed before the cancellation took effect.
+    #[cfg(target_arch = "wasm32")]
+    fn cancel_and_wait(rt: &RuntimeType, task: TaskJoinType) -> bool {
+        futures_lite::future::block_on(rt.run(task.cancel())).is_none()
+    }
+
     fn wait_finished_and_drop(&mut self, catch: bool) {
         let mut inner = self.io_runtime.borrow_mut();
         let task_join = Self::drop_task(self.queue_id, &mut inner);
@@ -123,6 +148,8 @@ impl<S> IoRuntimeTask<S> {
         inner.rt.try_tick();
     }
 
+    /// Whether the task completed, panicked or was cancelled.
+    /// A task that is still running or was not polled yet is not finished.
     pub fn is_finished(&self) -> bool {
         #[cfg(target_arch = "wasm32")]
         self.try_run();
@@ -145,6 +172,25 @@ impl<S> IoRuntimeTask<S> {
     pub fn abortable(self) -> Self {
         self.cancelable()
     }
+
+    /// Aborts a task that was spawned using the task queue ([`IoRuntime::spawn`])
+    /// right away, instead of when it is dropped.
+    ///
+    /// Returns `false` if the task already completed, in which case its result
+    /// can still be retrieved. The result of a cancelled task is an error.
+    ///
+    /// This waits until the task stopped, because it might complete
+    /// while it is aborted.
+    pub fn cancel(&mut self) -> bool {
+        if let TaskState::WaitAndDrop | TaskState::CancelAndDrop = self.task_state {
+            let mut inner = self.io_runtime.borrow_mut();
+            let task = Self::drop_task(self.queue_id, &mut inner);
+            self.task_state = TaskState::None;
+            Self::cancel_and_wait(&inner.rt, task)
+        } else {
+            !self.is_finished()
+        }
+    }
 }
 
 impl<S> Drop for IoRuntimeTask<S> {
@@ -229,13 +275,7 @@ impl IoRuntime {
     {
         let _g = self.inner.borrow_mut().rt.enter();
         tokio::spawn(async move {
-            struct Finisher(Arc<AtomicBool>);
-            impl Drop for Finisher {
-                fn drop(&mut self) {
-                    self.0.store(true, std::sync::atomic::Ordering::SeqCst);
-                }
-            }
-            Finisher(task_finished);
+            let _finisher = Finisher(task_finished);
             let storage_wrapped = task.await;
             *storage_task.lock().await = storage_wrapped;
         })
@@ -252,9 +292,9 @@ impl IoRuntime {
         F: Future<Output = anyhow::Result<S>> + Send + 'static,
     {
         self.inner.borrow_mut().rt.spawn(async move {
+            let _finisher = Finisher(task_finished);
             let storage_wrapped = task.await;
             *storage_task.lock().await = storage_wrapped;
-            task_finished.store(true, std::sync::atomic::Ordering::SeqCst);
         })
     }
 
@@ -353,3 +393,65 @@ impl IoRuntime {
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::IoRuntime;
+
+    fn runtime() -> IoRuntime {
+        IoRuntime::new(
+            tokio::runtime::Builder::new_multi_thread()
+                .worker_threads(1)
+                .build()
+                .unwrap(),
+        )
+    }
+
+    #[test]
+    fn task_is_finished_after_completion() {
+        let rt = runtime();
+        let (sender, receiver) = tokio::sync::oneshot::channel::<()>();
+        let task = rt.spawn(async move {
+            receiver.await?;
+            anyhow::Ok(5)
+        });
+
+        // the task already started, but it waits for the sender
+        std::thread::sleep(Duration::from_millis(50));
+        assert!(!task.is_finished());
+
+        sender.send(()).unwrap();
+        while !task.is_finished() {
+            std::thread::yield_now();
+        }
+        assert_eq!(task.get().unwrap(), 5);
+    }
+
+    #[test]
+    fn cancel_completed_task() {
+        let rt = runtime();
+        let mut task = rt.spawn(async { anyhow::Ok(5) });
+        while !task.is_finished() {
+            std::thread::yield_now();
+        }
+
+        // the result was stored, so it was not cancelled
+        assert!(!task.cancel());
+        assert_eq!(task.get().unwrap(), 5);
+    }
+
+    #[test]
+    fn task_is_finished_after_cancel() {
+        let rt = runtime();
+        let mut task = rt.spawn(std::future::pending::<anyhow::Result<()>>());
+        assert!(!task.is_finished());
+
+        assert!(task.cancel());
+        while !task.is_finished() {
+            std::thread::yield_now();
+        }
+        assert!(task.get().is_err());
+    }
+}
@@ -65,6 +65,17 @@ impl Benchmark {
         }
     }
 
+    /// Prints a label, e.g. the hardware, to tell apart the output of different runs.
+    pub fn label(&self, label: &str) {
+        if self.is_active {
+            let tabs: String = (0..self.offset)
+                .map(|_| "  ")
+                .collect::<Vec<&str>>()
+                .join("");
+            println!("{tabs}{label}");
+        }
+    }
+
     pub fn bench(&self, name: &str) {
         if self.is_active {
             self.cur_diff
@@ -63,6 +63,9 @@ pub struct ConfigInput {
 #[config_default]
 #[derive(Debug, Hiarc, Clone, Serialize, Deserialize, ConfigInterface)]
 pub struct ConfigGfx {
+    /// The graphics backend, `Vulkan` or `null`.
+    /// The `null` backend renders nothing, but still clears
+    /// and fetches frames from a CPU framebuffer.
     #[default = "Vulkan"]
     pub backend: String,
 }
@@ -175,6 +178,11 @@ pub struct ConfigNetwork {
 #[derive(Debug, Hiarc, Serialize, Deserialize, ConfigInterface, Clone, Copy)]
 pub struct ConfigDebug {
     pub gfx: GfxDebugModes,
+    /// If `true`, errors of the graphics validation (see `gfx`) are logged as errors
+    /// instead of panicking, e.g. to run validation without crashing on the
+    /// noise of third-party layers.
+    #[default = false]
+    pub gfx_log_validation_errors: bool,
     // Show various "benchmarks" (e.g. loading of components etc.)
     #[default = false]
     pub bench: bool,
@@ -183,6 +191,11 @@ pub struct ConfigDebug {
     pub app: bool,
     #[default = false]
     pub untrusted_cert: bool,
+    /// Reads back samples of the buffers of loaded maps and logs every
+    /// sample that differs from what was uploaded.
+    /// Only has an effect in debug builds.
+    #[default = false]
+    pub verify_map_uploads: bool,
 }
 
 #[config_default]
@@ -191,6 +204,31 @@ pub struct ConfigBackend {
     #[conf_valid(range(min = -100.0, max = 100.0))]
     #[default = -0.5]
     pub global_texture_lod_bias: f64,
+    /// The anisotropic filtering level of textures, clamped to what the gpu supports.
+    /// 0 or 1 disables anisotropic filtering.
+    /// Requires a restart of the graphics backend.
+    #[conf_valid(range(min = 0, max = 16))]
+    #[default = 0]
+    pub anisotropy: u32,
+    /// The sharpest mip level textures sample, raising it skips the sharpest
+    /// mips, which e.g. saves memory bandwidth on weak gpus.
+    /// 0 doesn't clamp.
+    /// Requires a restart of the graphics backend.
+    #[conf_valid(range(min = 0.0, max = 16.0))]
+    #[default = 0.0]
+    pub texture_min_lod: f64,
+    /// The blurriest mip level textures sample, never smaller than `texture_min_lod`.
+    /// Requires a restart of the graphics backend.
+    #[conf_valid(range(min = 0.0, max = 1000.0))]
+    #[default = 1000.0]
+    pub texture_max_lod: f64,
+    /// The most mip levels generated for a texture, e.g. to save memory
+    /// on large tilesets whose smallest mips are never sampled.
+    /// 0 generates the full mip chain.
+    /// Requires a restart of the graphics backend.
+    #[conf_valid(range(min = 0, max = 16))]
+    #[default = 0]
+    pub max_mip_levels: u32,
     #[default = 0]
     pub thread_count: u32,
     #[default = 0]
@@ -205,6 +243,44 @@ pub struct ConfigBackend {
     /// Whether to create all pipelines for max performance.
     #[default = true]
     pub full_pipeline_creation: bool,
+    /// By how much the streamed uniform instance pool grows,
+    /// relative to its current capacity, before it runs out of instances.
+    /// A value of 1 only allocates what is needed.
+    #[conf_valid(range(min = 1.0, max = 8.0))]
+    #[default = 2.0]
+    pub uniform_pool_growth_factor: f64,
+    /// The size of the heaps staging memory for uploads is pooled in, in MiB.
+    /// Uploads smaller than a third of it share a heap.
+    /// Requires a restart of the graphics backend.
+    #[conf_valid(range(min = 3, max = 1024))]
+    #[default = 24]
+    pub staging_pool_size: u32,
+    /// Up to this size in MiB the staging memory grows, before uploads
+    /// wait for the gpu to finish the frames that still use staging memory.
+    /// Requires a restart of the graphics backend.
+    #[conf_valid(range(min = 3, max = 16384))]
+    #[default = 512]
+    pub staging_pool_max_size: u32,
+    /// How often the swapchain is recreated on the real surface after the
+    /// surface was lost, before falling back to a headless surface
+    /// until the window is recreated.
+    /// 0 falls back immediately.
+    #[conf_valid(range(min = 0, max = 16))]
+    #[default = 3]
+    pub surface_lost_retries: u32,
+    /// How many frames the cpu may record ahead of the gpu, independent of
+    /// the swapchain image count. 1 gives the lowest input latency,
+    /// higher values more throughput.
+    /// 0 only limits it by the swapchain image count.
+    #[conf_valid(range(min = 0, max = 8))]
+    #[default = 0]
+    pub max_frames_in_flight: u32,
+    /// Caps how many frames the backend renders per second,
+    /// independent of vsync.
+    /// 0 does not limit the frame rate.
+    #[conf_valid(range(min = 0, max = 10000))]
+    #[default = 0]
+    pub max_fps: u32,
 }
 
 #[config_default]
@@ -1,3 +1,5 @@
+#[cfg(feature = "encode")]
+use std::borrow::Cow;
 use std::fmt::Debug;
 
 use anyhow::Error;
@@ -7,7 +9,7 @@ use thiserror::Error;
 
 pub type OffscreenCanvasId = u128;
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum FetchCanvasIndex {
     Onscreen,
     Offscreen(OffscreenCanvasId),
@@ -17,8 +19,19 @@ pub enum FetchCanvasIndex {
 pub enum FetchCanvasError {
     #[error("canvas with the id, which was obtained by `current_fetch_index`, was not found.")]
     CanvasNotFound,
+    #[error("no frame was presented yet.")]
+    NotPresentedYet,
     #[error("the backend had an error: {0}")]
     DriverErr(String),
+    /// The depth attachment of the canvas can not be read back, e.g. because of its format.
+    #[error("the depth can not be fetched: {0}")]
+    DepthUnsupported(String),
+}
+
+#[derive(Debug, Hiarc, Error, Clone, Copy, PartialEq, Eq)]
+pub enum FrameFetcherAttachError {
+    #[error("a frame fetcher with the same name is already attached.")]
+    AlreadyAttached,
 }
 
 impl From<Error> for FetchCanvasError {
@@ -27,16 +40,104 @@ impl From<Error> for FetchCanvasError {
     }
 }
 
+/// The order of the rows of a fetched image.
+#[derive(Debug, Hiarc, Default, Clone, Copy, PartialEq, Eq)]
+pub enum PresentedImageOrientation {
+    /// The first row is the top row of the image.
+    #[default]
+    TopToBottom,
+    /// The first row is the bottom row of the image,
+    /// e.g. for encoders that expect a bottom-left origin.
+    BottomToTop,
+}
+
 #[derive(Debug, Hiarc)]
 pub struct BackendPresentedImageDataRgba {
     pub width: u32,
     pub height: u32,
+    pub orientation: PresentedImageOrientation,
     pub dest_data_buffer: PoolUnclearedVec<u8>,
 }
 
+/// The image file formats a fetched frame can be encoded to,
+/// see [`BackendPresentedImageDataRgba::encode`].
+#[cfg(feature = "encode")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PresentedImageFormat {
+    Png,
+    /// Lossless WebP
+    #[cfg(feature = "webp")]
+    WebP,
+}
+
+#[cfg(feature = "encode")]
+impl BackendPresentedImageDataRgba {
+    /// Encodes the frame to an image file.
+    ///
+    /// The data is already in rgba channel order, frames fetched
+    /// [`PresentedImageOrientation::BottomToTop`] are flipped back,
+    /// since image files start with the top row.
+    pub fn encode(&self, format: PresentedImageFormat) -> anyhow::Result<Vec<u8>> {
+        let row_size = self.width as usize * 4;
+        let image_size = row_size * self.height as usize;
+        anyhow::ensure!(image_size > 0, "can't encode an empty image");
+        anyhow::ensure!(
+            self.dest_data_buffer.len() >= image_size,
+            "the image data ({} bytes) is too small for a {}x{} rgba image",
+            self.dest_data_buffer.len(),
+            self.width,
+            self.height
+        );
+
+        let data = &self.dest_data_buffer[..image_size];
+        let data: Cow<[u8]> = match self.orientation {
+            PresentedImageOrientation::TopToBottom => Cow::Borrowed(data),
+            PresentedImageOrientation::BottomToTop => Cow::Owned(
+                data.chunks_exact(row_size)
+                    .rev()
+                    .flatten()
+                    .copied()
+                    .collect(),
+            ),
+        };
+        match format {
+            PresentedImageFormat::Png => {
+                image_utils::png::save_png_image(&data, self.width, self.height)
+            }
+            #[cfg(feature = "webp")]
+            PresentedImageFormat::WebP => {
+                image_utils::webp::save_webp_image(&data, self.width, self.height)
+            }
+        }
+    }
+}
+
+/// The depth buffer of a canvas, normalized to `0.0..=1.0`.
+#[derive(Debug, Hiarc)]
+pub struct BackendDepthImageData {
+    pub width: u32,
+    pub height: u32,
+    /// row by row, one value per pixel
+    pub depth_data: Vec<f32>,
+}
+
 pub trait BackendFrameFetcher: Debug + Sync + Send + 'static {
     fn next_frame(&self, frame_data: BackendPresentedImageDataRgba);
 
+    /// the row order the fetcher wants the image data in.
+    fn orientation(&self) -> PresentedImageOrientation {
+        Default::default()
+    }
+
+    /// whether the backend should additionally fetch the depth buffer
+    /// of the canvas and call [`BackendFrameFetcher::next_depth_frame`].
+    fn fetch_depth(&self) -> bool {
+        false
+    }
+
+    /// the depth buffer of the fetched canvas, see [`BackendFrameFetcher::fetch_depth`].
+    fn next_depth_frame(&self, _depth_data: BackendDepthImageData) {}
+
     /// generally a frame fetcher should only fetch the content of a specific canvas
     /// if for whatever reason it changes it can however,
     /// the backend must respect it for every frame.
@@ -45,3 +146,80 @@ pub trait BackendFrameFetcher: Debug + Sync + Send + 'static {
     /// informs that fetching failed for some reason
     fn fetch_err(&self, err: FetchCanvasError);
 }
+
+#[cfg(all(test, feature = "encode"))]
+mod tests {
+    use pool::mt_datatypes::PoolUnclearedVec;
+
+    use super::{BackendPresentedImageDataRgba, PresentedImageFormat, PresentedImageOrientation};
+
+    /// A 3x2 image with distinct colors & alpha values per pixel, top row first.
+    fn known_pixels() -> Vec<u8> {
+        (0..6u8)
+            .flat_map(|i| [i * 40, 255 - i * 40, i * 7, 255 - i])
+            .collect()
+    }
+
+    fn frame(
+        orientation: PresentedImageOrientation,
+        pixels: &[u8],
+    ) -> BackendPresentedImageDataRgba {
+        let mut dest_data_buffer = PoolUnclearedVec::new_without_pool();
+        dest_data_buffer.extend_from_slice(pixels);
+        BackendPresentedImageDataRgba {
+            width: 3,
+            height: 2,
+            orientation,
+            dest_data_buffer,
+        }
+    }
+
+    fn decode_png(file: &[u8]) -> (Vec<u8>, u32, u32) {
+        let mut data = Vec::new();
+        let img = image_utils::png::load_png_image_as_rgba(file, |width, height, channels| {
+            data.resize(width * height * channels, 0);
+            &mut data
+        })
+        .unwrap();
+        let (width, height) = (img.width, img.height);
+        (img.data.to_vec(), width, height)
+    }
+
+    #[test]
+    fn encode_png() {
+        let pixels = known_pixels();
+        let file = frame(PresentedImageOrientation::TopToBottom, &pixels)
+            .encode(PresentedImageFormat::Png)
+            .unwrap();
+        assert_eq!(decode_png(&file), (pixels.clone(), 3, 2));
+
+        // the rows of bottom to top frames are flipped back
+        let bottom_to_top: Vec<u8> = pixels
+            .chunks_exact(3 * 4)
+            .rev()
+            .flatten()
+            .copied()
+            .collect();
+        let file = frame(PresentedImageOrientation::BottomToTop, &bottom_to_top)
+            .encode(PresentedImageFormat::Png)
+            .unwrap();
+        assert_eq!(decode_png(&file), (pixels, 3, 2));
+
+        assert!(frame(PresentedImageOrientation::TopToBottom, &[0; 4])
+            .encode(PresentedImageFormat::Png)
+            .is_err());
+    }
+
+    #[cfg(feature = "webp")]
+    #[test]
+    fn encode_webp() {
+        let pixels = known_pixels();
+        let file = frame(PresentedImageOrientation::TopToBottom, &pixels)
+            .encode(PresentedImageFormat::WebP)
+            .unwrap();
+        assert_eq!(
+            image_utils::webp::load_webp_image_as_rgba(&file).unwrap(),
+            (pixels, 3, 2)
+        );
+    }
+}
@@ -39,6 +39,10 @@ pub enum SamplerAddressMode {
     ClampToEdge,
     /// clamp uv, mirror repeat r
     Texture2dArray,
+    /// repeat on uv, nearest filtering
+    RepeatNearest,
+    /// clamp uv, nearest filtering
+    ClampToEdgeNearest,
 }
 
 /// the resource descriptors are pre-defined sets of descriptors that
@@ -132,7 +136,9 @@ pub trait BackendRenderExecuteInterface {
         address_mode_index: u64,
     );
 
-    fn set_texture_3d(&mut self, index: u64, texture_index: u128);
+    /// `address_mode_index` also selects the filter of the sampler,
+    /// see [`Self::get_address_mode_index`].
+    fn set_texture_3d(&mut self, index: u64, texture_index: u128, address_mode_index: u64);
 
     fn uses_stream_vertex_buffer(&mut self, offset: u64);
 
@@ -9,12 +9,19 @@ use graphics_types::{
 use pool::{mixed_pool::PoolSyncPoint, mt_datatypes::PoolVec};
 
 use crate::{
-    frame_fetcher_plugin::BackendFrameFetcher, plugin::GraphicsObjectRewriteFunc,
+    frame_fetcher_plugin::{BackendFrameFetcher, FrameFetcherAttachError},
+    plugin::GraphicsObjectRewriteFunc,
     types::BackendCommands,
 };
 
 pub trait DriverBackendInterface: Debug {
-    fn attach_frame_fetcher(&mut self, name: String, fetcher: Arc<dyn BackendFrameFetcher>);
+    /// Must not replace a fetcher that is already attached with the same name.
+    fn attach_frame_fetcher(
+        &mut self,
+        name: String,
+        fetcher: Arc<dyn BackendFrameFetcher>,
+    ) -> Result<(), FrameFetcherAttachError>;
+    /// Does nothing if no fetcher with this name is attached.
     fn detach_frame_fetcher(&mut self, name: String);
 
     fn run_command(&mut self, cmd: AllCommands) -> anyhow::Result<()>;
@@ -39,7 +46,9 @@ pub trait GraphicsBackendInterface: Debug + 'static {
     /// putting similar memory types into the same heap
     fn mem_alloc(&self, alloc_type: GraphicsMemoryAllocationType) -> GraphicsBackendMemory;
 
-    /// This only throws errors if the driver backend crashed
+    /// Throws [`FrameFetcherAttachError::AlreadyAttached`] if a fetcher
+    /// with the same name is already attached, else only
+    /// if the driver backend crashed
     fn attach_frame_fetcher(
         &self,
         name: String,
@@ -58,6 +67,33 @@ pub trait GraphicsBackendInterface: Debug + 'static {
     fn gpus(&self) -> Arc<Gpus>;
 
     fn get_backend_mt(&self) -> Arc<dyn GraphicsBackendMtInterface + Sync + Send + 'static>;
+
+    /// Reads back `size` bytes at `offset` of the buffer object `buffer_index`,
+    /// after all commands that were run before are executed.
+    /// Mostly interesting to verify uploads in debug builds.
+    fn read_buffer_object(
+        &self,
+        _buffer_index: u128,
+        _offset: usize,
+        _size: usize,
+    ) -> anyhow::Result<Vec<u8>> {
+        Err(anyhow::anyhow!(
+            "this backend does not support reading back buffer objects."
+        ))
+    }
+
+    /// Reads back `size` bytes at `offset` of the shader storage `shader_storage_index`,
+    /// see [`GraphicsBackendInterface::read_buffer_object`].
+    fn read_shader_storage(
+        &self,
+        _shader_storage_index: u128,
+        _offset: usize,
+        _size: usize,
+    ) -> anyhow::Result<Vec<u8>> {
+        Err(anyhow::anyhow!(
+            "this backend does not support reading back shader storages."
+        ))
+    }
 }
 
 pub trait GraphicsBackendMtInterface: Debug {
@@ -100,4 +136,18 @@ pub trait GraphicsBackendMtInterface: Debug {
         mem: &mut GraphicsBackendMemory,
         do_expensive_flushing: bool,
     ) -> anyhow::Result<()>;
+
+    /// Like [`GraphicsBackendMtInterface::try_flush_mem`], but flushes all `mems` at once,
+    /// which saves the synchronization overhead of flushing many small buffers.
+    /// Every memory is flushed, even if an earlier one failed.
+    /// The first error is returned.
+    fn try_flush_mem_batch(
+        &self,
+        mems: &mut [&mut GraphicsBackendMemory],
+        do_expensive_flushing: bool,
+    ) -> anyhow::Result<()> {
+        mems.iter_mut()
+            .map(|mem| self.try_flush_mem(mem, do_expensive_flushing))
+            .fold(Ok(()), |res, flush_res| res.and(flush_res))
+    }
 }
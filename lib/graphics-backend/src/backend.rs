@@ -2,13 +2,16 @@ use std::{
     cell::RefCell,
     collections::HashMap,
     rc::Rc,
-    sync::{atomic::AtomicU64, Arc},
+    sync::{
+        atomic::{AtomicU64, AtomicUsize},
+        Arc,
+    },
 };
 
 use base_io::io::IoFileSys;
 use config::config::{ConfigBackend, ConfigDebug, ConfigGfx};
 use graphics_backend_traits::{
-    frame_fetcher_plugin::BackendFrameFetcher,
+    frame_fetcher_plugin::{BackendFrameFetcher, BackendPresentedImageDataRgba, FetchCanvasIndex},
     plugin::{BackendCustomPipeline, GraphicsObjectRewriteFunc},
     traits::{GraphicsBackendInterface, GraphicsBackendMtInterface},
     types::BackendCommands,
@@ -20,7 +23,15 @@ use pool::{mixed_pool::PoolSyncPoint, mt_datatypes::PoolVec};
 
 use crate::{
     backend_thread::{BackendThread, BackendThreadInitData},
-    backends::vulkan::vulkan::{VulkanBackendLoadedIo, VulkanBackendLoadingIo},
+    backends::{
+        types::{BackendFrameStats, BackendWaitStats},
+        vulkan::{
+            stream_memory_pool::StreamMemoryPoolOccupancy,
+            vulkan::{
+                FramePresented, TryFrameError, VulkanBackendLoadedIo, VulkanBackendLoadingIo,
+            },
+        },
+    },
     window::{BackendDisplayRequirements, BackendRawDisplayHandle, BackendWindow},
 };
 
@@ -37,6 +48,7 @@ use graphics_types::{
     },
 };
 
+pub use super::backend_mt::MemoryPressureCallback;
 pub use parking_lot::RwLock;
 
 pub type CustomPipelines = Arc<RwLock<Vec<Box<dyn BackendCustomPipeline>>>>;
@@ -70,6 +82,10 @@ pub struct GraphicsBackendMemoryUsage {
     pub buffer_memory_usage: Arc<AtomicU64>,
     pub stream_memory_usage: Arc<AtomicU64>,
     pub staging_memory_usage: Arc<AtomicU64>,
+    /// Texture uploads that are waiting for staging memory.
+    pub pending_texture_uploads: Arc<AtomicU64>,
+    /// Render passes whose full pipeline creation is still queued.
+    pub pending_pipeline_compiles: Arc<AtomicUsize>,
 }
 
 #[derive(Debug)]
@@ -78,7 +94,7 @@ pub struct GraphicsBackendLoading {
 
     backend: BackendThread,
 
-    custom_pipes: Option<CustomPipelines>,
+    custom_pipes: CustomPipelines,
 
     config_dbg: ConfigDebug,
     config_gl: ConfigBackend,
@@ -97,10 +113,15 @@ impl GraphicsBackendLoading {
 
         let benchmark = Benchmark::new(config_dbg.bench);
 
+        // always shared with the backend, so pipes can be registered later
+        let custom_pipes = custom_pipes.unwrap_or_default();
+
         let texture_memory_usage: Arc<AtomicU64> = Default::default();
         let buffer_memory_usage: Arc<AtomicU64> = Default::default();
         let stream_memory_usage: Arc<AtomicU64> = Default::default();
         let staging_memory_usage: Arc<AtomicU64> = Default::default();
+        let pending_texture_uploads: Arc<AtomicU64> = Default::default();
+        let pending_pipeline_compiles: Arc<AtomicUsize> = Default::default();
 
         let backend = BackendThread::new(
             backend.clone(),
@@ -110,11 +131,13 @@ impl GraphicsBackendLoading {
             },
             *config_dbg,
             config_gl.clone(),
-            custom_pipes.clone(),
+            Some(custom_pipes.clone()),
             texture_memory_usage.clone(),
             buffer_memory_usage.clone(),
             stream_memory_usage.clone(),
             staging_memory_usage.clone(),
+            pending_texture_uploads.clone(),
+            pending_pipeline_compiles.clone(),
             io,
         )?;
         benchmark.bench("initializing the backend instance");
@@ -125,6 +148,8 @@ impl GraphicsBackendLoading {
                 buffer_memory_usage,
                 stream_memory_usage,
                 staging_memory_usage,
+                pending_texture_uploads,
+                pending_pipeline_compiles,
             },
 
             backend,
@@ -147,7 +172,7 @@ pub struct GraphicsBackendBase {
     memory_usage: GraphicsBackendMemoryUsage,
 
     #[hiarc_skip_unsafe]
-    custom_pipes: Option<CustomPipelines>,
+    custom_pipes: CustomPipelines,
     pipeline_names: HashMap<String, usize>,
 }
 
@@ -183,10 +208,14 @@ impl GraphicsBackendBase {
                     runtime_threadpool: runtime_threadpool.clone(),
                     window_width: size.width,
                     window_height: size.height,
+                    content_scale: scale_factor,
                     dbg: backend_loading.config_dbg,
                     gl: backend_loading.config_gl.clone(),
                 },
-                GraphicsBackendLoadingIoType::Null => BackendThreadInitData::Null,
+                GraphicsBackendLoadingIoType::Null => BackendThreadInitData::Null {
+                    window_width: size.width,
+                    window_height: size.height,
+                },
             },
             &backend_loading.config_dbg,
             window,
@@ -206,11 +235,8 @@ impl GraphicsBackendBase {
         );
 
         let mut pipeline_names: HashMap<String, usize> = Default::default();
-        if let Some(custom_pipes) = &backend_loading.custom_pipes {
-            let pipes = custom_pipes.read();
-            for (index, pipe) in pipes.iter().enumerate() {
-                pipeline_names.insert(pipe.pipe_name(), index);
-            }
+        for (index, pipe) in backend_loading.custom_pipes.read().iter().enumerate() {
+            pipeline_names.insert(pipe.pipe_name(), index);
         }
 
         let mut res = GraphicsBackendBase {
@@ -264,22 +290,23 @@ impl GraphicsBackendBase {
         new_height: u32,
     ) -> WindowProps {
         self.wait_idle().unwrap();
+        let scale_factor = window_handling
+            .borrow_window()
+            .scale_factor()
+            .clamp(0.0001, f64::MAX);
+
         let cmd_viewport = CommandsMisc::UpdateViewport(CommandUpdateViewport {
             x: 0,
             y: 0,
             width: new_width,
             height: new_height,
             by_resize: true,
+            content_scale: scale_factor,
         });
 
         buffer.add_cmd(AllCommands::Misc(cmd_viewport));
         self.run_cmds_impl(buffer, stream_data).unwrap(); // TODO: unwrap here?
 
-        let scale_factor = window_handling
-            .borrow_window()
-            .scale_factor()
-            .clamp(0.0001, f64::MAX);
-
         self.window_props.window_width = new_width as f64 / scale_factor;
         self.window_props.window_height = new_height as f64 / scale_factor;
         self.window_props.canvas_width = new_width;
@@ -296,6 +323,102 @@ impl GraphicsBackendBase {
         self.backend.window_destroyed_ntfy()
     }
 
+    fn capture_current_frame(
+        &self,
+        index: FetchCanvasIndex,
+    ) -> anyhow::Result<BackendPresentedImageDataRgba> {
+        self.backend.capture_current_frame(index)
+    }
+
+    fn read_texture(&self, slot: u128) -> anyhow::Result<(u32, u32, Vec<u8>)> {
+        self.backend.read_texture(slot)
+    }
+
+    fn read_buffer_object(
+        &self,
+        buffer_index: u128,
+        offset: usize,
+        size: usize,
+    ) -> anyhow::Result<Vec<u8>> {
+        self.backend.read_buffer_object(buffer_index, offset, size)
+    }
+
+    fn read_shader_storage(
+        &self,
+        shader_storage_index: u128,
+        offset: usize,
+        size: usize,
+    ) -> anyhow::Result<Vec<u8>> {
+        self.backend
+            .read_shader_storage(shader_storage_index, offset, size)
+    }
+
+    fn texture_mip_map_count(&self, slot: u128) -> anyhow::Result<u32> {
+        self.backend.texture_mip_map_count(slot)
+    }
+
+    fn last_frame_stats(&self) -> anyhow::Result<BackendFrameStats> {
+        self.backend.last_frame_stats()
+    }
+
+    fn wait_stats(&self) -> anyhow::Result<BackendWaitStats> {
+        self.backend.wait_stats()
+    }
+
+    fn uniform_instance_pool_occupancy(&self) -> anyhow::Result<StreamMemoryPoolOccupancy> {
+        self.backend.uniform_instance_pool_occupancy()
+    }
+
+    fn restart_render_thread(&self, index: usize) -> anyhow::Result<()> {
+        self.backend.restart_render_thread(index)
+    }
+
+    fn try_next_frame(&self) -> Result<FramePresented, TryFrameError> {
+        self.backend.try_next_frame()
+    }
+
+    fn recreate_swapchain(&self, force_now: bool) -> anyhow::Result<()> {
+        self.backend.recreate_swapchain(force_now)
+    }
+
+    fn recover_surface(&self) -> anyhow::Result<()> {
+        self.backend.recover_surface()
+    }
+
+    fn staging_high_water_mark(&self) -> anyhow::Result<u64> {
+        self.backend.staging_high_water_mark()
+    }
+
+    fn set_render_paused(&self, paused: bool) -> anyhow::Result<()> {
+        self.backend.set_render_paused(paused)
+    }
+
+    fn flush_and_drain(&self) -> anyhow::Result<()> {
+        self.backend.flush_and_drain()
+    }
+
+    fn register_custom_pipe(&mut self, pipe: Box<dyn BackendCustomPipeline>) -> anyhow::Result<()> {
+        let name = pipe.pipe_name();
+        anyhow::ensure!(
+            !self.pipeline_names.contains_key(&name),
+            "a custom pipe with the name {name} is already registered"
+        );
+        let mut pipes = self.custom_pipes.write();
+        let index = pipes.len();
+        pipes.push(pipe);
+        drop(pipes);
+        self.pipeline_names.insert(name, index);
+        self.backend.custom_pipes_changed()
+    }
+
+    fn list_custom_pipes(&self) -> Vec<String> {
+        self.custom_pipes
+            .read()
+            .iter()
+            .map(|pipe| pipe.pipe_name())
+            .collect()
+    }
+
     fn run_cmds(
         &mut self,
         buffer: &BackendCommands,
@@ -339,15 +462,11 @@ impl GraphicsBackendBase {
         cmd: &mut PoolVec<u8>,
         f: &dyn Fn(GraphicsObjectRewriteFunc),
     ) {
-        let backend_plugins = self
-            .custom_pipes
-            .as_ref()
-            .expect("no backend custom pipeline plugins registered.");
         let pipe_index = self
             .pipeline_names
             .get(mod_name)
             .expect("pipeline with that name not found");
-        backend_plugins.read()[*pipe_index].rewrite_texture_and_buffer_object_indices(cmd, f)
+        self.custom_pipes.read()[*pipe_index].rewrite_texture_and_buffer_object_indices(cmd, f)
     }
 }
 
@@ -378,6 +497,35 @@ impl GraphicsBackend {
         self.0.borrow().memory_usage.clone()
     }
 
+    /// The number of texture uploads that are waiting for staging memory
+    /// and will be uploaded in one of the next frames.
+    #[must_use]
+    pub fn pending_texture_uploads(&self) -> u64 {
+        self.0
+            .borrow()
+            .memory_usage
+            .pending_texture_uploads
+            .load(std::sync::atomic::Ordering::Relaxed)
+    }
+
+    /// The number of render passes that still wait for their full pipeline
+    /// creation (see `full_pipeline_creation` in the backend config).
+    /// Until then pipelines are compiled on demand, which can cause stutters.
+    #[must_use]
+    pub fn pipelines_pending_count(&self) -> usize {
+        self.0
+            .borrow()
+            .memory_usage
+            .pending_pipeline_compiles
+            .load(std::sync::atomic::Ordering::Relaxed)
+    }
+
+    /// Whether all pipelines finished compiling, see [`Self::pipelines_pending_count`].
+    #[must_use]
+    pub fn pipelines_ready(&self) -> bool {
+        self.pipelines_pending_count() == 0
+    }
+
     pub fn window_created_ntfy(
         &self,
         window: BackendWindow,
@@ -389,6 +537,143 @@ impl GraphicsBackend {
     pub fn window_destroyed_ntfy(&self) -> anyhow::Result<()> {
         self.0.borrow().window_destroyed_ntfy()
     }
+
+    /// Captures the last presented image of the given canvas,
+    /// without attaching a frame fetcher.
+    pub fn capture_current_frame(
+        &self,
+        index: FetchCanvasIndex,
+    ) -> anyhow::Result<BackendPresentedImageDataRgba> {
+        self.0.borrow().capture_current_frame(index)
+    }
+
+    /// Reads back the texture in `slot` as `(width, height, rgba)`.
+    pub fn read_texture(&self, slot: u128) -> anyhow::Result<(u32, u32, Vec<u8>)> {
+        self.0.borrow().read_texture(slot)
+    }
+
+    /// Reads back `size` bytes at `offset` of the buffer object `buffer_index`.
+    pub fn read_buffer_object(
+        &self,
+        buffer_index: u128,
+        offset: usize,
+        size: usize,
+    ) -> anyhow::Result<Vec<u8>> {
+        self.0
+            .borrow()
+            .read_buffer_object(buffer_index, offset, size)
+    }
+
+    /// Reads back `size` bytes at `offset` of the shader storage `shader_storage_index`.
+    pub fn read_shader_storage(
+        &self,
+        shader_storage_index: u128,
+        offset: usize,
+        size: usize,
+    ) -> anyhow::Result<Vec<u8>> {
+        self.0
+            .borrow()
+            .read_shader_storage(shader_storage_index, offset, size)
+    }
+
+    /// The amount of mip levels the texture in `slot` was created with.
+    pub fn texture_mip_map_count(&self, slot: u128) -> anyhow::Result<u32> {
+        self.0.borrow().texture_mip_map_count(slot)
+    }
+
+    /// What the backend did during the last frame,
+    /// e.g. the estimated draw calls and the serviced updates.
+    pub fn last_frame_stats(&self) -> anyhow::Result<BackendFrameStats> {
+        self.0.borrow().last_frame_stats()
+    }
+
+    /// How often the backend waited for the gpu since it was created,
+    /// e.g. because the staging memory ran out.
+    pub fn wait_stats(&self) -> anyhow::Result<BackendWaitStats> {
+        self.0.borrow().wait_stats()
+    }
+
+    /// How many stream uniform instances exist and how many of them are free.
+    pub fn uniform_instance_pool_occupancy(&self) -> anyhow::Result<StreamMemoryPoolOccupancy> {
+        self.0.borrow().uniform_instance_pool_occupancy()
+    }
+
+    /// Replaces the render thread at `index` by a new one,
+    /// e.g. after it panicked while rendering.
+    /// Should only be called between frames.
+    pub fn restart_render_thread(&self, index: usize) -> anyhow::Result<()> {
+        self.0.borrow().restart_render_thread(index)
+    }
+
+    /// Submits the current frame and starts the next one, unless the gpu
+    /// did not finish the previous frames yet.
+    /// In that case [`TryFrameError::WouldBlock`] is returned and nothing changes.
+    pub fn try_next_frame(&self) -> Result<FramePresented, TryFrameError> {
+        self.0.borrow().try_next_frame()
+    }
+
+    /// Recreates the swapchain before the next frame, even if the window was not resized.
+    /// Useful for embedders that manage their own window loop, e.g. after the window
+    /// moved to a monitor with different capabilities.
+    pub fn request_swapchain_recreate(&self) -> anyhow::Result<()> {
+        self.0.borrow().recreate_swapchain(false)
+    }
+
+    /// Finishes the current frame and recreates the swapchain right away.
+    pub fn force_recreate_now(&self) -> anyhow::Result<()> {
+        self.0.borrow().recreate_swapchain(true)
+    }
+
+    /// Replaces the surface by a new one for the same window,
+    /// like it's done if the surface is reported as lost.
+    pub fn recover_surface(&self) -> anyhow::Result<()> {
+        self.0.borrow().recover_surface()
+    }
+
+    /// The most staging memory that was allocated at once for uploads, in bytes.
+    pub fn staging_high_water_mark(&self) -> anyhow::Result<u64> {
+        self.0.borrow().staging_high_water_mark()
+    }
+
+    /// Pauses or resumes rendering, e.g. to save power while the window is minimized.
+    /// While paused, nothing is presented, but memory commands like texture
+    /// & buffer updates are still executed.
+    pub fn set_render_paused(&self, paused: bool) -> anyhow::Result<()> {
+        self.0.borrow().set_render_paused(paused)
+    }
+
+    /// Submits everything that was sent to the backend since the last swap
+    /// and lets the frame fetchers fetch it, then waits until the gpu is idle.
+    /// Call this on shutdown, so e.g. a last screenshot is not lost.
+    pub fn flush_and_drain(&self) -> anyhow::Result<()> {
+        self.0.borrow().flush_and_drain()
+    }
+
+    /// Registers a custom pipe after the backend was created, e.g. for mods that are loaded
+    /// at runtime. Afterwards [`CommandsRenderMod`](graphics_types::commands::CommandsRenderMod)
+    /// commands with the pipe's name are rendered by it.
+    ///
+    /// Fails if a pipe with the same name is already registered.
+    pub fn register_custom_pipe(&self, pipe: Box<dyn BackendCustomPipeline>) -> anyhow::Result<()> {
+        self.0.borrow_mut().register_custom_pipe(pipe)
+    }
+
+    /// The names of all registered custom pipes.
+    pub fn list_custom_pipes(&self) -> Vec<String> {
+        self.0.borrow().list_custom_pipes()
+    }
+
+    /// Sets a callback that is called whenever a memory allocation ran out of
+    /// staging memory and had to fall back to system memory, so embedders can
+    /// e.g. free caches before the system runs out of memory.
+    /// `None` removes the callback.
+    pub fn set_memory_pressure_callback(&self, callback: Option<MemoryPressureCallback>) {
+        self.0
+            .borrow()
+            .backend_mt
+            .backend_mt
+            .set_memory_pressure_callback(callback)
+    }
 }
 
 impl GraphicsBackendInterface for GraphicsBackend {
@@ -436,4 +721,22 @@ impl GraphicsBackendInterface for GraphicsBackend {
     fn gpus(&self) -> Arc<Gpus> {
         self.0.borrow().backend_mt.backend_mt.gpus()
     }
+
+    fn read_buffer_object(
+        &self,
+        buffer_index: u128,
+        offset: usize,
+        size: usize,
+    ) -> anyhow::Result<Vec<u8>> {
+        GraphicsBackend::read_buffer_object(self, buffer_index, offset, size)
+    }
+
+    fn read_shader_storage(
+        &self,
+        shader_storage_index: u128,
+        offset: usize,
+        size: usize,
+    ) -> anyhow::Result<Vec<u8>> {
+        GraphicsBackend::read_shader_storage(self, shader_storage_index, offset, size)
+    }
 }
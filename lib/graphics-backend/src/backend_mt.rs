@@ -9,6 +9,26 @@ use graphics_types::{
 };
 use hiarc::Hiarc;
 
+/// Called with the allocation type and the allocated size in bytes, whenever an allocation
+/// could not get staging memory and fell back to (slower) system memory.
+#[derive(Clone)]
+pub struct MemoryPressureCallback(
+    pub Arc<dyn Fn(&GraphicsMemoryAllocationType, usize) + Send + Sync>,
+);
+
+impl MemoryPressureCallback {
+    pub fn new(f: impl Fn(&GraphicsMemoryAllocationType, usize) + Send + Sync + 'static) -> Self {
+        Self(Arc::new(f))
+    }
+}
+
+impl std::fmt::Debug for MemoryPressureCallback {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MemoryPressureCallback")
+            .finish_non_exhaustive()
+    }
+}
+
 #[derive(Debug, Hiarc)]
 pub enum GraphicsBackendMtType {
     Vulkan(VulkanBackendMt),
@@ -40,6 +60,16 @@ impl GraphicsBackendMtType {
             }),
         }
     }
+
+    pub fn set_memory_pressure_callback(&self, callback: Option<MemoryPressureCallback>) {
+        match self {
+            GraphicsBackendMtType::Vulkan(backend) => {
+                backend.set_memory_pressure_callback(callback)
+            }
+            // the null backend always allocates system memory
+            GraphicsBackendMtType::Null(_) => {}
+        }
+    }
 }
 
 /// The multi-threaded backend part to [`graphics::graphics_mt::GraphicsMultiThreaded`]
@@ -66,4 +96,14 @@ impl GraphicsBackendMtInterface for GraphicsBackendMultiThreaded {
             .unwrap()
             .try_flush_mem(mem, do_expensive_flushing)
     }
+
+    fn try_flush_mem_batch(
+        &self,
+        mems: &mut [&mut GraphicsBackendMemory],
+        do_expensive_flushing: bool,
+    ) -> anyhow::Result<()> {
+        self.backend_mt
+            .unwrap()
+            .try_flush_mem_batch(mems, do_expensive_flushing)
+    }
 }
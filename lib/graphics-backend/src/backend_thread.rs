@@ -1,5 +1,5 @@
 use std::sync::{
-    atomic::AtomicU64,
+    atomic::{AtomicU64, AtomicUsize},
     mpsc::{Receiver, Sender, SyncSender},
     Arc,
 };
@@ -9,7 +9,11 @@ use base::join_thread::JoinThread;
 use base_io::io::IoFileSys;
 use config::config::{ConfigBackend, ConfigDebug};
 use graphics_backend_traits::{
-    frame_fetcher_plugin::BackendFrameFetcher, traits::DriverBackendInterface,
+    frame_fetcher_plugin::{
+        BackendFrameFetcher, BackendPresentedImageDataRgba, FetchCanvasIndex,
+        FrameFetcherAttachError,
+    },
+    traits::DriverBackendInterface,
 };
 use graphics_base_traits::traits::{
     GraphicsStreamUniformRawDataStatic, GraphicsStreamVertices, GraphicsStreamVerticesStatic,
@@ -31,13 +35,15 @@ use crate::{
     backend_mt::GraphicsBackendMtType,
     backends::{
         null::NullBackend,
-        types::BackendWriteFiles,
+        types::{BackendFrameStats, BackendWaitStats, BackendWriteFiles},
         vulkan::{
+            stream_memory_pool::StreamMemoryPoolOccupancy,
             vulkan::{
-                VulkanBackend, VulkanBackendLoadedIo, VulkanBackendLoading, VulkanInUseStreamData,
+                FramePresented, RenderThreadError, TryFrameError, VulkanBackend,
+                VulkanBackendLoadedIo, VulkanBackendLoading, VulkanInUseStreamData,
                 VulkanMainThreadData, VulkanMainThreadInit,
             },
-            Options,
+            OptionsBuilder,
         },
     },
     cache::get_backend_cache,
@@ -47,7 +53,7 @@ use crate::{
 #[derive(Debug)]
 enum GraphicsBackendLoadingType {
     Vulkan(Box<VulkanBackendLoading>),
-    Null(NullBackend),
+    Null,
 }
 
 #[derive(Debug, Hiarc)]
@@ -74,10 +80,14 @@ pub enum BackendThreadInitData {
 
         window_width: u32,
         window_height: u32,
+        content_scale: f64,
         dbg: ConfigDebug,
         gl: ConfigBackend,
     },
-    Null,
+    Null {
+        window_width: u32,
+        window_height: u32,
+    },
 }
 
 #[derive(Debug, Hiarc)]
@@ -105,6 +115,8 @@ pub enum BackendThreadBackendEvent {
         buffer_memory_usage: Arc<AtomicU64>,
         stream_memory_usage: Arc<AtomicU64>,
         staging_memory_usage: Arc<AtomicU64>,
+        pending_texture_uploads: Arc<AtomicU64>,
+        pending_pipeline_compiles: Arc<AtomicUsize>,
         write_files: BackendWriteFiles,
     },
     FinishInit {
@@ -119,6 +131,8 @@ pub enum BackendThreadBackendEvent {
         name: String,
         #[hiarc_skip_unsafe]
         fetcher: Arc<dyn BackendFrameFetcher>,
+        #[hiarc_skip_unsafe]
+        sender: SyncSender<Result<(), FrameFetcherAttachError>>,
     },
     DetachFrameFetcher {
         name: String,
@@ -130,7 +144,61 @@ pub enum BackendThreadBackendEvent {
         main_thread_init: BackendThreadMainThreadInit,
     },
     WindowDestroyNtfy(SyncSender<()>),
-    WaitForIdle(SyncSender<()>),
+    WaitForIdle(#[hiarc_skip_unsafe] SyncSender<anyhow::Result<()>>),
+    CaptureFrame {
+        #[hiarc_skip_unsafe]
+        index: FetchCanvasIndex,
+        #[hiarc_skip_unsafe]
+        sender: SyncSender<anyhow::Result<BackendPresentedImageDataRgba>>,
+    },
+    ReadTexture {
+        slot: u128,
+        #[hiarc_skip_unsafe]
+        sender: SyncSender<anyhow::Result<(u32, u32, Vec<u8>)>>,
+    },
+    TextureMipMapCount {
+        slot: u128,
+        #[hiarc_skip_unsafe]
+        sender: SyncSender<anyhow::Result<u32>>,
+    },
+    LastFrameStats(#[hiarc_skip_unsafe] SyncSender<BackendFrameStats>),
+    WaitStats(#[hiarc_skip_unsafe] SyncSender<BackendWaitStats>),
+    UniformInstancePoolOccupancy(#[hiarc_skip_unsafe] SyncSender<StreamMemoryPoolOccupancy>),
+    ReadBufferObject {
+        buffer_index: u128,
+        offset: usize,
+        size: usize,
+        #[hiarc_skip_unsafe]
+        sender: SyncSender<anyhow::Result<Vec<u8>>>,
+    },
+    ReadShaderStorage {
+        shader_storage_index: u128,
+        offset: usize,
+        size: usize,
+        #[hiarc_skip_unsafe]
+        sender: SyncSender<anyhow::Result<Vec<u8>>>,
+    },
+    TryNextFrame(#[hiarc_skip_unsafe] SyncSender<Result<FramePresented, TryFrameError>>),
+    RestartRenderThread {
+        index: usize,
+        #[hiarc_skip_unsafe]
+        sender: SyncSender<anyhow::Result<()>>,
+    },
+    RecreateSwapchain {
+        /// Recreate it right away instead of before the next frame.
+        force_now: bool,
+        #[hiarc_skip_unsafe]
+        sender: SyncSender<anyhow::Result<()>>,
+    },
+    RecoverSurface(#[hiarc_skip_unsafe] SyncSender<anyhow::Result<()>>),
+    StagingHighWaterMark(#[hiarc_skip_unsafe] SyncSender<u64>),
+    CustomPipesChanged(#[hiarc_skip_unsafe] SyncSender<anyhow::Result<()>>),
+    SetRenderPaused {
+        paused: bool,
+        #[hiarc_skip_unsafe]
+        sender: SyncSender<()>,
+    },
+    FlushAndDrain(#[hiarc_skip_unsafe] SyncSender<anyhow::Result<()>>),
     Stop,
 }
 
@@ -172,7 +240,7 @@ pub struct BackendThread {
     sync_points: Vec<Box<dyn PoolSyncPoint>>,
 
     // custom drop, must stay second element
-    _thread: JoinThread<anyhow::Result<()>>,
+    thread: JoinThread<anyhow::Result<()>>,
     // custom drop, must stay last element
     _file_writer: FileWriterDrop,
 }
@@ -188,6 +256,8 @@ impl BackendThread {
         buffer_memory_usage: Arc<AtomicU64>,
         stream_memory_usage: Arc<AtomicU64>,
         staging_memory_usage: Arc<AtomicU64>,
+        pending_texture_uploads: Arc<AtomicU64>,
+        pending_pipeline_compiles: Arc<AtomicUsize>,
         io: IoFileSys,
     ) -> anyhow::Result<Self> {
         let (events, recv) = std::sync::mpsc::channel();
@@ -205,6 +275,8 @@ impl BackendThread {
             buffer_memory_usage,
             stream_memory_usage,
             staging_memory_usage,
+            pending_texture_uploads,
+            pending_pipeline_compiles,
             write_files: write_files.clone(),
         })?;
 
@@ -223,7 +295,7 @@ impl BackendThread {
             events,
             recv_events: frontend_events,
             sync_points: Default::default(),
-            _thread: JoinThread::new(thread),
+            thread: JoinThread::new(thread),
             _file_writer: FileWriterDrop { write_files, io },
         })
     }
@@ -287,22 +359,208 @@ impl BackendThread {
         Ok(())
     }
 
-    pub fn window_destroyed_ntfy(&self) -> anyhow::Result<()> {
+    /// Captures the last presented image of the canvas, see
+    /// [`VulkanBackend::capture_current_frame`].
+    pub fn capture_current_frame(
+        &self,
+        index: FetchCanvasIndex,
+    ) -> anyhow::Result<BackendPresentedImageDataRgba> {
         let (sender, recv) = std::sync::mpsc::sync_channel(0);
         self.events
-            .send(BackendThreadBackendEvent::WindowDestroyNtfy(sender))?;
+            .send(BackendThreadBackendEvent::CaptureFrame { index, sender })?;
+        recv.recv()?
+    }
+
+    /// Reads back a texture, see [`VulkanBackend::read_texture`].
+    pub fn read_texture(&self, slot: u128) -> anyhow::Result<(u32, u32, Vec<u8>)> {
+        let (sender, recv) = std::sync::mpsc::sync_channel(0);
+        self.events
+            .send(BackendThreadBackendEvent::ReadTexture { slot, sender })?;
+        recv.recv()?
+    }
+
+    /// Reads back a part of a buffer object, see [`VulkanBackend::read_buffer_object`].
+    pub fn read_buffer_object(
+        &self,
+        buffer_index: u128,
+        offset: usize,
+        size: usize,
+    ) -> anyhow::Result<Vec<u8>> {
+        let (sender, recv) = std::sync::mpsc::sync_channel(0);
+        self.events
+            .send(BackendThreadBackendEvent::ReadBufferObject {
+                buffer_index,
+                offset,
+                size,
+                sender,
+            })?;
+        recv.recv()?
+    }
+
+    /// Reads back a part of a shader storage, see [`VulkanBackend::read_shader_storage`].
+    pub fn read_shader_storage(
+        &self,
+        shader_storage_index: u128,
+        offset: usize,
+        size: usize,
+    ) -> anyhow::Result<Vec<u8>> {
+        let (sender, recv) = std::sync::mpsc::sync_channel(0);
+        self.events
+            .send(BackendThreadBackendEvent::ReadShaderStorage {
+                shader_storage_index,
+                offset,
+                size,
+                sender,
+            })?;
+        recv.recv()?
+    }
+
+    /// The mip level count of a texture, see [`VulkanBackend::texture_mip_map_count`].
+    pub fn texture_mip_map_count(&self, slot: u128) -> anyhow::Result<u32> {
+        let (sender, recv) = std::sync::mpsc::sync_channel(0);
+        self.events
+            .send(BackendThreadBackendEvent::TextureMipMapCount { slot, sender })?;
+        recv.recv()?
+    }
+
+    /// The stats of the last frame, see [`VulkanBackend::last_frame_stats`].
+    pub fn last_frame_stats(&self) -> anyhow::Result<BackendFrameStats> {
+        let (sender, recv) = std::sync::mpsc::sync_channel(0);
+        self.events
+            .send(BackendThreadBackendEvent::LastFrameStats(sender))?;
+        Ok(recv.recv()?)
+    }
+
+    /// See [`VulkanBackend::wait_stats`].
+    pub fn wait_stats(&self) -> anyhow::Result<BackendWaitStats> {
+        let (sender, recv) = std::sync::mpsc::sync_channel(0);
+        self.events
+            .send(BackendThreadBackendEvent::WaitStats(sender))?;
+        Ok(recv.recv()?)
+    }
+
+    /// See [`VulkanBackend::uniform_instance_pool_occupancy`].
+    pub fn uniform_instance_pool_occupancy(&self) -> anyhow::Result<StreamMemoryPoolOccupancy> {
+        let (sender, recv) = std::sync::mpsc::sync_channel(0);
+        self.events
+            .send(BackendThreadBackendEvent::UniformInstancePoolOccupancy(
+                sender,
+            ))?;
+        Ok(recv.recv()?)
+    }
+
+    /// Starts the next frame if the gpu is ready, see [`VulkanBackend::try_next_frame`].
+    pub fn try_next_frame(&self) -> Result<FramePresented, TryFrameError> {
+        let (sender, recv) = std::sync::mpsc::sync_channel(0);
+        self.events
+            .send(BackendThreadBackendEvent::TryNextFrame(sender))
+            .map_err(|err| TryFrameError::Frame(err.into()))?;
+        recv.recv()
+            .map_err(|err| TryFrameError::Frame(err.into()))?
+    }
+
+    /// Replaces a render thread by a new one, see [`VulkanBackend::restart_render_thread`].
+    pub fn restart_render_thread(&self, index: usize) -> anyhow::Result<()> {
+        let (sender, recv) = std::sync::mpsc::sync_channel(0);
+        self.events
+            .send(BackendThreadBackendEvent::RestartRenderThread { index, sender })?;
+        recv.recv()?
+    }
+
+    /// Requests a swapchain recreation, see [`VulkanBackend::request_swapchain_recreate`]
+    /// and [`VulkanBackend::force_recreate_now`].
+    pub fn recreate_swapchain(&self, force_now: bool) -> anyhow::Result<()> {
+        let (sender, recv) = std::sync::mpsc::sync_channel(0);
+        self.events
+            .send(BackendThreadBackendEvent::RecreateSwapchain { force_now, sender })?;
+        recv.recv()?
+    }
+
+    /// Replaces the surface by a new one, see [`VulkanBackend::recover_surface`].
+    pub fn recover_surface(&self) -> anyhow::Result<()> {
+        let (sender, recv) = std::sync::mpsc::sync_channel(0);
+        self.events
+            .send(BackendThreadBackendEvent::RecoverSurface(sender))?;
+        recv.recv()?
+    }
+
+    /// See [`VulkanBackend::staging_high_water_mark`].
+    pub fn staging_high_water_mark(&self) -> anyhow::Result<u64> {
+        let (sender, recv) = std::sync::mpsc::sync_channel(0);
+        self.events
+            .send(BackendThreadBackendEvent::StagingHighWaterMark(sender))?;
+        Ok(recv.recv()?)
+    }
+
+    /// Notifies the backend about custom pipes that were added to the shared pipe list,
+    /// see [`VulkanBackend::custom_pipes_changed`].
+    pub fn custom_pipes_changed(&self) -> anyhow::Result<()> {
+        let (sender, recv) = std::sync::mpsc::sync_channel(0);
+        self.events
+            .send(BackendThreadBackendEvent::CustomPipesChanged(sender))?;
+        recv.recv()?
+    }
+
+    /// See [`VulkanBackend::set_render_paused`].
+    pub fn set_render_paused(&self, paused: bool) -> anyhow::Result<()> {
+        let (sender, recv) = std::sync::mpsc::sync_channel(0);
+        self.events
+            .send(BackendThreadBackendEvent::SetRenderPaused { paused, sender })?;
         recv.recv()?;
         Ok(())
     }
 
-    pub fn wait_idle(&self) -> anyhow::Result<()> {
+    /// See [`VulkanBackend::flush_and_drain`].
+    /// Like [`Self::wait_idle`] it returns the error of a failed render thread.
+    pub fn flush_and_drain(&self) -> anyhow::Result<()> {
         let (sender, recv) = std::sync::mpsc::sync_channel(0);
         self.events
-            .send(BackendThreadBackendEvent::WaitForIdle(sender))?;
+            .send(BackendThreadBackendEvent::FlushAndDrain(sender))?;
+        recv.recv()?
+    }
+
+    pub fn window_destroyed_ntfy(&self) -> anyhow::Result<()> {
+        let (sender, recv) = std::sync::mpsc::sync_channel(0);
+        self.events
+            .send(BackendThreadBackendEvent::WindowDestroyNtfy(sender))?;
         recv.recv()?;
         Ok(())
     }
 
+    /// Waits until all previous commands were executed.
+    ///
+    /// If a render thread failed since the last call, its error is returned here,
+    /// while the backend thread keeps running (see [`RenderThreadError`]).
+    pub fn wait_idle(&mut self) -> anyhow::Result<()> {
+        let (sender, recv) = std::sync::mpsc::sync_channel(0);
+        let res = match self
+            .events
+            .send(BackendThreadBackendEvent::WaitForIdle(sender))
+        {
+            Ok(()) => match recv.recv() {
+                Ok(res) => return res,
+                Err(err) => Err(err.into()),
+            },
+            Err(err) => Err(err.into()),
+        };
+        res.map_err(|err| self.thread_error(err))
+    }
+
+    /// The channels to the backend thread only close if it stopped,
+    /// in which case the error it stopped with is returned instead of `err`,
+    /// e.g. the error of a failed render command.
+    fn thread_error(&mut self, err: anyhow::Error) -> anyhow::Error {
+        // the thread is about to exit, since its channels are closed
+        while !self.thread.is_finished() {
+            std::thread::yield_now();
+        }
+        match self.thread.try_join() {
+            Ok(Some(Err(thread_err))) => thread_err,
+            Err(panic) => panic.context("the graphics backend thread panicked"),
+            Ok(_) => err,
+        }
+    }
+
     /// add a pool sync pointer before the [`BackendThread::run_cmds`] command is called
     /// sync points can not be removed, so call carefully
     pub fn add_sync_point(&mut self, sync_point: Box<dyn PoolSyncPoint>) {
@@ -345,8 +603,14 @@ impl BackendThread {
         name: String,
         fetcher: Arc<dyn BackendFrameFetcher>,
     ) -> anyhow::Result<()> {
+        let (sender, recv) = std::sync::mpsc::sync_channel(1);
         self.events
-            .send(BackendThreadBackendEvent::AttachFrameFetcher { name, fetcher })?;
+            .send(BackendThreadBackendEvent::AttachFrameFetcher {
+                name,
+                fetcher,
+                sender,
+            })?;
+        recv.recv()??;
         Ok(())
     }
 
@@ -372,19 +636,18 @@ impl BackendThread {
             buffer_memory_usage,
             stream_memory_usage,
             staging_memory_usage,
+            pending_texture_uploads,
+            pending_pipeline_compiles,
             write_files,
         } = load_ev
         else {
             return Err(anyhow!("first event is always the load event"));
         };
         let backend_loading = match backend_ty.to_ascii_lowercase().as_str() {
-            "null" => GraphicsBackendLoadingType::Null(NullBackend {}),
+            "null" => GraphicsBackendLoadingType::Null,
             // "vulkan"
             _ => {
-                let options = Options {
-                    dbg: &config_dbg,
-                    gl: &config_gl,
-                };
+                let options = OptionsBuilder::new(&config_dbg, &config_gl).build()?;
                 // prepare the GL instance
                 let backend = VulkanBackendLoading::new(
                     display_requirements,
@@ -392,6 +655,8 @@ impl BackendThread {
                     buffer_memory_usage,
                     stream_memory_usage,
                     staging_memory_usage,
+                    pending_texture_uploads,
+                    pending_pipeline_compiles,
                     &options,
                     custom_pipes,
                 )?;
@@ -406,7 +671,7 @@ impl BackendThread {
                         loading,
                     ))
                 }
-                GraphicsBackendLoadingType::Null(_) => BackendThreadInitFromMainThread::Null,
+                GraphicsBackendLoadingType::Null => BackendThreadInitFromMainThread::Null,
             },
         ))?;
 
@@ -428,6 +693,7 @@ impl BackendThread {
                 runtime_threadpool,
                 window_width,
                 window_height,
+                content_scale,
                 dbg,
                 gl,
             } => {
@@ -444,11 +710,15 @@ impl BackendThread {
                     main_thread_init,
                     window_width,
                     window_height,
-                    &Options { dbg: &dbg, gl: &gl },
+                    content_scale,
+                    &OptionsBuilder::new(&dbg, &gl).build()?,
                     write_files,
                 )?)
             }
-            BackendThreadInitData::Null => GraphicsBackendType::Null(NullBackend {}),
+            BackendThreadInitData::Null {
+                window_width,
+                window_height,
+            } => GraphicsBackendType::Null(NullBackend::new(window_width, window_height)),
         };
 
         enum InUseDataPerBackend {
@@ -525,6 +795,10 @@ impl BackendThread {
             cmds: Vec::new(),
         })?;
 
+        // render threads can be restarted, so their errors don't stop the backend thread,
+        // instead the first one is reported by the next synchronizing event.
+        let mut render_thread_err: Option<anyhow::Error> = None;
+
         'outer: while let Ok(event) = events.recv() {
             match event {
                 BackendThreadBackendEvent::Init { .. } => {
@@ -555,7 +829,12 @@ impl BackendThread {
                     backend_ref.start_commands(cmds.len());
 
                     for cmd in cmds.drain(..) {
-                        backend_ref.run_command(cmd)?;
+                        match backend_ref.run_command(cmd) {
+                            Err(err) if err.downcast_ref::<RenderThreadError>().is_some() => {
+                                render_thread_err.get_or_insert(err);
+                            }
+                            res => res?,
+                        }
                     }
                     backend_ref.end_commands()?;
 
@@ -633,8 +912,12 @@ impl BackendThread {
                     })?;
                 }
                 BackendThreadBackendEvent::Stop => break 'outer,
-                BackendThreadBackendEvent::AttachFrameFetcher { name, fetcher } => {
-                    backend.as_mut().attach_frame_fetcher(name, fetcher)
+                BackendThreadBackendEvent::AttachFrameFetcher {
+                    name,
+                    fetcher,
+                    sender,
+                } => {
+                    sender.send(backend.as_mut().attach_frame_fetcher(name, fetcher))?;
                 }
                 BackendThreadBackendEvent::DetachFrameFetcher { name } => {
                     backend.as_mut().detach_frame_fetcher(name)
@@ -667,8 +950,150 @@ impl BackendThread {
                     GraphicsBackendType::Null(_) => {}
                 },
                 BackendThreadBackendEvent::WaitForIdle(sender) => {
+                    sender.send(render_thread_err.take().map_or(Ok(()), Err))?;
+                }
+                BackendThreadBackendEvent::CaptureFrame { index, sender } => {
+                    sender.send(match &mut backend {
+                        GraphicsBackendType::Vulkan(backend) => {
+                            backend.capture_current_frame(index)
+                        }
+                        GraphicsBackendType::Null(_) => {
+                            Err(anyhow!("the null backend can not capture frames."))
+                        }
+                    })?;
+                }
+                BackendThreadBackendEvent::ReadTexture { slot, sender } => {
+                    sender.send(match &mut backend {
+                        GraphicsBackendType::Vulkan(backend) => backend.read_texture(slot),
+                        GraphicsBackendType::Null(backend) => backend.read_texture(slot),
+                    })?;
+                }
+                BackendThreadBackendEvent::ReadBufferObject {
+                    buffer_index,
+                    offset,
+                    size,
+                    sender,
+                } => {
+                    sender.send(match &mut backend {
+                        GraphicsBackendType::Vulkan(backend) => {
+                            backend.read_buffer_object(buffer_index, offset, size)
+                        }
+                        GraphicsBackendType::Null(backend) => {
+                            backend.read_buffer_object(buffer_index, offset, size)
+                        }
+                    })?;
+                }
+                BackendThreadBackendEvent::ReadShaderStorage {
+                    shader_storage_index,
+                    offset,
+                    size,
+                    sender,
+                } => {
+                    sender.send(match &mut backend {
+                        GraphicsBackendType::Vulkan(backend) => {
+                            backend.read_shader_storage(shader_storage_index, offset, size)
+                        }
+                        GraphicsBackendType::Null(backend) => {
+                            backend.read_shader_storage(shader_storage_index, offset, size)
+                        }
+                    })?;
+                }
+                BackendThreadBackendEvent::TextureMipMapCount { slot, sender } => {
+                    sender.send(match &mut backend {
+                        GraphicsBackendType::Vulkan(backend) => backend.texture_mip_map_count(slot),
+                        GraphicsBackendType::Null(backend) => backend.texture_mip_map_count(slot),
+                    })?;
+                }
+                BackendThreadBackendEvent::LastFrameStats(sender) => {
+                    sender.send(match &backend {
+                        GraphicsBackendType::Vulkan(backend) => backend.last_frame_stats(),
+                        GraphicsBackendType::Null(backend) => backend.last_frame_stats(),
+                    })?;
+                }
+                BackendThreadBackendEvent::WaitStats(sender) => {
+                    sender.send(match &backend {
+                        GraphicsBackendType::Vulkan(backend) => backend.wait_stats(),
+                        // there is no gpu to wait for
+                        GraphicsBackendType::Null(_) => Default::default(),
+                    })?;
+                }
+                BackendThreadBackendEvent::UniformInstancePoolOccupancy(sender) => {
+                    sender.send(match &backend {
+                        GraphicsBackendType::Vulkan(backend) => {
+                            backend.uniform_instance_pool_occupancy()
+                        }
+                        GraphicsBackendType::Null(_) => Default::default(),
+                    })?;
+                }
+                BackendThreadBackendEvent::TryNextFrame(sender) => {
+                    sender.send(match &mut backend {
+                        GraphicsBackendType::Vulkan(backend) => backend.try_next_frame(),
+                        GraphicsBackendType::Null(_) => Err(TryFrameError::Frame(anyhow!(
+                            "the null backend does not render frames."
+                        ))),
+                    })?;
+                }
+                BackendThreadBackendEvent::RestartRenderThread { index, sender } => {
+                    sender.send(match &mut backend {
+                        GraphicsBackendType::Vulkan(backend) => {
+                            backend.restart_render_thread(index)
+                        }
+                        GraphicsBackendType::Null(_) => {
+                            Err(anyhow!("the null backend has no render threads."))
+                        }
+                    })?;
+                }
+                BackendThreadBackendEvent::RecreateSwapchain { force_now, sender } => {
+                    sender.send(match &mut backend {
+                        GraphicsBackendType::Vulkan(backend) => {
+                            if force_now {
+                                backend.force_recreate_now()
+                            } else {
+                                backend.request_swapchain_recreate();
+                                Ok(())
+                            }
+                        }
+                        // there is no swapchain
+                        GraphicsBackendType::Null(_) => Ok(()),
+                    })?;
+                }
+                BackendThreadBackendEvent::RecoverSurface(sender) => {
+                    sender.send(match &mut backend {
+                        GraphicsBackendType::Vulkan(backend) => backend.recover_surface(),
+                        // there is no surface
+                        GraphicsBackendType::Null(_) => Ok(()),
+                    })?;
+                }
+                BackendThreadBackendEvent::StagingHighWaterMark(sender) => {
+                    sender.send(match &backend {
+                        GraphicsBackendType::Vulkan(backend) => backend.staging_high_water_mark(),
+                        // uploads don't need staging memory
+                        GraphicsBackendType::Null(_) => 0,
+                    })?;
+                }
+                BackendThreadBackendEvent::CustomPipesChanged(sender) => {
+                    sender.send(match &mut backend {
+                        GraphicsBackendType::Vulkan(backend) => backend.custom_pipes_changed(),
+                        // mod commands are discarded anyway
+                        GraphicsBackendType::Null(_) => Ok(()),
+                    })?;
+                }
+                BackendThreadBackendEvent::SetRenderPaused { paused, sender } => {
+                    match &mut backend {
+                        GraphicsBackendType::Vulkan(backend) => backend.set_render_paused(paused),
+                        // never renders anything
+                        GraphicsBackendType::Null(_) => {}
+                    }
                     sender.send(())?;
                 }
+                BackendThreadBackendEvent::FlushAndDrain(sender) => {
+                    let res = match &mut backend {
+                        GraphicsBackendType::Vulkan(backend) => backend.flush_and_drain(),
+                        // nothing is ever submitted
+                        GraphicsBackendType::Null(_) => Ok(()),
+                    };
+                    sender.send(render_thread_err.take().map_or(res, Err))?;
+                }
             }
         }
 
@@ -1,41 +1,466 @@
+use std::{collections::HashMap, sync::Arc};
+
 use anyhow::anyhow;
-use graphics_backend_traits::traits::{DriverBackendInterface, GraphicsBackendMtInterface};
+use base::linked_hash_map_view::FxLinkedHashMap;
+use graphics_backend_traits::{
+    frame_fetcher_plugin::{
+        BackendFrameFetcher, BackendPresentedImageDataRgba, FetchCanvasError, FetchCanvasIndex,
+        FrameFetcherAttachError, PresentedImageOrientation,
+    },
+    traits::{DriverBackendInterface, GraphicsBackendMtInterface},
+};
 use graphics_types::{
-    commands::AllCommands,
+    commands::{
+        AllCommands, CommandClear, CommandSwitchCanvasModeType, CommandUpdateBufferRegion,
+        CommandsMisc, CommandsRender,
+    },
+    rendering::ColorRgba,
     types::{
         GraphicsBackendMemory, GraphicsBackendMemoryAllocation, GraphicsMemoryAllocationMode,
         GraphicsMemoryAllocationType,
     },
 };
 use hiarc::Hiarc;
+use pool::mt_datatypes::PoolUnclearedVec;
+
+use crate::backends::types::BackendFrameStats;
+
+/// A RGBA framebuffer in CPU memory.
+#[derive(Debug, Hiarc, Default)]
+pub struct NullCanvas {
+    pub width: u32,
+    pub height: u32,
+    pub data: Vec<u8>,
+    /// The color the canvas is cleared with, when it's switched to.
+    clear_color: Option<ColorRgba>,
+}
+
+impl NullCanvas {
+    fn new(width: u32, height: u32, clear_color: Option<ColorRgba>) -> Self {
+        Self {
+            width,
+            height,
+            data: vec![0; width as usize * height as usize * 4],
+            clear_color,
+        }
+    }
+
+    fn resize(&mut self, width: u32, height: u32) {
+        self.width = width;
+        self.height = height;
+        self.data.resize(width as usize * height as usize * 4, 0);
+    }
+
+    /// Fills the canvas with the color,
+    /// or only the part inside the clip rect of the command.
+    fn clear(&mut self, cmd: &CommandClear) {
+        let color = [cmd.color.r, cmd.color.g, cmd.color.b, cmd.color.a]
+            .map(|c| (c.clamp(0.0, 1.0) * 255.0).round() as u8);
+        let (x0, y0, x1, y1) = match &cmd.clip {
+            Some(clip) => {
+                let clamp_x = |x: i64| x.clamp(0, self.width as i64) as usize;
+                let clamp_y = |y: i64| y.clamp(0, self.height as i64) as usize;
+                (
+                    clamp_x(clip.x as i64),
+                    clamp_y(clip.y as i64),
+                    clamp_x(clip.x as i64 + clip.w as i64),
+                    clamp_y(clip.y as i64 + clip.h as i64),
+                )
+            }
+            None => (0, 0, self.width as usize, self.height as usize),
+        };
+        let row_size = self.width as usize * 4;
+        if row_size == 0 {
+            return;
+        }
+        for row in self.data.chunks_exact_mut(row_size).take(y1).skip(y0) {
+            for pixel in row[x0 * 4..x1 * 4].chunks_exact_mut(4) {
+                pixel.copy_from_slice(&color);
+            }
+        }
+    }
+}
 
 #[derive(Debug, Hiarc)]
-pub struct NullBackend {}
+pub struct NullTexture {
+    pub width: usize,
+    pub height: usize,
+    pub depth: usize,
+    pub is_3d_tex: bool,
+    pub data: Vec<u8>,
+}
+
+/// A backend that does not render anything, e.g. for machines
+/// without a working vulkan driver, headless tooling or CI.
+///
+/// It keeps textures, buffers and shader storages in CPU memory
+/// and clears canvases into CPU framebuffers, so that frame fetchers
+/// get the cleared canvas. All draw commands are discarded.
+#[derive(Debug, Hiarc)]
+pub struct NullBackend {
+    onscreen: NullCanvas,
+    offscreens: HashMap<u128, NullCanvas>,
+    #[hiarc_skip_unsafe]
+    cur_canvas: FetchCanvasIndex,
+
+    textures: HashMap<u128, NullTexture>,
+    buffer_objects: HashMap<u128, Vec<u8>>,
+    shader_storages: HashMap<u128, Vec<u8>>,
+
+    #[hiarc_skip_unsafe]
+    frame_fetchers: FxLinkedHashMap<String, Arc<dyn BackendFrameFetcher>>,
+
+    cur_frame_stats: BackendFrameStats,
+    last_frame_stats: BackendFrameStats,
+}
 
 impl NullBackend {
+    pub fn new(window_width: u32, window_height: u32) -> Self {
+        Self {
+            onscreen: NullCanvas::new(window_width, window_height, None),
+            offscreens: Default::default(),
+            cur_canvas: FetchCanvasIndex::Onscreen,
+
+            textures: Default::default(),
+            buffer_objects: Default::default(),
+            shader_storages: Default::default(),
+
+            frame_fetchers: Default::default(),
+
+            cur_frame_stats: Default::default(),
+            last_frame_stats: Default::default(),
+        }
+    }
+
+    /// The stats of the last frame. Since draw commands are discarded,
+    /// only the updates are counted.
+    pub fn last_frame_stats(&self) -> BackendFrameStats {
+        self.last_frame_stats
+    }
+
     pub fn get_mt_backend() -> NullBackendMt {
         NullBackendMt {}
     }
+
+    fn mem_to_vec(
+        mem: GraphicsBackendMemory,
+    ) -> anyhow::Result<(Vec<u8>, GraphicsMemoryAllocationType)> {
+        let (alloc, ty) = mem.take();
+        match alloc {
+            GraphicsBackendMemoryAllocation::Vector(data) => Ok((data, ty)),
+            GraphicsBackendMemoryAllocation::Static(mem) => Ok((
+                mem.mem
+                    .as_deref()
+                    .ok_or_else(|| anyhow!("static memory was already taken"))?
+                    .to_vec(),
+                ty,
+            )),
+        }
+    }
+
+    fn update_regions(
+        data: &mut [u8],
+        update_data: &[u8],
+        update_regions: &[CommandUpdateBufferRegion],
+    ) -> anyhow::Result<()> {
+        for region in update_regions {
+            let src = update_data
+                .get(region.src_offset..region.src_offset + region.size)
+                .ok_or_else(|| anyhow!("update region is outside of the update data"))?;
+            data.get_mut(region.dst_offset..region.dst_offset + region.size)
+                .ok_or_else(|| anyhow!("update region is outside of the buffer"))?
+                .copy_from_slice(src);
+        }
+        Ok(())
+    }
+
+    fn cur_canvas_mut(&mut self) -> anyhow::Result<&mut NullCanvas> {
+        match self.cur_canvas {
+            FetchCanvasIndex::Onscreen => Ok(&mut self.onscreen),
+            FetchCanvasIndex::Offscreen(id) => self
+                .offscreens
+                .get_mut(&id)
+                .ok_or_else(|| anyhow!("offscreen canvas with that index does not exist")),
+        }
+    }
+
+    fn canvas(&self, index: FetchCanvasIndex) -> Result<&NullCanvas, FetchCanvasError> {
+        match index {
+            FetchCanvasIndex::Onscreen => Ok(&self.onscreen),
+            FetchCanvasIndex::Offscreen(id) => self
+                .offscreens
+                .get(&id)
+                .ok_or(FetchCanvasError::CanvasNotFound),
+        }
+    }
+
+    fn fetch_frames(&self) {
+        for fetcher in self.frame_fetchers.values() {
+            let canvas = match self.canvas(fetcher.current_fetch_index()) {
+                Ok(canvas) => canvas,
+                Err(err) => {
+                    fetcher.fetch_err(err);
+                    continue;
+                }
+            };
+            let orientation = fetcher.orientation();
+            let mut dest_data_buffer = PoolUnclearedVec::new_without_pool();
+            match orientation {
+                PresentedImageOrientation::BottomToTop if canvas.width > 0 => {
+                    for row in canvas.data.chunks_exact(canvas.width as usize * 4).rev() {
+                        dest_data_buffer.extend_from_slice(row);
+                    }
+                }
+                PresentedImageOrientation::TopToBottom | PresentedImageOrientation::BottomToTop => {
+                    dest_data_buffer.extend_from_slice(&canvas.data)
+                }
+            }
+            fetcher.next_frame(BackendPresentedImageDataRgba {
+                width: canvas.width,
+                height: canvas.height,
+                orientation,
+                dest_data_buffer,
+            });
+        }
+    }
+
+    /// Reads back the texture in `slot`, see
+    /// [`crate::backends::vulkan::vulkan::VulkanBackend::read_texture`].
+    pub fn read_texture(&self, slot: u128) -> anyhow::Result<(u32, u32, Vec<u8>)> {
+        let tex = self
+            .textures
+            .get(&slot)
+            .ok_or(anyhow!("texture with that index does not exist"))?;
+        if tex.is_3d_tex {
+            return Err(anyhow!("reading back 3d textures is not supported."));
+        }
+        Ok((tex.width as u32, tex.height as u32, tex.data.clone()))
+    }
+
+    /// Reads back `size` bytes at `offset` of the buffer object `buffer_index`, see
+    /// [`crate::backends::vulkan::vulkan::VulkanBackend::read_buffer_object`].
+    pub fn read_buffer_object(
+        &self,
+        buffer_index: u128,
+        offset: usize,
+        size: usize,
+    ) -> anyhow::Result<Vec<u8>> {
+        let data = self
+            .buffer_objects
+            .get(&buffer_index)
+            .ok_or(anyhow!("buffer object with that index does not exist"))?;
+        data.get(offset..offset + size)
+            .map(|data| data.to_vec())
+            .ok_or(anyhow!(
+                "reading {size} bytes at offset {offset} is outside of the buffer object."
+            ))
+    }
+
+    /// Reads back `size` bytes at `offset` of the shader storage `shader_storage_index`, see
+    /// [`crate::backends::vulkan::vulkan::VulkanBackend::read_shader_storage`].
+    pub fn read_shader_storage(
+        &self,
+        shader_storage_index: u128,
+        offset: usize,
+        size: usize,
+    ) -> anyhow::Result<Vec<u8>> {
+        let data = self
+            .shader_storages
+            .get(&shader_storage_index)
+            .ok_or(anyhow!("shader storage with that index does not exist"))?;
+        data.get(offset..offset + size)
+            .map(|data| data.to_vec())
+            .ok_or(anyhow!(
+                "reading {size} bytes at offset {offset} is outside of the shader storage."
+            ))
+    }
+
+    /// The null backend never generates mipmaps, so every existing
+    /// texture has exactly one mip level.
+    pub fn texture_mip_map_count(&self, slot: u128) -> anyhow::Result<u32> {
+        self.textures
+            .get(&slot)
+            .map(|_| 1)
+            .ok_or(anyhow!("texture with that index does not exist"))
+    }
+
+    fn run_misc_command(&mut self, cmd: CommandsMisc) -> anyhow::Result<()> {
+        match cmd {
+            CommandsMisc::TextureCreate(cmd) => {
+                let (data, ty) = Self::mem_to_vec(cmd.data)?;
+                let (width, height, depth, is_3d_tex) = match ty {
+                    GraphicsMemoryAllocationType::TextureRgbaU8 { width, height, .. }
+                    | GraphicsMemoryAllocationType::TextureCompressed { width, height, .. } => {
+                        (width.get(), height.get(), 1, false)
+                    }
+                    GraphicsMemoryAllocationType::TextureRgbaU82dArray {
+                        width,
+                        height,
+                        depth,
+                        ..
+                    } => (width.get(), height.get(), depth.get(), true),
+                    GraphicsMemoryAllocationType::VertexBuffer { .. }
+                    | GraphicsMemoryAllocationType::ShaderStorage { .. } => {
+                        return Err(anyhow!("memory was not allocated for a texture"));
+                    }
+                };
+                self.textures.insert(
+                    cmd.texture_index,
+                    NullTexture {
+                        width,
+                        height,
+                        depth,
+                        is_3d_tex,
+                        data,
+                    },
+                );
+            }
+            CommandsMisc::TextureDestroy(cmd) => {
+                self.textures.remove(&cmd.texture_index);
+            }
+            CommandsMisc::TextureUpdate(cmd) => {
+                self.cur_frame_stats.texture_updates += 1;
+                let tex = self
+                    .textures
+                    .get_mut(&cmd.texture_index)
+                    .ok_or(anyhow!("texture with that index does not exist"))?;
+                let (x, y) = (cmd.x as usize, cmd.y as usize);
+                let (width, height) = (cmd.width as usize, cmd.height as usize);
+                if tex.is_3d_tex
+                    || cmd.x < 0
+                    || cmd.y < 0
+                    || x + width > tex.width
+                    || y + height > tex.height
+                    || cmd.data.len() < width * height * 4
+                {
+                    return Err(anyhow!("texture update is outside of the texture"));
+                }
+                for (row_index, row) in cmd.data.chunks_exact(width * 4).take(height).enumerate() {
+                    let offset = ((y + row_index) * tex.width + x) * 4;
+                    tex.data[offset..offset + width * 4].copy_from_slice(row);
+                }
+            }
+            CommandsMisc::CreateBufferObject(cmd) => {
+                let (data, _) = Self::mem_to_vec(cmd.upload_data)?;
+                self.buffer_objects.insert(cmd.buffer_index, data);
+            }
+            CommandsMisc::RecreateBufferObject(cmd) => {
+                let (data, _) = Self::mem_to_vec(cmd.upload_data)?;
+                self.buffer_objects.insert(cmd.buffer_index, data);
+            }
+            CommandsMisc::UpdateBufferObject(cmd) => {
+                self.cur_frame_stats.buffer_updates += 1;
+                let data = self
+                    .buffer_objects
+                    .get_mut(&cmd.buffer_index)
+                    .ok_or(anyhow!("buffer object with that index does not exist"))?;
+                Self::update_regions(data, &cmd.update_data, &cmd.update_regions)?;
+            }
+            CommandsMisc::DeleteBufferObject(cmd) => {
+                self.buffer_objects.remove(&cmd.buffer_index);
+            }
+            CommandsMisc::CreateShaderStorage(cmd) => {
+                let (data, _) = Self::mem_to_vec(cmd.upload_data)?;
+                self.shader_storages.insert(cmd.shader_storage_index, data);
+            }
+            CommandsMisc::UpdateShaderStorage(cmd) => {
+                self.cur_frame_stats.buffer_updates += 1;
+                let data = self
+                    .shader_storages
+                    .get_mut(&cmd.shader_storage_index)
+                    .ok_or(anyhow!("shader storage with that index does not exist"))?;
+                Self::update_regions(data, &cmd.update_data, &cmd.update_regions)?;
+            }
+            CommandsMisc::DeleteShaderStorage(cmd) => {
+                self.shader_storages.remove(&cmd.shader_storage_index);
+            }
+            CommandsMisc::OffscreenCanvasCreate(cmd) => {
+                self.offscreens.insert(
+                    cmd.offscreen_index,
+                    NullCanvas::new(cmd.width, cmd.height, cmd.clear_color),
+                );
+            }
+            CommandsMisc::OffscreenCanvasDestroy(cmd) => {
+                self.offscreens.remove(&cmd.offscreen_index);
+                if self.cur_canvas == FetchCanvasIndex::Offscreen(cmd.offscreen_index) {
+                    self.cur_canvas = FetchCanvasIndex::Onscreen;
+                }
+            }
+            CommandsMisc::OffscreenCanvasResize(cmd) => {
+                if let Some(canvas) = self.offscreens.get_mut(&cmd.offscreen_index) {
+                    canvas.resize(cmd.width, cmd.height);
+                }
+            }
+            CommandsMisc::SwitchCanvas(cmd) => {
+                self.cur_canvas = match cmd.mode {
+                    CommandSwitchCanvasModeType::Onscreen => FetchCanvasIndex::Onscreen,
+                    CommandSwitchCanvasModeType::Offscreen { id } => {
+                        FetchCanvasIndex::Offscreen(id)
+                    }
+                };
+                let canvas = self.cur_canvas_mut()?;
+                if let Some(color) = canvas.clear_color {
+                    canvas.clear(&CommandClear {
+                        color,
+                        force_clear: true,
+                        clip: None,
+                    });
+                }
+            }
+            CommandsMisc::UpdateViewport(cmd) => {
+                if cmd.by_resize {
+                    self.onscreen.resize(cmd.width, cmd.height);
+                }
+            }
+            CommandsMisc::Swap => {
+                self.fetch_frames();
+                self.cur_canvas = FetchCanvasIndex::Onscreen;
+                self.last_frame_stats = std::mem::take(&mut self.cur_frame_stats);
+            }
+            CommandsMisc::OffscreenCanvasSkipFetchingOnce(_)
+            | CommandsMisc::IndicesForQuadsRequiredNotify(_)
+            | CommandsMisc::NextSwitchPass
+            | CommandsMisc::ConsumeMultiSamplingTargets
+            | CommandsMisc::Multisampling(_)
+            | CommandsMisc::VSync(_)
+            | CommandsMisc::SetPolygonMode(_)
+            | CommandsMisc::DumpFrameGraph => {
+                // nothing to do
+            }
+        }
+        Ok(())
+    }
 }
 
 impl DriverBackendInterface for NullBackend {
     fn attach_frame_fetcher(
         &mut self,
-        _name: String,
-        _fetcher: std::sync::Arc<
-            dyn graphics_backend_traits::frame_fetcher_plugin::BackendFrameFetcher,
-        >,
-    ) {
-        // do nothing
+        name: String,
+        fetcher: Arc<dyn BackendFrameFetcher>,
+    ) -> Result<(), FrameFetcherAttachError> {
+        if self.frame_fetchers.contains_key(&name) {
+            return Err(FrameFetcherAttachError::AlreadyAttached);
+        }
+        self.frame_fetchers.insert(name, fetcher);
+        Ok(())
     }
 
-    fn detach_frame_fetcher(&mut self, _name: String) {
-        // do nothing
+    fn detach_frame_fetcher(&mut self, name: String) {
+        self.frame_fetchers.remove(&name);
     }
 
-    fn run_command(&mut self, _cmd: AllCommands) -> anyhow::Result<()> {
-        // nothing to do
-        Ok(())
+    fn run_command(&mut self, cmd: AllCommands) -> anyhow::Result<()> {
+        match cmd {
+            AllCommands::Render(CommandsRender::Clear(cmd)) => {
+                self.cur_canvas_mut()?.clear(&cmd);
+                Ok(())
+            }
+            AllCommands::Render(_) => {
+                // draw commands are discarded
+                Ok(())
+            }
+            AllCommands::Misc(cmd) => self.run_misc_command(cmd),
+        }
     }
 
     fn start_commands(&mut self, _command_count: usize) {
@@ -63,6 +488,15 @@ pub fn mem_alloc_lazy(alloc_type: GraphicsMemoryAllocationType) -> GraphicsBacke
             width.get() * height.get() * depth.get() * 4,
             Default::default(),
         ),
+        GraphicsMemoryAllocationType::TextureCompressed {
+            width,
+            height,
+            format,
+            ..
+        } => mem.resize(
+            format.image_size(width.get(), height.get()),
+            Default::default(),
+        ),
         GraphicsMemoryAllocationType::VertexBuffer { required_size } => {
             mem.resize(required_size.get(), Default::default())
         }
@@ -1,3 +1,35 @@
 use std::{collections::HashMap, path::PathBuf, sync::Arc};
 
+use hiarc::Hiarc;
+
 pub type BackendWriteFiles = Arc<parking_lot::Mutex<HashMap<PathBuf, Vec<u8>>>>;
+
+/// What the backend did during a single frame,
+/// e.g. to find out why a map renders slowly.
+#[derive(Debug, Hiarc, Default, Clone, Copy, PartialEq, Eq)]
+pub struct BackendFrameStats {
+    /// The sum of the draw calls the render commands estimated for themselves.
+    pub estimated_draw_calls: usize,
+    /// The groups of render commands that were recorded by the render threads.
+    pub command_groups: usize,
+    /// The render passes of all canvases that recorded render commands.
+    pub render_passes: usize,
+    pub texture_updates: usize,
+    /// Updates of buffer objects and shader storages.
+    pub buffer_updates: usize,
+}
+
+/// How often the backend waited for the gpu since it was created,
+/// e.g. to find out why frames stall.
+#[derive(Debug, Hiarc, Default, Clone, Copy, PartialEq, Eq)]
+pub struct BackendWaitStats {
+    /// Waits until the gpu finished all frames that used staging memory,
+    /// because the staging memory ran out.
+    pub staging_memory_waits: u64,
+    /// Submits of the copies of the canvases that frame fetchers requested.
+    pub frame_fetch_submits: u64,
+    /// Waits for these copies to finish.
+    pub frame_fetch_fence_waits: u64,
+    /// Waits for older frames because `max_frames_in_flight` was reached.
+    pub frames_in_flight_waits: u64,
+}
@@ -182,6 +182,29 @@ pub fn image_barrier(
         barrier.subresource_range.aspect_mask = vk::ImageAspectFlags::STENCIL;
 
         needs_dependency = true;
+    } else if old_layout == vk::ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL
+        && new_layout == vk::ImageLayout::TRANSFER_SRC_OPTIMAL
+    {
+        barrier.src_access_mask = vk::AccessFlags::DEPTH_STENCIL_ATTACHMENT_WRITE;
+        barrier.dst_access_mask = vk::AccessFlags::TRANSFER_READ;
+
+        source_stage = vk::PipelineStageFlags::LATE_FRAGMENT_TESTS;
+        destination_stage = vk::PipelineStageFlags::TRANSFER;
+
+        barrier.subresource_range.aspect_mask =
+            vk::ImageAspectFlags::DEPTH | vk::ImageAspectFlags::STENCIL;
+    } else if old_layout == vk::ImageLayout::TRANSFER_SRC_OPTIMAL
+        && new_layout == vk::ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL
+    {
+        barrier.src_access_mask = vk::AccessFlags::TRANSFER_READ;
+        barrier.dst_access_mask = vk::AccessFlags::DEPTH_STENCIL_ATTACHMENT_READ
+            | vk::AccessFlags::DEPTH_STENCIL_ATTACHMENT_WRITE;
+
+        source_stage = vk::PipelineStageFlags::TRANSFER;
+        destination_stage = vk::PipelineStageFlags::EARLY_FRAGMENT_TESTS;
+
+        barrier.subresource_range.aspect_mask =
+            vk::ImageAspectFlags::DEPTH | vk::ImageAspectFlags::STENCIL;
     } else {
         panic!("unsupported layout transition! old: {old_layout:?} -> new: {new_layout:?}");
     }
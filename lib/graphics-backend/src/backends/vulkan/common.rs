@@ -1,4 +1,5 @@
 use ash::vk;
+use graphics_types::types::TextureCompressedFormat;
 
 pub fn image_mip_level_count_ex(width: usize, height: usize, depth: usize) -> usize {
     (((std::cmp::max(width, std::cmp::max(height, depth)) as f32).log2()).floor() + 1.0) as usize
@@ -11,3 +12,29 @@ pub fn image_mip_level_count(img_extent: vk::Extent3D) -> usize {
         img_extent.depth as usize,
     )
 }
+
+/// Caps `mip_level_count` to `max_mip_levels`, where 0 means no cap.
+pub fn capped_mip_level_count(mip_level_count: usize, max_mip_levels: u32) -> usize {
+    if max_mip_levels == 0 {
+        mip_level_count
+    } else {
+        mip_level_count.min(max_mip_levels as usize)
+    }
+}
+
+pub fn compressed_texture_format(format: TextureCompressedFormat) -> vk::Format {
+    match format {
+        TextureCompressedFormat::Bc7 => vk::Format::BC7_UNORM_BLOCK,
+        TextureCompressedFormat::Astc4x4 => vk::Format::ASTC_4X4_UNORM_BLOCK,
+    }
+}
+
+/// The inverse of [`compressed_texture_format`],
+/// `None` for formats that are not block compressed.
+pub fn texture_compressed_format(format: vk::Format) -> Option<TextureCompressedFormat> {
+    match format {
+        vk::Format::BC7_UNORM_BLOCK => Some(TextureCompressedFormat::Bc7),
+        vk::Format::ASTC_4X4_UNORM_BLOCK => Some(TextureCompressedFormat::Astc4x4),
+        _ => None,
+    }
+}
@@ -348,6 +348,78 @@ impl<'a> FrameCollector<'a> {
         Ok(())
     }
 
+    /// Brings the image of a persistent canvas into the layout that its render pass loads from.
+    /// The first time the image is cleared, afterwards it was sampled in the previous frame.
+    fn prepare_persistent_canvas(
+        current_frame_resources: &mut FrameResources,
+        render: &CanvasSetup,
+        props: &VulkanBackendProps,
+        cur_image_index: u32,
+        main_command_buffer: vk::CommandBuffer,
+        clear_color: &[f32; 4],
+    ) -> anyhow::Result<()> {
+        let device = &props.ash_vk.vk_device;
+        let img = &render.native.swap_chain_images[cur_image_index as usize];
+        if img.layout.load(std::sync::atomic::Ordering::SeqCst) == ImageLayout::Undefined {
+            image_barrier(
+                current_frame_resources,
+                device,
+                main_command_buffer,
+                img,
+                0,
+                1,
+                0,
+                1,
+                vk::ImageLayout::UNDEFINED,
+                vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+            )
+            .map_err(|err| anyhow!("could not transition persistent canvas for clearing: {err}"))?;
+            let mut range = vk::ImageSubresourceRange::default();
+            range.aspect_mask = vk::ImageAspectFlags::COLOR;
+            range.level_count = 1;
+            range.layer_count = 1;
+            unsafe {
+                device.device.cmd_clear_color_image(
+                    main_command_buffer,
+                    img.img(current_frame_resources),
+                    vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                    &vk::ClearColorValue {
+                        float32: *clear_color,
+                    },
+                    &[range],
+                );
+            }
+            image_barrier(
+                current_frame_resources,
+                device,
+                main_command_buffer,
+                img,
+                0,
+                1,
+                0,
+                1,
+                vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                device.final_layout(),
+            )
+            .map_err(|err| anyhow!("could not transition cleared persistent canvas: {err}"))?;
+        } else {
+            image_barrier(
+                current_frame_resources,
+                device,
+                main_command_buffer,
+                img,
+                0,
+                1,
+                0,
+                1,
+                vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+                device.final_layout(),
+            )
+            .map_err(|err| anyhow!("could not transition persistent canvas for loading: {err}"))?;
+        }
+        Ok(())
+    }
+
     fn collect_frame_of_canvas(
         frame: &Frame,
         props: &VulkanBackendProps,
@@ -474,6 +546,20 @@ impl<'a> FrameCollector<'a> {
         // can use also have offscreen canvases.
         for (_, render_canvas) in frame.render.offscreen_canvases.iter().rev() {
             let render_setup = &render_canvas.setup;
+            let clear_color = render_setup
+                .clear_color
+                .as_ref()
+                .unwrap_or(&self.backend.clear_color);
+            if render_setup.persistent {
+                Self::prepare_persistent_canvas(
+                    &mut self.backend.current_frame_resources,
+                    render_setup,
+                    &self.backend.props,
+                    self.backend.render.cur_image_index,
+                    main_command_buffer,
+                    clear_color,
+                )?;
+            }
             Self::collect_frame_of_canvas(
                 &frame,
                 &self.backend.props,
@@ -482,7 +568,7 @@ impl<'a> FrameCollector<'a> {
                 &render_canvas.canvas,
                 main_command_buffer,
                 self.backend.render.cur_image_index,
-                &self.backend.clear_color,
+                clear_color,
             )?;
             image_barrier(
                 &mut self.backend.current_frame_resources,
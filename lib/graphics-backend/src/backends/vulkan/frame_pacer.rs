@@ -0,0 +1,54 @@
+use std::time::{Duration, Instant};
+
+use hiarc::Hiarc;
+
+/// Sleeping is imprecise, so the last part of a frame is spun instead.
+const SPIN_THRESHOLD: Duration = Duration::from_millis(1);
+
+/// Limits how often frames are started, independent of the present mode.
+#[derive(Debug, Hiarc)]
+pub struct FramePacer {
+    frame_time: Option<Duration>,
+    next_frame: Option<Instant>,
+}
+
+impl FramePacer {
+    /// A `max_fps` of 0 does not limit the frame rate.
+    pub fn new(max_fps: u32) -> Self {
+        Self {
+            frame_time: (max_fps > 0).then(|| Duration::from_secs(1) / max_fps),
+            next_frame: None,
+        }
+    }
+
+    /// Waits until the next frame may start.
+    ///
+    /// The deadline advances by exactly one frame time, so timer inaccuracies
+    /// don't add up. If the caller fell behind by more than a frame,
+    /// pacing starts over from now instead of catching up with a burst of frames.
+    pub fn wait(&mut self) {
+        let Some(frame_time) = self.frame_time else {
+            return;
+        };
+        let now = Instant::now();
+        let deadline = match self.next_frame {
+            Some(deadline) if deadline + frame_time >= now => deadline,
+            _ => now,
+        };
+
+        loop {
+            let now = Instant::now();
+            if now >= deadline {
+                break;
+            }
+            let left = deadline - now;
+            if left > SPIN_THRESHOLD {
+                std::thread::sleep(left - SPIN_THRESHOLD);
+            } else {
+                std::hint::spin_loop();
+            }
+        }
+
+        self.next_frame = Some(deadline + frame_time);
+    }
+}
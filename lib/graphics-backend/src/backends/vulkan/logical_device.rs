@@ -1,7 +1,10 @@
 use std::{
     ffi::{CStr, CString},
     ops::Deref,
-    sync::{atomic::AtomicU64, Arc},
+    sync::{
+        atomic::{AtomicBool, AtomicU64},
+        Arc,
+    },
 };
 
 use anyhow::anyhow;
@@ -38,6 +41,9 @@ pub struct LogicalDevice {
     pub staging_memory_usage: Arc<AtomicU64>,
 
     pub is_headless: bool,
+    /// Whether new pipelines only rasterize the edges of polygons.
+    /// Requires the `fill_mode_non_solid` feature.
+    pub line_polygon_mode: AtomicBool,
 
     #[hiarc_skip_unsafe]
     pub dbg: Arc<AtomicGfxDebugModes>,
@@ -77,6 +83,7 @@ impl LogicalDevice {
             phy_device: phy_gpu,
 
             is_headless,
+            line_polygon_mode: AtomicBool::new(false),
 
             texture_memory_usage,
             buffer_memory_usage,
@@ -121,8 +128,14 @@ impl LogicalDevice {
         let mut timeline_semaphore_features =
             vk::PhysicalDeviceTimelineSemaphoreFeatures::default().timeline_semaphore(true);
 
-        let mut vk_create_info =
-            vk::DeviceCreateInfo::default().queue_create_infos(&vk_queue_create_info);
+        let config = phy_gpu.config.read().unwrap().clone();
+        let features = vk::PhysicalDeviceFeatures::default()
+            .sampler_anisotropy(config.sampler_anisotropy)
+            .fill_mode_non_solid(config.fill_mode_non_solid);
+
+        let mut vk_create_info = vk::DeviceCreateInfo::default()
+            .queue_create_infos(&vk_queue_create_info)
+            .enabled_features(&features);
 
         if is_headless {
             vk_create_info = vk_create_info.push_next(&mut timeline_semaphore_features);
@@ -13,6 +13,7 @@ pub mod descriptor_set;
 pub mod fence;
 pub mod frame;
 pub mod frame_collection;
+pub mod frame_pacer;
 pub mod frame_resources;
 pub mod framebuffer;
 pub mod image;
@@ -51,7 +52,85 @@ pub mod vulkan_mem;
 pub mod vulkan_types;
 pub mod vulkan_uniform;
 
-pub struct Options<'a> {
-    pub dbg: &'a ConfigDebug,
-    pub gl: &'a ConfigBackend,
+pub struct Options {
+    pub dbg: ConfigDebug,
+    pub gl: ConfigBackend,
+}
+
+/// The highest sample count vulkan supports.
+const MAX_MSAA_SAMPLES: u32 = 64;
+
+/// Builds [`Options`] from the configs, validating them before the
+/// backend is initialized instead of failing deep inside the init.
+#[derive(Debug, Clone)]
+pub struct OptionsBuilder {
+    dbg: ConfigDebug,
+    gl: ConfigBackend,
+}
+
+impl OptionsBuilder {
+    pub fn new(dbg: &ConfigDebug, gl: &ConfigBackend) -> Self {
+        Self {
+            dbg: *dbg,
+            gl: gl.clone(),
+        }
+    }
+
+    /// 0 picks the thread count automatically.
+    pub fn thread_count(mut self, thread_count: u32) -> Self {
+        self.gl.thread_count = thread_count;
+        self
+    }
+
+    /// 0 or 1 disables multi sampling.
+    pub fn msaa_samples(mut self, msaa_samples: u32) -> Self {
+        self.gl.msaa_samples = msaa_samples;
+        self
+    }
+
+    pub fn full_pipeline_creation(mut self, full_pipeline_creation: bool) -> Self {
+        self.gl.full_pipeline_creation = full_pipeline_creation;
+        self
+    }
+
+    /// 0 does not limit the frame rate.
+    pub fn max_fps(mut self, max_fps: u32) -> Self {
+        self.gl.max_fps = max_fps;
+        self
+    }
+
+    pub fn bench(mut self, bench: bool) -> Self {
+        self.dbg.bench = bench;
+        self
+    }
+
+    /// Thread counts above the available parallelism are clamped to it,
+    /// a single sample is the same as no multi sampling.
+    ///
+    /// Sample counts that are no power of two or exceed what vulkan supports
+    /// are rejected.
+    pub fn build(mut self) -> anyhow::Result<Options> {
+        let available_threads = std::thread::available_parallelism()
+            .map(|threads| threads.get())
+            .unwrap_or(1) as u32;
+        self.gl.thread_count = self.gl.thread_count.min(available_threads);
+
+        let msaa_samples = self.gl.msaa_samples;
+        anyhow::ensure!(
+            msaa_samples <= 1 || msaa_samples.is_power_of_two(),
+            "msaa samples must be a power of two, but were {msaa_samples}."
+        );
+        anyhow::ensure!(
+            msaa_samples <= MAX_MSAA_SAMPLES,
+            "msaa samples must be at most {MAX_MSAA_SAMPLES}, but were {msaa_samples}."
+        );
+        if msaa_samples == 1 {
+            self.gl.msaa_samples = 0;
+        }
+
+        Ok(Options {
+            dbg: self.dbg,
+            gl: self.gl,
+        })
+    }
 }
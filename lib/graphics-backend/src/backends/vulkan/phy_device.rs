@@ -14,6 +14,81 @@ use super::{
     Options,
 };
 
+/// Identifies a device independent of the machine and the order
+/// in which the driver lists the devices, e.g. to label benchmark runs.
+#[derive(Debug, Hiarc, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct DeviceFingerprint {
+    pub vendor_id: u32,
+    pub device_id: u32,
+    pub driver_version: u32,
+    pub api_version: u32,
+}
+
+impl DeviceFingerprint {
+    pub fn new(device_prop: &vk::PhysicalDeviceProperties) -> Self {
+        Self {
+            vendor_id: device_prop.vendor_id,
+            device_id: device_prop.device_id,
+            driver_version: device_prop.driver_version,
+            api_version: device_prop.api_version,
+        }
+    }
+}
+
+impl std::fmt::Display for DeviceFingerprint {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{:04x}:{:04x} vulkan {}.{}.{} driver {}",
+            self.vendor_id,
+            self.device_id,
+            vk::api_version_major(self.api_version),
+            vk::api_version_minor(self.api_version),
+            vk::api_version_patch(self.api_version),
+            PhyDevice::get_driver_verson(self.driver_version, self.vendor_id)
+        )
+    }
+}
+
+/// A device that supports the required vulkan version.
+#[derive(Debug, Clone)]
+pub struct DeviceCandidate {
+    pub name: String,
+    pub ty: GpuType,
+    pub fingerprint: DeviceFingerprint,
+}
+
+/// Selects the device named `gpu`, or the device with the best type if `gpu` is `auto`.
+/// Devices of the same type are ordered by their fingerprint,
+/// so the selection does not depend on the order the driver lists them in.
+pub fn select_device(candidates: &[DeviceCandidate], gpu: &str) -> Option<usize> {
+    if gpu != "auto" {
+        return candidates
+            .iter()
+            .position(|candidate| candidate.name == gpu);
+    }
+    candidates
+        .iter()
+        .enumerate()
+        .filter(|(_, candidate)| candidate.ty < GpuType::Invalid)
+        .min_by(|(_, c1), (_, c2)| {
+            c1.ty
+                .partial_cmp(&c2.ty)
+                .unwrap_or(std::cmp::Ordering::Equal)
+                .then_with(|| c1.fingerprint.cmp(&c2.fingerprint))
+        })
+        .map(|(index, _)| index)
+}
+
+/// The size of the largest device local memory heap.
+pub fn vram_estimate(mem_props: &vk::PhysicalDeviceMemoryProperties) -> Option<u64> {
+    mem_props.memory_heaps[..mem_props.memory_heap_count as usize]
+        .iter()
+        .filter(|heap| heap.flags.contains(vk::MemoryHeapFlags::DEVICE_LOCAL))
+        .map(|heap| heap.size)
+        .max()
+}
+
 #[derive(Debug, Hiarc)]
 pub struct PhyDevice {
     pub gpu_list: Arc<Gpus>,
@@ -22,11 +97,14 @@ pub struct PhyDevice {
     pub renderer_name: String,
     pub vendor_name: String,
     pub version_name: String,
+    pub fingerprint: DeviceFingerprint,
     #[hiarc_skip_unsafe]
     pub cur_device: vk::PhysicalDevice,
     #[hiarc_skip_unsafe]
     pub raw_device_props: vk::PhysicalDeviceProperties,
     pub queue_node_index: u32,
+    /// See [`vram_estimate`]
+    pub vram_estimate: Option<u64>,
 
     // take an instance of the vk instance. it must outlive the device
     pub instance: Arc<Instance>,
@@ -99,6 +177,29 @@ impl PhyDevice {
         {
             self.config.write().unwrap().linear_rgba_image_blitting = true;
         }
+
+        // check which compressed formats can be sampled
+        let supports_sampling = |format: vk::Format| {
+            let format_properties = unsafe {
+                self.instance
+                    .vk_instance
+                    .get_physical_device_format_properties(self.cur_device, format)
+            };
+            !(format_properties.optimal_tiling_features & vk::FormatFeatureFlags::SAMPLED_IMAGE)
+                .is_empty()
+        };
+        let bc7_textures = supports_sampling(vk::Format::BC7_UNORM_BLOCK);
+        let astc_4x4_textures = supports_sampling(vk::Format::ASTC_4X4_UNORM_BLOCK);
+        let features = unsafe {
+            self.instance
+                .vk_instance
+                .get_physical_device_features(self.cur_device)
+        };
+        let mut config = self.config.write().unwrap();
+        config.bc7_textures = bc7_textures;
+        config.astc_4x4_textures = astc_4x4_textures;
+        config.sampler_anisotropy = features.sampler_anisotropy == vk::TRUE;
+        config.fill_mode_non_solid = features.fill_mode_non_solid == vk::TRUE;
     }
 
     pub fn update_surface_texture_capabilities(&self, surface_format: vk::Format) {
@@ -124,87 +225,67 @@ impl PhyDevice {
     ) -> anyhow::Result<Arc<Self>> {
         let device_list = unsafe { instance.vk_instance.enumerate_physical_devices() }?;
 
-        let mut gpu_list: Vec<Gpu> = Default::default();
-
-        let mut device_prop_list = Vec::<vk::PhysicalDeviceProperties>::new();
-        device_prop_list.resize(device_list.len(), Default::default());
-        gpu_list.reserve(device_list.len());
-
-        let mut found_device_index: usize = 0;
-        let mut found_gpu_type = GpuType::Invalid;
-
-        let mut auto_gpu_type = GpuType::Invalid;
-        let mut auto_gpu = None;
-
-        let is_auto_gpu: bool = options.gl.gpu == "auto";
-
-        let vk_backend_major: usize = 1;
-        let vk_backend_minor: usize = if is_headless { 2 } else { 1 };
-
-        for (index, cur_device) in device_list.iter().enumerate() {
-            device_prop_list[index] = unsafe {
+        let device_prop_list: Vec<vk::PhysicalDeviceProperties> = device_list
+            .iter()
+            .map(|cur_device| unsafe {
                 instance
                     .vk_instance
                     .get_physical_device_properties(*cur_device)
-            };
-
-            let device_prop = &mut device_prop_list[index];
-
-            let gpu_type = Self::vk_gputype_to_graphics_gputype(device_prop.device_type);
-
-            let dev_api_major: i32 = vk::api_version_major(device_prop.api_version) as i32;
-            let dev_api_minor: i32 = vk::api_version_minor(device_prop.api_version) as i32;
-
-            if dev_api_major > vk_backend_major as i32
-                || (dev_api_major == vk_backend_major as i32
-                    && dev_api_minor >= vk_backend_minor as i32)
-            {
-                gpu_list.push(Gpu {
-                    name: unsafe {
-                        CStr::from_ptr(device_prop.device_name.as_ptr())
-                            .to_str()
-                            .unwrap()
-                            .to_string()
-                    },
-                    ty: gpu_type,
-                });
-
-                if gpu_type < auto_gpu_type {
-                    auto_gpu = Some(Gpu {
+            })
+            .collect();
+
+        let vk_backend_major: u32 = 1;
+        let vk_backend_minor: u32 = if is_headless { 2 } else { 1 };
+
+        // (index in the device list, candidate)
+        let (candidate_indices, candidates): (Vec<usize>, Vec<DeviceCandidate>) = device_prop_list
+            .iter()
+            .enumerate()
+            .filter(|(_, device_prop)| {
+                let dev_api_major = vk::api_version_major(device_prop.api_version);
+                let dev_api_minor = vk::api_version_minor(device_prop.api_version);
+
+                dev_api_major > vk_backend_major
+                    || (dev_api_major == vk_backend_major && dev_api_minor >= vk_backend_minor)
+            })
+            .map(|(index, device_prop)| {
+                (
+                    index,
+                    DeviceCandidate {
                         name: unsafe {
                             CStr::from_ptr(device_prop.device_name.as_ptr())
                                 .to_str()
                                 .unwrap()
                                 .to_string()
                         },
-                        ty: gpu_type,
-                    });
-
-                    auto_gpu_type = gpu_type;
-                }
-
-                if ((is_auto_gpu && gpu_type < found_gpu_type)
-                    || unsafe {
-                        CStr::from_ptr(device_prop.device_name.as_ptr())
-                            .to_str()
-                            .unwrap()
-                            == options.gl.gpu
-                    })
-                    && (dev_api_major > vk_backend_major as i32
-                        || (dev_api_major == vk_backend_major as i32
-                            && dev_api_minor >= vk_backend_minor as i32))
-                {
-                    found_device_index = index;
-                    found_gpu_type = gpu_type;
-                }
-            }
-        }
-
-        if gpu_list.is_empty() || auto_gpu.is_none() {
+                        ty: Self::vk_gputype_to_graphics_gputype(device_prop.device_type),
+                        fingerprint: DeviceFingerprint::new(device_prop),
+                    },
+                )
+            })
+            .unzip();
+
+        let Some(auto_gpu) = select_device(&candidates, "auto").map(|index| Gpu {
+            name: candidates[index].name.clone(),
+            ty: candidates[index].ty,
+        }) else {
             return Err(anyhow!("No devices with required vulkan version found."));
-        }
+        };
+
+        let (found_device_index, found_gpu_type) = select_device(&candidates, &options.gl.gpu)
+            .map(|index| (candidate_indices[index], candidates[index].ty))
+            .unwrap_or((0, GpuType::Invalid));
+
+        let gpu_list: Vec<Gpu> = candidates
+            .into_iter()
+            .map(|candidate| Gpu {
+                name: candidate.name,
+                ty: candidate.ty,
+            })
+            .collect();
 
         let device_prop = &device_prop_list[found_device_index];
+        let fingerprint = DeviceFingerprint::new(device_prop);
 
         let dev_api_major: i32 = vk::api_version_major(device_prop.api_version) as i32;
         let dev_api_minor: i32 = vk::api_version_minor(device_prop.api_version) as i32;
@@ -302,12 +383,19 @@ impl PhyDevice {
             ));
         }
 
+        let mem_props = unsafe {
+            instance
+                .vk_instance
+                .get_physical_device_memory_properties(cur_device)
+        };
+        let vram_estimate = vram_estimate(&mem_props);
+
         let res = Self {
             instance,
 
             gpu_list: Arc::new(Gpus {
                 gpus: gpu_list,
-                auto: auto_gpu.unwrap(),
+                auto: auto_gpu,
                 cur: CurGpu {
                     name: renderer_name.clone(),
                     msaa_sampling_count: limits.max_multi_sample.as_raw(),
@@ -320,13 +408,19 @@ impl PhyDevice {
                 optimal_swap_chain_image_blitting: Default::default(),
                 optimal_rgba_image_blitting: Default::default(),
                 linear_rgba_image_blitting: Default::default(),
+                bc7_textures: Default::default(),
+                astc_4x4_textures: Default::default(),
+                sampler_anisotropy: Default::default(),
+                fill_mode_non_solid: Default::default(),
             }),
             renderer_name,
             vendor_name,
             version_name,
+            fingerprint,
             cur_device,
             raw_device_props: *device_prop,
             queue_node_index,
+            vram_estimate,
         };
         res.update_texture_capabilities();
 
@@ -158,7 +158,14 @@ impl<'a> PipelineManager<'a> {
 
         rasterizer.depth_clamp_enable = vk::FALSE;
         rasterizer.rasterizer_discard_enable = vk::FALSE;
-        rasterizer.polygon_mode = vk::PolygonMode::FILL;
+        rasterizer.polygon_mode = if device
+            .line_polygon_mode
+            .load(std::sync::atomic::Ordering::Relaxed)
+        {
+            vk::PolygonMode::LINE
+        } else {
+            vk::PolygonMode::FILL
+        };
         rasterizer.line_width = 1.0;
         rasterizer.cull_mode = vk::CullModeFlags::NONE;
         rasterizer.front_face = vk::FrontFace::CLOCKWISE;
@@ -11,7 +11,7 @@ use graphics_types::{
         CommandsRenderQuadContainer, CommandsRenderStream, PrimType, RenderSpriteInfo,
         GRAPHICS_DEFAULT_UNIFORM_SIZE, GRAPHICS_MAX_UNIFORM_RENDER_COUNT,
     },
-    rendering::{ColorRgba, State, StateTexture, WrapType},
+    rendering::{ColorRgba, State, StateTexture, TextureFilter, WrapType},
 };
 use math::math::vector::{vec2, vec4};
 
@@ -31,11 +31,12 @@ use super::{
 };
 
 pub fn get_address_mode_index(state: &State) -> usize {
-    if state.wrap_mode == WrapType::Repeat {
-        SupportedAddressModes::Repeat as usize
-    } else {
-        SupportedAddressModes::ClampEdges as usize
-    }
+    (match (state.wrap_mode, state.texture_filter) {
+        (WrapType::Repeat, TextureFilter::Linear) => SupportedAddressModes::Repeat,
+        (WrapType::Clamp, TextureFilter::Linear) => SupportedAddressModes::ClampEdges,
+        (WrapType::Repeat, TextureFilter::Nearest) => SupportedAddressModes::RepeatNearest,
+        (WrapType::Clamp, TextureFilter::Nearest) => SupportedAddressModes::ClampEdgesNearest,
+    }) as usize
 }
 
 fn render_blur(
@@ -192,6 +193,18 @@ fn cmd_render_blurred(
     )
 }
 
+/// The byte offset into the quad index buffer and the index count
+/// to draw the quads `[quad_offset..quad_offset + quad_num]` of a quad container,
+/// e.g. a single animation range of a quad layer.
+pub fn quad_range_indices(quad_offset: usize, quad_num: usize) -> (vk::DeviceSize, u32) {
+    let index_offset = quad_offset
+        .checked_mul(6)
+        .and_then(|v| v.checked_mul(std::mem::size_of::<u32>()))
+        .unwrap() as vk::DeviceSize;
+    let index_count: u32 = quad_num.checked_mul(6).unwrap().try_into().unwrap();
+    (index_offset, index_count)
+}
+
 fn cmd_render_quad_container_ex(
     mut render_manager: RenderManager,
     cmd: &CommandRenderQuadContainer,
@@ -212,11 +225,7 @@ fn cmd_render_quad_container_ex(
 
     render_manager.bind_vertex_buffer();
 
-    let index_offset = (cmd
-        .quad_offset
-        .checked_mul(6)
-        .and_then(|v| v.checked_mul(std::mem::size_of::<u32>()))
-        .unwrap()) as vk::DeviceSize;
+    let (index_offset, index_count) = quad_range_indices(cmd.quad_offset, cmd.quad_num);
 
     render_manager.bind_index_buffer(index_offset);
 
@@ -255,7 +264,6 @@ fn cmd_render_quad_container_ex(
         },
     );
 
-    let index_count: u32 = (cmd.quad_num.checked_mul(6).unwrap()).try_into().unwrap();
     render_manager.draw_indexed(index_count, 1, 0, 0, 0);
 }
 
@@ -274,11 +282,7 @@ fn cmd_render_quad_container_as_sprite_multiple(
 
     render_manager.bind_vertex_buffer();
 
-    let index_offset = cmd
-        .quad_offset
-        .checked_mul(6)
-        .and_then(|v| v.checked_mul(std::mem::size_of::<u32>()))
-        .unwrap() as vk::DeviceSize;
+    let (index_offset, index_count) = quad_range_indices(cmd.quad_offset, cmd.quad_num);
     render_manager.bind_index_buffer(index_offset);
 
     render_manager.bind_texture_descriptor_sets(0, 0);
@@ -321,7 +325,6 @@ fn cmd_render_quad_container_as_sprite_multiple(
 
         render_manager.bind_uniform_descriptor_sets(2, 0);
 
-        let index_count: u32 = cmd.quad_num.checked_mul(6).unwrap().try_into().unwrap();
         render_manager.draw_indexed(index_count, uniform_count as u32, 0, 0, 0);
 
         draw_count -= uniform_count;
@@ -344,11 +347,24 @@ fn cmd_clear(
                 },
             },
         }];
-        let clear_rects = [vk::ClearRect {
-            rect: vk::Rect2D {
+        // only clear commands with a clip rect fill the dynamic state
+        let rect = if exec_buffer.has_dynamic_state {
+            unsafe {
+                device.device.cmd_set_scissor(
+                    command_buffer.command_buffer,
+                    0,
+                    &[exec_buffer.scissor],
+                );
+            }
+            exec_buffer.scissor
+        } else {
+            vk::Rect2D {
                 offset: vk::Offset2D { x: 0, y: 0 },
                 extent: exec_buffer.viewport_size,
-            },
+            }
+        };
+        let clear_rects = [vk::ClearRect {
+            rect,
             base_array_layer: 0,
             layer_count: 1,
         }];
@@ -411,8 +427,8 @@ pub(crate) fn command_cb_render(
             }
         },
         CommandsRender::Mod(CommandsRenderMod { mod_name, cmd }) => {
-            if let Some(pipe_index) = custom_pipes.pipe_indices.get(mod_name.as_str()) {
-                custom_pipes.pipes.read()[*pipe_index].render(cmd, &mut render_manager)
+            if let Some(pipe_index) = custom_pipes.pipe_index(mod_name.as_str()) {
+                custom_pipes.pipes.read()[pipe_index].render(cmd, &mut render_manager)
             } else {
                 Err(anyhow::anyhow!("Missing mod for {}", mod_name.as_str()))
             }
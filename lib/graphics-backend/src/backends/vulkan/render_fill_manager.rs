@@ -2,15 +2,16 @@ use ash::vk;
 use graphics_backend_traits::plugin::BackendRenderExecuteInterface;
 use graphics_types::{
     commands::{CommandsRender, SColorf},
-    rendering::State,
+    rendering::{State, StateClip},
 };
 use hiarc::Hiarc;
 
 use super::{
     render_cmds::get_address_mode_index,
     render_setup::RenderSetupNativeType,
+    utils::clear_rect_scissor,
     vulkan::VulkanBackend,
-    vulkan_types::{CanvasClipModes, RenderPassSubType, RenderPassType, SupportedSamplerTypes},
+    vulkan_types::{CanvasClipModes, RenderPassSubType, RenderPassType},
 };
 
 #[derive(Debug, Hiarc, Default)]
@@ -50,6 +51,25 @@ pub struct RenderCommandExecuteBuffer {
     pub scissor: vk::Rect2D,
 }
 
+impl RenderCommandExecuteBuffer {
+    /// Clears only the part of `clip` that lies inside the canvas.
+    /// The render thread sets the scissor and clears the rect of it.
+    pub fn clear_rect_in_render_thread(
+        &mut self,
+        clip: &StateClip,
+        viewport_offset: vk::Offset2D,
+        canvas_size: vk::Extent2D,
+    ) {
+        let scissor = clear_rect_scissor(clip, viewport_offset, canvas_size);
+        // nothing to clear outside of the canvas
+        if scissor.extent.width > 0 && scissor.extent.height > 0 {
+            self.clear_color_in_render_thread = true;
+            self.has_dynamic_state = true;
+            self.scissor = scissor;
+        }
+    }
+}
+
 pub struct RenderCommandExecuteManager<'a> {
     exec_buffer: &'a mut RenderCommandExecuteBuffer,
     pub(crate) backend: &'a mut VulkanBackend,
@@ -67,7 +87,16 @@ impl<'a> RenderCommandExecuteManager<'a> {
     }
 
     pub fn clear_color_in_render_thread(&mut self, is_forced_clear: bool, color: SColorf) {
-        if !is_forced_clear {
+        // a persistent canvas keeps its content, only forced clears apply
+        let persistent = self.backend.render.get().persistent;
+        if let Some(canvas_clear_color) = self.backend.render.get().clear_color {
+            // the render pass already cleared the canvas with its own color
+            if is_forced_clear
+                || (!persistent && canvas_clear_color != [color.r, color.g, color.b, color.a])
+            {
+                self.exec_buffer.clear_color_in_render_thread = true;
+            }
+        } else if !is_forced_clear {
             let color_changed: bool = self.backend.clear_color[0] != color.r
                 || self.backend.clear_color[1] != color.g
                 || self.backend.clear_color[2] != color.b
@@ -76,7 +105,7 @@ impl<'a> RenderCommandExecuteManager<'a> {
             self.backend.clear_color[1] = color.g;
             self.backend.clear_color[2] = color.b;
             self.backend.clear_color[3] = color.a;
-            if color_changed {
+            if color_changed && !persistent {
                 self.exec_buffer.clear_color_in_render_thread = true;
             }
         } else {
@@ -84,8 +113,24 @@ impl<'a> RenderCommandExecuteManager<'a> {
         }
     }
 
+    pub fn clear_rect_in_render_thread(&mut self, clip: &StateClip) {
+        let viewport_offset = self
+            .backend
+            .dynamic_viewport()
+            .map(|viewport| viewport.offset)
+            .unwrap_or_default();
+        let canvas_size = self
+            .backend
+            .render
+            .get()
+            .native
+            .swap_img_and_viewport_extent;
+        self.exec_buffer
+            .clear_rect_in_render_thread(clip, viewport_offset, canvas_size);
+    }
+
     fn get_dynamic_mode_index_from_state(&self, state: &State) -> usize {
-        if state.clip.is_some() || self.backend.has_dynamic_viewport {
+        if state.clip.is_some() || self.backend.dynamic_viewport().is_some() {
             CanvasClipModes::DynamicScissorAndViewport as usize
         } else {
             CanvasClipModes::None as usize
@@ -177,7 +222,7 @@ impl BackendRenderExecuteInterface for RenderCommandExecuteManager<'_> {
         );
     }
 
-    fn set_texture_3d(&mut self, index: u64, texture_index: u128) {
+    fn set_texture_3d(&mut self, index: u64, texture_index: u128, address_mode_index: u64) {
         self.exec_buffer.texture_descriptors[index as usize] = Some(
             self.backend
                 .props
@@ -190,7 +235,7 @@ impl BackendRenderExecuteInterface for RenderCommandExecuteManager<'_> {
                 .set(&mut self.backend.current_frame_resources),
         );
         self.exec_buffer.sampler_descriptors[index as usize] = Some(
-            self.backend.props.device.samplers[SupportedSamplerTypes::Texture2dArray as usize]
+            self.backend.props.device.samplers[address_mode_index as usize]
                 .1
                 .set(&mut self.backend.current_frame_resources),
         );
@@ -230,11 +275,12 @@ impl BackendRenderExecuteInterface for RenderCommandExecuteManager<'_> {
         let dynamic_state_index: usize = self.get_dynamic_mode_index_from_state(state);
         if dynamic_state_index == CanvasClipModes::DynamicScissorAndViewport as usize {
             let mut viewport = vk::Viewport::default();
-            if self.backend.has_dynamic_viewport {
-                viewport.x = self.backend.dynamic_viewport_offset.x as f32;
-                viewport.y = self.backend.dynamic_viewport_offset.y as f32;
-                viewport.width = self.backend.dynamic_viewport_size.width as f32;
-                viewport.height = self.backend.dynamic_viewport_size.height as f32;
+            let dynamic_viewport = self.backend.dynamic_viewport();
+            if let Some(dynamic_viewport) = dynamic_viewport {
+                viewport.x = dynamic_viewport.offset.x as f32;
+                viewport.y = dynamic_viewport.offset.y as f32;
+                viewport.width = dynamic_viewport.size.width as f32;
+                viewport.height = dynamic_viewport.size.height as f32;
                 viewport.min_depth = 0.0;
                 viewport.max_depth = 1.0;
             } else {
@@ -284,9 +330,9 @@ impl BackendRenderExecuteInterface for RenderCommandExecuteManager<'_> {
             }
 
             // if there is a dynamic viewport shift the clip
-            if self.backend.has_dynamic_viewport && state.clip.is_some() {
-                scissor.offset.x += self.backend.dynamic_viewport_offset.x;
-                scissor.offset.y += self.backend.dynamic_viewport_offset.y;
+            if let Some(dynamic_viewport) = dynamic_viewport.filter(|_| state.clip.is_some()) {
+                scissor.offset.x += dynamic_viewport.offset.x;
+                scissor.offset.y += dynamic_viewport.offset.y;
             }
 
             viewport.x = viewport.x.clamp(0.0, f32::MAX);
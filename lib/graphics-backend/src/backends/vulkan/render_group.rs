@@ -206,6 +206,8 @@ impl RenderSetup {
         width: u32,
         height: u32,
         has_multi_sampling: Option<u32>,
+        clear_color: Option<[f32; 4]>,
+        persistent: bool,
         props: OffscreenCanvasCreateProps<'_>,
     ) -> anyhow::Result<()> {
         self.offscreens.insert(
@@ -226,6 +228,8 @@ impl RenderSetup {
                     extent: vk::Extent2D { width, height },
                     img_count: self.onscreen.swap_chain_image_count(),
                     img_format: self.onscreen.surf_format,
+                    clear_color,
+                    persistent,
                 },
                 if props.should_queue_full_compile {
                     CompileOneByOneTypeRef::CompileAndQueueFullCompile(
@@ -240,6 +244,23 @@ impl RenderSetup {
         Ok(())
     }
 
+    pub fn resize_offscreen_canvas(
+        &mut self,
+        id: u128,
+        width: u32,
+        height: u32,
+        standard_texture_descr_pool: &Arc<parking_lot::Mutex<DeviceDescriptorPools>>,
+    ) -> anyhow::Result<()> {
+        let offscreen = self
+            .offscreens
+            .get_mut(&id)
+            .ok_or_else(|| anyhow!("offscreen canvas with id {id} does not exist"))?;
+        // frames in flight keep the old setup alive
+        *offscreen = offscreen
+            .resize_offscreen(standard_texture_descr_pool, vk::Extent2D { width, height })?;
+        Ok(())
+    }
+
     pub fn destroy_offscreen_canvas(&mut self, id: u128) {
         let had_item = self.offscreens.remove(&id).is_some();
         debug_assert!(had_item);
@@ -258,15 +279,22 @@ impl RenderSetup {
         self.try_finish_compile(frame_resources)
     }
 
+    /// The number of render passes that still wait for their full pipeline creation.
+    pub fn pipelines_pending_count(&self) -> usize {
+        self.pipeline_compile_in_queue
+            .load(std::sync::atomic::Ordering::SeqCst)
+    }
+
+    /// Whether no full pipeline creation is queued anymore.
+    pub fn pipelines_ready(&self) -> bool {
+        self.pipelines_pending_count() == 0
+    }
+
     pub fn try_finish_compile(
         &mut self,
         frame_resources: &mut FrameResources,
     ) -> anyhow::Result<()> {
-        if self
-            .pipeline_compile_in_queue
-            .load(std::sync::atomic::Ordering::SeqCst)
-            > 0
-        {
+        if !self.pipelines_ready() {
             Arc::get_mut(&mut self.onscreen)
                 .ok_or(anyhow!(
                     "could not get onscreen canvas setup as mut form Arc"
@@ -17,6 +17,7 @@ use super::pipeline_cache::PipelineCacheInner;
 use super::render_setup::{
     CanvasSetupCreationType, CanvasSetupNative, CanvasSetupSwitching, OffscreenSurface,
     RenderSetupCreationType, RenderSetupNativeType, RenderSetupSwitchingCreation,
+    RenderSetupSwitchingPass,
 };
 use super::sub_render_pass::SubRenderPass;
 use super::swapchain::Swapchain;
@@ -296,6 +297,7 @@ impl Deref for SubRenderPassDeref {
 #[derive(Debug, Hiarc)]
 pub struct CanvasSetupMultiSampling {
     pub native: CanvasSetupNative,
+    pub multi_sampling_count: u32,
 }
 
 #[derive(Debug, Hiarc)]
@@ -328,6 +330,12 @@ pub struct CanvasSetup {
     setup_props: CanvasSetupArguments,
 
     pub inner_type: RenderSetupNativeType,
+
+    /// Overrides the backend's clear color for this canvas.
+    pub clear_color: Option<[f32; 4]>,
+    /// The canvas keeps its content across frames,
+    /// see [`graphics_types::commands::CommandOffscreenCanvasCreate::persistent`].
+    pub persistent: bool,
 }
 
 impl CanvasSetup {
@@ -344,7 +352,7 @@ impl CanvasSetup {
         compile_one_by_one: CompileOneByOneTypeRef<'_>,
         has_multi_sampling: Option<u32>,
     ) -> anyhow::Result<Arc<Self>> {
-        let (creation_type, surf_format, ty) = match creation_type {
+        let (creation_type, surf_format, ty, clear_color, persistent) = match creation_type {
             CanvasSetupCreationType::Swapchain((swapchain, swapchain_backend)) => {
                 let swapchain_images =
                     Swapchain::get_swap_chain_image_handles(swapchain_backend, device)?;
@@ -365,49 +373,28 @@ impl CanvasSetup {
                     },
                     swapchain_format,
                     ty,
+                    None,
+                    false,
                 )
             }
             CanvasSetupCreationType::Offscreen {
                 img_format,
                 img_count,
                 extent,
+                clear_color,
+                persistent,
             } => {
-                let images_res = CanvasSetupNative::create_offscreen_images(
-                    mem_allocator,
-                    extent.width,
-                    extent.height,
-                    img_count,
-                    img_format.format,
-                )?;
-                let (images, img_mems): (Vec<_>, Vec<_>) = images_res.into_iter().unzip();
-                let image_views =
-                    CanvasSetupNative::create_image_views(device, img_format.format, &images)?;
-
-                let descrs = image_views
-                    .iter()
-                    .map(|img_view| {
-                        Device::create_new_textured_standard_descriptor_sets(
+                let (creation_type, ty) = Self::create_offscreen_canvas_images(
                     device,
                     layouts,
                     standard_texture_descr_pool,
-                    img_view,
-                )
-                .map_err(|err| {
-                    anyhow!("Could not create image descriptors for switching pass images: {err}")
-                })
-                    })
-                    .collect::<anyhow::Result<Vec<_>>>()?;
-
-                (
-                    RenderSetupCreationType::ExistingImages {
-                        images,
-                        image_views,
-                        extent,
-                        img_format,
-                    },
+                    mem_allocator,
+                    extent,
+                    img_count,
                     img_format,
-                    RenderSetupNativeType::Offscreen { img_mems, descrs },
-                )
+                    persistent,
+                )?;
+                (creation_type, img_format, ty, clear_color, persistent)
             }
         };
 
@@ -422,7 +409,8 @@ impl CanvasSetup {
             shader_compiler,
             creation_type,
             compile_one_by_one,
-            has_multi_sampling.is_none(),
+            // a persistent canvas loads the content of the previous frame
+            has_multi_sampling.is_none() && !persistent,
         )?;
 
         let offscreen_surfaces = [
@@ -497,6 +485,9 @@ impl CanvasSetup {
             },
 
             inner_type: ty,
+
+            clear_color,
+            persistent,
         };
 
         if let Some(multi_sampling_count) = has_multi_sampling {
@@ -506,6 +497,197 @@ impl CanvasSetup {
         Ok(Arc::new(res))
     }
 
+    fn create_offscreen_canvas_images(
+        device: &Arc<LogicalDevice>,
+        layouts: &DescriptorLayouts,
+        standard_texture_descr_pool: &Arc<parking_lot::Mutex<DeviceDescriptorPools>>,
+        mem_allocator: &Arc<parking_lot::Mutex<VulkanAllocator>>,
+        extent: vk::Extent2D,
+        img_count: usize,
+        img_format: vk::SurfaceFormatKHR,
+        persistent: bool,
+    ) -> anyhow::Result<(RenderSetupCreationType, RenderSetupNativeType)> {
+        let images_res = CanvasSetupNative::create_offscreen_images(
+            mem_allocator,
+            extent.width,
+            extent.height,
+            if persistent { 1 } else { img_count },
+            img_format.format,
+        )?;
+        let (mut images, img_mems): (Vec<_>, Vec<_>) = images_res.into_iter().unzip();
+        if persistent {
+            // every swapchain image index renders into the same image,
+            // so that the next frame sees the content of the previous one
+            images = vec![images[0].clone(); img_count];
+        }
+        let image_views =
+            CanvasSetupNative::create_image_views(device, img_format.format, &images)?;
+
+        let descrs = image_views
+            .iter()
+            .map(|img_view| {
+                Device::create_new_textured_standard_descriptor_sets(
+                    device,
+                    layouts,
+                    standard_texture_descr_pool,
+                    img_view,
+                )
+                .map_err(|err| {
+                    anyhow!("Could not create image descriptors for switching pass images: {err}")
+                })
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        Ok((
+            RenderSetupCreationType::ExistingImages {
+                images,
+                image_views,
+                extent,
+                img_format,
+            },
+            RenderSetupNativeType::Offscreen { img_mems, descrs },
+        ))
+    }
+
+    /// Creates a copy of this offscreen canvas with a different size.
+    /// Render passes and pipelines are reused, only the images
+    /// and framebuffers are recreated.
+    pub fn resize_offscreen(
+        &self,
+        standard_texture_descr_pool: &Arc<parking_lot::Mutex<DeviceDescriptorPools>>,
+        extent: vk::Extent2D,
+    ) -> anyhow::Result<Arc<Self>> {
+        anyhow::ensure!(
+            matches!(self.inner_type, RenderSetupNativeType::Offscreen { .. }),
+            "only offscreen canvases can be resized"
+        );
+        let props = &self.setup_props;
+        let img_count = self.swap_chain_image_count();
+
+        let (creation_type, ty) = Self::create_offscreen_canvas_images(
+            &props.device,
+            &props.layouts,
+            standard_texture_descr_pool,
+            &props.mem_allocator,
+            extent,
+            img_count,
+            self.surf_format,
+            self.persistent,
+        )?;
+
+        let native = CanvasSetupNative::new_with_render_pass(
+            &props.device,
+            0,
+            &props.mem_allocator,
+            creation_type,
+            self.native.render_pass.clone(),
+        )?;
+
+        let surface1 = OffscreenSurface::new(
+            &props.device,
+            0,
+            &props.layouts,
+            standard_texture_descr_pool,
+            &props.mem_allocator,
+            img_count,
+            self.surf_format.format,
+            extent,
+        )?;
+        let surface2 = OffscreenSurface::new(
+            &props.device,
+            0,
+            &props.layouts,
+            standard_texture_descr_pool,
+            &props.mem_allocator,
+            img_count,
+            self.surf_format.format,
+            extent,
+        )?;
+
+        let (stencil_images, stencil_format) =
+            CanvasSetupSwitching::create_stencil_attachments_for_pass_transition(
+                &props.device,
+                0,
+                &props.mem_allocator,
+                img_count,
+                extent,
+            )?;
+        anyhow::ensure!(
+            stencil_format == self.stencil_format,
+            "stencil format changed while resizing"
+        );
+
+        let switching = CanvasSetupSwitching {
+            passes: [
+                RenderSetupSwitchingPass::new_with_render_pass(
+                    &props.device,
+                    0,
+                    &stencil_images,
+                    extent,
+                    surface1,
+                    self.switching.passes[0].render_pass.clone(),
+                )?,
+                RenderSetupSwitchingPass::new_with_render_pass(
+                    &props.device,
+                    0,
+                    &stencil_images,
+                    extent,
+                    surface2,
+                    self.switching.passes[1].render_pass.clone(),
+                )?,
+            ],
+            stencil_list_for_pass_transition: stencil_images,
+        };
+
+        let multi_sampling = self
+            .multi_sampling
+            .as_ref()
+            .map(|multi_sampling| {
+                anyhow::Ok(CanvasSetupMultiSampling {
+                    native: CanvasSetupNative::new_with_render_pass(
+                        &props.device,
+                        multi_sampling.multi_sampling_count,
+                        &props.mem_allocator,
+                        RenderSetupCreationType::ExistingImages {
+                            images: native.swap_chain_images.clone(),
+                            image_views: native.swap_chain_image_view_list.clone(),
+                            extent,
+                            img_format: self.surf_format,
+                        },
+                        multi_sampling.native.render_pass.clone(),
+                    )?,
+                    multi_sampling_count: multi_sampling.multi_sampling_count,
+                })
+            })
+            .transpose()?;
+
+        Ok(Arc::new(Self {
+            surf_format: self.surf_format,
+            stencil_format,
+
+            switching,
+            native,
+
+            multi_sampling,
+
+            setup_props: CanvasSetupArguments {
+                device: props.device.clone(),
+                layouts: props.layouts.clone(),
+                custom_pipes: props.custom_pipes.clone(),
+                pipeline_cache: props.pipeline_cache.clone(),
+                mem_allocator: props.mem_allocator.clone(),
+                runtime_threadpool: props.runtime_threadpool.clone(),
+                shader_compiler: props.shader_compiler.clone(),
+                compile_one_by_one: props.compile_one_by_one.as_ref().into(),
+            },
+
+            inner_type: ty,
+
+            clear_color: self.clear_color,
+            persistent: self.persistent,
+        }))
+    }
+
     pub fn init_multi_sampling(&mut self, multi_sampling_count: u32) -> anyhow::Result<()> {
         let native = CanvasSetupNative::new(
             &self.setup_props.device,
@@ -529,7 +711,10 @@ impl CanvasSetup {
             true,
         )?;
 
-        self.multi_sampling = Some(CanvasSetupMultiSampling { native });
+        self.multi_sampling = Some(CanvasSetupMultiSampling {
+            native,
+            multi_sampling_count,
+        });
 
         Ok(())
     }
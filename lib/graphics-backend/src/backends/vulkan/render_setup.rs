@@ -123,6 +123,8 @@ pub enum CanvasSetupCreationType<'a> {
         extent: vk::Extent2D,
         img_count: usize,
         img_format: vk::SurfaceFormatKHR,
+        clear_color: Option<[f32; 4]>,
+        persistent: bool,
     },
 }
 
@@ -259,23 +261,15 @@ impl CanvasSetupNative {
         compile_one_by_one: CompileOneByOneTypeRef<'_>,
         is_first_render_pass_in_frame: bool,
     ) -> anyhow::Result<Self> {
-        let (swap_chain_images, img_views, extent, swapchain_format) = match create_type {
+        let (swap_chain_images, img_views, extent, img_format) = match create_type {
             RenderSetupCreationType::ExistingImages {
                 images,
                 image_views,
                 extent,
                 img_format,
-            } => (images, image_views, extent, img_format.format),
+            } => (images, image_views, extent, img_format),
         };
-
-        let multi_sampling_imgs = create_multi_sampler_image_attachments(
-            device,
-            multi_sampling_count,
-            mem_allocator,
-            swapchain_format,
-            extent,
-            swap_chain_images.len(),
-        )?;
+        let swapchain_format = img_format.format;
 
         let render_pass = Self::create_render_pass(
             device,
@@ -291,6 +285,48 @@ impl CanvasSetupNative {
             is_first_render_pass_in_frame,
         )?;
 
+        Self::new_with_render_pass(
+            device,
+            multi_sampling_count,
+            mem_allocator,
+            RenderSetupCreationType::ExistingImages {
+                images: swap_chain_images,
+                image_views: img_views,
+                extent,
+                img_format,
+            },
+            render_pass,
+        )
+    }
+
+    /// Creates the attachments & framebuffers for an existing render pass.
+    /// The render pass' pipelines use a dynamic viewport,
+    /// so they can be reused for any extent.
+    pub fn new_with_render_pass(
+        device: &Arc<LogicalDevice>,
+        multi_sampling_count: u32,
+        mem_allocator: &Arc<parking_lot::Mutex<VulkanAllocator>>,
+        create_type: RenderSetupCreationType,
+        render_pass: Arc<RenderPass>,
+    ) -> anyhow::Result<Self> {
+        let (swap_chain_images, img_views, extent, swapchain_format) = match create_type {
+            RenderSetupCreationType::ExistingImages {
+                images,
+                image_views,
+                extent,
+                img_format,
+            } => (images, image_views, extent, img_format.format),
+        };
+
+        let multi_sampling_imgs = create_multi_sampler_image_attachments(
+            device,
+            multi_sampling_count,
+            mem_allocator,
+            swapchain_format,
+            extent,
+            swap_chain_images.len(),
+        )?;
+
         let frame_buffers = create_framebuffers_impl(
             device,
             multi_sampling_count,
@@ -492,6 +528,25 @@ impl RenderSetupSwitchingPass {
             compile_one_by_one,
         )?;
 
+        Self::new_with_render_pass(
+            device,
+            multi_sampling_count,
+            stencil_list_for_pass_transition,
+            swapchain_extent,
+            surface,
+            render_pass,
+        )
+    }
+
+    /// Creates the framebuffers for an existing render pass.
+    pub fn new_with_render_pass(
+        device: &Arc<LogicalDevice>,
+        multi_sampling_count: u32,
+        stencil_list_for_pass_transition: &Vec<SwapChainImageBase>,
+        swapchain_extent: vk::Extent2D,
+        surface: Arc<OffscreenSurface>,
+        render_pass: Arc<RenderPass>,
+    ) -> anyhow::Result<Self> {
         let framebuffers = create_framebuffers_impl(
             device,
             multi_sampling_count,
@@ -571,7 +626,8 @@ impl CanvasSetupSwitching {
                 1,
                 stencil_format,
                 vk::ImageTiling::OPTIMAL,
-                vk::ImageUsageFlags::DEPTH_STENCIL_ATTACHMENT,
+                // transfer src allows depth readback
+                vk::ImageUsageFlags::DEPTH_STENCIL_ATTACHMENT | vk::ImageUsageFlags::TRANSFER_SRC,
                 multi_sampling_count,
             )?;
             let img_view = Device::create_image_view(
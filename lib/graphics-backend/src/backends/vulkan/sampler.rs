@@ -12,31 +12,95 @@ pub struct Sampler {
     device: Arc<LogicalDevice>,
 }
 
+/// The max anisotropy of texture samplers for the requested anisotropy level,
+/// clamped to what the device supports.
+/// `None` disables anisotropic filtering.
+pub fn sampler_anisotropy(
+    requested: u32,
+    is_supported: bool,
+    max_sampler_anisotropy: u32,
+) -> Option<f32> {
+    (is_supported && requested > 1 && max_sampler_anisotropy > 1)
+        .then(|| requested.min(max_sampler_anisotropy) as f32)
+}
+
+/// The lod bias and the range of mip levels texture samplers sample.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SamplerLod {
+    pub bias: f32,
+    pub min: f32,
+    pub max: f32,
+}
+
+/// The lod of texture samplers for the requested lod bias & clamp.
+/// A `min_lod` of 0 doesn't clamp, a `max_lod` smaller than `min_lod` is raised to it.
+pub fn sampler_lod(lod_bias: f64, min_lod: f64, max_lod: f64) -> SamplerLod {
+    let min = if min_lod > 0.0 {
+        min_lod as f32
+    } else {
+        -1000.0
+    };
+    SamplerLod {
+        bias: lod_bias as f32,
+        min,
+        max: (max_lod as f32).max(min),
+    }
+}
+
 impl Sampler {
-    pub fn new(
-        device: &Arc<LogicalDevice>,
-        max_sampler_anisotropy: u32,
-        global_texture_lod_bias: f64,
+    /// The [`vk::Filter::NEAREST`] filter also picks the nearest mip map.
+    pub fn create_info(
+        anisotropy: Option<f32>,
+        lod: SamplerLod,
+        filter: vk::Filter,
         addr_mode_u: vk::SamplerAddressMode,
         addr_mode_v: vk::SamplerAddressMode,
         addr_mode_w: vk::SamplerAddressMode,
-    ) -> anyhow::Result<Arc<Self>> {
+    ) -> vk::SamplerCreateInfo<'static> {
         let mut sampler_info = vk::SamplerCreateInfo::default();
-        sampler_info.mag_filter = vk::Filter::LINEAR;
-        sampler_info.min_filter = vk::Filter::LINEAR;
+        sampler_info.mag_filter = filter;
+        sampler_info.min_filter = filter;
         sampler_info.address_mode_u = addr_mode_u;
         sampler_info.address_mode_v = addr_mode_v;
         sampler_info.address_mode_w = addr_mode_w;
-        sampler_info.anisotropy_enable = vk::FALSE;
-        sampler_info.max_anisotropy = max_sampler_anisotropy as f32;
+        sampler_info.anisotropy_enable = if anisotropy.is_some() {
+            vk::TRUE
+        } else {
+            vk::FALSE
+        };
+        sampler_info.max_anisotropy = anisotropy.unwrap_or(1.0);
         sampler_info.border_color = vk::BorderColor::INT_OPAQUE_BLACK;
         sampler_info.unnormalized_coordinates = vk::FALSE;
         sampler_info.compare_enable = vk::FALSE;
         sampler_info.compare_op = vk::CompareOp::ALWAYS;
-        sampler_info.mipmap_mode = vk::SamplerMipmapMode::LINEAR;
-        sampler_info.mip_lod_bias = global_texture_lod_bias as f32;
-        sampler_info.min_lod = -1000.0;
-        sampler_info.max_lod = 1000.0;
+        sampler_info.mipmap_mode = if filter == vk::Filter::NEAREST {
+            vk::SamplerMipmapMode::NEAREST
+        } else {
+            vk::SamplerMipmapMode::LINEAR
+        };
+        sampler_info.mip_lod_bias = lod.bias;
+        sampler_info.min_lod = lod.min;
+        sampler_info.max_lod = lod.max;
+        sampler_info
+    }
+
+    pub fn new(
+        device: &Arc<LogicalDevice>,
+        anisotropy: Option<f32>,
+        lod: SamplerLod,
+        filter: vk::Filter,
+        addr_mode_u: vk::SamplerAddressMode,
+        addr_mode_v: vk::SamplerAddressMode,
+        addr_mode_w: vk::SamplerAddressMode,
+    ) -> anyhow::Result<Arc<Self>> {
+        let sampler_info = Self::create_info(
+            anisotropy,
+            lod,
+            filter,
+            addr_mode_u,
+            addr_mode_v,
+            addr_mode_w,
+        );
 
         let sampler = unsafe { device.device.create_sampler(&sampler_info, None) }?;
 
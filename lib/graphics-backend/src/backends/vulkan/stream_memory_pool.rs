@@ -82,6 +82,40 @@ impl<T: Send + Sync> Drop for StreamMemoryBlock<T> {
     }
 }
 
+/// How many instances of a [`StreamMemoryPool`] exist
+/// and how many of them are currently not in use.
+#[derive(Debug, Hiarc, Default, Clone, Copy, PartialEq, Eq)]
+pub struct StreamMemoryPoolOccupancy {
+    pub free: usize,
+    pub capacity: usize,
+}
+
+/// Decides how many instances a [`StreamMemoryPool`] allocates.
+#[derive(Debug, Hiarc, Clone, Copy)]
+pub struct StreamMemoryPoolGrowth {
+    /// The minimum amount of instances allocated at once.
+    pub min_alloc: usize,
+    /// The capacity is multiplied by this factor when the pool grows.
+    /// For factors bigger than 1 the pool also grows ahead of exhaustion,
+    /// so it always keeps another request worth of instances.
+    pub factor: f64,
+}
+
+impl StreamMemoryPoolGrowth {
+    /// The amount of instances to allocate, so that `count` instances
+    /// can be taken from a pool with the given occupancy.
+    pub fn alloc_amount(&self, occupancy: StreamMemoryPoolOccupancy, count: usize) -> usize {
+        let required = if self.factor > 1.0 { count * 2 } else { count };
+        if occupancy.free >= required {
+            return 0;
+        }
+        let grow_amount = (occupancy.capacity as f64 * (self.factor - 1.0)).ceil() as usize;
+        self.min_alloc
+            .max(required - occupancy.free)
+            .max(grow_amount)
+    }
+}
+
 #[derive(Debug, Hiarc)]
 pub struct StreamMemoryPool<T: Send + Sync> {
     mem: Memory,
@@ -93,7 +127,10 @@ pub struct StreamMemoryPool<T: Send + Sync> {
 
     size_of_instance: usize,
     instances_per_buffer: usize,
-    buffers_per_allocation: usize,
+    growth: StreamMemoryPoolGrowth,
+    capacity: usize,
+    /// How often new instances were allocated.
+    allocations: u64,
 
     pub vec_pool: MtPool<Vec<StreamMemory<T>>>,
     pub block_pool: ArcPool<StreamMemoryBlock<T>>,
@@ -117,6 +154,7 @@ impl<T: Send + Sync> StreamMemoryPool<T> {
         size_of_instance: usize,
         instances_per_buffer: usize,
         buffers_per_allocation: usize,
+        growth_factor: f64,
     ) -> Self {
         let vec_pool = MtPool::with_capacity(10);
 
@@ -134,7 +172,12 @@ impl<T: Send + Sync> StreamMemoryPool<T> {
             usage,
             size_of_instance,
             instances_per_buffer,
-            buffers_per_allocation,
+            growth: StreamMemoryPoolGrowth {
+                min_alloc: buffers_per_allocation,
+                factor: growth_factor.max(1.0),
+            },
+            capacity: 0,
+            allocations: 0,
 
             pool: Default::default(),
 
@@ -143,15 +186,44 @@ impl<T: Send + Sync> StreamMemoryPool<T> {
         }
     }
 
-    /// allocates only if pool is smaller than count
+    pub fn occupancy(&self) -> StreamMemoryPoolOccupancy {
+        StreamMemoryPoolOccupancy {
+            free: self.pool.lock().len(),
+            capacity: self.capacity,
+        }
+    }
+
+    /// How often [`Self::try_alloc`] allocated new instances.
+    /// Blocks that are no longer in use are returned to the pool,
+    /// so this only grows until the pool can serve the frames in flight.
+    pub fn allocations(&self) -> u64 {
+        self.allocations
+    }
+
+    /// How many instances [`Self::try_alloc`] would allocate for `count` instances.
+    pub fn alloc_amount(&self, count: usize) -> usize {
+        self.growth.alloc_amount(self.occupancy(), count)
+    }
+
+    /// allocates only if pool is smaller than count,
+    /// or would be exhausted soon according to the growth factor
     pub fn try_alloc(
         &mut self,
         mut new_instance_func: impl FnMut(&Arc<Buffer>, vk::DeviceSize, usize) -> anyhow::Result<Vec<T>>,
         count: usize,
     ) -> anyhow::Result<(), BufferAllocationError> {
         let mut pool = self.pool.lock();
-        while pool.len() < count {
-            let alloc_amount = self.buffers_per_allocation.max(count - pool.len());
+        loop {
+            let alloc_amount = self.growth.alloc_amount(
+                StreamMemoryPoolOccupancy {
+                    free: pool.len(),
+                    capacity: self.capacity,
+                },
+                count,
+            );
+            if alloc_amount == 0 {
+                break;
+            }
             // allocate new buffers
             let new_buffer_single_size =
                 (self.size_of_instance * self.instances_per_buffer) as vk::DeviceSize;
@@ -171,6 +243,8 @@ impl<T: Send + Sync> StreamMemoryPool<T> {
             .unwrap();
             let mut users = new_instance_func(&stream_buffer, 0, alloc_amount)
                 .map_err(BufferAllocationError::MemoryRelatedOperationFailed)?;
+            self.capacity += users.len();
+            self.allocations += 1;
             for (i, user) in users.drain(..).enumerate() {
                 let offset = new_buffer_single_size * i as vk::DeviceSize;
                 pool.push(StreamMemory {
@@ -1,7 +1,11 @@
-use std::sync::Arc;
+use std::{collections::HashSet, sync::Arc};
 
 use anyhow::anyhow;
 use ash::vk;
+use graphics_backend_traits::frame_fetcher_plugin::{
+    FetchCanvasIndex, OffscreenCanvasId, PresentedImageOrientation,
+};
+use graphics_types::{commands::CommandUpdateBufferRegion, rendering::StateClip};
 
 use crate::backends::vulkan::image::ImageLayout;
 
@@ -17,6 +21,197 @@ use super::{
     vulkan_mem::{BufferAllocationError, ImageAllocationError},
 };
 
+/// The part of `clip`, shifted by `viewport_offset`,
+/// that lies inside a canvas of the size `canvas_size`.
+pub fn clear_rect_scissor(
+    clip: &StateClip,
+    viewport_offset: vk::Offset2D,
+    canvas_size: vk::Extent2D,
+) -> vk::Rect2D {
+    let x = clip.x as i64 + viewport_offset.x as i64;
+    let y = clip.y as i64 + viewport_offset.y as i64;
+    let x0 = x.clamp(0, canvas_size.width as i64);
+    let y0 = y.clamp(0, canvas_size.height as i64);
+    let x1 = (x + clip.w as i64).clamp(0, canvas_size.width as i64);
+    let y1 = (y + clip.h as i64).clamp(0, canvas_size.height as i64);
+    vk::Rect2D {
+        offset: vk::Offset2D {
+            x: x0 as i32,
+            y: y0 as i32,
+        },
+        extent: vk::Extent2D {
+            width: (x1 - x0) as u32,
+            height: (y1 - y0) as u32,
+        },
+    }
+}
+
+/// Packs `height` rows of `row_size` bytes, that start `pitch` bytes apart,
+/// tightly together and reverses their order for [`PresentedImageOrientation::BottomToTop`].
+/// Works in place, without any scratch memory.
+pub fn pack_image_rows(
+    data: &mut [u8],
+    row_size: usize,
+    pitch: usize,
+    height: usize,
+    orientation: PresentedImageOrientation,
+) {
+    if row_size < pitch {
+        // a packed row never starts behind its unpacked row,
+        // so moving the rows front to back never overwrites unread data
+        for y in 1..height {
+            data.copy_within(y * pitch..y * pitch + row_size, y * row_size);
+        }
+    }
+    if orientation == PresentedImageOrientation::BottomToTop {
+        for y in 0..height / 2 {
+            let (top, bottom) = data.split_at_mut((height - 1 - y) * row_size);
+            top[y * row_size..(y + 1) * row_size].swap_with_slice(&mut bottom[..row_size]);
+        }
+    }
+}
+
+/// Plans the readback of all frame fetchers of a frame, so that a single submit
+/// covers all of them. Every distinct canvas is copied once, fetchers of the same canvas
+/// share that copy.
+/// Returns the canvases to copy and, for every fetcher, the index of its copy
+/// or `None` if its canvas skips the fetching of this frame.
+pub fn frame_fetch_copies(
+    fetch_indices: &[FetchCanvasIndex],
+    skips: &HashSet<OffscreenCanvasId>,
+) -> (Vec<FetchCanvasIndex>, Vec<Option<usize>>) {
+    let mut canvases: Vec<FetchCanvasIndex> = Vec::new();
+    let copy_indices = fetch_indices
+        .iter()
+        .map(|&fetch_index| {
+            if let FetchCanvasIndex::Offscreen(id) = fetch_index {
+                if skips.contains(&id) {
+                    return None;
+                }
+            }
+            let copy_index = canvases.iter().position(|&canvas| canvas == fetch_index);
+            Some(copy_index.unwrap_or_else(|| {
+                canvases.push(fetch_index);
+                canvases.len() - 1
+            }))
+        })
+        .collect();
+    (canvases, copy_indices)
+}
+
+/// The extent of every layer of a texture array with `depth` layers of `width` x `height`,
+/// whose upload data is `data_len` bytes big.
+/// `layer_size` is the size of a single layer in bytes, if it is known for the format.
+///
+/// Layers don't have to be square or a power of two, but all share the same extent.
+/// If the data implies differently sized layers, e.g. because the layers were split
+/// from a source image that is not divisible by the layer count, an error is returned,
+/// instead of creating an array view that samples the wrong texels.
+pub fn texture_array_layer_extent(
+    width: usize,
+    height: usize,
+    depth: usize,
+    layer_size: Option<usize>,
+    data_len: usize,
+) -> anyhow::Result<(usize, usize)> {
+    anyhow::ensure!(
+        width > 0 && height > 0 && depth > 0,
+        anyhow!("texture array layers must not be empty ({width}x{height}x{depth}).")
+    );
+    anyhow::ensure!(
+        data_len % depth == 0,
+        anyhow!(
+            "the {data_len} bytes of the texture array can't be split \
+            into {depth} equally sized layers."
+        )
+    );
+    if let Some(layer_size) = layer_size {
+        anyhow::ensure!(
+            data_len / depth == layer_size,
+            anyhow!(
+                "the layers of the texture array are {} bytes big, \
+                but a {width}x{height} layer needs {layer_size} bytes.",
+                data_len / depth
+            )
+        );
+    }
+    Ok((width, height))
+}
+
+/// Translates the regions of a buffer update into copies relative to the start
+/// of the update data, so all regions are copied at once.
+/// Additionally returns the smallest range of the destination buffer
+/// that contains all regions, which is the range that needs barriers.
+pub fn buffer_update_copies(
+    update_data_len: usize,
+    update_regions: &[CommandUpdateBufferRegion],
+    dst_buffer_align: usize,
+) -> anyhow::Result<(Vec<vk::BufferCopy>, vk::DeviceSize, vk::DeviceSize)> {
+    anyhow::ensure!(
+        !update_regions.is_empty(),
+        anyhow!("copy regions shall not be empty.")
+    );
+    anyhow::ensure!(
+        !update_regions.iter().any(|region| region.size == 0),
+        anyhow!("copy regions sizes must be bigger than zero.")
+    );
+    anyhow::ensure!(
+        !update_regions
+            .iter()
+            .any(|region| region.src_offset + region.size > update_data_len),
+        anyhow!("copy regions must be inside the update data.")
+    );
+
+    let min_dst_off = update_regions
+        .iter()
+        .map(|region| region.dst_offset)
+        .min()
+        .unwrap();
+    let max_dst_off = update_regions
+        .iter()
+        .map(|region| region.dst_offset + region.size)
+        .max()
+        .unwrap();
+
+    let copies = update_regions
+        .iter()
+        .map(|region| vk::BufferCopy {
+            src_offset: region.src_offset as vk::DeviceSize,
+            dst_offset: region.dst_offset as vk::DeviceSize + dst_buffer_align as vk::DeviceSize,
+            size: region.size as vk::DeviceSize,
+        })
+        .collect();
+
+    Ok((
+        copies,
+        min_dst_off as vk::DeviceSize + dst_buffer_align as vk::DeviceSize,
+        (max_dst_off - min_dst_off) as vk::DeviceSize,
+    ))
+}
+
+/// Same as [`buffer_update_copies`] for a single region,
+/// without allocating. The barrier range is the range of the copy itself.
+pub fn buffer_update_copy(
+    update_data_len: usize,
+    update_region: &CommandUpdateBufferRegion,
+    dst_buffer_align: usize,
+) -> anyhow::Result<vk::BufferCopy> {
+    anyhow::ensure!(
+        update_region.size != 0,
+        anyhow!("copy regions sizes must be bigger than zero.")
+    );
+    anyhow::ensure!(
+        update_region.src_offset + update_region.size <= update_data_len,
+        anyhow!("copy regions must be inside the update data.")
+    );
+
+    Ok(vk::BufferCopy {
+        src_offset: update_region.src_offset as vk::DeviceSize,
+        dst_offset: update_region.dst_offset as vk::DeviceSize + dst_buffer_align as vk::DeviceSize,
+        size: update_region.size as vk::DeviceSize,
+    })
+}
+
 pub fn copy_buffer_to_image(
     frame_resources: &mut FrameResources,
     device: &Arc<LogicalDevice>,
@@ -1,21 +1,23 @@
 use std::{
-    collections::{HashMap, HashSet},
+    collections::{HashMap, HashSet, VecDeque},
     ffi::CStr,
-    num::NonZeroUsize,
+    num::{NonZeroU32, NonZeroUsize},
     os::raw::c_void,
     rc::Rc,
     sync::{
         atomic::{AtomicU64, AtomicUsize},
         Arc,
     },
+    time::Duration,
 };
 
 use base_io::{io::IoFileSys, runtime::IoRuntimeTask};
-use crossbeam::channel::{bounded, unbounded, Receiver};
+use crossbeam::channel::{bounded, unbounded, Receiver, Sender};
 use graphics_backend_traits::{
     frame_fetcher_plugin::{
-        BackendFrameFetcher, BackendPresentedImageDataRgba, FetchCanvasError, FetchCanvasIndex,
-        OffscreenCanvasId,
+        BackendDepthImageData, BackendFrameFetcher, BackendPresentedImageDataRgba,
+        FetchCanvasError, FetchCanvasIndex, FrameFetcherAttachError, OffscreenCanvasId,
+        PresentedImageOrientation,
     },
     plugin::{BackendCustomPipeline, BackendRenderExecuteInterface},
     traits::{DriverBackendInterface, GraphicsBackendMtInterface},
@@ -27,25 +29,26 @@ use graphics_base_traits::traits::{
 use anyhow::anyhow;
 use graphics_types::{
     commands::{
-        AllCommands, CommandClear, CommandCreateBufferObject, CommandCreateShaderStorage,
-        CommandDeleteBufferObject, CommandDeleteShaderStorage,
+        relative_canvas_extent, AllCommands, CommandClear, CommandCreateBufferObject,
+        CommandCreateShaderStorage, CommandDeleteBufferObject, CommandDeleteShaderStorage,
         CommandIndicesForQuadsRequiredNotify, CommandMultiSampling, CommandOffscreenCanvasCreate,
-        CommandOffscreenCanvasDestroy, CommandOffscreenCanvasSkipFetchingOnce,
-        CommandRecreateBufferObject, CommandRender, CommandRenderQuadContainer,
-        CommandRenderQuadContainerAsSpriteMultiple, CommandSwitchCanvasMode,
-        CommandSwitchCanvasModeType, CommandTextureCreate, CommandTextureDestroy,
-        CommandTextureUpdate, CommandUpdateBufferObject, CommandUpdateBufferRegion,
-        CommandUpdateShaderStorage, CommandUpdateViewport, CommandVsync, CommandsMisc,
-        CommandsRender, CommandsRenderMod, CommandsRenderQuadContainer, CommandsRenderStream,
-        GlVertexTex3DStream, RenderSpriteInfo, StreamDataMax, GRAPHICS_DEFAULT_UNIFORM_SIZE,
-        GRAPHICS_MAX_UNIFORM_RENDER_COUNT, GRAPHICS_UNIFORM_INSTANCE_COUNT,
+        CommandOffscreenCanvasDestroy, CommandOffscreenCanvasResize,
+        CommandOffscreenCanvasSkipFetchingOnce, CommandRecreateBufferObject, CommandRender,
+        CommandRenderQuadContainer, CommandRenderQuadContainerAsSpriteMultiple,
+        CommandSwitchCanvasMode, CommandSwitchCanvasModeType, CommandTextureCreate,
+        CommandTextureDestroy, CommandTextureUpdate, CommandUpdateBufferObject,
+        CommandUpdateBufferRegion, CommandUpdateShaderStorage, CommandUpdateViewport, CommandVsync,
+        CommandsMisc, CommandsRender, CommandsRenderMod, CommandsRenderQuadContainer,
+        CommandsRenderStream, GlVertexTex3DStream, PolygonMode, RenderSpriteInfo, StreamDataMax,
+        TextureUsage, GRAPHICS_DEFAULT_UNIFORM_SIZE, GRAPHICS_MAX_UNIFORM_RENDER_COUNT,
+        GRAPHICS_UNIFORM_INSTANCE_COUNT,
     },
-    gpu::Gpus,
+    gpu::{GpuInfo, Gpus},
     rendering::{GlVertex, State, StateTexture},
     types::{
         GraphicsBackendMemory, GraphicsBackendMemoryAllocation, GraphicsBackendMemoryStatic,
         GraphicsBackendMemoryStaticCleaner, GraphicsMemoryAllocationMode,
-        GraphicsMemoryAllocationType,
+        GraphicsMemoryAllocationType, TextureCompressedFormat,
     },
 };
 
@@ -54,11 +57,15 @@ use hiarc::Hiarc;
 use log::{info, warn};
 use pool::{arc::PoolArc, mt_pool::Pool as MtPool, traits::UnclearedVec};
 use pool::{datatypes::PoolVec, pool::Pool};
+use thiserror::Error;
 
 use crate::{
     backend::CustomPipelines,
+    backend_mt::MemoryPressureCallback,
     backends::{
-        null::mem_alloc_lazy, types::BackendWriteFiles, vulkan::pipeline_cache::PipelineCache,
+        null::mem_alloc_lazy,
+        types::{BackendFrameStats, BackendWaitStats, BackendWriteFiles},
+        vulkan::pipeline_cache::PipelineCache,
     },
     window::{
         BackendDisplayRequirements, BackendSurface, BackendSurfaceAndHandles, BackendSwapchain,
@@ -72,17 +79,19 @@ use config::config::{AtomicGfxDebugModes, ConfigDebug, GfxDebugModes};
 use super::{
     buffer::Buffer,
     command_pool::{AutoCommandBuffer, AutoCommandBufferType, CommandPool},
+    common::texture_compressed_format,
     compiler::compiler::{ShaderCompiler, ShaderCompilerType},
     dbg_utils_messenger::DebugUtilsMessengerEXT,
     descriptor_set::{split_descriptor_sets, DescriptorSet},
     fence::Fence,
     frame::{Frame, FrameCanvasIndex},
     frame_collection::FrameCollector,
+    frame_pacer::FramePacer,
     frame_resources::{
         FrameResources, FrameResourcesPool, RenderThreadFrameResources,
         RenderThreadFrameResourcesPool,
     },
-    image::Image,
+    image::{Image, ImageLayout},
     instance::Instance,
     logical_device::LogicalDevice,
     mapped_memory::MappedMemory,
@@ -95,17 +104,23 @@ use super::{
     render_group::{CanvasMode, OffscreenCanvasCreateProps, RenderSetup},
     render_pass::{CompileThreadpools, CompileThreadpoolsRef},
     render_setup::RenderSetupNativeType,
-    stream_memory_pool::{StreamMemoryBlock, StreamMemoryPool},
+    stream_memory_pool::{StreamMemoryBlock, StreamMemoryPool, StreamMemoryPoolOccupancy},
     swapchain::Swapchain,
+    utils::{
+        buffer_update_copies, buffer_update_copy, frame_fetch_copies, pack_image_rows,
+        texture_array_layer_extent,
+    },
     vulkan_allocator::{
         VulkanAllocator, VulkanAllocatorImageCacheEntryData, VulkanDeviceInternalMemory,
     },
     vulkan_dbg::is_verbose,
     vulkan_device::Device,
+    vulkan_mem::{AllocationError, ImageAllocationError},
     vulkan_types::{
-        DescriptorPoolType, DeviceDescriptorPools, MemoryBlockType, RenderPassSubType,
-        RenderPassType, RenderThread, RenderThreadEvent, StreamedUniformBuffer, TextureData,
-        TextureObject, ThreadCommandGroup,
+        DescriptorPoolType, DeviceDescriptorPools, DynamicViewport, FrameGraphGroup,
+        MemoryBlockType, PendingTextureUpload, RenderPassSubType, RenderPassType, RenderThread,
+        RenderThreadEvent, StreamedUniformBuffer, TextureData, TextureObject, ThreadCommandGroup,
+        MAX_PENDING_TEXTURE_UPLOADS,
     },
     Options,
 };
@@ -143,6 +158,46 @@ impl VulkanBackendLoadingIo {
             pipeline_cache,
         }
     }
+
+    /// The names of the tasks that are still running.
+    pub fn pending_tasks(&self) -> Vec<&'static str> {
+        [
+            ("shader_compiler", self.shader_compiler.is_finished()),
+            ("pipeline_cache", self.pipeline_cache.is_finished()),
+        ]
+        .into_iter()
+        .filter_map(|(name, finished)| (!finished).then_some(name))
+        .collect()
+    }
+
+    /// Stops the tasks that are still running, e.g. because loading was aborted.
+    /// Cancelled tasks return an error when their result is requested.
+    pub fn cancel(&mut self) -> VulkanBackendLoadingIoCancel {
+        let state = |cancelled| {
+            if cancelled {
+                LoadingTaskCancel::Cancelled
+            } else {
+                LoadingTaskCancel::AlreadyCompleted
+            }
+        };
+        VulkanBackendLoadingIoCancel {
+            shader_compiler: state(self.shader_compiler.cancel()),
+            pipeline_cache: state(self.pipeline_cache.cancel()),
+        }
+    }
+}
+
+/// What [`VulkanBackendLoadingIo::cancel`] did with a task.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LoadingTaskCancel {
+    Cancelled,
+    AlreadyCompleted,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VulkanBackendLoadingIoCancel {
+    pub shader_compiler: LoadingTaskCancel,
+    pub pipeline_cache: LoadingTaskCancel,
 }
 
 #[derive(Hiarc)]
@@ -177,7 +232,59 @@ pub struct VulkanFetchFramebuffer {
     get_presented_img_data_helper_mapped_layout_pitch: vk::DeviceSize,
     get_presented_img_data_helper_width: u32,
     get_presented_img_data_helper_height: u32,
-    get_presented_img_data_helper_fence: Arc<Fence>,
+}
+
+/// A canvas image that was copied into a fetch frame buffer,
+/// see [`VulkanBackend::copy_presented_images_impl`].
+#[derive(Debug, Clone, Copy)]
+struct PresentedImageCopy {
+    /// Index into the fetch frame buffers.
+    slot: usize,
+    width: u32,
+    height: u32,
+    is_b8_g8_r8_a8: bool,
+}
+
+#[derive(Debug, Hiarc)]
+pub struct VulkanFetchDepthBuffer {
+    mem: Arc<DeviceMemoryBlock>,
+    buffer: Arc<Buffer>,
+    mapped_memory: Arc<MappedMemory>,
+    size: vk::DeviceSize,
+    fence: Arc<Fence>,
+}
+
+/// Size in bytes of a single texel of the depth aspect, when copied to a buffer.
+/// `None` if the format has no depth aspect.
+fn depth_texel_size(format: vk::Format) -> Option<usize> {
+    match format {
+        vk::Format::D32_SFLOAT_S8_UINT | vk::Format::D24_UNORM_S8_UINT => Some(4),
+        vk::Format::D16_UNORM_S8_UINT => Some(2),
+        _ => None,
+    }
+}
+
+/// Normalizes a depth texel of the given format to `0.0..=1.0`.
+fn normalize_depth_texel(format: vk::Format, texel: &[u8]) -> f32 {
+    match format {
+        vk::Format::D32_SFLOAT_S8_UINT => {
+            let depth = f32::from_ne_bytes([texel[0], texel[1], texel[2], texel[3]]);
+            if depth.is_nan() {
+                0.0
+            } else {
+                depth.clamp(0.0, 1.0)
+            }
+        }
+        vk::Format::D24_UNORM_S8_UINT => {
+            // the upper 8 bits are undefined
+            let depth = u32::from_ne_bytes([texel[0], texel[1], texel[2], texel[3]]) & 0x00FF_FFFF;
+            depth as f32 / 0x00FF_FFFF as f32
+        }
+        vk::Format::D16_UNORM_S8_UINT => {
+            u16::from_ne_bytes([texel[0], texel[1]]) as f32 / u16::MAX as f32
+        }
+        _ => 0.0,
+    }
 }
 
 #[derive(Debug, Hiarc)]
@@ -185,22 +292,35 @@ pub(crate) struct VulkanCustomPipes {
     #[hiarc_skip_unsafe]
     pub(crate) pipes: CustomPipelines,
 
-    pub(crate) pipe_indices: HashMap<String, usize>,
+    #[hiarc_skip_unsafe]
+    pub(crate) pipe_indices: parking_lot::RwLock<HashMap<String, usize>>,
 }
 
 impl VulkanCustomPipes {
+    fn pipe_indices_of(pipes: &[Box<dyn BackendCustomPipeline>]) -> HashMap<String, usize> {
+        pipes
+            .iter()
+            .enumerate()
+            .map(|(index, pipe)| (pipe.pipe_name(), index))
+            .collect()
+    }
+
     pub fn new(pipes: CustomPipelines) -> Arc<Self> {
-        let mut pipe_indices: HashMap<String, usize> = Default::default();
-        let pipes_guard = pipes.read();
-        for (index, pipe) in pipes_guard.iter().enumerate() {
-            pipe_indices.insert(pipe.pipe_name(), index);
-        }
-        drop(pipes_guard);
+        let pipe_indices = Self::pipe_indices_of(&pipes.read());
         Arc::new(Self {
             pipes,
-            pipe_indices,
+            pipe_indices: parking_lot::RwLock::new(pipe_indices),
         })
     }
+
+    /// Rebuilds the name lookup after pipes were added to the shared pipe list.
+    pub fn update_pipe_indices(&self) {
+        *self.pipe_indices.write() = Self::pipe_indices_of(&self.pipes.read());
+    }
+
+    pub fn pipe_index(&self, name: &str) -> Option<usize> {
+        self.pipe_indices.read().get(name).copied()
+    }
 }
 
 #[derive(Debug, Hiarc)]
@@ -211,6 +331,8 @@ pub(crate) struct VulkanBackendProps {
     #[hiarc_skip_unsafe]
     dbg: Arc<AtomicGfxDebugModes>,
     gfx_vsync: bool,
+    surface_lost_retries: u32,
+    max_frames_in_flight: u32,
 
     thread_count: usize,
 
@@ -229,6 +351,13 @@ pub(crate) struct VulkanBackendProps {
 
     uniform_buffer_descr_pools: Arc<parking_lot::Mutex<DeviceDescriptorPools>>,
 
+    /// Mirrors the length of [`VulkanBackend::pending_texture_uploads`]
+    /// for diagnostics outside of the backend thread.
+    pending_texture_uploads_count: Arc<AtomicU64>,
+    /// Mirrors [`RenderSetup::pipelines_pending_count`]
+    /// for diagnostics outside of the backend thread.
+    pending_pipeline_compiles_count: Arc<AtomicUsize>,
+
     /************************
      * ERROR MANAGEMENT
      ************************/
@@ -259,6 +388,92 @@ pub struct VulkanBackendLoading {
     props: VulkanBackendProps,
 }
 
+/// The errors of [`VulkanBackend::try_next_frame`].
+#[derive(Debug, Error)]
+pub enum TryFrameError {
+    /// The gpu did not finish the previous frames yet.
+    /// Nothing was submitted, the current frame can still be recorded.
+    #[error("the gpu did not finish the previous frames yet.")]
+    WouldBlock,
+    #[error("{0}")]
+    Frame(anyhow::Error),
+}
+
+/// The context of errors that a render thread caused, e.g. a failed render command
+/// or a panic. The backend itself is still usable, the render thread can be replaced
+/// by [`VulkanBackend::restart_render_thread`].
+#[derive(Debug, Error)]
+#[error("render thread {0} failed")]
+pub struct RenderThreadError(pub usize);
+
+/// The waits between the attempts of [`VulkanBackend::retry_with_backoff`].
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct RetryBackoff {
+    /// The wait before the first retry, every following wait is twice as long.
+    pub initial: Duration,
+    pub max_delay: Duration,
+    /// The maximum of all waits together.
+    pub max_total: Duration,
+}
+
+impl RetryBackoff {
+    /// The waits before the retries of `attempts` attempts.
+    /// Retries whose waits would exceed [`Self::max_total`] are dropped.
+    pub(crate) fn delays(&self, attempts: u32) -> Vec<Duration> {
+        let mut total = Duration::ZERO;
+        (1..attempts)
+            .map(|retry| {
+                self.initial
+                    .saturating_mul(2u32.saturating_pow(retry - 1))
+                    .min(self.max_delay)
+            })
+            .take_while(|delay| {
+                total += *delay;
+                total <= self.max_total
+            })
+            .collect()
+    }
+}
+
+/// Even at the maximum of `surface_lost_retries` the render thread waits for at most a second.
+const SURFACE_LOST_BACKOFF: RetryBackoff = RetryBackoff {
+    initial: Duration::from_millis(8),
+    max_delay: Duration::from_millis(250),
+    max_total: Duration::from_secs(1),
+};
+
+/// A frame that was submitted by [`VulkanBackend::try_next_frame`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FramePresented {
+    /// The frame that is recorded next.
+    pub frame: u64,
+}
+
+/// The user data of [`VulkanBackendLoading::vk_debug_callback`].
+#[derive(Debug)]
+pub(crate) struct VulkanDebugCallbackOptions {
+    /// Panic on validation errors, instead of only logging them.
+    panic_on_error: bool,
+}
+
+impl VulkanDebugCallbackOptions {
+    /// The callback outlives any borrow, so both options are statics.
+    pub(crate) fn user_data(panic_on_error: bool) -> *mut c_void {
+        static PANIC_ON_ERROR: VulkanDebugCallbackOptions = VulkanDebugCallbackOptions {
+            panic_on_error: true,
+        };
+        static LOG_ON_ERROR: VulkanDebugCallbackOptions = VulkanDebugCallbackOptions {
+            panic_on_error: false,
+        };
+        let options = if panic_on_error {
+            &PANIC_ON_ERROR
+        } else {
+            &LOG_ON_ERROR
+        };
+        options as *const VulkanDebugCallbackOptions as *mut c_void
+    }
+}
+
 type InitNativeResult = (
     Arc<LogicalDevice>,
     Arc<PhyDevice>,
@@ -273,38 +488,32 @@ type ArcRwLock<T> = Arc<parking_lot::RwLock<T>>;
 type InitialIndexBuffer = ((Arc<Buffer>, Arc<DeviceMemoryBlock>), usize);
 
 impl VulkanBackendLoading {
-    unsafe extern "system" fn vk_debug_callback(
+    /// `ptr_raw_user` points to the [`VulkanDebugCallbackOptions`],
+    /// if it's null, errors panic.
+    pub(crate) unsafe extern "system" fn vk_debug_callback(
         message_severity: vk::DebugUtilsMessageSeverityFlagsEXT,
         message_type: vk::DebugUtilsMessageTypeFlagsEXT,
         ptr_callback_data: *const vk::DebugUtilsMessengerCallbackDataEXT,
-        _ptr_raw_user: *mut c_void,
+        ptr_raw_user: *mut c_void,
     ) -> vk::Bool32 {
+        let msg = unsafe {
+            CStr::from_ptr((*ptr_callback_data).p_message)
+                .to_str()
+                .unwrap()
+        };
         if message_severity.contains(vk::DebugUtilsMessageSeverityFlagsEXT::ERROR) {
-            let msg = unsafe {
-                CStr::from_ptr((*ptr_callback_data).p_message)
-                    .to_str()
-                    .unwrap()
-            };
-            println!("{msg}");
-            panic!("[vulkan debug] error: {msg} {message_severity:?} {message_type:?}");
+            let options = unsafe { (ptr_raw_user as *const VulkanDebugCallbackOptions).as_ref() };
+            if options.is_none_or(|options| options.panic_on_error) {
+                panic!("[vulkan debug] error: {msg} {message_severity:?} {message_type:?}");
+            } else {
+                log::error!("[vulkan debug] error: {msg} {message_severity:?} {message_type:?}");
+            }
         } else if message_type.contains(vk::DebugUtilsMessageTypeFlagsEXT::PERFORMANCE) {
-            println!(
-                "[vulkan debug] performance: {} {message_severity:?} {message_type:?}",
-                unsafe {
-                    CStr::from_ptr((*ptr_callback_data).p_message)
-                        .to_str()
-                        .unwrap()
-                }
-            );
+            log::warn!("[vulkan debug] performance: {msg} {message_severity:?} {message_type:?}");
+        } else if message_severity.contains(vk::DebugUtilsMessageSeverityFlagsEXT::WARNING) {
+            log::warn!("[vulkan debug]: {msg} {message_severity:?} {message_type:?}");
         } else {
-            println!(
-                "[vulkan debug]: {} {message_severity:?} {message_type:?}",
-                unsafe {
-                    CStr::from_ptr((*ptr_callback_data).p_message)
-                        .to_str()
-                        .unwrap()
-                }
-            );
+            log::info!("[vulkan debug]: {msg} {message_severity:?} {message_type:?}");
         }
 
         vk::FALSE
@@ -313,6 +522,7 @@ impl VulkanBackendLoading {
     fn setup_debug_callback(
         entry: &ash::Entry,
         instance: &ash::Instance,
+        panic_on_error: bool,
     ) -> anyhow::Result<Arc<DebugUtilsMessengerEXT>> {
         let mut create_info = vk::DebugUtilsMessengerCreateInfoEXT::default();
         create_info.message_severity = vk::DebugUtilsMessageSeverityFlagsEXT::VERBOSE
@@ -321,6 +531,7 @@ impl VulkanBackendLoading {
         create_info.message_type = vk::DebugUtilsMessageTypeFlagsEXT::VALIDATION
             | vk::DebugUtilsMessageTypeFlagsEXT::PERFORMANCE; // | vk::DebugUtilsMessageTypeFlagsEXT::GENERAL <- too annoying
         create_info.pfn_user_callback = Some(Self::vk_debug_callback);
+        create_info.p_user_data = VulkanDebugCallbackOptions::user_data(panic_on_error);
 
         let res_dbg = DebugUtilsMessengerEXT::new(entry, instance, &create_info)
             .map_err(|err| anyhow!("Debug extension could not be loaded: {err}"))?;
@@ -355,7 +566,11 @@ impl VulkanBackendLoading {
 
         let mut dbg_callback = None;
         if dbg_mode == GfxDebugModes::Minimum || dbg_mode == GfxDebugModes::All {
-            let dbg_res = Self::setup_debug_callback(&instance.vk_entry, &instance.vk_instance);
+            let dbg_res = Self::setup_debug_callback(
+                &instance.vk_entry,
+                &instance.vk_instance,
+                !options.dbg.gfx_log_validation_errors,
+            );
             if let Ok(dbg) = dbg_res {
                 dbg_callback = Some(dbg);
             }
@@ -364,6 +579,10 @@ impl VulkanBackendLoading {
         let physical_gpu =
             PhyDevice::new(instance.clone(), options, display_requirements.is_headless)?;
         benchmark.bench("selecting vk physical device");
+        benchmark.label(&format!(
+            "vk physical device: {} ({})",
+            physical_gpu.fingerprint, physical_gpu.renderer_name
+        ));
 
         let device = LogicalDevice::new(
             physical_gpu.clone(),
@@ -419,6 +638,8 @@ impl VulkanBackendLoading {
         buffer_memory_usage: Arc<AtomicU64>,
         stream_memory_usage: Arc<AtomicU64>,
         staging_memory_usage: Arc<AtomicU64>,
+        pending_texture_uploads: Arc<AtomicU64>,
+        pending_pipeline_compiles: Arc<AtomicUsize>,
 
         options: &Options,
 
@@ -466,6 +687,8 @@ impl VulkanBackendLoading {
             props: VulkanBackendProps {
                 dbg: dbg.clone(),
                 gfx_vsync: options.gl.vsync,
+                surface_lost_retries: options.gl.surface_lost_retries,
+                max_frames_in_flight: options.gl.max_frames_in_flight,
                 thread_count,
 
                 graphics_uniform_buffers: MtPool::with_capacity(
@@ -491,6 +714,9 @@ impl VulkanBackendLoading {
                     DescriptorPoolType::Uniform,
                 )?,
 
+                pending_texture_uploads_count: pending_texture_uploads,
+                pending_pipeline_compiles_count: pending_pipeline_compiles,
+
                 custom_pipes: VulkanCustomPipes::new(custom_pipes.unwrap_or_default()),
             },
         };
@@ -536,11 +762,11 @@ pub struct VulkanBackend {
 
     last_render_thread_index: usize,
     recreate_swap_chain: bool,
-    pub(crate) has_dynamic_viewport: bool,
-    #[hiarc_skip_unsafe]
-    pub(crate) dynamic_viewport_offset: vk::Offset2D,
-    #[hiarc_skip_unsafe]
-    pub(crate) dynamic_viewport_size: vk::Extent2D,
+    /// See [`VulkanBackend::set_render_paused`].
+    render_paused: bool,
+    /// Every canvas keeps its own viewport,
+    /// see [`VulkanBackend::dynamic_viewport`].
+    dynamic_viewports: HashMap<FrameCanvasIndex, DynamicViewport>,
     cur_render_cmds_count_in_pipe: usize,
 
     commands_in_pipe: usize,
@@ -552,7 +778,10 @@ pub struct VulkanBackend {
     cur_frame: u64,
     image_last_frame_check: Vec<u64>,
 
-    fetch_frame_buffer: Option<VulkanFetchFramebuffer>,
+    /// One helper image per canvas that is fetched in the same frame.
+    fetch_frame_buffers: Vec<VulkanFetchFramebuffer>,
+    fetch_frame_fence: Option<Arc<Fence>>,
+    fetch_depth_buffer: Option<VulkanFetchDepthBuffer>,
     last_presented_swap_chain_image_index: u32,
     #[hiarc_skip_unsafe]
     frame_fetchers: FxLinkedHashMap<String, Arc<dyn BackendFrameFetcher>>,
@@ -560,6 +789,19 @@ pub struct VulkanBackend {
     /// Offscreen canvases that asked to be skiped this frame,
     /// e.g. because they couldn't render.
     offscreen_canvases_frame_fetching_skips: HashSet<OffscreenCanvasId>,
+    /// Offscreen canvases that follow the window's size,
+    /// see [`CommandOffscreenCanvasCreate::relative_size`].
+    relative_offscreen_canvases: HashMap<OffscreenCanvasId, NonZeroU32>,
+    /// Whether commands ran since the last frame was submitted,
+    /// see [`Self::flush_and_drain`].
+    has_unsubmitted_work: bool,
+    frame_pacer: FramePacer,
+    /// The stats of the frame that is currently recorded.
+    cur_frame_stats: BackendFrameStats,
+    /// The render passes that recorded commands in the current frame.
+    cur_frame_render_passes: HashSet<(FrameCanvasIndex, usize)>,
+    last_frame_stats: BackendFrameStats,
+    wait_stats: BackendWaitStats,
 
     render_threads: Vec<Arc<RenderThread>>,
     pub(crate) render: RenderSetup,
@@ -570,9 +812,18 @@ pub struct VulkanBackend {
 
     window_width: u32,
     window_height: u32,
+    /// The factor between physical pixels and logical window units.
+    content_scale: f64,
 
     pub(crate) clear_color: [f32; 4],
 
+    /// Texture uploads that ran out of staging memory,
+    /// processed in order over the next frames.
+    pending_texture_uploads: VecDeque<PendingTextureUpload>,
+    /// The image indices of the submitted frames, oldest first,
+    /// only tracked if `max_frames_in_flight` limits them.
+    frames_in_flight: VecDeque<u32>,
+
     pub(crate) current_command_groups: HashMap<FrameCanvasIndex, ThreadCommandGroup>,
     command_groups: Vec<ThreadCommandGroup>,
     pub(crate) current_frame_resources: FrameResources,
@@ -602,11 +853,34 @@ impl VulkanBackend {
         for _ in 0..self.render.onscreen.swap_chain_image_count() + 1 {
             self.next_frame()?;
         }
+        self.wait_stats.staging_memory_waits += 1;
+        self.props
+            .device
+            .mem_allocator
+            .lock()
+            .allow_staging_over_budget();
 
         Ok(())
     }
 
     fn uniform_stream_alloc_func(&mut self, count: usize) -> anyhow::Result<()> {
+        let alloc_amount = self.streamed_uniform_buffers_pool.alloc_amount(count);
+        if alloc_amount == 0 {
+            return Ok(());
+        }
+        {
+            // every instance needs two descriptor sets, grow the descriptor pools in line,
+            // so a single descriptor pool can hold all new sets
+            let mut pools = self.props.uniform_buffer_descr_pools.lock();
+            pools.default_alloc_size = pools
+                .default_alloc_size
+                .max(alloc_amount as vk::DeviceSize * 2);
+        }
+        log::debug!(
+            "growing the stream uniform instance pool by {alloc_amount} instances: {:?}",
+            self.streamed_uniform_buffers_pool.occupancy()
+        );
+
         let device = &self.props.ash_vk.vk_device;
         let pools = &mut self.props.uniform_buffer_descr_pools;
         let sprite_descr_layout = &self
@@ -669,10 +943,17 @@ impl VulkanBackend {
      * COMMAND CALLBACKS
      ************************/
     fn command_cb_misc(&mut self, cmd_param: CommandsMisc) -> anyhow::Result<()> {
+        match &cmd_param {
+            CommandsMisc::TextureUpdate(_) => self.cur_frame_stats.texture_updates += 1,
+            CommandsMisc::UpdateBufferObject(_) | CommandsMisc::UpdateShaderStorage(_) => {
+                self.cur_frame_stats.buffer_updates += 1
+            }
+            _ => {}
+        }
         match cmd_param {
             CommandsMisc::TextureCreate(cmd) => self.cmd_texture_create(cmd),
             CommandsMisc::TextureDestroy(cmd) => self.cmd_texture_destroy(&cmd),
-            CommandsMisc::TextureUpdate(cmd) => self.cmd_texture_update(&cmd),
+            CommandsMisc::TextureUpdate(cmd) => self.cmd_texture_update(cmd),
             CommandsMisc::CreateBufferObject(cmd) => self.cmd_create_buffer_object(cmd),
             CommandsMisc::RecreateBufferObject(cmd) => self.cmd_recreate_buffer_object(cmd),
             CommandsMisc::UpdateBufferObject(cmd) => self.cmd_update_buffer_object(cmd),
@@ -682,6 +963,7 @@ impl VulkanBackend {
             CommandsMisc::DeleteShaderStorage(cmd) => self.cmd_delete_shader_storage(&cmd),
             CommandsMisc::OffscreenCanvasCreate(cmd) => self.cmd_create_offscreen_canvas(&cmd),
             CommandsMisc::OffscreenCanvasDestroy(cmd) => self.cmd_destroy_offscreen_canvas(&cmd),
+            CommandsMisc::OffscreenCanvasResize(cmd) => self.cmd_resize_offscreen_canvas(&cmd),
             CommandsMisc::OffscreenCanvasSkipFetchingOnce(cmd) => {
                 self.cmd_skip_fetching_offscreen_canvas(&cmd)
             }
@@ -695,6 +977,8 @@ impl VulkanBackend {
             CommandsMisc::UpdateViewport(cmd) => self.cmd_update_viewport(&cmd),
             CommandsMisc::Multisampling(cmd) => self.cmd_mutli_sampling(cmd),
             CommandsMisc::VSync(cmd) => self.cmd_vsync(cmd),
+            CommandsMisc::SetPolygonMode(mode) => self.cmd_set_polygon_mode(mode),
+            CommandsMisc::DumpFrameGraph => self.cmd_dump_frame_graph(),
         }
     }
 
@@ -735,8 +1019,7 @@ impl VulkanBackend {
                     .backend
                     .props
                     .custom_pipes
-                    .pipe_indices
-                    .get(mod_name.as_str())
+                    .pipe_index(mod_name.as_str())
                 {
                     let pipes = render_execute_manager
                         .backend
@@ -744,7 +1027,7 @@ impl VulkanBackend {
                         .custom_pipes
                         .pipes
                         .clone();
-                    pipes.read()[*mod_index].fill_exec_buffer(cmd, &mut render_execute_manager);
+                    pipes.read()[mod_index].fill_exec_buffer(cmd, &mut render_execute_manager);
                 }
             }
         }
@@ -753,30 +1036,24 @@ impl VulkanBackend {
     /*****************************
      * VIDEO AND SCREENSHOT HELPER
      ******************************/
+    /// Makes sure the fetch frame buffer `slot` exists and has the given size.
+    /// Slots are used in order, so `slot` is at most the current slot count.
     fn prepare_presented_image_data_image(
         &mut self,
-        res_image_data: &mut &mut [u8],
+        slot: usize,
         width: u32,
         height: u32,
     ) -> anyhow::Result<()> {
-        let needs_new_img: bool = self.fetch_frame_buffer.is_none()
-            || width
-                != self
-                    .fetch_frame_buffer
-                    .as_ref()
-                    .unwrap()
-                    .get_presented_img_data_helper_width
-            || height
-                != self
-                    .fetch_frame_buffer
-                    .as_ref()
-                    .unwrap()
-                    .get_presented_img_data_helper_height;
+        debug_assert!(slot <= self.fetch_frame_buffers.len());
+        let needs_new_img: bool =
+            !self
+                .fetch_frame_buffers
+                .get(slot)
+                .is_some_and(|fetch_frame_buffer| {
+                    width == fetch_frame_buffer.get_presented_img_data_helper_width
+                        && height == fetch_frame_buffer.get_presented_img_data_helper_height
+                });
         if needs_new_img {
-            if self.fetch_frame_buffer.is_some() {
-                self.delete_presented_image_data_image();
-            }
-
             let mut image_info = vk::ImageCreateInfo::default();
             image_info.image_type = vk::ImageType::TYPE_2D;
             image_info.extent.width = width;
@@ -845,7 +1122,7 @@ impl VulkanBackend {
                     )
             };
 
-            self.fetch_frame_buffer = Some(VulkanFetchFramebuffer {
+            let fetch_frame_buffer = VulkanFetchFramebuffer {
                 get_presented_img_data_helper_mapped_memory: MappedMemory::new(
                     self.props.ash_vk.vk_device.clone(),
                     presented_img_data_helper_mem.clone(),
@@ -853,50 +1130,33 @@ impl VulkanBackend {
                 )?,
                 get_presented_img_data_helper_mapped_layout_offset: sub_resource_layout.offset,
                 get_presented_img_data_helper_mapped_layout_pitch: sub_resource_layout.row_pitch,
-                get_presented_img_data_helper_fence: Fence::new(
-                    self.props.ash_vk.vk_device.clone(),
-                )?,
                 get_presented_img_data_helper_width: width,
                 get_presented_img_data_helper_height: height,
                 get_presented_img_data_helper_image: presented_img_data_helper_image,
                 get_presented_img_data_helper_mem: presented_img_data_helper_mem,
-            });
+            };
+            if slot < self.fetch_frame_buffers.len() {
+                self.fetch_frame_buffers[slot] = fetch_frame_buffer;
+            } else {
+                self.fetch_frame_buffers.push(fetch_frame_buffer);
+            }
         }
-        *res_image_data = unsafe {
-            std::slice::from_raw_parts_mut(
-                self.fetch_frame_buffer
-                    .as_ref()
-                    .ok_or_else(|| anyhow!("copy image mapped mem was empty"))?
-                    .get_presented_img_data_helper_mapped_memory
-                    .get_mem(),
-                self.fetch_frame_buffer
-                    .as_ref()
-                    .ok_or_else(|| anyhow!("copy image mem was empty"))?
-                    .get_presented_img_data_helper_mem
-                    .as_ref()
-                    .size() as usize
-                    - self
-                        .fetch_frame_buffer
-                        .as_ref()
-                        .ok_or_else(|| anyhow!("copy image offset was empty"))?
-                        .get_presented_img_data_helper_mapped_layout_offset
-                        as usize,
-            )
-        };
         Ok(())
     }
 
     fn delete_presented_image_data_image(&mut self) {
-        self.fetch_frame_buffer = None;
+        self.fetch_frame_buffers.clear();
+        self.fetch_frame_fence = None;
     }
 
-    fn get_presented_image_data_impl(
+    /// Records the copy of the last presented image of the canvas
+    /// into the fetch frame buffer `slot`.
+    /// The copy is only submitted by [`Self::submit_presented_image_copies`].
+    fn record_presented_image_copy(
         &mut self,
+        slot: usize,
         fetch_index: FetchCanvasIndex,
-    ) -> anyhow::Result<BackendPresentedImageDataRgba, FetchCanvasError> {
-        let width: u32;
-        let height: u32;
-        let mut dest_data_buff = self.frame_data_pool.new();
+    ) -> anyhow::Result<PresentedImageCopy, FetchCanvasError> {
         let (render, final_layout) = match fetch_index {
             FetchCanvasIndex::Onscreen => (
                 &self.render.onscreen,
@@ -915,13 +1175,10 @@ impl VulkanBackend {
             render.surf_format.format == vk::Format::R8G8B8A8_UNORM || is_b8_g8_r8_a8;
         if uses_rgba_like_format && self.last_presented_swap_chain_image_index != u32::MAX {
             let viewport = render.native.swap_img_and_viewport_extent;
-            width = viewport.width;
-            height = viewport.height;
+            let width = viewport.width;
+            let height = viewport.height;
 
-            let image_total_size: usize = width as usize * height as usize * 4;
-
-            let mut res_image_data: &mut [u8] = &mut [];
-            self.prepare_presented_image_data_image(&mut res_image_data, width, height)
+            self.prepare_presented_image_data_image(slot, width, height)
                 .map_err(|err| anyhow!("Could not prepare presented image data: {err}"))?;
 
             let render = match fetch_index {
@@ -929,10 +1186,7 @@ impl VulkanBackend {
                 FetchCanvasIndex::Offscreen(id) => self.render.offscreens.get(&id).unwrap(),
             };
 
-            let fetch_frame_buffer = self
-                .fetch_frame_buffer
-                .as_ref()
-                .ok_or_else(|| anyhow!("fetch resources were none"))?;
+            let fetch_frame_buffer = &self.fetch_frame_buffers[slot];
 
             let command_buffer = self
                 .props
@@ -1074,216 +1328,554 @@ impl VulkanBackend {
                 )
                 .map_err(|err| anyhow!("Image barrier failed for the swap chain image: {err}"))?;
 
-            self.props.device.memory_command_buffer = None;
-
-            let command_buffers = [command_buffer];
-            let submit_info = vk::SubmitInfo::default().command_buffers(&command_buffers);
-
-            unsafe {
-                self.props
-                    .ash_vk
-                    .vk_device
-                    .device
-                    .reset_fences(&[fetch_frame_buffer
-                        .get_presented_img_data_helper_fence
-                        .fence(&mut self.current_frame_resources)])
-            }
-            .map_err(|err| anyhow!("Could not reset fences: {err}"))?;
-            unsafe {
-                let queue = &self.props.queue.queues.lock();
-                self.props.ash_vk.vk_device.device.queue_submit(
-                    queue.graphics_queue,
-                    &[submit_info],
-                    fetch_frame_buffer
-                        .get_presented_img_data_helper_fence
-                        .fence(&mut self.current_frame_resources),
-                )
-            }
-            .map_err(|err| anyhow!("Queue submit failed: {err}"))?;
-            unsafe {
-                self.props.ash_vk.vk_device.device.wait_for_fences(
-                    &[fetch_frame_buffer
-                        .get_presented_img_data_helper_fence
-                        .fence(&mut self.current_frame_resources)],
-                    true,
-                    u64::MAX,
-                )
-            }
-            .map_err(|err| anyhow!("Could not wait for fences: {err}"))?;
-
-            let mut mem_range = vk::MappedMemoryRange::default();
-            mem_range.memory = fetch_frame_buffer
-                .get_presented_img_data_helper_mem
-                .mem(&mut FrameResources::new(None));
-            mem_range.offset =
-                fetch_frame_buffer.get_presented_img_data_helper_mapped_layout_offset;
-            mem_range.size = vk::WHOLE_SIZE;
-            unsafe {
-                self.props
-                    .ash_vk
-                    .vk_device
-                    .device
-                    .invalidate_mapped_memory_ranges(&[mem_range])
-            }
-            .map_err(|err| anyhow!("Could not invalidate mapped memory ranges: {err}"))?;
-
-            let real_full_image_size: usize = image_total_size.max(
-                height as usize
-                    * fetch_frame_buffer.get_presented_img_data_helper_mapped_layout_pitch as usize,
-            );
-            if dest_data_buff.len() < real_full_image_size + (width * 4) as usize {
-                dest_data_buff.resize(
-                    real_full_image_size + (width * 4) as usize,
-                    Default::default(),
-                ); // extra space for flipping
-            }
-            let dst_buff = dest_data_buff
-                .as_mut_slice()
-                .split_at_mut(real_full_image_size)
-                .0;
-            let src_buff = res_image_data.split_at(real_full_image_size).0;
-            dst_buff.copy_from_slice(src_buff);
-
-            // pack image data together without any offset
-            // that the driver might require
-            if width as u64 * 4
-                < fetch_frame_buffer.get_presented_img_data_helper_mapped_layout_pitch
-            {
-                for y in 0..height as usize {
-                    let offset_image_packed: usize = y * width as usize * 4;
-                    let offset_image_unpacked: usize = y * fetch_frame_buffer
-                        .get_presented_img_data_helper_mapped_layout_pitch
-                        as usize;
-
-                    let (img_part, help_part) = dest_data_buff
-                        .as_mut_slice()
-                        .split_at_mut(real_full_image_size);
-
-                    let unpacked_part = img_part.split_at(offset_image_unpacked).1;
-                    help_part[..width as usize * 4]
-                        .copy_from_slice(&unpacked_part[..width as usize * 4]);
-
-                    let packed_part = img_part.split_at_mut(offset_image_packed).1;
-                    packed_part[..width as usize * 4]
-                        .copy_from_slice(&help_part[..width as usize * 4]);
-                }
-            }
-
-            if is_b8_g8_r8_a8 {
-                // swizzle
-                for y in 0..height as usize {
-                    for x in 0..width as usize {
-                        let img_off: usize = (y * width as usize * 4) + (x * 4);
-                        if is_b8_g8_r8_a8 {
-                            let tmp = dest_data_buff[img_off];
-                            dest_data_buff[img_off] = dest_data_buff[img_off + 2];
-                            dest_data_buff[img_off + 2] = tmp;
-                        }
-                        dest_data_buff[img_off + 3] = 255;
-                    }
-                }
-            }
-
-            dest_data_buff.resize(width as usize * height as usize * 4, Default::default());
-
-            Ok(BackendPresentedImageDataRgba {
+            Ok(PresentedImageCopy {
+                slot,
                 width,
                 height,
-                dest_data_buffer: dest_data_buff,
+                is_b8_g8_r8_a8,
             })
         } else if !uses_rgba_like_format {
             Err(FetchCanvasError::DriverErr("Swap chain image was not ready to be copied, because it was not in a RGBA like format.".to_string()))
         } else {
-            Err(FetchCanvasError::DriverErr(
-                "Swap chain image was not ready to be copied.".to_string(),
-            ))
+            Err(FetchCanvasError::NotPresentedYet)
         }
     }
 
-    /************************
-     * SWAPPING MECHANISM
-     ************************/
-    fn start_render_thread(&mut self, thread_index: usize) -> anyhow::Result<()> {
-        if !self.command_groups.is_empty() {
-            let thread = &mut self.render_threads[thread_index];
-            for command_group in self.command_groups.drain(..) {
-                let render = self.render.get_of_frame(command_group.canvas_index).clone();
-                thread
-                    .events
-                    .fetch_add(1, std::sync::atomic::Ordering::SeqCst);
-                thread
-                    .sender
-                    .send(RenderThreadEvent::Render((command_group, render)))?;
-            }
+    /// Submits all recorded presented image copies at once and waits for them,
+    /// so that the first `slot_count` fetch frame buffers can be read.
+    fn submit_presented_image_copies(&mut self, slot_count: usize) -> anyhow::Result<()> {
+        let command_buffer = self
+            .props
+            .device
+            .memory_command_buffer
+            .take()
+            .ok_or_else(|| anyhow!("no presented image copy was recorded"))?;
+        let command_buffers = [command_buffer.command_buffer];
+        // ends the recording
+        drop(command_buffer);
+
+        if self.fetch_frame_fence.is_none() {
+            self.fetch_frame_fence = Some(Fence::new(self.props.ash_vk.vk_device.clone())?);
         }
-        Ok(())
-    }
+        let fence = self
+            .fetch_frame_fence
+            .as_ref()
+            .unwrap()
+            .fence(&mut self.current_frame_resources);
 
-    fn handle_all_command_groups(&mut self) -> anyhow::Result<()> {
-        // execute threads
-        let mut thread_index = self.last_render_thread_index;
-        while !self.command_groups.is_empty() {
-            self.start_render_thread(thread_index % self.props.thread_count)?;
-            thread_index += 1;
-        }
-        Ok(())
-    }
+        let submit_info = vk::SubmitInfo::default().command_buffers(&command_buffers);
 
-    fn finish_render_threads(&mut self) -> anyhow::Result<()> {
-        self.handle_all_command_groups()?;
+        unsafe { self.props.ash_vk.vk_device.device.reset_fences(&[fence]) }
+            .map_err(|err| anyhow!("Could not reset fences: {err}"))?;
+        unsafe {
+            let queue = &self.props.queue.queues.lock();
+            self.props.ash_vk.vk_device.device.queue_submit(
+                queue.graphics_queue,
+                &[submit_info],
+                fence,
+            )
+        }
+        .map_err(|err| anyhow!("Queue submit failed: {err}"))?;
+        self.wait_stats.frame_fetch_submits += 1;
+        unsafe {
+            self.props
+                .ash_vk
+                .vk_device
+                .device
+                .wait_for_fences(&[fence], true, u64::MAX)
+        }
+        .map_err(|err| anyhow!("Could not wait for fences: {err}"))?;
+        self.wait_stats.frame_fetch_fence_waits += 1;
 
-        for thread_index in 0..self.props.thread_count {
-            let render_thread = &mut self.render_threads[thread_index];
-            if render_thread
-                .events
-                .load(std::sync::atomic::Ordering::SeqCst)
-                != 0
-            {
-                let (sender, receiver) = bounded(1);
-                render_thread
-                    .events
-                    .fetch_add(1, std::sync::atomic::Ordering::SeqCst);
-                render_thread.sender.send(RenderThreadEvent::Sync(sender))?;
-                receiver.recv()?;
-            }
+        let mem_ranges: Vec<_> = self.fetch_frame_buffers[..slot_count]
+            .iter()
+            .map(|fetch_frame_buffer| {
+                vk::MappedMemoryRange::default()
+                    .memory(
+                        fetch_frame_buffer
+                            .get_presented_img_data_helper_mem
+                            .mem(&mut FrameResources::new(None)),
+                    )
+                    .offset(fetch_frame_buffer.get_presented_img_data_helper_mapped_layout_offset)
+                    .size(vk::WHOLE_SIZE)
+            })
+            .collect();
+        unsafe {
+            self.props
+                .ash_vk
+                .vk_device
+                .device
+                .invalidate_mapped_memory_ranges(&mem_ranges)
         }
+        .map_err(|err| anyhow!("Could not invalidate mapped memory ranges: {err}"))?;
+
         Ok(())
     }
 
-    fn execute_memory_command_buffer(&mut self) {
-        if let Some(memory_command_buffer) = self.props.device.memory_command_buffer.take() {
-            let command_buffer = memory_command_buffer.command_buffer;
-            drop(memory_command_buffer);
-
-            let command_buffers = [command_buffer];
-            let submit_info = vk::SubmitInfo::default().command_buffers(&command_buffers);
-            unsafe {
-                let queue = &self.props.queue.queues.lock();
-                self.props
-                    .ash_vk
-                    .vk_device
-                    .device
-                    .queue_submit(queue.graphics_queue, &[submit_info], vk::Fence::null())
-                    .unwrap();
+    /// Copies the last presented images of all canvases into the fetch frame buffers.
+    /// All copies share a single submit and fence wait.
+    /// The result of each canvas is at the same index as the canvas in `fetch_indices`.
+    fn copy_presented_images_impl(
+        &mut self,
+        fetch_indices: &[FetchCanvasIndex],
+    ) -> Vec<anyhow::Result<PresentedImageCopy, FetchCanvasError>> {
+        let mut slot_count = 0;
+        let mut copies = Vec::with_capacity(fetch_indices.len());
+        for &fetch_index in fetch_indices {
+            let copy = self.record_presented_image_copy(slot_count, fetch_index);
+            if copy.is_ok() {
+                slot_count += 1;
             }
-            unsafe {
-                let queue = &self.props.queue.queues.lock();
-                self.props
-                    .ash_vk
-                    .vk_device
-                    .device
-                    .queue_wait_idle(queue.graphics_queue)
-                    .unwrap();
+            copies.push(copy);
+        }
+        if slot_count > 0 {
+            if let Err(err) = self.submit_presented_image_copies(slot_count) {
+                for copy in copies.iter_mut().filter(|copy| copy.is_ok()) {
+                    *copy = Err(FetchCanvasError::DriverErr(err.to_string()));
+                }
             }
         }
+        copies
     }
 
-    fn flush_memory_ranges(&mut self) {
-        if !self.props.device.non_flushed_memory_ranges.is_empty() {
-            unsafe {
-                self.props
-                    .ash_vk
-                    .vk_device
+    /// Reads a submitted copy back into a tightly packed RGBA buffer.
+    fn read_presented_image_data(
+        &self,
+        copy: &PresentedImageCopy,
+        orientation: PresentedImageOrientation,
+    ) -> BackendPresentedImageDataRgba {
+        let PresentedImageCopy {
+            slot,
+            width,
+            height,
+            is_b8_g8_r8_a8,
+        } = *copy;
+        let fetch_frame_buffer = &self.fetch_frame_buffers[slot];
+        let mut dest_data_buff = self.frame_data_pool.new();
+
+        let image_total_size: usize = width as usize * height as usize * 4;
+
+        let res_image_data = unsafe {
+            std::slice::from_raw_parts(
+                fetch_frame_buffer
+                    .get_presented_img_data_helper_mapped_memory
+                    .get_mem(),
+                fetch_frame_buffer.get_presented_img_data_helper_mem.size() as usize
+                    - fetch_frame_buffer.get_presented_img_data_helper_mapped_layout_offset
+                        as usize,
+            )
+        };
+
+        let real_full_image_size: usize = image_total_size.max(
+            height as usize
+                * fetch_frame_buffer.get_presented_img_data_helper_mapped_layout_pitch as usize,
+        );
+        if dest_data_buff.len() < real_full_image_size {
+            dest_data_buff.resize(real_full_image_size, Default::default());
+        }
+        let dst_buff = dest_data_buff
+            .as_mut_slice()
+            .split_at_mut(real_full_image_size)
+            .0;
+        let src_buff = res_image_data.split_at(real_full_image_size).0;
+        dst_buff.copy_from_slice(src_buff);
+
+        // pack image data together without any offset
+        // that the driver might require
+        pack_image_rows(
+            dest_data_buff.as_mut_slice(),
+            width as usize * 4,
+            fetch_frame_buffer.get_presented_img_data_helper_mapped_layout_pitch as usize,
+            height as usize,
+            orientation,
+        );
+
+        if is_b8_g8_r8_a8 {
+            // swizzle
+            for y in 0..height as usize {
+                for x in 0..width as usize {
+                    let img_off: usize = (y * width as usize * 4) + (x * 4);
+                    if is_b8_g8_r8_a8 {
+                        let tmp = dest_data_buff[img_off];
+                        dest_data_buff[img_off] = dest_data_buff[img_off + 2];
+                        dest_data_buff[img_off + 2] = tmp;
+                    }
+                    dest_data_buff[img_off + 3] = 255;
+                }
+            }
+        }
+
+        dest_data_buff.resize(width as usize * height as usize * 4, Default::default());
+
+        BackendPresentedImageDataRgba {
+            width,
+            height,
+            orientation,
+            dest_data_buffer: dest_data_buff,
+        }
+    }
+
+    fn get_presented_image_data_impl(
+        &mut self,
+        fetch_index: FetchCanvasIndex,
+        orientation: PresentedImageOrientation,
+    ) -> anyhow::Result<BackendPresentedImageDataRgba, FetchCanvasError> {
+        let copy = self
+            .copy_presented_images_impl(&[fetch_index])
+            .pop()
+            .unwrap()?;
+        Ok(self.read_presented_image_data(&copy, orientation))
+    }
+
+    fn prepare_presented_depth_data_buffer(&mut self, size: vk::DeviceSize) -> anyhow::Result<()> {
+        if self
+            .fetch_depth_buffer
+            .as_ref()
+            .is_some_and(|fetch_depth_buffer| fetch_depth_buffer.size == size)
+        {
+            return Ok(());
+        }
+        self.fetch_depth_buffer = None;
+
+        let (buffer, mem) = self.props.device.mem.create_buffer(
+            size,
+            MemoryBlockType::Buffer,
+            vk::BufferUsageFlags::TRANSFER_DST,
+            vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_CACHED,
+        )?;
+
+        self.fetch_depth_buffer = Some(VulkanFetchDepthBuffer {
+            mapped_memory: MappedMemory::new(self.props.ash_vk.vk_device.clone(), mem.clone(), 0)?,
+            mem,
+            buffer,
+            size,
+            fence: Fence::new(self.props.ash_vk.vk_device.clone())?,
+        });
+        Ok(())
+    }
+
+    /// Copies the depth aspect of the canvas' depth/stencil attachment
+    /// into a linear helper buffer (one R32 or R16 value per pixel)
+    /// and normalizes it to `0.0..=1.0`.
+    fn get_presented_depth_data_impl(
+        &mut self,
+        fetch_index: FetchCanvasIndex,
+    ) -> anyhow::Result<BackendDepthImageData, FetchCanvasError> {
+        let render = match fetch_index {
+            FetchCanvasIndex::Onscreen => &self.render.onscreen,
+            FetchCanvasIndex::Offscreen(id) => self
+                .render
+                .offscreens
+                .get(&id)
+                .ok_or(FetchCanvasError::CanvasNotFound)?,
+        };
+        if self.last_presented_swap_chain_image_index == u32::MAX {
+            return Err(FetchCanvasError::NotPresentedYet);
+        }
+        let stencil_format = render.stencil_format;
+        let texel_size = depth_texel_size(stencil_format).ok_or_else(|| {
+            FetchCanvasError::DepthUnsupported(format!(
+                "The canvas has no depth attachment (format: {stencil_format:?})."
+            ))
+        })?;
+        if render.multi_sampling.is_some() {
+            return Err(FetchCanvasError::DepthUnsupported(
+                "Multi sampled depth attachments can not be copied.".to_string(),
+            ));
+        }
+        let depth_img = render.switching.stencil_list_for_pass_transition
+            [self.last_presented_swap_chain_image_index as usize]
+            .image
+            .clone();
+        if depth_img.layout.load(std::sync::atomic::Ordering::SeqCst)
+            != ImageLayout::DepthStencilAttachment
+        {
+            return Err(FetchCanvasError::DriverErr(
+                "The depth attachment was never rendered to.".to_string(),
+            ));
+        }
+        let viewport = render.native.swap_img_and_viewport_extent;
+        let width = viewport.width;
+        let height = viewport.height;
+        let image_total_size = width as usize * height as usize * texel_size;
+
+        self.prepare_presented_depth_data_buffer(image_total_size as vk::DeviceSize)
+            .map_err(|err| anyhow!("Could not prepare presented depth data: {err}"))?;
+
+        let fetch_depth_buffer = self
+            .fetch_depth_buffer
+            .as_ref()
+            .ok_or_else(|| anyhow!("fetch depth resources were none"))?;
+
+        let command_buffer = self
+            .props
+            .device
+            .get_memory_command_buffer(&mut FrameResources::new(None))
+            .map_err(|err| anyhow!("Could not get memory command buffer: {err}"))?
+            .command_buffer;
+
+        self.props
+            .device
+            .image_barrier(
+                &mut self.current_frame_resources,
+                &depth_img,
+                0,
+                1,
+                0,
+                1,
+                vk::ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL,
+                vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+            )
+            .map_err(|err| anyhow!("Image barrier failed for the depth image: {err}"))?;
+
+        let mut region = vk::BufferImageCopy::default();
+        region.image_subresource.aspect_mask = vk::ImageAspectFlags::DEPTH;
+        region.image_subresource.layer_count = 1;
+        region.image_extent.width = width;
+        region.image_extent.height = height;
+        region.image_extent.depth = 1;
+
+        unsafe {
+            self.props.ash_vk.vk_device.device.cmd_copy_image_to_buffer(
+                command_buffer,
+                depth_img.img(&mut self.current_frame_resources),
+                vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+                fetch_depth_buffer
+                    .buffer
+                    .get_buffer(&mut self.current_frame_resources),
+                &[region],
+            );
+        }
+
+        self.props
+            .device
+            .image_barrier(
+                &mut self.current_frame_resources,
+                &depth_img,
+                0,
+                1,
+                0,
+                1,
+                vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+                vk::ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL,
+            )
+            .map_err(|err| anyhow!("Image barrier failed for the depth image: {err}"))?;
+
+        self.props.device.memory_command_buffer = None;
+
+        let command_buffers = [command_buffer];
+        let submit_info = vk::SubmitInfo::default().command_buffers(&command_buffers);
+
+        unsafe {
+            self.props
+                .ash_vk
+                .vk_device
+                .device
+                .reset_fences(&[fetch_depth_buffer
+                    .fence
+                    .fence(&mut self.current_frame_resources)])
+        }
+        .map_err(|err| anyhow!("Could not reset fences: {err}"))?;
+        unsafe {
+            let queue = &self.props.queue.queues.lock();
+            self.props.ash_vk.vk_device.device.queue_submit(
+                queue.graphics_queue,
+                &[submit_info],
+                fetch_depth_buffer
+                    .fence
+                    .fence(&mut self.current_frame_resources),
+            )
+        }
+        .map_err(|err| anyhow!("Queue submit failed: {err}"))?;
+        unsafe {
+            self.props.ash_vk.vk_device.device.wait_for_fences(
+                &[fetch_depth_buffer
+                    .fence
+                    .fence(&mut self.current_frame_resources)],
+                true,
+                u64::MAX,
+            )
+        }
+        .map_err(|err| anyhow!("Could not wait for fences: {err}"))?;
+
+        let mut mem_range = vk::MappedMemoryRange::default();
+        mem_range.memory = fetch_depth_buffer.mem.mem(&mut FrameResources::new(None));
+        mem_range.offset = 0;
+        mem_range.size = vk::WHOLE_SIZE;
+        unsafe {
+            self.props
+                .ash_vk
+                .vk_device
+                .device
+                .invalidate_mapped_memory_ranges(&[mem_range])
+        }
+        .map_err(|err| anyhow!("Could not invalidate mapped memory ranges: {err}"))?;
+
+        let src_buff = unsafe {
+            std::slice::from_raw_parts(fetch_depth_buffer.mapped_memory.get_mem(), image_total_size)
+        };
+        let depth_data = src_buff
+            .chunks_exact(texel_size)
+            .map(|texel| normalize_depth_texel(stencil_format, texel))
+            .collect();
+
+        Ok(BackendDepthImageData {
+            width,
+            height,
+            depth_data,
+        })
+    }
+
+    /************************
+     * SWAPPING MECHANISM
+     ************************/
+    fn start_render_thread(&mut self, thread_index: usize) -> anyhow::Result<()> {
+        if !self.command_groups.is_empty() {
+            let thread = &mut self.render_threads[thread_index];
+            for command_group in self.command_groups.drain(..) {
+                let render = self.render.get_of_frame(command_group.canvas_index).clone();
+                thread
+                    .events
+                    .fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+                if thread
+                    .sender
+                    .send(RenderThreadEvent::Render((command_group, render)))
+                    .is_err()
+                {
+                    return Err(Self::render_thread_error(thread_index, thread)
+                        .context(RenderThreadError(thread_index)));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn handle_all_command_groups(&mut self) -> anyhow::Result<()> {
+        // execute threads
+        let mut thread_index = self.last_render_thread_index;
+        while !self.command_groups.is_empty() {
+            self.start_render_thread(thread_index % self.props.thread_count)?;
+            thread_index += 1;
+        }
+        Ok(())
+    }
+
+    /// Waits for all render threads, even if one of them failed.
+    /// The first failure is returned with a [`RenderThreadError`] context.
+    fn finish_render_threads(&mut self) -> anyhow::Result<()> {
+        self.handle_all_command_groups()?;
+
+        let mut res = Ok(());
+        for (thread_index, render_thread) in self.render_threads.iter().enumerate() {
+            let thread_res = Self::finish_render_thread(thread_index, render_thread);
+            if let (Ok(()), Err(err)) = (&res, thread_res) {
+                res = Err(err.context(RenderThreadError(thread_index)));
+            }
+        }
+        res
+    }
+
+    fn finish_render_thread(
+        thread_index: usize,
+        render_thread: &RenderThread,
+    ) -> anyhow::Result<()> {
+        if render_thread
+            .events
+            .load(std::sync::atomic::Ordering::SeqCst)
+            != 0
+        {
+            let (sender, receiver) = bounded(1);
+            render_thread
+                .events
+                .fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            if render_thread
+                .sender
+                .send(RenderThreadEvent::Sync(sender))
+                .is_err()
+            {
+                return Err(Self::render_thread_error(thread_index, render_thread));
+            }
+            Self::wait_for_render_thread_sync(
+                thread_index,
+                render_thread,
+                &receiver,
+                Self::RENDER_THREAD_SYNC_TIMEOUT,
+            )?;
+        }
+        match render_thread.errors.try_recv() {
+            Ok(err) => Err(err),
+            Err(_) => Ok(()),
+        }
+    }
+
+    /// How long the main thread waits for a render thread to
+    /// finish its commands, before assuming it is deadlocked.
+    const RENDER_THREAD_SYNC_TIMEOUT: Duration = Duration::from_secs(10);
+
+    /// Waits for the render thread to answer a [`RenderThreadEvent::Sync`].
+    /// Instead of hanging forever, an error is returned if the thread stopped
+    /// or did not answer within `timeout`.
+    pub(crate) fn wait_for_render_thread_sync(
+        thread_index: usize,
+        render_thread: &RenderThread,
+        receiver: &Receiver<()>,
+        timeout: Duration,
+    ) -> anyhow::Result<()> {
+        receiver
+            .recv_timeout(timeout)
+            .map_err(|_| Self::render_thread_error(thread_index, render_thread))
+    }
+
+    /// Describes why a render thread did not answer,
+    /// including the panic message if the thread panicked.
+    fn render_thread_error(thread_index: usize, render_thread: &RenderThread) -> anyhow::Error {
+        let mut thread = render_thread.thread.lock();
+        if !thread.is_finished() {
+            return anyhow!(
+                "render thread {thread_index} did not finish its commands in time, \
+                it might be deadlocked."
+            );
+        }
+        match thread.try_join() {
+            Err(err) => anyhow!("render thread {thread_index} panicked: {err}"),
+            Ok(_) => anyhow!("render thread {thread_index} stopped unexpectedly."),
+        }
+    }
+
+    fn execute_memory_command_buffer(&mut self) {
+        if let Some(memory_command_buffer) = self.props.device.memory_command_buffer.take() {
+            let command_buffer = memory_command_buffer.command_buffer;
+            drop(memory_command_buffer);
+
+            let command_buffers = [command_buffer];
+            let submit_info = vk::SubmitInfo::default().command_buffers(&command_buffers);
+            unsafe {
+                let queue = &self.props.queue.queues.lock();
+                self.props
+                    .ash_vk
+                    .vk_device
+                    .device
+                    .queue_submit(queue.graphics_queue, &[submit_info], vk::Fence::null())
+                    .unwrap();
+            }
+            unsafe {
+                let queue = &self.props.queue.queues.lock();
+                self.props
+                    .ash_vk
+                    .vk_device
+                    .device
+                    .queue_wait_idle(queue.graphics_queue)
+                    .unwrap();
+            }
+        }
+    }
+
+    fn flush_memory_ranges(&mut self) {
+        if !self.props.device.non_flushed_memory_ranges.is_empty() {
+            unsafe {
+                self.props
+                    .ash_vk
+                    .vk_device
                     .device
                     .flush_mapped_memory_ranges(
                         self.props.device.non_flushed_memory_ranges.as_slice(),
@@ -1361,6 +1953,14 @@ impl VulkanBackend {
         Ok(())
     }
 
+    /// The viewport that was set while the current canvas was active,
+    /// if it doesn't cover the whole canvas.
+    pub(crate) fn dynamic_viewport(&self) -> Option<DynamicViewport> {
+        self.dynamic_viewports
+            .get(&self.render.cur_canvas())
+            .copied()
+    }
+
     fn cmd_switch_canvas_mode(&mut self, cmd: CommandSwitchCanvasMode) -> anyhow::Result<()> {
         let (canvas_index, has_multi_sampling) = match &cmd.mode {
             // even if onscreen has multi-sampling. this is not allowed
@@ -1427,6 +2027,11 @@ impl VulkanBackend {
             .entry(canvas_index)
             .or_insert_with(create_command_group);
         if !current_command_group.cmds.is_empty() {
+            Self::count_command_group(
+                &mut self.cur_frame_stats,
+                &mut self.cur_frame_render_passes,
+                current_command_group,
+            );
             self.command_groups
                 .push(std::mem::take(current_command_group));
         } else {
@@ -1460,6 +2065,20 @@ impl VulkanBackend {
         Ok(())
     }
 
+    /// Drains the command groups in the order they were created in,
+    /// so the submission order does not depend on the iteration order
+    /// of the hash map.
+    pub(crate) fn drain_command_groups_in_order(
+        command_groups: &mut HashMap<FrameCanvasIndex, ThreadCommandGroup>,
+    ) -> Vec<ThreadCommandGroup> {
+        let mut res: Vec<ThreadCommandGroup> = command_groups
+            .drain()
+            .map(|(_, command_group)| command_group)
+            .collect();
+        res.sort_by_key(|command_group| command_group.in_order_id);
+        res
+    }
+
     fn add_command_group(
         command_groups: &mut Vec<ThreadCommandGroup>,
         command_group: ThreadCommandGroup,
@@ -1469,6 +2088,18 @@ impl VulkanBackend {
         }
     }
 
+    fn count_command_group(
+        frame_stats: &mut BackendFrameStats,
+        frame_render_passes: &mut HashSet<(FrameCanvasIndex, usize)>,
+        command_group: &ThreadCommandGroup,
+    ) {
+        frame_stats.command_groups += 1;
+        if frame_render_passes.insert((command_group.canvas_index, command_group.render_pass_index))
+        {
+            frame_stats.render_passes += 1;
+        }
+    }
+
     fn wait_frame(&mut self) -> anyhow::Result<()> {
         let command_buffer = self
             .main_render_command_buffer
@@ -1477,7 +2108,16 @@ impl VulkanBackend {
             .command_buffer;
 
         // make sure even the current unhandled commands get handled
-        for (_, current_command_group) in self.current_command_groups.drain() {
+        for current_command_group in
+            Self::drain_command_groups_in_order(&mut self.current_command_groups)
+        {
+            if !current_command_group.cmds.is_empty() {
+                Self::count_command_group(
+                    &mut self.cur_frame_stats,
+                    &mut self.cur_frame_render_passes,
+                    &current_command_group,
+                );
+            }
             Self::add_command_group(&mut self.command_groups, current_command_group);
         }
 
@@ -1579,6 +2219,9 @@ impl VulkanBackend {
             )
         }
         .map_err(|err| anyhow!("Submitting to graphics queue failed: {err}"))?;
+        if self.props.max_frames_in_flight > 0 {
+            self.frames_in_flight.push_back(self.render.cur_image_index);
+        }
 
         std::mem::swap(
             &mut self.render.busy_acquire_image_semaphores[self.render.cur_image_index as usize],
@@ -1593,24 +2236,34 @@ impl VulkanBackend {
         self.last_presented_swap_chain_image_index = self.render.cur_image_index;
 
         if !self.frame_fetchers.is_empty() {
-            // TODO: removed cloning
-            let keys: Vec<String> = self.frame_fetchers.keys().cloned().collect();
-            for i in keys.iter() {
-                // get current frame and fill the frame fetcher with it
-                let fetch_index = self.frame_fetchers.get(i).unwrap().current_fetch_index();
-                // ignore offscreen canvases that requested to skip this frame
-                if let FetchCanvasIndex::Offscreen(index) = fetch_index {
-                    if self
-                        .offscreen_canvases_frame_fetching_skips
-                        .contains(&index)
-                    {
-                        continue;
-                    }
+            let frame_fetchers: Vec<_> = self.frame_fetchers.values().cloned().collect();
+            let fetch_indices: Vec<_> = frame_fetchers
+                .iter()
+                .map(|frame_fetcher| frame_fetcher.current_fetch_index())
+                .collect();
+            // ignore offscreen canvases that requested to skip this frame
+            let (canvases, copy_indices) = frame_fetch_copies(
+                &fetch_indices,
+                &self.offscreen_canvases_frame_fetching_skips,
+            );
+            // copy all canvases at once and fill the frame fetchers with them
+            let copies = self.copy_presented_images_impl(&canvases);
+            for ((frame_fetcher, fetch_index), copy_index) in
+                frame_fetchers.iter().zip(fetch_indices).zip(copy_indices)
+            {
+                let Some(copy_index) = copy_index else {
+                    continue;
+                };
+                if let Ok(copy) = &copies[copy_index] {
+                    frame_fetcher.next_frame(
+                        self.read_presented_image_data(copy, frame_fetcher.orientation()),
+                    );
                 }
-                let img_data = self.get_presented_image_data_impl(fetch_index);
-                if let Ok(img_data) = img_data {
-                    let frame_fetcher = self.frame_fetchers.get(i).unwrap();
-                    frame_fetcher.next_frame(img_data);
+                if frame_fetcher.fetch_depth() {
+                    match self.get_presented_depth_data_impl(fetch_index) {
+                        Ok(depth_data) => frame_fetcher.next_depth_frame(depth_data),
+                        Err(err) => frame_fetcher.fetch_err(err),
+                    }
                 }
             }
         }
@@ -1628,11 +2281,8 @@ impl VulkanBackend {
         {
             Some(vk::Result::ERROR_OUT_OF_DATE_KHR)
         } else if queue_present_res.is_err_and(|err| err == vk::Result::ERROR_SURFACE_LOST_KHR) {
-            let surface = self.create_fake_surface()?;
-            log::warn!("surface lost after presenting queue, creating fake surface.");
-            self.reinit_vulkan_swap_chain(|_| &surface)?;
-            self.ash_surf.surface.replace(surface);
-            self.recreate_swap_chain = false;
+            log::warn!("surface lost after presenting queue, trying to recover.");
+            self.recover_lost_surface()?;
             self.prepare_frame()?;
             None
         } else {
@@ -1676,6 +2326,22 @@ impl VulkanBackend {
             self.recreate_swap_chain()?;
         }
 
+        // bound how far the cpu runs ahead of the gpu, before recording the next frame
+        for image_index in Self::frames_to_finish_before_next(
+            &mut self.frames_in_flight,
+            self.props.max_frames_in_flight as usize,
+        ) {
+            unsafe {
+                self.props.ash_vk.vk_device.device.wait_for_fences(
+                    &[self.render.queue_submit_fences[image_index as usize]
+                        .fence(&mut self.current_frame_resources)],
+                    true,
+                    u64::MAX,
+                )
+            }?;
+            self.wait_stats.frames_in_flight_waits += 1;
+        }
+
         let acquire_res = unsafe {
             self.ash_surf.vk_swap_chain_ash.acquire_next_image(
                 u64::MAX,
@@ -1694,11 +2360,8 @@ impl VulkanBackend {
             self.recreate_swap_chain()?;
             return self.prepare_frame();
         } else if acquire_res.is_err_and(|err| err == vk::Result::ERROR_SURFACE_LOST_KHR) {
-            let surface = self.create_fake_surface()?;
-            log::warn!("surface lost after acquiring next image, creating fake surface.");
-            self.reinit_vulkan_swap_chain(|_| &surface)?;
-            self.ash_surf.surface.replace(surface);
-            self.recreate_swap_chain = false;
+            log::warn!("surface lost after acquiring next image, trying to recover.");
+            self.recover_lost_surface()?;
             self.prepare_frame()?;
             return Ok(());
         }
@@ -1744,6 +2407,10 @@ impl VulkanBackend {
             },
         )?;
         self.render.new_frame(&mut self.current_frame_resources)?;
+        self.props.pending_pipeline_compiles_count.store(
+            self.render.pipelines_pending_count(),
+            std::sync::atomic::Ordering::Relaxed,
+        );
 
         // check if older frames weren't used in a long time
         for frame_image_index in 0..self.image_last_frame_check.len() {
@@ -1799,9 +2466,15 @@ impl VulkanBackend {
         Ok(())
     }
 
+    /// Whether frames are rendered & presented, instead of only executing memory commands.
+    fn can_render(&self) -> bool {
+        !self.render_paused && self.ash_surf.surface.can_render()
+    }
+
     pub fn next_frame(&mut self) -> anyhow::Result<()> {
-        if self.ash_surf.surface.can_render() {
+        if self.can_render() {
             self.wait_frame()?;
+            self.frame_pacer.wait();
             self.prepare_frame()?;
         }
         // else only execute the memory command buffer
@@ -1809,24 +2482,118 @@ impl VulkanBackend {
             self.pure_memory_frame()?;
         }
 
+        // the memory of the previous usage of this frame is free again
+        self.process_pending_texture_uploads(false)?;
+
+        self.has_unsubmitted_work = false;
+
+        self.last_frame_stats = std::mem::take(&mut self.cur_frame_stats);
+        self.cur_frame_render_passes.clear();
+
         Ok(())
     }
 
+    /// The stats of the last frame that was submitted.
+    pub fn last_frame_stats(&self) -> BackendFrameStats {
+        self.last_frame_stats
+    }
+
+    /// How often the backend waited for the gpu since it was created.
+    pub fn wait_stats(&self) -> BackendWaitStats {
+        self.wait_stats
+    }
+
+    /// Whether the gpu finished all submitted frames, so preparing the next frame
+    /// does not wait for their fences.
+    /// The fence of the current frame stays signaled until it is submitted.
+    fn submitted_frames_finished(&mut self) -> anyhow::Result<bool> {
+        let fences: Vec<_> = self
+            .render
+            .queue_submit_fences
+            .iter()
+            .map(|fence| fence.fence(&mut self.current_frame_resources))
+            .collect();
+        match unsafe {
+            self.props
+                .ash_vk
+                .vk_device
+                .device
+                .wait_for_fences(&fences, true, 0)
+        } {
+            Ok(()) => Ok(true),
+            Err(vk::Result::TIMEOUT) => Ok(false),
+            Err(err) => Err(anyhow!("checking the frame fences failed: {err}")),
+        }
+    }
+
+    /// Like [`Self::next_frame`], but instead of waiting for the gpu to finish
+    /// the previous frames, [`TryFrameError::WouldBlock`] is returned and nothing
+    /// is submitted.
+    ///
+    /// With a `max_frames_in_flight` of 1 the next frame still waits
+    /// for the frame that is submitted by this call.
+    pub fn try_next_frame(&mut self) -> Result<FramePresented, TryFrameError> {
+        if self.can_render()
+            && !self
+                .submitted_frames_finished()
+                .map_err(TryFrameError::Frame)?
+        {
+            return Err(TryFrameError::WouldBlock);
+        }
+        self.next_frame().map_err(TryFrameError::Frame)?;
+        Ok(FramePresented {
+            frame: self.cur_frame,
+        })
+    }
+
+    /// Submits all work that was recorded since the last frame, e.g. buffer updates
+    /// or render commands, and lets all frame fetchers fetch the result one last time.
+    /// Afterwards waits until the gpu is idle.
+    ///
+    /// Should be called before shutting down, since otherwise everything after the
+    /// last [`CommandsMisc::Swap`] is lost. Dropping the backend calls it, too.
+    pub fn flush_and_drain(&mut self) -> anyhow::Result<()> {
+        if self.has_unsubmitted_work {
+            self.next_frame()?;
+        }
+
+        let _g = self.props.queue.queues.lock();
+        unsafe { self.props.ash_vk.vk_device.device.device_wait_idle() }
+            .map_err(|err| anyhow!("wait idle after draining the backend failed: {err}"))
+    }
+
     /************************
      * TEXTURES
      ************************/
     fn update_texture(
         &mut self,
         texture_slot: u128,
-        format: vk::Format,
         data: &[u8],
         x_off: i64,
         y_off: i64,
         width: usize,
         height: usize,
-        color_channel_count: usize,
-    ) -> anyhow::Result<()> {
-        let image_size: usize = width * height * color_channel_count;
+        can_stall: bool,
+    ) -> anyhow::Result<bool> {
+        let tex = self
+            .props
+            .device
+            .textures
+            .get(&texture_slot)
+            .ok_or(anyhow!("texture with that index does not exist"))?;
+        let format = tex.format;
+        let compressed = texture_compressed_format(format);
+        let image_size = Self::check_texture_update_region(
+            x_off,
+            y_off,
+            width,
+            height,
+            compressed,
+            data.len(),
+            tex.width,
+            tex.height,
+        )?;
+
         let mut staging_allocation = self
             .props
             .device
@@ -1839,6 +2606,9 @@ impl VulkanBackend {
                 image_size as u64,
             );
         if let Err(_) = staging_allocation {
+            if !can_stall {
+                return Ok(false);
+            }
             self.skip_frames_until_current_frame_is_used_again()?;
             staging_allocation = self
                 .props
@@ -1935,14 +2705,157 @@ impl VulkanBackend {
             staging_buffer,
         );
 
+        Ok(true)
+    }
+
+    /// Returns the upload back, if no staging memory was available
+    /// and `can_stall` is `false`.
+    fn texture_upload(
+        &mut self,
+        upload: PendingTextureUpload,
+        can_stall: bool,
+    ) -> anyhow::Result<Option<PendingTextureUpload>> {
+        match upload {
+            PendingTextureUpload::Create(CommandTextureCreate {
+                texture_index,
+                data,
+                usage,
+            }) => {
+                let mut data_mem = self
+                    .props
+                    .device
+                    .mem_allocator
+                    .lock()
+                    .memory_to_internal_memory(data);
+                if let Err((_, AllocationError::Image(ImageAllocationError::UnsupportedFormat))) =
+                    data_mem
+                {
+                    // waiting for memory does not help here
+                    return Err(anyhow!(
+                        "texture format is not supported by the vulkan device"
+                    ));
+                }
+                if let Err((mem, _)) = data_mem {
+                    if !can_stall {
+                        return Ok(Some(PendingTextureUpload::Create(CommandTextureCreate {
+                            texture_index,
+                            data: mem,
+                            usage,
+                        })));
+                    }
+                    self.skip_frames_until_current_frame_is_used_again()?;
+                    data_mem = self
+                        .props
+                        .device
+                        .mem_allocator
+                        .lock()
+                        .memory_to_internal_memory(mem);
+                }
+                let data_mem = data_mem.map_err(|(_, err)| err)?;
+
+                self.create_texture_cmd(texture_index, data_mem, usage)?;
+
+                Ok(None)
+            }
+            PendingTextureUpload::Update(cmd) => {
+                let uploaded = self.update_texture(
+                    cmd.texture_index,
+                    &cmd.data,
+                    cmd.x as i64,
+                    cmd.y as i64,
+                    cmd.width as usize,
+                    cmd.height as usize,
+                    can_stall,
+                )?;
+
+                Ok((!uploaded).then_some(PendingTextureUpload::Update(cmd)))
+            }
+        }
+    }
+
+    fn has_pending_texture_upload(&self, texture_index: u128) -> bool {
+        self.pending_texture_uploads
+            .iter()
+            .any(|upload| upload.texture_index() == texture_index)
+    }
+
+    fn update_pending_texture_uploads_count(&self) {
+        self.props.pending_texture_uploads_count.store(
+            self.pending_texture_uploads.len() as u64,
+            std::sync::atomic::Ordering::Relaxed,
+        );
+    }
+
+    /// Uploads the texture immediately if staging memory is available,
+    /// else defers it to one of the next frames.
+    /// Only if too many uploads are pending, this stalls the GPU.
+    fn queue_texture_upload(&mut self, upload: PendingTextureUpload) -> anyhow::Result<()> {
+        // uploads to the same texture must keep their order
+        let upload = if self.has_pending_texture_upload(upload.texture_index()) {
+            Some(upload)
+        } else {
+            self.texture_upload(upload, false)?
+        };
+
+        if let Some(upload) = upload {
+            if self.pending_texture_uploads.len() < MAX_PENDING_TEXTURE_UPLOADS {
+                self.pending_texture_uploads.push_back(upload);
+                self.update_pending_texture_uploads_count();
+            } else {
+                self.process_pending_texture_uploads(true)?;
+                self.texture_upload(upload, true)?;
+            }
+        }
+
         Ok(())
     }
 
+    /// If `force` is `true`, all pending uploads are finished,
+    /// even if that requires waiting for the GPU.
+    fn process_pending_texture_uploads(&mut self, force: bool) -> anyhow::Result<()> {
+        while let Some(upload) = self.pending_texture_uploads.pop_front() {
+            if let Some(upload) = self.texture_upload(upload, force)? {
+                // still no staging memory, try again next frame
+                self.pending_texture_uploads.push_front(upload);
+                break;
+            }
+        }
+        self.update_pending_texture_uploads_count();
+
+        Ok(())
+    }
+
+    /// Whether the render command might sample a texture
+    /// that is not uploaded yet.
+    fn render_cmd_uses_pending_texture(&self, cmd: &CommandsRender) -> bool {
+        let texture_index = match cmd {
+            CommandsRender::Clear(_) => return false,
+            CommandsRender::Stream(CommandsRenderStream::Render(cmd))
+            | CommandsRender::Stream(CommandsRenderStream::RenderBlurred { cmd, .. }) => {
+                &cmd.texture_index
+            }
+            CommandsRender::QuadContainer(CommandsRenderQuadContainer::Render(cmd)) => {
+                &cmd.texture_index
+            }
+            CommandsRender::QuadContainer(CommandsRenderQuadContainer::RenderAsSpriteMultiple(
+                cmd,
+            )) => &cmd.texture_index,
+            // mods can use any texture
+            CommandsRender::Mod(_) => return true,
+        };
+        match texture_index {
+            StateTexture::Texture(index) => self.has_pending_texture_upload(*index),
+            StateTexture::None
+            | StateTexture::ColorAttachmentOfPreviousPass
+            | StateTexture::ColorAttachmentOfOffscreen(_) => false,
+        }
+    }
+
     fn create_texture_cmd(
         &mut self,
         slot: u128,
-        tex_format: vk::Format,
         upload_data: VulkanDeviceInternalMemory,
+        usage: TextureUsage,
     ) -> anyhow::Result<()> {
         let image_index = slot;
 
@@ -1952,6 +2865,7 @@ impl VulkanBackend {
             depth,
             is_3d_tex,
             mip_map_count,
+            format: tex_format,
             ..
         } = self
             .props
@@ -1959,6 +2873,13 @@ impl VulkanBackend {
             .mem_allocator
             .lock()
             .mem_image_cache_entry(upload_data.mem.as_mut_ptr());
+        // the image was allocated with all mip levels already,
+        // only the first level is built and sampled.
+        let mip_map_count = if usage.generates_mip_maps() {
+            mip_map_count
+        } else {
+            1
+        };
 
         let texture_data = if !is_3d_tex {
             match self.props.device.create_texture_image(
@@ -2000,8 +2921,14 @@ impl VulkanBackend {
                 }
             }
         } else {
-            let image_3d_width = width;
-            let image_3d_height = height;
+            let (image_3d_width, image_3d_height) = texture_array_layer_extent(
+                width,
+                height,
+                depth,
+                // compressed layers are validated by the allocation already
+                (tex_format == vk::Format::R8G8B8A8_UNORM).then_some(width * height * 4),
+                upload_data.mem.len(),
+            )?;
 
             let (img_3d, img_mem_3d) = self.props.device.create_texture_image(
                 &mut self.current_frame_resources,
@@ -2039,6 +2966,10 @@ impl VulkanBackend {
         let texture = TextureObject {
             data: texture_data,
             mip_map_count: mip_map_count as u32,
+            width: width as u32,
+            height: height as u32,
+            format: tex_format,
+            usage,
         };
 
         self.props.device.textures.insert(image_index, texture); // TODO better fix
@@ -2079,6 +3010,8 @@ impl VulkanBackend {
                 .device_wait_idle()
                 .map_err(|err| anyhow!("wait idle wait while recreating swapchain {err}"))?
         };
+        // all frames finished, the fences are recreated anyway
+        self.frames_in_flight.clear();
 
         if is_verbose(&self.props.dbg) {
             info!("recreating swap chain.");
@@ -2155,6 +3088,7 @@ impl VulkanBackend {
         )?;
 
         self.render.offscreens = offscreen_canvases;
+        self.resize_relative_offscreen_canvases()?;
 
         self.last_presented_swap_chain_image_index = u32::MAX;
 
@@ -2168,6 +3102,30 @@ impl VulkanBackend {
         Ok(())
     }
 
+    /// Resizes all offscreen canvases with a relative size
+    /// to the current size of the swapchain.
+    fn resize_relative_offscreen_canvases(&mut self) -> anyhow::Result<()> {
+        let window_extent = self.render.onscreen.native.swap_img_and_viewport_extent;
+        for (&offscreen_index, &divisor) in &self.relative_offscreen_canvases {
+            let (width, height) =
+                relative_canvas_extent(window_extent.width, window_extent.height, divisor);
+            let extent = self
+                .render
+                .offscreens
+                .get(&offscreen_index)
+                .map(|offscreen| offscreen.native.swap_img_and_viewport_extent);
+            if extent.is_some_and(|extent| extent.width != width || extent.height != height) {
+                self.render.resize_offscreen_canvas(
+                    offscreen_index,
+                    width,
+                    height,
+                    &self.props.device.standard_texture_descr_pool,
+                )?;
+            }
+        }
+        Ok(())
+    }
+
     fn init_vulkan_with_io(&mut self) -> anyhow::Result<()> {
         self.image_last_frame_check
             .resize(self.render.onscreen.swap_chain_image_count(), 0);
@@ -2189,63 +3147,40 @@ impl VulkanBackend {
     /************************
      * COMMAND IMPLEMENTATION
      ************************/
-    fn cmd_texture_update(&mut self, cmd: &CommandTextureUpdate) -> anyhow::Result<()> {
-        let index_tex = cmd.texture_index;
-
-        self.update_texture(
-            index_tex,
-            vk::Format::R8G8B8A8_UNORM,
-            &cmd.data,
-            cmd.x as i64,
-            cmd.y as i64,
-            cmd.width as usize,
-            cmd.height as usize,
-            4,
-        )
+    fn cmd_texture_update(&mut self, cmd: CommandTextureUpdate) -> anyhow::Result<()> {
+        self.queue_texture_upload(PendingTextureUpload::Update(cmd))
     }
 
     fn cmd_texture_destroy(&mut self, cmd: &CommandTextureDestroy) -> anyhow::Result<()> {
         let image_index = cmd.texture_index;
-        self.props
-            .device
-            .textures
-            .remove(&image_index)
-            .ok_or(anyhow!("texture not found in vk backend"))?;
+        let was_pending = self.has_pending_texture_upload(image_index);
+        if was_pending {
+            self.pending_texture_uploads
+                .retain(|upload| upload.texture_index() != image_index);
+            self.update_pending_texture_uploads_count();
+        }
+        let was_created = self.props.device.textures.remove(&image_index).is_some();
+        anyhow::ensure!(
+            was_created || was_pending,
+            "texture not found in vk backend"
+        );
 
         Ok(())
     }
 
     fn cmd_texture_create(&mut self, cmd: CommandTextureCreate) -> anyhow::Result<()> {
-        let texture_index = cmd.texture_index;
-
-        let data_mem = cmd.data;
-        let mut data_mem = self
-            .props
-            .device
-            .mem_allocator
-            .lock()
-            .memory_to_internal_memory(data_mem);
-        if let Err((mem, _)) = data_mem {
-            self.skip_frames_until_current_frame_is_used_again()?;
-            data_mem = self
-                .props
-                .device
-                .mem_allocator
-                .lock()
-                .memory_to_internal_memory(mem);
-        }
-        let data_mem = data_mem.map_err(|(_, err)| err)?;
-
-        self.create_texture_cmd(texture_index, vk::Format::R8G8B8A8_UNORM, data_mem)?;
-
-        Ok(())
+        self.queue_texture_upload(PendingTextureUpload::Create(cmd))
     }
 
     fn cmd_clear_fill_execute_buffer(
         render_execute_manager: &mut RenderCommandExecuteManager,
         cmd: &CommandClear,
     ) {
-        render_execute_manager.clear_color_in_render_thread(cmd.force_clear, cmd.color);
+        if let Some(clip) = &cmd.clip {
+            render_execute_manager.clear_rect_in_render_thread(clip);
+        } else {
+            render_execute_manager.clear_color_in_render_thread(cmd.force_clear, cmd.color);
+        }
         render_execute_manager.estimated_render_calls(0);
     }
 
@@ -2292,6 +3227,20 @@ impl VulkanBackend {
         Self::cmd_render_fill_execute_buffer(render_execute_manager, cmd);
     }
 
+    /// Applies a viewport update caused by a resize event.
+    ///
+    /// The content scale is always stored, but only a change
+    /// of the physical pixel size recreates the swapchain.
+    /// Returns `true` if the swapchain has to be recreated.
+    pub(crate) fn apply_resize_viewport(
+        swap_chain_extent: vk::Extent2D,
+        content_scale: &mut f64,
+        cmd: &CommandUpdateViewport,
+    ) -> bool {
+        *content_scale = cmd.content_scale;
+        swap_chain_extent.width != cmd.width || swap_chain_extent.height != cmd.height
+    }
+
     fn cmd_update_viewport(&mut self, cmd: &CommandUpdateViewport) -> anyhow::Result<()> {
         if cmd.by_resize {
             if is_verbose(&self.props.dbg) {
@@ -2299,43 +3248,43 @@ impl VulkanBackend {
             }
 
             // TODO: rethink if this is a good idea (checking if width changed. maybe some weird edge cases)
-            if self
-                .render
-                .onscreen
-                .native
-                .swap_img_and_viewport_extent
-                .width
-                != cmd.width
-                || self
-                    .render
-                    .onscreen
-                    .native
-                    .swap_img_and_viewport_extent
-                    .height
-                    != cmd.height
-            {
+            if Self::apply_resize_viewport(
+                self.render.onscreen.native.swap_img_and_viewport_extent,
+                &mut self.content_scale,
+                cmd,
+            ) {
                 self.window_width = cmd.width;
                 self.window_height = cmd.height;
                 self.recreate_swap_chain = true;
                 if is_verbose(&self.props.dbg) {
                     info!("queue recreate swapchain because of a viewport update with by_resize == true.");
                 }
+            } else if is_verbose(&self.props.dbg) {
+                info!(
+                    "physical size unchanged, only updated the content scale to {}.",
+                    self.content_scale
+                );
             }
         } else {
+            self.content_scale = cmd.content_scale;
             let viewport = self.render.get().native.swap_img_and_viewport_extent;
             if cmd.x != 0
                 || cmd.y != 0
                 || cmd.width != viewport.width
                 || cmd.height != viewport.height
             {
-                self.has_dynamic_viewport = true;
-                self.dynamic_viewport_offset = vk::Offset2D { x: cmd.x, y: cmd.y };
-                self.dynamic_viewport_size = vk::Extent2D {
-                    width: cmd.width,
-                    height: cmd.height,
-                };
+                self.dynamic_viewports.insert(
+                    self.render.cur_canvas(),
+                    DynamicViewport {
+                        offset: vk::Offset2D { x: cmd.x, y: cmd.y },
+                        size: vk::Extent2D {
+                            width: cmd.width,
+                            height: cmd.height,
+                        },
+                    },
+                );
             } else {
-                self.has_dynamic_viewport = false;
+                self.dynamic_viewports.remove(&self.render.cur_canvas());
             }
         }
 
@@ -2367,6 +3316,106 @@ impl VulkanBackend {
         Ok(())
     }
 
+    /// The command groups of the current frame in submission order, both the ones
+    /// that wait for a render thread and the ones that are still recorded.
+    /// Groups that render threads already picked up are not part of it.
+    pub(crate) fn frame_graph(
+        command_groups: &[ThreadCommandGroup],
+        current_command_groups: &HashMap<FrameCanvasIndex, ThreadCommandGroup>,
+    ) -> Vec<FrameGraphGroup> {
+        let mut groups: Vec<FrameGraphGroup> = command_groups
+            .iter()
+            .chain(current_command_groups.values())
+            .map(FrameGraphGroup::from)
+            .collect();
+        groups.sort_by_key(|group| group.in_order_id);
+        groups
+    }
+
+    fn cmd_dump_frame_graph(&mut self) -> anyhow::Result<()> {
+        if !is_verbose(&self.props.dbg) {
+            return Ok(());
+        }
+        let groups = Self::frame_graph(&self.command_groups, &self.current_command_groups);
+        info!(
+            "frame graph of frame {} ({} command groups):",
+            self.cur_frame,
+            groups.len()
+        );
+        for group in groups {
+            info!(
+                "  #{} {:?} pass {} ({:?}): {} commands",
+                group.in_order_id,
+                group.canvas_index,
+                group.render_pass_index,
+                group.render_pass,
+                group.cmd_count
+            );
+        }
+        Ok(())
+    }
+
+    /// Removes the oldest frames from `frames_in_flight` until the next frame fits
+    /// into `max_frames_in_flight` and returns their image indices,
+    /// whose fences must be waited for before the next frame is recorded.
+    /// 0 never waits.
+    pub(crate) fn frames_to_finish_before_next(
+        frames_in_flight: &mut VecDeque<u32>,
+        max_frames_in_flight: usize,
+    ) -> Vec<u32> {
+        if max_frames_in_flight == 0 {
+            frames_in_flight.clear();
+            return Vec::new();
+        }
+        let finish_count = (frames_in_flight.len() + 1).saturating_sub(max_frames_in_flight);
+        frames_in_flight.drain(..finish_count).collect()
+    }
+
+    /// Applies the requested polygon mode, [`PolygonMode::Line`] requires
+    /// the `fill_mode_non_solid` device feature, else fill mode is kept.
+    /// Returns `true` if the mode changed and the pipelines have to be recreated.
+    pub(crate) fn apply_polygon_mode(
+        cur_mode: &mut PolygonMode,
+        mode: PolygonMode,
+        fill_mode_non_solid: bool,
+    ) -> bool {
+        let mode = if mode == PolygonMode::Line && !fill_mode_non_solid {
+            warn!("line polygon mode is not supported by the device, keeping fill mode.");
+            PolygonMode::Fill
+        } else {
+            mode
+        };
+        let changed = *cur_mode != mode;
+        *cur_mode = mode;
+        changed
+    }
+
+    fn cmd_set_polygon_mode(&mut self, mode: PolygonMode) -> anyhow::Result<()> {
+        let device = &self.props.device.ash_vk.device;
+        let mut cur_mode = if device
+            .line_polygon_mode
+            .load(std::sync::atomic::Ordering::Relaxed)
+        {
+            PolygonMode::Line
+        } else {
+            PolygonMode::Fill
+        };
+        let fill_mode_non_solid = device.phy_device.config.read().unwrap().fill_mode_non_solid;
+        if Self::apply_polygon_mode(&mut cur_mode, mode, fill_mode_non_solid) {
+            if is_verbose(&self.props.dbg) {
+                info!("queueing swap chain recreation because the polygon mode was changed");
+            }
+            device.line_polygon_mode.store(
+                cur_mode == PolygonMode::Line,
+                std::sync::atomic::Ordering::Relaxed,
+            );
+            // recreates all pipelines
+            self.recreate_swap_chain = true;
+        }
+
+        Ok(())
+    }
+
     fn cmd_swap(&mut self) -> anyhow::Result<()> {
         self.next_frame()
     }
@@ -2380,14 +3429,33 @@ impl VulkanBackend {
         access_flags: vk::AccessFlags,
         source_stage_flags: vk::PipelineStageFlags,
     ) -> anyhow::Result<()> {
-        anyhow::ensure!(
-            !update_regions.is_empty(),
-            anyhow!("copy regions shall not be empty.")
-        );
-        anyhow::ensure!(
-            !update_regions.iter().any(|region| region.size == 0),
-            anyhow!("copy regions sizes must be bigger than zero.")
-        );
+        // single regions are by far the most common updates (e.g. editor tile painting)
+        let mut single_copy;
+        let mut batch_copies;
+        let (copies, barrier_offset, barrier_size) = match update_regions.as_slice() {
+            [update_region] => {
+                single_copy = buffer_update_copy(
+                    update_data.len(),
+                    update_region,
+                    mem.heap_data.offset_to_align,
+                )?;
+                let (barrier_offset, barrier_size) = (single_copy.dst_offset, single_copy.size);
+                (
+                    std::slice::from_mut(&mut single_copy),
+                    barrier_offset,
+                    barrier_size,
+                )
+            }
+            update_regions => {
+                let (copies, barrier_offset, barrier_size) = buffer_update_copies(
+                    update_data.len(),
+                    update_regions,
+                    mem.heap_data.offset_to_align,
+                )?;
+                batch_copies = copies;
+                (batch_copies.as_mut_slice(), barrier_offset, barrier_size)
+            }
+        };
 
         let mut staging_allocation = self.props.device.mem_allocator.lock().get_staging_buffer(
             update_data.as_ptr() as _,
@@ -2403,27 +3471,19 @@ impl VulkanBackend {
         }
         let staging_buffer = staging_allocation?;
 
-        let dst_buffer_align = mem.heap_data.offset_to_align;
+        copies.iter_mut().for_each(|copy| {
+            copy.src_offset += staging_buffer.heap_data.offset_to_align as vk::DeviceSize
+        });
         let src_buffer = staging_buffer
             .buffer(&mut self.current_frame_resources)
             .clone()
             .ok_or(anyhow!("staging mem had no buffer attached to it"))?;
 
-        let min_dst_off = update_regions
-            .iter()
-            .map(|region| region.dst_offset)
-            .min()
-            .unwrap();
-        let max_dst_off = update_regions
-            .iter()
-            .map(|region| region.dst_offset + region.size)
-            .max()
-            .unwrap();
         self.props.device.memory_barrier(
             &mut self.current_frame_resources,
             &buffer,
-            min_dst_off as vk::DeviceSize + dst_buffer_align as vk::DeviceSize,
-            (max_dst_off - min_dst_off) as vk::DeviceSize,
+            barrier_offset,
+            barrier_size,
             access_flags,
             true,
             source_stage_flags,
@@ -2432,22 +3492,13 @@ impl VulkanBackend {
             &mut self.current_frame_resources,
             &src_buffer,
             &buffer,
-            &update_regions
-                .into_iter()
-                .map(|region| vk::BufferCopy {
-                    src_offset: staging_buffer.heap_data.offset_to_align as vk::DeviceSize
-                        + region.src_offset as vk::DeviceSize,
-                    dst_offset: region.dst_offset as vk::DeviceSize
-                        + dst_buffer_align as vk::DeviceSize,
-                    size: region.size as vk::DeviceSize,
-                })
-                .collect::<Vec<_>>(),
+            copies,
         )?;
         self.props.device.memory_barrier(
             &mut self.current_frame_resources,
             &buffer,
-            min_dst_off as vk::DeviceSize + dst_buffer_align as vk::DeviceSize,
-            (max_dst_off - min_dst_off) as vk::DeviceSize,
+            barrier_offset,
+            barrier_size,
             access_flags,
             false,
             source_stage_flags,
@@ -2606,12 +3657,27 @@ impl VulkanBackend {
         cmd: &CommandOffscreenCanvasCreate,
     ) -> anyhow::Result<()> {
         let offscreen_index = cmd.offscreen_index;
+        // the multi sampling target is not stored, so it can't be loaded in the next frame
+        anyhow::ensure!(
+            !cmd.persistent || cmd.has_multi_sampling.is_none(),
+            "persistent offscreen canvases can't use multi sampling."
+        );
 
+        let (width, height) = match cmd.relative_size {
+            Some(divisor) => {
+                let window_extent = self.render.onscreen.native.swap_img_and_viewport_extent;
+                relative_canvas_extent(window_extent.width, window_extent.height, divisor)
+            }
+            None => (cmd.width, cmd.height),
+        };
         self.render.create_offscreen_canvas(
             offscreen_index,
-            cmd.width,
-            cmd.height,
+            width,
+            height,
             cmd.has_multi_sampling,
+            cmd.clear_color
+                .map(|color| [color.r, color.g, color.b, color.a]),
+            cmd.persistent,
             OffscreenCanvasCreateProps {
                 device: &self.props.device.ash_vk.device,
                 layouts: &self.props.device.layouts,
@@ -2632,6 +3698,10 @@ impl VulkanBackend {
                 should_queue_full_compile: false,
             },
         )?;
+        if let Some(divisor) = cmd.relative_size {
+            self.relative_offscreen_canvases
+                .insert(offscreen_index, divisor);
+        }
 
         Ok(())
     }
@@ -2651,6 +3721,35 @@ impl VulkanBackend {
         }
         self.handle_all_command_groups()?;
         self.render.destroy_offscreen_canvas(offscreen_index);
+        self.relative_offscreen_canvases.remove(&offscreen_index);
+        self.dynamic_viewports
+            .remove(&FrameCanvasIndex::Offscreen(offscreen_index));
+
+        Ok(())
+    }
+
+    fn cmd_resize_offscreen_canvas(
+        &mut self,
+        cmd: &CommandOffscreenCanvasResize,
+    ) -> anyhow::Result<()> {
+        let offscreen_index = cmd.offscreen_index;
+
+        // make sure all previous commands render to the old canvas size
+        if let Some(current_command_group) = self
+            .current_command_groups
+            .remove(&FrameCanvasIndex::Offscreen(offscreen_index))
+        {
+            Self::add_command_group(&mut self.command_groups, current_command_group);
+        }
+        self.handle_all_command_groups()?;
+        self.render.resize_offscreen_canvas(
+            offscreen_index,
+            cmd.width,
+            cmd.height,
+            &self.props.device.standard_texture_descr_pool,
+        )?;
+        // an explicit size overrides the relative one
+        self.relative_offscreen_canvases.remove(&offscreen_index);
 
         Ok(())
     }
@@ -2829,93 +3928,489 @@ impl VulkanBackend {
         Ok(surface)
     }
 
-    pub fn get_main_thread_data(&self) -> VulkanMainThreadData {
-        let phy_gpu = self.props.ash_vk.vk_device.phy_device.clone();
-        let instance = phy_gpu.instance.clone();
-        VulkanMainThreadData {
-            instance,
-            mem_allocator: self.props.device.mem_allocator.clone(),
-            phy_gpu,
-        }
+    pub fn get_main_thread_data(&self) -> VulkanMainThreadData {
+        let phy_gpu = self.props.ash_vk.vk_device.phy_device.clone();
+        let instance = phy_gpu.instance.clone();
+        VulkanMainThreadData {
+            instance,
+            mem_allocator: self.props.device.mem_allocator.clone(),
+            phy_gpu,
+        }
+    }
+
+    pub fn main_thread_data(loading: &VulkanBackendLoading) -> VulkanMainThreadData {
+        let instance = loading.props.ash_vk.vk_device.phy_device.instance.clone();
+        let phy_gpu = loading.props.ash_vk.vk_device.phy_device.clone();
+        let mem_allocator = loading.props.device.mem_allocator.clone();
+        VulkanMainThreadData {
+            instance,
+            mem_allocator,
+            phy_gpu,
+        }
+    }
+
+    fn create_fake_surface(&self) -> anyhow::Result<BackendSurface> {
+        let phy_gpu = &self.props.ash_vk.vk_device.phy_device;
+        let instance = &phy_gpu.instance;
+        unsafe {
+            BackendWindow::create_fake_headless_surface().create_vk_surface(
+                &instance.vk_entry,
+                &instance.vk_instance,
+                &self.props.device.mem_allocator,
+            )
+        }
+    }
+
+    pub fn try_create_surface_impl(
+        instance: &Arc<Instance>,
+        phy_gpu: &Arc<PhyDevice>,
+        mem_allocator: &Arc<parking_lot::Mutex<VulkanAllocator>>,
+        window: &BackendWindow,
+        dbg: &ConfigDebug,
+    ) -> anyhow::Result<BackendSurface> {
+        let benchmark = Benchmark::new(dbg.bench);
+        let surface = window.create_surface(&instance.vk_entry, &instance.vk_instance)?;
+        let surface = Self::create_surface(
+            &instance.vk_entry,
+            surface,
+            &instance.vk_instance,
+            &phy_gpu.cur_device,
+            phy_gpu.queue_node_index,
+            mem_allocator,
+        )?;
+        benchmark.bench("creating vk surface");
+
+        Ok(surface)
+    }
+
+    pub fn init_from_main_thread(
+        data: VulkanMainThreadData,
+        window: &BackendWindow,
+        dbg: &ConfigDebug,
+    ) -> anyhow::Result<VulkanMainThreadInit> {
+        Ok(VulkanMainThreadInit {
+            surface: Self::try_create_surface_impl(
+                &data.instance,
+                &data.phy_gpu,
+                &data.mem_allocator,
+                window,
+                dbg,
+            )?,
+        })
+    }
+
+    pub fn set_from_main_thread(&mut self, data: VulkanMainThreadInit) -> anyhow::Result<()> {
+        self.wait_frame()?;
+        log::info!("new surface from main thred, recreating swapchain.");
+        self.reinit_vulkan_swap_chain(|_| &data.surface)?;
+        self.ash_surf.surface.replace(data.surface);
+        self.recreate_swap_chain = false;
+        self.prepare_frame()?;
+        Ok(())
+    }
+
+    pub fn recreate_with_fake_surface(&mut self) -> anyhow::Result<()> {
+        let surface = self.create_fake_surface()?;
+        self.reinit_vulkan_swap_chain(|_| &surface)?;
+        self.ash_surf.surface.replace(surface);
+        Ok(())
+    }
+
+    /// Calls `f` up to `attempts` times until it succeeds,
+    /// waiting the [`RetryBackoff::delays`] before the retries.
+    ///
+    /// Returns `None` if no attempt succeeded.
+    pub(crate) fn retry_with_backoff<T>(
+        attempts: u32,
+        backoff: RetryBackoff,
+        mut f: impl FnMut(u32) -> anyhow::Result<T>,
+    ) -> Option<T> {
+        std::iter::once(Duration::ZERO)
+            .chain(backoff.delays(attempts))
+            .take(attempts as usize)
+            .zip(0..)
+            .find_map(|(delay, attempt)| {
+                std::thread::sleep(delay);
+                f(attempt)
+                    .inspect_err(|err| log::debug!("attempt {attempt} failed: {err}"))
+                    .ok()
+            })
+    }
+
+    /// A lost surface can't be used anymore, so it's destroyed together with its
+    /// swapchain and a new surface is created for the same window.
+    /// Some compositors only give the window a working surface again after a frame or two,
+    /// so this is retried a bounded amount of times, before rendering to a fake surface.
+    fn recover_lost_surface(&mut self) -> anyhow::Result<()> {
+        let recreate_info = self.ash_surf.surface.recreate_info();
+        // the fake surface replaces the lost one, which destroys it,
+        // the swapchain of a lost surface can't be reused
+        self.ash_surf.vk_swap_chain_ash.out_of_date_ntf();
+        self.recreate_with_fake_surface()?;
+
+        let attempts = self.props.surface_lost_retries;
+        let recovered = Self::retry_with_backoff(attempts, SURFACE_LOST_BACKOFF, |_| {
+            let instance = &self.props.ash_vk.vk_device.phy_device.instance;
+            let surface = unsafe {
+                recreate_info.create_vk_surface(
+                    &instance.vk_entry,
+                    &instance.vk_instance,
+                    &self.props.device.mem_allocator,
+                )
+            }?;
+            self.reinit_vulkan_swap_chain(|_| &surface)?;
+            self.ash_surf.surface.replace(surface);
+            Ok(())
+        })
+        .is_some();
+        if !recovered {
+            log::warn!("surface did not recover, rendering to a fake surface.");
+        }
+        self.recreate_swap_chain = false;
+        Ok(())
+    }
+
+    /// Replaces the surface by a new one for the same window and recreates the swapchain,
+    /// the same way as if the surface was reported as lost.
+    /// Useful if the surface was invalidated without vulkan noticing it.
+    pub fn recover_surface(&mut self) -> anyhow::Result<()> {
+        if !self.can_render() {
+            // no frame is in flight, the surface is recreated with the window
+            return Ok(());
+        }
+        self.wait_frame()?;
+        self.recover_lost_surface()?;
+        self.prepare_frame()
+    }
+    /// Captures the last presented image of the given canvas,
+    /// without the need of a frame fetcher.
+    ///
+    /// Returns [`FetchCanvasError::NotPresentedYet`] if no frame was presented yet.
+    pub fn capture_current_frame(
+        &mut self,
+        index: FetchCanvasIndex,
+    ) -> anyhow::Result<BackendPresentedImageDataRgba> {
+        Ok(self.get_presented_image_data_impl(index, Default::default())?)
+    }
+
+    /// The amount of mip levels the texture in `slot` was created with.
+    pub fn texture_mip_map_count(&self, slot: u128) -> anyhow::Result<u32> {
+        self.props
+            .device
+            .textures
+            .get(&slot)
+            .map(|tex| tex.mip_map_count)
+            .ok_or(anyhow!("texture with that index does not exist"))
+    }
+
+    /// Reads back the first mip level of the texture in `slot`.
+    ///
+    /// Returns `(width, height, rgba)`. Only RGBA 2D textures are supported for now.
+    pub fn read_texture(&mut self, slot: u128) -> anyhow::Result<(u32, u32, Vec<u8>)> {
+        let tex = self
+            .props
+            .device
+            .textures
+            .get(&slot)
+            .ok_or(anyhow!("texture with that index does not exist"))?;
+        let TextureData::Tex2D { img, .. } = &tex.data else {
+            return Err(anyhow!("reading back 3d textures is not supported."));
+        };
+        if tex.format != vk::Format::R8G8B8A8_UNORM {
+            return Err(anyhow!(
+                "only rgba textures can be read back, the texture has the format {:?}.",
+                tex.format
+            ));
+        }
+        let img = img.clone();
+        let (width, height) = (tex.width, tex.height);
+        let image_total_size = width as usize * height as usize * 4;
+
+        let (buffer, mem) = self.props.device.mem.create_buffer(
+            image_total_size as vk::DeviceSize,
+            MemoryBlockType::Buffer,
+            vk::BufferUsageFlags::TRANSFER_DST,
+            vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_CACHED,
+        )?;
+        let mapped_memory = MappedMemory::new(self.props.ash_vk.vk_device.clone(), mem.clone(), 0)?;
+
+        // the memory command buffer also contains pending uploads to the texture
+        let command_buffer = self
+            .props
+            .device
+            .get_memory_command_buffer(&mut FrameResources::new(None))?
+            .command_buffer;
+
+        self.props
+            .device
+            .image_barrier(
+                &mut self.current_frame_resources,
+                &img,
+                0,
+                1,
+                0,
+                1,
+                vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+                vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+            )
+            .map_err(|err| anyhow!("Image barrier failed for the texture: {err}"))?;
+
+        // a buffer copy without a row length is tightly packed,
+        // unlike the linear helper image of the presented images,
+        // so no row pitch has to be removed
+        let mut region = vk::BufferImageCopy::default();
+        region.image_subresource.aspect_mask = vk::ImageAspectFlags::COLOR;
+        region.image_subresource.layer_count = 1;
+        region.image_extent.width = width;
+        region.image_extent.height = height;
+        region.image_extent.depth = 1;
+
+        unsafe {
+            self.props.ash_vk.vk_device.device.cmd_copy_image_to_buffer(
+                command_buffer,
+                img.img(&mut self.current_frame_resources),
+                vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+                buffer.get_buffer(&mut self.current_frame_resources),
+                &[region],
+            );
+        }
+
+        self.props
+            .device
+            .image_barrier(
+                &mut self.current_frame_resources,
+                &img,
+                0,
+                1,
+                0,
+                1,
+                vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+                vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+            )
+            .map_err(|err| anyhow!("Image barrier failed for the texture: {err}"))?;
+
+        self.execute_memory_command_buffer();
+
+        let mut mem_range = vk::MappedMemoryRange::default();
+        mem_range.memory = mem.mem(&mut FrameResources::new(None));
+        mem_range.offset = 0;
+        mem_range.size = vk::WHOLE_SIZE;
+        unsafe {
+            self.props
+                .ash_vk
+                .vk_device
+                .device
+                .invalidate_mapped_memory_ranges(&[mem_range])
+        }?;
+
+        let data = unsafe { std::slice::from_raw_parts(mapped_memory.get_mem(), image_total_size) };
+
+        Ok((width, height, data.to_vec()))
+    }
+
+    /// Reads back `size` bytes at `offset` of the buffer object `buffer_index`,
+    /// e.g. to verify that an upload ended up in GPU memory as expected.
+    pub fn read_buffer_object(
+        &mut self,
+        buffer_index: u128,
+        offset: usize,
+        size: usize,
+    ) -> anyhow::Result<Vec<u8>> {
+        let buffer_object = self
+            .props
+            .device
+            .buffer_objects
+            .get(&buffer_index)
+            .ok_or(anyhow!("buffer object with that index does not exist"))?;
+        let src_buffer = buffer_object.cur_buffer.clone();
+        let buffer_offset = buffer_object.cur_buffer_offset;
+        let buffer_size = buffer_object.buffer_object.mem.heap_data.allocation_size;
+        self.read_buffer(
+            src_buffer,
+            buffer_offset,
+            buffer_size,
+            offset,
+            size,
+            vk::AccessFlags::VERTEX_ATTRIBUTE_READ,
+            vk::PipelineStageFlags::VERTEX_INPUT,
+        )
+    }
+
+    /// Reads back `size` bytes at `offset` of the shader storage `shader_storage_index`,
+    /// see [`Self::read_buffer_object`].
+    pub fn read_shader_storage(
+        &mut self,
+        shader_storage_index: u128,
+        offset: usize,
+        size: usize,
+    ) -> anyhow::Result<Vec<u8>> {
+        let shader_storage = self
+            .props
+            .device
+            .shader_storages
+            .get(&shader_storage_index)
+            .ok_or(anyhow!("shader storage with that index does not exist"))?;
+        let src_buffer = shader_storage.buffer.cur_buffer.clone();
+        let buffer_offset = shader_storage.buffer.cur_buffer_offset;
+        let buffer_size = shader_storage
+            .buffer
+            .buffer_object
+            .mem
+            .heap_data
+            .allocation_size;
+        self.read_buffer(
+            src_buffer,
+            buffer_offset,
+            buffer_size,
+            offset,
+            size,
+            vk::AccessFlags::SHADER_READ,
+            vk::PipelineStageFlags::VERTEX_SHADER,
+        )
     }
 
-    pub fn main_thread_data(loading: &VulkanBackendLoading) -> VulkanMainThreadData {
-        let instance = loading.props.ash_vk.vk_device.phy_device.instance.clone();
-        let phy_gpu = loading.props.ash_vk.vk_device.phy_device.clone();
-        let mem_allocator = loading.props.device.mem_allocator.clone();
-        VulkanMainThreadData {
-            instance,
-            mem_allocator,
-            phy_gpu,
+    /// `access` & `stage` describe how the buffer is used while rendering.
+    fn read_buffer(
+        &mut self,
+        src_buffer: Arc<Buffer>,
+        buffer_offset: usize,
+        buffer_size: usize,
+        offset: usize,
+        size: usize,
+        access: vk::AccessFlags,
+        stage: vk::PipelineStageFlags,
+    ) -> anyhow::Result<Vec<u8>> {
+        if size == 0 {
+            return Ok(Vec::new());
         }
-    }
+        if offset + size > buffer_size {
+            return Err(anyhow!(
+                "reading {size} bytes at offset {offset} is outside of the buffer."
+            ));
+        }
+        let src_offset = (buffer_offset + offset) as vk::DeviceSize;
 
-    fn create_fake_surface(&self) -> anyhow::Result<BackendSurface> {
-        let phy_gpu = &self.props.ash_vk.vk_device.phy_device;
-        let instance = &phy_gpu.instance;
+        let (buffer, mem) = self.props.device.mem.create_buffer(
+            size as vk::DeviceSize,
+            MemoryBlockType::Buffer,
+            vk::BufferUsageFlags::TRANSFER_DST,
+            vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_CACHED,
+        )?;
+        let mapped_memory = MappedMemory::new(self.props.ash_vk.vk_device.clone(), mem.clone(), 0)?;
+
+        // the memory command buffer also contains pending uploads to the buffer
+        let command_buffer = self
+            .props
+            .device
+            .get_memory_command_buffer(&mut FrameResources::new(None))?
+            .command_buffer;
+
+        self.props.device.memory_barrier(
+            &mut self.current_frame_resources,
+            &src_buffer,
+            src_offset,
+            size as vk::DeviceSize,
+            access,
+            true,
+            stage,
+        )?;
         unsafe {
-            BackendWindow::create_fake_headless_surface().create_vk_surface(
-                &instance.vk_entry,
-                &instance.vk_instance,
-                &self.props.device.mem_allocator,
-            )
+            self.props.ash_vk.vk_device.device.cmd_copy_buffer(
+                command_buffer,
+                src_buffer.get_buffer(&mut self.current_frame_resources),
+                buffer.get_buffer(&mut self.current_frame_resources),
+                &[vk::BufferCopy {
+                    src_offset,
+                    dst_offset: 0,
+                    size: size as vk::DeviceSize,
+                }],
+            );
         }
-    }
-
-    pub fn try_create_surface_impl(
-        instance: &Arc<Instance>,
-        phy_gpu: &Arc<PhyDevice>,
-        mem_allocator: &Arc<parking_lot::Mutex<VulkanAllocator>>,
-        window: &BackendWindow,
-        dbg: &ConfigDebug,
-    ) -> anyhow::Result<BackendSurface> {
-        let benchmark = Benchmark::new(dbg.bench);
-        let surface = window.create_surface(&instance.vk_entry, &instance.vk_instance)?;
-        let surface = Self::create_surface(
-            &instance.vk_entry,
-            surface,
-            &instance.vk_instance,
-            &phy_gpu.cur_device,
-            phy_gpu.queue_node_index,
-            mem_allocator,
+        self.props.device.memory_barrier(
+            &mut self.current_frame_resources,
+            &src_buffer,
+            src_offset,
+            size as vk::DeviceSize,
+            access,
+            false,
+            stage,
         )?;
-        benchmark.bench("creating vk surface");
 
-        Ok(surface)
+        self.execute_memory_command_buffer();
+
+        let mut mem_range = vk::MappedMemoryRange::default();
+        mem_range.memory = mem.mem(&mut FrameResources::new(None));
+        mem_range.offset = 0;
+        mem_range.size = vk::WHOLE_SIZE;
+        unsafe {
+            self.props
+                .ash_vk
+                .vk_device
+                .device
+                .invalidate_mapped_memory_ranges(&[mem_range])
+        }?;
+
+        let data = unsafe { std::slice::from_raw_parts(mapped_memory.get_mem(), size) };
+
+        Ok(data.to_vec())
     }
 
-    pub fn init_from_main_thread(
-        data: VulkanMainThreadData,
-        window: &BackendWindow,
-        dbg: &ConfigDebug,
-    ) -> anyhow::Result<VulkanMainThreadInit> {
-        Ok(VulkanMainThreadInit {
-            surface: Self::try_create_surface_impl(
-                &data.instance,
-                &data.phy_gpu,
-                &data.mem_allocator,
-                window,
-                dbg,
-            )?,
-        })
+    /// Recreates the swapchain before the next frame is prepared,
+    /// e.g. after the window moved to a monitor with different capabilities
+    /// without being resized.
+    pub fn request_swapchain_recreate(&mut self) {
+        if is_verbose(&self.props.dbg) {
+            info!("queue recreate swapchain because it was requested explicitly.");
+        }
+        self.recreate_swap_chain = true;
     }
 
-    pub fn set_from_main_thread(&mut self, data: VulkanMainThreadInit) -> anyhow::Result<()> {
+    /// Finishes the current frame and recreates the swapchain right away,
+    /// instead of waiting for the next frame like [`Self::request_swapchain_recreate`].
+    pub fn force_recreate_now(&mut self) -> anyhow::Result<()> {
+        if !self.can_render() {
+            // no frame is in flight, recreate it once there is a surface again
+            self.request_swapchain_recreate();
+            return Ok(());
+        }
         self.wait_frame()?;
-        log::info!("new surface from main thred, recreating swapchain.");
-        self.reinit_vulkan_swap_chain(|_| &data.surface)?;
-        self.ash_surf.surface.replace(data.surface);
+        if is_verbose(&self.props.dbg) {
+            info!("recreating swap chain because it was forced explicitly.");
+        }
         self.recreate_swap_chain = false;
+        self.recreate_swap_chain()?;
         self.prepare_frame()?;
         Ok(())
     }
 
-    pub fn recreate_with_fake_surface(&mut self) -> anyhow::Result<()> {
-        let surface = self.create_fake_surface()?;
-        self.reinit_vulkan_swap_chain(|_| &surface)?;
-        self.ash_surf.surface.replace(surface);
-        Ok(())
+    /// The most staging memory that was allocated at once, in bytes.
+    pub fn staging_high_water_mark(&self) -> u64 {
+        self.props
+            .device
+            .mem_allocator
+            .lock()
+            .staging_high_water_mark()
+    }
+
+    /// Makes custom pipes that were added to the shared pipe list usable.
+    /// Their pipelines are created with the swapchain, so it's recreated right away.
+    /// Offscreen canvases that already exist keep their pipelines.
+    pub fn custom_pipes_changed(&mut self) -> anyhow::Result<()> {
+        self.props.custom_pipes.update_pipe_indices();
+        self.force_recreate_now()
+    }
+
+    /// Pauses rendering, e.g. while the window is minimized.
+    /// While paused, frames only execute memory commands (like buffer & texture updates),
+    /// no image is acquired or presented, so the gpu is mostly idle.
+    /// The image that was acquired before pausing is used by the first frame after resuming.
+    pub fn set_render_paused(&mut self, paused: bool) {
+        if self.render_paused != paused && is_verbose(&self.props.dbg) {
+            info!("{} rendering.", if paused { "pausing" } else { "resuming" });
+        }
+        self.render_paused = paused;
     }
+
     pub fn surface_lost(&mut self) -> anyhow::Result<()> {
         self.wait_frame()?;
         log::warn!("surface lost, creating fake surface.");
@@ -2933,6 +4428,7 @@ impl VulkanBackend {
         main_thread_data: VulkanMainThreadInit,
         window_width: u32,
         window_height: u32,
+        content_scale: f64,
         options: &Options,
 
         write_files: BackendWriteFiles,
@@ -3048,6 +4544,7 @@ impl VulkanBackend {
             std::mem::size_of::<GlVertexTex3DStream>(),
             StreamDataMax::MaxVertices as usize,
             1,
+            1.0,
         );
 
         let streamed_uniform_buffers_pool = StreamMemoryPool::new(
@@ -3063,6 +4560,7 @@ impl VulkanBackend {
             GRAPHICS_DEFAULT_UNIFORM_SIZE,
             GRAPHICS_MAX_UNIFORM_RENDER_COUNT,
             GRAPHICS_UNIFORM_INSTANCE_COUNT,
+            options.gl.uniform_pool_growth_factor,
         );
 
         let cur_stream_vertex_buffer = StreamMemoryBlock::new(
@@ -3112,25 +4610,34 @@ impl VulkanBackend {
             last_render_thread_index: Default::default(),
 
             recreate_swap_chain: Default::default(),
-            has_dynamic_viewport: Default::default(),
-            dynamic_viewport_offset: Default::default(),
-            dynamic_viewport_size: Default::default(),
+            render_paused: false,
+            dynamic_viewports: Default::default(),
 
             main_render_command_buffer: Default::default(),
             cur_frame: Default::default(),
             order_id_gen: Default::default(),
             image_last_frame_check: Default::default(),
 
-            fetch_frame_buffer: Default::default(),
+            fetch_frame_buffers: Default::default(),
+            fetch_frame_fence: Default::default(),
+            fetch_depth_buffer: Default::default(),
             last_presented_swap_chain_image_index: u32::MAX,
             frame_fetchers: Default::default(),
             frame_data_pool: MtPool::with_capacity(0),
             offscreen_canvases_frame_fetching_skips: Default::default(),
+            relative_offscreen_canvases: Default::default(),
+            has_unsubmitted_work: false,
+            frame_pacer: FramePacer::new(options.gl.max_fps),
+            cur_frame_stats: Default::default(),
+            cur_frame_render_passes: Default::default(),
+            last_frame_stats: Default::default(),
+            wait_stats: Default::default(),
 
             frame: Frame::new(),
 
             window_width,
             window_height,
+            content_scale,
             clear_color: [
                 options.gl.clear_color.r as f32 / 255.0,
                 options.gl.clear_color.g as f32 / 255.0,
@@ -3138,6 +4645,9 @@ impl VulkanBackend {
                 1.0,
             ],
 
+            pending_texture_uploads: Default::default(),
+            frames_in_flight: Default::default(),
+
             command_groups: Default::default(),
             current_command_groups: Default::default(),
             current_frame_resources: frame_resouces,
@@ -3168,34 +4678,8 @@ impl VulkanBackend {
         );
 
         for i in 0..thread_count {
-            let frame = res.frame.clone();
-            let device = res.props.ash_vk.vk_device.clone();
-            let queue_index = res.props.ash_vk.vk_device.phy_device.queue_node_index;
-            let custom_pipes = res.props.custom_pipes.clone();
-
-            let (sender, receiver) = unbounded();
-
-            let events: Arc<AtomicUsize> = Default::default();
-            let events_counter = events.clone();
-
-            let thread = std::thread::Builder::new()
-                .name(format!("vk-render {i}"))
-                .spawn(move || {
-                    Self::run_thread(
-                        receiver,
-                        events_counter,
-                        frame,
-                        device,
-                        queue_index,
-                        custom_pipes,
-                    )
-                })?;
-
-            res.render_threads.push(Arc::new(RenderThread {
-                sender,
-                events,
-                _thread: JoinThread::new(thread),
-            }));
+            let render_thread = res.spawn_render_thread(i)?;
+            res.render_threads.push(Arc::new(render_thread));
         }
 
         benchmark.bench("creating vk render threads");
@@ -3211,8 +4695,81 @@ impl VulkanBackend {
      * RENDER THREADS
      *****************/
 
+    fn spawn_render_thread(&self, index: usize) -> anyhow::Result<RenderThread> {
+        let frame = self.frame.clone();
+        let device = self.props.ash_vk.vk_device.clone();
+        let queue_index = self.props.ash_vk.vk_device.phy_device.queue_node_index;
+        let custom_pipes = self.props.custom_pipes.clone();
+
+        let (sender, receiver) = unbounded();
+        let (errors_sender, errors) = unbounded();
+
+        let events: Arc<AtomicUsize> = Default::default();
+        let events_counter = events.clone();
+
+        let thread = std::thread::Builder::new()
+            .name(format!("vk-render {index}"))
+            .spawn(move || {
+                Self::run_thread(
+                    receiver,
+                    errors_sender,
+                    events_counter,
+                    frame,
+                    device,
+                    queue_index,
+                    custom_pipes,
+                )
+            })?;
+
+        Ok(RenderThread {
+            sender,
+            events,
+            errors,
+            thread: parking_lot::Mutex::new(JoinThread::new(thread)),
+        })
+    }
+
+    /// Replaces the render thread at `index` with a newly spawned one,
+    /// e.g. after it panicked or reported an error.
+    ///
+    /// The old thread is joined, the new thread creates its own command pool.
+    /// Commands that were not rendered by the old thread are lost,
+    /// so this should only be called between frames.
+    pub fn restart_render_thread(&mut self, index: usize) -> anyhow::Result<()> {
+        anyhow::ensure!(
+            index < self.render_threads.len(),
+            "render thread {index} does not exist, there are only {} render threads.",
+            self.render_threads.len()
+        );
+
+        // the old thread's command buffers might still be in use by the GPU
+        unsafe { self.props.ash_vk.vk_device.device.device_wait_idle() }?;
+
+        let render_thread = self.spawn_render_thread(index)?;
+        let old_thread =
+            std::mem::replace(&mut self.render_threads[index], Arc::new(render_thread));
+
+        {
+            let mut thread = old_thread.thread.lock();
+            if thread.is_finished() {
+                if let Err(err) = thread.try_join() {
+                    log::warn!("restarted render thread {index}, it panicked before: {err}");
+                }
+            }
+        }
+        while let Ok(err) = old_thread.errors.try_recv() {
+            log::warn!("restarted render thread {index}, it failed before: {err}");
+        }
+        // dropping the sender stops a thread that is still running,
+        // the join thread then waits for it.
+        drop(old_thread);
+
+        Ok(())
+    }
+
     fn run_thread(
         receiver: Receiver<RenderThreadEvent>,
+        errors: Sender<anyhow::Error>,
         events_count: Arc<AtomicUsize>,
         frame: Arc<parking_lot::Mutex<Frame>>,
         device: Arc<LogicalDevice>,
@@ -3269,7 +4826,7 @@ impl VulkanBackend {
                             next_cmd,
                             &command_buffer,
                         ) {
-                            // an error occured, the thread will not continue execution
+                            // an error occured, the remaining commands of the group are skipped
                             has_error_from_cmd = Some(err);
                             break;
                         }
@@ -3283,8 +4840,9 @@ impl VulkanBackend {
                 RenderThreadEvent::Sync(sender) => sender.send(()).unwrap(),
             }
             if let Some(err) = has_error_from_cmd {
-                log::error!("FATAL ERROR: {err}");
-                panic!("TODO:")
+                log::error!("render command failed: {err}");
+                // the main thread returns the error when it waits for the render threads
+                let _ = errors.send(err);
             }
 
             benchmark.bench("vulkan render thread");
@@ -3293,6 +4851,20 @@ impl VulkanBackend {
         }
     }
 
+    /// How many stream uniform instances exist and how many of them are free.
+    pub fn uniform_instance_pool_occupancy(&self) -> StreamMemoryPoolOccupancy {
+        self.streamed_uniform_buffers_pool.occupancy()
+    }
+
+    /// How often the stream uniform instances, including their descriptor sets, were allocated.
+    pub fn uniform_instance_pool_allocations(&self) -> u64 {
+        self.streamed_uniform_buffers_pool.allocations()
+    }
+
+    /// The stream data for the next frame.
+    /// The instances of previous frames that are no longer in flight
+    /// were returned to the pools when their frame resources were freed,
+    /// so new instances are only allocated if the pools can't serve this frame.
     pub fn get_stream_data(&mut self) -> anyhow::Result<VulkanInUseStreamData> {
         let cur_stream_vertex_buffer = self
             .streamed_vertex_buffers_pool
@@ -3309,11 +4881,87 @@ impl VulkanBackend {
             cur_stream_uniform_buffers,
         })
     }
+    /// Makes sure the streamed vertices fit into the stream vertex buffer,
+    /// which holds `capacity` vertices.
+    pub(crate) fn check_stream_vertices_fit(
+        vertices_count: usize,
+        capacity: usize,
+    ) -> anyhow::Result<()> {
+        anyhow::ensure!(
+            vertices_count <= capacity,
+            "{vertices_count} vertices were streamed, \
+            but the stream vertex buffer only fits {capacity} vertices."
+        );
+        Ok(())
+    }
+
+    /// Makes sure a texture update lies inside of the texture
+    /// and that `data` covers the whole updated region.
+    /// Updates of `compressed` textures must be aligned to the blocks of the format,
+    /// only at the right & bottom edge of the texture the region may end inside a block.
+    /// Returns the size of the updated region in bytes.
+    pub(crate) fn check_texture_update_region(
+        x: i64,
+        y: i64,
+        width: usize,
+        height: usize,
+        compressed: Option<TextureCompressedFormat>,
+        data_len: usize,
+        tex_width: u32,
+        tex_height: u32,
+    ) -> anyhow::Result<usize> {
+        let fits = |off: i64, size: usize, tex_size: u32| {
+            off >= 0
+                && (off as u64)
+                    .checked_add(size as u64)
+                    .is_some_and(|end| end <= tex_size as u64)
+        };
+        anyhow::ensure!(
+            fits(x, width, tex_width) && fits(y, height, tex_height),
+            "the texture update at ({x}, {y}) with size {width}x{height} \
+            is outside of the {tex_width}x{tex_height} texture."
+        );
+        let region_size = match compressed {
+            Some(format) => {
+                let (block_width, block_height) = format.block_extent();
+                // the region lies inside the texture, so it ends at most at the edge
+                let aligned = |off: i64, size: usize, block_size: usize, tex_size: u32| {
+                    off as usize % block_size == 0
+                        && (size % block_size == 0 || off as usize + size == tex_size as usize)
+                };
+                anyhow::ensure!(
+                    aligned(x, width, block_width, tex_width)
+                        && aligned(y, height, block_height, tex_height),
+                    "the texture update at ({x}, {y}) with size {width}x{height} \
+                    is not aligned to the {block_width}x{block_height} blocks of the {format:?} texture."
+                );
+                Some(format.image_size(width, height))
+            }
+            None => width
+                .checked_mul(height)
+                .and_then(|size| size.checked_mul(4)),
+        };
+        let region_size = region_size
+            .filter(|&size| size <= data_len)
+            .ok_or_else(|| {
+                anyhow!(
+                    "the texture update of size {width}x{height} only contains {data_len} bytes."
+                )
+            })?;
+        Ok(region_size)
+    }
+
     pub fn set_stream_data_in_use(
         &mut self,
         stream_data: &GraphicsStreamedData,
         data: &VulkanInUseStreamData,
     ) -> anyhow::Result<()> {
+        // the stream vertex buffers are allocated for the max vertices
+        Self::check_stream_vertices_fit(
+            stream_data.vertices_count(),
+            StreamDataMax::MaxVertices as usize,
+        )?;
+
         self.current_frame_resources
             .stream_vertex_buffers
             .push(data.cur_stream_vertex_buffer.clone());
@@ -3358,13 +5006,29 @@ impl VulkanBackend {
             mem_allocator: data.mem_allocator.clone(),
             flush_lock: Default::default(),
             gpus: data.phy_gpu.gpu_list.clone(),
+            vram_estimate: data.phy_gpu.vram_estimate,
+            memory_pressure_callback: Default::default(),
         }
     }
+
+    /// Information about the gpu this backend renders with.
+    pub fn gpu_info(&self) -> GpuInfo {
+        let phy_device = &self.props.ash_vk.vk_device.phy_device;
+        GpuInfo::new(&phy_device.gpu_list, phy_device.vram_estimate)
+    }
 }
 
 impl DriverBackendInterface for VulkanBackend {
-    fn attach_frame_fetcher(&mut self, name: String, fetcher: Arc<dyn BackendFrameFetcher>) {
+    fn attach_frame_fetcher(
+        &mut self,
+        name: String,
+        fetcher: Arc<dyn BackendFrameFetcher>,
+    ) -> Result<(), FrameFetcherAttachError> {
+        if self.frame_fetchers.contains_key(&name) {
+            return Err(FrameFetcherAttachError::AlreadyAttached);
+        }
         self.frame_fetchers.insert(name, fetcher);
+        Ok(())
     }
 
     fn detach_frame_fetcher(&mut self, name: String) {
@@ -3372,11 +5036,21 @@ impl DriverBackendInterface for VulkanBackend {
     }
 
     fn run_command(&mut self, cmd: AllCommands) -> anyhow::Result<()> {
+        if !matches!(cmd, AllCommands::Misc(CommandsMisc::Swap)) {
+            self.has_unsubmitted_work = true;
+        }
+
         let mut buffer = RenderCommandExecuteBuffer::default();
         buffer.viewport_size = self.render.get().native.swap_img_and_viewport_extent;
 
         let mut can_start_thread: bool = false;
         if let AllCommands::Render(render_cmd) = &cmd {
+            if !self.pending_texture_uploads.is_empty()
+                && self.render_cmd_uses_pending_texture(render_cmd)
+            {
+                self.process_pending_texture_uploads(true)?;
+            }
+
             let thread_index = ((self.cur_render_cmds_count_in_pipe * self.props.thread_count)
                 / self.commands_in_pipe.max(1))
                 % self.props.thread_count;
@@ -3395,7 +5069,7 @@ impl DriverBackendInterface for VulkanBackend {
             if let AllCommands::Misc(cmd) = cmd {
                 self.command_cb_misc(cmd)?;
             }
-        } else if self.ash_surf.surface.can_render() {
+        } else if self.can_render() {
             if let AllCommands::Render(render_cmd) = cmd {
                 buffer.raw_render_command = Some(render_cmd)
             }
@@ -3403,6 +5077,7 @@ impl DriverBackendInterface for VulkanBackend {
                 .current_command_groups
                 .get_mut(&self.render.cur_canvas())
             {
+                self.cur_frame_stats.estimated_draw_calls += buffer.estimated_render_call_count;
                 current_command_group.cmds.push(buffer);
 
                 if can_start_thread {
@@ -3432,6 +5107,11 @@ impl DriverBackendInterface for VulkanBackend {
 
 impl Drop for VulkanBackend {
     fn drop(&mut self) {
+        // safety net, if the client did not drain the backend itself
+        if let Err(err) = self.flush_and_drain() {
+            log::warn!("could not submit the outstanding work before shutdown: {err}");
+        }
+
         unsafe {
             let _g = self.props.queue.queues.lock();
             self.props
@@ -3455,6 +5135,22 @@ pub struct VulkanBackendMt {
     pub mem_allocator: Arc<parking_lot::Mutex<VulkanAllocator>>,
     pub flush_lock: parking_lot::Mutex<()>,
     pub gpus: Arc<Gpus>,
+    pub vram_estimate: Option<u64>,
+    #[hiarc_skip_unsafe]
+    memory_pressure_callback: parking_lot::RwLock<Option<MemoryPressureCallback>>,
+}
+
+impl VulkanBackendMt {
+    /// Information about the gpu this backend renders with.
+    pub fn gpu_info(&self) -> GpuInfo {
+        GpuInfo::new(&self.gpus, self.vram_estimate)
+    }
+
+    /// Sets the callback that is called whenever [`GraphicsBackendMtInterface::mem_alloc`]
+    /// falls back to system memory, because no staging memory was left.
+    pub fn set_memory_pressure_callback(&self, callback: Option<MemoryPressureCallback>) {
+        *self.memory_pressure_callback.write() = callback;
+    }
 }
 
 #[derive(Debug)]
@@ -3481,141 +5177,202 @@ impl GraphicsBackendMtInterface for VulkanBackendMt {
         let buffer_data: *const c_void = std::ptr::null();
         let allocator_clone = self.mem_allocator.clone();
         let mut allocator = self.mem_allocator.lock();
-        GraphicsBackendMemory::new(
-            match alloc_type {
-                GraphicsMemoryAllocationType::VertexBuffer { required_size } => {
-                    let res = allocator.get_staging_buffer_for_mem_alloc(
-                        buffer_data,
-                        required_size.get() as vk::DeviceSize,
-                    );
-                    match res {
-                        Ok(res) => {
-                            GraphicsBackendMemoryAllocation::Static(GraphicsBackendMemoryStatic {
-                                mem: Some(res),
-                                deallocator: Some(Box::new(VulkanBackendDellocator {
-                                    mem_allocator: allocator_clone,
-                                })),
-                            })
-                        }
-                        Err(_) => {
-                            // go to slow memory as backup
-                            let mut res = Vec::new();
-                            res.resize(required_size.get(), Default::default());
-                            GraphicsBackendMemoryAllocation::Vector(res)
-                        }
+        let mem = match alloc_type {
+            GraphicsMemoryAllocationType::VertexBuffer { required_size } => {
+                let res = allocator.get_staging_buffer_for_mem_alloc(
+                    buffer_data,
+                    required_size.get() as vk::DeviceSize,
+                );
+                match res {
+                    Ok(res) => {
+                        GraphicsBackendMemoryAllocation::Static(GraphicsBackendMemoryStatic {
+                            mem: Some(res),
+                            deallocator: Some(Box::new(VulkanBackendDellocator {
+                                mem_allocator: allocator_clone,
+                            })),
+                        })
+                    }
+                    Err(_) => {
+                        // go to slow memory as backup
+                        let mut res = Vec::new();
+                        res.resize(required_size.get(), Default::default());
+                        GraphicsBackendMemoryAllocation::Vector(res)
                     }
                 }
-                GraphicsMemoryAllocationType::ShaderStorage { required_size } => {
-                    let res = allocator.get_staging_buffer_for_shader_storage_mem_alloc(
-                        buffer_data,
-                        required_size.get() as vk::DeviceSize,
-                    );
-                    match res {
-                        Ok(res) => {
-                            GraphicsBackendMemoryAllocation::Static(GraphicsBackendMemoryStatic {
-                                mem: Some(res),
-                                deallocator: Some(Box::new(VulkanBackendDellocator {
-                                    mem_allocator: allocator_clone,
-                                })),
-                            })
-                        }
-                        Err(_) => {
-                            // go to slow memory as backup
-                            let mut res = Vec::new();
-                            res.resize(required_size.get(), Default::default());
-                            GraphicsBackendMemoryAllocation::Vector(res)
-                        }
+            }
+            GraphicsMemoryAllocationType::ShaderStorage { required_size } => {
+                let res = allocator.get_staging_buffer_for_shader_storage_mem_alloc(
+                    buffer_data,
+                    required_size.get() as vk::DeviceSize,
+                );
+                match res {
+                    Ok(res) => {
+                        GraphicsBackendMemoryAllocation::Static(GraphicsBackendMemoryStatic {
+                            mem: Some(res),
+                            deallocator: Some(Box::new(VulkanBackendDellocator {
+                                mem_allocator: allocator_clone,
+                            })),
+                        })
+                    }
+                    Err(_) => {
+                        // go to slow memory as backup
+                        let mut res = Vec::new();
+                        res.resize(required_size.get(), Default::default());
+                        GraphicsBackendMemoryAllocation::Vector(res)
                     }
                 }
-                GraphicsMemoryAllocationType::TextureRgbaU8 {
-                    width,
-                    height,
+            }
+            GraphicsMemoryAllocationType::TextureRgbaU8 {
+                width,
+                height,
+                flags,
+            } => {
+                let res = allocator.get_staging_buffer_image_for_mem_alloc(
+                    buffer_data,
+                    width.get(),
+                    height.get(),
+                    1,
+                    false,
                     flags,
-                } => {
-                    let res = allocator.get_staging_buffer_image_for_mem_alloc(
-                        buffer_data,
-                        width.get(),
-                        height.get(),
-                        1,
-                        false,
-                        flags,
-                    );
-                    match res {
-                        Ok(res) => {
-                            GraphicsBackendMemoryAllocation::Static(GraphicsBackendMemoryStatic {
-                                mem: Some(res),
-                                deallocator: Some(Box::new(VulkanBackendDellocator {
-                                    mem_allocator: allocator_clone,
-                                })),
-                            })
-                        }
-                        Err(_) => {
-                            // go to slow memory as backup
-                            let mut res = Vec::new();
-                            res.resize(width.get() * height.get() * 4, Default::default());
-                            GraphicsBackendMemoryAllocation::Vector(res)
-                        }
+                    None,
+                );
+                match res {
+                    Ok(res) => {
+                        GraphicsBackendMemoryAllocation::Static(GraphicsBackendMemoryStatic {
+                            mem: Some(res),
+                            deallocator: Some(Box::new(VulkanBackendDellocator {
+                                mem_allocator: allocator_clone,
+                            })),
+                        })
+                    }
+                    Err(_) => {
+                        // go to slow memory as backup
+                        let mut res = Vec::new();
+                        res.resize(width.get() * height.get() * 4, Default::default());
+                        GraphicsBackendMemoryAllocation::Vector(res)
                     }
                 }
-                GraphicsMemoryAllocationType::TextureRgbaU82dArray {
-                    width,
-                    height,
-                    depth,
+            }
+            GraphicsMemoryAllocationType::TextureRgbaU82dArray {
+                width,
+                height,
+                depth,
+                flags,
+            } => {
+                let res = allocator.get_staging_buffer_image_for_mem_alloc(
+                    buffer_data,
+                    width.get(),
+                    height.get(),
+                    depth.get(),
+                    true,
                     flags,
-                } => {
-                    let res = allocator.get_staging_buffer_image_for_mem_alloc(
-                        buffer_data,
-                        width.get(),
-                        height.get(),
-                        depth.get(),
-                        true,
-                        flags,
-                    );
-                    match res {
-                        Ok(res) => {
-                            GraphicsBackendMemoryAllocation::Static(GraphicsBackendMemoryStatic {
-                                mem: Some(res),
-                                deallocator: Some(Box::new(VulkanBackendDellocator {
-                                    mem_allocator: allocator_clone,
-                                })),
-                            })
-                        }
-                        Err(_) => {
-                            // go to slow memory as backup
-                            let mut res = Vec::new();
-                            res.resize(
-                                width.get() * height.get() * depth.get() * 4,
-                                Default::default(),
-                            );
-                            GraphicsBackendMemoryAllocation::Vector(res)
-                        }
+                    None,
+                );
+                match res {
+                    Ok(res) => {
+                        GraphicsBackendMemoryAllocation::Static(GraphicsBackendMemoryStatic {
+                            mem: Some(res),
+                            deallocator: Some(Box::new(VulkanBackendDellocator {
+                                mem_allocator: allocator_clone,
+                            })),
+                        })
+                    }
+                    Err(_) => {
+                        // go to slow memory as backup
+                        let mut res = Vec::new();
+                        res.resize(
+                            width.get() * height.get() * depth.get() * 4,
+                            Default::default(),
+                        );
+                        GraphicsBackendMemoryAllocation::Vector(res)
                     }
                 }
-            },
-            alloc_type,
-        )
+            }
+            GraphicsMemoryAllocationType::TextureCompressed {
+                width,
+                height,
+                format,
+                flags,
+            } => {
+                let res = allocator.get_staging_buffer_image_for_mem_alloc(
+                    buffer_data,
+                    width.get(),
+                    height.get(),
+                    1,
+                    false,
+                    flags,
+                    Some(format),
+                );
+                match res {
+                    Ok(res) => {
+                        GraphicsBackendMemoryAllocation::Static(GraphicsBackendMemoryStatic {
+                            mem: Some(res),
+                            deallocator: Some(Box::new(VulkanBackendDellocator {
+                                mem_allocator: allocator_clone,
+                            })),
+                        })
+                    }
+                    Err(_) => {
+                        // go to slow memory as backup
+                        let mut res = Vec::new();
+                        res.resize(
+                            format.image_size(width.get(), height.get()),
+                            Default::default(),
+                        );
+                        GraphicsBackendMemoryAllocation::Vector(res)
+                    }
+                }
+            }
+        };
+        drop(allocator);
+
+        if let GraphicsBackendMemoryAllocation::Vector(mem) = &mem {
+            log::debug!(
+                "no staging memory left for {alloc_type:?}, fell back to {} bytes of system memory",
+                mem.len()
+            );
+            // clone the callback, so it can replace itself without deadlocking
+            let callback = self.memory_pressure_callback.read().clone();
+            if let Some(callback) = callback {
+                (callback.0)(&alloc_type, mem.len());
+            }
+        }
+
+        GraphicsBackendMemory::new(mem, alloc_type)
     }
 
     fn try_flush_mem(
         &self,
         mem: &mut GraphicsBackendMemory,
         do_expensive_flushing: bool,
+    ) -> anyhow::Result<()> {
+        self.try_flush_mem_batch(&mut [mem], do_expensive_flushing)
+    }
+
+    fn try_flush_mem_batch(
+        &self,
+        mems: &mut [&mut GraphicsBackendMemory],
+        do_expensive_flushing: bool,
     ) -> anyhow::Result<()> {
         // make sure only one flush at a time happens
         let _lock = self.flush_lock.lock();
-        let res = self
-            .mem_allocator
-            .lock()
-            .try_flush_mem(mem, do_expensive_flushing)?;
-        if let Some((fence, command_buffer, device)) = res {
-            unsafe {
-                device.wait_for_fences(&[fence], true, u64::MAX)?;
-                device.reset_command_buffer(
-                    command_buffer,
-                    vk::CommandBufferResetFlags::RELEASE_RESOURCES,
-                )
-            }?;
-        }
-        Ok(())
+        let mut mem_allocator = self.mem_allocator.lock();
+        mems.iter_mut()
+            .map(|mem| {
+                let res = mem_allocator.try_flush_mem(mem, do_expensive_flushing)?;
+                // the expensive flush reuses the same command buffer,
+                // so it has to finish before the next memory is flushed.
+                if let Some((fence, command_buffer, device)) = res {
+                    unsafe {
+                        device.wait_for_fences(&[fence], true, u64::MAX)?;
+                        device.reset_command_buffer(
+                            command_buffer,
+                            vk::CommandBufferResetFlags::RELEASE_RESOURCES,
+                        )
+                    }?;
+                }
+                anyhow::Ok(())
+            })
+            .fold(Ok(()), |res, flush_res| res.and(flush_res))
     }
 }
@@ -8,7 +8,10 @@ use anyhow::anyhow;
 use ash::vk;
 use graphics_types::{
     commands::TexFlags,
-    types::{GraphicsBackendMemory, GraphicsBackendMemoryAllocation, GraphicsMemoryAllocationType},
+    types::{
+        GraphicsBackendMemory, GraphicsBackendMemoryAllocation, GraphicsMemoryAllocationType,
+        TextureCompressedFormat,
+    },
 };
 use hiarc::Hiarc;
 use libc::c_void;
@@ -19,7 +22,7 @@ use super::{
     buffer::Buffer,
     command_buffer::CommandBuffers,
     command_pool::CommandPool,
-    common::image_mip_level_count,
+    common::{capped_mip_level_count, compressed_texture_format, image_mip_level_count},
     descriptor_layout::DescriptorSetLayout,
     descriptor_pool::DescriptorPool,
     descriptor_set::DescriptorSets,
@@ -39,6 +42,97 @@ use super::{
 // good approximation of 1024x1024 image with mipmaps
 pub const IMG_SIZE1024X1024: i64 = (1024 * 1024 * 4) * 2;
 
+/// The amount of memory blocks a staging heap is split into.
+const STAGING_HEAP_BLOCK_COUNT: usize = 3;
+
+/// How staging memory is pooled and how big it may grow.
+#[derive(Debug, Hiarc, Clone, Copy)]
+pub struct StagingMemoryBudget {
+    /// The size of a single staging heap.
+    /// Allocations smaller than a memory block of the heap share it,
+    /// bigger allocations get their own staging memory.
+    pub heap_size: vk::DeviceSize,
+    /// Up to this size the staging memory grows, before allocations fail
+    /// and uploads wait for the gpu to finish the frames that use staging memory.
+    pub max_size: vk::DeviceSize,
+}
+
+impl StagingMemoryBudget {
+    pub fn new(heap_size_mib: u32, max_size_mib: u32) -> Self {
+        let heap_size = heap_size_mib.max(1) as vk::DeviceSize * 1024 * 1024;
+        Self {
+            heap_size,
+            max_size: (max_size_mib as vk::DeviceSize * 1024 * 1024).max(heap_size),
+        }
+    }
+
+    /// The size of a single memory block inside a staging heap.
+    pub fn block_size(&self) -> vk::DeviceSize {
+        self.heap_size / STAGING_HEAP_BLOCK_COUNT as vk::DeviceSize
+    }
+
+    /// Whether `required_size` bytes of staging memory can be allocated,
+    /// while `used_size` bytes are already allocated.
+    /// If no staging memory is allocated, waiting for the gpu can't free any,
+    /// so such allocations are always allowed.
+    pub fn allows(&self, used_size: vk::DeviceSize, required_size: vk::DeviceSize) -> bool {
+        used_size == 0 || used_size.saturating_add(required_size) <= self.max_size
+    }
+}
+
+/// Allocates staging memory inside the limits of a [`StagingMemoryBudget`].
+#[derive(Debug, Hiarc)]
+pub struct StagingMemory {
+    pub budget: StagingMemoryBudget,
+    /// The most staging memory that was allocated at once.
+    high_water_mark: vk::DeviceSize,
+    /// Set after the backend waited for the gpu,
+    /// the next allocation may grow beyond the budget.
+    over_budget_allowed: bool,
+}
+
+impl StagingMemory {
+    pub fn new(budget: StagingMemoryBudget) -> Self {
+        Self {
+            budget,
+            high_water_mark: 0,
+            over_budget_allowed: false,
+        }
+    }
+
+    fn alloc(
+        &mut self,
+        mem: &Memory,
+        cache: &Arc<parking_lot::Mutex<MemoryCache>>,
+        buffer_data: *const c_void,
+        required_size: vk::DeviceSize,
+        target_alignment: vk::DeviceSize,
+    ) -> anyhow::Result<Arc<MemoryBlock>, BufferAllocationError> {
+        let used_size = mem
+            .staging_memory_usage
+            .load(std::sync::atomic::Ordering::Relaxed);
+        if !self.over_budget_allowed && !self.budget.allows(used_size, required_size) {
+            return Err(BufferAllocationError::StagingMemoryLimitReached);
+        }
+        let res = mem.get_buffer_block_sized::<true>(
+            cache,
+            self.budget.block_size(),
+            STAGING_HEAP_BLOCK_COUNT,
+            vk::BufferUsageFlags::TRANSFER_SRC,
+            vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_CACHED,
+            buffer_data,
+            required_size,
+            target_alignment,
+        )?;
+        self.over_budget_allowed = false;
+        self.high_water_mark = self.high_water_mark.max(
+            mem.staging_memory_usage
+                .load(std::sync::atomic::Ordering::Relaxed),
+        );
+        Ok(res)
+    }
+}
+
 #[derive(Debug, Hiarc)]
 pub enum FlushType {
     None,
@@ -78,6 +172,8 @@ pub struct VulkanAllocatorImageCacheEntryData {
     pub is_3d_tex: bool,
     pub flags: TexFlags,
     pub mip_map_count: usize,
+    #[hiarc_skip_unsafe]
+    pub format: vk::Format,
 }
 
 #[derive(Debug, Hiarc)]
@@ -121,11 +217,14 @@ pub struct VulkanAllocator {
     pub mem: Memory,
     pub staging_buffer_cache: Arc<parking_lot::Mutex<MemoryCache>>,
     pub staging_buffer_cache_image: Arc<parking_lot::Mutex<MemoryCache>>,
+    pub staging: StagingMemory,
     pub vertex_buffer_cache: Arc<parking_lot::Mutex<MemoryCache>>,
     pub shader_storage_cache: Arc<parking_lot::Mutex<MemoryCache>>,
     pub image_buffer_caches: BTreeMap<u32, Arc<parking_lot::Mutex<MemoryCache>>>,
 
     pub limits: Limits,
+    /// The most mip levels a texture gets, 0 means the full mip chain.
+    pub max_mip_levels: u32,
 
     // private
     device: Arc<LogicalDevice>,
@@ -146,6 +245,8 @@ impl VulkanAllocator {
         limits: Limits,
         graphics_queue: Arc<Queue>,
         vertex_shader_storage_descriptor_set_layout: Arc<DescriptorSetLayout>,
+        staging_budget: StagingMemoryBudget,
+        max_mip_levels: u32,
     ) -> anyhow::Result<Arc<parking_lot::Mutex<Self>>> {
         let command_pool = CommandPool::new(
             logical_device.clone(),
@@ -168,10 +269,12 @@ impl VulkanAllocator {
             mem,
             staging_buffer_cache: MemoryCache::new(),
             staging_buffer_cache_image: MemoryCache::new(),
+            staging: StagingMemory::new(staging_budget),
             image_buffer_caches: Default::default(),
             vertex_buffer_cache: MemoryCache::new(),
             shader_storage_cache: MemoryCache::new(),
             limits,
+            max_mip_levels,
 
             ptr_work: Default::default(),
             queue: graphics_queue,
@@ -200,6 +303,9 @@ impl VulkanAllocator {
                     GraphicsMemoryAllocationType::TextureRgbaU82dArray { .. } => {
                         self.mem_block_image_exists(mem.as_ptr() as *mut _)
                     }
+                    GraphicsMemoryAllocationType::TextureCompressed { .. } => {
+                        self.mem_block_image_exists(mem.as_ptr() as *mut _)
+                    }
                     GraphicsMemoryAllocationType::VertexBuffer { .. } => {
                         self.mem_block_exists(mem.as_ptr() as *mut _)
                     }
@@ -264,6 +370,7 @@ impl VulkanAllocator {
                             1,
                             false,
                             flags,
+                            None,
                         )
                         .map_err(|err| {
                             (
@@ -291,6 +398,35 @@ impl VulkanAllocator {
                             depth.get(),
                             true,
                             flags,
+                            None,
+                        )
+                        .map_err(|err| {
+                            (
+                                GraphicsBackendMemory::new(
+                                    GraphicsBackendMemoryAllocation::Vector(m),
+                                    usage,
+                                ),
+                                err.into(),
+                            )
+                        })?;
+
+                    Ok(VulkanDeviceInternalMemory { mem: res })
+                }
+                GraphicsMemoryAllocationType::TextureCompressed {
+                    width,
+                    height,
+                    format,
+                    flags,
+                } => {
+                    let res = self
+                        .get_staging_buffer_image_for_mem_alloc(
+                            m.as_ptr() as *const _,
+                            width.get(),
+                            height.get(),
+                            1,
+                            false,
+                            flags,
+                            Some(format),
                         )
                         .map_err(|err| {
                             (
@@ -341,7 +477,8 @@ impl VulkanAllocator {
             )
     }
 
-    /// TRANSFER_DST usage is automatically applied
+    /// TRANSFER_DST & TRANSFER_SRC usage is automatically applied,
+    /// the latter allows reading the buffer back.
     pub fn get_buffer_with_usage(
         mem: &Memory,
         cache: &Arc<parking_lot::Mutex<MemoryCache>>,
@@ -350,7 +487,7 @@ impl VulkanAllocator {
     ) -> anyhow::Result<Arc<MemoryBlock>, BufferAllocationError> {
         mem.get_buffer_block_impl::<{ 8 * 1024 * 1024 }, 3, false>(
             cache,
-            vk::BufferUsageFlags::TRANSFER_DST | usage,
+            vk::BufferUsageFlags::TRANSFER_DST | vk::BufferUsageFlags::TRANSFER_SRC | usage,
             vk::MemoryPropertyFlags::DEVICE_LOCAL,
             std::ptr::null(),
             required_size,
@@ -430,15 +567,24 @@ impl VulkanAllocator {
         buffer_data: *const c_void,
         required_size: vk::DeviceSize,
     ) -> anyhow::Result<Arc<MemoryBlock>, BufferAllocationError> {
-        self.mem
-            .get_buffer_block_impl::<{ 8 * 1024 * 1024 }, 3, true>(
-                &self.staging_buffer_cache,
-                vk::BufferUsageFlags::TRANSFER_SRC,
-                vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_CACHED,
-                buffer_data,
-                required_size,
-                std::cmp::max::<vk::DeviceSize>(self.limits.non_coherent_mem_alignment, 16),
-            )
+        self.staging.alloc(
+            &self.mem,
+            &self.staging_buffer_cache,
+            buffer_data,
+            required_size,
+            std::cmp::max::<vk::DeviceSize>(self.limits.non_coherent_mem_alignment, 16),
+        )
+    }
+
+    /// The most staging memory that was allocated at once, in bytes.
+    pub fn staging_high_water_mark(&self) -> vk::DeviceSize {
+        self.staging.high_water_mark
+    }
+
+    /// Called after waiting for the gpu, which freed all staging memory it could.
+    /// The next staging allocation may grow beyond the budget.
+    pub fn allow_staging_over_budget(&mut self) {
+        self.staging.over_budget_allowed = true;
     }
 
     pub fn allocate_descriptor_pool(
@@ -625,10 +771,9 @@ impl VulkanAllocator {
         buffer_data: &[u8],
         required_size: vk::DeviceSize,
     ) -> anyhow::Result<Arc<MemoryBlock>, BufferAllocationError> {
-        mem.get_buffer_block_impl::<{ 8 * 1024 * 1024 }, 3, true>(
+        self.staging.alloc(
+            mem,
             &self.staging_buffer_cache_image,
-            vk::BufferUsageFlags::TRANSFER_SRC,
-            vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_CACHED,
             buffer_data.as_ptr() as *const c_void,
             required_size,
             std::cmp::max::<vk::DeviceSize>(
@@ -641,6 +786,7 @@ impl VulkanAllocator {
     fn get_staging_buffer_for_mem_alloc_impl<T: From<VulkanAllocatorBufferCacheEntry>>(
         mem: &Memory,
         limits: &Limits,
+        staging: &mut StagingMemory,
         staging_buffer_cache: &Arc<parking_lot::Mutex<MemoryCache>>,
         buffer_data: *const c_void,
         required_size: vk::DeviceSize,
@@ -648,10 +794,9 @@ impl VulkanAllocator {
         usage: vk::BufferUsageFlags,
         cache_map: &mut HashMap<std::ptr::NonNull<u8>, T>,
     ) -> anyhow::Result<&'static mut [u8], BufferAllocationError> {
-        let res_block = mem.get_buffer_block_impl::<{ 8 * 1024 * 1024 }, 3, true>(
+        let res_block = staging.alloc(
+            mem,
             staging_buffer_cache,
-            vk::BufferUsageFlags::TRANSFER_SRC,
-            vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_CACHED,
             buffer_data,
             required_size,
             std::cmp::max::<vk::DeviceSize>(limits.non_coherent_mem_alignment, 16),
@@ -685,6 +830,7 @@ impl VulkanAllocator {
         Self::get_staging_buffer_for_mem_alloc_impl(
             &self.mem,
             &self.limits,
+            &mut self.staging,
             &self.staging_buffer_cache,
             buffer_data,
             required_size,
@@ -702,6 +848,7 @@ impl VulkanAllocator {
         Self::get_staging_buffer_for_mem_alloc_impl(
             &self.mem,
             &self.limits,
+            &mut self.staging,
             &self.staging_buffer_cache,
             buffer_data,
             required_size,
@@ -711,6 +858,17 @@ impl VulkanAllocator {
         )
     }
 
+    /// Whether the device can sample textures of the given compressed format.
+    pub fn supports_compressed_format(&self, format: TextureCompressedFormat) -> bool {
+        let config = self.device.phy_device.config.read().unwrap();
+        match format {
+            TextureCompressedFormat::Bc7 => config.bc7_textures,
+            TextureCompressedFormat::Astc4x4 => config.astc_4x4_textures,
+        }
+    }
+
+    /// If `compressed` is `Some`, the memory holds the block compressed image
+    /// and no mipmaps are generated, else the memory is a RGBA image.
     pub fn get_staging_buffer_image_for_mem_alloc(
         &mut self,
         buffer_data: *const c_void,
@@ -720,6 +878,7 @@ impl VulkanAllocator {
         depth: usize,
         is_3d_tex: bool,
         flags: TexFlags,
+        compressed: Option<TextureCompressedFormat>,
     ) -> anyhow::Result<&'static mut [u8], ImageAllocationError> {
         if width as u32 > self.limits.max_texture_size
             || height as u32 > self.limits.max_texture_size
@@ -729,22 +888,32 @@ impl VulkanAllocator {
         {
             return Err(ImageAllocationError::ImageDimensionsTooBig);
         }
+        if compressed.is_some_and(|format| !self.supports_compressed_format(format)) {
+            return Err(ImageAllocationError::UnsupportedFormat);
+        }
 
-        let res_block = self
-            .mem
-            .get_buffer_block_impl::<{ 8 * 1024 * 1024 }, 3, true>(
-                &self.staging_buffer_cache_image,
-                vk::BufferUsageFlags::TRANSFER_SRC,
-                vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_CACHED,
-                buffer_data,
-                (width * height * depth * 4) as vk::DeviceSize,
-                std::cmp::max::<vk::DeviceSize>(
-                    self.limits.optimal_image_copy_mem_alignment,
-                    std::cmp::max::<vk::DeviceSize>(self.limits.non_coherent_mem_alignment, 16),
-                ),
-            )?;
+        let (image_size, format) = match compressed {
+            Some(format) => (
+                format.image_size(width, height) * depth,
+                compressed_texture_format(format),
+            ),
+            None => (width * height * depth * 4, vk::Format::R8G8B8A8_UNORM),
+        };
+
+        let res_block = self.staging.alloc(
+            &self.mem,
+            &self.staging_buffer_cache_image,
+            buffer_data,
+            image_size as vk::DeviceSize,
+            std::cmp::max::<vk::DeviceSize>(
+                self.limits.optimal_image_copy_mem_alignment,
+                std::cmp::max::<vk::DeviceSize>(self.limits.non_coherent_mem_alignment, 16),
+            ),
+        )?;
 
-        let requires_mip_maps = (flags & TexFlags::TEXFLAG_NOMIPMAPS).is_empty();
+        // compressed data can't be blitted, so mipmaps can't be generated
+        let requires_mip_maps =
+            (flags & TexFlags::TEXFLAG_NOMIPMAPS).is_empty() && compressed.is_none();
         let mut mip_map_level_count: usize = 1;
         if requires_mip_maps {
             let img_size = vk::Extent3D {
@@ -752,7 +921,8 @@ impl VulkanAllocator {
                 height: height as u32,
                 depth: 1,
             };
-            mip_map_level_count = image_mip_level_count(img_size);
+            mip_map_level_count =
+                capped_mip_level_count(image_mip_level_count(img_size), self.max_mip_levels);
             if !self
                 .device
                 .phy_device
@@ -770,7 +940,7 @@ impl VulkanAllocator {
             height as u32,
             depth as u32,
             mip_map_level_count,
-            vk::Format::R8G8B8A8_UNORM,
+            format,
             vk::ImageTiling::OPTIMAL,
             vk::ImageUsageFlags::TRANSFER_SRC
                 | vk::ImageUsageFlags::TRANSFER_DST
@@ -780,7 +950,7 @@ impl VulkanAllocator {
 
         let res = unsafe {
             let mem = res_block.mapped_buffer.as_ref().unwrap();
-            mem.get_mem(width * height * depth * 4)
+            mem.get_mem(image_size)
         };
 
         self.ptr_work.mapped_memory_cache_image.insert(
@@ -797,6 +967,7 @@ impl VulkanAllocator {
                     is_3d_tex,
                     flags,
                     mip_map_count: mip_map_level_count,
+                    format,
                 },
 
                 is_flushed: FlushType::None,
@@ -916,7 +1087,7 @@ impl VulkanAllocator {
                         .get(&mut RenderThreadFrameResources::new(None)),
                     &img.staging,
                     &img.img,
-                    vk::Format::R8G8B8A8_UNORM,
+                    img.data.format,
                     img.data.width,
                     img.data.height,
                     img.data.depth,
@@ -7,4 +7,9 @@ pub struct Config {
     pub optimal_swap_chain_image_blitting: bool,
     pub optimal_rgba_image_blitting: bool,
     pub linear_rgba_image_blitting: bool,
+    pub bc7_textures: bool,
+    pub astc_4x4_textures: bool,
+    pub sampler_anisotropy: bool,
+    /// Allows to rasterize only the edges of polygons.
+    pub fill_mode_non_solid: bool,
 }
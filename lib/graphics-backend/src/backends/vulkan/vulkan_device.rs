@@ -9,6 +9,7 @@ use ash::vk;
 use config::config::AtomicGfxDebugModes;
 use hiarc::Hiarc;
 use libc::c_void;
+use num_traits::FromPrimitive;
 use strum::EnumCount;
 
 use super::{
@@ -26,12 +27,14 @@ use super::{
     memory_block::DeviceMemoryBlock,
     phy_device::PhyDevice,
     queue::Queue,
-    sampler::Sampler,
+    sampler::{sampler_anisotropy, sampler_lod, Sampler, SamplerLod},
     utils::{
         build_mipmaps, complete_buffer_object, complete_shader_storage_object, complete_texture,
         copy_buffer, copy_buffer_to_image, get_memory_range,
     },
-    vulkan_allocator::{FlushType, VulkanAllocator, VulkanDeviceInternalMemory},
+    vulkan_allocator::{
+        FlushType, StagingMemoryBudget, VulkanAllocator, VulkanDeviceInternalMemory,
+    },
     vulkan_limits::Limits,
     vulkan_mem::{BufferAllocationError, ImageAllocationError, Memory},
     vulkan_types::{
@@ -41,17 +44,9 @@ use super::{
     Options,
 };
 
-type TextureSamplersRes = (
-    (Arc<Sampler>, Arc<DescriptorSetLayout>),
-    (Arc<Sampler>, Arc<DescriptorSetLayout>),
-    (Arc<Sampler>, Arc<DescriptorSetLayout>),
-);
+type TextureSamplersRes = [(Arc<Sampler>, Arc<DescriptorSetLayout>); SupportedSamplerTypes::COUNT];
 
-type DescriptorSetsRes = (
-    Arc<DescriptorSets>,
-    Arc<DescriptorSets>,
-    Arc<DescriptorSets>,
-);
+type DescriptorSetsRes = [Arc<DescriptorSets>; SupportedSamplerTypes::COUNT];
 
 #[derive(Debug, Hiarc)]
 pub struct DeviceAsh {
@@ -149,35 +144,57 @@ impl Device {
 
     fn create_texture_samplers(
         device: &Arc<LogicalDevice>,
-        limits: &Limits,
-        global_texture_lod_bias: f64,
+        anisotropy: Option<f32>,
+        lod: SamplerLod,
     ) -> anyhow::Result<TextureSamplersRes> {
-        Ok((
+        Ok([
             Device::create_texture_samplers_impl(
                 device,
-                limits.max_sampler_anisotropy,
-                global_texture_lod_bias,
+                anisotropy,
+                lod,
+                vk::Filter::LINEAR,
                 vk::SamplerAddressMode::REPEAT,
                 vk::SamplerAddressMode::REPEAT,
                 vk::SamplerAddressMode::REPEAT,
             )?,
             Device::create_texture_samplers_impl(
                 device,
-                limits.max_sampler_anisotropy,
-                global_texture_lod_bias,
+                anisotropy,
+                lod,
+                vk::Filter::LINEAR,
                 vk::SamplerAddressMode::CLAMP_TO_EDGE,
                 vk::SamplerAddressMode::CLAMP_TO_EDGE,
                 vk::SamplerAddressMode::CLAMP_TO_EDGE,
             )?,
             Device::create_texture_samplers_impl(
                 device,
-                limits.max_sampler_anisotropy,
-                global_texture_lod_bias,
+                anisotropy,
+                lod,
+                vk::Filter::LINEAR,
                 vk::SamplerAddressMode::CLAMP_TO_EDGE,
                 vk::SamplerAddressMode::CLAMP_TO_EDGE,
                 vk::SamplerAddressMode::MIRRORED_REPEAT,
             )?,
-        ))
+            // anisotropic filtering would blur the texels again
+            Device::create_texture_samplers_impl(
+                device,
+                None,
+                lod,
+                vk::Filter::NEAREST,
+                vk::SamplerAddressMode::REPEAT,
+                vk::SamplerAddressMode::REPEAT,
+                vk::SamplerAddressMode::REPEAT,
+            )?,
+            Device::create_texture_samplers_impl(
+                device,
+                None,
+                lod,
+                vk::Filter::NEAREST,
+                vk::SamplerAddressMode::CLAMP_TO_EDGE,
+                vk::SamplerAddressMode::CLAMP_TO_EDGE,
+                vk::SamplerAddressMode::CLAMP_TO_EDGE,
+            )?,
+        ])
     }
 
     pub fn new(
@@ -197,30 +214,35 @@ impl Device {
 
         command_pool: Rc<CommandPool>,
     ) -> anyhow::Result<Self> {
-        let (repeat, clamp_to_edge, texture_2d_array) = Self::create_texture_samplers(
-            &device,
-            &device.phy_device.limits,
+        let anisotropy = sampler_anisotropy(
+            options.gl.anisotropy,
+            device.phy_device.config.read().unwrap().sampler_anisotropy,
+            device.phy_device.limits.max_sampler_anisotropy,
+        );
+        let lod = sampler_lod(
             options.gl.global_texture_lod_bias,
-        )?;
-
-        let samplers: [Arc<Sampler>; SupportedSamplerTypes::COUNT] =
-            [repeat.0, clamp_to_edge.0, texture_2d_array.0];
-
+            options.gl.texture_min_lod,
+            options.gl.texture_max_lod,
+        );
+        let (samplers, sampler_layouts): (Vec<_>, Vec<_>) =
+            Self::create_texture_samplers(&device, anisotropy, lod)?
+                .into_iter()
+                .unzip();
+        let samplers: [Arc<Sampler>; SupportedSamplerTypes::COUNT] = samplers.try_into().unwrap();
         let sampler_layouts: [Arc<DescriptorSetLayout>; SupportedSamplerTypes::COUNT] =
-            [repeat.1, clamp_to_edge.1, texture_2d_array.1];
+            sampler_layouts.try_into().unwrap();
 
         let sampler_descr_pool = DeviceDescriptorPools::new(
             &device,
             SupportedSamplerTypes::COUNT as vk::DeviceSize,
             DescriptorPoolType::Sampler,
         )?;
-        let (repeat_set, clamp_to_edge_set, texture_2d_set) =
-            Self::create_new_sampler_descriptor_sets(
-                &device,
-                &sampler_layouts,
-                &sampler_descr_pool,
-                &samplers,
-            )?;
+        let sampler_sets = Self::create_new_sampler_descriptor_sets(
+            &device,
+            &sampler_layouts,
+            &sampler_descr_pool,
+            &samplers,
+        )?;
 
         let vertex_uniform_descriptor_set_layout =
             Self::create_vertex_uniform_descriptor_set_layout(&device)?;
@@ -251,6 +273,11 @@ impl Device {
                 vk_gpu.limits.clone(),
                 graphics_queue,
                 vertex_shader_storage_descriptor_set_layout.clone(),
+                StagingMemoryBudget::new(
+                    options.gl.staging_pool_size,
+                    options.gl.staging_pool_max_size,
+                ),
+                options.gl.max_mip_levels,
             )?,
 
             ash_vk: DeviceAsh {
@@ -259,11 +286,15 @@ impl Device {
 
             vk_gpu,
             non_flushed_memory_ranges: Default::default(),
-            samplers: Arc::new([
-                (samplers[0].clone(), repeat_set),
-                (samplers[1].clone(), clamp_to_edge_set),
-                (samplers[2].clone(), texture_2d_set),
-            ]),
+            samplers: Arc::new(
+                samplers
+                    .iter()
+                    .cloned()
+                    .zip(sampler_sets)
+                    .collect::<Vec<_>>()
+                    .try_into()
+                    .unwrap(),
+            ),
             textures: Default::default(),
             shader_storages: Default::default(),
 
@@ -505,16 +536,18 @@ impl Device {
 
     pub fn create_texture_samplers_impl(
         device: &Arc<LogicalDevice>,
-        max_sampler_anisotropy: u32,
-        global_texture_lod_bias: f64,
+        anisotropy: Option<f32>,
+        lod: SamplerLod,
+        filter: vk::Filter,
         addr_mode_u: vk::SamplerAddressMode,
         addr_mode_v: vk::SamplerAddressMode,
         addr_mode_w: vk::SamplerAddressMode,
     ) -> anyhow::Result<(Arc<Sampler>, Arc<DescriptorSetLayout>)> {
         let sampler = Sampler::new(
             device,
-            max_sampler_anisotropy,
-            global_texture_lod_bias,
+            anisotropy,
+            lod,
+            filter,
             addr_mode_u,
             addr_mode_v,
             addr_mode_w,
@@ -968,28 +1001,17 @@ impl Device {
         sampler_descr_pool: &Arc<parking_lot::Mutex<DeviceDescriptorPools>>,
         samplers: &[Arc<Sampler>; SupportedSamplerTypes::COUNT],
     ) -> anyhow::Result<DescriptorSetsRes, ImageAllocationError> {
-        Ok((
-            Self::create_new_sampler_descriptor_set(
-                device,
-                layouts,
-                sampler_descr_pool,
-                samplers,
-                SupportedSamplerTypes::Repeat,
-            )?,
-            Self::create_new_sampler_descriptor_set(
-                device,
-                layouts,
-                sampler_descr_pool,
-                samplers,
-                SupportedSamplerTypes::ClampToEdge,
-            )?,
-            Self::create_new_sampler_descriptor_set(
-                device,
-                layouts,
-                sampler_descr_pool,
-                samplers,
-                SupportedSamplerTypes::Texture2dArray,
-            )?,
-        ))
+        let sets = (0..SupportedSamplerTypes::COUNT)
+            .map(|address_mode| {
+                Self::create_new_sampler_descriptor_set(
+                    device,
+                    layouts,
+                    sampler_descr_pool,
+                    samplers,
+                    SupportedSamplerTypes::from_u32(address_mode as u32).unwrap(),
+                )
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(sets.try_into().unwrap())
     }
 }
@@ -48,6 +48,8 @@ pub enum BufferAllocationError {
     HeapAllocationFailed,
     #[error("Memory related operation failed: {0}")]
     MemoryRelatedOperationFailed(anyhow::Error),
+    #[error("The staging memory reached its size limit.")]
+    StagingMemoryLimitReached,
 }
 
 impl From<MemoryAllocationError> for BufferAllocationError {
@@ -82,6 +84,8 @@ pub enum ImageAllocationError {
     ImageDimensionsTooBig,
     #[error("No memory heap was found to allocate the required memory for the image.")]
     NoMemoryHeap,
+    #[error("The image format is not supported by the device.")]
+    UnsupportedFormat,
 }
 
 impl From<MemoryAllocationError> for ImageAllocationError {
@@ -234,14 +238,13 @@ impl Memory {
         }
     }
 
-    pub fn get_block_impl<
-        const MEMORY_BLOCK_SIZE: i64,
-        const BLOCK_COUNT: usize,
-        const REQUIRES_MAPPING: bool,
-        FAlloc,
-    >(
+    /// Allocations smaller than `memory_block_size` are sub allocated from cached heaps
+    /// of `block_count` memory blocks, bigger allocations get their own memory.
+    pub fn get_block_impl<const REQUIRES_MAPPING: bool, FAlloc>(
         &self,
         memory_cache: &Arc<parking_lot::Mutex<MemoryCache>>,
+        memory_block_size: vk::DeviceSize,
+        block_count: usize,
         buffer_usage: vk::BufferUsageFlags,
         buffer_properties: vk::MemoryPropertyFlags,
         buffer_data: *const c_void,
@@ -264,7 +267,7 @@ impl Memory {
     {
         // if the required size is in the region of a single memory block
         // try to find it or create it
-        if required_size < MEMORY_BLOCK_SIZE as vk::DeviceSize {
+        if required_size < memory_block_size {
             let create_or_find_cache_block = || {
                 let mut found_allocation = false;
                 let mut allocated_mem = None;
@@ -288,7 +291,7 @@ impl Memory {
                 drop(memory_cache_guard);
                 // if no heap was suited, we create a new block
                 if !found_allocation {
-                    let block_allocation_size = MEMORY_BLOCK_SIZE as u64 * BLOCK_COUNT as u64;
+                    let block_allocation_size = memory_block_size * block_count as u64;
                     let buffer_allocation = allocate_block(
                         block_allocation_size,
                         if REQUIRES_MAPPING {
@@ -326,7 +329,7 @@ impl Memory {
                         tmp_buffer,
                         res_block,
                         mapped_data_as_ptr,
-                        MEMORY_BLOCK_SIZE as usize * BLOCK_COUNT,
+                        block_allocation_size as usize,
                         0,
                     );
 
@@ -444,8 +447,35 @@ impl Memory {
         requized_size: vk::DeviceSize,
         target_alignment: vk::DeviceSize,
     ) -> anyhow::Result<Arc<MemoryBlock>, BufferAllocationError> {
-        self.get_block_impl::<{ MEMORY_BLOCK_SIZE }, { BLOCK_COUNT }, { REQUIRES_MAPPING }, _>(
+        self.get_buffer_block_sized::<{ REQUIRES_MAPPING }>(
+            memory_cache,
+            MEMORY_BLOCK_SIZE as vk::DeviceSize,
+            BLOCK_COUNT,
+            buffer_usage,
+            buffer_properties,
+            buffer_data,
+            requized_size,
+            target_alignment,
+        )
+    }
+
+    /// Like [`Memory::get_buffer_block_impl`], but with the size of the cached heaps
+    /// only known at runtime.
+    pub fn get_buffer_block_sized<const REQUIRES_MAPPING: bool>(
+        &self,
+        memory_cache: &Arc<parking_lot::Mutex<MemoryCache>>,
+        memory_block_size: vk::DeviceSize,
+        block_count: usize,
+        buffer_usage: vk::BufferUsageFlags,
+        buffer_properties: vk::MemoryPropertyFlags,
+        buffer_data: *const c_void,
+        requized_size: vk::DeviceSize,
+        target_alignment: vk::DeviceSize,
+    ) -> anyhow::Result<Arc<MemoryBlock>, BufferAllocationError> {
+        self.get_block_impl::<{ REQUIRES_MAPPING }, _>(
             memory_cache,
+            memory_block_size,
+            block_count,
             buffer_usage,
             buffer_properties,
             buffer_data,
@@ -542,8 +572,10 @@ impl Memory {
         required_alignment: vk::DeviceSize,
         required_memory_type_bits: u32,
     ) -> anyhow::Result<MemoryImageBlock, BufferAllocationError> {
-        let base_block = self.get_block_impl::<{ MEMORY_BLOCK_SIZE }, { BLOCK_COUNT }, false, _>(
+        let base_block = self.get_block_impl::<false, _>(
             memory_cache,
+            MEMORY_BLOCK_SIZE as vk::DeviceSize,
+            BLOCK_COUNT,
             vk::BufferUsageFlags::empty(),
             buffer_properties,
             std::ptr::null(),
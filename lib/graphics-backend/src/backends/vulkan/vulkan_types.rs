@@ -3,9 +3,11 @@ use std::sync::{atomic::AtomicUsize, Arc};
 use arc_swap::ArcSwap;
 use ash::vk;
 use base::join_thread::JoinThread;
-use crossbeam::channel::Sender;
+use crossbeam::channel::{Receiver, Sender};
 use graphics_backend_traits::plugin::SamplerAddressMode;
-use graphics_types::commands::StreamDataMax;
+use graphics_types::commands::{
+    CommandTextureCreate, CommandTextureUpdate, StreamDataMax, TextureUsage,
+};
 use hiarc::Hiarc;
 use num_derive::FromPrimitive;
 use strum::EnumCount;
@@ -127,6 +129,35 @@ pub struct TextureObject {
     pub data: TextureData,
 
     pub mip_map_count: u32,
+    pub width: u32,
+    pub height: u32,
+    #[hiarc_skip_unsafe]
+    pub format: vk::Format,
+    /// Textures that are not [`TextureUsage::Static`] only use a single mip level,
+    /// so updates don't rebuild the mipmaps.
+    pub usage: TextureUsage,
+}
+
+/// The maximum number of texture uploads that can wait
+/// for staging memory, before the backend falls back
+/// to stalling the GPU.
+pub const MAX_PENDING_TEXTURE_UPLOADS: usize = 64;
+
+/// A texture upload that could not get staging memory
+/// and is retried in one of the next frames.
+#[derive(Debug, Hiarc)]
+pub enum PendingTextureUpload {
+    Create(CommandTextureCreate),
+    Update(CommandTextureUpdate),
+}
+
+impl PendingTextureUpload {
+    pub fn texture_index(&self) -> u128 {
+        match self {
+            PendingTextureUpload::Create(cmd) => cmd.texture_index,
+            PendingTextureUpload::Update(cmd) => cmd.texture_index,
+        }
+    }
 }
 
 #[derive(Debug, Hiarc)]
@@ -196,11 +227,14 @@ impl Drop for ShaderModule {
     }
 }
 
+/// The index of the sampler in [`SupportedSamplerTypes`].
 #[derive(FromPrimitive, Copy, Clone, EnumCount)]
 #[repr(u32)]
 pub enum SupportedAddressModes {
     Repeat = 0,
     ClampEdges = 1,
+    RepeatNearest = 3,
+    ClampEdgesNearest = 4,
 }
 
 #[derive(Debug, Hiarc, FromPrimitive, Copy, Clone, PartialEq, EnumCount)]
@@ -319,6 +353,8 @@ pub enum SupportedSamplerTypes {
     Repeat = 0,
     ClampToEdge,
     Texture2dArray,
+    RepeatNearest,
+    ClampToEdgeNearest,
 }
 
 impl From<SupportedSamplerTypes> for SamplerAddressMode {
@@ -327,6 +363,8 @@ impl From<SupportedSamplerTypes> for SamplerAddressMode {
             SupportedSamplerTypes::Repeat => SamplerAddressMode::Repeat,
             SupportedSamplerTypes::ClampToEdge => SamplerAddressMode::ClampToEdge,
             SupportedSamplerTypes::Texture2dArray => SamplerAddressMode::Texture2dArray,
+            SupportedSamplerTypes::RepeatNearest => SamplerAddressMode::RepeatNearest,
+            SupportedSamplerTypes::ClampToEdgeNearest => SamplerAddressMode::ClampToEdgeNearest,
         }
     }
 }
@@ -345,6 +383,15 @@ pub struct SwapChainImageFull {
     pub texture_descr_sets: Arc<DescriptorSets>,
 }
 
+/// A viewport that only covers a part of its canvas.
+#[derive(Debug, Hiarc, Clone, Copy)]
+pub struct DynamicViewport {
+    #[hiarc_skip_unsafe]
+    pub offset: vk::Offset2D,
+    #[hiarc_skip_unsafe]
+    pub size: vk::Extent2D,
+}
+
 #[derive(Debug, Hiarc, Default)]
 pub struct ThreadCommandGroup {
     pub render_pass: RenderPassType,
@@ -359,6 +406,29 @@ pub struct ThreadCommandGroup {
     pub cmds: Vec<RenderCommandExecuteBuffer>,
 }
 
+/// The structure of a [`ThreadCommandGroup`] without its commands,
+/// see `CommandsMisc::DumpFrameGraph`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FrameGraphGroup {
+    pub in_order_id: usize,
+    pub canvas_index: FrameCanvasIndex,
+    pub render_pass_index: usize,
+    pub render_pass: RenderPassType,
+    pub cmd_count: usize,
+}
+
+impl From<&ThreadCommandGroup> for FrameGraphGroup {
+    fn from(command_group: &ThreadCommandGroup) -> Self {
+        Self {
+            in_order_id: command_group.in_order_id,
+            canvas_index: command_group.canvas_index,
+            render_pass_index: command_group.render_pass_index,
+            render_pass: command_group.render_pass,
+            cmd_count: command_group.cmds.len(),
+        }
+    }
+}
+
 #[derive(Debug, Hiarc)]
 pub enum RenderThreadEvent {
     ClearFrame(u32),
@@ -371,5 +441,10 @@ pub enum RenderThreadEvent {
 pub struct RenderThread {
     pub sender: Sender<RenderThreadEvent>,
     pub events: Arc<AtomicUsize>,
-    pub _thread: JoinThread<()>,
+    /// The errors of failed render commands.
+    #[hiarc_skip_unsafe]
+    pub errors: Receiver<anyhow::Error>,
+    /// Locked to join the thread after it stopped unexpectedly,
+    /// e.g. to get its panic message.
+    pub thread: parking_lot::Mutex<JoinThread<()>>,
 }
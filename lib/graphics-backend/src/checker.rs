@@ -245,6 +245,10 @@ impl GraphicsContainersAPI {
                     cmd.has_multi_sampling.is_none_or(|s| s < 64),
                     "msaa above x64 is not allowed."
                 );
+                assert!(
+                    !cmd.persistent || cmd.has_multi_sampling.is_none(),
+                    "persistent canvases can't use multi sampling."
+                );
                 let real_index = cmd.offscreen_index + self.id_offset;
                 cmd.offscreen_index = real_index;
                 assert!(
@@ -263,6 +267,19 @@ impl GraphicsContainersAPI {
                 );
                 self.offscreen_canvases.remove(&real_index);
             }
+            CommandsMisc::OffscreenCanvasResize(cmd) => {
+                assert!(cmd.offscreen_index < u64::MAX as u128, "invalid index");
+                assert!(
+                    cmd.width <= 4096 && cmd.height <= 4096,
+                    "widths & heights above 4096 pixels are currently not allowed."
+                );
+                let real_index = cmd.offscreen_index + self.id_offset;
+                cmd.offscreen_index = real_index;
+                assert!(
+                    self.offscreen_canvases.contains(&real_index),
+                    "offscreen canvas does not exists, this is not allowed"
+                );
+            }
             CommandsMisc::OffscreenCanvasSkipFetchingOnce(cmd) => {
                 assert!(cmd.offscreen_index < u64::MAX as u128, "invalid index");
                 let real_index = cmd.offscreen_index + self.id_offset;
@@ -309,7 +326,9 @@ impl GraphicsContainersAPI {
                     cmd.sample_count < 64 && (cmd.sample_count == 1 || cmd.sample_count % 2 == 0)
                 );
             }
-            CommandsMisc::VSync(_) => {
+            CommandsMisc::VSync(_)
+            | CommandsMisc::SetPolygonMode(_)
+            | CommandsMisc::DumpFrameGraph => {
                 // Nothing to do
             }
         }
@@ -24,16 +24,30 @@ pub mod window;
 
 #[cfg(test)]
 mod tests {
-    use std::{rc::Rc, sync::Arc};
+    use std::{
+        collections::{HashMap, HashSet},
+        num::{NonZeroU32, NonZeroUsize},
+        rc::Rc,
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc, Mutex,
+        },
+        time::{Duration, Instant},
+    };
 
-    use base::benchmark::Benchmark;
+    use base::{benchmark::Benchmark, join_thread::JoinThread};
     use base_fs::filesys::FileSystem;
     use base_http::http::HttpClient;
     use base_io::io::{Io, IoFileSys};
     use config::config::ConfigBackend;
     use graphics_backend_traits::{
         frame_fetcher_plugin::{
-            BackendFrameFetcher, BackendPresentedImageDataRgba, FetchCanvasError, FetchCanvasIndex,
+            BackendDepthImageData, BackendFrameFetcher, BackendPresentedImageDataRgba,
+            FetchCanvasError, FetchCanvasIndex, FrameFetcherAttachError, PresentedImageOrientation,
+        },
+        plugin::{
+            BackendCustomPipeline, BackendPipelineLayout, BackendRenderExecuteInterface,
+            BackendRenderInterface, GraphicsObjectRewriteFunc,
         },
         traits::GraphicsBackendInterface,
         types::BackendCommands,
@@ -41,24 +55,78 @@ mod tests {
     use graphics_base_traits::traits::GraphicsStreamedData;
     use graphics_types::{
         commands::{
-            AllCommands, CommandClear, CommandOffscreenCanvasCreate, CommandRender,
-            CommandSwitchCanvasMode, CommandSwitchCanvasModeType, CommandsMisc, CommandsRender,
-            CommandsRenderStream, PrimType,
+            relative_canvas_extent, AllCommands, CommandClear, CommandCreateBufferObject,
+            CommandCreateShaderStorage, CommandOffscreenCanvasCreate, CommandOffscreenCanvasResize,
+            CommandRender, CommandSwitchCanvasMode, CommandSwitchCanvasModeType,
+            CommandTextureCreate, CommandTextureUpdate, CommandUpdateBufferObject,
+            CommandUpdateBufferRegion, CommandUpdateViewport, CommandsMisc, CommandsRender,
+            CommandsRenderMod, CommandsRenderStream, PolygonMode, PrimType, StreamDataMax,
+            TexFlags, TextureUsage,
+        },
+        gpu::{CurGpu, Gpu, GpuInfo, GpuType, Gpus},
+        rendering::{ColorRgba, State, StateClip, StateTexture, TextureFilter, WrapType},
+        types::{
+            GraphicsBackendMemory, GraphicsBackendMemoryAllocation, GraphicsMemoryAllocationMode,
+            GraphicsMemoryAllocationType, TextureCompressedFormat,
         },
-        rendering::{ColorRgba, StateTexture},
     };
 
+    use num_traits::FromPrimitive;
+    use pool::mt_datatypes::{PoolString, PoolVec};
+
     use crate::{
         backend::{
             GraphicsBackend, GraphicsBackendBase, GraphicsBackendIoLoading, GraphicsBackendLoading,
+            MemoryPressureCallback,
+        },
+        backends::types::{BackendFrameStats, BackendWaitStats},
+        backends::vulkan::{
+            compiler::compiler::ShaderCompiler,
+            frame::FrameCanvasIndex,
+            frame_pacer::FramePacer,
+            phy_device::{select_device, vram_estimate, DeviceCandidate, DeviceFingerprint},
+            render_cmds::{get_address_mode_index, quad_range_indices},
+            render_fill_manager::RenderCommandExecuteBuffer,
+            sampler::{sampler_anisotropy, sampler_lod, Sampler},
+            stream_memory_pool::{StreamMemoryPoolGrowth, StreamMemoryPoolOccupancy},
+            utils::{
+                buffer_update_copies, buffer_update_copy, frame_fetch_copies, pack_image_rows,
+                texture_array_layer_extent,
+            },
+            vulkan::{
+                LoadingTaskCancel, RetryBackoff, TryFrameError, VulkanBackend,
+                VulkanBackendLoading, VulkanBackendLoadingIo, VulkanDebugCallbackOptions,
+            },
+            vulkan_allocator::StagingMemoryBudget,
+            vulkan_types::{
+                FrameGraphGroup, RenderPassSubType, RenderPassType, RenderThread,
+                SupportedSamplerTypes, ThreadCommandGroup,
+            },
+            OptionsBuilder,
         },
-        backends::vulkan::compiler::compiler::ShaderCompiler,
     };
 
     fn prepare_backend(
         thread_count: usize,
         config_gl: ConfigBackend,
     ) -> (Rc<GraphicsBackend>, GraphicsStreamedData) {
+        try_prepare_backend(thread_count, config_gl).expect("no vulkan device available")
+    }
+
+    /// Like [`prepare_backend`], but returns `None` if the backend
+    /// could not be initialized, e.g. because no vulkan device is available.
+    fn try_prepare_backend(
+        thread_count: usize,
+        config_gl: ConfigBackend,
+    ) -> Option<(Rc<GraphicsBackend>, GraphicsStreamedData)> {
+        try_prepare_backend_of_type(thread_count, config_gl, Default::default())
+    }
+
+    fn try_prepare_backend_of_type(
+        thread_count: usize,
+        config_gl: ConfigBackend,
+        config_gfx: config::config::ConfigGfx,
+    ) -> Option<(Rc<GraphicsBackend>, GraphicsStreamedData)> {
         let workspace_root = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("../../");
         std::env::set_current_dir(workspace_root).unwrap();
         let io = IoFileSys::new(|rt| {
@@ -74,7 +142,6 @@ mod tests {
                 .unwrap(),
         );
 
-        let config_gfx = config::config::ConfigGfx::default();
         let config_wnd = config::config::ConfigWindow::default();
         let io_loading = GraphicsBackendIoLoading::new(&config_gfx, &io);
         let mut config_dbg = config::config::ConfigDebug::default();
@@ -90,7 +157,8 @@ mod tests {
             None,
             io.clone(),
         )
-        .unwrap();
+        .inspect_err(|err| println!("skipping, backend loading failed: {err}"))
+        .ok()?;
         bench.bench("backend loading");
         let (backend_base, stream_data) = GraphicsBackendBase::new(
             io_loading,
@@ -101,12 +169,87 @@ mod tests {
                 height: config_wnd.window_height as u32,
             },
         )
-        .unwrap();
+        .inspect_err(|err| println!("skipping, backend init failed: {err}"))
+        .ok()?;
         bench.bench("backend base init");
         let backend = GraphicsBackend::new(backend_base);
         bench.bench("backend init");
 
-        (backend, stream_data)
+        Some((backend, stream_data))
+    }
+
+    /// Remembers the last frame it was given.
+    #[derive(Debug)]
+    struct LastFrameFetcher {
+        fetch_index: FetchCanvasIndex,
+        last_frame: Mutex<Option<(u32, u32, Vec<u8>)>>,
+    }
+
+    impl BackendFrameFetcher for LastFrameFetcher {
+        fn next_frame(&self, frame_data: BackendPresentedImageDataRgba) {
+            *self.last_frame.lock().unwrap() = Some((
+                frame_data.width,
+                frame_data.height,
+                frame_data.dest_data_buffer.to_vec(),
+            ));
+        }
+
+        fn current_fetch_index(&self) -> FetchCanvasIndex {
+            self.fetch_index
+        }
+
+        fn fetch_err(&self, err: FetchCanvasError) {
+            panic!("{:?}", err)
+        }
+    }
+
+    /// Renders `frame_count` headless frames, that are cleared with `clear_color`
+    /// and contain a simple stream render.
+    /// Returns the last frame that was fetched from the onscreen canvas
+    /// as `(width, height, rgba)`, or `None` if no vulkan device is available.
+    fn render_headless_frames(
+        frame_count: usize,
+        clear_color: ColorRgba,
+    ) -> Option<Option<(u32, u32, Vec<u8>)>> {
+        let (backend, stream_data) = try_prepare_backend(1, Default::default())?;
+
+        let fetcher = Arc::new(LastFrameFetcher {
+            fetch_index: FetchCanvasIndex::Onscreen,
+            last_frame: Default::default(),
+        });
+        backend
+            .attach_frame_fetcher("headless".to_string(), fetcher.clone())
+            .unwrap();
+
+        let cmds = BackendCommands::default();
+        for _ in 0..frame_count {
+            cmds.add_cmd(AllCommands::Render(CommandsRender::Clear(CommandClear {
+                color: clear_color,
+                force_clear: true,
+                clip: None,
+            })));
+
+            stream_data.add_vertices(&[Default::default(); 4]);
+            cmds.add_cmd(AllCommands::Render(CommandsRender::Stream(
+                CommandsRenderStream::Render(CommandRender {
+                    state: Default::default(),
+                    texture_index: StateTexture::None,
+                    prim_type: PrimType::Lines,
+                    prim_count: 4,
+                    vertices_offset: 0,
+                }),
+            )));
+
+            cmds.add_cmd(AllCommands::Misc(CommandsMisc::Swap));
+            backend.run_cmds(&cmds, &stream_data);
+        }
+        backend.wait_idle().unwrap();
+        backend
+            .detach_frame_fetcher("headless".to_string())
+            .unwrap();
+
+        let last_frame = fetcher.last_frame.lock().unwrap().take();
+        Some(last_frame)
     }
 
     #[derive(Debug)]
@@ -152,6 +295,7 @@ mod tests {
                 a: 0.0,
             },
             force_clear: true,
+            clip: None,
         })));
 
         stream_data.add_vertices(&[Default::default(); 4]);
@@ -171,6 +315,9 @@ mod tests {
                 width: 20,
                 height: 10,
                 has_multi_sampling: None,
+                clear_color: None,
+                relative_size: None,
+                persistent: false,
             },
         )));
 
@@ -188,6 +335,7 @@ mod tests {
                 a: 0.0,
             },
             force_clear: true,
+            clip: None,
         })));
 
         stream_data.add_vertices(&[Default::default(); 4]);
@@ -210,6 +358,41 @@ mod tests {
         backend.run_cmds(&cmds, &stream_data);
     }
 
+    #[test]
+    fn options_builder_validates_msaa_and_thread_count() {
+        let dbg = config::config::ConfigDebug::default();
+        let gl = ConfigBackend::default();
+        let available_threads = std::thread::available_parallelism()
+            .map(|threads| threads.get())
+            .unwrap_or(1) as u32;
+
+        let options = OptionsBuilder::new(&dbg, &gl)
+            .thread_count(u32::MAX)
+            .msaa_samples(8)
+            .build()
+            .unwrap();
+        assert_eq!(options.gl.thread_count, available_threads);
+        assert_eq!(options.gl.msaa_samples, 8);
+
+        // auto thread count and a single sample stay valid
+        let options = OptionsBuilder::new(&dbg, &gl)
+            .thread_count(0)
+            .msaa_samples(1)
+            .build()
+            .unwrap();
+        assert_eq!(options.gl.thread_count, 0);
+        assert_eq!(options.gl.msaa_samples, 0);
+
+        assert!(OptionsBuilder::new(&dbg, &gl)
+            .msaa_samples(6)
+            .build()
+            .is_err());
+        assert!(OptionsBuilder::new(&dbg, &gl)
+            .msaa_samples(128)
+            .build()
+            .is_err());
+    }
+
     #[test]
     fn vk_multi_sampling() {
         let mut config_gl: ConfigBackend = Default::default();
@@ -230,6 +413,7 @@ mod tests {
                 a: 0.0,
             },
             force_clear: true,
+            clip: None,
         })));
 
         cmds.add_cmd(AllCommands::Misc(CommandsMisc::OffscreenCanvasCreate(
@@ -238,6 +422,9 @@ mod tests {
                 width: 20,
                 height: 10,
                 has_multi_sampling: None,
+                clear_color: None,
+                relative_size: None,
+                persistent: false,
             },
         )));
 
@@ -255,6 +442,7 @@ mod tests {
                 a: 0.0,
             },
             force_clear: true,
+            clip: None,
         })));
 
         cmds.add_cmd(AllCommands::Misc(CommandsMisc::Swap));
@@ -352,6 +540,2817 @@ mod tests {
         backend.run_cmds(&cmds, &stream_data);
     }
 
+    #[test]
+    fn vk_deferred_texture_uploads() {
+        let Some((backend, stream_data)) = try_prepare_backend(1, Default::default()) else {
+            return;
+        };
+
+        let cmds = BackendCommands::default();
+
+        // more texture memory than the staging memory usually holds in one frame
+        let texture_count = 64;
+        let (width, height) = (1024, 1024);
+        for texture_index in 0..texture_count {
+            cmds.add_cmd(AllCommands::Misc(CommandsMisc::TextureCreate(
+                CommandTextureCreate {
+                    texture_index,
+                    data: GraphicsBackendMemory::new(
+                        GraphicsBackendMemoryAllocation::Vector(vec![255; width * height * 4]),
+                        GraphicsMemoryAllocationType::TextureRgbaU8 {
+                            width: NonZeroUsize::new(width).unwrap(),
+                            height: NonZeroUsize::new(height).unwrap(),
+                            flags: TexFlags::TEXFLAG_NOMIPMAPS,
+                        },
+                    ),
+                    usage: TextureUsage::Static,
+                },
+            )));
+        }
+        cmds.add_cmd(AllCommands::Misc(CommandsMisc::Swap));
+        backend.run_cmds(&cmds, &stream_data);
+
+        // all uploads must land within a few frames
+        for _ in 0..8 {
+            cmds.add_cmd(AllCommands::Misc(CommandsMisc::Swap));
+            backend.run_cmds(&cmds, &stream_data);
+        }
+        backend.wait_idle().unwrap();
+
+        assert_eq!(backend.pending_texture_uploads(), 0);
+        // the uploads waited for staging memory in later frames,
+        // instead of waiting for the gpu
+        assert_eq!(backend.wait_stats().unwrap(), BackendWaitStats::default());
+    }
+
+    #[test]
+    fn vk_full_pipeline_creation_finishes() {
+        let mut config_gl = ConfigBackend::default();
+        config_gl.full_pipeline_creation = true;
+        let Some((backend, stream_data)) = try_prepare_backend(1, config_gl) else {
+            return;
+        };
+
+        let cmds = BackendCommands::default();
+        let mut last_pending = usize::MAX;
+        // the full compile runs in the background, give it plenty of frames
+        for _ in 0..1000 {
+            cmds.add_cmd(AllCommands::Misc(CommandsMisc::Swap));
+            backend.run_cmds(&cmds, &stream_data);
+            backend.wait_idle().unwrap();
+
+            let pending = backend.pipelines_pending_count();
+            assert!(
+                pending <= last_pending,
+                "pending pipeline compiles increased from {last_pending} to {pending}"
+            );
+            last_pending = pending;
+            if backend.pipelines_ready() {
+                break;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(10));
+        }
+
+        assert!(backend.pipelines_ready());
+        assert_eq!(backend.pipelines_pending_count(), 0);
+    }
+
+    #[test]
+    fn vk_read_texture() {
+        let Some((backend, stream_data)) = try_prepare_backend(1, Default::default()) else {
+            return;
+        };
+
+        let (width, height) = (8, 4);
+        let color = [255, 128, 0, 255];
+        let cmds = BackendCommands::default();
+        cmds.add_cmd(AllCommands::Misc(CommandsMisc::TextureCreate(
+            CommandTextureCreate {
+                texture_index: 0,
+                data: GraphicsBackendMemory::new(
+                    GraphicsBackendMemoryAllocation::Vector(color.repeat(width * height)),
+                    GraphicsMemoryAllocationType::TextureRgbaU8 {
+                        width: NonZeroUsize::new(width).unwrap(),
+                        height: NonZeroUsize::new(height).unwrap(),
+                        flags: TexFlags::TEXFLAG_NOMIPMAPS,
+                    },
+                ),
+                usage: TextureUsage::Static,
+            },
+        )));
+        cmds.add_cmd(AllCommands::Misc(CommandsMisc::TextureCreate(
+            CommandTextureCreate {
+                texture_index: 1,
+                data: GraphicsBackendMemory::new(
+                    GraphicsBackendMemoryAllocation::Vector(color.repeat(width * height * 2)),
+                    GraphicsMemoryAllocationType::TextureRgbaU82dArray {
+                        width: NonZeroUsize::new(width).unwrap(),
+                        height: NonZeroUsize::new(height).unwrap(),
+                        depth: NonZeroUsize::new(2).unwrap(),
+                        flags: TexFlags::TEXFLAG_NOMIPMAPS,
+                    },
+                ),
+                usage: TextureUsage::Static,
+            },
+        )));
+        cmds.add_cmd(AllCommands::Misc(CommandsMisc::Swap));
+        backend.run_cmds(&cmds, &stream_data);
+        backend.wait_idle().unwrap();
+
+        let (read_width, read_height, data) = backend.read_texture(0).unwrap();
+        assert_eq!((read_width, read_height), (width as u32, height as u32));
+        assert_eq!(data, color.repeat(width * height));
+
+        // 3d textures and unknown slots are not supported
+        assert!(backend.read_texture(1).is_err());
+        assert!(backend.read_texture(2).is_err());
+    }
+
+    #[test]
+    fn vk_max_mip_levels_caps_the_mip_chain() {
+        let Some((backend, stream_data)) = try_prepare_backend(
+            1,
+            ConfigBackend {
+                max_mip_levels: 4,
+                ..Default::default()
+            },
+        ) else {
+            return;
+        };
+
+        let size = 1024;
+        let cmds = BackendCommands::default();
+        cmds.add_cmd(AllCommands::Misc(CommandsMisc::TextureCreate(
+            CommandTextureCreate {
+                texture_index: 0,
+                data: GraphicsBackendMemory::new(
+                    GraphicsBackendMemoryAllocation::Vector(vec![255; size * size * 4]),
+                    GraphicsMemoryAllocationType::TextureRgbaU8 {
+                        width: NonZeroUsize::new(size).unwrap(),
+                        height: NonZeroUsize::new(size).unwrap(),
+                        flags: TexFlags::empty(),
+                    },
+                ),
+                usage: TextureUsage::Static,
+            },
+        )));
+        cmds.add_cmd(AllCommands::Misc(CommandsMisc::Swap));
+        backend.run_cmds(&cmds, &stream_data);
+        backend.wait_idle().unwrap();
+
+        // a 1024x1024 texture would otherwise get 11 mip levels
+        assert_eq!(backend.texture_mip_map_count(0).unwrap(), 4);
+        assert!(backend.texture_mip_map_count(1).is_err());
+    }
+
+    #[test]
+    fn vk_dynamic_textures_skip_mip_maps() {
+        let Some((backend, stream_data)) = try_prepare_backend(1, Default::default()) else {
+            return;
+        };
+
+        let size = 256;
+        let cmds = BackendCommands::default();
+        for (texture_index, usage) in [
+            TextureUsage::Static,
+            TextureUsage::Dynamic,
+            TextureUsage::Streaming,
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            cmds.add_cmd(AllCommands::Misc(CommandsMisc::TextureCreate(
+                CommandTextureCreate {
+                    texture_index: texture_index as u128,
+                    data: GraphicsBackendMemory::new(
+                        GraphicsBackendMemoryAllocation::Vector(vec![255; size * size * 4]),
+                        GraphicsMemoryAllocationType::TextureRgbaU8 {
+                            width: NonZeroUsize::new(size).unwrap(),
+                            height: NonZeroUsize::new(size).unwrap(),
+                            flags: TexFlags::empty(),
+                        },
+                    ),
+                    usage,
+                },
+            )));
+        }
+        cmds.add_cmd(AllCommands::Misc(CommandsMisc::Swap));
+        backend.run_cmds(&cmds, &stream_data);
+        backend.wait_idle().unwrap();
+
+        // a 256x256 texture gets 9 mip levels
+        assert_eq!(backend.texture_mip_map_count(0).unwrap(), 9);
+        assert_eq!(backend.texture_mip_map_count(1).unwrap(), 1);
+        assert_eq!(backend.texture_mip_map_count(2).unwrap(), 1);
+
+        // updating a texture without mipmaps works, too
+        let update = BackendCommands::default();
+        update.add_cmd(AllCommands::Misc(CommandsMisc::TextureUpdate(
+            CommandTextureUpdate {
+                texture_index: 1,
+                x: 0,
+                y: 0,
+                width: 2,
+                height: 2,
+                data: vec![0; 2 * 2 * 4],
+            },
+        )));
+        update.add_cmd(AllCommands::Misc(CommandsMisc::Swap));
+        backend.run_cmds(&update, &stream_data);
+        backend.wait_idle().unwrap();
+        let (_, _, data) = backend.read_texture(1).unwrap();
+        assert_eq!(&data[..4], [0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn vk_texture_update_outside_of_texture_fails() {
+        let Some((backend, stream_data)) = try_prepare_backend(1, Default::default()) else {
+            return;
+        };
+
+        let size = 16;
+        let cmds = BackendCommands::default();
+        cmds.add_cmd(AllCommands::Misc(CommandsMisc::TextureCreate(
+            CommandTextureCreate {
+                texture_index: 0,
+                data: GraphicsBackendMemory::new(
+                    GraphicsBackendMemoryAllocation::Vector(vec![255; size * size * 4]),
+                    GraphicsMemoryAllocationType::TextureRgbaU8 {
+                        width: NonZeroUsize::new(size).unwrap(),
+                        height: NonZeroUsize::new(size).unwrap(),
+                        flags: TexFlags::empty(),
+                    },
+                ),
+                usage: TextureUsage::Static,
+            },
+        )));
+        cmds.add_cmd(AllCommands::Misc(CommandsMisc::Swap));
+        backend.run_cmds(&cmds, &stream_data);
+        backend.wait_idle().unwrap();
+
+        let update = BackendCommands::default();
+        update.add_cmd(AllCommands::Misc(CommandsMisc::TextureUpdate(
+            CommandTextureUpdate {
+                texture_index: 0,
+                x: 12,
+                y: 0,
+                width: 8,
+                height: 8,
+                data: vec![0; 8 * 8 * 4],
+            },
+        )));
+        update.add_cmd(AllCommands::Misc(CommandsMisc::Swap));
+        backend.run_cmds(&update, &stream_data);
+
+        let err = backend
+            .wait_idle()
+            .expect_err("the update outside of the texture was not reported");
+        assert!(err
+            .chain()
+            .any(|err| err.to_string().contains("is outside of the 16x16 texture")));
+    }
+
+    #[test]
+    fn vk_render_paused() {
+        let Some((backend, stream_data)) = try_prepare_backend(1, Default::default()) else {
+            return;
+        };
+
+        let fetcher = Arc::new(LastFrameFetcher {
+            fetch_index: FetchCanvasIndex::Onscreen,
+            last_frame: Default::default(),
+        });
+        backend
+            .attach_frame_fetcher("paused".to_string(), fetcher.clone())
+            .unwrap();
+
+        let (width, height) = (4, 4);
+        let cmds = BackendCommands::default();
+        cmds.add_cmd(AllCommands::Misc(CommandsMisc::TextureCreate(
+            CommandTextureCreate {
+                texture_index: 0,
+                data: GraphicsBackendMemory::new(
+                    GraphicsBackendMemoryAllocation::Vector(vec![0; width * height * 4]),
+                    GraphicsMemoryAllocationType::TextureRgbaU8 {
+                        width: NonZeroUsize::new(width).unwrap(),
+                        height: NonZeroUsize::new(height).unwrap(),
+                        flags: TexFlags::TEXFLAG_NOMIPMAPS,
+                    },
+                ),
+                usage: TextureUsage::Static,
+            },
+        )));
+        cmds.add_cmd(AllCommands::Misc(CommandsMisc::Swap));
+        backend.run_cmds(&cmds, &stream_data);
+        backend.wait_idle().unwrap();
+        assert!(fetcher.last_frame.lock().unwrap().take().is_some());
+
+        let render_frames = |count: usize, texture_color: Option<[u8; 4]>| {
+            for _ in 0..count {
+                if let Some(color) = texture_color {
+                    cmds.add_cmd(AllCommands::Misc(CommandsMisc::TextureUpdate(
+                        CommandTextureUpdate {
+                            texture_index: 0,
+                            x: 0,
+                            y: 0,
+                            width: width as u32,
+                            height: height as u32,
+                            data: color.repeat(width * height),
+                        },
+                    )));
+                }
+                cmds.add_cmd(AllCommands::Render(CommandsRender::Clear(CommandClear {
+                    color: ColorRgba::new(1.0, 0.0, 0.0, 1.0),
+                    force_clear: true,
+                    clip: None,
+                })));
+                cmds.add_cmd(AllCommands::Misc(CommandsMisc::Swap));
+                backend.run_cmds(&cmds, &stream_data);
+            }
+            backend.wait_idle().unwrap();
+        };
+
+        backend.set_render_paused(true).unwrap();
+        let color = [0, 255, 0, 255];
+        render_frames(4, Some(color));
+        // nothing was presented, but the texture was updated
+        assert!(fetcher.last_frame.lock().unwrap().is_none());
+        let (_, _, data) = backend.read_texture(0).unwrap();
+        assert_eq!(data, color.repeat(width * height));
+
+        backend.set_render_paused(false).unwrap();
+        render_frames(2, None);
+        assert!(fetcher.last_frame.lock().unwrap().is_some());
+
+        backend.detach_frame_fetcher("paused".to_string()).unwrap();
+    }
+
+    #[test]
+    fn vk_headless_frames() {
+        let Some(last_frame) = render_headless_frames(
+            4,
+            ColorRgba {
+                r: 1.0,
+                g: 0.0,
+                b: 0.0,
+                a: 1.0,
+            },
+        ) else {
+            return;
+        };
+
+        let (width, height, data) = last_frame.expect("no frame was fetched");
+        assert_eq!(data.len(), (width * height * 4) as usize);
+        // the stream render only draws degenerated lines, so the clear color is visible
+        assert_eq!(&data[0..3], &[255, 0, 0]);
+    }
+
+    #[test]
+    fn vk_capture_before_shutdown() {
+        let Some((backend, stream_data)) = try_prepare_backend(1, Default::default()) else {
+            return;
+        };
+
+        let fetcher = Arc::new(LastFrameFetcher {
+            fetch_index: FetchCanvasIndex::Onscreen,
+            last_frame: Default::default(),
+        });
+        backend
+            .attach_frame_fetcher("shutdown".to_string(), fetcher.clone())
+            .unwrap();
+
+        // no swap follows, the frame is only submitted by the shutdown
+        let cmds = BackendCommands::default();
+        cmds.add_cmd(AllCommands::Render(CommandsRender::Clear(CommandClear {
+            color: ColorRgba {
+                r: 0.0,
+                g: 1.0,
+                b: 0.0,
+                a: 1.0,
+            },
+            force_clear: true,
+            clip: None,
+        })));
+        backend.run_cmds(&cmds, &stream_data);
+        assert!(fetcher.last_frame.lock().unwrap().is_none());
+
+        drop(stream_data);
+        drop(backend);
+
+        let (width, height, data) = fetcher
+            .last_frame
+            .lock()
+            .unwrap()
+            .take()
+            .expect("the frame before shutdown was not fetched");
+        assert_eq!(data.len(), (width * height * 4) as usize);
+        assert_eq!(&data[0..3], &[0, 255, 0]);
+    }
+
+    #[test]
+    fn vk_offscreen_canvas_resize() {
+        let Some((backend, stream_data)) = try_prepare_backend(1, Default::default()) else {
+            return;
+        };
+
+        let fetcher = Arc::new(LastFrameFetcher {
+            fetch_index: FetchCanvasIndex::Offscreen(0),
+            last_frame: Default::default(),
+        });
+        backend
+            .attach_frame_fetcher("resize".to_string(), fetcher.clone())
+            .unwrap();
+
+        let cmds = BackendCommands::default();
+        cmds.add_cmd(AllCommands::Misc(CommandsMisc::OffscreenCanvasCreate(
+            CommandOffscreenCanvasCreate {
+                offscreen_index: 0,
+                width: 20,
+                height: 10,
+                has_multi_sampling: None,
+                clear_color: None,
+                relative_size: None,
+                persistent: false,
+            },
+        )));
+        cmds.add_cmd(AllCommands::Misc(CommandsMisc::OffscreenCanvasResize(
+            CommandOffscreenCanvasResize {
+                offscreen_index: 0,
+                width: 40,
+                height: 30,
+            },
+        )));
+        cmds.add_cmd(AllCommands::Misc(CommandsMisc::SwitchCanvas(
+            CommandSwitchCanvasMode {
+                mode: CommandSwitchCanvasModeType::Offscreen { id: 0 },
+            },
+        )));
+        cmds.add_cmd(AllCommands::Render(CommandsRender::Clear(CommandClear {
+            color: ColorRgba {
+                r: 0.0,
+                g: 1.0,
+                b: 0.0,
+                a: 1.0,
+            },
+            force_clear: true,
+            clip: None,
+        })));
+        cmds.add_cmd(AllCommands::Misc(CommandsMisc::Swap));
+        backend.run_cmds(&cmds, &stream_data);
+        // no extra swap, the next frame would not render to the canvas
+        backend.wait_idle().unwrap();
+
+        let (width, height, data) = fetcher
+            .last_frame
+            .lock()
+            .unwrap()
+            .take()
+            .expect("no frame was fetched");
+        assert_eq!((width, height), (40, 30));
+        assert_eq!(data.len(), 40 * 30 * 4);
+        assert_eq!(&data[0..4], &[0, 255, 0, 255]);
+    }
+
+    #[test]
+    fn vk_persistent_offscreen_canvas_keeps_its_content() {
+        let Some((backend, stream_data)) = try_prepare_backend(1, Default::default()) else {
+            return;
+        };
+
+        let fetcher = Arc::new(LastFrameFetcher {
+            fetch_index: FetchCanvasIndex::Offscreen(0),
+            last_frame: Default::default(),
+        });
+        backend
+            .attach_frame_fetcher("persistent".to_string(), fetcher.clone())
+            .unwrap();
+
+        let cmds = BackendCommands::default();
+        cmds.add_cmd(AllCommands::Misc(CommandsMisc::OffscreenCanvasCreate(
+            CommandOffscreenCanvasCreate {
+                offscreen_index: 0,
+                width: 20,
+                height: 10,
+                has_multi_sampling: None,
+                clear_color: Some(ColorRgba::new(0.0, 0.0, 0.0, 1.0)),
+                relative_size: None,
+                persistent: true,
+            },
+        )));
+        // the first frame draws the left half, the second one the right half
+        for (x, color) in [
+            (0, ColorRgba::new(0.0, 1.0, 0.0, 1.0)),
+            (10, ColorRgba::new(1.0, 0.0, 0.0, 1.0)),
+        ] {
+            cmds.add_cmd(AllCommands::Misc(CommandsMisc::SwitchCanvas(
+                CommandSwitchCanvasMode {
+                    mode: CommandSwitchCanvasModeType::Offscreen { id: 0 },
+                },
+            )));
+            cmds.add_cmd(AllCommands::Render(CommandsRender::Clear(CommandClear {
+                color,
+                force_clear: true,
+                clip: Some(StateClip {
+                    x,
+                    y: 0,
+                    w: 10,
+                    h: 10,
+                }),
+            })));
+            cmds.add_cmd(AllCommands::Misc(CommandsMisc::Swap));
+            backend.run_cmds(&cmds, &stream_data);
+        }
+        backend.wait_idle().unwrap();
+
+        let (width, height, data) = fetcher
+            .last_frame
+            .lock()
+            .unwrap()
+            .take()
+            .expect("no frame was fetched");
+        assert_eq!((width, height), (20, 10));
+        // the second frame was drawn on top of the first one
+        assert_eq!(&data[0..4], &[0, 255, 0, 255]);
+        assert_eq!(&data[19 * 4..20 * 4], &[255, 0, 0, 255]);
+    }
+
+    #[test]
+    fn vk_relative_offscreen_canvas_follows_window_resize() {
+        let Some((backend, stream_data)) = try_prepare_backend(1, Default::default()) else {
+            return;
+        };
+
+        let fetcher = Arc::new(LastFrameFetcher {
+            fetch_index: FetchCanvasIndex::Offscreen(0),
+            last_frame: Default::default(),
+        });
+        backend
+            .attach_frame_fetcher("relative".to_string(), fetcher.clone())
+            .unwrap();
+
+        let divisor = NonZeroU32::new(2).unwrap();
+        let (window_width, window_height) = (640, 360);
+        let cmds = BackendCommands::default();
+        cmds.add_cmd(AllCommands::Misc(CommandsMisc::OffscreenCanvasCreate(
+            CommandOffscreenCanvasCreate {
+                offscreen_index: 0,
+                width: 0,
+                height: 0,
+                has_multi_sampling: None,
+                clear_color: None,
+                relative_size: Some(divisor),
+                persistent: false,
+            },
+        )));
+        cmds.add_cmd(AllCommands::Misc(CommandsMisc::UpdateViewport(
+            CommandUpdateViewport {
+                x: 0,
+                y: 0,
+                width: window_width,
+                height: window_height,
+                by_resize: true,
+                content_scale: 1.0,
+            },
+        )));
+        // the swap chain is recreated at the start of the next frame
+        cmds.add_cmd(AllCommands::Misc(CommandsMisc::Swap));
+        cmds.add_cmd(AllCommands::Misc(CommandsMisc::SwitchCanvas(
+            CommandSwitchCanvasMode {
+                mode: CommandSwitchCanvasModeType::Offscreen { id: 0 },
+            },
+        )));
+        cmds.add_cmd(AllCommands::Render(CommandsRender::Clear(CommandClear {
+            color: ColorRgba {
+                r: 0.0,
+                g: 1.0,
+                b: 0.0,
+                a: 1.0,
+            },
+            force_clear: true,
+            clip: None,
+        })));
+        cmds.add_cmd(AllCommands::Misc(CommandsMisc::Swap));
+        backend.run_cmds(&cmds, &stream_data);
+        backend.wait_idle().unwrap();
+
+        let (width, height, data) = fetcher
+            .last_frame
+            .lock()
+            .unwrap()
+            .take()
+            .expect("no frame was fetched");
+        assert_eq!((width, height), (window_width / 2, window_height / 2));
+        assert_eq!(data.len(), (width * height * 4) as usize);
+        assert_eq!(&data[0..4], &[0, 255, 0, 255]);
+    }
+
+    #[test]
+    fn vk_offscreen_dynamic_viewport_stays_on_its_canvas() {
+        let Some((backend, stream_data)) = try_prepare_backend(1, Default::default()) else {
+            return;
+        };
+
+        let fetcher = Arc::new(LastFrameFetcher {
+            fetch_index: FetchCanvasIndex::Onscreen,
+            last_frame: Default::default(),
+        });
+        backend
+            .attach_frame_fetcher("viewport".to_string(), fetcher.clone())
+            .unwrap();
+
+        let cmds = BackendCommands::default();
+        cmds.add_cmd(AllCommands::Misc(CommandsMisc::OffscreenCanvasCreate(
+            CommandOffscreenCanvasCreate {
+                offscreen_index: 0,
+                width: 64,
+                height: 64,
+                has_multi_sampling: None,
+                clear_color: None,
+                relative_size: None,
+                persistent: false,
+            },
+        )));
+        cmds.add_cmd(AllCommands::Misc(CommandsMisc::SwitchCanvas(
+            CommandSwitchCanvasMode {
+                mode: CommandSwitchCanvasModeType::Offscreen { id: 0 },
+            },
+        )));
+        // only the bottom right quarter of the offscreen canvas
+        cmds.add_cmd(AllCommands::Misc(CommandsMisc::UpdateViewport(
+            CommandUpdateViewport {
+                x: 32,
+                y: 32,
+                width: 32,
+                height: 32,
+                by_resize: false,
+                content_scale: 1.0,
+            },
+        )));
+        cmds.add_cmd(AllCommands::Misc(CommandsMisc::SwitchCanvas(
+            CommandSwitchCanvasMode {
+                mode: CommandSwitchCanvasModeType::Onscreen,
+            },
+        )));
+        cmds.add_cmd(AllCommands::Render(CommandsRender::Clear(CommandClear {
+            color: ColorRgba::new(0.0, 1.0, 0.0, 1.0),
+            force_clear: true,
+            clip: None,
+        })));
+        // a clip is shifted by the dynamic viewport of its canvas
+        cmds.add_cmd(AllCommands::Render(CommandsRender::Clear(CommandClear {
+            color: ColorRgba::new(1.0, 0.0, 0.0, 1.0),
+            force_clear: true,
+            clip: Some(StateClip {
+                x: 0,
+                y: 0,
+                w: 16384,
+                h: 16384,
+            }),
+        })));
+        cmds.add_cmd(AllCommands::Misc(CommandsMisc::Swap));
+        backend.run_cmds(&cmds, &stream_data);
+        backend.wait_idle().unwrap();
+
+        let (width, height, data) = fetcher
+            .last_frame
+            .lock()
+            .unwrap()
+            .take()
+            .expect("no frame was fetched");
+        assert_eq!(data.len(), (width * height * 4) as usize);
+        // the onscreen canvas still uses its full viewport
+        assert_eq!(&data[0..4], &[255, 0, 0, 255]);
+
+        backend
+            .detach_frame_fetcher("viewport".to_string())
+            .unwrap();
+    }
+
+    #[test]
+    fn relative_canvas_extent_never_collapses() {
+        let divisor = |n| NonZeroU32::new(n).unwrap();
+        assert_eq!(relative_canvas_extent(1920, 1080, divisor(1)), (1920, 1080));
+        assert_eq!(relative_canvas_extent(1920, 1080, divisor(2)), (960, 540));
+        assert_eq!(relative_canvas_extent(1919, 1079, divisor(4)), (479, 269));
+        assert_eq!(relative_canvas_extent(3, 1, divisor(8)), (1, 1));
+    }
+
+    #[test]
+    fn vk_offscreen_canvas_clear_color() {
+        let Some((backend, stream_data)) = try_prepare_backend(1, Default::default()) else {
+            return;
+        };
+
+        // all canvases are read back by the same submit
+        let canvases = [
+            (0, [255, 0, 0, 255]),
+            (1, [0, 0, 255, 0]),
+            (2, [255, 255, 0, 255]),
+        ];
+        let cmds = BackendCommands::default();
+        let fetchers: Vec<_> = canvases
+            .iter()
+            .map(|&(offscreen_index, [r, g, b, a])| {
+                let fetcher = Arc::new(LastFrameFetcher {
+                    fetch_index: FetchCanvasIndex::Offscreen(offscreen_index),
+                    last_frame: Default::default(),
+                });
+                backend
+                    .attach_frame_fetcher(format!("clear{offscreen_index}"), fetcher.clone())
+                    .unwrap();
+
+                cmds.add_cmd(AllCommands::Misc(CommandsMisc::OffscreenCanvasCreate(
+                    CommandOffscreenCanvasCreate {
+                        offscreen_index,
+                        width: 20,
+                        height: 10,
+                        has_multi_sampling: None,
+                        clear_color: Some(ColorRgba {
+                            r: r as f32 / 255.0,
+                            g: g as f32 / 255.0,
+                            b: b as f32 / 255.0,
+                            a: a as f32 / 255.0,
+                        }),
+                        relative_size: None,
+                        persistent: false,
+                    },
+                )));
+                cmds.add_cmd(AllCommands::Misc(CommandsMisc::SwitchCanvas(
+                    CommandSwitchCanvasMode {
+                        mode: CommandSwitchCanvasModeType::Offscreen {
+                            id: offscreen_index,
+                        },
+                    },
+                )));
+                fetcher
+            })
+            .collect();
+        // the onscreen canvas keeps the backend's clear color
+        cmds.add_cmd(AllCommands::Misc(CommandsMisc::SwitchCanvas(
+            CommandSwitchCanvasMode {
+                mode: CommandSwitchCanvasModeType::Onscreen,
+            },
+        )));
+        cmds.add_cmd(AllCommands::Render(CommandsRender::Clear(CommandClear {
+            color: ColorRgba {
+                r: 0.0,
+                g: 1.0,
+                b: 0.0,
+                a: 1.0,
+            },
+            force_clear: false,
+            clip: None,
+        })));
+        cmds.add_cmd(AllCommands::Misc(CommandsMisc::Swap));
+        backend.run_cmds(&cmds, &stream_data);
+        // no extra swap, the next frame would not render to the canvas
+        backend.wait_idle().unwrap();
+
+        for (fetcher, (_, color)) in fetchers.iter().zip(canvases) {
+            let (_, _, data) = fetcher
+                .last_frame
+                .lock()
+                .unwrap()
+                .take()
+                .expect("no frame was fetched");
+            assert_eq!(&data[0..4], &color);
+        }
+    }
+
+    #[test]
+    fn vk_frame_fetch_single_submit() {
+        let Some((backend, stream_data)) = try_prepare_backend(1, Default::default()) else {
+            return;
+        };
+
+        let cmds = BackendCommands::default();
+        let fetchers: Vec<_> = [
+            FetchCanvasIndex::Offscreen(0),
+            FetchCanvasIndex::Offscreen(1),
+            FetchCanvasIndex::Onscreen,
+        ]
+        .into_iter()
+        .enumerate()
+        .map(|(index, fetch_index)| {
+            let fetcher = Arc::new(LastFrameFetcher {
+                fetch_index,
+                last_frame: Default::default(),
+            });
+            backend
+                .attach_frame_fetcher(format!("fetcher{index}"), fetcher.clone())
+                .unwrap();
+            if let FetchCanvasIndex::Offscreen(offscreen_index) = fetch_index {
+                cmds.add_cmd(AllCommands::Misc(CommandsMisc::OffscreenCanvasCreate(
+                    CommandOffscreenCanvasCreate {
+                        offscreen_index,
+                        width: 20,
+                        height: 10,
+                        has_multi_sampling: None,
+                        clear_color: Some(Default::default()),
+                        relative_size: None,
+                        persistent: false,
+                    },
+                )));
+                cmds.add_cmd(AllCommands::Misc(CommandsMisc::SwitchCanvas(
+                    CommandSwitchCanvasMode {
+                        mode: CommandSwitchCanvasModeType::Offscreen {
+                            id: offscreen_index,
+                        },
+                    },
+                )));
+            }
+            fetcher
+        })
+        .collect();
+        cmds.add_cmd(AllCommands::Misc(CommandsMisc::SwitchCanvas(
+            CommandSwitchCanvasMode {
+                mode: CommandSwitchCanvasModeType::Onscreen,
+            },
+        )));
+        cmds.add_cmd(AllCommands::Misc(CommandsMisc::Swap));
+        backend.run_cmds(&cmds, &stream_data);
+        backend.wait_idle().unwrap();
+
+        assert!(fetchers
+            .iter()
+            .all(|fetcher| fetcher.last_frame.lock().unwrap().is_some()));
+        // all three canvases were copied by a single submit & wait
+        let stats = backend.wait_stats().unwrap();
+        assert_eq!(stats.frame_fetch_submits, 1);
+        assert_eq!(stats.frame_fetch_fence_waits, 1);
+    }
+
+    /// Remembers the last depth buffer or the error why it could not be fetched.
+    #[derive(Debug)]
+    struct DepthFetcher {
+        last_depth: Mutex<Option<Result<BackendDepthImageData, FetchCanvasError>>>,
+    }
+
+    impl BackendFrameFetcher for DepthFetcher {
+        fn next_frame(&self, _frame_data: BackendPresentedImageDataRgba) {}
+
+        fn fetch_depth(&self) -> bool {
+            true
+        }
+
+        fn next_depth_frame(&self, depth_data: BackendDepthImageData) {
+            *self.last_depth.lock().unwrap() = Some(Ok(depth_data));
+        }
+
+        fn current_fetch_index(&self) -> FetchCanvasIndex {
+            FetchCanvasIndex::Onscreen
+        }
+
+        fn fetch_err(&self, err: FetchCanvasError) {
+            *self.last_depth.lock().unwrap() = Some(Err(err));
+        }
+    }
+
+    #[test]
+    fn vk_depth_readback() {
+        let Some((backend, stream_data)) = try_prepare_backend(1, Default::default()) else {
+            return;
+        };
+
+        let fetcher = Arc::new(DepthFetcher {
+            last_depth: Default::default(),
+        });
+        backend
+            .attach_frame_fetcher("depth".to_string(), fetcher.clone())
+            .unwrap();
+
+        let cmds = BackendCommands::default();
+        cmds.add_cmd(AllCommands::Render(CommandsRender::Clear(CommandClear {
+            color: ColorRgba {
+                r: 0.0,
+                g: 0.0,
+                b: 1.0,
+                a: 1.0,
+            },
+            force_clear: true,
+            clip: None,
+        })));
+        // the depth/stencil attachment is only used by the switching passes
+        cmds.add_cmd(AllCommands::Misc(CommandsMisc::NextSwitchPass));
+        stream_data.add_vertices(&[Default::default(); 4]);
+        cmds.add_cmd(AllCommands::Render(CommandsRender::Stream(
+            CommandsRenderStream::Render(CommandRender {
+                state: Default::default(),
+                texture_index: StateTexture::None,
+                prim_type: PrimType::Lines,
+                prim_count: 4,
+                vertices_offset: 0,
+            }),
+        )));
+        cmds.add_cmd(AllCommands::Misc(CommandsMisc::Swap));
+        backend.run_cmds(&cmds, &stream_data);
+        backend.wait_idle().unwrap();
+
+        let depth = match fetcher
+            .last_depth
+            .lock()
+            .unwrap()
+            .take()
+            .expect("no depth was fetched")
+        {
+            Ok(depth) => depth,
+            // e.g. a depth format that has no known texel size
+            Err(FetchCanvasError::DepthUnsupported(_)) => return,
+            Err(err) => panic!("depth readback failed: {err}"),
+        };
+        assert_eq!(
+            depth.depth_data.len(),
+            depth.width as usize * depth.height as usize
+        );
+        // the switching passes clear the depth to 0.0
+        // and none of their pipelines writes depth
+        assert!(depth.depth_data.iter().all(|&depth| depth == 0.0));
+    }
+
+    #[test]
+    fn vk_frame_fetcher_duplicate_name() {
+        let Some((backend, stream_data)) = try_prepare_backend(1, Default::default()) else {
+            return;
+        };
+
+        let first = Arc::new(LastFrameFetcher {
+            fetch_index: FetchCanvasIndex::Onscreen,
+            last_frame: Default::default(),
+        });
+        let second = Arc::new(LastFrameFetcher {
+            fetch_index: FetchCanvasIndex::Onscreen,
+            last_frame: Default::default(),
+        });
+        backend
+            .attach_frame_fetcher("fetcher".to_string(), first.clone())
+            .unwrap();
+        let err = backend
+            .attach_frame_fetcher("fetcher".to_string(), second.clone())
+            .unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<FrameFetcherAttachError>(),
+            Some(&FrameFetcherAttachError::AlreadyAttached)
+        );
+
+        // the first fetcher is still the one that is attached
+        let cmds = BackendCommands::default();
+        cmds.add_cmd(AllCommands::Render(CommandsRender::Clear(CommandClear {
+            color: ColorRgba {
+                r: 1.0,
+                g: 0.0,
+                b: 0.0,
+                a: 1.0,
+            },
+            force_clear: true,
+            clip: None,
+        })));
+        cmds.add_cmd(AllCommands::Misc(CommandsMisc::Swap));
+        backend.run_cmds(&cmds, &stream_data);
+        backend.wait_idle().unwrap();
+        assert!(first.last_frame.lock().unwrap().is_some());
+        assert!(second.last_frame.lock().unwrap().is_none());
+
+        // detaching is idempotent and frees the name again
+        backend.detach_frame_fetcher("fetcher".to_string()).unwrap();
+        backend.detach_frame_fetcher("fetcher".to_string()).unwrap();
+        backend
+            .attach_frame_fetcher("fetcher".to_string(), second)
+            .unwrap();
+        backend.detach_frame_fetcher("fetcher".to_string()).unwrap();
+    }
+
+    #[test]
+    fn vk_capture_current_frame() {
+        let Some((backend, stream_data)) = try_prepare_backend(1, Default::default()) else {
+            return;
+        };
+
+        let cmds = BackendCommands::default();
+        cmds.add_cmd(AllCommands::Render(CommandsRender::Clear(CommandClear {
+            color: ColorRgba {
+                r: 1.0,
+                g: 0.0,
+                b: 1.0,
+                a: 1.0,
+            },
+            force_clear: true,
+            clip: None,
+        })));
+        cmds.add_cmd(AllCommands::Misc(CommandsMisc::Swap));
+        backend.run_cmds(&cmds, &stream_data);
+
+        let frame = backend
+            .capture_current_frame(FetchCanvasIndex::Onscreen)
+            .unwrap();
+        assert_eq!(
+            frame.dest_data_buffer.len(),
+            frame.width as usize * frame.height as usize * 4
+        );
+        assert_eq!(&frame.dest_data_buffer[0..4], &[255, 0, 255, 255]);
+    }
+
+    #[test]
+    fn vk_force_swapchain_recreate() {
+        let Some((backend, stream_data)) = try_prepare_backend(1, Default::default()) else {
+            return;
+        };
+
+        let fetcher = Arc::new(LastFrameFetcher {
+            fetch_index: FetchCanvasIndex::Onscreen,
+            last_frame: Default::default(),
+        });
+        backend
+            .attach_frame_fetcher("recreate".to_string(), fetcher.clone())
+            .unwrap();
+
+        let render_frame = |color: ColorRgba| {
+            let cmds = BackendCommands::default();
+            cmds.add_cmd(AllCommands::Render(CommandsRender::Clear(CommandClear {
+                color,
+                force_clear: true,
+                clip: None,
+            })));
+            cmds.add_cmd(AllCommands::Misc(CommandsMisc::Swap));
+            backend.run_cmds(&cmds, &stream_data);
+            backend.wait_idle().unwrap();
+            fetcher.last_frame.lock().unwrap().take().unwrap()
+        };
+        let red = ColorRgba {
+            r: 1.0,
+            g: 0.0,
+            b: 0.0,
+            a: 1.0,
+        };
+        let blue = ColorRgba {
+            r: 0.0,
+            g: 0.0,
+            b: 1.0,
+            a: 1.0,
+        };
+
+        let (width, height, _) = render_frame(red);
+
+        // recreate between two frames
+        backend.force_recreate_now().unwrap();
+        let (new_width, new_height, data) = render_frame(blue);
+        assert_eq!((new_width, new_height), (width, height));
+        assert!(data.chunks_exact(4).all(|pixel| pixel == [0, 0, 255, 255]));
+
+        // a queued recreate happens before the next frame
+        backend.request_swapchain_recreate().unwrap();
+        let (new_width, new_height, data) = render_frame(red);
+        assert_eq!((new_width, new_height), (width, height));
+        assert!(data.chunks_exact(4).all(|pixel| pixel == [255, 0, 0, 255]));
+
+        backend
+            .detach_frame_fetcher("recreate".to_string())
+            .unwrap();
+    }
+
+    /// A custom pipe without pipelines, that counts how often it rendered.
+    #[derive(Debug)]
+    struct CountingPipe {
+        name: String,
+        rendered: Arc<AtomicUsize>,
+        /// Rendering returns an error.
+        fails: bool,
+        /// Rendering panics.
+        panics: bool,
+        /// The names of the threads that rendered.
+        threads: Arc<Mutex<HashSet<String>>>,
+    }
+
+    impl BackendCustomPipeline for CountingPipe {
+        fn pipe_name(&self) -> String {
+            self.name.clone()
+        }
+
+        fn pipeline_count(&self) -> u64 {
+            0
+        }
+
+        fn pipeline_names(&mut self, _name_of_first: u64) {}
+
+        fn pipe_layout_of(&self, _name: u64, _is_textured: bool) -> BackendPipelineLayout {
+            unreachable!("the pipe has no pipelines")
+        }
+
+        fn pipe_shader_names(&self, _name: u64, _is_textured: bool) -> Option<(String, String)> {
+            None
+        }
+
+        fn fill_exec_buffer(
+            &self,
+            _cmd: &PoolVec<u8>,
+            _render_execute: &mut dyn BackendRenderExecuteInterface,
+        ) {
+        }
+
+        fn render(
+            &self,
+            _cmd: &PoolVec<u8>,
+            _render: &mut dyn BackendRenderInterface,
+        ) -> anyhow::Result<()> {
+            self.rendered.fetch_add(1, Ordering::SeqCst);
+            if let Some(name) = std::thread::current().name() {
+                self.threads.lock().unwrap().insert(name.to_string());
+            }
+            assert!(!self.panics, "the counting pipe panicked while rendering");
+            anyhow::ensure!(!self.fails, "the counting pipe failed to render");
+            Ok(())
+        }
+
+        fn rewrite_texture_and_buffer_object_indices(
+            &self,
+            _cmd: &mut PoolVec<u8>,
+            _f: &dyn Fn(GraphicsObjectRewriteFunc),
+        ) {
+        }
+    }
+
+    fn counting_pipe(name: &str) -> (Box<CountingPipe>, Arc<AtomicUsize>) {
+        let rendered: Arc<AtomicUsize> = Default::default();
+        (
+            Box::new(CountingPipe {
+                name: name.to_string(),
+                rendered: rendered.clone(),
+                fails: false,
+                panics: false,
+                threads: Default::default(),
+            }),
+            rendered,
+        )
+    }
+
+    #[test]
+    fn vk_try_next_frame_does_not_block() {
+        let Some((backend, stream_data)) = try_prepare_backend(1, Default::default()) else {
+            return;
+        };
+
+        // the gpu might still render the frames that were submitted before
+        let next_frame_when_ready = || {
+            for _ in 0..10_000 {
+                match backend.try_next_frame() {
+                    Ok(presented) => return presented,
+                    Err(TryFrameError::WouldBlock) => {
+                        std::thread::sleep(Duration::from_millis(1));
+                    }
+                    Err(TryFrameError::Frame(err)) => panic!("{err}"),
+                }
+            }
+            panic!("the gpu never finished the frames");
+        };
+
+        let first = next_frame_when_ready();
+
+        let cmds = BackendCommands::default();
+        cmds.add_cmd(AllCommands::Misc(CommandsMisc::Swap));
+        backend.run_cmds(&cmds, &stream_data);
+
+        // calls that would have blocked did not start a new frame
+        let presented = next_frame_when_ready();
+        assert_eq!(presented.frame, first.frame + 2);
+        assert_eq!(next_frame_when_ready().frame, presented.frame + 1);
+    }
+
+    #[test]
+    fn vk_register_custom_pipe() {
+        let Some((backend, stream_data)) = try_prepare_backend(1, Default::default()) else {
+            return;
+        };
+
+        let (pipe, rendered) = counting_pipe("test::counting");
+        backend.register_custom_pipe(pipe).unwrap();
+        assert!(backend
+            .list_custom_pipes()
+            .contains(&"test::counting".to_string()));
+
+        let cmds = BackendCommands::default();
+        cmds.add_cmd(AllCommands::Render(CommandsRender::Mod(
+            CommandsRenderMod {
+                mod_name: PoolString::from_without_pool("test::counting".to_string()),
+                cmd: PoolVec::new_without_pool(),
+            },
+        )));
+        cmds.add_cmd(AllCommands::Misc(CommandsMisc::Swap));
+        backend.run_cmds(&cmds, &stream_data);
+        backend.wait_idle().unwrap();
+        assert_eq!(rendered.load(Ordering::SeqCst), 1);
+
+        // names must stay unique
+        let (duplicate, _) = counting_pipe("test::counting");
+        assert!(backend.register_custom_pipe(duplicate).is_err());
+        assert_eq!(
+            backend
+                .list_custom_pipes()
+                .iter()
+                .filter(|name| *name == "test::counting")
+                .count(),
+            1
+        );
+    }
+
+    #[test]
+    fn vk_render_thread_error_reaches_the_main_thread() {
+        let Some((backend, stream_data)) = try_prepare_backend(1, Default::default()) else {
+            return;
+        };
+
+        let (mut pipe, rendered) = counting_pipe("test::failing");
+        pipe.fails = true;
+        backend.register_custom_pipe(pipe).unwrap();
+
+        let cmds = BackendCommands::default();
+        cmds.add_cmd(AllCommands::Render(CommandsRender::Mod(
+            CommandsRenderMod {
+                mod_name: PoolString::from_without_pool("test::failing".to_string()),
+                cmd: PoolVec::new_without_pool(),
+            },
+        )));
+        cmds.add_cmd(AllCommands::Misc(CommandsMisc::Swap));
+        backend.run_cmds(&cmds, &stream_data);
+
+        // the render thread did not panic, its error is returned instead
+        let err = backend
+            .wait_idle()
+            .expect_err("the failed render command was not reported");
+        assert_eq!(rendered.load(Ordering::SeqCst), 1);
+        assert!(err
+            .chain()
+            .any(|err| err.to_string() == "the counting pipe failed to render"));
+
+        // the backend thread kept running and the error is only reported once
+        let cmds = BackendCommands::default();
+        cmds.add_cmd(AllCommands::Misc(CommandsMisc::Swap));
+        backend.run_cmds(&cmds, &stream_data);
+        backend.wait_idle().unwrap();
+    }
+
+    #[test]
+    fn vk_restarted_render_thread_renders_again() {
+        let Some((backend, stream_data)) = try_prepare_backend(
+            1,
+            ConfigBackend {
+                thread_count: 2,
+                ..Default::default()
+            },
+        ) else {
+            return;
+        };
+        let thread_count = std::thread::available_parallelism()
+            .map(|count| count.get())
+            .unwrap_or(1)
+            .min(2);
+
+        let (mut panicking, _) = counting_pipe("test::panicking");
+        panicking.panics = true;
+        let (pipe, rendered) = counting_pipe("test::counting");
+        let threads = pipe.threads.clone();
+        backend.register_custom_pipe(panicking).unwrap();
+        backend.register_custom_pipe(pipe).unwrap();
+
+        let render_frame = |mod_name: &str, count: usize| {
+            let cmds = BackendCommands::default();
+            for _ in 0..count {
+                cmds.add_cmd(AllCommands::Render(CommandsRender::Mod(
+                    CommandsRenderMod {
+                        mod_name: PoolString::from_without_pool(mod_name.to_string()),
+                        cmd: PoolVec::new_without_pool(),
+                    },
+                )));
+            }
+            cmds.add_cmd(AllCommands::Misc(CommandsMisc::Swap));
+            backend.run_cmds(&cmds, &stream_data);
+            backend.wait_idle()
+        };
+
+        // the first render thread dies
+        render_frame("test::panicking", 1).expect_err("the render thread panic was not reported");
+
+        backend.restart_render_thread(0).unwrap();
+        assert!(backend.restart_render_thread(thread_count).is_err());
+
+        for _ in 0..3 {
+            render_frame("test::counting", 16).unwrap();
+        }
+        assert_eq!(rendered.load(Ordering::SeqCst), 3 * 16);
+        let threads = threads.lock().unwrap();
+        assert_eq!(threads.len(), thread_count);
+        assert!(threads.contains("vk-render 0"));
+    }
+
+    #[test]
+    fn null_backend_register_custom_pipe() {
+        let (backend, _) = try_prepare_backend_of_type(
+            1,
+            Default::default(),
+            config::config::ConfigGfx {
+                backend: "null".to_string(),
+            },
+        )
+        .unwrap();
+
+        let (first, _) = counting_pipe("test::first");
+        let (second, _) = counting_pipe("test::second");
+        backend.register_custom_pipe(first).unwrap();
+        backend.register_custom_pipe(second).unwrap();
+        let (duplicate, _) = counting_pipe("test::first");
+        assert!(backend.register_custom_pipe(duplicate).is_err());
+        assert_eq!(
+            backend.list_custom_pipes(),
+            ["test::first".to_string(), "test::second".to_string()]
+        );
+    }
+
+    #[test]
+    fn vk_memory_pressure_callback() {
+        let Some((backend, _)) = try_prepare_backend(
+            1,
+            ConfigBackend {
+                staging_pool_size: 3,
+                staging_pool_max_size: 3,
+                ..Default::default()
+            },
+        ) else {
+            return;
+        };
+
+        let fallbacks: Arc<Mutex<Vec<(GraphicsMemoryAllocationType, usize)>>> = Default::default();
+        let fallbacks_clone = fallbacks.clone();
+        backend.set_memory_pressure_callback(Some(MemoryPressureCallback::new(
+            move |alloc_type, size| {
+                fallbacks_clone.lock().unwrap().push((*alloc_type, size));
+            },
+        )));
+
+        let backend_mt = backend.get_backend_mt();
+        // keep some staging memory in use, so the next allocation can't exceed the budget
+        let _small = backend_mt.mem_alloc(
+            GraphicsMemoryAllocationType::VertexBuffer {
+                required_size: NonZeroUsize::new(1024).unwrap(),
+            },
+            GraphicsMemoryAllocationMode::Immediate,
+        );
+        const LARGE_SIZE: usize = 4 * 1024 * 1024;
+        let mut large = backend_mt.mem_alloc(
+            GraphicsMemoryAllocationType::VertexBuffer {
+                required_size: NonZeroUsize::new(LARGE_SIZE).unwrap(),
+            },
+            GraphicsMemoryAllocationMode::Immediate,
+        );
+        // the fallback itself still works
+        assert!(matches!(
+            large.alloc_mut(),
+            GraphicsBackendMemoryAllocation::Vector(mem) if mem.len() == LARGE_SIZE
+        ));
+        assert!(fallbacks.lock().unwrap().iter().any(|(alloc_type, size)| {
+            matches!(
+                alloc_type,
+                GraphicsMemoryAllocationType::VertexBuffer { required_size }
+                    if required_size.get() == LARGE_SIZE
+            ) && *size == LARGE_SIZE
+        }));
+    }
+
+    #[test]
+    fn vk_staging_high_water_mark() {
+        let Some((backend, stream_data)) = try_prepare_backend(1, Default::default()) else {
+            return;
+        };
+
+        const MIB: usize = 1024 * 1024;
+        let budget = StagingMemoryBudget::new(
+            ConfigBackend::default().staging_pool_size,
+            ConfigBackend::default().staging_pool_max_size,
+        );
+        // bigger than a whole staging heap, so each upload gets its own staging memory
+        let upload_size = budget.heap_size as usize + MIB;
+        let cmds = BackendCommands::default();
+        for buffer_index in 0..2 {
+            cmds.add_cmd(AllCommands::Misc(CommandsMisc::CreateBufferObject(
+                CommandCreateBufferObject {
+                    buffer_index,
+                    upload_data: GraphicsBackendMemory::new(
+                        GraphicsBackendMemoryAllocation::Vector(vec![
+                            buffer_index as u8;
+                            upload_size
+                        ]),
+                        GraphicsMemoryAllocationType::VertexBuffer {
+                            required_size: NonZeroUsize::new(upload_size).unwrap(),
+                        },
+                    ),
+                },
+            )));
+        }
+        cmds.add_cmd(AllCommands::Misc(CommandsMisc::Swap));
+        backend.run_cmds(&cmds, &stream_data);
+
+        // no wait for the device in between, both uploads used staging memory at once
+        let high_water_mark = backend.staging_high_water_mark().unwrap();
+        assert!(high_water_mark >= 2 * upload_size as u64);
+        assert!(high_water_mark <= budget.max_size);
+
+        backend.wait_idle().unwrap();
+        assert_eq!(
+            backend.read_buffer_object(1, upload_size - 1, 1).unwrap(),
+            [1]
+        );
+    }
+
+    #[test]
+    fn vk_batched_flush_matches_individual_flushes() {
+        let Some((backend, stream_data)) = try_prepare_backend(1, Default::default()) else {
+            return;
+        };
+
+        let backend_mt = backend.get_backend_mt();
+        let (width, height) = (4, 4);
+        let mut mems: Vec<_> = (0..4u8)
+            .map(|i| {
+                let mut mem = backend_mt.mem_alloc(
+                    GraphicsMemoryAllocationType::TextureRgbaU8 {
+                        width: NonZeroUsize::new(width).unwrap(),
+                        height: NonZeroUsize::new(height).unwrap(),
+                        flags: TexFlags::TEXFLAG_NOMIPMAPS,
+                    },
+                    GraphicsMemoryAllocationMode::Immediate,
+                );
+                mem.as_mut_slice()
+                    .copy_from_slice(&[i * 60, 255 - i * 60, i, 255].repeat(width * height));
+                mem
+            })
+            .collect();
+        let expected: Vec<Vec<u8>> = mems.iter().map(|mem| mem.as_slice().to_vec()).collect();
+
+        let (individual, batched) = mems.split_at_mut(2);
+        for mem in individual {
+            backend_mt.try_flush_mem(mem, false).unwrap();
+        }
+        let mut batched: Vec<_> = batched.iter_mut().collect();
+        backend_mt.try_flush_mem_batch(&mut batched, false).unwrap();
+
+        let cmds = BackendCommands::default();
+        for (texture_index, data) in mems.into_iter().enumerate() {
+            cmds.add_cmd(AllCommands::Misc(CommandsMisc::TextureCreate(
+                CommandTextureCreate {
+                    texture_index: texture_index as u128,
+                    data,
+                    usage: TextureUsage::Static,
+                },
+            )));
+        }
+        cmds.add_cmd(AllCommands::Misc(CommandsMisc::Swap));
+        backend.run_cmds(&cmds, &stream_data);
+        backend.wait_idle().unwrap();
+
+        for (slot, expected) in expected.into_iter().enumerate() {
+            let (_, _, data) = backend.read_texture(slot as u128).unwrap();
+            assert_eq!(data, expected);
+        }
+    }
+
+    #[test]
+    fn vk_expensive_flush_completes_the_upload() {
+        let Some((backend, stream_data)) = try_prepare_backend(1, Default::default()) else {
+            return;
+        };
+
+        let backend_mt = backend.get_backend_mt();
+        let (width, height) = (8, 8);
+        let color = [12, 34, 56, 255];
+        let mut mem = backend_mt.mem_alloc(
+            GraphicsMemoryAllocationType::TextureRgbaU8 {
+                width: NonZeroUsize::new(width).unwrap(),
+                height: NonZeroUsize::new(height).unwrap(),
+                flags: TexFlags::TEXFLAG_NOMIPMAPS,
+            },
+            GraphicsMemoryAllocationMode::Immediate,
+        );
+        mem.as_mut_slice()
+            .copy_from_slice(&color.repeat(width * height));
+        // waits for the fence of the copy to the device local image
+        backend_mt.try_flush_mem(&mut mem, true).unwrap();
+
+        let cmds = BackendCommands::default();
+        cmds.add_cmd(AllCommands::Misc(CommandsMisc::TextureCreate(
+            CommandTextureCreate {
+                texture_index: 0,
+                data: mem,
+                usage: TextureUsage::Static,
+            },
+        )));
+        cmds.add_cmd(AllCommands::Misc(CommandsMisc::Swap));
+        backend.run_cmds(&cmds, &stream_data);
+        backend.wait_idle().unwrap();
+
+        let (_, _, data) = backend.read_texture(0).unwrap();
+        assert_eq!(data, color.repeat(width * height));
+    }
+
+    #[test]
+    fn read_buffer_object() {
+        let read_back = |backend: &GraphicsBackend, stream_data: &GraphicsStreamedData| {
+            let data: Vec<u8> = (0..=255u8).cycle().take(1000).collect();
+            let cmds = BackendCommands::default();
+            cmds.add_cmd(AllCommands::Misc(CommandsMisc::CreateBufferObject(
+                CommandCreateBufferObject {
+                    buffer_index: 0,
+                    upload_data: GraphicsBackendMemory::new(
+                        GraphicsBackendMemoryAllocation::Vector(data.clone()),
+                        GraphicsMemoryAllocationType::VertexBuffer {
+                            required_size: NonZeroUsize::new(data.len()).unwrap(),
+                        },
+                    ),
+                },
+            )));
+            cmds.add_cmd(AllCommands::Misc(CommandsMisc::Swap));
+            backend.run_cmds(&cmds, stream_data);
+            backend.wait_idle().unwrap();
+
+            assert_eq!(backend.read_buffer_object(0, 0, data.len()).unwrap(), data);
+            assert_eq!(
+                backend.read_buffer_object(0, 500, 16).unwrap(),
+                data[500..516]
+            );
+            assert!(backend.read_buffer_object(1, 0, 16).is_err());
+        };
+
+        let (backend, stream_data) = try_prepare_backend_of_type(
+            1,
+            Default::default(),
+            config::config::ConfigGfx {
+                backend: "null".to_string(),
+            },
+        )
+        .unwrap();
+        read_back(&backend, &stream_data);
+        assert!(backend.read_buffer_object(0, 990, 16).is_err());
+        drop(backend);
+
+        let Some((backend, stream_data)) = try_prepare_backend(1, Default::default()) else {
+            return;
+        };
+        read_back(&backend, &stream_data);
+    }
+
+    #[test]
+    fn read_shader_storage() {
+        let read_back = |backend: &GraphicsBackend, stream_data: &GraphicsStreamedData| {
+            let data: Vec<u8> = (0..=255u8).cycle().take(1024).collect();
+            let mut upload_data = backend.get_backend_mt().mem_alloc(
+                GraphicsMemoryAllocationType::ShaderStorage {
+                    required_size: NonZeroUsize::new(data.len()).unwrap(),
+                },
+                GraphicsMemoryAllocationMode::Immediate,
+            );
+            upload_data.as_mut_slice().copy_from_slice(&data);
+            let cmds = BackendCommands::default();
+            cmds.add_cmd(AllCommands::Misc(CommandsMisc::CreateShaderStorage(
+                CommandCreateShaderStorage {
+                    shader_storage_index: 0,
+                    upload_data,
+                },
+            )));
+            cmds.add_cmd(AllCommands::Misc(CommandsMisc::Swap));
+            backend.run_cmds(&cmds, stream_data);
+            backend.wait_idle().unwrap();
+
+            assert_eq!(backend.read_shader_storage(0, 0, data.len()).unwrap(), data);
+            assert_eq!(
+                backend.read_shader_storage(0, 500, 16).unwrap(),
+                data[500..516]
+            );
+            assert!(backend.read_shader_storage(1, 0, 16).is_err());
+            // buffer objects & shader storages don't share their indices
+            assert!(backend.read_buffer_object(0, 0, 16).is_err());
+        };
+
+        let (backend, stream_data) = try_prepare_backend_of_type(
+            1,
+            Default::default(),
+            config::config::ConfigGfx {
+                backend: "null".to_string(),
+            },
+        )
+        .unwrap();
+        read_back(&backend, &stream_data);
+        drop(backend);
+
+        let Some((backend, stream_data)) = try_prepare_backend(1, Default::default()) else {
+            return;
+        };
+        read_back(&backend, &stream_data);
+    }
+
+    #[test]
+    fn last_frame_stats() {
+        let render_frame = |backend: &GraphicsBackend, stream_data: &GraphicsStreamedData| {
+            let cmds = BackendCommands::default();
+            cmds.add_cmd(AllCommands::Misc(CommandsMisc::CreateBufferObject(
+                CommandCreateBufferObject {
+                    buffer_index: 0,
+                    upload_data: GraphicsBackendMemory::new(
+                        GraphicsBackendMemoryAllocation::Vector(vec![0; 64]),
+                        GraphicsMemoryAllocationType::VertexBuffer {
+                            required_size: NonZeroUsize::new(64).unwrap(),
+                        },
+                    ),
+                },
+            )));
+            for _ in 0..2 {
+                cmds.add_cmd(AllCommands::Misc(CommandsMisc::UpdateBufferObject(
+                    CommandUpdateBufferObject {
+                        buffer_index: 0,
+                        update_data: vec![1; 16],
+                        update_regions: vec![CommandUpdateBufferRegion {
+                            src_offset: 0,
+                            dst_offset: 16,
+                            size: 16,
+                        }],
+                    },
+                )));
+            }
+            cmds.add_cmd(AllCommands::Render(CommandsRender::Clear(CommandClear {
+                color: Default::default(),
+                force_clear: true,
+                clip: None,
+            })));
+            for _ in 0..3 {
+                stream_data.add_vertices(&[Default::default(); 4]);
+                cmds.add_cmd(AllCommands::Render(CommandsRender::Stream(
+                    CommandsRenderStream::Render(CommandRender {
+                        state: Default::default(),
+                        texture_index: StateTexture::None,
+                        prim_type: PrimType::Lines,
+                        prim_count: 4,
+                        vertices_offset: 0,
+                    }),
+                )));
+            }
+            cmds.add_cmd(AllCommands::Misc(CommandsMisc::Swap));
+            backend.run_cmds(&cmds, stream_data);
+            backend.wait_idle().unwrap();
+            let stats = backend.last_frame_stats().unwrap();
+            assert_eq!(stats.buffer_updates, 2);
+            assert_eq!(stats.texture_updates, 0);
+
+            // the stats only cover a single frame
+            cmds.add_cmd(AllCommands::Misc(CommandsMisc::Swap));
+            backend.run_cmds(&cmds, stream_data);
+            backend.wait_idle().unwrap();
+            assert_eq!(
+                backend.last_frame_stats().unwrap(),
+                BackendFrameStats::default()
+            );
+
+            stats
+        };
+
+        let (backend, stream_data) = try_prepare_backend_of_type(
+            1,
+            Default::default(),
+            config::config::ConfigGfx {
+                backend: "null".to_string(),
+            },
+        )
+        .unwrap();
+        let stats = render_frame(&backend, &stream_data);
+        // draw commands are discarded
+        assert_eq!(stats.estimated_draw_calls, 0);
+        assert_eq!(stats.render_passes, 0);
+        drop(backend);
+
+        let Some((backend, stream_data)) = try_prepare_backend(1, Default::default()) else {
+            return;
+        };
+        let stats = render_frame(&backend, &stream_data);
+        // clearing is not a draw call, every stream render is one
+        assert_eq!(stats.estimated_draw_calls, 3);
+        assert!(stats.command_groups >= 1);
+        assert_eq!(stats.render_passes, 1);
+    }
+
+    #[test]
+    fn null_backend_clear_and_fetch() {
+        let (backend, stream_data) = try_prepare_backend_of_type(
+            1,
+            Default::default(),
+            config::config::ConfigGfx {
+                backend: "null".to_string(),
+            },
+        )
+        .unwrap();
+
+        let fetcher = Arc::new(LastFrameFetcher {
+            fetch_index: FetchCanvasIndex::Onscreen,
+            last_frame: Default::default(),
+        });
+        backend
+            .attach_frame_fetcher("null".to_string(), fetcher.clone())
+            .unwrap();
+
+        let cmds = BackendCommands::default();
+        cmds.add_cmd(AllCommands::Render(CommandsRender::Clear(CommandClear {
+            color: ColorRgba {
+                r: 0.0,
+                g: 1.0,
+                b: 0.0,
+                a: 1.0,
+            },
+            force_clear: true,
+            clip: None,
+        })));
+        // draw commands are discarded
+        stream_data.add_vertices(&[Default::default(); 4]);
+        cmds.add_cmd(AllCommands::Render(CommandsRender::Stream(
+            CommandsRenderStream::Render(CommandRender {
+                state: Default::default(),
+                texture_index: StateTexture::None,
+                prim_type: PrimType::Lines,
+                prim_count: 4,
+                vertices_offset: 0,
+            }),
+        )));
+        cmds.add_cmd(AllCommands::Misc(CommandsMisc::Swap));
+        backend.run_cmds(&cmds, &stream_data);
+        backend.wait_idle().unwrap();
+
+        let (width, height, data) = fetcher.last_frame.lock().unwrap().take().unwrap();
+        assert!(width > 0 && height > 0);
+        assert_eq!(data.len(), width as usize * height as usize * 4);
+        assert!(data.chunks_exact(4).all(|pixel| pixel == [0, 255, 0, 255]));
+    }
+
+    #[test]
+    fn presented_image_row_orientation() {
+        let (width, height, pitch) = (3, 4, 16);
+        let row_size = width * 4;
+        // every pixel of a row has the row index as value, the rest is driver padding
+        let mut padded = vec![0xFF; pitch * height];
+        for y in 0..height {
+            padded[y * pitch..y * pitch + row_size].fill(y as u8);
+        }
+
+        let mut top_to_bottom = padded.clone();
+        pack_image_rows(
+            &mut top_to_bottom,
+            row_size,
+            pitch,
+            height,
+            PresentedImageOrientation::TopToBottom,
+        );
+        let mut bottom_to_top = padded;
+        pack_image_rows(
+            &mut bottom_to_top,
+            row_size,
+            pitch,
+            height,
+            PresentedImageOrientation::BottomToTop,
+        );
+
+        for y in 0..height {
+            let row = y * row_size..(y + 1) * row_size;
+            assert!(top_to_bottom[row.clone()].iter().all(|&v| v == y as u8));
+            assert!(bottom_to_top[row]
+                .iter()
+                .all(|&v| v == (height - 1 - y) as u8));
+        }
+    }
+
+    #[test]
+    fn clear_rect_scissor_region() {
+        let canvas_size = ash::vk::Extent2D {
+            width: 100,
+            height: 50,
+        };
+        let mut exec_buffer = RenderCommandExecuteBuffer::default();
+        exec_buffer.clear_rect_in_render_thread(
+            &StateClip {
+                x: 10,
+                y: 20,
+                w: 30,
+                h: 40,
+            },
+            Default::default(),
+            canvas_size,
+        );
+        assert!(exec_buffer.clear_color_in_render_thread);
+        assert!(exec_buffer.has_dynamic_state);
+        assert_eq!(exec_buffer.estimated_render_call_count, 0);
+        // clamped to the canvas
+        assert_eq!(exec_buffer.scissor.offset.x, 10);
+        assert_eq!(exec_buffer.scissor.offset.y, 20);
+        assert_eq!(exec_buffer.scissor.extent.width, 30);
+        assert_eq!(exec_buffer.scissor.extent.height, 30);
+
+        // shifted by the dynamic viewport offset
+        let mut exec_buffer = RenderCommandExecuteBuffer::default();
+        exec_buffer.clear_rect_in_render_thread(
+            &StateClip {
+                x: -5,
+                y: 0,
+                w: 20,
+                h: 10,
+            },
+            ash::vk::Offset2D { x: 50, y: 5 },
+            canvas_size,
+        );
+        assert_eq!(exec_buffer.scissor.offset.x, 45);
+        assert_eq!(exec_buffer.scissor.offset.y, 5);
+        assert_eq!(exec_buffer.scissor.extent.width, 20);
+        assert_eq!(exec_buffer.scissor.extent.height, 10);
+
+        // nothing to clear outside of the canvas
+        let mut exec_buffer = RenderCommandExecuteBuffer::default();
+        exec_buffer.clear_rect_in_render_thread(
+            &StateClip {
+                x: 100,
+                y: 0,
+                w: 20,
+                h: 10,
+            },
+            Default::default(),
+            canvas_size,
+        );
+        assert!(!exec_buffer.clear_color_in_render_thread);
+        assert!(!exec_buffer.has_dynamic_state);
+    }
+
+    #[test]
+    fn buffer_update_batch_regions() {
+        let regions = [
+            CommandUpdateBufferRegion {
+                src_offset: 0,
+                dst_offset: 64,
+                size: 16,
+            },
+            CommandUpdateBufferRegion {
+                src_offset: 16,
+                dst_offset: 8,
+                size: 8,
+            },
+            CommandUpdateBufferRegion {
+                src_offset: 24,
+                dst_offset: 200,
+                size: 32,
+            },
+        ];
+        let (copies, barrier_offset, barrier_size) = buffer_update_copies(56, &regions, 4).unwrap();
+
+        assert_eq!(copies.len(), regions.len());
+        for (copy, region) in copies.iter().zip(regions.iter()) {
+            assert_eq!(copy.src_offset, region.src_offset as u64);
+            assert_eq!(copy.dst_offset, region.dst_offset as u64 + 4);
+            assert_eq!(copy.size, region.size as u64);
+        }
+        // one barrier range covers all regions
+        assert_eq!(barrier_offset, 8 + 4);
+        assert_eq!(barrier_size, 232 - 8);
+
+        // regions outside of the update data are rejected
+        assert!(buffer_update_copies(55, &regions, 4).is_err());
+        assert!(buffer_update_copies(56, &[], 4).is_err());
+    }
+
+    #[test]
+    fn buffer_update_single_region_matches_batch() {
+        let regions = [
+            CommandUpdateBufferRegion {
+                src_offset: 0,
+                dst_offset: 0,
+                size: 4,
+            },
+            CommandUpdateBufferRegion {
+                src_offset: 12,
+                dst_offset: 1024,
+                size: 52,
+            },
+            CommandUpdateBufferRegion {
+                src_offset: 63,
+                dst_offset: 7,
+                size: 1,
+            },
+        ];
+        for region in &regions {
+            let (copies, barrier_offset, barrier_size) =
+                buffer_update_copies(64, std::slice::from_ref(region), 16).unwrap();
+            let copy = buffer_update_copy(64, region, 16).unwrap();
+            assert_eq!(copies.len(), 1);
+            assert_eq!(copy.src_offset, copies[0].src_offset);
+            assert_eq!(copy.dst_offset, copies[0].dst_offset);
+            assert_eq!(copy.size, copies[0].size);
+            assert_eq!(copy.dst_offset, barrier_offset);
+            assert_eq!(copy.size, barrier_size);
+
+            // same rejections as the batch path
+            assert!(buffer_update_copy(region.src_offset + region.size - 1, region, 16).is_err());
+        }
+        assert!(buffer_update_copy(
+            64,
+            &CommandUpdateBufferRegion {
+                src_offset: 0,
+                dst_offset: 0,
+                size: 0,
+            },
+            16
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn texture_array_layers_share_their_extent() {
+        // non-square, non-power-of-two layers are fine
+        let (width, height, depth) = (24, 40, 3);
+        assert_eq!(
+            texture_array_layer_extent(
+                width,
+                height,
+                depth,
+                Some(width * height * 4),
+                width * height * 4 * depth
+            )
+            .unwrap(),
+            (width, height)
+        );
+
+        // the data can't be split into equally sized layers
+        let err =
+            texture_array_layer_extent(width, height, depth, None, width * height * 4 * 3 + 4)
+                .unwrap_err();
+        assert!(err.to_string().contains("equally sized layers"), "{err}");
+
+        // the layers in the data are of a different extent than the array's
+        let err = texture_array_layer_extent(
+            width,
+            height,
+            depth,
+            Some(width * height * 4),
+            32 * 40 * 4 * depth,
+        )
+        .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "the layers of the texture array are 5120 bytes big, \
+            but a 24x40 layer needs 3840 bytes."
+        );
+
+        assert!(texture_array_layer_extent(width, height, 0, None, 0).is_err());
+    }
+
+    #[test]
+    fn vk_uniform_instance_pool_grows_ahead() {
+        let Some((backend, stream_data)) = try_prepare_backend(1, Default::default()) else {
+            return;
+        };
+
+        // the backend allocates instances for a few frames up-front
+        let initial = backend.uniform_instance_pool_occupancy().unwrap();
+        assert!(initial.capacity >= GRAPHICS_UNIFORM_INSTANCE_COUNT * 4 * 2);
+
+        let cmds = BackendCommands::default();
+        for _ in 0..32 {
+            cmds.add_cmd(AllCommands::Misc(CommandsMisc::Swap));
+            backend.run_cmds(&cmds, &stream_data);
+            // the stream data of the next frame was already taken from the pool,
+            // the request after it is still served without allocating
+            let occupancy = backend.uniform_instance_pool_occupancy().unwrap();
+            assert!(
+                occupancy.free >= GRAPHICS_UNIFORM_INSTANCE_COUNT,
+                "{occupancy:?}"
+            );
+            assert!(occupancy.capacity >= initial.capacity);
+        }
+        backend.wait_idle().unwrap();
+    }
+
+    #[test]
+    fn uniform_instance_pool_reuses_released_frames() {
+        let growth = StreamMemoryPoolGrowth {
+            min_alloc: GRAPHICS_UNIFORM_INSTANCE_COUNT,
+            factor: ConfigBackend::default().uniform_pool_growth_factor,
+        };
+        // the backend allocates instances for a few frames up-front
+        let initial = growth.alloc_amount(
+            StreamMemoryPoolOccupancy {
+                free: 0,
+                capacity: 0,
+            },
+            GRAPHICS_UNIFORM_INSTANCE_COUNT * 4 * 2,
+        );
+        let mut occupancy = StreamMemoryPoolOccupancy {
+            free: initial,
+            capacity: initial,
+        };
+        // like the backend thread: the frames in flight, the frame in use
+        // and the one prepared for the next frame keep their instances
+        const HELD_BLOCKS: usize = 4;
+        let mut held_blocks = 0;
+        let mut allocations_per_frame = Vec::new();
+        for _ in 0..100 {
+            let mut allocations = 0;
+            let alloc_amount = growth.alloc_amount(occupancy, GRAPHICS_UNIFORM_INSTANCE_COUNT);
+            if alloc_amount > 0 {
+                allocations += 1;
+                occupancy.free += alloc_amount;
+                occupancy.capacity += alloc_amount;
+            }
+            occupancy.free -= GRAPHICS_UNIFORM_INSTANCE_COUNT;
+            held_blocks += 1;
+            // the oldest frame finished, its block returns to the pool
+            if held_blocks > HELD_BLOCKS {
+                held_blocks -= 1;
+                occupancy.free += GRAPHICS_UNIFORM_INSTANCE_COUNT;
+            }
+            allocations_per_frame.push(allocations);
+        }
+        // two consecutive light frames allocate at most once
+        assert!(allocations_per_frame
+            .windows(2)
+            .all(|frames| frames.iter().sum::<u32>() <= 1));
+        // once the frames in flight are served, nothing is allocated anymore
+        assert!(allocations_per_frame[HELD_BLOCKS..]
+            .iter()
+            .all(|&allocations| allocations == 0));
+        // the instances allocated up-front already serve all frames in flight
+        assert_eq!(occupancy.capacity, initial);
+    }
+
+    #[test]
+    fn staging_memory_budget() {
+        const MIB: u64 = 1024 * 1024;
+        let config = ConfigBackend::default();
+        let default_budget =
+            StagingMemoryBudget::new(config.staging_pool_size, config.staging_pool_max_size);
+        // the default heaps match the former fixed 8 MiB blocks
+        assert_eq!(default_budget.block_size(), 8 * MIB);
+
+        // an upload larger than the default staging memory, while other uploads are in flight
+        let in_flight = default_budget.heap_size;
+        let upload_size = default_budget.max_size + MIB;
+        assert!(!default_budget.allows(in_flight, upload_size));
+        // waiting for the gpu can't free any memory, so it's allowed anyway
+        assert!(default_budget.allows(0, upload_size));
+
+        let raised_budget = StagingMemoryBudget::new(
+            config.staging_pool_size,
+            ((in_flight + upload_size) / MIB) as u32,
+        );
+        assert!(raised_budget.allows(in_flight, upload_size));
+        assert!(!raised_budget.allows(in_flight + 1, upload_size));
+
+        // the limit is never smaller than a single heap
+        let small_budget = StagingMemoryBudget::new(64, 1);
+        assert_eq!(small_budget.max_size, small_budget.heap_size);
+        assert!(small_budget.allows(small_budget.block_size(), small_budget.block_size()));
+    }
+
+    #[test]
+    fn quad_container_sub_range() {
+        // quads 10..15 of a layer
+        let (index_offset, index_count) = quad_range_indices(10, 5);
+        // 6 indices per quad, skip the first 10 quads
+        assert_eq!(index_offset, 10 * 6 * std::mem::size_of::<u32>() as u64);
+        assert_eq!(index_count, 5 * 6);
+
+        assert_eq!(quad_range_indices(0, 1), (0, 6));
+    }
+
+    #[test]
+    fn device_selection_fingerprint() {
+        let device = |name: &str, ty: GpuType, device_id: u32| DeviceCandidate {
+            name: name.to_string(),
+            ty,
+            fingerprint: DeviceFingerprint {
+                vendor_id: 0x10DE,
+                device_id,
+                driver_version: 1,
+                api_version: ash::vk::make_api_version(0, 1, 3, 0),
+            },
+        };
+        let devices = vec![
+            device("integrated", GpuType::Integrated, 1),
+            device("discrete b", GpuType::Discrete, 3),
+            device("discrete a", GpuType::Discrete, 2),
+        ];
+        let fingerprint = |devices: &[DeviceCandidate], gpu: &str| {
+            select_device(devices, gpu).map(|index| devices[index].fingerprint)
+        };
+
+        let selected = fingerprint(&devices, "auto").unwrap();
+        assert_eq!(fingerprint(&devices, "auto"), Some(selected));
+        assert_eq!(selected.device_id, 2);
+        assert_eq!(
+            selected.to_string(),
+            "10de:0002 vulkan 1.3.0 driver 0.0.0.1"
+        );
+
+        // the driver listing the devices in another order does not change the selection
+        let reversed: Vec<_> = devices.iter().rev().cloned().collect();
+        assert_eq!(fingerprint(&reversed, "auto"), Some(selected));
+
+        assert_eq!(fingerprint(&devices, "integrated").unwrap().device_id, 1);
+        assert_eq!(fingerprint(&devices, "missing"), None);
+    }
+
+    #[test]
+    fn gpu_info_of_selected_device() {
+        let gpu = |name: &str, ty: GpuType| Gpu {
+            name: name.to_string(),
+            ty,
+        };
+        let gpus = Gpus {
+            gpus: vec![
+                gpu("integrated", GpuType::Integrated),
+                gpu("discrete", GpuType::Discrete),
+            ],
+            auto: gpu("discrete", GpuType::Discrete),
+            cur: CurGpu {
+                name: "integrated".to_string(),
+                msaa_sampling_count: 4,
+                ty: GpuType::Integrated,
+            },
+        };
+        let heap = |size: u64, flags: ash::vk::MemoryHeapFlags| {
+            ash::vk::MemoryHeap::default().size(size).flags(flags)
+        };
+        let mut mem_props = ash::vk::PhysicalDeviceMemoryProperties::default();
+        mem_props.memory_heap_count = 3;
+        mem_props.memory_heaps[0] = heap(8 << 30, ash::vk::MemoryHeapFlags::empty());
+        mem_props.memory_heaps[1] = heap(2 << 30, ash::vk::MemoryHeapFlags::DEVICE_LOCAL);
+        mem_props.memory_heaps[2] = heap(256 << 20, ash::vk::MemoryHeapFlags::DEVICE_LOCAL);
+        // not part of the heap count
+        mem_props.memory_heaps[3] = heap(16 << 30, ash::vk::MemoryHeapFlags::DEVICE_LOCAL);
+
+        // the info describes the selected device, not the automatically picked one
+        let info = GpuInfo::new(&gpus, vram_estimate(&mem_props));
+        assert_eq!(info.name, "integrated");
+        assert_eq!(info.ty, GpuType::Integrated);
+        assert!(info.is_integrated());
+        assert!(!info.is_discrete());
+        assert_eq!(info.vram_estimate, Some(2 << 30));
+
+        assert_eq!(
+            vram_estimate(&ash::vk::PhysicalDeviceMemoryProperties::default()),
+            None
+        );
+    }
+
+    #[test]
+    fn anisotropy_clamped_to_device() {
+        assert_eq!(sampler_anisotropy(64, true, 16), Some(16.0));
+        assert_eq!(sampler_anisotropy(4, true, 16), Some(4.0));
+        // not supported or disabled
+        assert_eq!(sampler_anisotropy(16, false, 16), None);
+        assert_eq!(sampler_anisotropy(1, true, 16), None);
+        assert_eq!(sampler_anisotropy(0, true, 16), None);
+    }
+
+    #[test]
+    fn texture_filter_selects_a_nearest_sampler() {
+        let sampler =
+            |state: &State| SupportedSamplerTypes::from_usize(get_address_mode_index(state));
+        let mut state = State::new();
+        assert_eq!(sampler(&state), Some(SupportedSamplerTypes::Repeat));
+        state.filter(TextureFilter::Nearest);
+        assert_eq!(sampler(&state), Some(SupportedSamplerTypes::RepeatNearest));
+        state.wrap(WrapType::Clamp);
+        assert_eq!(
+            sampler(&state),
+            Some(SupportedSamplerTypes::ClampToEdgeNearest)
+        );
+        state.filter(TextureFilter::Linear);
+        assert_eq!(sampler(&state), Some(SupportedSamplerTypes::ClampToEdge));
+
+        let nearest = Sampler::create_info(
+            None,
+            sampler_lod(0.0, 0.0, 0.0),
+            ash::vk::Filter::NEAREST,
+            ash::vk::SamplerAddressMode::REPEAT,
+            ash::vk::SamplerAddressMode::REPEAT,
+            ash::vk::SamplerAddressMode::REPEAT,
+        );
+        assert_eq!(nearest.mag_filter, ash::vk::Filter::NEAREST);
+        assert_eq!(nearest.min_filter, ash::vk::Filter::NEAREST);
+        assert_eq!(nearest.mipmap_mode, ash::vk::SamplerMipmapMode::NEAREST);
+    }
+
+    #[test]
+    fn sampler_lod_clamp() {
+        let info = |lod| {
+            Sampler::create_info(
+                None,
+                lod,
+                ash::vk::Filter::LINEAR,
+                ash::vk::SamplerAddressMode::REPEAT,
+                ash::vk::SamplerAddressMode::REPEAT,
+                ash::vk::SamplerAddressMode::REPEAT,
+            )
+        };
+
+        // skip the two sharpest mips
+        let clamped = info(sampler_lod(-0.5, 2.0, 4.0));
+        assert_eq!(clamped.mip_lod_bias, -0.5);
+        assert_eq!(clamped.min_lod, 2.0);
+        assert_eq!(clamped.max_lod, 4.0);
+
+        // the defaults don't clamp
+        let config = ConfigBackend::default();
+        let unclamped = info(sampler_lod(
+            config.global_texture_lod_bias,
+            config.texture_min_lod,
+            config.texture_max_lod,
+        ));
+        assert_eq!(unclamped.min_lod, -1000.0);
+        assert_eq!(unclamped.max_lod, 1000.0);
+
+        // the max is never smaller than the min
+        assert_eq!(sampler_lod(0.0, 3.0, 1.0).max, 3.0);
+    }
+
+    #[test]
+    fn vk_debug_callback_logs_errors_if_requested() {
+        let callback_data = ash::vk::DebugUtilsMessengerCallbackDataEXT::default()
+            .message(c"synthesized validation error");
+        let res = unsafe {
+            VulkanBackendLoading::vk_debug_callback(
+                ash::vk::DebugUtilsMessageSeverityFlagsEXT::ERROR,
+                ash::vk::DebugUtilsMessageTypeFlagsEXT::VALIDATION,
+                &callback_data,
+                VulkanDebugCallbackOptions::user_data(false),
+            )
+        };
+        assert_eq!(res, ash::vk::FALSE);
+    }
+
+    #[test]
+    fn command_groups_drain_in_creation_order() {
+        let canvases = [
+            FrameCanvasIndex::Offscreen(2),
+            FrameCanvasIndex::Onscreen,
+            FrameCanvasIndex::Offscreen(0),
+            FrameCanvasIndex::Offscreen(1),
+        ];
+        let drained_order = |insert_order: &[usize]| {
+            let mut command_groups: HashMap<FrameCanvasIndex, ThreadCommandGroup> =
+                Default::default();
+            for &index in insert_order {
+                command_groups.insert(
+                    canvases[index],
+                    ThreadCommandGroup {
+                        canvas_index: canvases[index],
+                        in_order_id: index,
+                        ..Default::default()
+                    },
+                );
+            }
+            let order: Vec<_> = VulkanBackend::drain_command_groups_in_order(&mut command_groups)
+                .into_iter()
+                .map(|command_group| command_group.canvas_index)
+                .collect();
+            assert!(command_groups.is_empty());
+            order
+        };
+
+        // the same command sequence always results in the same submission order,
+        // no matter how the hash map iterates
+        for _ in 0..16 {
+            assert_eq!(drained_order(&[0, 1, 2, 3]), canvases);
+            assert_eq!(drained_order(&[3, 1, 0, 2]), canvases);
+        }
+    }
+
+    #[test]
+    fn frame_graph_lists_passes_in_order() {
+        let group = |canvas_index, in_order_id, render_pass: RenderPassType, cmd_count| {
+            ThreadCommandGroup {
+                canvas_index,
+                in_order_id,
+                render_pass,
+                render_pass_index: match render_pass {
+                    RenderPassType::Normal(sub_type) => sub_type as usize,
+                    RenderPassType::MultiSampling => 0,
+                },
+                cmds: (0..cmd_count)
+                    .map(|_| RenderCommandExecuteBuffer::default())
+                    .collect(),
+                ..Default::default()
+            }
+        };
+        let switching = RenderPassType::Normal(RenderPassSubType::Switching1);
+
+        // onscreen, offscreen canvas, back to onscreen & a switch pass on it.
+        // the first two groups already wait for a render thread.
+        let command_groups = vec![
+            group(FrameCanvasIndex::Onscreen, 0, RenderPassType::default(), 3),
+            group(
+                FrameCanvasIndex::Offscreen(0),
+                1,
+                RenderPassType::default(),
+                1,
+            ),
+        ];
+        let mut current_command_groups: HashMap<FrameCanvasIndex, ThreadCommandGroup> =
+            Default::default();
+        current_command_groups.insert(
+            FrameCanvasIndex::Onscreen,
+            group(FrameCanvasIndex::Onscreen, 3, switching, 2),
+        );
+        current_command_groups.insert(
+            FrameCanvasIndex::Offscreen(1),
+            group(
+                FrameCanvasIndex::Offscreen(1),
+                2,
+                RenderPassType::default(),
+                0,
+            ),
+        );
+
+        let graph = VulkanBackend::frame_graph(&command_groups, &current_command_groups);
+        let entry = |in_order_id, canvas_index, render_pass_index, render_pass, cmd_count| {
+            FrameGraphGroup {
+                in_order_id,
+                canvas_index,
+                render_pass_index,
+                render_pass,
+                cmd_count,
+            }
+        };
+        assert_eq!(
+            graph,
+            [
+                entry(
+                    0,
+                    FrameCanvasIndex::Onscreen,
+                    0,
+                    RenderPassType::default(),
+                    3
+                ),
+                entry(
+                    1,
+                    FrameCanvasIndex::Offscreen(0),
+                    0,
+                    RenderPassType::default(),
+                    1
+                ),
+                entry(
+                    2,
+                    FrameCanvasIndex::Offscreen(1),
+                    0,
+                    RenderPassType::default(),
+                    0
+                ),
+                entry(3, FrameCanvasIndex::Onscreen, 1, switching, 2),
+            ]
+        );
+        // the dump does not touch the command groups
+        assert_eq!(command_groups.len(), 2);
+        assert_eq!(current_command_groups.len(), 2);
+    }
+
+    #[test]
+    fn scale_only_viewport_change_keeps_swap_chain() {
+        let extent = ash::vk::Extent2D {
+            width: 800,
+            height: 600,
+        };
+        let resize = |width, height, content_scale| CommandUpdateViewport {
+            x: 0,
+            y: 0,
+            width,
+            height,
+            by_resize: true,
+            content_scale,
+        };
+        let mut content_scale = 1.0;
+
+        // e.g. the window moved to a HiDPI display, but kept its pixel size
+        assert!(!VulkanBackend::apply_resize_viewport(
+            extent,
+            &mut content_scale,
+            &resize(800, 600, 2.0)
+        ));
+        assert_eq!(content_scale, 2.0);
+
+        assert!(VulkanBackend::apply_resize_viewport(
+            extent,
+            &mut content_scale,
+            &resize(1600, 1200, 2.0)
+        ));
+        assert_eq!(content_scale, 2.0);
+    }
+
+    #[test]
+    fn transient_surface_loss_recovers() {
+        const NO_BACKOFF: RetryBackoff = RetryBackoff {
+            initial: Duration::ZERO,
+            max_delay: Duration::ZERO,
+            max_total: Duration::ZERO,
+        };
+        // the surface is back on the second attempt
+        let mut attempts = Vec::new();
+        let recovered = VulkanBackend::retry_with_backoff(3, NO_BACKOFF, |attempt| {
+            attempts.push(attempt);
+            if attempt == 0 {
+                Err(anyhow::anyhow!("surface lost"))
+            } else {
+                Ok(attempt)
+            }
+        });
+        assert_eq!(recovered, Some(1));
+        assert_eq!(attempts, [0, 1]);
+
+        // the surface never returns, so the caller falls back to a fake surface
+        let mut attempt_count = 0;
+        let recovered = VulkanBackend::retry_with_backoff(3, NO_BACKOFF, |_| {
+            attempt_count += 1;
+            Err::<(), _>(anyhow::anyhow!("surface lost"))
+        });
+        assert_eq!(recovered, None);
+        assert_eq!(attempt_count, 3);
+
+        // no retries at all
+        assert_eq!(
+            VulkanBackend::retry_with_backoff(0, NO_BACKOFF, |_| anyhow::Ok(())),
+            None
+        );
+    }
+
+    #[test]
+    fn surface_loss_backoff_is_capped() {
+        let backoff = RetryBackoff {
+            initial: Duration::from_millis(8),
+            max_delay: Duration::from_millis(250),
+            max_total: Duration::from_secs(1),
+        };
+        assert_eq!(
+            backoff.delays(4),
+            [8, 16, 32].map(Duration::from_millis).to_vec()
+        );
+
+        // even the maximum amount of retries only waits for a bounded time
+        let delays = backoff.delays(u32::MAX);
+        assert!(delays.iter().all(|delay| *delay <= backoff.max_delay));
+        assert!(delays.iter().sum::<Duration>() <= backoff.max_total);
+        assert!(!delays.is_empty());
+    }
+
+    #[test]
+    fn vk_surface_recovery() {
+        let Some((backend, stream_data)) = try_prepare_backend(1, Default::default()) else {
+            return;
+        };
+
+        let fetcher = Arc::new(LastFrameFetcher {
+            fetch_index: FetchCanvasIndex::Onscreen,
+            last_frame: Default::default(),
+        });
+        backend
+            .attach_frame_fetcher("recovery".to_string(), fetcher.clone())
+            .unwrap();
+
+        let render_frame = |color: ColorRgba| {
+            let cmds = BackendCommands::default();
+            cmds.add_cmd(AllCommands::Render(CommandsRender::Clear(CommandClear {
+                color,
+                force_clear: true,
+                clip: None,
+            })));
+            cmds.add_cmd(AllCommands::Misc(CommandsMisc::Swap));
+            backend.run_cmds(&cmds, &stream_data);
+            backend.wait_idle().unwrap();
+        };
+
+        render_frame(ColorRgba {
+            r: 1.0,
+            g: 0.0,
+            b: 0.0,
+            a: 1.0,
+        });
+        // goes through the same path as a surface that was reported as lost
+        backend.recover_surface().unwrap();
+        render_frame(ColorRgba {
+            r: 0.0,
+            g: 1.0,
+            b: 0.0,
+            a: 1.0,
+        });
+
+        // the new surface is presented to
+        let (width, height, rgba) = fetcher
+            .last_frame
+            .lock()
+            .unwrap()
+            .take()
+            .expect("no frame was fetched");
+        assert_eq!(rgba.len(), (width * height * 4) as usize);
+        assert_eq!(&rgba[0..3], &[0, 255, 0]);
+
+        backend
+            .detach_frame_fetcher("recovery".to_string())
+            .unwrap();
+    }
+
+    #[test]
+    fn max_frames_in_flight_waits_for_previous_frames() {
+        let mut frames_in_flight = std::collections::VecDeque::new();
+
+        // with the cap at 1, the previous frame must finish before the next is recorded
+        assert!(VulkanBackend::frames_to_finish_before_next(&mut frames_in_flight, 1).is_empty());
+        frames_in_flight.push_back(0);
+        assert_eq!(
+            VulkanBackend::frames_to_finish_before_next(&mut frames_in_flight, 1),
+            vec![0]
+        );
+        assert!(frames_in_flight.is_empty());
+
+        // a cap of 2 allows one frame in flight while recording the next
+        frames_in_flight.extend([0, 1]);
+        assert_eq!(
+            VulkanBackend::frames_to_finish_before_next(&mut frames_in_flight, 2),
+            vec![0]
+        );
+        assert_eq!(frames_in_flight, [1]);
+        assert!(VulkanBackend::frames_to_finish_before_next(&mut frames_in_flight, 2).is_empty());
+
+        // without a cap only the swapchain image count limits the frames
+        frames_in_flight.extend([2, 0]);
+        assert!(VulkanBackend::frames_to_finish_before_next(&mut frames_in_flight, 0).is_empty());
+        assert!(frames_in_flight.is_empty());
+    }
+
+    #[test]
+    fn render_thread_panic_is_an_error() {
+        let render_thread = |thread| RenderThread {
+            sender: crossbeam::channel::unbounded().0,
+            events: Default::default(),
+            errors: crossbeam::channel::unbounded().1,
+            thread: parking_lot::Mutex::new(JoinThread::new(thread)),
+        };
+        // the answer to the sync event never arrives
+        let (_sender, receiver) = crossbeam::channel::bounded(1);
+
+        let panicked = std::thread::spawn(|| panic!("failed to render"));
+        while !panicked.is_finished() {
+            std::thread::yield_now();
+        }
+        let err = VulkanBackend::wait_for_render_thread_sync(
+            1,
+            &render_thread(panicked),
+            &receiver,
+            Duration::from_millis(10),
+        )
+        .expect_err("the panic of the render thread was not reported");
+        assert!(err.to_string().contains("render thread 1 panicked"));
+        assert!(err.to_string().contains("failed to render"));
+
+        // a thread that is still running is reported as deadlocked
+        let (stop_sender, stop_receiver) = crossbeam::channel::bounded::<()>(1);
+        let deadlocked = render_thread(std::thread::spawn(move || {
+            let _ = stop_receiver.recv();
+        }));
+        let err = VulkanBackend::wait_for_render_thread_sync(
+            0,
+            &deadlocked,
+            &receiver,
+            Duration::from_millis(10),
+        )
+        .expect_err("the deadlock was not detected");
+        assert!(err.to_string().contains("deadlocked"));
+        stop_sender.send(()).unwrap();
+    }
+
+    #[test]
+    fn streaming_too_many_vertices_fails() {
+        let capacity = StreamDataMax::MaxVertices as usize;
+        assert!(VulkanBackend::check_stream_vertices_fit(0, capacity).is_ok());
+        assert!(VulkanBackend::check_stream_vertices_fit(capacity, capacity).is_ok());
+
+        let err = VulkanBackend::check_stream_vertices_fit(capacity + 1, capacity)
+            .expect_err("the overflow was not detected");
+        assert!(err
+            .to_string()
+            .contains(&format!("only fits {capacity} vertices")));
+    }
+
+    #[test]
+    fn texture_update_region_is_checked() {
+        let check = |x, y, width, height, data_len| {
+            VulkanBackend::check_texture_update_region(x, y, width, height, None, data_len, 16, 8)
+        };
+        assert!(check(0, 0, 16, 8, 16 * 8 * 4).is_ok());
+        assert!(check(15, 7, 1, 1, 4).is_ok());
+
+        // past the edges
+        assert!(check(1, 0, 16, 8, 16 * 8 * 4).is_err());
+        assert!(check(0, 7, 1, 2, 2 * 4).is_err());
+        assert!(check(-1, 0, 1, 1, 4).is_err());
+        assert!(check(i64::MAX, 0, usize::MAX, 1, 4).is_err());
+        // not enough data
+        assert!(check(0, 0, 2, 2, 2 * 2 * 4 - 1).is_err());
+
+        // a 18x8 bc7 texture, 5x2 blocks of 16 bytes
+        let check_bc7 = |x, y, width, height, data_len| {
+            VulkanBackend::check_texture_update_region(
+                x,
+                y,
+                width,
+                height,
+                Some(TextureCompressedFormat::Bc7),
+                data_len,
+                18,
+                8,
+            )
+        };
+        assert_eq!(check_bc7(0, 0, 18, 8, 5 * 2 * 16).unwrap(), 5 * 2 * 16);
+        assert_eq!(check_bc7(4, 4, 4, 4, 16).unwrap(), 16);
+        // the last column of blocks is partial
+        assert_eq!(check_bc7(16, 0, 2, 8, 2 * 16).unwrap(), 2 * 16);
+
+        // not aligned to the blocks
+        assert!(check_bc7(1, 0, 4, 4, 16).is_err());
+        assert!(check_bc7(0, 2, 4, 4, 16).is_err());
+        assert!(check_bc7(0, 0, 3, 4, 16).is_err());
+        assert!(check_bc7(0, 0, 4, 6, 2 * 16).is_err());
+        // not enough data, rgba sizes don't apply
+        assert!(check_bc7(0, 0, 8, 4, 16).is_err());
+    }
+
+    #[test]
+    fn vk_max_frames_in_flight() {
+        let Some((backend, stream_data)) = try_prepare_backend(
+            1,
+            ConfigBackend {
+                max_frames_in_flight: 1,
+                ..Default::default()
+            },
+        ) else {
+            return;
+        };
+
+        let cmds = BackendCommands::default();
+        for _ in 0..8 {
+            cmds.add_cmd(AllCommands::Misc(CommandsMisc::Swap));
+            backend.run_cmds(&cmds, &stream_data);
+        }
+        backend.wait_idle().unwrap();
+
+        // with a single frame in flight every frame after the first
+        // waits for its predecessor
+        let stats = backend.wait_stats().unwrap();
+        assert!(
+            stats.frames_in_flight_waits >= 7,
+            "{}",
+            stats.frames_in_flight_waits
+        );
+    }
+
+    #[test]
+    fn frame_pacer_limits_the_frame_rate() {
+        // no limit never waits
+        let mut pacer = FramePacer::new(0);
+        let start = Instant::now();
+        for _ in 0..100 {
+            pacer.wait();
+        }
+        assert!(start.elapsed() < Duration::from_millis(50));
+
+        // the first frame starts right away, every other one a frame time later
+        let mut pacer = FramePacer::new(200);
+        let start = Instant::now();
+        for _ in 0..11 {
+            pacer.wait();
+        }
+        let elapsed = start.elapsed();
+        assert!(elapsed >= Duration::from_millis(50), "{elapsed:?}");
+        assert!(elapsed < Duration::from_millis(250), "{elapsed:?}");
+    }
+
+    #[test]
+    fn vk_max_fps() {
+        let Some((backend, stream_data)) = try_prepare_backend(
+            1,
+            ConfigBackend {
+                max_fps: 100,
+                ..Default::default()
+            },
+        ) else {
+            return;
+        };
+
+        let cmds = BackendCommands::default();
+        cmds.add_cmd(AllCommands::Misc(CommandsMisc::Swap));
+        backend.run_cmds(&cmds, &stream_data);
+        backend.wait_idle().unwrap();
+
+        let start = Instant::now();
+        for _ in 0..10 {
+            cmds.add_cmd(AllCommands::Misc(CommandsMisc::Swap));
+            backend.run_cmds(&cmds, &stream_data);
+        }
+        backend.wait_idle().unwrap();
+        let elapsed = start.elapsed();
+        // ten frames at 100 fps take about 100ms
+        assert!(elapsed >= Duration::from_millis(90), "{elapsed:?}");
+        assert!(elapsed < Duration::from_millis(500), "{elapsed:?}");
+    }
+
+    #[test]
+    fn polygon_mode_requires_device_support() {
+        let mut mode = PolygonMode::Fill;
+        assert!(VulkanBackend::apply_polygon_mode(
+            &mut mode,
+            PolygonMode::Line,
+            true
+        ));
+        assert_eq!(mode, PolygonMode::Line);
+        // same mode again does not recreate the pipelines
+        assert!(!VulkanBackend::apply_polygon_mode(
+            &mut mode,
+            PolygonMode::Line,
+            true
+        ));
+        assert!(VulkanBackend::apply_polygon_mode(
+            &mut mode,
+            PolygonMode::Fill,
+            true
+        ));
+        assert_eq!(mode, PolygonMode::Fill);
+
+        // unsupported devices keep fill mode
+        assert!(!VulkanBackend::apply_polygon_mode(
+            &mut mode,
+            PolygonMode::Line,
+            false
+        ));
+        assert_eq!(mode, PolygonMode::Fill);
+    }
+
+    #[test]
+    fn frame_fetch_single_submit() {
+        let fetch_indices = [
+            FetchCanvasIndex::Offscreen(0),
+            FetchCanvasIndex::Offscreen(1),
+            FetchCanvasIndex::Onscreen,
+        ];
+        // three fetchers, three copies in one batch
+        let (canvases, copy_indices) = frame_fetch_copies(&fetch_indices, &Default::default());
+        assert_eq!(canvases, fetch_indices);
+        assert_eq!(copy_indices, [Some(0), Some(1), Some(2)]);
+
+        // fetchers of the same canvas share the copy
+        let (canvases, copy_indices) = frame_fetch_copies(
+            &[
+                FetchCanvasIndex::Offscreen(1),
+                FetchCanvasIndex::Onscreen,
+                FetchCanvasIndex::Offscreen(1),
+            ],
+            &Default::default(),
+        );
+        assert_eq!(
+            canvases,
+            [FetchCanvasIndex::Offscreen(1), FetchCanvasIndex::Onscreen]
+        );
+        assert_eq!(copy_indices, [Some(0), Some(1), Some(0)]);
+
+        // skipped canvases are not copied at all
+        let (canvases, copy_indices) =
+            frame_fetch_copies(&fetch_indices, &[0].into_iter().collect());
+        assert_eq!(
+            canvases,
+            [FetchCanvasIndex::Offscreen(1), FetchCanvasIndex::Onscreen]
+        );
+        assert_eq!(copy_indices, [None, Some(0), Some(1)]);
+    }
+
+    #[test]
+    fn cancel_vulkan_loading_io() {
+        let workspace_root = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("../../");
+        std::env::set_current_dir(workspace_root).unwrap();
+        let io = IoFileSys::new(|rt| {
+            Arc::new(
+                FileSystem::new(rt, "ddnet-test", "ddnet-test", "ddnet-test", "ddnet-test")
+                    .unwrap(),
+            )
+        });
+
+        let mut loading_io = VulkanBackendLoadingIo::new(&io);
+        let cancelled = loading_io.cancel();
+
+        let shader_compiler = loading_io.shader_compiler.get();
+        if cancelled.shader_compiler == LoadingTaskCancel::Cancelled {
+            assert!(shader_compiler.is_err());
+        } else {
+            assert!(shader_compiler.is_ok());
+        }
+        drop(loading_io.pipeline_cache);
+
+        // cancelling right away also leaves the struct droppable
+        let mut loading_io = VulkanBackendLoadingIo::new(&io);
+        loading_io.cancel();
+        drop(loading_io);
+    }
+
     #[test]
     fn shader_compile() {
         let workspace_root = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("../../");
@@ -7,7 +7,10 @@ use native::native::{
     app::{MIN_WINDOW_HEIGHT, MIN_WINDOW_WIDTH},
     NativeDisplayBackend, PhysicalSize,
 };
-use raw_window_handle::{DisplayHandle, HasDisplayHandle, HasWindowHandle, WindowHandle};
+use raw_window_handle::{
+    DisplayHandle, HasDisplayHandle, HasWindowHandle, RawDisplayHandle, RawWindowHandle,
+    WindowHandle,
+};
 
 use crate::backends::vulkan::{
     frame_resources::FrameResources,
@@ -265,7 +268,13 @@ impl BackendSurfaceAndHandles<'_> {
                     None,
                 )?;
                 surface = SurfaceKHR::from_existing(surface.ash_surface.clone(), surf);
-                Ok(BackendSurface::Winit { surface })
+                Ok(BackendSurface::Winit {
+                    surface,
+                    handles: SurfaceWindowHandles {
+                        display_handle: display_handle.as_raw(),
+                        window_handle: window_handle.as_raw(),
+                    },
+                })
             }
             Self::Headless {
                 mut surface,
@@ -285,10 +294,93 @@ impl BackendSurfaceAndHandles<'_> {
     }
 }
 
+/// The raw handles of the window a surface was created for,
+/// so a lost surface can be replaced by a new one.
+#[derive(Debug, Clone, Copy)]
+pub struct SurfaceWindowHandles {
+    display_handle: RawDisplayHandle,
+    window_handle: RawWindowHandle,
+}
+
+// SAFETY: the handles are only used to create new surfaces for the window,
+// while the window still exists. The window is only destroyed after its
+// surface was replaced, see `VulkanBackend::surface_lost`.
+unsafe impl Send for SurfaceWindowHandles {}
+unsafe impl Sync for SurfaceWindowHandles {}
+
+/// What is needed to create a new surface for the window of a [`BackendSurface`],
+/// after the old surface was destroyed.
+#[derive(Debug, Clone)]
+pub enum BackendSurfaceRecreateInfo {
+    Winit {
+        ash_surface: ash::khr::surface::Instance,
+        handles: SurfaceWindowHandles,
+    },
+    Headless {
+        width: u32,
+        height: u32,
+        should_render: bool,
+    },
+}
+
+impl BackendSurfaceRecreateInfo {
+    /// # Safety
+    /// see [`ash_window::create_surface`]
+    pub unsafe fn create_vk_surface(
+        &self,
+        entry: &ash::Entry,
+        instance: &ash::Instance,
+        mem_allocator: &Arc<parking_lot::Mutex<VulkanAllocator>>,
+    ) -> anyhow::Result<BackendSurface> {
+        match self {
+            Self::Winit {
+                ash_surface,
+                handles,
+            } => {
+                // the surfaces of these windows must be created on the main thread
+                anyhow::ensure!(
+                    !matches!(
+                        handles.window_handle,
+                        RawWindowHandle::AppKit(_) | RawWindowHandle::UiKit(_)
+                    ),
+                    "the surface can only be recreated by the main thread."
+                );
+                let surf = ash_window::create_surface(
+                    entry,
+                    instance,
+                    handles.display_handle,
+                    handles.window_handle,
+                    None,
+                )?;
+                Ok(BackendSurface::Winit {
+                    surface: SurfaceKHR::from_existing(ash_surface.clone(), surf),
+                    handles: *handles,
+                })
+            }
+            Self::Headless {
+                width,
+                height,
+                should_render,
+            } => {
+                let mut surface = BackendSurfaceHeadless::default();
+                surface.create_surface_images_headless(mem_allocator, *width, *height);
+                Ok(BackendSurface::Headless {
+                    width: *width,
+                    height: *height,
+                    surface,
+                    should_render: *should_render,
+                })
+            }
+        }
+    }
+}
+
 #[derive(Debug, Hiarc)]
 pub enum BackendSurface {
     Winit {
         surface: Arc<SurfaceKHR>,
+        #[hiarc_skip_unsafe]
+        handles: SurfaceWindowHandles,
     },
     Headless {
         width: u32,
@@ -339,7 +431,7 @@ impl BackendSurface {
         queue_family_index: u32,
     ) -> VkResult<bool> {
         match self {
-            BackendSurface::Winit { surface } => {
+            BackendSurface::Winit { surface, .. } => {
                 surface.ash_surface.get_physical_device_surface_support(
                     physical_device,
                     queue_family_index,
@@ -355,7 +447,7 @@ impl BackendSurface {
         physical_device: vk::PhysicalDevice,
     ) -> VkResult<Vec<vk::SurfaceFormatKHR>> {
         match self {
-            BackendSurface::Winit { surface } => surface
+            BackendSurface::Winit { surface, .. } => surface
                 .ash_surface
                 .get_physical_device_surface_formats(physical_device, surface.surface),
             BackendSurface::Headless { .. } => Ok(vec![vk::SurfaceFormatKHR {
@@ -370,7 +462,7 @@ impl BackendSurface {
         physical_device: vk::PhysicalDevice,
     ) -> VkResult<Vec<vk::PresentModeKHR>> {
         match self {
-            BackendSurface::Winit { surface } => surface
+            BackendSurface::Winit { surface, .. } => surface
                 .ash_surface
                 .get_physical_device_surface_present_modes(physical_device, surface.surface),
             BackendSurface::Headless { .. } => Ok(vec![vk::PresentModeKHR::IMMEDIATE]),
@@ -382,7 +474,7 @@ impl BackendSurface {
         physical_device: vk::PhysicalDevice,
     ) -> VkResult<vk::SurfaceCapabilitiesKHR> {
         match self {
-            BackendSurface::Winit { surface } => surface
+            BackendSurface::Winit { surface, .. } => surface
                 .ash_surface
                 .get_physical_device_surface_capabilities(physical_device, surface.surface),
             BackendSurface::Headless { width, height, .. } => {
@@ -413,6 +505,26 @@ impl BackendSurface {
         }
     }
 
+    /// The info to create a new surface for the same window.
+    pub fn recreate_info(&self) -> BackendSurfaceRecreateInfo {
+        match self {
+            BackendSurface::Winit { surface, handles } => BackendSurfaceRecreateInfo::Winit {
+                ash_surface: surface.ash_surface.clone(),
+                handles: *handles,
+            },
+            BackendSurface::Headless {
+                width,
+                height,
+                should_render,
+                ..
+            } => BackendSurfaceRecreateInfo::Headless {
+                width: *width,
+                height: *height,
+                should_render: *should_render,
+            },
+        }
+    }
+
     pub fn replace(&mut self, new: Self) {
         drop(std::mem::replace(self, new));
     }
@@ -517,7 +629,7 @@ impl BackendSwapchain {
                     queue,
                     out_of_date,
                 } => match surface {
-                    BackendSurface::Winit { surface } => {
+                    BackendSurface::Winit { surface, .. } => {
                         let old_swap_chain = swapchain;
                         let ash_swapchain = old_swap_chain.ash_swapchain.clone();
 
@@ -584,7 +696,7 @@ impl BackendSwapchain {
                     can_render,
                     images,
                 } => match surface {
-                    BackendSurface::Winit { surface } => {
+                    BackendSurface::Winit { surface, .. } => {
                         let old_swapchain = match old_swapchain {
                             Some(old_swapchain) => old_swapchain,
                             None => {
@@ -1,10 +1,12 @@
+use std::num::NonZeroU32;
+
 use bitflags::bitflags;
 use hiarc::Hiarc;
 use pool::mt_datatypes::{PoolString, PoolVec};
 use serde::{Deserialize, Serialize};
 
 use crate::{
-    rendering::{ColorRgba, GlColor, GlColorf, GlPoint, SPoint, State, StateTexture},
+    rendering::{ColorRgba, GlColor, GlColorf, GlPoint, SPoint, State, StateClip, StateTexture},
     types::GraphicsBackendMemory,
 };
 use math::math::vector::*;
@@ -63,6 +65,10 @@ pub struct CommandClear {
     /// will be cleared.
     /// Else only the backend's clear color is updated.
     pub force_clear: bool,
+    /// If `Some`, only this rect of the current render target
+    /// is cleared, regardless of `force_clear`.
+    /// The backend's clear color is not updated.
+    pub clip: Option<StateClip>,
 }
 
 pub trait RenderCommand {
@@ -205,7 +211,8 @@ pub struct CommandRenderQuadContainer {
 
     /// number of quads to draw
     pub quad_num: usize,
-    /// number of quads to skip before rendering
+    /// number of quads to skip before rendering,
+    /// allows to draw only a sub range of the quads in the buffer
     pub quad_offset: usize,
 }
 
@@ -251,6 +258,17 @@ pub struct CommandVsync {
     pub on: bool,
 }
 
+/// How polygons are rasterized, [`PolygonMode::Line`] is a debug aid
+/// to inspect the geometry of e.g. tile & quad layers.
+#[derive(Debug, Hiarc, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PolygonMode {
+    #[default]
+    Fill,
+    /// Only the edges of the polygons are drawn.
+    /// Falls back to [`PolygonMode::Fill`] if the backend does not support it.
+    Line,
+}
+
 #[derive(Debug, Hiarc, Serialize, Deserialize)]
 pub struct CommandMultiSampling {
     pub sample_count: u32,
@@ -263,6 +281,31 @@ pub struct CommandUpdateViewport {
     pub width: u32,
     pub height: u32,
     pub by_resize: bool, // resized by an resize event.. a hint to make clear that the viewport update can be deferred if wanted
+    /// The factor between physical pixels and logical window units,
+    /// e.g. `2.0` on a HiDPI display.
+    pub content_scale: f64,
+}
+
+/// How often a texture is updated after it was created,
+/// a hint for the backend how to store it.
+#[derive(Debug, Hiarc, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TextureUsage {
+    /// Rarely or never updated, e.g. tilesets.
+    /// Mipmaps are generated.
+    #[default]
+    Static,
+    /// Updated from time to time, e.g. dynamic UI atlases.
+    /// No mipmaps are generated, so updates don't have to rebuild them.
+    Dynamic,
+    /// Updated about every frame, e.g. video frames.
+    /// No mipmaps are generated.
+    Streaming,
+}
+
+impl TextureUsage {
+    pub fn generates_mip_maps(&self) -> bool {
+        matches!(self, Self::Static)
+    }
 }
 
 #[derive(Debug, Hiarc, Serialize, Deserialize)]
@@ -273,6 +316,8 @@ pub struct CommandTextureCreate {
     /// note that this data must be memory allocated by mem_alloc of the graphics implementation
     /// it will be automatically free'd by the backend!
     pub data: GraphicsBackendMemory,
+
+    pub usage: TextureUsage,
 }
 
 #[derive(Debug, Hiarc, Serialize, Deserialize)]
@@ -302,6 +347,30 @@ pub struct CommandOffscreenCanvasCreate {
     pub width: u32,
     pub height: u32,
     pub has_multi_sampling: Option<u32>,
+    /// The color the canvas is cleared with at the start of a frame.
+    /// If `None`, the clear color of the last clear command is used.
+    pub clear_color: Option<ColorRgba>,
+    /// If `Some(n)`, the canvas is `1/n` of the window's size and follows
+    /// it when the window is resized. `width` and `height` are ignored then.
+    pub relative_size: Option<NonZeroU32>,
+    /// If `true`, the canvas keeps its content across frames instead of being
+    /// cleared at the start of a frame, e.g. for accumulation buffers.
+    /// Only forced clears clear it. Can't be combined with multi sampling.
+    pub persistent: bool,
+}
+
+/// The size of a canvas that is `1/divisor` of the window's size,
+/// see [`CommandOffscreenCanvasCreate::relative_size`].
+/// Never smaller than 1x1.
+pub fn relative_canvas_extent(
+    window_width: u32,
+    window_height: u32,
+    divisor: NonZeroU32,
+) -> (u32, u32) {
+    (
+        (window_width / divisor.get()).max(1),
+        (window_height / divisor.get()).max(1),
+    )
 }
 
 #[derive(Debug, Hiarc, Serialize, Deserialize)]
@@ -310,6 +379,15 @@ pub struct CommandOffscreenCanvasDestroy {
     pub offscreen_index: u128,
 }
 
+#[derive(Debug, Hiarc, Serialize, Deserialize)]
+pub struct CommandOffscreenCanvasResize {
+    // offscreen identifier
+    pub offscreen_index: u128,
+
+    pub width: u32,
+    pub height: u32,
+}
+
 #[derive(Debug, Hiarc, Serialize, Deserialize)]
 pub struct CommandOffscreenCanvasSkipFetchingOnce {
     // offscreen identifier
@@ -375,6 +453,7 @@ pub enum CommandsMisc {
     // offscreen canvases
     OffscreenCanvasCreate(CommandOffscreenCanvasCreate),
     OffscreenCanvasDestroy(CommandOffscreenCanvasDestroy),
+    OffscreenCanvasResize(CommandOffscreenCanvasResize),
     OffscreenCanvasSkipFetchingOnce(CommandOffscreenCanvasSkipFetchingOnce),
 
     IndicesForQuadsRequiredNotify(CommandIndicesForQuadsRequiredNotify), // create indices that are required
@@ -393,6 +472,12 @@ pub enum CommandsMisc {
     UpdateViewport(CommandUpdateViewport),
     Multisampling(CommandMultiSampling),
     VSync(CommandVsync),
+    SetPolygonMode(PolygonMode),
+
+    // debug
+    /// Logs the command groups that were recorded in the current frame,
+    /// only if the backend runs in verbose graphics debug mode.
+    DumpFrameGraph,
 }
 
 #[derive(Debug, Serialize, Deserialize, Hiarc)]
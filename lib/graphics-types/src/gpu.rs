@@ -31,3 +31,33 @@ pub struct Gpus {
     pub auto: Gpu,
     pub cur: CurGpu,
 }
+
+/// Information about the gpu the backend currently uses,
+/// e.g. to automatically pick quality presets.
+#[derive(Debug, Hiarc, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GpuInfo {
+    pub name: String,
+    pub ty: GpuType,
+    /// The size of the largest device local memory heap in bytes, if known.
+    ///
+    /// For integrated gpus this is usually shared with the system memory.
+    pub vram_estimate: Option<u64>,
+}
+
+impl GpuInfo {
+    pub fn new(gpus: &Gpus, vram_estimate: Option<u64>) -> Self {
+        Self {
+            name: gpus.cur.name.clone(),
+            ty: gpus.cur.ty,
+            vram_estimate,
+        }
+    }
+
+    pub fn is_discrete(&self) -> bool {
+        self.ty == GpuType::Discrete
+    }
+
+    pub fn is_integrated(&self) -> bool {
+        self.ty == GpuType::Integrated
+    }
+}
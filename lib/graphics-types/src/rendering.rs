@@ -19,6 +19,16 @@ pub enum WrapType {
 }
 pub const WRAP_TYPE_COUNT: usize = 2;
 
+/// How textures are filtered if they are magnified or minified.
+#[derive(Debug, Hiarc, Default, Copy, Clone, PartialEq, Eq, EnumCount, Serialize, Deserialize)]
+pub enum TextureFilter {
+    /// Interpolates between texels & mip maps.
+    #[default]
+    Linear = 0,
+    /// Uses the closest texel & mip map, e.g. for pixel art.
+    Nearest,
+}
+
 pub type SPoint = vec2;
 
 #[derive(Debug, Hiarc, Default, Copy, Clone, Serialize, Deserialize)]
@@ -61,6 +71,7 @@ pub struct StateClip {
 pub struct State {
     pub blend_mode: BlendType,
     pub wrap_mode: WrapType,
+    pub texture_filter: TextureFilter,
     pub canvas_tl: SPoint,
     pub canvas_br: SPoint,
 
@@ -79,6 +90,7 @@ impl State {
         State {
             blend_mode: Default::default(),
             wrap_mode: Default::default(),
+            texture_filter: Default::default(),
             canvas_tl: SPoint::default(),
             canvas_br: SPoint::default(),
 
@@ -100,6 +112,10 @@ impl State {
         self.wrap_mode = wrap;
     }
 
+    pub fn filter(&mut self, filter: TextureFilter) {
+        self.texture_filter = filter;
+    }
+
     /// like [`Self::clip`] but clamps all values to the window size
     pub fn clip_clamped(
         &mut self,
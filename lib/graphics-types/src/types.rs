@@ -5,6 +5,41 @@ use serde::{Deserialize, Serialize};
 
 use crate::commands::TexFlags;
 
+/// Block compressed texture formats, that are uploaded as is
+/// (no conversion, no mipmap generation).
+#[derive(
+    Debug, Hiarc, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
+pub enum TextureCompressedFormat {
+    /// BC7 (desktop GPUs)
+    Bc7,
+    /// ASTC with 4x4 blocks (mostly mobile GPUs)
+    Astc4x4,
+}
+
+impl TextureCompressedFormat {
+    /// The width and height of a single block in texels.
+    pub const fn block_extent(&self) -> (usize, usize) {
+        match self {
+            TextureCompressedFormat::Bc7 | TextureCompressedFormat::Astc4x4 => (4, 4),
+        }
+    }
+
+    /// The size of a single block in bytes.
+    pub const fn bytes_per_block(&self) -> usize {
+        match self {
+            TextureCompressedFormat::Bc7 | TextureCompressedFormat::Astc4x4 => 16,
+        }
+    }
+
+    /// The size in bytes of an image of the given size.
+    /// Partial blocks at the borders still take up a whole block.
+    pub const fn image_size(&self, width: usize, height: usize) -> usize {
+        let (block_width, block_height) = self.block_extent();
+        width.div_ceil(block_width) * height.div_ceil(block_height) * self.bytes_per_block()
+    }
+}
+
 #[derive(Debug, Hiarc, Clone, Copy, Serialize, Deserialize)]
 pub enum GraphicsMemoryAllocationType {
     TextureRgbaU8 {
@@ -18,6 +53,14 @@ pub enum GraphicsMemoryAllocationType {
         depth: NonZeroUsize,
         flags: TexFlags,
     },
+    /// A block compressed texture.
+    /// Creating the texture fails, if the backend doesn't support the format.
+    TextureCompressed {
+        width: NonZeroUsize,
+        height: NonZeroUsize,
+        format: TextureCompressedFormat,
+        flags: TexFlags,
+    },
     VertexBuffer {
         required_size: NonZeroUsize,
     },
@@ -80,6 +123,18 @@ pub struct WindowProps {
     pub window_height: f64,
 }
 
+impl WindowProps {
+    /// The factor between the physical pixels of the canvas
+    /// and the logical units of the window.
+    pub fn content_scale(&self) -> f64 {
+        if self.window_width > 0.0 {
+            self.canvas_width as f64 / self.window_width
+        } else {
+            1.0
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
 pub enum DrawModes {
     Quads = 1,
@@ -176,6 +231,12 @@ impl<'de> Deserialize<'de> for GraphicsBackendMemory {
                 depth,
                 ..
             } => width.get() * height.get() * depth.get() * 4,
+            GraphicsMemoryAllocationType::TextureCompressed {
+                width,
+                height,
+                format,
+                ..
+            } => format.image_size(width.get(), height.get()),
             GraphicsMemoryAllocationType::VertexBuffer { required_size } => required_size.get(),
             GraphicsMemoryAllocationType::ShaderStorage { required_size } => required_size.get(),
         };
@@ -254,3 +315,15 @@ impl GraphicsBackendMemory {
         self.as_slice().is_empty()
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::TextureCompressedFormat;
+
+    #[test]
+    fn compressed_image_size() {
+        assert_eq!(TextureCompressedFormat::Bc7.image_size(256, 256), 65536);
+        // partial blocks are rounded up
+        assert_eq!(TextureCompressedFormat::Astc4x4.image_size(5, 3), 32);
+    }
+}
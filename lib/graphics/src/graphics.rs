@@ -7,12 +7,13 @@ pub mod graphics {
     };
 
     use graphics_backend_traits::{
-        frame_fetcher_plugin::{BackendFrameFetcher, BackendPresentedImageDataRgba},
+        frame_fetcher_plugin::{
+            BackendFrameFetcher, BackendPresentedImageDataRgba, PresentedImageFormat,
+        },
         traits::GraphicsBackendInterface,
     };
     use graphics_base_traits::traits::GraphicsStreamedData;
     use hiarc::Hiarc;
-    use image_utils::png::save_png_image;
 
     use crate::{
         graphics_mt::GraphicsMultiThreaded,
@@ -27,7 +28,7 @@ pub mod graphics {
     };
 
     use graphics_types::{
-        commands::{AllCommands, CommandMultiSampling, CommandVsync, CommandsMisc},
+        commands::{AllCommands, CommandMultiSampling, CommandVsync, CommandsMisc, PolygonMode},
         types::{VideoMode, WindowProps},
     };
 
@@ -113,24 +114,20 @@ pub mod graphics {
     }
 
     impl BackendFrameFetcher for ScreenshotFetcher {
-        fn next_frame(&self, frame_data: BackendPresentedImageDataRgba) {
-            let BackendPresentedImageDataRgba {
-                width,
-                height,
-                mut dest_data_buffer,
-                ..
-            } = frame_data;
+        fn next_frame(&self, mut frame_data: BackendPresentedImageDataRgba) {
+            let width = frame_data.width;
+            let height = frame_data.height;
 
             // clear alpha values, not desired for screenshot
             for y in 0..height as usize {
                 for x in 0..width as usize {
                     let img_off: usize = (y * width as usize * 4) + (x * 4);
 
-                    dest_data_buffer[img_off + 3] = 255;
+                    frame_data.dest_data_buffer[img_off + 3] = 255;
                 }
             }
 
-            *self.data.lock().unwrap() = Some(save_png_image(&dest_data_buffer, width, height));
+            *self.data.lock().unwrap() = Some(frame_data.encode(PresentedImageFormat::Png));
         }
 
         fn current_fetch_index(
@@ -231,7 +228,13 @@ pub mod graphics {
             self.check_pending_screenshot();
         }
 
+        /// Requests a screenshot of the next frame.
+        /// Fails while a previous screenshot is still pending.
         pub fn do_screenshot<F: ScreenshotCb>(&self, f: F) -> anyhow::Result<()> {
+            anyhow::ensure!(
+                self.pending_screenshot.borrow().is_none(),
+                "a screenshot is still pending, the new request was dropped."
+            );
             let fetcher = Arc::new(ScreenshotFetcher::default());
             let fetcher_local = fetcher.clone();
             self.backend_handle
@@ -252,6 +255,20 @@ pub mod graphics {
                     CommandMultiSampling { sample_count },
                 )));
         }
+
+        /// Draw only the edges of all polygons, useful to debug geometry.
+        pub fn polygon_mode(&self, mode: PolygonMode) {
+            self.backend_handle
+                .add_cmd(AllCommands::Misc(CommandsMisc::SetPolygonMode(mode)));
+        }
+
+        /// Logs how the commands of the current frame were grouped into
+        /// canvases & render passes so far.
+        /// Requires the verbose graphics debug mode.
+        pub fn dump_frame_graph(&self) {
+            self.backend_handle
+                .add_cmd(AllCommands::Misc(CommandsMisc::DumpFrameGraph));
+        }
     }
 
     impl Drop for Graphics {
@@ -47,4 +47,14 @@ impl GraphicsMultiThreaded {
     ) -> anyhow::Result<()> {
         self.backend_mt.try_flush_mem(mem, do_expensive_flushing)
     }
+
+    /// Flushes all `mems` at once, see [`GraphicsBackendMtInterface::try_flush_mem_batch`].
+    pub fn try_flush_mem_batch(
+        &self,
+        mems: &mut [&mut GraphicsBackendMemory],
+        do_expensive_flushing: bool,
+    ) -> anyhow::Result<()> {
+        self.backend_mt
+            .try_flush_mem_batch(mems, do_expensive_flushing)
+    }
 }
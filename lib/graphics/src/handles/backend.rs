@@ -11,7 +11,7 @@ pub mod backend {
             CommandsRender,
         },
         gpu::Gpus,
-        rendering::ColorRgba,
+        rendering::{ColorRgba, StateClip},
         types::{GraphicsBackendMemory, GraphicsMemoryAllocationType},
     };
     use hiarc::Hiarc;
@@ -76,6 +76,17 @@ pub mod backend {
             self.add_cmd(AllCommands::Render(CommandsRender::Clear(CommandClear {
                 color: clear_color,
                 force_clear: false,
+                clip: None,
+            })));
+        }
+
+        /// Clears only the given rect of the current render target,
+        /// the clear color of the backend is not updated.
+        pub fn clear_rect(&self, color: ColorRgba, clip: StateClip) {
+            self.add_cmd(AllCommands::Render(CommandsRender::Clear(CommandClear {
+                color,
+                force_clear: true,
+                clip: Some(clip),
             })));
         }
 
@@ -105,6 +116,29 @@ pub mod backend {
         pub fn gpus(&self) -> Arc<Gpus> {
             self.backend.gpus()
         }
+
+        /// Reads back `size` bytes at `offset` of the buffer object `buffer_index`.
+        /// Only sees commands that were already run by [`Self::run_backend_buffer`].
+        pub fn read_buffer_object(
+            &self,
+            buffer_index: u128,
+            offset: usize,
+            size: usize,
+        ) -> anyhow::Result<Vec<u8>> {
+            self.backend.read_buffer_object(buffer_index, offset, size)
+        }
+
+        /// Reads back `size` bytes at `offset` of the shader storage `shader_storage_index`,
+        /// see [`Self::read_buffer_object`].
+        pub fn read_shader_storage(
+            &self,
+            shader_storage_index: u128,
+            offset: usize,
+            size: usize,
+        ) -> anyhow::Result<Vec<u8>> {
+            self.backend
+                .read_shader_storage(shader_storage_index, offset, size)
+        }
     }
 
     impl GraphicsBackendHandle {
@@ -86,6 +86,9 @@ pub mod buffer_object {
         /// updates the buffer object with specific limitations:
         /// - all commands that use this buffer object before this command was issued __might__ see the buffer update too
         /// - all commands that are issued after this update are guaranteed to see the buffer update
+        ///
+        /// All regions are copied using a single staging allocation and barrier pair,
+        /// so prefer one call with many regions over many calls.
         pub fn update_buffer_object(
             &self,
             update_data: Vec<u8>,
@@ -121,6 +124,12 @@ pub mod buffer_object {
             self.recreate_buffer_object(buffer_mem)
         }
 
+        /// See [`GraphicsBackendHandle::read_buffer_object`].
+        pub fn read_buffer_object(&self, offset: usize, size: usize) -> anyhow::Result<Vec<u8>> {
+            self.backend_handle
+                .read_buffer_object(self.index, offset, size)
+        }
+
         pub fn get_index_unsafe(&self) -> u128 {
             self.index
         }
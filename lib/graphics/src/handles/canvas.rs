@@ -1,10 +1,14 @@
 pub mod canvas {
+    use std::num::NonZeroU32;
+
     use graphics_types::{
         commands::{
-            AllCommands, CommandOffscreenCanvasCreate, CommandOffscreenCanvasDestroy,
+            relative_canvas_extent, AllCommands, CommandOffscreenCanvasCreate,
+            CommandOffscreenCanvasDestroy, CommandOffscreenCanvasResize,
             CommandOffscreenCanvasSkipFetchingOnce, CommandSwitchCanvasMode,
             CommandSwitchCanvasModeType, CommandUpdateViewport, CommandsMisc,
         },
+        rendering::ColorRgba,
         types::WindowProps,
     };
     use hiarc::{hiarc_safer_rc_refcell, Hiarc};
@@ -77,9 +81,85 @@ pub mod canvas {
             height: u32,
             pixels_per_point: f64,
             has_multi_sampling: Option<u32>,
+        ) -> OffscreenCanvas {
+            self.offscreen_canvas_with_clear_color(
+                width,
+                height,
+                pixels_per_point,
+                has_multi_sampling,
+                None,
+            )
+        }
+
+        /// Like [`Self::offscreen_canvas`], but the canvas starts every frame
+        /// cleared with `clear_color` (e.g. transparent for UI composites),
+        /// instead of the color of the last clear command.
+        /// A clear command with a different color still clears the canvas with that color.
+        pub fn offscreen_canvas_with_clear_color(
+            &mut self,
+            width: u32,
+            height: u32,
+            pixels_per_point: f64,
+            has_multi_sampling: Option<u32>,
+            clear_color: Option<ColorRgba>,
+        ) -> OffscreenCanvas {
+            let id = self.offscreen_canvas_id_gen;
+            self.offscreen_canvas_id_gen += 1;
+            OffscreenCanvas::new(
+                id,
+                self.backend_handle.clone(),
+                width,
+                height,
+                pixels_per_point,
+                has_multi_sampling,
+                clear_color,
+                None,
+                false,
+            )
+        }
+
+        /// Like [`Self::offscreen_canvas`], but the canvas keeps its content
+        /// across frames (e.g. for trails), it's only cleared with `clear_color`
+        /// when it's created and by forced clears.
+        pub fn offscreen_canvas_persistent(
+            &mut self,
+            width: u32,
+            height: u32,
+            pixels_per_point: f64,
+            clear_color: Option<ColorRgba>,
+        ) -> OffscreenCanvas {
+            let id = self.offscreen_canvas_id_gen;
+            self.offscreen_canvas_id_gen += 1;
+            OffscreenCanvas::new(
+                id,
+                self.backend_handle.clone(),
+                width,
+                height,
+                pixels_per_point,
+                None,
+                clear_color,
+                None,
+                true,
+            )
+        }
+
+        /// Like [`Self::offscreen_canvas`], but the canvas is `1/divisor`
+        /// of the window's size and automatically follows window resizes
+        /// (e.g. for a half resolution bloom buffer).
+        pub fn offscreen_canvas_relative(
+            &mut self,
+            divisor: NonZeroU32,
+            pixels_per_point: f64,
+            has_multi_sampling: Option<u32>,
         ) -> OffscreenCanvas {
             let id = self.offscreen_canvas_id_gen;
             self.offscreen_canvas_id_gen += 1;
+            let window_props = &self.canvases.onscreen.window_props;
+            let (width, height) = relative_canvas_extent(
+                window_props.canvas_width,
+                window_props.canvas_height,
+                divisor,
+            );
             OffscreenCanvas::new(
                 id,
                 self.backend_handle.clone(),
@@ -87,14 +167,23 @@ pub mod canvas {
                 height,
                 pixels_per_point,
                 has_multi_sampling,
+                None,
+                Some(divisor),
+                false,
             )
         }
 
         pub fn switch_canvas(&mut self, mode: GraphicsCanvasMode) {
             let switch_canvas = match &mode {
                 GraphicsCanvasMode::Offscreen { offscreen_canvas } => {
-                    let width = offscreen_canvas.width();
-                    let height = offscreen_canvas.height();
+                    let (width, height) = match offscreen_canvas.relative_size() {
+                        Some(divisor) => relative_canvas_extent(
+                            self.canvases.onscreen.window_props.canvas_width,
+                            self.canvases.onscreen.window_props.canvas_height,
+                            divisor,
+                        ),
+                        None => (offscreen_canvas.width(), offscreen_canvas.height()),
+                    };
                     let pixels_per_point = offscreen_canvas.pixels_per_point();
                     self.canvases.offscreen.window_props.window_width =
                         width as f64 / pixels_per_point;
@@ -127,6 +216,7 @@ pub mod canvas {
                 width,
                 height,
                 by_resize: false,
+                content_scale: self.get_cur_canvas().window_props.content_scale(),
             };
             self.backend_handle
                 .add_cmd(AllCommands::Misc(CommandsMisc::UpdateViewport(cmd)));
@@ -265,6 +355,7 @@ pub mod canvas {
         width: u32,
         height: u32,
         pixels_per_point: f64,
+        relative_size: Option<NonZeroU32>,
     }
 
     #[hiarc_safer_rc_refcell]
@@ -287,6 +378,9 @@ pub mod canvas {
             height: u32,
             pixels_per_point: f64,
             has_multi_sampling: Option<u32>,
+            clear_color: Option<ColorRgba>,
+            relative_size: Option<NonZeroU32>,
+            persistent: bool,
         ) -> Self {
             let cmd = CommandOffscreenCanvasCreate {
                 offscreen_index: index,
@@ -294,6 +388,9 @@ pub mod canvas {
                 width,
                 height,
                 has_multi_sampling,
+                clear_color,
+                relative_size,
+                persistent,
             };
             backend_handle.add_cmd(AllCommands::Misc(CommandsMisc::OffscreenCanvasCreate(cmd)));
             Self {
@@ -303,6 +400,7 @@ pub mod canvas {
                 width,
                 height,
                 pixels_per_point,
+                relative_size,
             }
         }
 
@@ -318,6 +416,25 @@ pub mod canvas {
             ));
         }
 
+        /// Resizes the canvas, without recreating its render pipelines.
+        /// The content of the canvas is undefined after resizing.
+        ///
+        /// A canvas with a relative size gets a fixed size after this call.
+        pub fn resize(&mut self, width: u32, height: u32) {
+            let cmd = CommandOffscreenCanvasResize {
+                offscreen_index: self.get_index_unsafe(),
+                width,
+                height,
+            };
+
+            self.backend_handle
+                .add_cmd(AllCommands::Misc(CommandsMisc::OffscreenCanvasResize(cmd)));
+
+            self.width = width;
+            self.height = height;
+            self.relative_size = None;
+        }
+
         pub fn width(&self) -> u32 {
             self.width
         }
@@ -327,6 +444,13 @@ pub mod canvas {
         pub fn pixels_per_point(&self) -> f64 {
             self.pixels_per_point
         }
+        /// See [`GraphicsCanvasHandle::offscreen_canvas_relative`].
+        ///
+        /// For these canvases [`Self::width`] and [`Self::height`]
+        /// are the size at creation.
+        pub fn relative_size(&self) -> Option<NonZeroU32> {
+            self.relative_size
+        }
 
         pub fn get_index_unsafe(&self) -> u128 {
             self.index
@@ -93,6 +93,12 @@ pub mod shader_storage {
                 .add_cmd(AllCommands::Misc(CommandsMisc::UpdateShaderStorage(cmd)));
         }
 
+        /// See [`GraphicsBackendHandle::read_shader_storage`].
+        pub fn read_shader_storage(&self, offset: usize, size: usize) -> anyhow::Result<Vec<u8>> {
+            self.backend_handle
+                .read_shader_storage(self.index, offset, size)
+        }
+
         pub fn get_index_unsafe(&self) -> u128 {
             self.index
         }
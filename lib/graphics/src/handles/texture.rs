@@ -3,7 +3,7 @@ pub mod texture {
     use graphics_types::{
         commands::{
             AllCommands, CommandTextureCreate, CommandTextureDestroy, CommandTextureUpdate,
-            CommandsMisc,
+            CommandsMisc, TextureUsage,
         },
         rendering::{StateTexture, StateTexture2dArray},
         types::{GraphicsBackendMemory, GraphicsMemoryAllocationType},
@@ -36,6 +36,7 @@ pub mod texture {
             &mut self,
             data: GraphicsBackendMemory,
             _tex_name: &str,
+            usage: TextureUsage,
         ) -> anyhow::Result<u128> {
             // grab texture
             self.id_gen += 1;
@@ -44,6 +45,7 @@ pub mod texture {
             let cmd = CommandTextureCreate {
                 texture_index: tex_index,
                 data,
+                usage,
             };
 
             self.backend_handle
@@ -56,6 +58,17 @@ pub mod texture {
             &mut self,
             data: GraphicsBackendMemory,
             tex_name: &str,
+        ) -> anyhow::Result<TextureContainer> {
+            self.load_texture_rgba_u8_with_usage(data, tex_name, TextureUsage::Static)
+        }
+
+        /// Like [`Self::load_texture_rgba_u8`], but e.g. for textures
+        /// that are updated often, see [`TextureUsage`].
+        pub fn load_texture_rgba_u8_with_usage(
+            &mut self,
+            data: GraphicsBackendMemory,
+            tex_name: &str,
+            usage: TextureUsage,
         ) -> anyhow::Result<TextureContainer> {
             anyhow::ensure!(
                 matches!(
@@ -64,7 +77,27 @@ pub mod texture {
                 ),
                 "{tex_name} was not a rgba u8 image"
             );
-            let tex_index = self.load_texture_impl(data, tex_name)?;
+            let tex_index = self.load_texture_impl(data, tex_name, usage)?;
+            Ok(TextureContainer::new(
+                tex_index,
+                self.backend_handle.clone(),
+            ))
+        }
+
+        /// Loads a block compressed texture.
+        pub fn load_texture_compressed(
+            &mut self,
+            data: GraphicsBackendMemory,
+            tex_name: &str,
+        ) -> anyhow::Result<TextureContainer> {
+            anyhow::ensure!(
+                matches!(
+                    data.usage(),
+                    GraphicsMemoryAllocationType::TextureCompressed { .. }
+                ),
+                "{tex_name} was not a compressed image"
+            );
+            let tex_index = self.load_texture_impl(data, tex_name, TextureUsage::Static)?;
             Ok(TextureContainer::new(
                 tex_index,
                 self.backend_handle.clone(),
@@ -83,7 +116,7 @@ pub mod texture {
                 ),
                 "{tex_name} was not a 2d array rgba u8 texture"
             );
-            let tex_index = self.load_texture_impl(data, tex_name)?;
+            let tex_index = self.load_texture_impl(data, tex_name, TextureUsage::Static)?;
             Ok(TextureContainer2dArray::new(
                 tex_index,
                 self.backend_handle.clone(),
@@ -1,2 +1,4 @@
 pub mod png;
 pub mod utils;
+#[cfg(feature = "webp")]
+pub mod webp;
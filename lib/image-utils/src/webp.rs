@@ -0,0 +1,26 @@
+use std::io::Cursor;
+
+use image::{
+    codecs::webp::{WebPDecoder, WebPEncoder},
+    DynamicImage, ExtendedColorType, ImageDecoder,
+};
+
+/// Encodes the rgba image as lossless WebP.
+pub fn save_webp_image(raw_bytes: &[u8], width: u32, height: u32) -> anyhow::Result<Vec<u8>> {
+    let mut res: Vec<u8> = Default::default();
+    WebPEncoder::new_lossless(&mut res).encode(
+        raw_bytes,
+        width,
+        height,
+        ExtendedColorType::Rgba8,
+    )?;
+    Ok(res)
+}
+
+/// Returns the rgba data, the width and the height of the image.
+pub fn load_webp_image_as_rgba(file: &[u8]) -> anyhow::Result<(Vec<u8>, u32, u32)> {
+    let decoder = WebPDecoder::new(Cursor::new(file))?;
+    let (width, height) = decoder.dimensions();
+    let img = DynamicImage::from_decoder(decoder)?.into_rgba8();
+    Ok((img.into_raw(), width, height))
+}
@@ -23,7 +23,7 @@ use wasm_logic_fs::fs::WasmFileSystemLogic;
 use wasm_logic_graphics::WasmGraphicsLogic;
 use wasm_logic_http::http::WasmHttpLogic;
 use wasm_logic_sound::sound::WasmSoundLogic;
-use wasm_runtime::{MemoryLimit, WasmManager, WasmManagerModuleType};
+use wasm_runtime::{IntEncoding, MemoryLimit, WasmManager, WasmManagerModuleType};
 use wasmer::Module;
 
 pub struct UiWasmPageEntry {
@@ -311,6 +311,7 @@ where
                                             Some(imports)
                                         },
                                         MemoryLimit::OneGibiByte,
+                                        IntEncoding::Fixed,
                                     )
                                     .unwrap();
                                     let mut entry = UiWasmPageEntry { wasm_runtime };
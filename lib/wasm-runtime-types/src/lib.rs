@@ -28,6 +28,76 @@ impl From<MemoryLimit> for usize {
     }
 }
 
+/// How integers of params and results are encoded by bincode.
+///
+/// The host and the guest must use the same encoding,
+/// guests select the variable encoding with the `variable_int_encoding`
+/// feature of the `api` crate.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum IntEncoding {
+    /// Every integer uses its full size.
+    #[default]
+    Fixed,
+    /// Small integers use less bytes, which shrinks payloads
+    /// full of small integers, like tile grids or indices.
+    Variable,
+}
+
+macro_rules! decode_with_limit {
+    ($bytes:expr, $config:expr, $memory_read_limit:expr) => {
+        match $memory_read_limit {
+            MemoryLimit::OneMebiByte => {
+                bincode::serde::decode_from_slice($bytes, $config.with_limit::<{ 1024 * 1024 }>())
+            }
+            MemoryLimit::TenMebiBytes => bincode::serde::decode_from_slice(
+                $bytes,
+                $config.with_limit::<{ 1024 * 1024 * 10 }>(),
+            ),
+            MemoryLimit::OneGibiByte => bincode::serde::decode_from_slice(
+                $bytes,
+                $config.with_limit::<{ 1024 * 1024 * 1024 }>(),
+            ),
+        }
+    };
+}
+
+/// Decodes a param or result that was encoded with the given int encoding.
+pub fn decode_param<F: DeserializeOwned>(
+    bytes: &[u8],
+    int_encoding: IntEncoding,
+    memory_read_limit: MemoryLimit,
+) -> Result<F, bincode::error::DecodeError> {
+    let config = bincode::config::standard();
+    match int_encoding {
+        IntEncoding::Fixed => {
+            decode_with_limit!(bytes, config.with_fixed_int_encoding(), memory_read_limit)
+        }
+        IntEncoding::Variable => decode_with_limit!(
+            bytes,
+            config.with_variable_int_encoding(),
+            memory_read_limit
+        ),
+    }
+    .map(|(param, _)| param)
+}
+
+/// Encodes a param or result with the given int encoding and appends it to `dst`.
+pub fn encode_param<F: Serialize>(
+    param: &F,
+    int_encoding: IntEncoding,
+    dst: &mut Vec<u8>,
+) -> Result<usize, bincode::error::EncodeError> {
+    let config = bincode::config::standard();
+    match int_encoding {
+        IntEncoding::Fixed => {
+            bincode::serde::encode_into_std_write(param, dst, config.with_fixed_int_encoding())
+        }
+        IntEncoding::Variable => {
+            bincode::serde::encode_into_std_write(param, dst, config.with_variable_int_encoding())
+        }
+    }
+}
+
 #[derive(Clone)]
 pub struct InstanceData {
     pub result_ptr_ptr: i32,
@@ -38,11 +108,14 @@ pub struct InstanceData {
     pub memory: Memory,
     pub prepare_result_func: TypedFunction<u32, ()>,
     pub memory_read_limit: MemoryLimit,
+    pub int_encoding: IntEncoding,
 }
 
 pub struct RawBytesEnv {
     raw_bytes: Pool<Vec<u8>>,
     instance: Mutex<SendOption<Rc<InstanceData>>>,
+    /// The message the guest reported with `env::host_panic` before it trapped.
+    guest_panic: Mutex<Option<String>>,
 }
 
 impl Default for RawBytesEnv {
@@ -50,6 +123,7 @@ impl Default for RawBytesEnv {
         Self {
             instance: Default::default(),
             raw_bytes: Pool::with_capacity(10),
+            guest_panic: Default::default(),
         }
     }
 }
@@ -70,6 +144,14 @@ impl RawBytesEnv {
     pub fn set_instance(&self, instance: InstanceData) {
         let _ = self.instance.lock().unwrap().insert(Rc::new(instance));
     }
+
+    pub fn set_guest_panic(&self, msg: String) {
+        *self.guest_panic.lock().unwrap() = Some(msg);
+    }
+
+    pub fn take_guest_panic(&self) -> Option<String> {
+        self.guest_panic.lock().unwrap().take()
+    }
 }
 
 pub fn read_global_location(
@@ -102,6 +184,11 @@ pub fn write_global(memory: &wasmer::Memory, store: &StoreRef<'_>, global_ptr: i
         .unwrap();
 }
 
+pub fn write_bytes(memory: &wasmer::Memory, store: &StoreRef<'_>, ptr: u32, bytes: &[u8]) {
+    let mem_view = memory.view(store);
+    mem_view.write(ptr as u64, bytes).unwrap();
+}
+
 pub fn read_param<F: DeserializeOwned>(
     instance: &InstanceData,
     store: &StoreRef<'_>,
@@ -130,32 +217,13 @@ pub fn read_param<F: DeserializeOwned>(
     let mem_view = instance.memory.view(store);
     mem_view.read(ptr as u64, raw_bytes).unwrap();
 
-    let config = bincode::config::standard().with_fixed_int_encoding();
-    match instance.memory_read_limit {
-        MemoryLimit::OneMebiByte => bincode::serde::decode_from_slice::<F, _>(
-            raw_bytes.as_slice(),
-            config.with_limit::<{ 1024 * 1024 }>(),
-        ),
-        MemoryLimit::TenMebiBytes => bincode::serde::decode_from_slice::<F, _>(
-            raw_bytes.as_slice(),
-            config.with_limit::<{ 1024 * 1024 * 10 }>(),
-        ),
-        MemoryLimit::OneGibiByte => bincode::serde::decode_from_slice::<F, _>(
-            raw_bytes.as_slice(),
-            config.with_limit::<{ 1024 * 1024 * 1024 }>(),
-        ),
-    }
-    .unwrap()
-    .0
+    decode_param(raw_bytes, instance.int_encoding, instance.memory_read_limit).unwrap()
 }
 
 pub fn write_result<F: Serialize>(instance: &InstanceData, store: &mut StoreMut<'_>, param: &F) {
     // encode and upload
-    let res = bincode::serde::encode_to_vec::<&F, _>(
-        param,
-        bincode::config::standard().with_fixed_int_encoding(),
-    )
-    .unwrap();
+    let mut res = Vec::new();
+    encode_param(param, instance.int_encoding, &mut res).unwrap();
 
     instance
         .prepare_result_func
@@ -172,3 +240,29 @@ pub fn write_result<F: Serialize>(instance: &InstanceData, store: &mut StoreMut<
     let mem_view = memory.view(store);
     mem_view.write(ptr as u64, &res).unwrap();
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::{decode_param, encode_param, IntEncoding, MemoryLimit};
+
+    #[test]
+    fn variable_int_encoding() {
+        let tiles: Vec<u32> = (0..1000).map(|i| i % 64).collect();
+
+        let mut fixed = Vec::new();
+        encode_param(&tiles, IntEncoding::Fixed, &mut fixed).unwrap();
+        let mut variable = Vec::new();
+        encode_param(&tiles, IntEncoding::Variable, &mut variable).unwrap();
+
+        assert!(variable.len() < fixed.len());
+        assert_eq!(
+            decode_param::<Vec<u32>>(&fixed, IntEncoding::Fixed, MemoryLimit::OneMebiByte).unwrap(),
+            tiles
+        );
+        assert_eq!(
+            decode_param::<Vec<u32>>(&variable, IntEncoding::Variable, MemoryLimit::OneMebiByte)
+                .unwrap(),
+            tiles
+        );
+    }
+}
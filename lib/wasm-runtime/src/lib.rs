@@ -1,18 +1,43 @@
-use std::{cell::RefCell, sync::Arc};
+use std::{
+    cell::{Cell, RefCell},
+    io::Read,
+    sync::Arc,
+};
 
+use anyhow::anyhow;
 use arrayvec::ArrayVec;
 use serde::{de::DeserializeOwned, Serialize};
-pub use wasm_runtime_types::MemoryLimit;
 use wasm_runtime_types::{
-    read_global, read_global_location, read_param, write_global, InstanceData, RawBytesEnv,
+    decode_param, encode_param, read_global, read_global_location, read_param, write_bytes,
+    write_global, write_result, InstanceData, RawBytesEnv,
 };
+pub use wasm_runtime_types::{IntEncoding, MemoryLimit};
 use wasmer::{
     imports,
     sys::{Cranelift, CraneliftOptLevel, EngineBuilder, Features},
-    AsStoreMut, AsStoreRef, Function, FunctionEnv, FunctionEnvMut, Imports, Instance, Module,
-    Store, TypedFunction,
+    AsStoreMut, AsStoreRef, ExternType, Function, FunctionEnv, FunctionEnvMut, Imports, Instance,
+    Module, Store, StoreRef, TypedFunction,
 };
 
+const THREADS_DISABLED_ERR: &str = "module requires threads which are disabled by design";
+
+/// What happens to the capacity of the host side buffers, that params
+/// are encoded into by [`WasmManager::add_param`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ParamBufferPolicy {
+    /// The buffers keep their biggest capacity for the life of the manager,
+    /// so a single huge param inflates the memory usage permanently.
+    /// This is the default, because params of similar sizes never reallocate.
+    #[default]
+    GrowOnly,
+    /// A buffer is shrunk to fit the param, after `calls` params in a row
+    /// used at most half of its capacity.
+    ShrinkAfter { calls: usize },
+    /// A buffer whose capacity exceeds `bytes` is shrunk to fit the param.
+    /// Bigger params are still uploaded.
+    Cap { bytes: usize },
+}
+
 /// Creates a WASM instance, automatically uses and fills the cache.
 ///
 /// TODO: should be allowed if no graphics modules etc. were used <= __Note: Please never provide multi-threading support, it doesn't fit our design__
@@ -21,13 +46,72 @@ pub struct WasmManager {
     instance: Instance,
     raw_bytes: Arc<RawBytesEnv>,
     guest_raw_bytes: [RefCell<Vec<u8>>; 10],
+    param_buffer_policy: ParamBufferPolicy,
+    /// The params in a row that used at most half of the capacity of
+    /// their buffer, see [`ParamBufferPolicy::ShrinkAfter`].
+    small_param_calls: [Cell<usize>; 10],
+    /// The guest's linear memory right after the instantiation,
+    /// see [`WasmManager::reset`].
+    initial_memory: Vec<u8>,
 
     instance_data: InstanceData,
     prepare_param_func: TypedFunction<(u32, u32), ()>,
 }
 
+/// The params that the guest uploaded for a host call,
+/// see [`host_function_with_result`].
+pub struct HostCallParams<'a> {
+    instance: &'a InstanceData,
+    store: StoreRef<'a>,
+    byte_buffer: &'a mut Vec<u8>,
+}
+
+impl HostCallParams<'_> {
+    /// Reads the param at `param_index`, that the guest uploaded with `api::upload_param`.
+    pub fn read<F: DeserializeOwned>(&mut self, param_index: usize) -> F {
+        read_param(self.instance, &self.store, self.byte_buffer, param_index)
+    }
+}
+
+/// Creates a host function for the guest's imports, whose return value is written
+/// into the guest's result buffer (see [`write_result`]).
+/// After the call the guest reads it with `api::read_result_from_host`.
+///
+/// Host functions without a return value can use [`Function::new_typed_with_env`] directly.
+pub fn host_function_with_result<R, F>(
+    store: &mut impl AsStoreMut,
+    env: &FunctionEnv<Arc<RawBytesEnv>>,
+    f: F,
+) -> Function
+where
+    R: Serialize,
+    F: Fn(&mut HostCallParams) -> R + Send + Sync + 'static,
+{
+    Function::new_typed_with_env(
+        store,
+        env,
+        move |mut env: FunctionEnvMut<Arc<RawBytesEnv>>| {
+            let (data, mut store) = env.data_and_store_mut();
+            let (mut byte_buffer, instance) = data.param_index_mut();
+            let instance = instance.unwrap();
+            let res = f(&mut HostCallParams {
+                instance: &instance,
+                store: store.as_store_ref(),
+                byte_buffer: &mut byte_buffer,
+            });
+            write_result(&instance, &mut store, &res);
+        },
+    )
+}
+
 pub enum WasmManagerModuleType<'a, F: FnOnce(&Store) -> anyhow::Result<Module>> {
     FromBytes(&'a [u8]),
+    /// Reads the module from e.g. a file or an archive entry.
+    ///
+    /// The compiler needs the whole module at once, so the reader is still
+    /// read into a buffer before compiling. This only saves the caller
+    /// from keeping its own copy of the bytes around.
+    FromReader(Box<dyn Read + 'a>),
     FromClosure(F),
 }
 
@@ -43,13 +127,59 @@ impl WasmManager {
     }
 
     pub fn compile_module(wasm_bytes: &[u8]) -> anyhow::Result<Module> {
-        Ok(Module::new(&Self::get_store(), wasm_bytes)?)
+        Self::compile_module_with_store(&Self::get_store(), wasm_bytes)
     }
 
+    fn compile_module_with_store(store: &Store, wasm_bytes: &[u8]) -> anyhow::Result<Module> {
+        Module::new(store, wasm_bytes).map_err(|err| {
+            // the validation of shared memories & atomics fails,
+            // because the threads feature is disabled
+            if err.to_string().contains("threads") {
+                anyhow!("{THREADS_DISABLED_ERR}: {err}")
+            } else {
+                err.into()
+            }
+        })
+    }
+
+    /// Modules that are loaded from the cache are not validated again,
+    /// so a module that was compiled for multi-threading would only fail
+    /// inside the instantiation with an error that hides the reason.
+    /// Multi-threading is never supported, see the note of [`WasmManager`].
+    fn validate_module_features(module: &Module) -> anyhow::Result<()> {
+        let uses_shared_memory = module
+            .imports()
+            .map(|import| import.ty().clone())
+            .chain(module.exports().map(|export| export.ty().clone()))
+            .any(|ty| matches!(ty, ExternType::Memory(ty) if ty.shared));
+        if uses_shared_memory {
+            return Err(anyhow!("{THREADS_DISABLED_ERR}: it uses a shared memory"));
+        }
+        if let Some(import) = module
+            .imports()
+            .find(|import| import.name() == "thread-spawn")
+        {
+            return Err(anyhow!(
+                "{THREADS_DISABLED_ERR}: it imports {}::{}",
+                import.module(),
+                import.name()
+            ));
+        }
+        Ok(())
+    }
+
+    /// `create_imports` can provide imports of any namespace, they are merged
+    /// into the built-in imports (`env::host_println` & `env::host_panic`).
+    /// An import with the same namespace and name as a built-in one replaces it,
+    /// e.g. a mod can provide its own `env::host_println`.
+    ///
+    /// Host functions that return data to the guest, e.g. the current time
+    /// or a config value, are created with [`host_function_with_result`].
     pub fn new<F, FM>(
         wasm_module: WasmManagerModuleType<FM>,
         create_imports: F,
         memory_read_limit: MemoryLimit,
+        int_encoding: IntEncoding,
     ) -> anyhow::Result<Self>
     where
         F: FnOnce(&mut Store, &FunctionEnv<Arc<RawBytesEnv>>) -> Option<Imports>,
@@ -59,9 +189,17 @@ impl WasmManager {
         // We then use our store and Wasm bytes to compile a `Module`.
         // A `Module` is a compiled WebAssembly module that isn't ready to execute yet.
         let module = match wasm_module {
-            WasmManagerModuleType::FromBytes(wasm_bytes) => Module::new(&store, wasm_bytes)?,
+            WasmManagerModuleType::FromBytes(wasm_bytes) => {
+                Self::compile_module_with_store(&store, wasm_bytes)?
+            }
+            WasmManagerModuleType::FromReader(mut reader) => {
+                let mut wasm_bytes = Vec::new();
+                reader.read_to_end(&mut wasm_bytes)?;
+                Self::compile_module_with_store(&store, &wasm_bytes)?
+            }
             WasmManagerModuleType::FromClosure(module_gen) => module_gen(&store)?,
         };
+        Self::validate_module_features(&module)?;
 
         let raw_bytes = Arc::new(RawBytesEnv::default());
 
@@ -80,15 +218,33 @@ impl WasmManager {
             println!("{text}");
         }
 
+        /// The guest's panic hook calls this with the formatted panic message,
+        /// right before the panic traps with an `unreachable`.
+        fn panic(mut env: FunctionEnvMut<Arc<RawBytesEnv>>, ptr: u32, len: u32) {
+            let (data, store) = env.data_and_store_mut();
+            let (_, instance) = data.param_index_mut();
+            let instance = instance.unwrap();
+
+            // cut overlong messages, instead of failing inside a panic
+            let len = (len as usize).min(instance.memory_read_limit.limit());
+            let mut msg = vec![0; len];
+            let mem_view = instance.memory.view(&store);
+            if mem_view.read(ptr as u64, &mut msg).is_ok() {
+                data.set_guest_panic(String::from_utf8_lossy(&msg).into_owned());
+            }
+        }
+
         // We then create an import object so that the `Module`'s imports can be satisfied.
         let mut import_object = imports! {
             "env" => {
                 "host_println" => Function::new_typed_with_env(&mut store, &raw_bytes_env, println),
+                "host_panic" => Function::new_typed_with_env(&mut store, &raw_bytes_env, panic),
             }
         };
 
         let additional_imports = create_imports(&mut store, &raw_bytes_env);
         if let Some(additional_imports) = additional_imports {
+            // overrides built-in imports with the same namespace & name
             import_object.extend(&additional_imports);
         }
 
@@ -97,6 +253,12 @@ impl WasmManager {
         // An `Instance` is a compiled WebAssembly module that has been set up
         // and is ready to execute.
         let instance = Instance::new(&mut store, &module, &import_object)?;
+        let initial_memory = {
+            let mem_view = instance.exports.get_memory("memory")?.view(&store);
+            let mut initial_memory = vec![0; mem_view.data_size() as usize];
+            mem_view.read(0, &mut initial_memory)?;
+            initial_memory
+        };
 
         let prepare_result_func = instance
             .exports
@@ -150,6 +312,7 @@ impl WasmManager {
             memory: instance.exports.get_memory("memory").unwrap().clone(),
             prepare_result_func,
             memory_read_limit,
+            int_encoding,
         };
         raw_bytes.set_instance(instance_data.clone());
 
@@ -165,11 +328,67 @@ impl WasmManager {
             instance,
             raw_bytes,
             guest_raw_bytes: Default::default(),
+            param_buffer_policy: Default::default(),
+            small_param_calls: Default::default(),
+            initial_memory,
         };
         res.run_by_name::<()>("api_setup")?;
         Ok(res)
     }
 
+    /// Resets the guest to the state right after [`Self::new`], without
+    /// compiling or instantiating the module again.
+    ///
+    /// The compiled code, the imports and the size of the linear memory survive.
+    /// The content of the linear memory is restored to its state after the
+    /// instantiation (pages that the guest grew since are zeroed, because the
+    /// memory can't shrink), so all statics, the guest's allocator & the param
+    /// and result buffers start over. Mutable wasm globals that the guest does
+    /// not export are not touched, outside of a call these are usually only
+    /// the stack pointer, which is back at its initial value anyway.
+    ///
+    /// Afterwards `api_setup` runs again, followed by `api_reset` if the guest exports it.
+    pub fn reset(&mut self) -> anyhow::Result<()> {
+        {
+            let store = self.store.borrow();
+            let mem_view = self.instance_data.memory.view(&store);
+            let grown_size = mem_view.data_size() as usize - self.initial_memory.len();
+            mem_view.write(0, &self.initial_memory)?;
+            mem_view.write(self.initial_memory.len() as u64, &vec![0; grown_size])?;
+        }
+
+        for bytes in &mut self.guest_raw_bytes {
+            bytes.get_mut().clear();
+        }
+        for calls in &mut self.small_param_calls {
+            *calls.get_mut() = 0;
+        }
+        self.raw_bytes.take_guest_panic();
+
+        self.run_by_name::<()>("api_setup")?;
+        if self.instance.exports.get_function("api_reset").is_ok() {
+            self.run_by_name::<()>("api_reset")?;
+        }
+        Ok(())
+    }
+
+    /// A panicking guest only traps with `unreachable`, so the panic message
+    /// that its panic hook reported with `env::host_panic` is attached to the error.
+    fn with_guest_panic(&self, err: wasmer::RuntimeError) -> anyhow::Error {
+        match self.raw_bytes.take_guest_panic() {
+            Some(msg) => anyhow::Error::from(err).context(format!("guest panicked: {msg}")),
+            None => err.into(),
+        }
+    }
+
+    /// Replaces the [`ParamBufferPolicy::GrowOnly`] default.
+    pub fn with_param_buffer_policy(mut self, policy: ParamBufferPolicy) -> Self {
+        self.param_buffer_policy = policy;
+        self
+    }
+
+    /// If the guest panics, the error contains its panic message,
+    /// see [`Self::with_guest_panic`].
     pub fn run_by_name<R>(&self, name: &str) -> anyhow::Result<R>
     where
         R: wasmer::WasmTypeList,
@@ -179,12 +398,14 @@ impl WasmManager {
             .instance
             .exports
             .get_typed_function(&self.store.borrow(), name)?;
-        Ok(run_func.call(&mut self.store.borrow_mut())?)
+        run_func
+            .call(&mut self.store.borrow_mut())
+            .map_err(|err| self.with_guest_panic(err))
     }
 
     pub fn run_by_ref(&self, func: &TypedFunction<(), ()>) -> anyhow::Result<()> {
-        func.call(&mut self.store.borrow_mut())?;
-        Ok(())
+        func.call(&mut self.store.borrow_mut())
+            .map_err(|err| self.with_guest_panic(err))
     }
 
     pub fn run_func_by_name(&self, name: &str) -> TypedFunction<(), ()> {
@@ -194,7 +415,11 @@ impl WasmManager {
             .unwrap()
     }
 
-    pub fn get_result_as<F: DeserializeOwned>(&self) -> F {
+    /// Gives `f` the raw bytes of the result, without deserializing them,
+    /// for guests that return an already framed binary payload.
+    ///
+    /// Like [`Self::get_result_as`], this panics if the result is bigger than the memory read limit.
+    pub fn with_result_bytes<R>(&self, f: impl FnOnce(&[u8]) -> R) -> R {
         let ptr = read_global(
             &self.instance_data.memory,
             &self.store.borrow().as_store_ref(),
@@ -215,76 +440,768 @@ impl WasmManager {
         }
         result.resize(size, Default::default());
 
-        let memory = &self.instance_data.memory;
-        let store = self.store.borrow();
-        let mem_view = memory.view(&store);
-        mem_view.read(ptr as u64, &mut result).unwrap();
-
-        let config = bincode::config::standard().with_fixed_int_encoding();
-        match self.instance_data.memory_read_limit {
-            MemoryLimit::OneMebiByte => bincode::serde::decode_from_slice::<F, _>(
-                result.as_slice(),
-                config.with_limit::<{ 1024 * 1024 }>(),
-            ),
-            MemoryLimit::TenMebiBytes => bincode::serde::decode_from_slice::<F, _>(
-                result.as_slice(),
-                config.with_limit::<{ 1024 * 1024 * 10 }>(),
-            ),
-            MemoryLimit::OneGibiByte => bincode::serde::decode_from_slice::<F, _>(
-                result.as_slice(),
-                config.with_limit::<{ 1024 * 1024 * 1024 }>(),
-            ),
+        {
+            let memory = &self.instance_data.memory;
+            let store = self.store.borrow();
+            let mem_view = memory.view(&store);
+            mem_view.read(ptr as u64, &mut result).unwrap();
         }
-        .unwrap()
-        .0
-    }
-
-    pub fn add_param<F: Serialize>(&self, param_index: usize, param: &F) {
-        let mut raw_bytes = self.guest_raw_bytes[param_index].borrow_mut();
 
-        // clear here and on guest
-        raw_bytes.clear();
+        f(result.as_slice())
+    }
 
-        // encode and upload
-        bincode::serde::encode_into_std_write::<&F, _, _>(
-            param,
-            &mut *raw_bytes,
-            bincode::config::standard().with_fixed_int_encoding(),
-        )
-        .unwrap();
+    pub fn get_result_as<F: DeserializeOwned>(&self) -> F {
+        self.with_result_bytes(|result| {
+            decode_param(
+                result,
+                self.instance_data.int_encoding,
+                self.instance_data.memory_read_limit,
+            )
+            .unwrap()
+        })
+    }
 
-        let alloc_size = read_global(
+    fn param_alloc_size(&self, param_index: usize) -> usize {
+        read_global(
             &self.instance_data.memory,
             &self.store.borrow().as_store_ref(),
             self.instance_data.param_alloc_size_ptrs[param_index],
-        ) as u32;
+        ) as u32 as usize
+    }
 
-        if alloc_size as usize >= raw_bytes.len() {
+    /// Makes sure the guest's param buffer can hold `size` bytes,
+    /// sets the param's size and returns the pointer to the buffer.
+    fn prepare_param(&self, param_index: usize, size: usize) -> u32 {
+        if self.param_alloc_size(param_index) >= size {
             write_global(
                 &self.instance_data.memory,
                 &self.store.borrow().as_store_ref(),
                 self.instance_data.param_size_ptrs[param_index],
-                raw_bytes.len() as i32,
+                size as i32,
             );
         } else {
             self.prepare_param_func
                 .call(
                     &mut self.store.borrow_mut(),
                     param_index as u32,
-                    raw_bytes.len() as u32,
+                    size as u32,
                 )
                 .unwrap();
         }
 
-        let ptr = read_global(
+        read_global(
             &self.instance_data.memory,
             &self.store.borrow().as_store_ref(),
             self.instance_data.param_ptr_ptrs[param_index],
-        ) as u32;
+        ) as u32
+    }
+
+    /// Grows the guest's param buffer up-front, so that it can hold at least `bytes` bytes.
+    /// Following [`Self::add_param`] & [`Self::add_param_bytes`] calls up to that size
+    /// don't allocate inside the guest, which is useful for per frame calls.
+    pub fn reserve_param(&self, param_index: usize, bytes: usize) {
+        if self.param_alloc_size(param_index) < bytes {
+            self.prepare_param_func
+                .call(
+                    &mut self.store.borrow_mut(),
+                    param_index as u32,
+                    bytes as u32,
+                )
+                .unwrap();
+        }
+    }
+
+    /// Grows the guest's result buffer up-front, so that it can hold at least `bytes` bytes.
+    /// The current result stays untouched.
+    pub fn reserve_result(&self, bytes: usize) {
+        let size = read_global(
+            &self.instance_data.memory,
+            &self.store.borrow().as_store_ref(),
+            self.instance_data.result_size_ptr,
+        );
+        if (size as u32 as usize) < bytes {
+            self.instance_data
+                .prepare_result_func
+                .call(&mut self.store.borrow_mut(), bytes as u32)
+                .unwrap();
+            // the guest keeps the capacity of the buffer,
+            // so only restore the size of the current result
+            write_global(
+                &self.instance_data.memory,
+                &self.store.borrow().as_store_ref(),
+                self.instance_data.result_size_ptr,
+                size,
+            );
+        }
+    }
+
+    pub fn add_param<F: Serialize>(&self, param_index: usize, param: &F) {
+        let mut raw_bytes = self.guest_raw_bytes[param_index].borrow_mut();
+
+        // clear here and on guest
+        raw_bytes.clear();
+
+        // encode and upload
+        encode_param(param, self.instance_data.int_encoding, &mut raw_bytes).unwrap();
+
+        let ptr = self.prepare_param(param_index, raw_bytes.len());
+        write_bytes(
+            &self.instance_data.memory,
+            &self.store.borrow().as_store_ref(),
+            ptr,
+            raw_bytes.as_slice(),
+        );
+
+        self.apply_param_buffer_policy(param_index, &mut raw_bytes);
+    }
+
+    fn apply_param_buffer_policy(&self, param_index: usize, raw_bytes: &mut Vec<u8>) {
+        match self.param_buffer_policy {
+            ParamBufferPolicy::GrowOnly => {}
+            ParamBufferPolicy::ShrinkAfter { calls } => {
+                let small_calls = &self.small_param_calls[param_index];
+                if raw_bytes.len() > raw_bytes.capacity() / 2 {
+                    small_calls.set(0);
+                } else if small_calls.get() + 1 >= calls {
+                    raw_bytes.shrink_to_fit();
+                    small_calls.set(0);
+                } else {
+                    small_calls.set(small_calls.get() + 1);
+                }
+            }
+            ParamBufferPolicy::Cap { bytes } => {
+                if raw_bytes.capacity() > bytes {
+                    raw_bytes.shrink_to_fit();
+                }
+            }
+        }
+    }
+
+    /// Writes the bytes straight into the guest's param buffer,
+    /// without bincode framing and without copying them into an intermediate buffer first.
+    /// Useful for big buffers, e.g. decoded images.
+    /// The guest reads them with `api::read_param_bytes_from_host`.
+    ///
+    /// The guest only sees `PARAM{index}_PTR` and `PARAM{index}_SIZE`, the size is
+    /// the exact length of `bytes` and the pointer is only guaranteed to be byte aligned,
+    /// so the guest must not reinterpret the bytes as types with a bigger alignment.
+    pub fn add_param_bytes(&self, param_index: usize, bytes: &[u8]) {
+        let ptr = self.prepare_param(param_index, bytes.len());
+        write_bytes(
+            &self.instance_data.memory,
+            &self.store.borrow().as_store_ref(),
+            ptr,
+            bytes,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::Cursor,
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        },
+    };
+
+    use wasm_runtime_types::{
+        decode_param, read_global, write_bytes, write_global, IntEncoding, MemoryLimit, RawBytesEnv,
+    };
+    use wasmer::{imports, AsStoreRef, Function, FunctionEnvMut, Module, Store};
+
+    use crate::{host_function_with_result, ParamBufferPolicy, WasmManager, WasmManagerModuleType};
+
+    fn leb128(mut value: u32, dst: &mut Vec<u8>) {
+        loop {
+            let byte = (value & 0x7f) as u8;
+            value >>= 7;
+            if value == 0 {
+                dst.push(byte);
+                break;
+            }
+            dst.push(byte | 0x80);
+        }
+    }
+
+    fn sleb128(mut value: i32, dst: &mut Vec<u8>) {
+        loop {
+            let byte = (value & 0x7f) as u8;
+            value >>= 7;
+            if (value == 0 && byte & 0x40 == 0) || (value == -1 && byte & 0x40 != 0) {
+                dst.push(byte);
+                break;
+            }
+            dst.push(byte | 0x80);
+        }
+    }
+
+    fn name(name: &str, dst: &mut Vec<u8>) {
+        leb128(name.len() as u32, dst);
+        dst.extend(name.as_bytes());
+    }
+
+    fn section(id: u8, count: usize, entries: Vec<u8>, dst: &mut Vec<u8>) {
+        let mut content = Vec::new();
+        leb128(count as u32, &mut content);
+        content.extend(entries);
+        dst.push(id);
+        leb128(content.len() as u32, dst);
+        dst.extend(content);
+    }
+
+    /// The globals of the guest api and their location in the guest's memory.
+    fn guest_globals() -> Vec<(String, i32)> {
+        (0..10)
+            .flat_map(|i| {
+                [
+                    (format!("PARAM{i}_PTR"), i * 12),
+                    (format!("PARAM{i}_SIZE"), i * 12 + 4),
+                    (format!("PARAM{i}_ALLOC_SIZE"), i * 12 + 8),
+                ]
+            })
+            .chain([
+                ("RESULT_PTR".to_string(), 120),
+                ("RESULT_SIZE".to_string(), 124),
+            ])
+            .collect()
+    }
+
+    /// Encodes a guest module, that imports the functions of the guest api from the host,
+    /// so that tests can instrument them.
+    /// `memory_limits` are the encoded limits of the guest's memory.
+    /// `extra_funcs` are additional `() -> ()` functions as `(namespace, name)`,
+    /// that are imported & exported under their name.
+    /// The built-in `env::host_panic` is always imported last.
+    /// `guest_funcs` are `() -> ()` functions as `(name, code)` that are implemented
+    /// by the guest itself and exported after the imported ones.
+    fn guest_module(
+        memory_limits: &[u8],
+        extra_funcs: &[(&str, &str)],
+        guest_funcs: &[(&str, &[u8])],
+    ) -> Vec<u8> {
+        let mut module = b"\0asm".to_vec();
+        module.extend(1u32.to_le_bytes());
+
+        // (index, size) -> (), (size) -> (), () -> ()
+        let types = vec![0x60, 2, 0x7f, 0x7f, 0, 0x60, 1, 0x7f, 0, 0x60, 0, 0];
+        section(1, 3, types, &mut module);
+
+        let funcs: Vec<_> = [
+            ("env", "prepare_param", 0),
+            ("env", "prepare_result", 1),
+            ("env", "api_setup", 2),
+        ]
+        .into_iter()
+        .chain(
+            extra_funcs
+                .iter()
+                .map(|&(namespace, func)| (namespace, func, 2)),
+        )
+        .chain([("env", "host_panic", 0)])
+        .collect();
+        let mut imports = Vec::new();
+        for &(namespace, func, ty) in &funcs {
+            name(namespace, &mut imports);
+            name(func, &mut imports);
+            imports.extend([0x00, ty]);
+        }
+        section(2, funcs.len(), imports, &mut module);
+
+        section(
+            3,
+            guest_funcs.len(),
+            vec![2; guest_funcs.len()],
+            &mut module,
+        );
 
-        let memory = &self.instance_data.memory;
-        let store = self.store.borrow();
-        let mem_view = memory.view(&store);
-        mem_view.write(ptr as u64, raw_bytes.as_slice()).unwrap();
+        section(5, 1, memory_limits.to_vec(), &mut module);
+
+        let globals = guest_globals();
+        let mut global_entries = Vec::new();
+        for (_, location) in &globals {
+            // immutable i32, initialized by an i32.const
+            global_entries.extend([0x7f, 0x00, 0x41]);
+            sleb128(*location, &mut global_entries);
+            global_entries.push(0x0b);
+        }
+        section(6, globals.len(), global_entries, &mut module);
+
+        let mut exports = Vec::new();
+        for (index, (_, func, _)) in funcs.iter().enumerate() {
+            name(func, &mut exports);
+            exports.push(0x00);
+            leb128(index as u32, &mut exports);
+        }
+        for (index, (func, _)) in guest_funcs.iter().enumerate() {
+            name(func, &mut exports);
+            exports.push(0x00);
+            leb128((funcs.len() + index) as u32, &mut exports);
+        }
+        name("memory", &mut exports);
+        exports.extend([0x02, 0x00]);
+        for (index, (global, _)) in globals.iter().enumerate() {
+            name(global, &mut exports);
+            exports.push(0x03);
+            leb128(index as u32, &mut exports);
+        }
+        section(
+            7,
+            funcs.len() + guest_funcs.len() + 1 + globals.len(),
+            exports,
+            &mut module,
+        );
+
+        let mut bodies = Vec::new();
+        for (_, code) in guest_funcs {
+            // no locals
+            let mut body = vec![0];
+            body.extend(*code);
+            body.push(0x0b);
+            leb128(body.len() as u32, &mut bodies);
+            bodies.extend(body);
+        }
+        section(10, guest_funcs.len(), bodies, &mut module);
+
+        module
+    }
+
+    /// Creates a manager for a [`guest_module`], whose guest api functions
+    /// are implemented by the host. The calls to `prepare_param` are counted.
+    /// `custom_funcs` are provided as additional imports, that count their calls.
+    /// `result_funcs` are provided as additional imports, that return their value
+    /// to the guest. They are imported after the `custom_funcs`.
+    fn test_manager(
+        memory_limits: &[u8],
+        prepare_param_calls: Arc<AtomicUsize>,
+        custom_funcs: Vec<(&str, &str, Arc<AtomicUsize>)>,
+        result_funcs: Vec<(&str, &str, u64)>,
+        guest_funcs: &[(&str, &[u8])],
+    ) -> anyhow::Result<WasmManager> {
+        let extra_funcs: Vec<_> = custom_funcs
+            .iter()
+            .map(|(namespace, func, _)| (*namespace, *func))
+            .chain(
+                result_funcs
+                    .iter()
+                    .map(|(namespace, func, _)| (*namespace, *func)),
+            )
+            .collect();
+        let module = guest_module(memory_limits, &extra_funcs, guest_funcs);
+        WasmManager::new(
+            WasmManagerModuleType::<fn(&Store) -> anyhow::Result<Module>>::FromBytes(&module),
+            |store, raw_bytes_env| {
+                let mut imports = imports! {
+                    "env" => {
+                        "prepare_param" => Function::new_typed_with_env(
+                            store,
+                            raw_bytes_env,
+                            move |mut env: FunctionEnvMut<Arc<RawBytesEnv>>, index: u32, size: u32| {
+                                prepare_param_calls.fetch_add(1, Ordering::SeqCst);
+                                let (data, store) = env.data_and_store_mut();
+                                let (_, instance) = data.param_index_mut();
+                                let instance = instance.unwrap();
+                                let store = store.as_store_ref();
+                                let index = index as usize;
+                                let alloc_size = read_global(
+                                    &instance.memory,
+                                    &store,
+                                    instance.param_alloc_size_ptrs[index],
+                                )
+                                .max(size as i32);
+                                for (ptr, value) in [
+                                    (instance.param_ptr_ptrs[index], 1024 + index as i32 * 4096),
+                                    (instance.param_size_ptrs[index], size as i32),
+                                    (instance.param_alloc_size_ptrs[index], alloc_size),
+                                ] {
+                                    write_global(&instance.memory, &store, ptr, value);
+                                }
+                            },
+                        ),
+                        "prepare_result" => Function::new_typed_with_env(
+                            store,
+                            raw_bytes_env,
+                            |mut env: FunctionEnvMut<Arc<RawBytesEnv>>, size: u32| {
+                                let (data, store) = env.data_and_store_mut();
+                                let (_, instance) = data.param_index_mut();
+                                let instance = instance.unwrap();
+                                let store = store.as_store_ref();
+                                write_global(&instance.memory, &store, instance.result_ptr_ptr, 45056);
+                                write_global(
+                                    &instance.memory,
+                                    &store,
+                                    instance.result_size_ptr,
+                                    size as i32,
+                                );
+                            },
+                        ),
+                        "api_setup" => Function::new_typed(store, || {}),
+                    }
+                };
+                for (namespace, func, calls) in custom_funcs {
+                    imports.define(
+                        namespace,
+                        func,
+                        Function::new_typed(store, move || {
+                            calls.fetch_add(1, Ordering::SeqCst);
+                        }),
+                    );
+                }
+                for (namespace, func, value) in result_funcs {
+                    imports.define(
+                        namespace,
+                        func,
+                        host_function_with_result(store, raw_bytes_env, move |_| value),
+                    );
+                }
+                Some(imports)
+            },
+            MemoryLimit::OneMebiByte,
+            IntEncoding::Fixed,
+        )
+    }
+
+    #[test]
+    fn threads_are_rejected() {
+        // shared memory with a minimum & maximum of 1 page
+        let shared_memory = [0x03, 0x01, 0x01];
+        let Err(err) = test_manager(
+            &shared_memory,
+            Default::default(),
+            Vec::new(),
+            Vec::new(),
+            &[],
+        ) else {
+            panic!("modules that use threads must not load");
+        };
+        assert!(
+            err.to_string()
+                .starts_with("module requires threads which are disabled by design"),
+            "{err}"
+        );
+        // compiling the module for the cache fails with the same reason
+        let Err(err) = WasmManager::compile_module(&guest_module(&shared_memory, &[], &[])) else {
+            panic!("modules that use threads must not compile");
+        };
+        assert!(err.to_string().contains("disabled by design"), "{err}");
+    }
+
+    #[test]
+    fn reserved_param_is_not_prepared_again() {
+        let prepare_param_calls: Arc<AtomicUsize> = Default::default();
+        let manager = test_manager(
+            &[0x00, 0x01],
+            prepare_param_calls.clone(),
+            Vec::new(),
+            Vec::new(),
+            &[],
+        )
+        .unwrap();
+        let calls = || prepare_param_calls.load(Ordering::SeqCst);
+
+        manager.reserve_param(0, 1024);
+        assert_eq!(calls(), 1);
+        // the buffer is already big enough
+        manager.reserve_param(0, 16);
+        assert_eq!(calls(), 1);
+
+        // 8 bytes length + 1016 bytes
+        manager.add_param(0, &vec![1u8; 1016]);
+        assert_eq!(calls(), 1);
+        manager.add_param_bytes(0, &[2; 1024]);
+        assert_eq!(calls(), 1);
+
+        // params that were not reserved are prepared on the first use
+        manager.add_param(1, &vec![1u8; 1016]);
+        assert_eq!(calls(), 2);
+
+        // reserving the result keeps the size of the current result
+        manager.reserve_result(2048);
+        let instance_data = &manager.instance_data;
+        let store = manager.store.borrow();
+        let read = |ptr| read_global(&instance_data.memory, &store.as_store_ref(), ptr);
+        assert_eq!(read(instance_data.result_ptr_ptr), 45056);
+        assert_eq!(read(instance_data.result_size_ptr), 0);
+    }
+
+    #[test]
+    fn param_buffers_shrink_by_policy() {
+        let manager = |policy| {
+            test_manager(
+                &[0x00, 0x01],
+                Default::default(),
+                Vec::new(),
+                Vec::new(),
+                &[],
+            )
+            .unwrap()
+            .with_param_buffer_policy(policy)
+        };
+        let capacity = |manager: &WasmManager| manager.guest_raw_bytes[0].borrow().capacity();
+        let big_param = vec![1u8; 16 * 1024];
+        let small_param = vec![1u8; 16];
+
+        let grow_only = manager(ParamBufferPolicy::GrowOnly);
+        grow_only.add_param(0, &big_param);
+        for _ in 0..100 {
+            grow_only.add_param(0, &small_param);
+        }
+        assert!(capacity(&grow_only) >= big_param.len());
+
+        let shrink = manager(ParamBufferPolicy::ShrinkAfter { calls: 10 });
+        shrink.add_param(0, &big_param);
+        for _ in 0..9 {
+            shrink.add_param(0, &small_param);
+        }
+        assert!(capacity(&shrink) >= big_param.len());
+        shrink.add_param(0, &small_param);
+        assert!(capacity(&shrink) < 1024);
+        // buffers that fit their params are not shrunk again
+        for _ in 0..100 {
+            shrink.add_param(0, &small_param);
+        }
+        assert!(capacity(&shrink) < 1024);
+
+        let cap = manager(ParamBufferPolicy::Cap { bytes: 1024 });
+        cap.add_param(0, &big_param);
+        cap.add_param(0, &small_param);
+        assert!(capacity(&cap) < 1024);
+    }
+
+    #[test]
+    fn custom_imports_override_the_built_in_ones() {
+        let println_calls: Arc<AtomicUsize> = Default::default();
+        let custom_calls: Arc<AtomicUsize> = Default::default();
+        let manager = test_manager(
+            &[0x00, 0x01],
+            Default::default(),
+            vec![
+                ("env", "host_println", println_calls.clone()),
+                ("mod", "custom", custom_calls.clone()),
+            ],
+            Vec::new(),
+            &[],
+        )
+        .unwrap();
+
+        // the built-in `host_println` would expect a string param
+        manager.run_by_name::<()>("host_println").unwrap();
+        assert_eq!(println_calls.load(Ordering::SeqCst), 1);
+        manager.run_by_name::<()>("custom").unwrap();
+        assert_eq!(custom_calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn host_function_returns_value_to_guest() {
+        const HOST_TIME: u64 = 0x1234_5678_9abc;
+        // calls `mod::host_time` (the first import after the guest api),
+        // reads the returned `u64` from the result buffer and echoes it incremented by one
+        let echo_time = [
+            // call mod::host_time
+            &[0x10u8, 0x03][..],
+            // the address of the result: RESULT_PTR (120 as signed leb128)
+            &[0x41, 0xf8, 0x00, 0x28, 0x02, 0x00],
+            // the returned value: i64.load(RESULT_PTR)
+            &[0x41, 0xf8, 0x00, 0x28, 0x02, 0x00, 0x29, 0x03, 0x00],
+            // + 1
+            &[0x42, 0x01, 0x7c],
+            // i64.store
+            &[0x37, 0x03, 0x00],
+        ]
+        .concat();
+        let manager = test_manager(
+            &[0x00, 0x01],
+            Default::default(),
+            Vec::new(),
+            vec![("mod", "host_time", HOST_TIME)],
+            &[("echo_time", &echo_time)],
+        )
+        .unwrap();
+
+        // the host function itself writes the value into the result buffer
+        manager.run_by_name::<()>("host_time").unwrap();
+        assert_eq!(manager.get_result_as::<u64>(), HOST_TIME);
+
+        manager.run_by_name::<()>("echo_time").unwrap();
+        assert_eq!(manager.get_result_as::<u64>(), HOST_TIME + 1);
+    }
+
+    #[test]
+    fn result_bytes_are_the_bytes_the_guest_wrote() {
+        // prepares an 8 byte result & writes the bytes 1 to 8 into it
+        let mut write_result = vec![
+            // call env::prepare_result(8)
+            0x41, 0x08, 0x10, 0x01,
+        ];
+        for (ptr, value) in [(45056, 0x04030201), (45060, 0x08070605)] {
+            write_result.push(0x41);
+            sleb128(ptr, &mut write_result);
+            write_result.push(0x41);
+            sleb128(value, &mut write_result);
+            // i32.store
+            write_result.extend([0x36, 0x02, 0x00]);
+        }
+        let manager = test_manager(
+            &[0x00, 0x01],
+            Default::default(),
+            Vec::new(),
+            Vec::new(),
+            &[("write_result", &write_result)],
+        )
+        .unwrap();
+
+        manager.run_by_name::<()>("write_result").unwrap();
+        manager.with_result_bytes(|bytes| assert_eq!(bytes, [1, 2, 3, 4, 5, 6, 7, 8]));
+        // the same bytes decode as the fixed int encoded result
+        assert_eq!(manager.get_result_as::<u64>(), 0x0807060504030201);
+    }
+
+    #[test]
+    fn guest_panic_message_is_attached_to_the_error() {
+        const PANIC_MSG: &str = "panicked at src/lib.rs:4:5:\nthe mod exploded";
+        const PANIC_MSG_PTR: i32 = 8192;
+        // calls `env::host_panic` (the only import after the guest api) with the message,
+        // like the panic hook of the `api` crate does, and traps afterwards
+        let mut explode = vec![0x41];
+        sleb128(PANIC_MSG_PTR, &mut explode);
+        explode.push(0x41);
+        sleb128(PANIC_MSG.len() as i32, &mut explode);
+        // call env::host_panic, unreachable
+        explode.extend([0x10, 0x03, 0x00]);
+        let manager = test_manager(
+            &[0x00, 0x01],
+            Default::default(),
+            Vec::new(),
+            Vec::new(),
+            &[("explode", &explode), ("trap", &[0x00][..])],
+        )
+        .unwrap();
+        write_bytes(
+            &manager.instance_data.memory,
+            &manager.store.borrow().as_store_ref(),
+            PANIC_MSG_PTR as u32,
+            PANIC_MSG.as_bytes(),
+        );
+
+        let err = manager.run_by_name::<()>("explode").unwrap_err();
+        assert_eq!(err.to_string(), format!("guest panicked: {PANIC_MSG}"));
+
+        // the message does not stick to later traps
+        let err = manager.run_by_name::<()>("trap").unwrap_err();
+        assert!(!err.to_string().contains("guest panicked"), "{err}");
+    }
+
+    #[test]
+    fn reset_restores_the_guest_memory() {
+        const COUNTER_PTR: i32 = 61440;
+        const RESET_MARKER_PTR: i32 = 61444;
+        let mut count = vec![0x41];
+        sleb128(COUNTER_PTR, &mut count);
+        count.push(0x41);
+        sleb128(COUNTER_PTR, &mut count);
+        // i32.load, + 1, i32.store
+        count.extend([0x28, 0x02, 0x00, 0x41, 0x01, 0x6a, 0x36, 0x02, 0x00]);
+        let mut api_reset = vec![0x41];
+        sleb128(RESET_MARKER_PTR, &mut api_reset);
+        // i32.store(1)
+        api_reset.extend([0x41, 0x01, 0x36, 0x02, 0x00]);
+        let mut manager = test_manager(
+            &[0x00, 0x01],
+            Default::default(),
+            Vec::new(),
+            Vec::new(),
+            &[("count", &count), ("api_reset", &api_reset)],
+        )
+        .unwrap();
+        let read = |manager: &WasmManager, ptr| {
+            read_global(
+                &manager.instance_data.memory,
+                &manager.store.borrow().as_store_ref(),
+                ptr,
+            )
+        };
+
+        manager.run_by_name::<()>("count").unwrap();
+        manager.run_by_name::<()>("count").unwrap();
+        manager.add_param(0, &"param");
+        assert_eq!(read(&manager, COUNTER_PTR), 2);
+
+        manager.reset().unwrap();
+        assert_eq!(read(&manager, COUNTER_PTR), 0);
+        assert_eq!(read(&manager, manager.instance_data.param_size_ptrs[0]), 0);
+        // the guest's own reset ran after the memory was restored
+        assert_eq!(read(&manager, RESET_MARKER_PTR), 1);
+
+        manager.run_by_name::<()>("count").unwrap();
+        assert_eq!(read(&manager, COUNTER_PTR), 1);
+    }
+
+    #[test]
+    fn module_is_loaded_from_a_reader() {
+        let api_setup_calls: Arc<AtomicUsize> = Default::default();
+        let module = guest_module(&[0x00, 0x01], &[], &[]);
+        let calls = api_setup_calls.clone();
+        WasmManager::new(
+            WasmManagerModuleType::<fn(&Store) -> anyhow::Result<Module>>::FromReader(Box::new(
+                Cursor::new(module),
+            )),
+            |store, _| {
+                Some(imports! {
+                    "env" => {
+                        "prepare_param" => Function::new_typed(store, |_: u32, _: u32| {}),
+                        "prepare_result" => Function::new_typed(store, |_: u32| {}),
+                        "api_setup" => Function::new_typed(store, move || {
+                            calls.fetch_add(1, Ordering::SeqCst);
+                        }),
+                    }
+                })
+            },
+            MemoryLimit::OneMebiByte,
+            IntEncoding::Fixed,
+        )
+        .unwrap();
+        assert_eq!(api_setup_calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn param_bytes_match_bincode_params() {
+        let manager = test_manager(
+            &[0x00, 0x01],
+            Default::default(),
+            Vec::new(),
+            Vec::new(),
+            &[],
+        )
+        .unwrap();
+        // the bytes as the guest sees them in its param buffer
+        let guest_param = |param_index: usize| {
+            let instance_data = &manager.instance_data;
+            let store = manager.store.borrow();
+            let read = |ptr| read_global(&instance_data.memory, &store.as_store_ref(), ptr);
+            let ptr = read(instance_data.param_ptr_ptrs[param_index]) as u32;
+            let size = read(instance_data.param_size_ptrs[param_index]) as u32 as usize;
+            let mut bytes = vec![0; size];
+            instance_data
+                .memory
+                .view(&store)
+                .read(ptr as u64, &mut bytes)
+                .unwrap();
+            bytes
+        };
+
+        let bytes: Vec<u8> = (0..3000).map(|i| (i % 251) as u8).collect();
+        manager.add_param_bytes(0, &bytes);
+        manager.add_param(1, &bytes);
+
+        let raw = guest_param(0);
+        let decoded: Vec<u8> = decode_param(
+            &guest_param(1),
+            IntEncoding::Fixed,
+            MemoryLimit::OneMebiByte,
+        )
+        .unwrap();
+        assert_eq!(raw, bytes);
+        assert_eq!(raw, decoded);
     }
 }
@@ -3653,6 +3653,11 @@ impl AppWithGraphics for ClientNativeImpl {
             self.config.engine.ui.path = Default::default();
         }
 
+        // submit what was rendered after the last swap, e.g. for a last screenshot
+        if let Err(err) = self.graphics_backend.flush_and_drain() {
+            log::warn!("Failed to drain the graphics backend: {err}");
+        }
+
         // destroy everything
         config_fs::save(&self.config.engine, &self.io.clone().into());
         game_config_fs::fs::save(&self.config.game, &self.io.clone().into());
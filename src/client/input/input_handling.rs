@@ -696,7 +696,9 @@ impl InputHandling {
                             }
                         }
                     }
-                    graphics.do_screenshot(Screenshot { io }).unwrap();
+                    if let Err(err) = graphics.do_screenshot(Screenshot { io }) {
+                        log::error!(target: "screenshot", "{err}");
+                    }
                 }
                 BindActionsHotkey::LocalConsole => {
                     local_console_state.is_ui_open = !local_console_state.is_ui_open;